@@ -0,0 +1,239 @@
+//! Audit trail of the tools an agent runs.
+//!
+//! The Claude CLI's stream-json output announces each tool call as a
+//! `tool_use` block in an `assistant` message, and its outcome as a
+//! `tool_result` block in the `user` message that follows. An
+//! [`AgentAuditor`] watches a session's stdout for both and records them in
+//! `agent_audit`, so users can review afterwards which commands ran and
+//! which files were touched. Arguments and results are shortened; file
+//! contents passed to Write or Edit are not stored. Files about to be
+//! edited are also snapshotted by [`crate::workspace::diff`] so the edit can
+//! be undone.
+
+use crate::db::agent_audit::{self as store, AuditStatus, NewAuditEntry};
+use crate::workspace::diff;
+use serde_json::{Map, Value};
+use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+use tracing::warn;
+
+/// Longest string argument kept as-is in the summary.
+const MAX_ARGUMENT_CHARS: usize = 200;
+const MAX_SUMMARY_CHARS: usize = 2000;
+const MAX_RESULT_CHARS: usize = 500;
+
+/// Input keys naming the file or directory a tool works on.
+const PATH_KEYS: &[&str] = &["file_path", "notebook_path", "path"];
+
+/// How the CLI words a refused permission, with and without the prompt
+/// bridge.
+const DENIAL_MARKERS: &[&str] = &[
+    crate::agent_permissions::DENIED_MESSAGE,
+    "requested permissions to use",
+];
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// The call's input as compact JSON, with long strings replaced by their
+/// length. Bash calls are summarized as their command line.
+fn summarize_arguments(tool_name: &str, input: &Value) -> String {
+    let command = input.get("command").and_then(Value::as_str);
+    if let ("Bash", Some(command)) = (tool_name, command) {
+        return truncate(command, MAX_SUMMARY_CHARS);
+    }
+    let summary = match input.as_object() {
+        Some(fields) => {
+            let shortened: Map<String, Value> = fields
+                .iter()
+                .map(|(key, value)| {
+                    let value = match value.as_str() {
+                        Some(text) if text.chars().count() > MAX_ARGUMENT_CHARS => {
+                            Value::from(format!("<{} chars>", text.chars().count()))
+                        }
+                        _ => value.clone(),
+                    };
+                    (key.clone(), value)
+                })
+                .collect();
+            Value::Object(shortened).to_string()
+        }
+        None => input.to_string(),
+    };
+    truncate(&summary, MAX_SUMMARY_CHARS)
+}
+
+fn touched_files(input: &Value) -> Vec<String> {
+    PATH_KEYS
+        .iter()
+        .filter_map(|key| input.get(*key)?.as_str())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Text of a `tool_result` block, whose content is a string or a list of
+/// text blocks.
+fn result_text(block: &Value) -> Option<String> {
+    let text = match block.get("content")? {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| part.get("text")?.as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => return None,
+    };
+    (!text.is_empty()).then(|| truncate(&text, MAX_RESULT_CHARS))
+}
+
+fn result_status(block: &Value, text: Option<&str>) -> AuditStatus {
+    if block.get("is_error").and_then(Value::as_bool) != Some(true) {
+        return AuditStatus::Success;
+    }
+    let denied = text.is_some_and(|text| DENIAL_MARKERS.iter().any(|m| text.contains(m)));
+    if denied {
+        AuditStatus::Denied
+    } else {
+        AuditStatus::Error
+    }
+}
+
+/// Records the tool calls of one agent run.
+pub(crate) struct AgentAuditor {
+    app: AppHandle,
+    pool: Option<SqlitePool>,
+    conversation_id: String,
+    agent_session_id: Option<String>,
+    /// What relative paths in tool input are relative to.
+    working_dir: PathBuf,
+}
+
+impl AgentAuditor {
+    pub(crate) fn new(app: &AppHandle, conversation_id: &str, working_dir: &Path) -> Self {
+        Self {
+            app: app.clone(),
+            pool: None,
+            conversation_id: conversation_id.to_string(),
+            agent_session_id: None,
+            working_dir: working_dir.to_path_buf(),
+        }
+    }
+
+    async fn pool(&mut self) -> Result<SqlitePool, String> {
+        if let Some(pool) = &self.pool {
+            return Ok(pool.clone());
+        }
+        let pool = crate::db::pool(&self.app).await?;
+        self.pool = Some(pool.clone());
+        Ok(pool)
+    }
+
+    /// Look at one stream-json message from the CLI's stdout.
+    pub(crate) async fn observe(&mut self, message: &Value) {
+        if let Some(session_id) = message.get("session_id").and_then(Value::as_str) {
+            self.agent_session_id = Some(session_id.to_string());
+        }
+        let kind = message.get("type").and_then(Value::as_str);
+        if !matches!(kind, Some("assistant" | "user")) {
+            return;
+        }
+        let Some(blocks) = message
+            .pointer("/message/content")
+            .and_then(Value::as_array)
+        else {
+            return;
+        };
+        for block in blocks {
+            if let Err(e) = self.record_block(block).await {
+                warn!("{}", e);
+            }
+        }
+    }
+
+    async fn record_block(&mut self, block: &Value) -> Result<(), String> {
+        match block.get("type").and_then(Value::as_str) {
+            Some("tool_use") => {
+                let (Some(id), Some(tool_name)) = (
+                    block.get("id").and_then(Value::as_str),
+                    block.get("name").and_then(Value::as_str),
+                ) else {
+                    return Ok(());
+                };
+                let input = block.get("input").cloned().unwrap_or(Value::Null);
+                let files = touched_files(&input);
+                if diff::is_edit_tool(tool_name) {
+                    for file in &files {
+                        diff::snapshot(&self.conversation_id, &self.working_dir.join(file));
+                    }
+                }
+                let pool = self.pool().await?;
+                let entry = NewAuditEntry {
+                    id,
+                    conversation_id: &self.conversation_id,
+                    agent_session_id: self.agent_session_id.as_deref(),
+                    tool_name,
+                    arguments: &summarize_arguments(tool_name, &input),
+                    files: &files,
+                };
+                store::record_call(&pool, entry).await
+            }
+            Some("tool_result") => {
+                let Some(id) = block.get("tool_use_id").and_then(Value::as_str) else {
+                    return Ok(());
+                };
+                let text = result_text(block);
+                let status = result_status(block, text.as_deref());
+                let pool = self.pool().await?;
+                store::record_result(&pool, id, status, text.as_deref()).await
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn tool_calls_are_summarized_without_file_contents() {
+        let write = json!({ "file_path": "/repo/a.txt", "content": "x".repeat(5000) });
+        let summary = summarize_arguments("Write", &write);
+        assert!(summary.contains("/repo/a.txt"));
+        assert!(summary.contains("<5000 chars>"));
+        assert_eq!(touched_files(&write), ["/repo/a.txt"]);
+        assert_eq!(
+            summarize_arguments("Bash", &json!({ "command": "cargo test" })),
+            "cargo test"
+        );
+
+        let failed = json!({
+            "type": "tool_result",
+            "tool_use_id": "toolu_1",
+            "is_error": true,
+            "content": [{ "type": "text", "text": "exit code 101" }],
+        });
+        let text = result_text(&failed);
+        assert_eq!(text.as_deref(), Some("exit code 101"));
+        assert_eq!(result_status(&failed, text.as_deref()), AuditStatus::Error);
+
+        let denied = json!({
+            "type": "tool_result",
+            "is_error": true,
+            "content": "Claude requested permissions to use Bash, but you haven't granted it yet.",
+        });
+        let text = result_text(&denied);
+        assert_eq!(result_status(&denied, text.as_deref()), AuditStatus::Denied);
+        assert_eq!(
+            result_status(&json!({ "content": "ok" }), Some("ok")),
+            AuditStatus::Success
+        );
+        assert_eq!(truncate("héllo", 2), "hé…");
+    }
+}
@@ -9,7 +9,8 @@
 //! 3. Reads stdout line-by-line, emitting `agent:stream:{session_id}` events
 //! 4. Returns a collected Vec<StreamEvent> when the process exits
 
-use crate::claude_config;
+use crate::agent_audit::AgentAuditor;
+use crate::claude_agent;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::process::Stdio;
@@ -63,26 +64,39 @@ pub async fn kill_agent_process(
 ///
 /// Uses `tokio::task::spawn_blocking` to avoid blocking the async executor with
 /// a synchronous `std::process::Command::status()` call.
+pub(crate) async fn kill_pid(pid: u32) {
+    let _ = tokio::task::spawn_blocking(move || kill_pid_blocking(pid)).await;
+}
+
+/// Blocking variant of [`kill_pid`] for shutdown paths where the async
+/// runtime may no longer be polling tasks.
 #[cfg(unix)]
-async fn kill_pid(pid: u32) {
+pub(crate) fn kill_pid_blocking(pid: u32) {
     // SIGTERM for graceful shutdown; the process group is not targeted since
     // CLI tools like `claude` may manage their own child processes.
-    let _ = tokio::task::spawn_blocking(move || {
-        let _ = std::process::Command::new("kill")
-            .args(["-TERM", &pid.to_string()])
-            .status();
-    })
-    .await;
+    let _ = std::process::Command::new("kill")
+        .args(["-TERM", &pid.to_string()])
+        .status();
 }
 
 #[cfg(windows)]
-async fn kill_pid(pid: u32) {
-    let _ = tokio::task::spawn_blocking(move || {
-        let _ = std::process::Command::new("taskkill")
-            .args(["/F", "/PID", &pid.to_string()])
-            .status();
-    })
-    .await;
+pub(crate) fn kill_pid_blocking(pid: u32) {
+    let _ = std::process::Command::new("taskkill")
+        .args(["/F", "/PID", &pid.to_string()])
+        .status();
+}
+
+/// Kill every agent process still registered. Called on app exit so that
+/// in-flight `run_*` commands do not leave orphaned CLIs behind.
+pub fn kill_all_agent_processes(registry: &AgentProcessRegistry) {
+    let pids: Vec<u32> = match registry.0.lock() {
+        Ok(mut map) => map.drain().map(|(_, pid)| pid).collect(),
+        Err(poisoned) => poisoned.into_inner().drain().map(|(_, pid)| pid).collect(),
+    };
+
+    for pid in pids {
+        kill_pid_blocking(pid);
+    }
 }
 
 // ============================================================================
@@ -405,57 +419,37 @@ pub async fn run_claude(
     payload: AgentPayload,
     registry: tauri::State<'_, AgentProcessRegistry>,
 ) -> Result<Vec<StreamEvent>, String> {
-    // Ensure the .claude config directory exists with default files on first run.
-    let claude_dir = claude_config::init_claude_config(&app)?;
-
-    let binary = resolve_binary("claude").await?;
-
-    let mut cmd = Command::new(&binary);
-    // Clear env vars that cause "nested session" detection when Freely
-    // itself was launched from inside a Claude Code terminal.
-    cmd.env_remove("CLAUDECODE")
-        .env_remove("CLAUDE_CODE_ENTRYPOINT");
-
-    // Set working directory: use the user's project directory when provided,
-    // otherwise fall back to the .claude config dir so the CLI picks up CLAUDE.md.
-    if let Some(ref working_dir) = payload.working_directory {
-        cmd.current_dir(working_dir);
-    } else {
-        cmd.current_dir(&claude_dir);
-    }
-
-    // Build the effective prompt, prepending any system_prompt from the frontend.
-    let effective_prompt = match &payload.system_prompt {
-        Some(sys) => format!("{}\n\n{}", sys, payload.prompt),
-        None => payload.prompt.clone(),
-    };
-
-    // Claude CLI: `claude -p "prompt"` for non-interactive
-    cmd.arg("-p")
-        .arg(&effective_prompt)
-        .arg("--output-format")
-        .arg("stream-json")
-        .arg("--verbose");
-
-    // Resume an existing Claude session for conversation continuity.
-    // The CLI maintains full conversation state — no history prepending needed.
-    if let Some(ref agent_sid) = payload.agent_session_id {
-        cmd.arg("--resume").arg(agent_sid);
-    }
-
-    if let Some(ref model) = payload.model {
-        cmd.arg("--model").arg(model);
-    }
-
-    if let Some(ref perm) = payload.permission_mode {
-        cmd.arg("--allowedTools").arg(perm);
-    }
-
-    cmd.stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .stdin(Stdio::null());
-
-    run_cli_process(app, cmd, &payload.session_id, &registry).await
+    let claude_agent::ClaudeCommand {
+        command,
+        working_dir,
+        resumes_stored,
+        ..
+    } = claude_agent::build_command(&app, &payload, false).await?;
+
+    let auditor = AgentAuditor::new(&app, &payload.session_id, &working_dir);
+    let result = run_cli_process(
+        app.clone(),
+        command,
+        &payload.session_id,
+        &registry,
+        Some(auditor),
+    )
+    .await;
+    let reported = result.as_ref().ok().and_then(|events| {
+        events
+            .iter()
+            .rev()
+            .find_map(|event| event.agent_session_id.clone())
+    });
+    claude_agent::record_session(
+        &app,
+        &payload.session_id,
+        &working_dir,
+        resumes_stored,
+        reported.as_deref(),
+    )
+    .await;
+    result
 }
 
 // ============================================================================
@@ -487,7 +481,7 @@ pub async fn run_codex(
         .stderr(Stdio::piped())
         .stdin(Stdio::null());
 
-    run_cli_process(app, cmd, &payload.session_id, &registry).await
+    run_cli_process(app, cmd, &payload.session_id, &registry, None).await
 }
 
 // ============================================================================
@@ -515,7 +509,7 @@ pub async fn run_gemini(
         .stderr(Stdio::piped())
         .stdin(Stdio::null());
 
-    run_cli_process(app, cmd, &payload.session_id, &registry).await
+    run_cli_process(app, cmd, &payload.session_id, &registry, None).await
 }
 
 // ============================================================================
@@ -523,7 +517,7 @@ pub async fn run_gemini(
 // ============================================================================
 
 /// Resolve a binary name to its full path, or return an error if not found.
-async fn resolve_binary(name: &str) -> Result<String, String> {
+pub(crate) async fn resolve_binary(name: &str) -> Result<String, String> {
     if which_exists(name).await {
         return Ok(name.to_string());
     }
@@ -550,6 +544,7 @@ async fn run_cli_process(
     mut cmd: Command,
     session_id: &str,
     registry: &AgentProcessRegistry,
+    mut auditor: Option<AgentAuditor>,
 ) -> Result<Vec<StreamEvent>, String> {
     let mut child = cmd.spawn().map_err(|e| format!("Failed to spawn process: {}", e))?;
 
@@ -603,6 +598,9 @@ async fn run_cli_process(
 
         // Try to parse as JSON (structured output from CLIs)
         if let Ok(json) = serde_json::from_str::<serde_json::Value>(trimmed) {
+            if let Some(auditor) = auditor.as_mut() {
+                auditor.observe(&json).await;
+            }
             // Handle structured JSON events from CLIs that support them
             let event = parse_json_event(&json);
             if let Err(e) = app.emit(&event_name, &event) {
@@ -691,7 +689,7 @@ async fn run_cli_process(
 }
 
 /// Parse a JSON value from CLI output into a StreamEvent.
-fn parse_json_event(json: &serde_json::Value) -> StreamEvent {
+pub(crate) fn parse_json_event(json: &serde_json::Value) -> StreamEvent {
     // Claude CLI stream-json format
     if let Some(event_type) = json.get("type").and_then(|t| t.as_str()) {
         match event_type {
@@ -0,0 +1,575 @@
+//! Optional loopback HTTP API for editors and scripts, configured by the
+//! `api_server` setting and off by default.
+//!
+//! The server listens on 127.0.0.1 only. Every request needs
+//! `Authorization: Bearer <token>`, a random token generated the first time
+//! the server is enabled and kept in the credential store;
+//! `regenerate_api_token` replaces it. Requests with an `Origin` header are
+//! refused, so a web page can't drive the API from the browser even if it
+//! guesses the port. Requests get `423 Locked` while the app is locked.
+//!
+//! - `GET /v1/health`
+//! - `POST /v1/conversations` with `{title?, messages?}`
+//! - `POST /v1/conversations/{id}/messages` with `{role?, content}`
+//! - `GET /v1/transcripts/latest?limit=`: the newest recording's transcript
+//!
+//! Conversations changed through the API are emitted as
+//! `api-conversation-changed` with their id, so open windows can refresh.
+
+use crate::db::chat::{self, Conversation, Message, MessageRole};
+use crate::db::transcripts::{self, Transcript};
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use futures_util::future::{BoxFuture, FutureExt};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::SqlitePool;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::oneshot;
+use tracing::{info, warn};
+
+/// Settings key for [`ApiServerConfig`].
+pub(crate) const SETTING_KEY: &str = "api_server";
+const TOKEN_NAME: &str = "api-server-token";
+const TOKEN_BYTES: usize = 32;
+const DEFAULT_PORT: u16 = 47821;
+const DEFAULT_TRANSCRIPT_LIMIT: u32 = 200;
+
+/// The running server's port, the sender that stops it, and its task.
+struct Running {
+    port: u16,
+    shutdown: oneshot::Sender<()>,
+    served: tauri::async_runtime::JoinHandle<()>,
+}
+
+static RUNNING: Lazy<Mutex<Option<Running>>> = Lazy::new(|| Mutex::new(None));
+
+fn default_port() -> u16 {
+    DEFAULT_PORT
+}
+
+/// Stored under the `api_server` setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiServerConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_port")]
+    pub port: u16,
+}
+
+impl Default for ApiServerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: DEFAULT_PORT,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiServerStatus {
+    #[serde(flatten)]
+    pub config: ApiServerConfig,
+    pub running: bool,
+    /// Base URL while running.
+    pub url: Option<String>,
+}
+
+#[derive(Clone)]
+struct ApiState {
+    /// Looked up per request, since encrypting the database replaces the
+    /// pool.
+    pool: Arc<dyn Fn() -> BoxFuture<'static, Result<SqlitePool, String>> + Send + Sync>,
+    token: Arc<String>,
+    on_change: Arc<dyn Fn(&str) + Send + Sync>,
+}
+
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+impl ApiState {
+    async fn pool(&self) -> Result<SqlitePool, ApiError> {
+        (self.pool)()
+            .await
+            .map_err(|e| ApiError(StatusCode::SERVICE_UNAVAILABLE, e))
+    }
+}
+
+fn internal(e: String) -> ApiError {
+    ApiError(StatusCode::INTERNAL_SERVER_ERROR, e)
+}
+
+fn bad_request(e: String) -> ApiError {
+    ApiError(StatusCode::BAD_REQUEST, e)
+}
+
+#[derive(Debug, Deserialize)]
+struct NewMessage {
+    #[serde(default = "default_role")]
+    role: MessageRole,
+    content: String,
+}
+
+fn default_role() -> MessageRole {
+    MessageRole::User
+}
+
+impl NewMessage {
+    fn into_message(self, timestamp: i64) -> Result<Message, ApiError> {
+        if self.content.trim().is_empty() {
+            return Err(bad_request("Message content must not be empty".to_string()));
+        }
+        Ok(Message {
+            id: uuid::Uuid::new_v4().to_string(),
+            role: self.role,
+            content: self.content,
+            timestamp,
+            attached_files: None,
+            truncated: false,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct NewConversation {
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    messages: Vec<NewMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TranscriptQuery {
+    limit: Option<u32>,
+}
+
+fn new_token() -> String {
+    let mut bytes = [0u8; TOKEN_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Compare without returning early, so response times don't reveal how much
+/// of a guessed token was right.
+pub(crate) fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+async fn authorize(State(state): State<ApiState>, request: Request, next: Next) -> Response {
+    if request.headers().contains_key(header::ORIGIN) {
+        return ApiError(
+            StatusCode::FORBIDDEN,
+            "Browser requests are not allowed".to_string(),
+        )
+        .into_response();
+    }
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if !token.is_some_and(|token| tokens_match(token, &state.token)) {
+        return ApiError(
+            StatusCode::UNAUTHORIZED,
+            "Missing or invalid API token".to_string(),
+        )
+        .into_response();
+    }
+    if crate::lock::is_locked() {
+        return ApiError(StatusCode::LOCKED, "Freely is locked".to_string()).into_response();
+    }
+    next.run(request).await
+}
+
+async fn health() -> Json<serde_json::Value> {
+    Json(json!({ "status": "ok", "version": env!("CARGO_PKG_VERSION") }))
+}
+
+async fn create_conversation(
+    State(state): State<ApiState>,
+    Json(body): Json<NewConversation>,
+) -> Result<(StatusCode, Json<Conversation>), ApiError> {
+    let now = crate::db::now_millis();
+    let messages = body
+        .messages
+        .into_iter()
+        .map(|message| message.into_message(now))
+        .collect::<Result<Vec<_>, _>>()?;
+    let conversation = Conversation {
+        id: uuid::Uuid::new_v4().to_string(),
+        title: body
+            .title
+            .map(|title| title.trim().to_string())
+            .filter(|title| !title.is_empty())
+            .unwrap_or_else(|| "From API".to_string()),
+        created_at: now,
+        updated_at: now,
+        pinned: false,
+        archived_at: None,
+        deleted_at: None,
+        messages,
+    };
+    chat::validate_conversation(&conversation).map_err(bad_request)?;
+    let conversation = chat::create(&state.pool().await?, conversation)
+        .await
+        .map_err(internal)?;
+    (state.on_change)(&conversation.id);
+    Ok((StatusCode::CREATED, Json(conversation)))
+}
+
+async fn append_message(
+    State(state): State<ApiState>,
+    Path(conversation_id): Path<String>,
+    Json(body): Json<NewMessage>,
+) -> Result<(StatusCode, Json<Message>), ApiError> {
+    let pool = state.pool().await?;
+    let exists: Option<i64> =
+        sqlx::query_scalar("SELECT 1 FROM conversations WHERE id = ? AND deleted_at IS NULL")
+            .bind(&conversation_id)
+            .fetch_optional(&pool)
+            .await
+            .map_err(|e| internal(format!("Failed to look up conversation: {}", e)))?;
+    if exists.is_none() {
+        return Err(ApiError(
+            StatusCode::NOT_FOUND,
+            format!("Conversation not found: {}", conversation_id),
+        ));
+    }
+    let message = body.into_message(crate::db::now_millis())?;
+    chat::append(&pool, &conversation_id, &message)
+        .await
+        .map_err(internal)?;
+    (state.on_change)(&conversation_id);
+    Ok((StatusCode::CREATED, Json(message)))
+}
+
+async fn latest_transcript(
+    State(state): State<ApiState>,
+    Query(query): Query<TranscriptQuery>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let segments: Vec<Transcript> = transcripts::latest(
+        &state.pool().await?,
+        query.limit.unwrap_or(DEFAULT_TRANSCRIPT_LIMIT),
+    )
+    .await
+    .map_err(internal)?;
+    let text = segments
+        .iter()
+        .map(|segment| match &segment.speaker_label {
+            Some(speaker) => format!("{}: {}", speaker, segment.text),
+            None => segment.text.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    Ok(Json(json!({
+        "conversationId": segments.first().and_then(|s| s.conversation_id.clone()),
+        "text": text,
+        "segments": segments,
+    })))
+}
+
+fn router(state: ApiState) -> Router {
+    Router::new()
+        .route("/v1/health", get(health))
+        .route("/v1/conversations", post(create_conversation))
+        .route("/v1/conversations/:id/messages", post(append_message))
+        .route("/v1/transcripts/latest", get(latest_transcript))
+        .layer(middleware::from_fn_with_state(state.clone(), authorize))
+        .with_state(state)
+}
+
+async fn load_config(app: &AppHandle) -> Result<ApiServerConfig, String> {
+    let pool = crate::db::pool(app).await?;
+    Ok(crate::settings::get_setting(&pool, SETTING_KEY)
+        .await?
+        .unwrap_or_default())
+}
+
+/// The stored token, creating one on first use.
+async fn token(app: &AppHandle) -> Result<String, String> {
+    if let Some(token) = crate::secrets::load_api_key(app, TOKEN_NAME).await? {
+        return Ok(token);
+    }
+    let token = new_token();
+    crate::secrets::set_api_key(app.clone(), TOKEN_NAME.to_string(), token.clone()).await?;
+    Ok(token)
+}
+
+/// Stop the server and wait until its port is free again.
+async fn stop() {
+    let running = RUNNING.lock().take();
+    if let Some(running) = running {
+        let _ = running.shutdown.send(());
+        let _ = running.served.await;
+        info!("Stopped API server on port {}", running.port);
+    }
+}
+
+async fn start(app: &AppHandle, port: u16) -> Result<(), String> {
+    stop().await;
+    let on_change = {
+        let app = app.clone();
+        move |conversation_id: &str| {
+            if let Err(e) = app.emit("api-conversation-changed", conversation_id) {
+                warn!("Failed to emit api-conversation-changed: {}", e);
+            }
+        }
+    };
+    let pool = {
+        let app = app.clone();
+        move || {
+            let app = app.clone();
+            async move { crate::db::pool(&app).await }.boxed()
+        }
+    };
+    let state = ApiState {
+        pool: Arc::new(pool),
+        token: Arc::new(token(app).await?),
+        on_change: Arc::new(on_change),
+    };
+    let listener = tokio::net::TcpListener::bind(("127.0.0.1", port))
+        .await
+        .map_err(|e| format!("Failed to listen on port {}: {}", port, e))?;
+
+    let (shutdown, shutdown_rx) = oneshot::channel::<()>();
+    let served = tauri::async_runtime::spawn(async move {
+        let served = axum::serve(listener, router(state))
+            .with_graceful_shutdown(async {
+                let _ = shutdown_rx.await;
+            })
+            .await;
+        if let Err(e) = served {
+            warn!("API server stopped: {}", e);
+        }
+    });
+    *RUNNING.lock() = Some(Running {
+        port,
+        shutdown,
+        served,
+    });
+    info!("API server listening on 127.0.0.1:{}", port);
+    Ok(())
+}
+
+/// Start the server at launch if it is enabled.
+pub fn start_api_server(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let config = match load_config(&app).await {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("Failed to load API server settings: {}", e);
+                return;
+            }
+        };
+        if config.enabled {
+            if let Err(e) = start(&app, config.port).await {
+                warn!("Failed to start API server: {}", e);
+            }
+        }
+    });
+}
+
+fn status(config: ApiServerConfig) -> ApiServerStatus {
+    let port = RUNNING.lock().as_ref().map(|running| running.port);
+    ApiServerStatus {
+        config,
+        running: port.is_some(),
+        url: port.map(|port| format!("http://127.0.0.1:{}", port)),
+    }
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+#[tauri::command]
+pub async fn get_api_server_status(app: AppHandle) -> Result<ApiServerStatus, String> {
+    Ok(status(load_config(&app).await?))
+}
+
+/// Save the config and start, restart or stop the server to match. The
+/// setting is only saved once the server is listening.
+#[tauri::command]
+pub async fn set_api_server_config(
+    app: AppHandle,
+    config: ApiServerConfig,
+) -> Result<ApiServerStatus, String> {
+    if config.port < 1024 {
+        return Err("API server port must be 1024 or higher".to_string());
+    }
+    if config.enabled {
+        start(&app, config.port).await?;
+    } else {
+        stop().await;
+    }
+    crate::settings::set_setting(&app, SETTING_KEY, &config).await?;
+    Ok(status(config))
+}
+
+/// The bearer token for API requests.
+#[tauri::command]
+pub async fn get_api_token(app: AppHandle) -> Result<String, String> {
+    token(&app).await
+}
+
+/// Replace the token, restarting a running server so the old one stops
+/// working immediately.
+#[tauri::command]
+pub async fn regenerate_api_token(app: AppHandle) -> Result<String, String> {
+    let token = new_token();
+    crate::secrets::set_api_key(app.clone(), TOKEN_NAME.to_string(), token.clone()).await?;
+    let port = RUNNING.lock().as_ref().map(|running| running.port);
+    if let Some(port) = port {
+        start(&app, port).await?;
+    }
+    Ok(token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::AudioSource;
+    use serde_json::Value;
+
+    async fn serve(pool: SqlitePool, changed: Arc<Mutex<Vec<String>>>) -> String {
+        let state = ApiState {
+            pool: Arc::new(move || futures_util::future::ready(Ok(pool.clone())).boxed()),
+            token: Arc::new("secret-token".to_string()),
+            on_change: Arc::new(move |id: &str| changed.lock().push(id.to_string())),
+        };
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router(state)).await.unwrap() });
+        url
+    }
+
+    #[tokio::test]
+    async fn scripts_push_conversations_with_the_token() {
+        let pool = crate::db::test_pool().await;
+        let changed = Arc::new(Mutex::new(Vec::new()));
+        let url = serve(pool.clone(), changed.clone()).await;
+        let client = reqwest::Client::builder().no_proxy().build().unwrap();
+
+        let unauthorized = client
+            .get(format!("{}/v1/health", url))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(unauthorized.status(), 401);
+        let wrong = client
+            .get(format!("{}/v1/health", url))
+            .bearer_auth("secret-tokem")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(wrong.status(), 401);
+        let from_browser = client
+            .get(format!("{}/v1/health", url))
+            .bearer_auth("secret-token")
+            .header("Origin", "https://evil.example")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(from_browser.status(), 403);
+
+        let created: Value = client
+            .post(format!("{}/v1/conversations", url))
+            .bearer_auth("secret-token")
+            .json(
+                &json!({ "title": "From my editor", "messages": [{ "content": "fn main() {}" }] }),
+            )
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let id = created["id"].as_str().unwrap().to_string();
+        assert_eq!(created["messages"][0]["role"], "user");
+
+        let appended = client
+            .post(format!("{}/v1/conversations/{}/messages", url, id))
+            .bearer_auth("secret-token")
+            .json(&json!({ "role": "assistant", "content": "Looks fine" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(appended.status(), 201);
+        let missing = client
+            .post(format!("{}/v1/conversations/nope/messages", url))
+            .bearer_auth("secret-token")
+            .json(&json!({ "content": "hi" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(missing.status(), 404);
+        let empty = client
+            .post(format!("{}/v1/conversations/{}/messages", url, id))
+            .bearer_auth("secret-token")
+            .json(&json!({ "content": "  " }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(empty.status(), 400);
+
+        let conversation = chat::get(&pool, &id).await.unwrap().unwrap();
+        assert_eq!(conversation.title, "From my editor");
+        assert_eq!(conversation.messages.len(), 2);
+        assert_eq!(*changed.lock(), [id.clone(), id.clone()]);
+
+        for (started_at, text) in [(1000, "Hello"), (2000, "Hi there")] {
+            transcripts::save(
+                &pool,
+                Transcript {
+                    id: String::new(),
+                    conversation_id: Some(id.clone()),
+                    source: AudioSource::SystemAudio,
+                    text: text.to_string(),
+                    started_at,
+                    ended_at: started_at + 500,
+                    confidence: None,
+                    speaker_label: Some("Speaker 1".to_string()),
+                    translated_text: None,
+                    translation_language: None,
+                    created_at: 0,
+                },
+            )
+            .await
+            .unwrap();
+        }
+        let latest: Value = client
+            .get(format!("{}/v1/transcripts/latest", url))
+            .bearer_auth("secret-token")
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(latest["conversationId"], id.as_str());
+        assert_eq!(latest["text"], "Speaker 1: Hello\nSpeaker 1: Hi there");
+    }
+}
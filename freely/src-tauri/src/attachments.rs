@@ -0,0 +1,403 @@
+//! Message attachments: screenshots, pasted images and files.
+//!
+//! Contents are stored once under `attachments/` in the app's local data
+//! directory, at `<first two hash chars>/<sha256>.<ext>`, and indexed in the
+//! `attachments` table (migration 13). The webview reads them through the
+//! asset protocol rather than shuttling base64 back and forth.
+//!
+//! Attachments can be added before their message exists (e.g. an image
+//! pasted into the prompt box) and linked once it is saved. Rows that end up
+//! belonging to no message, and files no row refers to, are removed by
+//! [`collect_attachment_garbage`] after a grace period.
+
+use crate::db::attachments::Attachment;
+use base64::{engine::general_purpose::STANDARD as B64, Engine as _};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tauri::{AppHandle, Manager};
+
+const MAX_ATTACHMENT_BYTES: usize = 50 * 1024 * 1024;
+/// Unlinked attachments and unreferenced files younger than this are kept,
+/// so a message still being composed doesn't lose its images.
+const ORPHAN_GRACE: Duration = Duration::from_secs(24 * 60 * 60);
+
+const MIME_TYPES: &[(&str, &str)] = &[
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("pdf", "application/pdf"),
+    ("txt", "text/plain"),
+    ("md", "text/markdown"),
+    ("csv", "text/csv"),
+    ("json", "application/json"),
+];
+
+/// Where the attachment comes from: a file on disk or base64 bytes (raw or
+/// a data URL; raw base64 is taken to be PNG, as screenshots are).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AttachmentSource {
+    Path(String),
+    Base64(String),
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachmentInfo {
+    #[serde(flatten)]
+    pub attachment: Attachment,
+    /// Asset protocol URL for `<img src>` and friends.
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AttachmentGcReport {
+    pub removed_attachments: usize,
+    pub removed_files: usize,
+    pub freed_bytes: u64,
+}
+
+fn mime_for_path(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    MIME_TYPES
+        .iter()
+        .find(|(known, _)| Some(*known) == ext.as_deref())
+        .map_or("application/octet-stream", |(_, mime)| mime)
+}
+
+fn extension_for_mime(mime_type: &str) -> &'static str {
+    MIME_TYPES
+        .iter()
+        .find(|(_, mime)| *mime == mime_type)
+        .map_or("bin", |(ext, _)| ext)
+}
+
+fn attachments_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_local_data_dir()
+        .map_err(|e| format!("Failed to resolve data directory: {}", e))?;
+    Ok(dir.join("attachments"))
+}
+
+fn file_path(dir: &Path, hash: &str, mime_type: &str) -> PathBuf {
+    dir.join(&hash[..2])
+        .join(format!("{}.{}", hash, extension_for_mime(mime_type)))
+}
+
+/// Write `bytes` under `dir` unless an identical file is already there.
+/// Returns the content hash.
+fn store(dir: &Path, bytes: &[u8], mime_type: &str) -> Result<String, String> {
+    let hash = format!("{:x}", Sha256::digest(bytes));
+    let path = file_path(dir, &hash, mime_type);
+    if path.exists() {
+        return Ok(hash);
+    }
+
+    let parent = path.parent().expect("attachment paths have a parent");
+    std::fs::create_dir_all(parent)
+        .map_err(|e| format!("Failed to create attachments directory: {}", e))?;
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, bytes).map_err(|e| format!("Failed to write attachment: {}", e))?;
+    std::fs::rename(&tmp, &path).map_err(|e| format!("Failed to write attachment: {}", e))?;
+    Ok(hash)
+}
+
+/// Same encoding as the frontend's `convertFileSrc`.
+fn asset_url(path: &Path) -> String {
+    let mut encoded = String::new();
+    for byte in path.to_string_lossy().bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.!~*'()".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+
+    if cfg!(windows) {
+        format!("http://asset.localhost/{}", encoded)
+    } else {
+        format!("asset://localhost/{}", encoded)
+    }
+}
+
+fn info(dir: &Path, attachment: Attachment) -> AttachmentInfo {
+    let url = asset_url(&file_path(dir, &attachment.hash, &attachment.mime_type));
+    AttachmentInfo { attachment, url }
+}
+
+/// Delete files under `dir` whose hash isn't in `referenced` and that were
+/// last modified before `cutoff`.
+fn remove_unreferenced_files(
+    dir: &Path,
+    referenced: &HashSet<String>,
+    cutoff: SystemTime,
+    report: &mut AttachmentGcReport,
+) -> Result<(), String> {
+    let Ok(buckets) = std::fs::read_dir(dir) else {
+        return Ok(());
+    };
+    for bucket in buckets.flatten() {
+        let Ok(files) = std::fs::read_dir(bucket.path()) else {
+            continue;
+        };
+        for file in files.flatten() {
+            let path = file.path();
+            let Some(hash) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.split('.').next())
+            else {
+                continue;
+            };
+            let Ok(metadata) = file.metadata() else {
+                continue;
+            };
+            let recent = metadata
+                .modified()
+                .map_or(true, |modified| modified > cutoff);
+            if referenced.contains(hash) || recent {
+                continue;
+            }
+            std::fs::remove_file(&path)
+                .map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+            report.removed_files += 1;
+            report.freed_bytes += metadata.len();
+        }
+        // Only succeeds once the bucket is empty
+        let _ = std::fs::remove_dir(bucket.path());
+    }
+    Ok(())
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// Store an attachment, optionally for an existing message. Identical
+/// contents are only written to disk once.
+#[tauri::command]
+pub async fn add_attachment(
+    app: AppHandle,
+    source: AttachmentSource,
+    message_id: Option<String>,
+    file_name: Option<String>,
+) -> Result<AttachmentInfo, String> {
+    let (bytes, mime_type, file_name) = match source {
+        AttachmentSource::Path(path) => {
+            let path = PathBuf::from(path);
+            let bytes = tokio::fs::read(&path)
+                .await
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            let name = file_name.or_else(|| {
+                path.file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+            });
+            (bytes, mime_for_path(&path).to_string(), name)
+        }
+        AttachmentSource::Base64(data) => {
+            let (mime_type, payload) = crate::providers::split_image(&data);
+            let bytes = B64
+                .decode(payload)
+                .map_err(|e| format!("Base64 decode error: {}", e))?;
+            (bytes, mime_type.to_string(), file_name)
+        }
+    };
+    if bytes.len() > MAX_ATTACHMENT_BYTES {
+        return Err(format!(
+            "Attachment is too large ({} MB max)",
+            MAX_ATTACHMENT_BYTES / (1024 * 1024)
+        ));
+    }
+
+    let dir = attachments_dir(&app)?;
+    let size = bytes.len() as i64;
+    let store_dir = dir.clone();
+    let store_mime = mime_type.clone();
+    let hash = tokio::task::spawn_blocking(move || store(&store_dir, &bytes, &store_mime))
+        .await
+        .map_err(|e| format!("Attachment task failed: {}", e))??;
+
+    let attachment = Attachment {
+        id: uuid::Uuid::new_v4().to_string(),
+        message_id,
+        hash,
+        mime_type,
+        size,
+        file_name,
+        created_at: crate::db::now_millis(),
+    };
+    let pool = crate::db::pool(&app).await?;
+    crate::db::attachments::insert(&pool, &attachment).await?;
+    Ok(info(&dir, attachment))
+}
+
+#[tauri::command]
+pub async fn get_attachment(app: AppHandle, id: String) -> Result<Option<AttachmentInfo>, String> {
+    let pool = crate::db::pool(&app).await?;
+    let dir = attachments_dir(&app)?;
+    Ok(crate::db::attachments::get(&pool, &id)
+        .await?
+        .map(|attachment| info(&dir, attachment)))
+}
+
+#[tauri::command]
+pub async fn list_message_attachments(
+    app: AppHandle,
+    message_id: String,
+) -> Result<Vec<AttachmentInfo>, String> {
+    let pool = crate::db::pool(&app).await?;
+    let dir = attachments_dir(&app)?;
+    Ok(crate::db::attachments::list_for_message(&pool, &message_id)
+        .await?
+        .into_iter()
+        .map(|attachment| info(&dir, attachment))
+        .collect())
+}
+
+/// Attach previously added attachments to `message_id` once it is saved.
+#[tauri::command]
+pub async fn link_attachments(
+    app: AppHandle,
+    message_id: String,
+    attachment_ids: Vec<String>,
+) -> Result<u64, String> {
+    let pool = crate::db::pool(&app).await?;
+    crate::db::attachments::link(&pool, &message_id, &attachment_ids).await
+}
+
+/// Remove attachments that belong to no message and files nothing refers
+/// to, once they are older than a day.
+#[tauri::command]
+pub async fn collect_attachment_garbage(app: AppHandle) -> Result<AttachmentGcReport, String> {
+    let pool = crate::db::pool(&app).await?;
+    let cutoff = crate::db::now_millis() - ORPHAN_GRACE.as_millis() as i64;
+    let removed = crate::db::attachments::delete_orphans(&pool, cutoff).await?;
+    let referenced: HashSet<String> = crate::db::attachments::hashes(&pool)
+        .await?
+        .into_iter()
+        .collect();
+
+    let dir = attachments_dir(&app)?;
+    let mut report = AttachmentGcReport {
+        removed_attachments: removed.len(),
+        ..Default::default()
+    };
+    tokio::task::spawn_blocking(move || {
+        let cutoff = SystemTime::now() - ORPHAN_GRACE;
+        remove_unreferenced_files(&dir, &referenced, cutoff, &mut report)?;
+        Ok::<_, String>(report)
+    })
+    .await
+    .map_err(|e| format!("Attachment task failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn identical_contents_share_one_file() {
+        let tmp = TempDir::new().unwrap();
+        let hash = store(tmp.path(), b"hello", "text/plain").unwrap();
+        assert_eq!(
+            hash,
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        assert_eq!(store(tmp.path(), b"hello", "text/plain").unwrap(), hash);
+
+        let path = file_path(tmp.path(), &hash, "text/plain");
+        assert!(path.ends_with(format!("2c/{}.txt", hash)));
+        assert_eq!(std::fs::read(&path).unwrap(), b"hello");
+        assert_eq!(std::fs::read_dir(tmp.path().join("2c")).unwrap().count(), 1);
+
+        // Unreferenced files go once they're past the cutoff
+        let mut report = AttachmentGcReport::default();
+        let future = SystemTime::now() + Duration::from_secs(60);
+        remove_unreferenced_files(
+            tmp.path(),
+            &HashSet::from([hash.clone()]),
+            future,
+            &mut report,
+        )
+        .unwrap();
+        assert!(path.exists());
+        remove_unreferenced_files(tmp.path(), &HashSet::new(), future, &mut report).unwrap();
+        assert!(!path.exists());
+        assert_eq!((report.removed_files, report.freed_bytes), (1, 5));
+    }
+
+    #[test]
+    fn mime_types_and_urls() {
+        assert_eq!(mime_for_path(Path::new("/tmp/Shot.PNG")), "image/png");
+        assert_eq!(
+            mime_for_path(Path::new("notes")),
+            "application/octet-stream"
+        );
+        assert_eq!(extension_for_mime("image/jpeg"), "jpg");
+        assert_eq!(extension_for_mime("application/zip"), "bin");
+
+        let url = asset_url(Path::new("/data/My Files/a.png"));
+        assert!(url.ends_with("%2Fdata%2FMy%20Files%2Fa.png"), "{}", url);
+    }
+
+    #[tokio::test]
+    async fn orphans_are_collected_after_the_grace_period() {
+        let pool = crate::db::test_pool().await;
+        sqlx::query(
+            "INSERT INTO conversations (id, title, created_at, updated_at) VALUES ('c1', 't', 0, 0);
+             INSERT INTO messages (id, conversation_id, role, content, timestamp)
+             VALUES ('m1', 'c1', 'user', 'hi', 0);",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        for (id, message_id, created_at) in [
+            ("linked", Some("m1"), 0),
+            ("stale", None, 0),
+            ("fresh", None, 1_000),
+        ] {
+            let attachment = Attachment {
+                id: id.to_string(),
+                message_id: message_id.map(str::to_string),
+                hash: format!("hash-{}", id),
+                mime_type: "image/png".to_string(),
+                size: 1,
+                file_name: None,
+                created_at,
+            };
+            crate::db::attachments::insert(&pool, &attachment)
+                .await
+                .unwrap();
+        }
+
+        let removed = crate::db::attachments::delete_orphans(&pool, 500)
+            .await
+            .unwrap();
+        let ids: Vec<_> = removed.iter().map(|a| a.id.as_str()).collect();
+        assert_eq!(ids, ["stale"]);
+
+        // Deleting the message orphans its attachments
+        crate::db::chat::purge(&pool, "c1").await.unwrap();
+        let removed = crate::db::attachments::delete_orphans(&pool, 500)
+            .await
+            .unwrap();
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].id, "linked");
+        assert_eq!(
+            crate::db::attachments::hashes(&pool).await.unwrap(),
+            ["hash-fresh"]
+        );
+    }
+}
@@ -0,0 +1,425 @@
+//! Microphone capture with cpal.
+//!
+//! cpal streams are not `Send` on every platform, so each capture session owns
+//! a dedicated thread that builds the stream, keeps it alive until asked to
+//! stop, and then drops it. The audio callback only downmixes, optionally
+//! denoises (see [`super::denoise`]) and batches samples; a tokio task turns
+//! each batch into an `audio-chunk` event and a [`PcmBlock`] on the broadcast
+//! channel.
+
+use super::denoise::{Denoiser, NoiseSuppressionConfig};
+use super::{downmix_to_mono, AudioChunk, AudioSource, PcmBlock, AUDIO_CHUNK_EVENT};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, Sample, SizedSample};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::{broadcast, mpsc as tokio_mpsc};
+use tracing::{error, warn};
+
+const DEFAULT_CHUNK_MS: u32 = 100;
+const MIN_CHUNK_MS: u32 = 20;
+const MAX_CHUNK_MS: u32 = 1000;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InputDeviceInfo {
+    /// cpal identifies devices by name; pass this back as `device_id`.
+    pub id: String,
+    pub name: String,
+    pub is_default: bool,
+    pub default_sample_rate: Option<u32>,
+    pub channels: Option<u16>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MicCaptureInfo {
+    pub device_name: String,
+    pub sample_rate: u32,
+    /// Channel count of the device stream, before downmixing.
+    pub device_channels: u16,
+    pub chunk_ms: u32,
+    pub noise_suppression: bool,
+}
+
+struct MicSession {
+    stop_tx: mpsc::Sender<()>,
+    thread: JoinHandle<()>,
+    info: MicCaptureInfo,
+    /// Read by the audio callback on every buffer.
+    denoise: Arc<AtomicBool>,
+}
+
+/// Shared state for the active microphone session.
+pub struct MicCaptureState {
+    session: Mutex<Option<MicSession>>,
+    /// Set while a start is waiting for its capture thread; only taken with
+    /// `session` locked.
+    starting: AtomicBool,
+    blocks: broadcast::Sender<PcmBlock>,
+}
+
+impl Default for MicCaptureState {
+    fn default() -> Self {
+        let (blocks, _) = broadcast::channel(64);
+        Self {
+            session: Mutex::new(None),
+            starting: AtomicBool::new(false),
+            blocks,
+        }
+    }
+}
+
+impl MicCaptureState {
+    /// Receive microphone audio in-process (e.g. for local STT). Lagging
+    /// receivers lose the oldest blocks rather than stalling capture.
+    pub fn subscribe(&self) -> broadcast::Receiver<PcmBlock> {
+        self.blocks.subscribe()
+    }
+}
+
+fn find_input_device(host: &cpal::Host, device_id: Option<&str>) -> Result<cpal::Device, String> {
+    match device_id {
+        Some(id) => host
+            .input_devices()
+            .map_err(|e| format!("Failed to enumerate input devices: {}", e))?
+            .find(|d| d.name().map(|name| name == id).unwrap_or(false))
+            .ok_or_else(|| format!("Input device not found: {}", id)),
+        None => host
+            .default_input_device()
+            .ok_or_else(|| "No default input device available".to_string()),
+    }
+}
+
+#[tauri::command]
+pub fn list_microphones() -> Result<Vec<InputDeviceInfo>, String> {
+    let host = cpal::default_host();
+    let default_name = host.default_input_device().and_then(|d| d.name().ok());
+
+    let devices = host
+        .input_devices()
+        .map_err(|e| format!("Failed to enumerate input devices: {}", e))?;
+
+    Ok(devices
+        .filter_map(|device| {
+            let name = device.name().ok()?;
+            let config = device.default_input_config().ok();
+            Some(InputDeviceInfo {
+                id: name.clone(),
+                is_default: default_name.as_deref() == Some(name.as_str()),
+                default_sample_rate: config.as_ref().map(|c| c.sample_rate().0),
+                channels: config.as_ref().map(|c| c.channels()),
+                name,
+            })
+        })
+        .collect())
+}
+
+fn denoise_block(
+    denoiser: &mut Option<Denoiser>,
+    sample_rate: u32,
+    samples: &[f32],
+) -> Result<Vec<f32>, String> {
+    let denoiser = match denoiser {
+        Some(denoiser) => denoiser,
+        None => denoiser.insert(Denoiser::new(sample_rate)?),
+    };
+    denoiser.process(samples)
+}
+
+/// Build an input stream that downmixes to mono, denoises while `denoise`
+/// is set, and forwards batches of `chunk_len` samples.
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    chunk_len: usize,
+    denoise: Arc<AtomicBool>,
+    tx: tokio_mpsc::UnboundedSender<Vec<f32>>,
+    app: AppHandle,
+) -> Result<cpal::Stream, cpal::BuildStreamError>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let channels = config.channels as usize;
+    let sample_rate = config.sample_rate.0;
+    let mut pending: Vec<f32> = Vec::with_capacity(chunk_len * 2);
+    let mut denoiser: Option<Denoiser> = None;
+
+    device.build_input_stream(
+        config,
+        move |data: &[T], _: &cpal::InputCallbackInfo| {
+            let interleaved: Vec<f32> = data.iter().map(|&s| f32::from_sample(s)).collect();
+            let mono = downmix_to_mono(&interleaved, channels);
+
+            if !denoise.load(Ordering::Relaxed) {
+                // Start from a clean state if it's switched back on
+                denoiser = None;
+                pending.extend(mono);
+            } else {
+                match denoise_block(&mut denoiser, sample_rate, &mono) {
+                    Ok(out) => pending.extend(out),
+                    Err(e) => {
+                        error!("Noise suppression failed, passing audio through: {}", e);
+                        denoise.store(false, Ordering::Relaxed);
+                        pending.extend(mono);
+                    }
+                }
+            }
+
+            while pending.len() >= chunk_len {
+                let rest = pending.split_off(chunk_len);
+                let chunk = std::mem::replace(&mut pending, rest);
+                // The receiver only goes away while the stream is shutting down
+                let _ = tx.send(chunk);
+            }
+        },
+        move |e| {
+            error!("Microphone stream error: {}", e);
+            if let Err(e) = app.emit("microphone-capture-error", e.to_string()) {
+                warn!("Failed to emit microphone-capture-error: {}", e);
+            }
+        },
+        None,
+    )
+}
+
+/// Open the input device on the current thread and keep the stream running
+/// until `stop_rx` fires. Startup success or failure is reported on `ready_tx`.
+fn run_stream_thread(
+    app: AppHandle,
+    device_id: Option<String>,
+    chunk_ms: u32,
+    denoise: Arc<AtomicBool>,
+    tx: tokio_mpsc::UnboundedSender<Vec<f32>>,
+    ready_tx: mpsc::Sender<Result<MicCaptureInfo, String>>,
+    stop_rx: mpsc::Receiver<()>,
+) {
+    let started = (|| {
+        let host = cpal::default_host();
+        let device = find_input_device(&host, device_id.as_deref())?;
+        let device_name = device.name().unwrap_or_else(|_| "Unknown".to_string());
+
+        let supported = device
+            .default_input_config()
+            .map_err(|e| format!("Failed to get input config: {}", e))?;
+        let sample_format = supported.sample_format();
+        let config: cpal::StreamConfig = supported.into();
+        let chunk_len = (config.sample_rate.0 as usize * chunk_ms as usize / 1000).max(1);
+        let stream = match sample_format {
+            cpal::SampleFormat::F32 => {
+                build_stream::<f32>(&device, &config, chunk_len, denoise, tx, app)
+            }
+            cpal::SampleFormat::I16 => {
+                build_stream::<i16>(&device, &config, chunk_len, denoise, tx, app)
+            }
+            cpal::SampleFormat::U16 => {
+                build_stream::<u16>(&device, &config, chunk_len, denoise, tx, app)
+            }
+            cpal::SampleFormat::I32 => {
+                build_stream::<i32>(&device, &config, chunk_len, denoise, tx, app)
+            }
+            other => return Err(format!("Unsupported sample format: {:?}", other)),
+        }
+        .map_err(|e| format!("Failed to open microphone: {}", e))?;
+
+        stream
+            .play()
+            .map_err(|e| format!("Failed to start microphone: {}", e))?;
+
+        let info = MicCaptureInfo {
+            device_name,
+            sample_rate: config.sample_rate.0,
+            device_channels: config.channels,
+            chunk_ms,
+            noise_suppression: false,
+        };
+        Ok((stream, info))
+    })();
+
+    match started {
+        Ok((stream, info)) => {
+            let _ = ready_tx.send(Ok(info));
+            // Blocks until stop is requested or the session handle is dropped
+            let _ = stop_rx.recv();
+            drop(stream);
+        }
+        Err(e) => {
+            let _ = ready_tx.send(Err(e));
+        }
+    }
+}
+
+/// Start capturing from `device_id` (or the default input) and stream
+/// `audio-chunk` events tagged `source: "microphone"`.
+#[tauri::command]
+pub async fn start_microphone_capture(
+    app: AppHandle,
+    device_id: Option<String>,
+    chunk_ms: Option<u32>,
+) -> Result<MicCaptureInfo, String> {
+    let state = app.state::<MicCaptureState>();
+    {
+        // Reserved before anything is awaited, so a second start is refused
+        // while this one is still waiting for the capture thread
+        let session = state
+            .session
+            .lock()
+            .map_err(|e| format!("Failed to acquire lock: {}", e))?;
+        if session.is_some() || state.starting.swap(true, Ordering::AcqRel) {
+            return Err("Microphone capture already running".to_string());
+        }
+    }
+
+    let started = start_capture(&app, &state, device_id, chunk_ms).await;
+    // A successful start has stored its session by now
+    state.starting.store(false, Ordering::Release);
+    let info = started?;
+
+    if let Err(e) = app.emit("microphone-capture-started", &info) {
+        warn!("Failed to emit microphone-capture-started: {}", e);
+    }
+
+    Ok(info)
+}
+
+/// Open the stream and store the session. The caller holds the `starting`
+/// reservation.
+async fn start_capture(
+    app: &AppHandle,
+    state: &MicCaptureState,
+    device_id: Option<String>,
+    chunk_ms: Option<u32>,
+) -> Result<MicCaptureInfo, String> {
+    let chunk_ms = chunk_ms
+        .unwrap_or(DEFAULT_CHUNK_MS)
+        .clamp(MIN_CHUNK_MS, MAX_CHUNK_MS);
+    let (tx, mut rx) = tokio_mpsc::unbounded_channel::<Vec<f32>>();
+    let (ready_tx, ready_rx) = mpsc::channel();
+    let (stop_tx, stop_rx) = mpsc::channel();
+    let denoise = Arc::new(AtomicBool::new(false));
+
+    let thread_app = app.clone();
+    let thread_denoise = denoise.clone();
+    let thread = std::thread::Builder::new()
+        .name("microphone-capture".to_string())
+        .spawn(move || {
+            run_stream_thread(
+                thread_app,
+                device_id,
+                chunk_ms,
+                thread_denoise,
+                tx,
+                ready_tx,
+                stop_rx,
+            )
+        })
+        .map_err(|e| format!("Failed to spawn capture thread: {}", e))?;
+
+    let mut info = tokio::task::spawn_blocking(move || ready_rx.recv())
+        .await
+        .map_err(|e| format!("Capture thread failed: {}", e))?
+        .map_err(|_| "Capture thread exited unexpectedly".to_string())??;
+
+    // Only known once the device name is resolved
+    let noise_suppression = super::denoise::load_config(app).await;
+    info.noise_suppression = noise_suppression.enabled_for(&info.device_name);
+    denoise.store(info.noise_suppression, Ordering::Relaxed);
+
+    *state
+        .session
+        .lock()
+        .map_err(|e| format!("Failed to store capture session: {}", e))? = Some(MicSession {
+        stop_tx,
+        thread,
+        info: info.clone(),
+        denoise,
+    });
+
+    let blocks = state.blocks.clone();
+    let sample_rate = info.sample_rate;
+    let forward_app = app.clone();
+    tokio::spawn(async move {
+        let started_at = crate::db::now_millis();
+        let mut samples_sent: u64 = 0;
+        let mut seq: u64 = 0;
+
+        while let Some(samples) = rx.recv().await {
+            let block = PcmBlock {
+                source: AudioSource::Microphone,
+                sample_rate,
+                timestamp_ms: started_at + (samples_sent * 1000 / sample_rate as u64) as i64,
+                samples: samples.into(),
+            };
+            samples_sent += block.samples.len() as u64;
+
+            if let Err(e) = forward_app.emit(AUDIO_CHUNK_EVENT, AudioChunk::from_block(&block, seq))
+            {
+                warn!("Failed to emit audio chunk: {}", e);
+            }
+            // No receivers is fine: STT consumers subscribe on demand
+            let _ = blocks.send(block);
+            seq += 1;
+        }
+    });
+
+    Ok(info)
+}
+
+#[tauri::command]
+pub async fn stop_microphone_capture(app: AppHandle) -> Result<(), String> {
+    let session = app
+        .state::<MicCaptureState>()
+        .session
+        .lock()
+        .map_err(|e| format!("Failed to acquire lock: {}", e))?
+        .take();
+
+    let Some(session) = session else {
+        return Ok(());
+    };
+
+    let _ = session.stop_tx.send(());
+    tokio::task::spawn_blocking(move || session.thread.join())
+        .await
+        .map_err(|e| format!("Failed to stop capture thread: {}", e))?
+        .map_err(|_| "Capture thread panicked".to_string())?;
+
+    if let Err(e) = app.emit("microphone-capture-stopped", ()) {
+        warn!("Failed to emit microphone-capture-stopped: {}", e);
+    }
+    Ok(())
+}
+
+/// The active session, if any.
+#[tauri::command]
+pub fn get_microphone_capture_status(app: AppHandle) -> Result<Option<MicCaptureInfo>, String> {
+    let state = app.state::<MicCaptureState>();
+    let session = state
+        .session
+        .lock()
+        .map_err(|e| format!("Failed to acquire lock: {}", e))?;
+    Ok(session.as_ref().map(|s| s.info.clone()))
+}
+
+/// Switch noise suppression on the running session, if any, to match
+/// `config`.
+pub(crate) fn apply_noise_suppression(
+    app: &AppHandle,
+    config: &NoiseSuppressionConfig,
+) -> Result<(), String> {
+    let state = app.state::<MicCaptureState>();
+    let mut session = state
+        .session
+        .lock()
+        .map_err(|e| format!("Failed to acquire lock: {}", e))?;
+    if let Some(session) = session.as_mut() {
+        let enabled = config.enabled_for(&session.info.device_name);
+        session.denoise.store(enabled, Ordering::Relaxed);
+        session.info.noise_suppression = enabled;
+    }
+    Ok(())
+}
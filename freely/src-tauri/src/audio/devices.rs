@@ -0,0 +1,251 @@
+//! Audio device enumeration and hot-plug notifications.
+//!
+//! cpal has no device-change callbacks, so a background thread re-enumerates
+//! every couple of seconds and emits `audio-devices-changed` when a device
+//! appears, disappears or the default device changes.
+
+use cpal::traits::{DeviceTrait, HostTrait};
+use serde::Serialize;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tracing::warn;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeviceKind {
+    Input,
+    Output,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioDeviceInfo {
+    /// cpal identifies devices by name; inputs accept this as `device_id`
+    /// in `start_microphone_capture`.
+    pub id: String,
+    pub name: String,
+    pub kind: DeviceKind,
+    pub is_default: bool,
+    pub default_sample_rate: Option<u32>,
+    pub channels: Option<u16>,
+    /// Every sample rate boundary the device advertises, ascending.
+    pub sample_rates: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioDevicesChanged {
+    pub added: Vec<AudioDeviceInfo>,
+    pub removed: Vec<AudioDeviceInfo>,
+    pub devices: Vec<AudioDeviceInfo>,
+}
+
+fn describe(
+    device: &cpal::Device,
+    kind: DeviceKind,
+    default_name: Option<&str>,
+) -> Option<AudioDeviceInfo> {
+    let name = device.name().ok()?;
+
+    let (default_config, ranges) = match kind {
+        DeviceKind::Input => (
+            device.default_input_config().ok(),
+            device
+                .supported_input_configs()
+                .map(|c| {
+                    c.map(|r| (r.min_sample_rate().0, r.max_sample_rate().0))
+                        .collect()
+                })
+                .unwrap_or_else(|_| Vec::new()),
+        ),
+        DeviceKind::Output => (
+            device.default_output_config().ok(),
+            device
+                .supported_output_configs()
+                .map(|c| {
+                    c.map(|r| (r.min_sample_rate().0, r.max_sample_rate().0))
+                        .collect()
+                })
+                .unwrap_or_else(|_| Vec::new()),
+        ),
+    };
+
+    let mut sample_rates: Vec<u32> = ranges
+        .into_iter()
+        .flat_map(|(min, max): (u32, u32)| [min, max])
+        .collect();
+    sample_rates.sort_unstable();
+    sample_rates.dedup();
+
+    Some(AudioDeviceInfo {
+        id: name.clone(),
+        is_default: default_name == Some(name.as_str()),
+        name,
+        kind,
+        default_sample_rate: default_config.as_ref().map(|c| c.sample_rate().0),
+        channels: default_config.as_ref().map(|c| c.channels()),
+        sample_rates,
+    })
+}
+
+pub(crate) fn enumerate() -> Result<Vec<AudioDeviceInfo>, String> {
+    let host = cpal::default_host();
+    let default_input = host.default_input_device().and_then(|d| d.name().ok());
+    let default_output = host.default_output_device().and_then(|d| d.name().ok());
+
+    let mut devices: Vec<AudioDeviceInfo> = host
+        .input_devices()
+        .map_err(|e| format!("Failed to enumerate input devices: {}", e))?
+        .filter_map(|d| describe(&d, DeviceKind::Input, default_input.as_deref()))
+        .collect();
+
+    devices.extend(
+        host.output_devices()
+            .map_err(|e| format!("Failed to enumerate output devices: {}", e))?
+            .filter_map(|d| describe(&d, DeviceKind::Output, default_output.as_deref())),
+    );
+
+    Ok(devices)
+}
+
+/// Devices present in only one of the snapshots, plus whether the default
+/// device of either kind moved.
+pub(crate) fn diff_devices(
+    old: &[AudioDeviceInfo],
+    new: &[AudioDeviceInfo],
+) -> (Vec<AudioDeviceInfo>, Vec<AudioDeviceInfo>, bool) {
+    let same = |a: &AudioDeviceInfo, b: &AudioDeviceInfo| a.kind == b.kind && a.id == b.id;
+
+    let added: Vec<AudioDeviceInfo> = new
+        .iter()
+        .filter(|d| !old.iter().any(|o| same(o, d)))
+        .cloned()
+        .collect();
+    let removed: Vec<AudioDeviceInfo> = old
+        .iter()
+        .filter(|d| !new.iter().any(|n| same(n, d)))
+        .cloned()
+        .collect();
+
+    let defaults = |list: &[AudioDeviceInfo]| -> Vec<(DeviceKind, String)> {
+        list.iter()
+            .filter(|d| d.is_default)
+            .map(|d| (d.kind, d.id.clone()))
+            .collect()
+    };
+    let default_changed = defaults(old) != defaults(new);
+
+    (added, removed, default_changed)
+}
+
+#[tauri::command]
+pub async fn list_audio_devices() -> Result<Vec<AudioDeviceInfo>, String> {
+    // Enumeration can take a noticeable moment on ALSA and CoreAudio
+    tokio::task::spawn_blocking(enumerate)
+        .await
+        .map_err(|e| format!("Device enumeration failed: {}", e))?
+}
+
+/// Start the background hot-plug watcher. Called once from `setup`.
+pub fn start_device_watcher(app: AppHandle) {
+    let spawned = std::thread::Builder::new()
+        .name("audio-device-watcher".to_string())
+        .spawn(move || {
+            let mut known = enumerate().unwrap_or_default();
+
+            loop {
+                std::thread::sleep(POLL_INTERVAL);
+
+                let current = match enumerate() {
+                    Ok(devices) => devices,
+                    Err(e) => {
+                        warn!("Audio device poll failed: {}", e);
+                        continue;
+                    }
+                };
+
+                let (added, removed, default_changed) = diff_devices(&known, &current);
+                if !added.is_empty() || !removed.is_empty() || default_changed {
+                    let event = AudioDevicesChanged {
+                        added,
+                        removed,
+                        devices: current.clone(),
+                    };
+                    if let Err(e) = app.emit("audio-devices-changed", &event) {
+                        warn!("Failed to emit audio-devices-changed: {}", e);
+                    }
+                }
+                known = current;
+            }
+        });
+
+    if let Err(e) = spawned {
+        warn!("Failed to start audio device watcher: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(id: &str, kind: DeviceKind, is_default: bool) -> AudioDeviceInfo {
+        AudioDeviceInfo {
+            id: id.to_string(),
+            name: id.to_string(),
+            kind,
+            is_default,
+            default_sample_rate: Some(48_000),
+            channels: Some(2),
+            sample_rates: vec![44_100, 48_000],
+        }
+    }
+
+    #[test]
+    fn diff_reports_added_and_removed_per_kind() {
+        let old = vec![
+            device("Built-in Mic", DeviceKind::Input, true),
+            device("Headset", DeviceKind::Output, true),
+        ];
+        let new = vec![
+            device("Built-in Mic", DeviceKind::Input, true),
+            device("Headset", DeviceKind::Input, false),
+            device("Speakers", DeviceKind::Output, true),
+        ];
+
+        let (added, removed, default_changed) = diff_devices(&old, &new);
+        let ids = |list: &[AudioDeviceInfo]| -> Vec<(DeviceKind, String)> {
+            list.iter().map(|d| (d.kind, d.id.clone())).collect()
+        };
+        assert_eq!(
+            ids(&added),
+            vec![
+                (DeviceKind::Input, "Headset".to_string()),
+                (DeviceKind::Output, "Speakers".to_string())
+            ]
+        );
+        assert_eq!(
+            ids(&removed),
+            vec![(DeviceKind::Output, "Headset".to_string())]
+        );
+        assert!(default_changed);
+    }
+
+    #[test]
+    fn diff_detects_default_switch_without_hotplug() {
+        let old = vec![
+            device("A", DeviceKind::Input, true),
+            device("B", DeviceKind::Input, false),
+        ];
+        let new = vec![
+            device("A", DeviceKind::Input, false),
+            device("B", DeviceKind::Input, true),
+        ];
+
+        let (added, removed, default_changed) = diff_devices(&old, &new);
+        assert!(added.is_empty() && removed.is_empty());
+        assert!(default_changed);
+        assert!(!diff_devices(&new, &new).2);
+    }
+}
@@ -0,0 +1,559 @@
+//! Resampling, downmixing and mixing of capture streams.
+//!
+//! Microphone and system audio arrive at whatever rate their devices run at,
+//! commonly 48 kHz and 44.1 kHz. The mixer converts both to one rate with
+//! rubato, lines them up by capture timestamp and sums them into a single
+//! [`AudioSource::Mixed`] stream. That stream is published like any other
+//! source: as `audio-chunk` events and on a [`PcmBlock`] broadcast, so VAD,
+//! streaming STT and the recorder can all use it.
+//!
+//! Like VAD, the mixer taps the existing broadcasts and doesn't start
+//! capture itself. When one source goes quiet for longer than `maxLagMs`
+//! (or was never started) the other is passed through on its own rather
+//! than held back waiting for it.
+
+use super::{AudioChunk, AudioSource, PcmBlock, AUDIO_CHUNK_EVENT};
+use parking_lot::Mutex;
+use rubato::{FftFixedIn, Resampler};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::mpsc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::{broadcast, oneshot};
+use tracing::warn;
+
+/// Input frames per FFT resampler chunk.
+const RESAMPLER_CHUNK: usize = 1024;
+/// Capture gaps shorter than this are treated as jitter and not padded.
+const GAP_TOLERANCE_MS: i64 = 100;
+/// How often the mixer thread checks for output while no blocks arrive.
+const TICK: Duration = Duration::from_millis(50);
+
+/// Average interleaved frames down to a single channel.
+pub(crate) fn downmix_to_mono(interleaved: &[f32], channels: usize) -> Vec<f32> {
+    if channels <= 1 {
+        return interleaved.to_vec();
+    }
+
+    interleaved
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+        .collect()
+}
+
+/// Band-limited sample-rate conversion for a mono stream fed in blocks of
+/// any size.
+pub(crate) struct StreamResampler {
+    /// `None` when the rates already match.
+    inner: Option<FftFixedIn<f32>>,
+    from: u32,
+    to: u32,
+    pending: Vec<f32>,
+    /// Leading output frames still to drop so output lines up with input.
+    delay: usize,
+    input_total: u64,
+    output_total: u64,
+}
+
+impl StreamResampler {
+    pub(crate) fn new(from: u32, to: u32) -> Result<Self, String> {
+        let inner = if from == to {
+            None
+        } else {
+            let resampler = FftFixedIn::new(from as usize, to as usize, RESAMPLER_CHUNK, 2, 1)
+                .map_err(|e| format!("Failed to create resampler: {}", e))?;
+            Some(resampler)
+        };
+        let delay = inner.as_ref().map_or(0, |r| r.output_delay());
+
+        Ok(Self {
+            inner,
+            from,
+            to,
+            pending: Vec::new(),
+            delay,
+            input_total: 0,
+            output_total: 0,
+        })
+    }
+
+    pub(crate) fn input_rate(&self) -> u32 {
+        self.from
+    }
+
+    /// Resample the next block. Output lags input by up to one chunk; call
+    /// [`flush`](Self::flush) at the end of the stream to get the rest.
+    pub(crate) fn process(&mut self, samples: &[f32]) -> Result<Vec<f32>, String> {
+        self.input_total += samples.len() as u64;
+        let Some(resampler) = self.inner.as_mut() else {
+            self.output_total += samples.len() as u64;
+            return Ok(samples.to_vec());
+        };
+
+        self.pending.extend_from_slice(samples);
+        let mut output = Vec::new();
+        let mut offset = 0;
+        while self.pending.len() - offset >= resampler.input_frames_next() {
+            let frames = resampler.input_frames_next();
+            let chunk = [&self.pending[offset..offset + frames]];
+            let mut out = resampler
+                .process(&chunk, None)
+                .map_err(|e| format!("Failed to resample audio: {}", e))?;
+            output.append(&mut out[0]);
+            offset += frames;
+        }
+        self.pending.drain(..offset);
+
+        Ok(self.skip_delay(output))
+    }
+
+    /// Drain whatever is still buffered, padding the final chunk with
+    /// silence that is then trimmed off.
+    pub(crate) fn flush(&mut self) -> Result<Vec<f32>, String> {
+        let Some(resampler) = self.inner.as_mut() else {
+            return Ok(Vec::new());
+        };
+
+        let expected = self.input_total * self.to as u64 / self.from as u64;
+        let mut output = Vec::new();
+        if !self.pending.is_empty() {
+            let chunk = [std::mem::take(&mut self.pending)];
+            let mut out = resampler
+                .process_partial(Some(&chunk), None)
+                .map_err(|e| format!("Failed to resample audio: {}", e))?;
+            output.append(&mut out[0]);
+        }
+        while self.output_total + (output.len().saturating_sub(self.delay) as u64) < expected {
+            let mut out = resampler
+                .process_partial::<Vec<f32>>(None, None)
+                .map_err(|e| format!("Failed to resample audio: {}", e))?;
+            if out[0].is_empty() {
+                break;
+            }
+            output.append(&mut out[0]);
+        }
+
+        let mut output = self.skip_delay(output);
+        let remaining = expected.saturating_sub(self.output_total - output.len() as u64);
+        output.truncate(remaining as usize);
+        self.output_total = expected;
+        Ok(output)
+    }
+
+    fn skip_delay(&mut self, mut output: Vec<f32>) -> Vec<f32> {
+        let skip = self.delay.min(output.len());
+        output.drain(..skip);
+        self.delay -= skip;
+        self.output_total += output.len() as u64;
+        output
+    }
+}
+
+/// Resample a complete buffer in one go.
+pub(crate) fn resample(samples: &[f32], from: u32, to: u32) -> Result<Vec<f32>, String> {
+    let mut resampler = StreamResampler::new(from, to)?;
+    let mut output = resampler.process(samples)?;
+    output.extend(resampler.flush()?);
+    Ok(output)
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MixOptions {
+    /// Rate of the mixed stream. 16 kHz suits every STT path.
+    pub sample_rate: u32,
+    pub microphone_gain: f32,
+    pub system_gain: f32,
+    /// How long to wait for a silent source before mixing without it.
+    pub max_lag_ms: u32,
+}
+
+impl Default for MixOptions {
+    fn default() -> Self {
+        Self {
+            sample_rate: 16_000,
+            microphone_gain: 1.0,
+            system_gain: 1.0,
+            max_lag_ms: 500,
+        }
+    }
+}
+
+/// One input of the [`Mixer`], already converted to the mix rate.
+struct Lane {
+    gain: f32,
+    resampler: Option<StreamResampler>,
+    /// Samples from the mixer's cursor onwards.
+    buffer: VecDeque<f32>,
+    /// Capture time just past the last block received, `None` until the
+    /// lane hears anything (or after it goes idle).
+    next_input_ms: Option<i64>,
+    /// When the lane last received a block, on the caller's clock.
+    last_seen_ms: i64,
+}
+
+impl Lane {
+    fn new(gain: f32) -> Self {
+        Self {
+            gain,
+            resampler: None,
+            buffer: VecDeque::new(),
+            next_input_ms: None,
+            last_seen_ms: i64::MIN,
+        }
+    }
+}
+
+/// Aligns and sums microphone and system audio at a common rate.
+pub(crate) struct Mixer {
+    sample_rate: u32,
+    max_lag_ms: i64,
+    /// Capture time of the first sample produced.
+    origin_ms: Option<i64>,
+    produced: u64,
+    microphone: Lane,
+    system: Lane,
+}
+
+impl Mixer {
+    pub(crate) fn new(options: &MixOptions) -> Self {
+        Self {
+            sample_rate: options.sample_rate,
+            max_lag_ms: options.max_lag_ms as i64,
+            origin_ms: None,
+            produced: 0,
+            microphone: Lane::new(options.microphone_gain),
+            system: Lane::new(options.system_gain),
+        }
+    }
+
+    fn ms_to_samples(&self, ms: i64) -> i64 {
+        ms * self.sample_rate as i64 / 1000
+    }
+
+    /// Add a capture block received at `now_ms`.
+    pub(crate) fn push(&mut self, block: &PcmBlock, now_ms: i64) -> Result<(), String> {
+        let origin_ms = *self.origin_ms.get_or_insert(block.timestamp_ms);
+        let block_index = self.ms_to_samples(block.timestamp_ms - origin_ms);
+        let produced = self.produced as i64;
+        let sample_rate = self.sample_rate;
+        let lane = match block.source {
+            AudioSource::Microphone => &mut self.microphone,
+            AudioSource::SystemAudio => &mut self.system,
+            AudioSource::Mixed => return Ok(()),
+        };
+
+        if lane
+            .resampler
+            .as_ref()
+            .is_none_or(|r| r.input_rate() != block.sample_rate)
+        {
+            lane.resampler = Some(StreamResampler::new(block.sample_rate, sample_rate)?);
+        }
+
+        // Pad capture gaps with silence so the lane stays in step with the
+        // other one; small jitter is left alone.
+        let gap_ms = lane
+            .next_input_ms
+            .map_or(GAP_TOLERANCE_MS + 1, |next| block.timestamp_ms - next);
+        if gap_ms > GAP_TOLERANCE_MS {
+            let lane_end = produced + lane.buffer.len() as i64;
+            let padding = (block_index - lane_end).max(0) as usize;
+            lane.buffer.extend(std::iter::repeat_n(0.0, padding));
+        }
+
+        let resampled = lane
+            .resampler
+            .as_mut()
+            .map_or_else(|| Ok(block.samples.to_vec()), |r| r.process(&block.samples))?;
+        lane.buffer.extend(resampled);
+        lane.next_input_ms =
+            Some(block.timestamp_ms + block.samples.len() as i64 * 1000 / block.sample_rate as i64);
+        lane.last_seen_ms = now_ms;
+        Ok(())
+    }
+
+    /// Mix everything that is ready at `now_ms`. A lane counts as ready up
+    /// to what it has buffered; lanes that have been idle for longer than
+    /// `max_lag_ms` don't hold the mix back.
+    pub(crate) fn pull(&mut self, now_ms: i64) -> Option<PcmBlock> {
+        let origin_ms = self.origin_ms?;
+        let max_lag_ms = self.max_lag_ms;
+        let lanes = [&mut self.microphone, &mut self.system];
+
+        let is_active = |lane: &Lane| {
+            lane.next_input_ms.is_some() && now_ms.saturating_sub(lane.last_seen_ms) <= max_lag_ms
+        };
+        let ready = if lanes.iter().any(|lane| is_active(lane)) {
+            lanes
+                .iter()
+                .filter(|lane| is_active(lane))
+                .map(|lane| lane.buffer.len())
+                .min()
+                .unwrap_or(0)
+        } else {
+            lanes
+                .iter()
+                .map(|lane| lane.buffer.len())
+                .max()
+                .unwrap_or(0)
+        };
+
+        let mut mixed = vec![0.0f32; ready];
+        for lane in lanes {
+            let take = ready.min(lane.buffer.len());
+            for (out, sample) in mixed.iter_mut().zip(lane.buffer.drain(..take)) {
+                *out += sample * lane.gain;
+            }
+            if !is_active(lane) {
+                // An idle lane rejoins in step with the mix, padded with
+                // silence, when it hears audio again
+                lane.buffer.clear();
+                lane.next_input_ms = None;
+                lane.resampler = None;
+            }
+        }
+        if ready == 0 {
+            return None;
+        }
+        for sample in &mut mixed {
+            *sample = sample.clamp(-1.0, 1.0);
+        }
+
+        let timestamp_ms = origin_ms + (self.produced * 1000 / self.sample_rate as u64) as i64;
+        self.produced += ready as u64;
+        Some(PcmBlock {
+            source: AudioSource::Mixed,
+            sample_rate: self.sample_rate,
+            timestamp_ms,
+            samples: mixed.into(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MixInfo {
+    pub sample_rate: u32,
+    pub started_at: i64,
+}
+
+struct MixSession {
+    stop: oneshot::Sender<()>,
+    info: MixInfo,
+}
+
+/// Shared state for the active mix.
+pub struct MixState {
+    session: Mutex<Option<MixSession>>,
+    blocks: broadcast::Sender<PcmBlock>,
+}
+
+impl Default for MixState {
+    fn default() -> Self {
+        let (blocks, _) = broadcast::channel(64);
+        Self {
+            session: Mutex::new(None),
+            blocks,
+        }
+    }
+}
+
+impl MixState {
+    /// Receive the mixed stream in-process. Lagging receivers lose the
+    /// oldest blocks rather than stalling the mixer.
+    pub fn subscribe(&self) -> broadcast::Receiver<PcmBlock> {
+        self.blocks.subscribe()
+    }
+}
+
+/// Forward a broadcast result to the mixer thread. Returns `false` once
+/// either side has gone away.
+fn forward(
+    block: Result<PcmBlock, broadcast::error::RecvError>,
+    tx: &mpsc::Sender<PcmBlock>,
+) -> bool {
+    match block {
+        Ok(block) => tx.send(block).is_ok(),
+        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+            warn!("Audio mixer fell behind, skipped {} blocks", skipped);
+            true
+        }
+        Err(broadcast::error::RecvError::Closed) => false,
+    }
+}
+
+fn run_mixer(app: AppHandle, mut mixer: Mixer, rx: mpsc::Receiver<PcmBlock>) {
+    let blocks = app.state::<MixState>().blocks.clone();
+    let mut seq: u64 = 0;
+    let mut publish = |block: PcmBlock| {
+        if let Err(e) = app.emit(AUDIO_CHUNK_EVENT, AudioChunk::from_block(&block, seq)) {
+            warn!("Failed to emit audio chunk: {}", e);
+        }
+        let _ = blocks.send(block);
+        seq += 1;
+    };
+
+    loop {
+        match rx.recv_timeout(TICK) {
+            Ok(block) => {
+                if let Err(e) = mixer.push(&block, crate::db::now_millis()) {
+                    warn!("{}", e);
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+        while let Some(block) = mixer.pull(crate::db::now_millis()) {
+            publish(block);
+        }
+    }
+
+    // Every lane counts as idle now, so this drains them completely
+    while let Some(block) = mixer.pull(i64::MAX) {
+        publish(block);
+    }
+}
+
+/// Start mixing microphone and system audio into one stream. Either capture
+/// may be started before or after; the mix runs until stopped.
+#[tauri::command]
+pub fn start_audio_mix(app: AppHandle, options: Option<MixOptions>) -> Result<MixInfo, String> {
+    let options = options.unwrap_or_default();
+    if options.sample_rate == 0 {
+        return Err("Sample rate must be greater than zero".to_string());
+    }
+
+    let state = app.state::<MixState>();
+    let mut session = state.session.lock();
+    if session.is_some() {
+        return Err("Audio mix already running".to_string());
+    }
+
+    let mut microphone = crate::stt::vad::subscribe(&app, AudioSource::Microphone);
+    let mut system = crate::stt::vad::subscribe(&app, AudioSource::SystemAudio);
+    let (tx, rx) = mpsc::channel();
+    let (stop, mut stop_rx) = oneshot::channel();
+
+    let mixer = Mixer::new(&options);
+    let thread_app = app.clone();
+    std::thread::spawn(move || run_mixer(thread_app, mixer, rx));
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let forwarded = tokio::select! {
+                _ = &mut stop_rx => break,
+                block = microphone.recv() => forward(block, &tx),
+                block = system.recv() => forward(block, &tx),
+            };
+            if !forwarded {
+                break;
+            }
+        }
+    });
+
+    let info = MixInfo {
+        sample_rate: options.sample_rate,
+        started_at: crate::db::now_millis(),
+    };
+    *session = Some(MixSession {
+        stop,
+        info: info.clone(),
+    });
+    Ok(info)
+}
+
+#[tauri::command]
+pub fn stop_audio_mix(app: AppHandle) -> Result<(), String> {
+    if let Some(session) = app.state::<MixState>().session.lock().take() {
+        let _ = session.stop.send(());
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_audio_mix_status(app: AppHandle) -> Result<Option<MixInfo>, String> {
+    Ok(app
+        .state::<MixState>()
+        .session
+        .lock()
+        .as_ref()
+        .map(|session| session.info.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(source: AudioSource, timestamp_ms: i64, samples: Vec<f32>) -> PcmBlock {
+        PcmBlock {
+            source,
+            sample_rate: 16_000,
+            timestamp_ms,
+            samples: samples.into(),
+        }
+    }
+
+    #[test]
+    fn downmix_averages_channels() {
+        let stereo = [1.0, 0.0, 0.5, 0.5, -1.0, 1.0];
+        assert_eq!(downmix_to_mono(&stereo, 2), vec![0.5, 0.5, 0.0]);
+        assert_eq!(downmix_to_mono(&stereo, 1), stereo.to_vec());
+    }
+
+    #[test]
+    fn resampling_keeps_length_and_pitch() {
+        // One second of 440 Hz at 48 kHz has 880 zero crossings
+        let input: Vec<f32> = (0..48_000)
+            .map(|n| (2.0 * std::f32::consts::PI * 440.0 * n as f32 / 48_000.0).sin())
+            .collect();
+
+        let mut resampler = StreamResampler::new(48_000, 16_000).unwrap();
+        let mut output = Vec::new();
+        for chunk in input.chunks(4_800) {
+            output.extend(resampler.process(chunk).unwrap());
+        }
+        output.extend(resampler.flush().unwrap());
+        assert_eq!(output.len(), 16_000);
+
+        let crossings = output
+            .windows(2)
+            .filter(|pair| (pair[0] < 0.0) != (pair[1] < 0.0))
+            .count();
+        assert!((875..=885).contains(&crossings), "{} crossings", crossings);
+        assert_eq!(resample(&input, 48_000, 48_000).unwrap(), input);
+    }
+
+    #[test]
+    fn mixer_waits_for_both_sources_until_one_goes_idle() {
+        let mut mixer = Mixer::new(&MixOptions::default());
+
+        mixer
+            .push(&block(AudioSource::Microphone, 1_000, vec![0.25; 160]), 0)
+            .unwrap();
+        // 10 ms of system audio starting 5 ms later, so 80 samples of lead-in
+        mixer
+            .push(&block(AudioSource::SystemAudio, 1_005, vec![0.5; 160]), 0)
+            .unwrap();
+        let mixed = mixer.pull(0).unwrap();
+        assert_eq!(mixed.source, AudioSource::Mixed);
+        assert_eq!(mixed.timestamp_ms, 1_000);
+        assert_eq!(mixed.samples.len(), 160);
+        assert_eq!(mixed.samples[0], 0.25);
+        assert_eq!(mixed.samples[100], 0.75);
+
+        // The microphone is ahead, so nothing more is ready...
+        mixer
+            .push(&block(AudioSource::Microphone, 1_010, vec![0.25; 160]), 400)
+            .unwrap();
+        let mixed = mixer.pull(400).unwrap();
+        assert_eq!(mixed.samples.len(), 80);
+        assert!(mixer.pull(400).is_none());
+
+        // ...until system audio has been quiet for longer than the lag
+        let mixed = mixer.pull(501).unwrap();
+        assert_eq!(mixed.timestamp_ms, 1_015);
+        assert_eq!(mixed.samples.len(), 80);
+        assert!(mixed.samples.iter().all(|&s| s == 0.25));
+    }
+}
@@ -0,0 +1,178 @@
+//! System audio (loopback) streaming.
+//!
+//! Reuses the per-platform loopback inputs in `speaker` (WASAPI loopback on
+//! Windows, a CoreAudio process tap on macOS, the PulseAudio monitor source on
+//! Linux) but, unlike the VAD-driven `start_system_audio_capture`, forwards the
+//! raw signal as `audio-chunk` events tagged `source: "system_audio"` and as
+//! [`PcmBlock`]s for in-process consumers.
+
+use super::{AudioChunk, AudioSource, PcmBlock, AUDIO_CHUNK_EVENT};
+use crate::speaker::SpeakerInput;
+use futures_util::StreamExt;
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tracing::{error, warn};
+
+const DEFAULT_CHUNK_MS: u32 = 100;
+const MIN_CHUNK_MS: u32 = 20;
+const MAX_CHUNK_MS: u32 = 1000;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoopbackInfo {
+    pub device_id: Option<String>,
+    pub sample_rate: u32,
+    pub chunk_ms: u32,
+}
+
+struct LoopbackSession {
+    task: JoinHandle<()>,
+    info: LoopbackInfo,
+}
+
+/// Shared state for the active system audio stream.
+pub struct LoopbackState {
+    session: Mutex<Option<LoopbackSession>>,
+    blocks: broadcast::Sender<PcmBlock>,
+}
+
+impl Default for LoopbackState {
+    fn default() -> Self {
+        let (blocks, _) = broadcast::channel(64);
+        Self {
+            session: Mutex::new(None),
+            blocks,
+        }
+    }
+}
+
+impl LoopbackState {
+    /// Receive system audio in-process. Lagging receivers lose the oldest
+    /// blocks rather than stalling capture.
+    pub fn subscribe(&self) -> broadcast::Receiver<PcmBlock> {
+        self.blocks.subscribe()
+    }
+}
+
+/// Start streaming system audio from `device_id` (or the default output).
+#[tauri::command]
+pub async fn start_system_audio_stream(
+    app: AppHandle,
+    device_id: Option<String>,
+    chunk_ms: Option<u32>,
+) -> Result<LoopbackInfo, String> {
+    let state = app.state::<LoopbackState>();
+    let mut session = state
+        .session
+        .lock()
+        .map_err(|e| format!("Failed to acquire lock: {}", e))?;
+    if session.is_some() {
+        return Err("System audio stream already running".to_string());
+    }
+
+    let input = SpeakerInput::new_with_device(device_id.clone()).map_err(|e| {
+        error!("Failed to create speaker input: {}", e);
+        format!("Failed to access system audio: {}", e)
+    })?;
+    let mut stream = input.stream();
+    let sample_rate = stream.sample_rate();
+    if !(8000..=96000).contains(&sample_rate) {
+        return Err(format!(
+            "Invalid sample rate: {}. Expected 8000-96000 Hz",
+            sample_rate
+        ));
+    }
+
+    let chunk_ms = chunk_ms
+        .unwrap_or(DEFAULT_CHUNK_MS)
+        .clamp(MIN_CHUNK_MS, MAX_CHUNK_MS);
+    let chunk_len = (sample_rate as usize * chunk_ms as usize / 1000).max(1);
+    let info = LoopbackInfo {
+        device_id,
+        sample_rate,
+        chunk_ms,
+    };
+
+    let blocks = state.blocks.clone();
+    let task_app = app.clone();
+    let task = tokio::spawn(async move {
+        let started_at = crate::db::now_millis();
+        let mut samples_sent: u64 = 0;
+        let mut seq: u64 = 0;
+        let mut pending = Vec::with_capacity(chunk_len);
+
+        while let Some(sample) = stream.next().await {
+            pending.push(sample);
+            if pending.len() < chunk_len {
+                continue;
+            }
+
+            let block = PcmBlock {
+                source: AudioSource::SystemAudio,
+                sample_rate,
+                timestamp_ms: started_at + (samples_sent * 1000 / sample_rate as u64) as i64,
+                samples: std::mem::replace(&mut pending, Vec::with_capacity(chunk_len)).into(),
+            };
+            samples_sent += block.samples.len() as u64;
+
+            if let Err(e) = task_app.emit(AUDIO_CHUNK_EVENT, AudioChunk::from_block(&block, seq)) {
+                warn!("Failed to emit audio chunk: {}", e);
+            }
+            let _ = blocks.send(block);
+            seq += 1;
+        }
+
+        warn!("System audio stream ended");
+        if let Ok(mut session) = task_app.state::<LoopbackState>().session.lock() {
+            *session = None;
+        }
+        if let Err(e) = task_app.emit("system-audio-stream-stopped", ()) {
+            warn!("Failed to emit system-audio-stream-stopped: {}", e);
+        }
+    });
+
+    *session = Some(LoopbackSession {
+        task,
+        info: info.clone(),
+    });
+    drop(session);
+
+    if let Err(e) = app.emit("system-audio-stream-started", &info) {
+        warn!("Failed to emit system-audio-stream-started: {}", e);
+    }
+
+    Ok(info)
+}
+
+#[tauri::command]
+pub fn stop_system_audio_stream(app: AppHandle) -> Result<(), String> {
+    let session = app
+        .state::<LoopbackState>()
+        .session
+        .lock()
+        .map_err(|e| format!("Failed to acquire lock: {}", e))?
+        .take();
+
+    if let Some(session) = session {
+        // Aborting drops the platform stream, which releases the device
+        session.task.abort();
+        if let Err(e) = app.emit("system-audio-stream-stopped", ()) {
+            warn!("Failed to emit system-audio-stream-stopped: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// The active stream, if any.
+#[tauri::command]
+pub fn get_system_audio_stream_status(app: AppHandle) -> Result<Option<LoopbackInfo>, String> {
+    let state = app.state::<LoopbackState>();
+    let session = state
+        .session
+        .lock()
+        .map_err(|e| format!("Failed to acquire lock: {}", e))?;
+    Ok(session.as_ref().map(|s| s.info.clone()))
+}
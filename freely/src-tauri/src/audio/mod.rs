@@ -0,0 +1,97 @@
+//! Native audio capture.
+//!
+//! Audio is captured in Rust and delivered two ways: as `audio-chunk` events
+//! carrying base64 16-bit PCM for the frontend, and as [`PcmBlock`]s on a
+//! broadcast channel for in-process consumers such as local STT. [`dsp`] can
+//! mix the microphone and system audio into a third, unified source.
+
+use base64::{engine::general_purpose::STANDARD as B64, Engine as _};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+pub(crate) use dsp::downmix_to_mono;
+
+pub mod capture;
+pub mod denoise;
+pub mod devices;
+pub mod dsp;
+pub mod loopback;
+pub mod recorder;
+
+/// Event carrying an [`AudioChunk`] from any capture source.
+pub const AUDIO_CHUNK_EVENT: &str = "audio-chunk";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioSource {
+    Microphone,
+    SystemAudio,
+    /// Microphone and system audio mixed at a common rate by [`dsp`].
+    Mixed,
+}
+
+/// A block of mono f32 samples for in-process consumers.
+#[derive(Debug, Clone)]
+pub struct PcmBlock {
+    pub source: AudioSource,
+    pub sample_rate: u32,
+    /// Capture time of the first sample, in milliseconds since the Unix epoch.
+    pub timestamp_ms: i64,
+    pub samples: Arc<[f32]>,
+}
+
+/// Frontend payload for [`AUDIO_CHUNK_EVENT`].
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioChunk {
+    pub source: AudioSource,
+    pub sample_rate: u32,
+    /// Always 1; chunks are downmixed before they are sent.
+    pub channels: u16,
+    pub timestamp_ms: i64,
+    /// Monotonic per-session counter so the frontend can spot dropped chunks.
+    pub seq: u64,
+    /// Base64-encoded little-endian signed 16-bit PCM.
+    pub pcm: String,
+}
+
+impl AudioChunk {
+    pub fn from_block(block: &PcmBlock, seq: u64) -> Self {
+        Self {
+            source: block.source,
+            sample_rate: block.sample_rate,
+            channels: 1,
+            timestamp_ms: block.timestamp_ms,
+            seq,
+            pcm: encode_pcm_s16le(&block.samples),
+        }
+    }
+}
+
+/// Little-endian signed 16-bit PCM, clamping out-of-range samples.
+pub(crate) fn pcm_s16le(samples: &[f32]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(samples.len() * 2);
+    for &s in samples {
+        let sample = (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        bytes.extend_from_slice(&sample.to_le_bytes());
+    }
+    bytes
+}
+
+pub(crate) fn encode_pcm_s16le(samples: &[f32]) -> String {
+    B64.encode(pcm_s16le(samples))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pcm_encoding_clamps_and_is_little_endian() {
+        let bytes = B64.decode(encode_pcm_s16le(&[0.0, 2.0, -1.0])).unwrap();
+        assert_eq!(bytes.len(), 6);
+        assert_eq!(i16::from_le_bytes([bytes[0], bytes[1]]), 0);
+        assert_eq!(i16::from_le_bytes([bytes[2], bytes[3]]), i16::MAX);
+        assert_eq!(i16::from_le_bytes([bytes[4], bytes[5]]), -i16::MAX);
+    }
+}
@@ -0,0 +1,488 @@
+//! Recording capture streams to disk.
+//!
+//! Each session gets a directory under `recordings/` in the app's local data
+//! directory, holding one mono 16-bit file per source (`microphone.wav`,
+//! `system_audio.wav`, `mixed.wav`) and a `recording.json` describing it,
+//! including the conversation it belongs to. Audio is written as WAV while recording;
+//! FLAC sessions are transcoded when they stop, so a crash mid-session still
+//! leaves a playable WAV.
+//!
+//! Like VAD, recording taps the [`PcmBlock`] broadcasts and doesn't start
+//! capture itself. A source that produces no audio produces no file. Each
+//! source gets a writer thread fed by a tokio task, so file I/O never runs
+//! on the async executor.
+
+use super::{AudioSource, PcmBlock};
+use flacenc::component::BitRepr;
+use flacenc::error::Verify;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+use tauri::{AppHandle, Manager};
+use tokio::sync::{broadcast, oneshot};
+use tracing::warn;
+
+const METADATA_FILE: &str = "recording.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordingFormat {
+    #[default]
+    Wav,
+    Flac,
+}
+
+impl RecordingFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Wav => "wav",
+            Self::Flac => "flac",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingFile {
+    pub source: AudioSource,
+    /// Relative to the recording's directory.
+    pub file_name: String,
+    pub sample_rate: u32,
+    pub duration_ms: i64,
+    pub size_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingInfo {
+    pub id: String,
+    pub conversation_id: Option<String>,
+    pub format: RecordingFormat,
+    pub started_at: i64,
+    /// `None` while recording, or if the app quit mid-session.
+    pub ended_at: Option<i64>,
+    pub files: Vec<RecordingFile>,
+    /// The recording's directory.
+    #[serde(skip_deserializing)]
+    pub path: String,
+}
+
+struct SourceWriter {
+    cancel: oneshot::Sender<()>,
+    thread: JoinHandle<Result<Option<RecordingFile>, String>>,
+}
+
+struct ActiveRecording {
+    info: RecordingInfo,
+    dir: PathBuf,
+    writers: Vec<SourceWriter>,
+}
+
+static ACTIVE: Lazy<Mutex<Option<ActiveRecording>>> = Lazy::new(|| Mutex::new(None));
+
+pub(crate) fn recordings_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_local_data_dir()
+        .map_err(|e| format!("Failed to resolve data directory: {}", e))?;
+    Ok(dir.join("recordings"))
+}
+
+fn source_name(source: AudioSource) -> &'static str {
+    match source {
+        AudioSource::Microphone => "microphone",
+        AudioSource::SystemAudio => "system_audio",
+        AudioSource::Mixed => "mixed",
+    }
+}
+
+fn to_i16(sample: f32) -> i16 {
+    (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16
+}
+
+/// Write every block from `blocks` to `path` as mono 16-bit WAV at the
+/// first block's sample rate. Returns `None` if no audio arrived.
+fn write_wav(
+    path: &Path,
+    source: AudioSource,
+    blocks: mpsc::Receiver<PcmBlock>,
+) -> Result<Option<RecordingFile>, String> {
+    let mut writer: Option<hound::WavWriter<BufWriter<std::fs::File>>> = None;
+    let mut sample_rate = 0;
+    let mut samples_written: u64 = 0;
+
+    for block in blocks {
+        if writer.is_none() {
+            sample_rate = block.sample_rate;
+            let spec = hound::WavSpec {
+                channels: 1,
+                sample_rate,
+                bits_per_sample: 16,
+                sample_format: hound::SampleFormat::Int,
+            };
+            writer = Some(
+                hound::WavWriter::create(path, spec)
+                    .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?,
+            );
+        }
+        let Some(writer) = writer.as_mut() else {
+            continue;
+        };
+        let samples: Box<dyn Iterator<Item = f32>> = if block.sample_rate == sample_rate {
+            Box::new(block.samples.iter().copied())
+        } else {
+            // The device changed mid-session
+            Box::new(
+                crate::stt::local::resample_linear(&block.samples, block.sample_rate, sample_rate)
+                    .into_iter(),
+            )
+        };
+        for sample in samples {
+            writer
+                .write_sample(to_i16(sample))
+                .map_err(|e| format!("Failed to write recording: {}", e))?;
+            samples_written += 1;
+        }
+    }
+
+    let Some(writer) = writer else {
+        return Ok(None);
+    };
+    writer
+        .finalize()
+        .map_err(|e| format!("Failed to finish recording: {}", e))?;
+    Ok(Some(RecordingFile {
+        source,
+        file_name: file_name_of(path),
+        sample_rate,
+        duration_ms: (samples_written * 1000 / sample_rate.max(1) as u64) as i64,
+        size_bytes: std::fs::metadata(path).map(|m| m.len()).unwrap_or(0),
+    }))
+}
+
+fn file_name_of(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Re-encode a 16-bit mono WAV as FLAC next to it, removing the WAV.
+fn transcode_to_flac(wav_path: &Path) -> Result<PathBuf, String> {
+    let reader = hound::WavReader::open(wav_path)
+        .map_err(|e| format!("Failed to read {}: {}", wav_path.display(), e))?;
+    let spec = reader.spec();
+    let samples: Vec<i32> = reader
+        .into_samples::<i16>()
+        .map(|s| s.map(i32::from))
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("Failed to read {}: {}", wav_path.display(), e))?;
+
+    let config = flacenc::config::Encoder::default()
+        .into_verified()
+        .map_err(|(_, e)| format!("Invalid FLAC config: {:?}", e))?;
+    let source = flacenc::source::MemSource::from_samples(
+        &samples,
+        spec.channels as usize,
+        spec.bits_per_sample as usize,
+        spec.sample_rate as usize,
+    );
+    let stream = flacenc::encode_with_fixed_block_size(&config, source, config.block_size)
+        .map_err(|e| format!("Failed to encode FLAC: {:?}", e))?;
+    let mut sink = flacenc::bitsink::ByteSink::new();
+    stream
+        .write(&mut sink)
+        .map_err(|e| format!("Failed to encode FLAC: {:?}", e))?;
+
+    let flac_path = wav_path.with_extension(RecordingFormat::Flac.extension());
+    std::fs::write(&flac_path, sink.as_slice())
+        .map_err(|e| format!("Failed to write {}: {}", flac_path.display(), e))?;
+    std::fs::remove_file(wav_path)
+        .map_err(|e| format!("Failed to remove {}: {}", wav_path.display(), e))?;
+    Ok(flac_path)
+}
+
+pub(crate) fn save_metadata(dir: &Path, info: &RecordingInfo) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(info)
+        .map_err(|e| format!("Failed to serialize recording: {}", e))?;
+    std::fs::write(dir.join(METADATA_FILE), json)
+        .map_err(|e| format!("Failed to write recording metadata: {}", e))
+}
+
+fn load_metadata(dir: &Path) -> Result<RecordingInfo, String> {
+    let raw = std::fs::read_to_string(dir.join(METADATA_FILE))
+        .map_err(|e| format!("Failed to read recording metadata: {}", e))?;
+    let mut info: RecordingInfo =
+        serde_json::from_str(&raw).map_err(|e| format!("Invalid recording metadata: {}", e))?;
+    info.path = dir.to_string_lossy().into_owned();
+    Ok(info)
+}
+
+/// Every recording in `root`, newest first.
+pub(crate) fn list_in(root: &Path, conversation_id: Option<&str>) -> Result<Vec<RecordingInfo>, String> {
+    let entries = match std::fs::read_dir(root) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read recordings directory: {}", e)),
+    };
+    let mut recordings: Vec<RecordingInfo> = entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| load_metadata(&entry.path()).ok())
+        .filter(|info| {
+            conversation_id.is_none() || info.conversation_id.as_deref() == conversation_id
+        })
+        .collect();
+    recordings.sort_by_key(|r| std::cmp::Reverse(r.started_at));
+    Ok(recordings)
+}
+
+fn validate_id(id: &str) -> Result<(), String> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(format!("Invalid recording id: {}", id));
+    }
+    Ok(())
+}
+
+fn start_writer(app: &AppHandle, dir: &Path, source: AudioSource) -> Result<SourceWriter, String> {
+    let mut rx = crate::stt::vad::subscribe(app, source);
+    let (block_tx, block_rx) = mpsc::channel::<PcmBlock>();
+    let (cancel, mut cancel_rx) = oneshot::channel();
+
+    let path = dir.join(format!("{}.wav", source_name(source)));
+    let thread = std::thread::Builder::new()
+        .name(format!("recorder-{}", source_name(source)))
+        .spawn(move || write_wav(&path, source, block_rx))
+        .map_err(|e| format!("Failed to spawn recorder thread: {}", e))?;
+
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = &mut cancel_rx => break,
+                block = rx.recv() => match block {
+                    Ok(block) => {
+                        if block_tx.send(block).is_err() {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Recorder fell behind, skipped {} audio blocks", skipped);
+                    }
+                    // Capture restarts don't end the recording
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+            }
+        }
+        // Dropping block_tx lets the writer thread finish the file
+    });
+
+    Ok(SourceWriter { cancel, thread })
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// Start recording `sources` (both by default) for `conversation_id`.
+#[tauri::command]
+pub async fn start_session_recording(
+    app: AppHandle,
+    conversation_id: Option<String>,
+    sources: Option<Vec<AudioSource>>,
+    format: Option<RecordingFormat>,
+) -> Result<RecordingInfo, String> {
+    let mut active = ACTIVE.lock();
+    if active.is_some() {
+        return Err("A recording is already in progress".to_string());
+    }
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let dir = recordings_dir(&app)?.join(&id);
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create recording directory: {}", e))?;
+
+    let mut sources =
+        sources.unwrap_or_else(|| vec![AudioSource::Microphone, AudioSource::SystemAudio]);
+    let mut seen = Vec::new();
+    sources.retain(|source| {
+        let first = !seen.contains(source);
+        seen.push(*source);
+        first
+    });
+    let info = RecordingInfo {
+        id,
+        conversation_id,
+        format: format.unwrap_or_default(),
+        started_at: crate::db::now_millis(),
+        ended_at: None,
+        files: Vec::new(),
+        path: dir.to_string_lossy().into_owned(),
+    };
+    save_metadata(&dir, &info)?;
+
+    let writers = sources
+        .into_iter()
+        .map(|source| start_writer(&app, &dir, source))
+        .collect::<Result<Vec<_>, _>>()?;
+    *active = Some(ActiveRecording {
+        info: info.clone(),
+        dir,
+        writers,
+    });
+    Ok(info)
+}
+
+/// Finish the current recording. Returns `None` if nothing was recording.
+#[tauri::command]
+pub async fn stop_session_recording() -> Result<Option<RecordingInfo>, String> {
+    let Some(active) = ACTIVE.lock().take() else {
+        return Ok(None);
+    };
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut info = active.info;
+        for writer in active.writers {
+            let _ = writer.cancel.send(());
+            let file = writer
+                .thread
+                .join()
+                .map_err(|_| "Recorder thread panicked".to_string())?;
+            match file {
+                Ok(Some(file)) => info.files.push(file),
+                Ok(None) => {}
+                Err(e) => warn!("{}", e),
+            }
+        }
+
+        if info.format == RecordingFormat::Flac {
+            for file in &mut info.files {
+                match transcode_to_flac(&active.dir.join(&file.file_name)) {
+                    Ok(path) => {
+                        file.file_name = file_name_of(&path);
+                        file.size_bytes = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+                    }
+                    // The WAV is kept, and listed as such
+                    Err(e) => warn!("{}", e),
+                }
+            }
+        }
+
+        info.ended_at = Some(crate::db::now_millis());
+        save_metadata(&active.dir, &info)?;
+        Ok(Some(info))
+    })
+    .await
+    .map_err(|e| format!("Failed to stop recording: {}", e))?
+}
+
+/// Recordings, newest first, optionally only those for `conversation_id`.
+#[tauri::command]
+pub async fn list_recordings(
+    app: AppHandle,
+    conversation_id: Option<String>,
+) -> Result<Vec<RecordingInfo>, String> {
+    let root = recordings_dir(&app)?;
+    tauri::async_runtime::spawn_blocking(move || list_in(&root, conversation_id.as_deref()))
+        .await
+        .map_err(|e| format!("Failed to list recordings: {}", e))?
+}
+
+/// Remove the recording `id` under `root`, unless it is still recording.
+pub(crate) fn delete_in(root: &Path, id: &str) -> Result<(), String> {
+    validate_id(id)?;
+    if ACTIVE
+        .lock()
+        .as_ref()
+        .is_some_and(|active| active.info.id == id)
+    {
+        return Err("Stop the recording before deleting it".to_string());
+    }
+    std::fs::remove_dir_all(root.join(id))
+        .map_err(|e| format!("Failed to delete recording: {}", e))
+}
+
+#[tauri::command]
+pub async fn delete_recording(app: AppHandle, id: String) -> Result<(), String> {
+    delete_in(&recordings_dir(&app)?, &id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn block(sample_rate: u32, len: usize) -> PcmBlock {
+        PcmBlock {
+            source: AudioSource::Microphone,
+            sample_rate,
+            timestamp_ms: 0,
+            samples: vec![0.25; len].into(),
+        }
+    }
+
+    #[test]
+    fn blocks_are_written_and_transcoded() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("microphone.wav");
+        let (tx, rx) = mpsc::channel();
+        tx.send(block(16_000, 8_000)).unwrap();
+        // A device switch mid-session is resampled to the first rate
+        tx.send(block(32_000, 16_000)).unwrap();
+        drop(tx);
+
+        let file = write_wav(&path, AudioSource::Microphone, rx)
+            .unwrap()
+            .unwrap();
+        assert_eq!(file.sample_rate, 16_000);
+        assert_eq!(file.duration_ms, 1000);
+        assert_eq!(hound::WavReader::open(&path).unwrap().len(), 16_000);
+
+        let flac = transcode_to_flac(&path).unwrap();
+        assert!(!path.exists());
+        assert_eq!(&std::fs::read(flac).unwrap()[..4], b"fLaC");
+
+        // No audio, no file
+        let (tx, rx) = mpsc::channel::<PcmBlock>();
+        drop(tx);
+        let silent = tmp.path().join("system_audio.wav");
+        assert!(write_wav(&silent, AudioSource::SystemAudio, rx)
+            .unwrap()
+            .is_none());
+        assert!(!silent.exists());
+    }
+
+    #[test]
+    fn recordings_are_listed_by_conversation() {
+        let tmp = TempDir::new().unwrap();
+        for (id, conversation, started_at) in
+            [("a", Some("c1"), 1), ("b", None, 2), ("c", Some("c1"), 3)]
+        {
+            let dir = tmp.path().join(id);
+            std::fs::create_dir_all(&dir).unwrap();
+            let info = RecordingInfo {
+                id: id.to_string(),
+                conversation_id: conversation.map(str::to_string),
+                format: RecordingFormat::Wav,
+                started_at,
+                ended_at: Some(started_at + 1),
+                files: Vec::new(),
+                path: String::new(),
+            };
+            save_metadata(&dir, &info).unwrap();
+        }
+
+        let ids = |conversation| -> Vec<String> {
+            list_in(tmp.path(), conversation)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.id)
+                .collect()
+        };
+        assert_eq!(ids(None), ["c", "b", "a"]);
+        assert_eq!(ids(Some("c1")), ["c", "a"]);
+        assert!(validate_id("../a").is_err());
+    }
+}
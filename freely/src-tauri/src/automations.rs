@@ -0,0 +1,412 @@
+//! Outbound webhooks for Zapier, n8n and the like, configured by the
+//! `automations` setting.
+//!
+//! Each webhook subscribes to some [`Trigger`]s. When one fires, Freely
+//! POSTs `{"event", "deliveryId", "occurredAt", "data"}` to the webhook's
+//! URL with `X-Freely-Signature: sha256=<hex>`, an HMAC-SHA256 of
+//! `<X-Freely-Timestamp>.<body>` under the webhook's secret, so receivers
+//! can check where a delivery came from and how old it is. Secrets are kept
+//! among the app's internal secrets, not in the setting.
+//!
+//! Message text and meeting minutes travel in `data.content`, which is
+//! only sent to webhooks with `includeContent` set. Failed deliveries are
+//! retried twice; the latest deliveries are kept in memory for
+//! `list_webhook_deliveries`.
+
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::collections::VecDeque;
+use std::time::Duration;
+use tauri::AppHandle;
+use tracing::warn;
+
+/// Settings key for [`AutomationsConfig`].
+pub(crate) const SETTING_KEY: &str = "automations";
+const SECRET_PREFIX: &str = "webhook-";
+const SECRET_BYTES: usize = 32;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+const RETRY_DELAYS: [Duration; 2] = [Duration::from_secs(2), Duration::from_secs(10)];
+const MAX_RECENT_DELIVERIES: usize = 50;
+const PING_EVENT: &str = "ping";
+
+static RECENT: Lazy<Mutex<VecDeque<Delivery>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Trigger {
+    /// A chat completion finished streaming.
+    ConversationFinished,
+    /// A meeting ended and its minutes were written.
+    MeetingSummaryReady,
+    /// A request was refused by a monthly budget.
+    BudgetExceeded,
+}
+
+impl Trigger {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::ConversationFinished => "conversation-finished",
+            Self::MeetingSummaryReady => "meeting-summary-ready",
+            Self::BudgetExceeded => "budget-exceeded",
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Webhook {
+    /// Assigned by `save_webhook` when empty.
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub url: String,
+    pub triggers: Vec<Trigger>,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Send message text and minutes, not just metadata.
+    #[serde(default)]
+    pub include_content: bool,
+}
+
+/// Stored under the `automations` setting.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AutomationsConfig {
+    pub webhooks: Vec<Webhook>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Delivery {
+    pub id: String,
+    pub webhook_id: String,
+    pub event: String,
+    /// HTTP status of the last attempt, if the server answered.
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    pub attempts: u32,
+    pub delivered_at: i64,
+}
+
+impl Webhook {
+    fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Webhook needs a name".to_string());
+        }
+        let url = reqwest::Url::parse(&self.url)
+            .map_err(|e| format!("Invalid webhook URL {}: {}", self.url, e))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err("Webhook URL must start with http:// or https://".to_string());
+        }
+        if self.triggers.is_empty() {
+            return Err("Webhook needs at least one trigger".to_string());
+        }
+        Ok(())
+    }
+}
+
+fn secret_name(webhook_id: &str) -> String {
+    format!("{}{}", SECRET_PREFIX, webhook_id)
+}
+
+fn new_secret() -> String {
+    let mut bytes = [0u8; SECRET_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("whsec_{}", hex)
+}
+
+/// `sha256=` and the hex HMAC of `<timestamp>.<body>`.
+fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    use hmac::{Hmac, Mac};
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes any key length");
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+    let hex: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("sha256={}", hex)
+}
+
+/// The JSON body for one delivery, without `data.content` unless the
+/// webhook asked for it.
+fn body(
+    event: &str,
+    delivery_id: &str,
+    occurred_at: i64,
+    data: &Value,
+    include_content: bool,
+) -> String {
+    let mut data = data.clone();
+    if !include_content {
+        if let Some(data) = data.as_object_mut() {
+            data.remove("content");
+        }
+    }
+    json!({
+        "event": event,
+        "deliveryId": delivery_id,
+        "occurredAt": occurred_at,
+        "data": data,
+    })
+    .to_string()
+}
+
+async fn load_config(app: &AppHandle) -> Result<AutomationsConfig, String> {
+    let pool = crate::db::pool(app).await?;
+    Ok(crate::settings::get_setting(&pool, SETTING_KEY)
+        .await?
+        .unwrap_or_default())
+}
+
+fn record(delivery: &Delivery) {
+    let mut recent = RECENT.lock();
+    if recent.len() == MAX_RECENT_DELIVERIES {
+        recent.pop_back();
+    }
+    recent.push_front(delivery.clone());
+}
+
+/// POST `body`, retrying on network errors, 429 and 5xx. The attempts made,
+/// and the last status or network error.
+async fn post(
+    client: &reqwest::Client,
+    url: &str,
+    secret: &str,
+    event: &str,
+    body: &str,
+) -> (u32, Result<reqwest::StatusCode, String>) {
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let timestamp = chrono::Utc::now().timestamp();
+        let result = client
+            .post(url)
+            .timeout(REQUEST_TIMEOUT)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header("X-Freely-Event", event)
+            .header("X-Freely-Timestamp", timestamp.to_string())
+            .header("X-Freely-Signature", sign(secret, timestamp, body))
+            .body(body.to_string())
+            .send()
+            .await
+            .map(|response| response.status())
+            .map_err(|e| e.to_string());
+        let retry = match &result {
+            Ok(status) => status.is_server_error() || status.as_u16() == 429,
+            Err(_) => true,
+        };
+        match RETRY_DELAYS.get(attempts as usize - 1) {
+            Some(delay) if retry => tokio::time::sleep(*delay).await,
+            _ => return (attempts, result),
+        }
+    }
+}
+
+async fn deliver(app: &AppHandle, webhook: &Webhook, event: &str, data: &Value) -> Delivery {
+    let delivery_id = uuid::Uuid::new_v4().to_string();
+    let delivered_at = crate::db::now_millis();
+    let body = body(
+        event,
+        &delivery_id,
+        delivered_at,
+        data,
+        webhook.include_content,
+    );
+
+    let sent = async {
+        crate::net::connectivity::require_online()?;
+        let secret = crate::secrets::load_secret(app, &secret_name(&webhook.id))
+            .await?
+            .ok_or_else(|| format!("Webhook {} has no secret", webhook.name))?;
+        let client = crate::net::client::http_client(None)?;
+        Ok::<_, String>(post(&client, &webhook.url, &secret, event, &body).await)
+    }
+    .await;
+
+    let (attempts, status, error) = match sent {
+        Ok((attempts, Ok(status))) if status.is_success() => (attempts, Some(status), None),
+        Ok((attempts, Ok(status))) => (
+            attempts,
+            Some(status),
+            Some(format!("Server answered {}", status)),
+        ),
+        Ok((attempts, Err(e))) => (attempts, None, Some(e)),
+        Err(e) => (0, None, Some(e)),
+    };
+    if let Some(error) = &error {
+        warn!("Webhook {} failed for {}: {}", webhook.name, event, error);
+    }
+    let delivery = Delivery {
+        id: delivery_id,
+        webhook_id: webhook.id.clone(),
+        event: event.to_string(),
+        status: status.map(|status| status.as_u16()),
+        error,
+        attempts,
+        delivered_at,
+    };
+    record(&delivery);
+    delivery
+}
+
+/// Send `data` to every enabled webhook subscribed to `trigger`, in the
+/// background.
+pub(crate) fn fire(app: &AppHandle, trigger: Trigger, data: Value) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let config = match load_config(&app).await {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("Failed to load automations: {}", e);
+                return;
+            }
+        };
+        for webhook in config
+            .webhooks
+            .iter()
+            .filter(|webhook| webhook.enabled && webhook.triggers.contains(&trigger))
+        {
+            deliver(&app, webhook, trigger.as_str(), &data).await;
+        }
+    });
+}
+
+fn find(config: &AutomationsConfig, id: &str) -> Result<Webhook, String> {
+    config
+        .webhooks
+        .iter()
+        .find(|webhook| webhook.id == id)
+        .cloned()
+        .ok_or_else(|| format!("Webhook not found: {}", id))
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+#[tauri::command]
+pub async fn list_webhooks(app: AppHandle) -> Result<Vec<Webhook>, String> {
+    Ok(load_config(&app).await?.webhooks)
+}
+
+/// Add or update a webhook. A new webhook gets a generated secret unless
+/// one is given; pass `secret` to replace an existing one.
+#[tauri::command]
+pub async fn save_webhook(
+    app: AppHandle,
+    mut webhook: Webhook,
+    secret: Option<String>,
+) -> Result<Webhook, String> {
+    webhook.name = webhook.name.trim().to_string();
+    webhook.url = webhook.url.trim().to_string();
+    webhook.validate()?;
+    if webhook.id.is_empty() {
+        webhook.id = uuid::Uuid::new_v4().to_string();
+    }
+
+    let mut config = load_config(&app).await?;
+    let name = secret_name(&webhook.id);
+    match secret.filter(|secret| !secret.trim().is_empty()) {
+        Some(secret) => crate::secrets::set_secret(&app, &name, secret).await?,
+        None => {
+            if crate::secrets::load_secret(&app, &name).await?.is_none() {
+                crate::secrets::set_secret(&app, &name, new_secret()).await?;
+            }
+        }
+    }
+
+    match config.webhooks.iter_mut().find(|w| w.id == webhook.id) {
+        Some(existing) => *existing = webhook.clone(),
+        None => config.webhooks.push(webhook.clone()),
+    }
+    crate::settings::set_setting(&app, SETTING_KEY, &config).await?;
+    Ok(webhook)
+}
+
+#[tauri::command]
+pub async fn delete_webhook(app: AppHandle, id: String) -> Result<(), String> {
+    let mut config = load_config(&app).await?;
+    find(&config, &id)?;
+    config.webhooks.retain(|webhook| webhook.id != id);
+    crate::settings::set_setting(&app, SETTING_KEY, &config).await?;
+    crate::secrets::delete_secret(&app, &secret_name(&id)).await
+}
+
+/// The signing secret, for pasting into the receiving service.
+#[tauri::command]
+pub async fn reveal_webhook_secret(app: AppHandle, id: String) -> Result<String, String> {
+    find(&load_config(&app).await?, &id)?;
+    crate::secrets::load_secret(&app, &secret_name(&id))
+        .await?
+        .ok_or_else(|| "Webhook has no secret".to_string())
+}
+
+/// Send a `ping` event to a webhook now, whatever its triggers.
+#[tauri::command]
+pub async fn test_webhook(app: AppHandle, id: String) -> Result<Delivery, String> {
+    let webhook = find(&load_config(&app).await?, &id)?;
+    let data = json!({ "webhookId": webhook.id, "name": webhook.name });
+    Ok(deliver(&app, &webhook, PING_EVENT, &data).await)
+}
+
+/// The latest deliveries, newest first.
+#[tauri::command]
+pub fn list_webhook_deliveries() -> Vec<Delivery> {
+    RECENT.lock().iter().cloned().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deliveries_are_signed_and_hold_content_back_unless_asked() {
+        assert_eq!(
+            sign("whsec_test", 1_700_000_000, r#"{"event":"ping"}"#),
+            "sha256=aa8efe37b751e71157c508c5ac4acb1e9fe5225db98355dfc00f4b680afbc447"
+        );
+
+        let data = json!({ "requestId": "r1", "model": "gpt-4o", "content": "the answer" });
+        let lean: Value =
+            serde_json::from_str(&body("conversation-finished", "d1", 5, &data, false)).unwrap();
+        assert_eq!(lean["event"], "conversation-finished");
+        assert_eq!(lean["data"]["model"], "gpt-4o");
+        assert!(lean["data"].get("content").is_none());
+        let full: Value =
+            serde_json::from_str(&body("conversation-finished", "d1", 5, &data, true)).unwrap();
+        assert_eq!(full["data"]["content"], "the answer");
+
+        let webhook: Webhook = serde_json::from_value(json!({
+            "name": "n8n",
+            "url": "https://n8n.example.com/webhook/abc",
+            "triggers": ["meeting-summary-ready", "budget-exceeded"],
+        }))
+        .unwrap();
+        assert!(webhook.enabled && !webhook.include_content);
+        webhook.validate().unwrap();
+        let ftp = Webhook {
+            url: "ftp://example.com".to_string(),
+            ..webhook.clone()
+        };
+        assert!(ftp.validate().is_err());
+        let silent = Webhook {
+            triggers: Vec::new(),
+            ..webhook
+        };
+        assert!(silent.validate().is_err());
+        assert!(new_secret().starts_with("whsec_"));
+    }
+}
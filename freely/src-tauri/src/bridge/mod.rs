@@ -0,0 +1,265 @@
+//! Bridge between the companion browser extension and the running app.
+//!
+//! The extension can't reach the app directly: the browser starts a second
+//! copy of Freely as its native messaging host (see [`native_messaging`]),
+//! which forwards each message here over a loopback TCP connection. At
+//! startup the app listens on a random port and writes the port and a fresh
+//! token to `bridge.json` in the app data directory, readable only by the
+//! user; connections that don't present the token are dropped.
+//!
+//! An `ask` message carries selected text or page context. It is emitted to
+//! the frontend as `browser-request`, which starts a conversation with the
+//! source attached and answers with `respond_browser_request`; the answer
+//! goes back to the extension. While the app is locked, asks are refused
+//! straight away.
+
+pub(crate) mod native_messaging;
+
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::oneshot;
+use tracing::{info, warn};
+
+pub(crate) const BRIDGE_FILE: &str = "bridge.json";
+/// How long the extension waits for the frontend to answer.
+pub(crate) const ANSWER_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+const TOKEN_BYTES: usize = 32;
+const MAX_LINE_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Requests waiting for `respond_browser_request`, by id.
+static PENDING: Lazy<Mutex<HashMap<String, oneshot::Sender<Result<String, String>>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+/// `bridge.json`, removed again on exit.
+static BRIDGE_PATH: Lazy<Mutex<Option<PathBuf>>> = Lazy::new(|| Mutex::new(None));
+
+/// Contents of `bridge.json`.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct BridgeInfo {
+    pub(crate) port: u16,
+    pub(crate) token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Envelope {
+    token: String,
+    message: Value,
+}
+
+/// The page the text came from.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BrowserSource {
+    pub url: Option<String>,
+    pub title: Option<String>,
+}
+
+/// Emitted as `browser-request`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BrowserRequest {
+    pub id: String,
+    /// Selected text, or the page content the extension extracted.
+    pub text: String,
+    /// What to do with the text, e.g. "Summarize"; `None` to just ask.
+    pub instruction: Option<String>,
+    pub source: BrowserSource,
+    pub received_at: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct AskMessage {
+    #[serde(default)]
+    id: Option<Value>,
+    text: String,
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    instruction: Option<String>,
+}
+
+fn new_token() -> String {
+    let mut bytes = [0u8; TOKEN_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn error_reply(id: Option<&Value>, error: &str) -> Value {
+    json!({ "type": "error", "id": id, "error": error })
+}
+
+async fn ask(app: &AppHandle, message: Value) -> Value {
+    let ask: AskMessage = match serde_json::from_value(message) {
+        Ok(ask) => ask,
+        Err(e) => return error_reply(None, &format!("Invalid ask message: {}", e)),
+    };
+    if ask.text.trim().is_empty() {
+        return error_reply(ask.id.as_ref(), "Nothing to ask about");
+    }
+    if crate::lock::is_locked() {
+        return error_reply(ask.id.as_ref(), "Freely is locked");
+    }
+    let request = BrowserRequest {
+        id: uuid::Uuid::new_v4().to_string(),
+        text: ask.text,
+        instruction: ask.instruction.filter(|i| !i.trim().is_empty()),
+        source: BrowserSource {
+            url: ask.url,
+            title: ask.title,
+        },
+        received_at: crate::db::now_millis(),
+    };
+
+    let (tx, rx) = oneshot::channel();
+    PENDING.lock().insert(request.id.clone(), tx);
+    if let Err(e) = crate::window::show_main_window(app) {
+        warn!("Failed to show main window: {}", e);
+    }
+    if let Err(e) = app.emit("browser-request", &request) {
+        PENDING.lock().remove(&request.id);
+        return error_reply(ask.id.as_ref(), &format!("Failed to reach Freely: {}", e));
+    }
+
+    let answer = tokio::time::timeout(ANSWER_TIMEOUT, rx).await;
+    PENDING.lock().remove(&request.id);
+    match answer {
+        Ok(Ok(Ok(text))) => json!({ "type": "answer", "id": ask.id, "text": text }),
+        Ok(Ok(Err(e))) => error_reply(ask.id.as_ref(), &e),
+        Ok(Err(_)) => error_reply(ask.id.as_ref(), "The request was dropped"),
+        Err(_) => error_reply(ask.id.as_ref(), "Timed out waiting for an answer"),
+    }
+}
+
+async fn handle(app: &AppHandle, message: Value) -> Value {
+    match message.get("type").and_then(Value::as_str) {
+        Some("ping") => json!({ "type": "pong", "version": env!("CARGO_PKG_VERSION") }),
+        Some("ask") => ask(app, message).await,
+        other => error_reply(
+            message.get("id"),
+            &format!("Unknown message type: {}", other.unwrap_or("none")),
+        ),
+    }
+}
+
+async fn serve_connection(app: AppHandle, stream: TcpStream, token: &str) -> Result<(), String> {
+    let (read, mut write) = stream.into_split();
+    let mut line = String::new();
+    BufReader::new(read.take(MAX_LINE_BYTES))
+        .read_line(&mut line)
+        .await
+        .map_err(|e| format!("Failed to read from host: {}", e))?;
+    let envelope: Envelope =
+        serde_json::from_str(&line).map_err(|e| format!("Invalid message from host: {}", e))?;
+    if !crate::api_server::tokens_match(&envelope.token, token) {
+        return Err("Host presented the wrong token".to_string());
+    }
+
+    let reply = handle(&app, envelope.message).await;
+    write
+        .write_all(format!("{}\n", reply).as_bytes())
+        .await
+        .map_err(|e| format!("Failed to answer host: {}", e))
+}
+
+/// Listen for the native messaging host and advertise the port in
+/// `bridge.json`.
+pub fn start_bridge(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let started = async {
+            let listener = TcpListener::bind(("127.0.0.1", 0))
+                .await
+                .map_err(|e| format!("Failed to listen: {}", e))?;
+            let port = listener
+                .local_addr()
+                .map_err(|e| format!("Failed to read bridge address: {}", e))?
+                .port();
+            let token = new_token();
+            let dir = app
+                .path()
+                .app_local_data_dir()
+                .map_err(|e| format!("Could not resolve app_local_data_dir: {}", e))?;
+            std::fs::create_dir_all(&dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+            let path = dir.join(BRIDGE_FILE);
+            let info = serde_json::to_vec(&BridgeInfo {
+                port,
+                token: token.clone(),
+            })
+            .map_err(|e| e.to_string())?;
+            crate::secrets::write_private(&path, &info)?;
+            *BRIDGE_PATH.lock() = Some(path);
+            info!("Browser bridge listening on 127.0.0.1:{}", port);
+            Ok::<_, String>((listener, token))
+        }
+        .await;
+        let (listener, token) = match started {
+            Ok(started) => started,
+            Err(e) => {
+                warn!("Failed to start browser bridge: {}", e);
+                return;
+            }
+        };
+
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    warn!("Browser bridge accept failed: {}", e);
+                    continue;
+                }
+            };
+            let app = app.clone();
+            let token = token.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = serve_connection(app, stream, &token).await {
+                    warn!("Browser bridge: {}", e);
+                }
+            });
+        }
+    });
+}
+
+/// Remove `bridge.json` on exit, so the host reports that Freely isn't
+/// running instead of trying a dead port.
+pub fn shutdown_all() {
+    if let Some(path) = BRIDGE_PATH.lock().take() {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// Answer a `browser-request` with the assistant's reply, or an error to
+/// show in the extension.
+#[tauri::command]
+pub fn respond_browser_request(
+    id: String,
+    answer: Option<String>,
+    error: Option<String>,
+) -> Result<(), String> {
+    let result = match (answer, error) {
+        (Some(answer), None) => Ok(answer),
+        (None, Some(error)) => Err(error),
+        _ => return Err("Pass exactly one of answer and error".to_string()),
+    };
+    let sender = PENDING
+        .lock()
+        .remove(&id)
+        .ok_or_else(|| format!("No pending browser request: {}", id))?;
+    // The extension may have given up already
+    let _ = sender.send(result);
+    Ok(())
+}
@@ -0,0 +1,317 @@
+//! Native messaging host mode, for the companion browser extension.
+//!
+//! The browser starts the host itself, as `freely chrome-extension://<id>/`
+//! (Chromium browsers) or `freely <path to our manifest> <id>` (Firefox),
+//! and talks to it over stdio: each message is UTF-8 JSON preceded by its
+//! length as a 32-bit native-endian integer. [`run_from_args`] spots those
+//! arguments before Tauri starts, so the host never opens a window or
+//! trips the single-instance check. It relays each message to the running
+//! app through the loopback bridge (see [`super`]) and writes the reply
+//! back; if Freely isn't running the extension gets an `error` reply.
+//!
+//! `install_browser_bridge` registers the host with a browser for one
+//! extension id: a manifest file in the browser's `NativeMessagingHosts`
+//! directory, plus a registry key on Windows.
+
+use super::{BridgeInfo, BRIDGE_FILE};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Name the extension connects to with `runtime.connectNative`.
+pub(crate) const HOST_NAME: &str = "com.freely.bridge";
+/// Chrome refuses larger messages from a host.
+const MAX_OUTGOING_BYTES: usize = 1024 * 1024;
+const MAX_INCOMING_BYTES: usize = 8 * 1024 * 1024;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+/// Slightly longer than the app waits for an answer, so its timeout wins.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(super::ANSWER_TIMEOUT.as_secs() + 15);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BrowserKind {
+    Chrome,
+    Chromium,
+    Edge,
+    Firefox,
+}
+
+/// Whether the browser launched us as its native messaging host.
+fn is_host_invocation(args: &[String]) -> bool {
+    args.first().is_some_and(|first| {
+        first.starts_with("chrome-extension://") || first.ends_with(&format!("{}.json", HOST_NAME))
+    })
+}
+
+/// One message from `reader`, or `None` once the browser closes the pipe.
+fn read_message(reader: &mut impl Read) -> Result<Option<Value>, String> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(format!("Failed to read message: {}", e)),
+    }
+    let len = u32::from_ne_bytes(len) as usize;
+    if len > MAX_INCOMING_BYTES {
+        return Err(format!("Message of {} bytes is too large", len));
+    }
+    let mut body = vec![0u8; len];
+    reader
+        .read_exact(&mut body)
+        .map_err(|e| format!("Failed to read message: {}", e))?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(|e| format!("Invalid message: {}", e))
+}
+
+fn write_message(writer: &mut impl Write, message: &Value) -> Result<(), String> {
+    let body = message.to_string();
+    if body.len() > MAX_OUTGOING_BYTES {
+        return Err(format!("Reply of {} bytes is too large", body.len()));
+    }
+    writer
+        .write_all(&(body.len() as u32).to_ne_bytes())
+        .and_then(|()| writer.write_all(body.as_bytes()))
+        .and_then(|()| writer.flush())
+        .map_err(|e| format!("Failed to write message: {}", e))
+}
+
+fn error_reply(message: &Value, error: &str) -> Value {
+    json!({ "type": "error", "id": message.get("id"), "error": error })
+}
+
+/// Hand `message` to the running app and wait for its reply.
+fn relay(data_dir: &Path, message: &Value) -> Result<Value, String> {
+    let not_running = || "Freely is not running".to_string();
+    let raw = std::fs::read(data_dir.join(BRIDGE_FILE)).map_err(|_| not_running())?;
+    let info: BridgeInfo =
+        serde_json::from_slice(&raw).map_err(|e| format!("Invalid {}: {}", BRIDGE_FILE, e))?;
+
+    let address = std::net::SocketAddr::from(([127, 0, 0, 1], info.port));
+    let mut stream =
+        TcpStream::connect_timeout(&address, CONNECT_TIMEOUT).map_err(|_| not_running())?;
+    stream
+        .set_read_timeout(Some(REPLY_TIMEOUT))
+        .map_err(|e| e.to_string())?;
+    let envelope = json!({ "token": info.token, "message": message });
+    writeln!(stream, "{}", envelope).map_err(|_| not_running())?;
+
+    let mut line = String::new();
+    BufReader::new(stream.take(MAX_OUTGOING_BYTES as u64 * 2))
+        .read_line(&mut line)
+        .map_err(|e| format!("Freely did not answer: {}", e))?;
+    serde_json::from_str(&line).map_err(|e| format!("Invalid reply from Freely: {}", e))
+}
+
+fn run_host(data_dir: &Path) -> Result<(), String> {
+    let mut stdin = std::io::stdin().lock();
+    let mut stdout = std::io::stdout().lock();
+    while let Some(message) = read_message(&mut stdin)? {
+        let reply = relay(data_dir, &message).unwrap_or_else(|e| error_reply(&message, &e));
+        let written = write_message(&mut stdout, &reply);
+        if let Err(e) = written {
+            write_message(&mut stdout, &error_reply(&message, &e))?;
+        }
+    }
+    Ok(())
+}
+
+/// Run as the native messaging host if the browser started us, and return
+/// the exit code. `None` means the app should start normally.
+pub fn run_from_args() -> Option<i32> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !is_host_invocation(&args) {
+        return None;
+    }
+    // stdout belongs to the browser, so errors can only go to stderr
+    let result = crate::cli::data_dir().and_then(|dir| run_host(&dir));
+    Some(match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("freely: {}", e);
+            1
+        }
+    })
+}
+
+fn manifest(browser: BrowserKind, extension_id: &str, exe: &Path) -> Value {
+    let mut manifest = json!({
+        "name": HOST_NAME,
+        "description": "Freely browser bridge",
+        "path": exe,
+        "type": "stdio",
+    });
+    if browser == BrowserKind::Firefox {
+        manifest["allowed_extensions"] = json!([extension_id]);
+    } else {
+        manifest["allowed_origins"] = json!([format!("chrome-extension://{}/", extension_id)]);
+    }
+    manifest
+}
+
+fn validate_extension_id(browser: BrowserKind, id: &str) -> Result<(), String> {
+    let valid = match browser {
+        // Chromium ids are 32 letters from a to p
+        BrowserKind::Chrome | BrowserKind::Chromium | BrowserKind::Edge => {
+            id.len() == 32 && id.bytes().all(|b| (b'a'..=b'p').contains(&b))
+        }
+        // Firefox ids look like an email address or a braced UUID
+        BrowserKind::Firefox => {
+            !id.is_empty()
+                && id.len() <= 255
+                && id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "@.-_{}".contains(c))
+        }
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid extension id: {:?}", id))
+    }
+}
+
+/// Where `browser` looks for host manifests, on platforms that use a
+/// directory rather than the registry.
+#[cfg(not(windows))]
+fn manifest_dir(browser: BrowserKind) -> Result<PathBuf, String> {
+    let home = dirs::home_dir().ok_or("Failed to resolve home directory")?;
+    let relative = if cfg!(target_os = "macos") {
+        match browser {
+            BrowserKind::Chrome => "Library/Application Support/Google/Chrome/NativeMessagingHosts",
+            BrowserKind::Chromium => "Library/Application Support/Chromium/NativeMessagingHosts",
+            BrowserKind::Edge => "Library/Application Support/Microsoft Edge/NativeMessagingHosts",
+            BrowserKind::Firefox => "Library/Application Support/Mozilla/NativeMessagingHosts",
+        }
+    } else {
+        match browser {
+            BrowserKind::Chrome => ".config/google-chrome/NativeMessagingHosts",
+            BrowserKind::Chromium => ".config/chromium/NativeMessagingHosts",
+            BrowserKind::Edge => ".config/microsoft-edge/NativeMessagingHosts",
+            BrowserKind::Firefox => ".mozilla/native-messaging-hosts",
+        }
+    };
+    Ok(home.join(relative))
+}
+
+/// The registry key pointing `browser` at our manifest.
+#[cfg(windows)]
+fn registry_key(browser: BrowserKind) -> String {
+    let vendor = match browser {
+        BrowserKind::Chrome => "Google\\Chrome",
+        BrowserKind::Chromium => "Chromium",
+        BrowserKind::Edge => "Microsoft\\Edge",
+        BrowserKind::Firefox => "Mozilla",
+    };
+    format!(
+        "HKCU\\Software\\{}\\NativeMessagingHosts\\{}",
+        vendor, HOST_NAME
+    )
+}
+
+/// Register this executable as the native messaging host for the extension
+/// `extension_id` in `browser`. Returns the manifest path.
+#[tauri::command]
+pub fn install_browser_bridge(
+    browser: BrowserKind,
+    extension_id: String,
+) -> Result<PathBuf, String> {
+    let extension_id = extension_id.trim();
+    validate_extension_id(browser, extension_id)?;
+    let exe = std::env::current_exe()
+        .map_err(|e| format!("Failed to locate the Freely executable: {}", e))?;
+    let body = serde_json::to_vec_pretty(&manifest(browser, extension_id, &exe))
+        .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+
+    #[cfg(not(windows))]
+    let dir = manifest_dir(browser)?;
+    // The registry points at the manifest, which can live anywhere
+    #[cfg(windows)]
+    let dir = crate::cli::data_dir()?
+        .join("native-messaging")
+        .join(format!("{:?}", browser).to_lowercase());
+
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let path = dir.join(format!("{}.json", HOST_NAME));
+    std::fs::write(&path, body)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+    #[cfg(windows)]
+    {
+        let status = std::process::Command::new("reg")
+            .args(["add", &registry_key(browser), "/ve", "/t", "REG_SZ", "/d"])
+            .arg(&path)
+            .arg("/f")
+            .status()
+            .map_err(|e| format!("Failed to run reg: {}", e))?;
+        if !status.success() {
+            return Err("Failed to register the native messaging host".to_string());
+        }
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn messages_are_length_prefixed_json() {
+        let mut framed = Vec::new();
+        write_message(&mut framed, &json!({ "type": "ping" })).unwrap();
+        write_message(&mut framed, &json!({ "type": "ask", "text": "héllo" })).unwrap();
+        assert_eq!(&framed[..4], &15u32.to_ne_bytes());
+
+        let mut reader = Cursor::new(framed);
+        assert_eq!(read_message(&mut reader).unwrap().unwrap()["type"], "ping");
+        assert_eq!(read_message(&mut reader).unwrap().unwrap()["text"], "héllo");
+        assert!(read_message(&mut reader).unwrap().is_none());
+
+        let huge = json!({ "text": "x".repeat(MAX_OUTGOING_BYTES) });
+        assert!(write_message(&mut Vec::new(), &huge).is_err());
+        let mut oversized = Cursor::new((MAX_INCOMING_BYTES as u32 + 1).to_ne_bytes().to_vec());
+        assert!(read_message(&mut oversized).is_err());
+    }
+
+    #[test]
+    fn browsers_are_recognised_and_given_matching_manifests() {
+        let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        assert!(is_host_invocation(&args(&[
+            "chrome-extension://abcdefghijklmnopabcdefghijklmnop/",
+            "--parent-window=0"
+        ])));
+        assert!(is_host_invocation(&args(&[
+            "/home/me/.mozilla/native-messaging-hosts/com.freely.bridge.json",
+            "bridge@freely.app"
+        ])));
+        assert!(!is_host_invocation(&args(&["export", "--all"])));
+        assert!(!is_host_invocation(&args(&["freely://open"])));
+
+        let exe = Path::new("/opt/freely/freely");
+        let chrome = manifest(BrowserKind::Chrome, "abcdefghijklmnopabcdefghijklmnop", exe);
+        assert_eq!(
+            chrome["allowed_origins"][0],
+            "chrome-extension://abcdefghijklmnopabcdefghijklmnop/"
+        );
+        assert_eq!(chrome["path"], "/opt/freely/freely");
+        let firefox = manifest(BrowserKind::Firefox, "bridge@freely.app", exe);
+        assert_eq!(firefox["allowed_extensions"][0], "bridge@freely.app");
+        assert!(firefox.get("allowed_origins").is_none());
+
+        assert!(
+            validate_extension_id(BrowserKind::Chrome, "abcdefghijklmnopabcdefghijklmnop").is_ok()
+        );
+        assert!(validate_extension_id(BrowserKind::Chrome, "not-an-id").is_err());
+        assert!(validate_extension_id(
+            BrowserKind::Firefox,
+            "{6a2b3c4d-1111-2222-3333-444455556666}"
+        )
+        .is_ok());
+        assert!(validate_extension_id(BrowserKind::Firefox, "a\"b").is_err());
+    }
+}
@@ -53,7 +53,7 @@ pub async fn start_screen_capture(app: tauri::AppHandle) -> Result<(), String> {
         .map_err(|e| format!("Failed to get monitor layout: {}", e))?;
 
     if tauri_monitors.len() != capture_monitors.len() {
-        eprintln!(
+        tracing::warn!(
             "Monitor count mismatch between capture ({}) and layout ({}); falling back to capture dimensions",
             capture_monitors.len(),
             tauri_monitors.len()
@@ -0,0 +1,532 @@
+//! Supervised Claude Code CLI sessions.
+//!
+//! Unlike `agents::run_claude`, which blocks until the CLI exits and returns the
+//! collected events, `start_claude_agent` returns immediately and keeps the
+//! child process under Rust-side supervision:
+//!
+//! - stdout and stderr are streamed line-by-line as `claude-agent:output:{session_id}`
+//!   events (stdout lines in stream-json format also carry a parsed [`StreamEvent`])
+//! - `claude-agent:exit:{session_id}` is emitted once when the process ends
+//! - `cancel_claude_agent` sends SIGTERM, escalating to a hard kill after a grace period
+//! - tool permission prompts are bridged to the frontend by [`agent_permissions`],
+//!   with the prompt and the answers written to the CLI's stdin as stream-json
+//! - [`shutdown_all`] kills every live session when the app exits, so no CLI is
+//!   left running without a window attached to it
+//!
+//! The CLI session ID each run reports is stored per Freely session
+//! (`db::agent_sessions`), and later runs without an explicit
+//! `agentSessionId` pass it to `--resume`, so the agent keeps its context
+//! across app launches.
+
+use crate::agent_audit::AgentAuditor;
+use crate::agent_permissions::{self, StdinSender};
+use crate::agents::{self, AgentPayload, StreamEvent};
+use crate::claude_config;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader};
+use tokio::process::Command;
+use tokio::sync::{mpsc, oneshot};
+use tracing::warn;
+
+/// How long a cancelled CLI gets to exit after SIGTERM before it is killed.
+const KILL_GRACE_PERIOD: Duration = Duration::from_secs(3);
+
+static NEXT_RUN_ID: AtomicU64 = AtomicU64::new(0);
+
+struct RunningAgent {
+    /// Tells this run apart from a later one for the same session.
+    run_id: u64,
+    /// Set once the CLI has been spawned.
+    pid: Option<u32>,
+    cancel: oneshot::Sender<()>,
+}
+
+/// Shared state: session_id → live supervised Claude process.
+#[derive(Default)]
+pub struct ClaudeAgentManager {
+    running: Mutex<HashMap<String, RunningAgent>>,
+}
+
+impl ClaudeAgentManager {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, RunningAgent>> {
+        match self.running.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// Drop `session_id`'s entry if it still belongs to run `run_id`.
+    fn release(&self, session_id: &str, run_id: u64) {
+        let mut running = self.lock();
+        if running
+            .get(session_id)
+            .is_some_and(|agent| agent.run_id == run_id)
+        {
+            running.remove(session_id);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentOutputLine {
+    pub session_id: String,
+    /// "stdout" | "stderr"
+    pub stream: &'static str,
+    pub line: String,
+    /// Parsed stream-json event, when the stdout line is JSON.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event: Option<StreamEvent>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentExit {
+    pub session_id: String,
+    pub code: Option<i32>,
+    pub cancelled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A Claude CLI invocation ready to spawn.
+pub(crate) struct ClaudeCommand {
+    pub command: Command,
+    pub working_dir: PathBuf,
+    /// Whether `--resume` uses the stored session rather than one the
+    /// frontend passed.
+    pub resumes_stored: bool,
+    /// With permission prompts bridged, the prompt goes on stdin instead of
+    /// the command line.
+    pub stdin_prompt: Option<String>,
+}
+
+/// The stored CLI session for `session_id`, if it was started in
+/// `working_dir`; the CLI can't find it from anywhere else.
+async fn stored_session(app: &AppHandle, session_id: &str, working_dir: &Path) -> Option<String> {
+    let pool = crate::db::pool(app).await.ok()?;
+    match crate::db::agent_sessions::get(&pool, session_id).await {
+        Ok(Some(stored)) => {
+            let same_dir =
+                stored.working_directory.as_deref() == Some(working_dir.to_string_lossy().as_ref());
+            same_dir.then_some(stored.agent_session_id)
+        }
+        Ok(None) => None,
+        Err(e) => {
+            warn!("{}", e);
+            None
+        }
+    }
+}
+
+/// Remember the CLI session a run of `session_id` ended with. When a run
+/// resumed the stored session but reported none, the CLI no longer has it,
+/// so it is forgotten instead of failing every later run.
+pub(crate) async fn record_session(
+    app: &AppHandle,
+    session_id: &str,
+    working_dir: &Path,
+    resumes_stored: bool,
+    reported: Option<&str>,
+) {
+    let pool = match crate::db::pool(app).await {
+        Ok(pool) => pool,
+        Err(e) => {
+            warn!("{}", e);
+            return;
+        }
+    };
+    let saved = match reported {
+        Some(agent_session_id) => {
+            let working_dir = working_dir.to_string_lossy();
+            crate::db::agent_sessions::save(&pool, session_id, agent_session_id, Some(&working_dir))
+                .await
+        }
+        None if resumes_stored => crate::db::agent_sessions::forget(&pool, session_id)
+            .await
+            .map(|_| ()),
+        None => Ok(()),
+    };
+    if let Err(e) = saved {
+        warn!("{}", e);
+    }
+}
+
+/// Build the Claude CLI invocation for a payload.
+///
+/// Ensures the `.claude` config directory exists and uses it as the working
+/// directory when neither the payload nor the active project names one, so
+/// the CLI picks up `CLAUDE.md` and `settings.json`. Servers from Freely's `mcp.json` are
+/// passed with `--mcp-config`. Without an `agentSessionId` in the payload, the
+/// session stored for `sessionId` is resumed. With `bridge_permissions`, the
+/// CLI asks for permissions over stdio instead of refusing tools outside its
+/// allow list; see [`agent_permissions`].
+pub(crate) async fn build_command(
+    app: &AppHandle,
+    payload: &AgentPayload,
+    bridge_permissions: bool,
+) -> Result<ClaudeCommand, String> {
+    let claude_dir = claude_config::init_claude_config(app)?;
+
+    let binary = agents::resolve_binary("claude").await?;
+
+    let mut cmd = Command::new(&binary);
+    // Clear env vars that cause "nested session" detection when Freely
+    // itself was launched from inside a Claude Code terminal.
+    cmd.env_remove("CLAUDECODE")
+        .env_remove("CLAUDE_CODE_ENTRYPOINT");
+
+    // Set working directory: use the user's project directory when provided,
+    // then the active project, otherwise fall back to the .claude config dir
+    // so the CLI picks up CLAUDE.md.
+    let working_dir = match payload.working_directory {
+        Some(ref working_dir) => PathBuf::from(working_dir),
+        None => {
+            let active_project = crate::db::projects::active_path(app)
+                .await
+                .unwrap_or_else(|e| {
+                    warn!("Failed to load active project: {}", e);
+                    None
+                });
+            active_project.unwrap_or_else(|| claude_dir.clone())
+        }
+    };
+    cmd.current_dir(&working_dir);
+
+    // Build the effective prompt, prepending any system_prompt from the frontend.
+    let effective_prompt = match &payload.system_prompt {
+        Some(sys) => format!("{}\n\n{}", sys, payload.prompt),
+        None => payload.prompt.clone(),
+    };
+
+    // Claude CLI: `claude -p "prompt"` for non-interactive
+    let stdin_prompt = if bridge_permissions {
+        cmd.arg("-p")
+            .arg("--input-format")
+            .arg("stream-json")
+            .arg("--permission-prompt-tool")
+            .arg("stdio");
+        Some(effective_prompt)
+    } else {
+        cmd.arg("-p").arg(&effective_prompt);
+        None
+    };
+    cmd.arg("--output-format")
+        .arg("stream-json")
+        .arg("--verbose");
+
+    // MCP servers configured in Freely's mcp.json, next to the .claude dir
+    if let Some(mcp_config) = crate::mcp::cli_config_path(&claude_dir) {
+        cmd.arg("--mcp-config").arg(mcp_config);
+    }
+
+    // Resume an existing Claude session for conversation continuity.
+    // The CLI maintains full conversation state — no history prepending needed.
+    let mut resumes_stored = false;
+    let resume = match payload.agent_session_id {
+        Some(ref agent_sid) => Some(agent_sid.clone()),
+        None => {
+            let stored = stored_session(app, &payload.session_id, &working_dir).await;
+            resumes_stored = stored.is_some();
+            stored
+        }
+    };
+    if let Some(agent_sid) = resume {
+        cmd.arg("--resume").arg(agent_sid);
+    }
+
+    if let Some(ref model) = payload.model {
+        cmd.arg("--model").arg(model);
+    }
+
+    if let Some(ref perm) = payload.permission_mode {
+        cmd.arg("--allowedTools").arg(perm);
+    }
+
+    let stdin = if stdin_prompt.is_some() {
+        Stdio::piped()
+    } else {
+        Stdio::null()
+    };
+    cmd.stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .stdin(stdin)
+        .kill_on_drop(true);
+
+    Ok(ClaudeCommand {
+        command: cmd,
+        working_dir,
+        resumes_stored,
+        stdin_prompt,
+    })
+}
+
+/// Spawn the Claude CLI for `payload.session_id` and return without waiting.
+#[tauri::command]
+pub async fn start_claude_agent(app: AppHandle, payload: AgentPayload) -> Result<(), String> {
+    let session_id = payload.session_id.clone();
+    let run_id = NEXT_RUN_ID.fetch_add(1, Ordering::Relaxed);
+    let (cancel_tx, cancel_rx) = oneshot::channel();
+    {
+        // Reserved before anything is awaited, so a second start for the
+        // session is refused while this one is still spawning the CLI
+        let manager = app.state::<ClaudeAgentManager>();
+        let mut running = manager.lock();
+        if running.contains_key(&session_id) {
+            return Err(format!(
+                "Claude agent already running for session {}",
+                session_id
+            ));
+        }
+        running.insert(
+            session_id.clone(),
+            RunningAgent {
+                run_id,
+                pid: None,
+                cancel: cancel_tx,
+            },
+        );
+    }
+
+    let started = supervise(app.clone(), payload, run_id, cancel_rx).await;
+    if started.is_err() {
+        app.state::<ClaudeAgentManager>()
+            .release(&session_id, run_id);
+    }
+    started
+}
+
+/// Spawn the CLI for a reserved session and leave it to a supervising task.
+async fn supervise(
+    app: AppHandle,
+    payload: AgentPayload,
+    run_id: u64,
+    mut cancel_rx: oneshot::Receiver<()>,
+) -> Result<(), String> {
+    let session_id = payload.session_id.clone();
+    let ClaudeCommand {
+        mut command,
+        working_dir,
+        resumes_stored,
+        stdin_prompt,
+    } = build_command(&app, &payload, true).await?;
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("Failed to spawn Claude CLI: {}", e))?;
+
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| "Failed to capture stdout".to_string())?;
+    let stderr = child
+        .stderr
+        .take()
+        .ok_or_else(|| "Failed to capture stderr".to_string())?;
+
+    let stdin = match (child.stdin.take(), stdin_prompt) {
+        (Some(pipe), Some(prompt)) => Some(spawn_stdin_writer(pipe, &prompt)),
+        _ => None,
+    };
+
+    if let Some(agent) = app
+        .state::<ClaudeAgentManager>()
+        .lock()
+        .get_mut(&session_id)
+        .filter(|agent| agent.run_id == run_id)
+    {
+        agent.pid = child.id();
+    }
+
+    let stdout_task = tokio::spawn(forward_lines(
+        app.clone(),
+        session_id.clone(),
+        "stdout",
+        stdout,
+        stdin,
+        Some(AgentAuditor::new(&app, &session_id, &working_dir)),
+    ));
+    let stderr_task = tokio::spawn(forward_lines(
+        app.clone(),
+        session_id.clone(),
+        "stderr",
+        stderr,
+        None,
+        None,
+    ));
+
+    tokio::spawn(async move {
+        let mut cancelled = false;
+        let status = tokio::select! {
+            status = child.wait() => status,
+            // A dropped sender isn't a cancel; only an explicit send is
+            Ok(()) = &mut cancel_rx => {
+                cancelled = true;
+                if let Some(pid) = child.id() {
+                    agents::kill_pid(pid).await;
+                }
+                match tokio::time::timeout(KILL_GRACE_PERIOD, child.wait()).await {
+                    Ok(status) => status,
+                    Err(_) => {
+                        let _ = child.start_kill();
+                        child.wait().await
+                    }
+                }
+            }
+        };
+
+        // Drain the pipes so the exit event is always the last one emitted
+        let reported = stdout_task.await.ok().flatten();
+        let _ = stderr_task.await;
+        agent_permissions::clear_session(&app, &session_id);
+        record_session(
+            &app,
+            &session_id,
+            &working_dir,
+            resumes_stored,
+            reported.as_deref(),
+        )
+        .await;
+
+        app.state::<ClaudeAgentManager>()
+            .release(&session_id, run_id);
+
+        let exit = match status {
+            Ok(status) => AgentExit {
+                session_id: session_id.clone(),
+                code: status.code(),
+                cancelled,
+                error: None,
+            },
+            Err(e) => AgentExit {
+                session_id: session_id.clone(),
+                code: None,
+                cancelled,
+                error: Some(format!("Failed to wait for process: {}", e)),
+            },
+        };
+        if let Err(e) = app.emit(&format!("claude-agent:exit:{}", session_id), &exit) {
+            warn!("Failed to emit claude agent exit event: {}", e);
+        }
+    });
+
+    Ok(())
+}
+
+/// Write the prompt and then every line sent on the returned channel to
+/// the CLI's stdin. The pipe is closed, letting the CLI exit, once all
+/// senders are dropped.
+fn spawn_stdin_writer(mut pipe: tokio::process::ChildStdin, prompt: &str) -> StdinSender {
+    let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+    let _ = tx.send(agent_permissions::user_message(prompt));
+    tokio::spawn(async move {
+        while let Some(line) = rx.recv().await {
+            let written = pipe.write_all(format!("{}\n", line).as_bytes()).await;
+            if let Err(e) = written.and(pipe.flush().await) {
+                warn!("Failed to write to Claude CLI: {}", e);
+                break;
+            }
+        }
+    });
+    tx
+}
+
+/// Read a child pipe line-by-line and emit each line to the frontend.
+/// Returns the last CLI session ID reported on it.
+///
+/// With `stdin` set, permission requests are handed to
+/// [`agent_permissions`] instead of being forwarded, and stdin is released
+/// once the CLI reports its result. With `auditor` set, tool calls are
+/// recorded in the audit log.
+async fn forward_lines(
+    app: AppHandle,
+    session_id: String,
+    stream: &'static str,
+    pipe: impl AsyncRead + Unpin,
+    mut stdin: Option<StdinSender>,
+    mut auditor: Option<AgentAuditor>,
+) -> Option<String> {
+    let event_name = format!("claude-agent:output:{}", session_id);
+    let mut lines = BufReader::new(pipe).lines();
+    let mut reported = None;
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        let json = if stream == "stdout" {
+            serde_json::from_str::<serde_json::Value>(line.trim()).ok()
+        } else {
+            None
+        };
+        if let (Some(json), Some(auditor)) = (&json, &mut auditor) {
+            auditor.observe(json).await;
+        }
+        if let (Some(json), Some(sender)) = (&json, &stdin) {
+            if agent_permissions::handle_control_request(&app, &session_id, json, sender) {
+                continue;
+            }
+            if json.get("type").and_then(|t| t.as_str()) == Some("result") {
+                stdin = None;
+            }
+        }
+        let event = json.map(|json| agents::parse_json_event(&json));
+        if let Some(agent_session_id) = event.as_ref().and_then(|e| e.agent_session_id.as_ref()) {
+            reported = Some(agent_session_id.clone());
+        }
+
+        let output = AgentOutputLine {
+            session_id: session_id.clone(),
+            stream,
+            line,
+            event,
+        };
+        if let Err(e) = app.emit(&event_name, &output) {
+            warn!("Failed to emit claude agent output: {}", e);
+        }
+    }
+    reported
+}
+
+/// Cancel a supervised Claude session. Unknown or finished sessions are a no-op.
+#[tauri::command]
+pub fn cancel_claude_agent(app: AppHandle, session_id: String) -> Result<(), String> {
+    let running = app.state::<ClaudeAgentManager>().lock().remove(&session_id);
+    if let Some(running) = running {
+        // The supervising task performs the SIGTERM → kill escalation
+        let _ = running.cancel.send(());
+    }
+    Ok(())
+}
+
+/// Session IDs of all Claude processes currently under supervision.
+#[tauri::command]
+pub fn list_claude_agents(app: AppHandle) -> Vec<String> {
+    app.state::<ClaudeAgentManager>()
+        .lock()
+        .keys()
+        .cloned()
+        .collect()
+}
+
+/// Terminate every supervised Claude process. Called when the app exits, at
+/// which point the async runtime may no longer drive the supervising tasks,
+/// so the processes are signalled synchronously.
+pub fn shutdown_all(app: &AppHandle) {
+    let running: Vec<RunningAgent> = app
+        .state::<ClaudeAgentManager>()
+        .lock()
+        .drain()
+        .map(|(_, agent)| agent)
+        .collect();
+
+    for agent in running {
+        if let Some(pid) = agent.pid {
+            agents::kill_pid_blocking(pid);
+        }
+        let _ = agent.cancel.send(());
+    }
+}
@@ -104,6 +104,471 @@ pub fn update_claude_md(app: AppHandle, content: String) -> Result<(), String> {
         .map_err(|e| format!("Failed to write CLAUDE.md: {}", e))
 }
 
+// ============================================================================
+// settings.json
+// ============================================================================
+
+/// Top-level keys accepted by the Claude CLI in `settings.json`.
+const KNOWN_SETTINGS_KEYS: &[&str] = &[
+    "$schema",
+    "apiKeyHelper",
+    "awsAuthRefresh",
+    "awsCredentialExport",
+    "cleanupPeriodDays",
+    "disableAllHooks",
+    "disabledMcpjsonServers",
+    "enableAllProjectMcpServers",
+    "enabledMcpjsonServers",
+    "env",
+    "forceLoginMethod",
+    "forceLoginOrgUUID",
+    "hooks",
+    "includeCoAuthoredBy",
+    "model",
+    "otelHeadersHelper",
+    "outputStyle",
+    "permissions",
+    "statusLine",
+];
+
+/// Keys accepted under `permissions`.
+const KNOWN_PERMISSION_KEYS: &[&str] = &[
+    "additionalDirectories",
+    "allow",
+    "ask",
+    "defaultMode",
+    "deny",
+    "disableBypassPermissionsMode",
+];
+
+const PERMISSION_MODES: &[&str] = &["acceptEdits", "bypassPermissions", "default", "plan"];
+
+const STRING_SETTINGS: &[&str] = &[
+    "$schema",
+    "apiKeyHelper",
+    "awsAuthRefresh",
+    "awsCredentialExport",
+    "forceLoginMethod",
+    "forceLoginOrgUUID",
+    "model",
+    "otelHeadersHelper",
+    "outputStyle",
+];
+
+const BOOL_SETTINGS: &[&str] = &[
+    "disableAllHooks",
+    "enableAllProjectMcpServers",
+    "includeCoAuthoredBy",
+];
+
+const STRING_ARRAY_SETTINGS: &[&str] = &["disabledMcpjsonServers", "enabledMcpjsonServers"];
+
+fn check_string_array(value: &serde_json::Value, path: &str, errors: &mut Vec<String>) {
+    match value.as_array() {
+        Some(items) => {
+            for (i, item) in items.iter().enumerate() {
+                if !item.is_string() {
+                    errors.push(format!("{}[{}] must be a string", path, i));
+                }
+            }
+        }
+        None => errors.push(format!("{} must be an array of strings", path)),
+    }
+}
+
+fn check_permissions(value: &serde_json::Value, errors: &mut Vec<String>) {
+    let Some(permissions) = value.as_object() else {
+        errors.push("permissions must be an object".to_string());
+        return;
+    };
+
+    for (key, value) in permissions {
+        let path = format!("permissions.{}", key);
+        match key.as_str() {
+            "allow" | "deny" | "ask" | "additionalDirectories" => {
+                check_string_array(value, &path, errors)
+            }
+            "defaultMode" => match value.as_str() {
+                Some(mode) if PERMISSION_MODES.contains(&mode) => {}
+                _ => errors.push(format!(
+                    "{} must be one of: {}",
+                    path,
+                    PERMISSION_MODES.join(", ")
+                )),
+            },
+            "disableBypassPermissionsMode" => {
+                if value.as_str() != Some("disable") {
+                    errors.push(format!("{} must be \"disable\"", path));
+                }
+            }
+            _ => errors.push(format!(
+                "Unknown key {} (expected one of: {})",
+                path,
+                KNOWN_PERMISSION_KEYS.join(", ")
+            )),
+        }
+    }
+}
+
+/// Validate a `settings.json` document against the subset of the Claude
+/// settings schema the CLI enforces. Returns every problem found, joined into
+/// one message, so the user can fix them in a single pass.
+pub(crate) fn validate_claude_settings(settings: &serde_json::Value) -> Result<(), String> {
+    let Some(root) = settings.as_object() else {
+        return Err("Invalid settings.json: root must be a JSON object".to_string());
+    };
+
+    let mut errors = Vec::new();
+
+    for (key, value) in root {
+        let key = key.as_str();
+        if !KNOWN_SETTINGS_KEYS.contains(&key) {
+            errors.push(format!("Unknown key {}", key));
+        } else if key == "permissions" {
+            check_permissions(value, &mut errors);
+        } else if STRING_SETTINGS.contains(&key) && !value.is_string() {
+            errors.push(format!("{} must be a string", key));
+        } else if BOOL_SETTINGS.contains(&key) && !value.is_boolean() {
+            errors.push(format!("{} must be a boolean", key));
+        } else if STRING_ARRAY_SETTINGS.contains(&key) {
+            check_string_array(value, key, &mut errors);
+        } else if key == "cleanupPeriodDays" && !value.is_u64() {
+            errors.push("cleanupPeriodDays must be a non-negative integer".to_string());
+        } else if key == "env" {
+            match value.as_object() {
+                Some(env) => {
+                    for (name, value) in env {
+                        if !value.is_string() {
+                            errors.push(format!("env.{} must be a string", name));
+                        }
+                    }
+                }
+                None => errors.push("env must be an object".to_string()),
+            }
+        } else if (key == "hooks" || key == "statusLine") && !value.is_object() {
+            errors.push(format!("{} must be an object", key));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Invalid settings.json: {}", errors.join("; ")))
+    }
+}
+
+/// Validate `settings` and write it to `settings.json` under `claude_dir`.
+/// Nothing is written when validation fails.
+pub(crate) fn write_claude_settings_in(
+    claude_dir: &std::path::Path,
+    settings: &serde_json::Value,
+) -> Result<(), String> {
+    validate_claude_settings(settings)?;
+
+    std::fs::create_dir_all(claude_dir)
+        .map_err(|e| format!("Failed to create .claude directory: {}", e))?;
+
+    let mut content = serde_json::to_string_pretty(settings)
+        .map_err(|e| format!("Failed to serialize settings.json: {}", e))?;
+    content.push('\n');
+
+    std::fs::write(claude_dir.join("settings.json"), content)
+        .map_err(|e| format!("Failed to write settings.json: {}", e))
+}
+
+pub(crate) fn read_claude_settings_in(
+    claude_dir: &std::path::Path,
+) -> Result<serde_json::Value, String> {
+    let raw = std::fs::read_to_string(claude_dir.join("settings.json"))
+        .map_err(|e| format!("Failed to read settings.json: {}", e))?;
+    serde_json::from_str(&raw).map_err(|e| format!("Failed to parse settings.json: {}", e))
+}
+
+/// Add `rule` to `permissions.allow` in the `settings.json` under
+/// `claude_dir`. Returns `false` if it was already there.
+pub(crate) fn add_allow_rule_in(claude_dir: &std::path::Path, rule: &str) -> Result<bool, String> {
+    let mut settings = read_claude_settings_in(claude_dir)?;
+
+    let root = settings
+        .as_object_mut()
+        .ok_or("Invalid settings.json: root must be a JSON object")?;
+    let permissions = root
+        .entry("permissions")
+        .or_insert_with(|| serde_json::json!({}))
+        .as_object_mut()
+        .ok_or("Invalid settings.json: permissions must be an object")?;
+    let allow = permissions
+        .entry("allow")
+        .or_insert_with(|| serde_json::json!([]))
+        .as_array_mut()
+        .ok_or("Invalid settings.json: permissions.allow must be an array of strings")?;
+    if allow.iter().any(|existing| existing.as_str() == Some(rule)) {
+        return Ok(false);
+    }
+    allow.push(serde_json::Value::from(rule));
+
+    write_claude_settings_in(claude_dir, &settings)?;
+    Ok(true)
+}
+
+/// Read the current settings.json from the app's `.claude` config directory.
+#[tauri::command]
+pub fn get_claude_settings(app: AppHandle) -> Result<serde_json::Value, String> {
+    let data_dir = app
+        .path()
+        .app_local_data_dir()
+        .map_err(|e| format!("Could not resolve app_local_data_dir: {}", e))?;
+
+    read_claude_settings_in(&data_dir.join(".claude"))
+}
+
+/// Validate and write new settings.json content to the app's `.claude` config
+/// directory. Invalid settings are rejected instead of being written, since
+/// the CLI refuses to start with a malformed settings file.
+#[tauri::command]
+pub fn update_claude_settings(app: AppHandle, settings: serde_json::Value) -> Result<(), String> {
+    let data_dir = app
+        .path()
+        .app_local_data_dir()
+        .map_err(|e| format!("Could not resolve app_local_data_dir: {}", e))?;
+
+    write_claude_settings_in(&data_dir.join(".claude"), &settings)
+}
+
+// ============================================================================
+// commands/ (slash-command skills)
+// ============================================================================
+
+/// Slash commands built into the Claude CLI. A custom command with the same
+/// name would never run, so creating one is refused.
+const BUILTIN_COMMANDS: &[&str] = &[
+    "add-dir",
+    "agents",
+    "bug",
+    "clear",
+    "compact",
+    "config",
+    "cost",
+    "doctor",
+    "help",
+    "init",
+    "login",
+    "logout",
+    "mcp",
+    "memory",
+    "model",
+    "permissions",
+    "pr_comments",
+    "review",
+    "status",
+    "terminal-setup",
+    "vim",
+];
+
+const MAX_SKILL_NAME_LEN: usize = 64;
+
+#[derive(Debug, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Skill {
+    /// File stem, which is also the slash command (`/<name>`).
+    pub name: String,
+    pub file_name: String,
+    pub description: Option<String>,
+    pub content: String,
+    pub modified_at: Option<i64>,
+}
+
+/// Turn a user-entered name into a safe file stem: lowercase ASCII letters,
+/// digits, `-` and `_`, with whitespace and other separators collapsed to
+/// single dashes.
+pub(crate) fn sanitize_skill_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    let name = name.strip_suffix(".md").unwrap_or(name);
+
+    let mut slug = String::with_capacity(name.len());
+    for c in name.chars() {
+        let c = c.to_ascii_lowercase();
+        if c.is_ascii_alphanumeric() || c == '_' {
+            slug.push(c);
+        } else if (c == '-' || c.is_whitespace() || c == '.' || c == '/' || c == '\\')
+            && !slug.ends_with('-')
+        {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_matches('-').to_string();
+
+    if slug.is_empty() {
+        return Err(format!(
+            "Invalid skill name {:?}: use letters, digits, '-' or '_'",
+            name
+        ));
+    }
+    if slug.len() > MAX_SKILL_NAME_LEN {
+        return Err(format!(
+            "Invalid skill name: must be at most {} characters",
+            MAX_SKILL_NAME_LEN
+        ));
+    }
+    Ok(slug)
+}
+
+/// Description from YAML frontmatter, or else the first non-empty line with
+/// any heading markers removed.
+fn skill_description(content: &str) -> Option<String> {
+    let mut lines = content.lines();
+    if content.starts_with("---") {
+        lines.next();
+        for line in lines.by_ref() {
+            if line.trim() == "---" {
+                break;
+            }
+            if let Some(value) = line.strip_prefix("description:") {
+                let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+                return (!value.is_empty()).then(|| value.to_string());
+            }
+        }
+    }
+    lines
+        .map(|line| line.trim().trim_start_matches('#').trim())
+        .find(|line| !line.is_empty())
+        .map(str::to_string)
+}
+
+/// Path of an existing skill. `name` must be a plain file stem as returned
+/// by [`list_skills_in`], never a path.
+fn existing_skill_path(commands_dir: &std::path::Path, name: &str) -> Result<PathBuf, String> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err(format!("Invalid skill name {:?}", name));
+    }
+    let path = commands_dir.join(format!("{}.md", name));
+    if !path.is_file() {
+        return Err(format!("Skill not found: {}", name));
+    }
+    Ok(path)
+}
+
+fn read_skill(path: &std::path::Path) -> Result<Skill, String> {
+    let content =
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read skill: {}", e))?;
+    let modified_at = std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as i64);
+
+    Ok(Skill {
+        name: path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        file_name: path
+            .file_name()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        description: skill_description(&content),
+        content,
+        modified_at,
+    })
+}
+
+/// Every `*.md` file directly under `commands/`, sorted by name.
+pub(crate) fn list_skills_in(claude_dir: &std::path::Path) -> Result<Vec<Skill>, String> {
+    let commands_dir = claude_dir.join("commands");
+    let entries = match std::fs::read_dir(&commands_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read commands directory: {}", e)),
+    };
+
+    let mut skills = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "md") {
+            skills.push(read_skill(&path)?);
+        }
+    }
+    skills.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(skills)
+}
+
+/// Create `commands/<name>.md`. Fails if the sanitized name is taken,
+/// compared case-insensitively since macOS and Windows file systems are.
+pub(crate) fn create_skill_in(
+    claude_dir: &std::path::Path,
+    name: &str,
+    content: &str,
+) -> Result<Skill, String> {
+    let name = sanitize_skill_name(name)?;
+    if BUILTIN_COMMANDS.contains(&name.as_str()) {
+        return Err(format!("/{} is a built-in Claude command", name));
+    }
+    if list_skills_in(claude_dir)?
+        .iter()
+        .any(|skill| skill.name.eq_ignore_ascii_case(&name))
+    {
+        return Err(format!("A skill named {} already exists", name));
+    }
+
+    let commands_dir = claude_dir.join("commands");
+    std::fs::create_dir_all(&commands_dir)
+        .map_err(|e| format!("Failed to create commands directory: {}", e))?;
+    let path = commands_dir.join(format!("{}.md", name));
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .and_then(|mut file| std::io::Write::write_all(&mut file, content.as_bytes()))
+        .map_err(|e| format!("Failed to write skill: {}", e))?;
+    read_skill(&path)
+}
+
+pub(crate) fn update_skill_in(
+    claude_dir: &std::path::Path,
+    name: &str,
+    content: &str,
+) -> Result<Skill, String> {
+    let path = existing_skill_path(&claude_dir.join("commands"), name)?;
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write skill: {}", e))?;
+    read_skill(&path)
+}
+
+pub(crate) fn delete_skill_in(claude_dir: &std::path::Path, name: &str) -> Result<(), String> {
+    let path = existing_skill_path(&claude_dir.join("commands"), name)?;
+    std::fs::remove_file(&path).map_err(|e| format!("Failed to delete skill: {}", e))
+}
+
+pub(crate) fn claude_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app
+        .path()
+        .app_local_data_dir()
+        .map_err(|e| format!("Could not resolve app_local_data_dir: {}", e))?;
+    Ok(data_dir.join(".claude"))
+}
+
+/// List the slash-command skills in the app's `.claude/commands` directory.
+#[tauri::command]
+pub fn list_skills(app: AppHandle) -> Result<Vec<Skill>, String> {
+    list_skills_in(&claude_dir(&app)?)
+}
+
+/// Create a new skill; `name` is sanitized into the file name.
+#[tauri::command]
+pub fn create_skill(app: AppHandle, name: String, content: String) -> Result<Skill, String> {
+    create_skill_in(&claude_dir(&app)?, &name, &content)
+}
+
+/// Replace the content of an existing skill.
+#[tauri::command]
+pub fn update_skill(app: AppHandle, name: String, content: String) -> Result<Skill, String> {
+    update_skill_in(&claude_dir(&app)?, &name, &content)
+}
+
+#[tauri::command]
+pub fn delete_skill(app: AppHandle, name: String) -> Result<(), String> {
+    delete_skill_in(&claude_dir(&app)?, &name)
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -209,4 +674,97 @@ mod tests {
         let content = std::fs::read_to_string(&canary).unwrap();
         assert!(content.contains("Canary Skill"));
     }
+
+    #[test]
+    fn default_settings_pass_validation() {
+        let parsed: serde_json::Value = serde_json::from_str(DEFAULT_SETTINGS_JSON).unwrap();
+        assert!(validate_claude_settings(&parsed).is_ok());
+    }
+
+    #[test]
+    fn settings_validation_rejects_unknown_keys_and_bad_types() {
+        let settings = serde_json::json!({
+            "permisions": {},
+            "permissions": { "allow": ["Read", 42], "deny": "Bash", "bogus": [] },
+            "includeCoAuthoredBy": "yes",
+        });
+        let err = validate_claude_settings(&settings).unwrap_err();
+        assert!(err.contains("Unknown key permisions"), "{}", err);
+        assert!(err.contains("permissions.allow[1] must be a string"), "{}", err);
+        assert!(err.contains("permissions.deny must be an array"), "{}", err);
+        assert!(err.contains("Unknown key permissions.bogus"), "{}", err);
+        assert!(err.contains("includeCoAuthoredBy must be a boolean"), "{}", err);
+
+        assert!(validate_claude_settings(&serde_json::json!([])).is_err());
+    }
+
+    #[test]
+    fn write_settings_round_trips_and_skips_invalid() {
+        let (_tmp, claude_dir) = setup();
+        let settings = serde_json::json!({
+            "permissions": { "allow": ["Read"], "deny": ["Bash(rm:*)"], "defaultMode": "plan" },
+            "env": { "FOO": "bar" },
+        });
+        write_claude_settings_in(&claude_dir, &settings).unwrap();
+
+        let raw = std::fs::read_to_string(claude_dir.join("settings.json")).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&raw).unwrap();
+        assert_eq!(parsed, settings);
+
+        let invalid = serde_json::json!({ "permissions": { "defaultMode": "yolo" } });
+        assert!(write_claude_settings_in(&claude_dir, &invalid).is_err());
+        let after = std::fs::read_to_string(claude_dir.join("settings.json")).unwrap();
+        assert_eq!(after, raw, "Invalid settings must not be written");
+    }
+
+    #[test]
+    fn skill_names_are_sanitized() {
+        assert_eq!(sanitize_skill_name("Review PR").unwrap(), "review-pr");
+        assert_eq!(sanitize_skill_name("  fix_bug.md ").unwrap(), "fix_bug");
+        assert_eq!(
+            sanitize_skill_name("../../etc/passwd").unwrap(),
+            "etc-passwd"
+        );
+        assert_eq!(sanitize_skill_name("a -- b").unwrap(), "a-b");
+        assert!(sanitize_skill_name("???").is_err());
+        assert!(sanitize_skill_name(&"x".repeat(65)).is_err());
+    }
+
+    #[test]
+    fn skills_crud_with_collision_checks() {
+        let (_tmp, claude_dir) = setup();
+        assert!(list_skills_in(&claude_dir).unwrap().is_empty());
+
+        let content = "---\ndescription: Review the staged diff\n---\nReview $ARGUMENTS";
+        let skill = create_skill_in(&claude_dir, "Code Review", content).unwrap();
+        assert_eq!(skill.name, "code-review");
+        assert_eq!(skill.file_name, "code-review.md");
+        assert_eq!(skill.description.as_deref(), Some("Review the staged diff"));
+
+        let err = create_skill_in(&claude_dir, "code review", "dup").unwrap_err();
+        assert!(err.contains("already exists"), "{}", err);
+        assert!(create_skill_in(&claude_dir, "Clear", "x").is_err());
+
+        let updated =
+            update_skill_in(&claude_dir, "code-review", "# Careful review\nBody").unwrap();
+        assert_eq!(updated.description.as_deref(), Some("Careful review"));
+        assert!(update_skill_in(&claude_dir, "missing", "x").is_err());
+        assert!(update_skill_in(&claude_dir, "../settings", "x").is_err());
+
+        delete_skill_in(&claude_dir, "code-review").unwrap();
+        assert!(list_skills_in(&claude_dir).unwrap().is_empty());
+        assert!(delete_skill_in(&claude_dir, "code-review").is_err());
+    }
+
+    #[test]
+    fn always_allow_rules_are_added_once() {
+        let (_tmp, claude_dir) = setup();
+        assert!(add_allow_rule_in(&claude_dir, "Bash(npm test)").unwrap());
+        assert!(!add_allow_rule_in(&claude_dir, "Bash(npm test)").unwrap());
+
+        let settings = read_claude_settings_in(&claude_dir).unwrap();
+        let allow = settings["permissions"]["allow"].as_array().unwrap();
+        assert_eq!(allow.last().unwrap(), "Bash(npm test)");
+        assert!(allow.iter().any(|rule| rule == "Read"), "existing rules stay");
+    }
 }
@@ -0,0 +1,484 @@
+//! Headless subcommands for scripting Freely.
+//!
+//! ```text
+//! freely export --all [--format json|markdown|html|pdf] [--out PATH]
+//! freely export <conversation-id> [--format json|markdown|html|pdf] [--out PATH]
+//! freely backup [--out PATH]
+//! freely transcribe <file.wav> [--model tiny.en|base.en|small.en]
+//! ```
+//!
+//! The arguments are checked before Tauri starts; anything that isn't one
+//! of these subcommands, `freely://` links included, launches the app as
+//! usual. The subcommands use the same export, backup and Whisper code as
+//! the app, on the database in the app's data directory, so they work
+//! whether or not the app is running, once it has created the database.
+//! Results go to stdout (or `--out`) and errors to stderr.
+
+use crate::db::{self, backup, cipher};
+use crate::export::{self, ExportFormat};
+use crate::models::ModelKind;
+use crate::redaction::Redactor;
+use crate::speaker::local_whisper::{WhisperEngine, WhisperModel};
+use crate::stt::local::{self as whisper, WHISPER_SAMPLE_RATE};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// `identifier` in `tauri.conf.json`, which names the data directory.
+const APP_IDENTIFIER: &str = "com.freely.app";
+const PAGE_SIZE: u32 = 200;
+/// How long to wait for the app to finish a write before giving up.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+const USAGE: &str = "\
+Usage:
+  freely export --all [--format json|markdown|html|pdf] [--out PATH]
+  freely export <conversation-id> [--format json|markdown|html|pdf] [--out PATH]
+  freely backup [--out PATH]
+  freely transcribe <file.wav> [--model tiny.en|base.en|small.en]
+
+Run without arguments to open the app.
+
+  export      Write conversations as one JSON document (the default with
+              --all, printed unless --out is given) or as Markdown (one
+              file per conversation in the --out directory).
+  backup      Snapshot the database, by default into the backups directory.
+  transcribe  Print a timestamped transcript of a WAV file using a
+              downloaded Whisper model, by default the largest one.";
+
+#[derive(Debug, PartialEq)]
+enum ExportTarget {
+    All,
+    Conversation(String),
+}
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Export {
+        target: ExportTarget,
+        format: ExportFormat,
+        out: Option<PathBuf>,
+    },
+    Backup {
+        out: Option<PathBuf>,
+    },
+    Transcribe {
+        file: PathBuf,
+        model: Option<WhisperModel>,
+    },
+    Help,
+}
+
+#[derive(Debug, Default)]
+struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+    switches: HashSet<String>,
+}
+
+/// Split `args` into positionals, `--name value` (or `--name=value`)
+/// `options` and bare `switches`.
+fn split_args(args: &[String], options: &[&str], switches: &[&str]) -> Result<Args, String> {
+    let mut parsed = Args::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let Some(flag) = arg.strip_prefix("--") else {
+            parsed.positional.push(arg.clone());
+            continue;
+        };
+        let (name, inline) = match flag.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (flag, None),
+        };
+        if options.contains(&name) {
+            let value = match inline {
+                Some(value) => value,
+                None => args
+                    .next()
+                    .cloned()
+                    .ok_or_else(|| format!("--{} needs a value", name))?,
+            };
+            parsed.options.insert(name.to_string(), value);
+        } else if switches.contains(&name) && inline.is_none() {
+            parsed.switches.insert(name.to_string());
+        } else {
+            return Err(format!("Unknown option: {}", arg));
+        }
+    }
+    Ok(parsed)
+}
+
+fn parse_format(value: &str) -> Result<ExportFormat, String> {
+    match value {
+        "json" => Ok(ExportFormat::Json),
+        "markdown" | "md" => Ok(ExportFormat::Markdown),
+        "html" => Ok(ExportFormat::Html),
+        "pdf" => Ok(ExportFormat::Pdf),
+        other => Err(format!("Unknown export format: {}", other)),
+    }
+}
+
+fn parse_model(value: &str) -> Result<WhisperModel, String> {
+    match value {
+        "tiny.en" => Ok(WhisperModel::TinyEn),
+        "base.en" => Ok(WhisperModel::BaseEn),
+        "small.en" => Ok(WhisperModel::SmallEn),
+        other => Err(format!("Unknown Whisper model: {}", other)),
+    }
+}
+
+fn parse_export(args: &[String]) -> Result<Command, String> {
+    let args = split_args(args, &["format", "out"], &["all"])?;
+    let target = match (args.switches.contains("all"), args.positional.as_slice()) {
+        (true, []) => ExportTarget::All,
+        (false, [id]) => ExportTarget::Conversation(id.clone()),
+        _ => return Err("export takes either --all or one conversation ID".to_string()),
+    };
+    let format = match args.options.get("format") {
+        Some(format) => parse_format(format)?,
+        None if target == ExportTarget::All => ExportFormat::Json,
+        None => ExportFormat::Markdown,
+    };
+    let out = args.options.get("out").map(PathBuf::from);
+    if target == ExportTarget::All && format != ExportFormat::Json && out.is_none() {
+        return Err(format!(
+            "{} exports of every conversation need an --out directory",
+            format.filter_name()
+        ));
+    }
+    Ok(Command::Export {
+        target,
+        format,
+        out,
+    })
+}
+
+fn parse_backup(args: &[String]) -> Result<Command, String> {
+    let args = split_args(args, &["out"], &[])?;
+    if let Some(extra) = args.positional.first() {
+        return Err(format!("Unexpected argument: {}", extra));
+    }
+    Ok(Command::Backup {
+        out: args.options.get("out").map(PathBuf::from),
+    })
+}
+
+fn parse_transcribe(args: &[String]) -> Result<Command, String> {
+    let args = split_args(args, &["model"], &[])?;
+    let [file] = args.positional.as_slice() else {
+        return Err("transcribe takes one audio file".to_string());
+    };
+    Ok(Command::Transcribe {
+        file: PathBuf::from(file),
+        model: args
+            .options
+            .get("model")
+            .map(|m| parse_model(m))
+            .transpose()?,
+    })
+}
+
+/// The subcommand named by `args` (without the executable), or `None` when
+/// the app should start normally.
+fn parse(args: &[String]) -> Option<Result<Command, String>> {
+    let (name, rest) = args.split_first()?;
+    Some(match name.as_str() {
+        "export" => parse_export(rest),
+        "backup" => parse_backup(rest),
+        "transcribe" => parse_transcribe(rest),
+        "help" | "--help" | "-h" => Ok(Command::Help),
+        _ => return None,
+    })
+}
+
+pub(crate) fn data_dir() -> Result<PathBuf, String> {
+    dirs::data_local_dir()
+        .map(|dir| dir.join(APP_IDENTIFIER))
+        .ok_or_else(|| "Failed to resolve data directory".to_string())
+}
+
+/// The app's database, if it exists and is on the schema this build
+/// expects.
+async fn open_database(data_dir: &Path) -> Result<SqlitePool, String> {
+    let path = backup::database_path_in(data_dir);
+    if !path.is_file() {
+        return Err(format!(
+            "No database at {}; open Freely once to create it",
+            path.display()
+        ));
+    }
+    let mut options = SqliteConnectOptions::new()
+        .filename(&path)
+        .busy_timeout(BUSY_TIMEOUT);
+    if cipher::is_encrypted(&path) {
+        let key = cipher::database_key_in(data_dir)?;
+        options = options.pragma("key", key.pragma());
+        // `backup` writes its snapshot with the same key
+        cipher::set_active_key(Some(key));
+    }
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(options)
+        .await
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+
+    let version: Option<i64> = sqlx::query_scalar("SELECT MAX(version) FROM _sqlx_migrations")
+        .fetch_one(&pool)
+        .await
+        .map_err(|e| format!("Failed to read schema version: {}", e))?;
+    let latest = db::schema::latest_version();
+    match version {
+        Some(version) if version == latest => Ok(pool),
+        Some(version) if version > latest => Err(format!(
+            "The database is from a newer version of Freely (schema {})",
+            version
+        )),
+        _ => Err("The database needs upgrading; open Freely once first".to_string()),
+    }
+}
+
+fn write_output(out: Option<&Path>, content: &[u8]) -> Result<(), String> {
+    match out {
+        Some(path) => {
+            std::fs::write(path, content)
+                .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+            eprintln!("Wrote {}", path.display());
+        }
+        None => std::io::stdout()
+            .write_all(content)
+            .map_err(|e| format!("Failed to write to stdout: {}", e))?,
+    }
+    Ok(())
+}
+
+/// Every conversation outside the trash, archived ones included.
+async fn all_conversations(pool: &SqlitePool) -> Result<Vec<db::chat::Conversation>, String> {
+    let mut conversations = Vec::new();
+    for archived in [false, true] {
+        let mut offset = 0;
+        loop {
+            let page = db::chat::list(pool, archived, PAGE_SIZE, offset).await?;
+            for summary in &page {
+                conversations.extend(db::chat::get(pool, &summary.id).await?);
+            }
+            if page.len() < PAGE_SIZE as usize {
+                break;
+            }
+            offset += PAGE_SIZE;
+        }
+    }
+    Ok(conversations)
+}
+
+async fn run_export(
+    pool: &SqlitePool,
+    target: ExportTarget,
+    format: ExportFormat,
+    out: Option<&Path>,
+) -> Result<(), String> {
+    let conversation = match target {
+        ExportTarget::All => None,
+        ExportTarget::Conversation(id) => Some(
+            db::chat::get(pool, &id)
+                .await?
+                .ok_or_else(|| format!("Conversation not found: {}", id))?,
+        ),
+    };
+    match (format, conversation) {
+        (_, Some(conversation)) => {
+            let content = export::render(pool, &conversation, format, &Redactor::none()).await?;
+            write_output(out, &content)
+        }
+        (ExportFormat::Json, None) => write_output(
+            out,
+            export::render_json(all_conversations(pool).await?)?.as_bytes(),
+        ),
+        (_, None) => {
+            let dir = out.ok_or_else(|| {
+                format!("{} exports need an --out directory", format.filter_name())
+            })?;
+            let conversations = all_conversations(pool).await?;
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+            let mut used = HashSet::new();
+            for conversation in &conversations {
+                let mut name = export::default_file_name(&conversation.title, format);
+                // Titles repeat; the ID keeps their files apart
+                if !used.insert(name.clone()) {
+                    name = format!("{}-{}", conversation.id, name);
+                }
+                let path = dir.join(name);
+                let content = export::render(pool, conversation, format, &Redactor::none()).await?;
+                std::fs::write(&path, content)
+                    .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+            }
+            eprintln!(
+                "Exported {} conversation(s) to {}",
+                conversations.len(),
+                dir.display()
+            );
+            Ok(())
+        }
+    }
+}
+
+async fn run_backup(
+    pool: &SqlitePool,
+    data_dir: &Path,
+    out: Option<PathBuf>,
+) -> Result<(), String> {
+    let dest = out.unwrap_or_else(|| {
+        let name = format!("manual-{}.db", chrono::Local::now().format("%Y%m%d-%H%M%S"));
+        backup::backups_dir_in(data_dir).join(name)
+    });
+    if dest == backup::database_path_in(data_dir) {
+        return Err("Choose a location other than the live database".to_string());
+    }
+    backup::backup_to(pool, &dest).await?;
+    println!("{}", dest.display());
+    Ok(())
+}
+
+fn run_transcribe(data_dir: &Path, file: &Path, model: Option<WhisperModel>) -> Result<(), String> {
+    let models_dir = data_dir.join("models").join(ModelKind::Whisper.dir_name());
+    let model = match model {
+        Some(model) => model,
+        None => whisper::largest_downloaded(&models_dir)
+            .ok_or("No Whisper model downloaded; download one in Freely's settings")?,
+    };
+    let model_path = models_dir.join(model.filename());
+    if !model_path.is_file() {
+        return Err(format!("Model {} is not downloaded", model.filename()));
+    }
+
+    let bytes =
+        std::fs::read(file).map_err(|e| format!("Failed to read {}: {}", file.display(), e))?;
+    let (samples, sample_rate) = whisper::decode_wav(&bytes)?;
+    let samples = whisper::resample_linear(&samples, sample_rate, WHISPER_SAMPLE_RATE);
+
+    let mut engine = WhisperEngine::new();
+    engine.init(model_path)?;
+    whisper::transcribe_windows(&engine, &samples, |window| {
+        if !window.text.is_empty() {
+            println!(
+                "[{}] {}",
+                crate::stt::file::format_offset(window.start_ms),
+                window.text
+            );
+        }
+        Ok(())
+    })?;
+    Ok(())
+}
+
+fn execute(command: Command) -> Result<(), String> {
+    if command == Command::Help {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+    let data_dir = data_dir()?;
+    if let Command::Transcribe { file, model } = &command {
+        return run_transcribe(&data_dir, file, *model);
+    }
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| format!("Failed to start runtime: {}", e))?;
+    runtime.block_on(async {
+        let pool = open_database(&data_dir).await?;
+        let result = match command {
+            Command::Export {
+                target,
+                format,
+                out,
+            } => run_export(&pool, target, format, out.as_deref()).await,
+            Command::Backup { out } => run_backup(&pool, &data_dir, out).await,
+            Command::Transcribe { .. } | Command::Help => Ok(()),
+        };
+        pool.close().await;
+        result
+    })
+}
+
+/// Release builds on Windows are GUI programs with no console; borrow the
+/// terminal's so output shows up there.
+#[cfg(windows)]
+fn attach_console() {
+    use windows_sys::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+    // SAFETY: no preconditions; failing just means there's no console
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+/// Run the subcommand named on the command line, if any, and return the
+/// process exit code. `None` means the app should start normally.
+pub fn run_from_args() -> Option<i32> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command = parse(&args)?;
+    #[cfg(windows)]
+    attach_console();
+    let result = command
+        .map_err(|e| format!("{}\n\n{}", e, USAGE))
+        .and_then(execute);
+    Some(match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("freely: {}", e);
+            1
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_str(args: &str) -> Option<Result<Command, String>> {
+        let args: Vec<String> = args.split_whitespace().map(String::from).collect();
+        parse(&args)
+    }
+
+    #[test]
+    fn subcommands_are_parsed() {
+        assert_eq!(
+            parse_str("export --all"),
+            Some(Ok(Command::Export {
+                target: ExportTarget::All,
+                format: ExportFormat::Json,
+                out: None,
+            }))
+        );
+        assert_eq!(
+            parse_str("export abc --format=json --out chat.json"),
+            Some(Ok(Command::Export {
+                target: ExportTarget::Conversation("abc".to_string()),
+                format: ExportFormat::Json,
+                out: Some(PathBuf::from("chat.json")),
+            }))
+        );
+        assert_eq!(
+            parse_str("transcribe call.wav --model base.en"),
+            Some(Ok(Command::Transcribe {
+                file: PathBuf::from("call.wav"),
+                model: Some(WhisperModel::BaseEn),
+            }))
+        );
+        assert_eq!(parse_str("backup"), Some(Ok(Command::Backup { out: None })));
+
+        assert!(matches!(
+            parse_str("export --all --format md"),
+            Some(Err(_))
+        ));
+        assert!(matches!(parse_str("export --all abc"), Some(Err(_))));
+        assert!(matches!(parse_str("backup --keep 3"), Some(Err(_))));
+
+        // Not subcommands: the app starts
+        assert!(parse_str("").is_none());
+        assert!(parse_str("freely://new?prompt=hi").is_none());
+    }
+}
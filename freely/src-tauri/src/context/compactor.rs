@@ -0,0 +1,427 @@
+//! Rolling context compaction for long conversations.
+//!
+//! A background task periodically looks for conversations whose
+//! unsummarized history has grown past `triggerTokens`, and queues a job
+//! asking the configured provider to fold the older part into the
+//! conversation's running summary. The most recent `keepRecentTokens` of messages are left
+//! verbatim. Prompt building then uses `get_compacted_context`, which
+//! returns the summary plus the messages after it, trimmed to a budget.
+
+use crate::db::chat::{Message, MessageRole};
+use crate::db::summaries::{self, ContextSummary};
+use crate::jobs::JobSpec;
+use crate::providers::middleware::Retry;
+use crate::providers::{ChatMessage, ChatRole, CompletionRequest, ProviderKind};
+use crate::tokens::{self, Encoding};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+
+pub(crate) const CONFIG_SETTING_KEY: &str = "context_compaction";
+const SCAN_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// Conversations compacted per scan, so one pass can't burn through a
+/// rate limit.
+const MAX_PER_SCAN: u32 = 5;
+/// Shorter conversations are never worth a summarization call.
+const MIN_MESSAGES: i64 = 12;
+const SUMMARY_MAX_TOKENS: u32 = 1024;
+
+const SUMMARY_PROMPT: &str = "You maintain a running summary of a conversation between a user and \
+an AI assistant. Merge the previous summary (if any) with the new messages into one updated \
+summary. Keep names, numbers, decisions, open questions and anything the user asked to remember. \
+Write in the third person, as compact notes, without preamble.";
+
+/// Serialises compaction so the background task and `compact_conversation`
+/// never summarise the same history twice.
+static COMPACTION_LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompactionConfig {
+    pub enabled: bool,
+    pub provider: ProviderKind,
+    pub model: String,
+    #[serde(default)]
+    pub base_url: Option<String>,
+    #[serde(default)]
+    pub api_key_name: Option<String>,
+    /// Unsummarized history size that triggers compaction.
+    #[serde(default = "default_trigger_tokens")]
+    pub trigger_tokens: usize,
+    /// Newest history left out of the summary.
+    #[serde(default = "default_keep_recent_tokens")]
+    pub keep_recent_tokens: usize,
+}
+
+fn default_trigger_tokens() -> usize {
+    12_000
+}
+
+fn default_keep_recent_tokens() -> usize {
+    4_000
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompactedContext {
+    /// Prompt-ready messages: the summary as a system message, then the
+    /// history after it.
+    pub messages: Vec<ChatMessage>,
+    pub summary: Option<ContextSummary>,
+    pub tokens: usize,
+    /// Messages after the summary that still had to be dropped for budget.
+    pub dropped: usize,
+    pub exact: bool,
+}
+
+fn chat_role(role: MessageRole) -> ChatRole {
+    match role {
+        MessageRole::User => ChatRole::User,
+        MessageRole::Assistant => ChatRole::Assistant,
+        MessageRole::System => ChatRole::System,
+    }
+}
+
+fn to_chat_message(message: &Message) -> ChatMessage {
+    ChatMessage {
+        role: chat_role(message.role),
+        content: message.content.clone(),
+        images: Vec::new(),
+    }
+}
+
+fn summary_message(summary: &str) -> ChatMessage {
+    ChatMessage {
+        role: ChatRole::System,
+        content: format!("Summary of the earlier conversation:\n{}", summary),
+        images: Vec::new(),
+    }
+}
+
+/// Number of leading `messages` to fold into the summary: everything except
+/// the newest `keep_recent_tokens`. Always leaves the last message alone.
+pub(crate) fn split_point(
+    encoding: &Encoding,
+    messages: &[Message],
+    keep_recent_tokens: usize,
+) -> usize {
+    let mut kept_tokens = 0;
+    let mut split = messages.len();
+    for (idx, message) in messages.iter().enumerate().rev() {
+        kept_tokens += tokens::message_tokens(encoding, &to_chat_message(message));
+        split = idx;
+        if kept_tokens >= keep_recent_tokens {
+            break;
+        }
+    }
+    split.min(messages.len().saturating_sub(1))
+}
+
+fn transcript(messages: &[Message]) -> String {
+    messages
+        .iter()
+        .map(|m| format!("{}: {}", m.role.as_str(), m.content))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+pub(crate) async fn load_config(pool: &SqlitePool) -> Result<Option<CompactionConfig>, String> {
+    crate::db::settings::get(pool, CONFIG_SETTING_KEY).await
+}
+
+/// Messages of `conversation_id` not yet covered by `summary`.
+async fn unsummarized(
+    pool: &SqlitePool,
+    conversation_id: &str,
+    summary: Option<&ContextSummary>,
+) -> Result<Vec<Message>, String> {
+    let conversation = crate::db::chat::get(pool, conversation_id)
+        .await?
+        .ok_or_else(|| format!("Conversation not found: {}", conversation_id))?;
+    let covers_until = summary.map(|s| s.covers_until).unwrap_or(i64::MIN);
+    Ok(conversation
+        .messages
+        .into_iter()
+        .filter(|m| m.timestamp > covers_until)
+        .collect())
+}
+
+/// Conversations whose unsummarized history is likely past the trigger,
+/// most recently active first. Text length is a cheap pre-filter (a token
+/// is at least ~3 characters for English); `compact` counts exactly.
+async fn candidates(pool: &SqlitePool, trigger_tokens: usize) -> Result<Vec<String>, String> {
+    sqlx::query_scalar(
+        "SELECT c.id FROM conversations c
+         JOIN messages m ON m.conversation_id = c.id
+         WHERE c.deleted_at IS NULL AND m.timestamp > COALESCE(
+             (SELECT MAX(s.covers_until) FROM conversation_summaries s
+              WHERE s.conversation_id = c.id), -1)
+         GROUP BY c.id
+         HAVING COUNT(*) >= ? AND SUM(LENGTH(m.content)) >= ?
+         ORDER BY c.updated_at DESC
+         LIMIT ?",
+    )
+    .bind(MIN_MESSAGES)
+    .bind((trigger_tokens * 3) as i64)
+    .bind(MAX_PER_SCAN as i64)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to find conversations to compact: {}", e))
+}
+
+/// Summarize the older part of `conversation_id` if it has outgrown the
+/// trigger. Returns the new summary, or `None` when nothing needed doing.
+pub(crate) async fn compact(
+    app: &AppHandle,
+    pool: &SqlitePool,
+    config: &CompactionConfig,
+    conversation_id: &str,
+    force: bool,
+) -> Result<Option<ContextSummary>, String> {
+    let _guard = COMPACTION_LOCK.lock().await;
+
+    let previous = summaries::latest(pool, conversation_id).await?;
+    let messages = unsummarized(pool, conversation_id, previous.as_ref()).await?;
+    let encoding = tokens::encoding_for(&config.model);
+
+    let chat: Vec<ChatMessage> = messages.iter().map(to_chat_message).collect();
+    if !force && tokens::prompt_tokens(&encoding, &chat) < config.trigger_tokens {
+        return Ok(None);
+    }
+    let split = split_point(&encoding, &messages, config.keep_recent_tokens);
+    if split == 0 {
+        return Ok(None);
+    }
+    let folded = &messages[..split];
+
+    let mut prompt = String::new();
+    if let Some(previous) = &previous {
+        prompt.push_str(&format!("Previous summary:\n{}\n\n", previous.summary));
+    }
+    prompt.push_str(&format!("New messages:\n{}", transcript(folded)));
+
+    let request = CompletionRequest {
+        provider: config.provider,
+        model: config.model.clone(),
+        messages: vec![ChatMessage {
+            role: ChatRole::User,
+            content: prompt,
+            images: Vec::new(),
+        }],
+        system_prompt: Some(SUMMARY_PROMPT.to_string()),
+        temperature: Some(0.2),
+        max_tokens: Some(SUMMARY_MAX_TOKENS),
+        base_url: config.base_url.clone(),
+        api_key_name: config.api_key_name.clone(),
+        retry: None,
+        profile_id: None,
+        skip_cache: false,
+    };
+    if let Some(exceeded) = crate::usage::budget_exceeded(app, request.provider).await {
+        return Err(exceeded.to_string());
+    }
+    let provider = crate::providers::connect(
+        app,
+        request.provider,
+        request.base_url.as_deref(),
+        request.api_key_name.as_deref(),
+        Retry::default(),
+    )
+    .await?;
+    let started = Instant::now();
+    let output = provider.stream_completion(&request, &|_| {}).await?;
+    crate::usage::record(
+        app,
+        "compaction",
+        None,
+        &request,
+        &output,
+        started.elapsed(),
+    )
+    .await;
+    let summary = output.text.trim();
+
+    let covers_until = folded.last().map(|m| m.timestamp).unwrap_or_default();
+    let message_count =
+        previous.as_ref().map(|s| s.message_count).unwrap_or(0) + folded.len() as i64;
+    let saved = summaries::save(
+        pool,
+        conversation_id,
+        summary,
+        covers_until,
+        message_count,
+        encoding.count(summary) as i64,
+        output.model.as_deref().or(Some(config.model.as_str())),
+    )
+    .await?;
+    Ok(Some(saved))
+}
+
+/// Assemble the prompt context for `conversation_id`: latest summary, then
+/// the newer messages, oldest dropped first if over `budget`.
+pub(crate) async fn compacted_context(
+    pool: &SqlitePool,
+    conversation_id: &str,
+    budget: usize,
+    model: &str,
+) -> Result<CompactedContext, String> {
+    let summary = summaries::latest(pool, conversation_id).await?;
+    let messages = unsummarized(pool, conversation_id, summary.as_ref()).await?;
+
+    let mut chat: Vec<ChatMessage> = Vec::with_capacity(messages.len() + 1);
+    if let Some(summary) = &summary {
+        chat.push(summary_message(&summary.summary));
+    }
+    chat.extend(messages.iter().map(to_chat_message));
+
+    let trimmed = tokens::trim_to_budget(&tokens::encoding_for(model), chat, budget)?;
+    Ok(CompactedContext {
+        messages: trimmed.messages,
+        summary,
+        tokens: trimmed.tokens,
+        dropped: trimmed.dropped,
+        exact: trimmed.exact,
+    })
+}
+
+async fn scan(app: &AppHandle) -> Result<(), String> {
+    let pool = crate::db::pool(app).await?;
+    let Some(config) = load_config(&pool).await? else {
+        return Ok(());
+    };
+    if !config.enabled || config.model.trim().is_empty() {
+        return Ok(());
+    }
+
+    // Jobs run one at a time behind COMPACTION_LOCK; a conversation already
+    // queued is not queued twice
+    for conversation_id in candidates(&pool, config.trigger_tokens).await? {
+        crate::jobs::enqueue(app, JobSpec::CompactConversation { conversation_id }).await?;
+    }
+    Ok(())
+}
+
+/// Start the periodic compaction scan. Called once from `setup`.
+pub fn start_compactor(app: AppHandle) {
+    crate::jobs::run_periodically(
+        app,
+        "Context compaction scan",
+        SCAN_INTERVAL,
+        SCAN_INTERVAL,
+        |app| async move { scan(&app).await },
+    );
+}
+
+#[tauri::command]
+pub async fn get_compaction_config(app: AppHandle) -> Result<Option<CompactionConfig>, String> {
+    let pool = crate::db::pool(&app).await?;
+    load_config(&pool).await
+}
+
+#[tauri::command]
+pub async fn set_compaction_config(app: AppHandle, config: CompactionConfig) -> Result<(), String> {
+    if config.keep_recent_tokens >= config.trigger_tokens {
+        return Err("keepRecentTokens must be smaller than triggerTokens".to_string());
+    }
+    let pool = crate::db::pool(&app).await?;
+    crate::db::settings::set(&pool, CONFIG_SETTING_KEY, &config).await
+}
+
+/// Summarize `conversation_id` now, regardless of the trigger size.
+#[tauri::command]
+pub async fn compact_conversation(
+    app: AppHandle,
+    conversation_id: String,
+) -> Result<Option<ContextSummary>, String> {
+    let pool = crate::db::pool(&app).await?;
+    let config = load_config(&pool)
+        .await?
+        .ok_or("Context compaction is not configured")?;
+    compact(&app, &pool, &config, &conversation_id, true).await
+}
+
+/// Prompt context for `conversation_id` within `budget` tokens. `model`
+/// picks the tokenizer; it defaults to the compaction model.
+#[tauri::command]
+pub async fn get_compacted_context(
+    app: AppHandle,
+    conversation_id: String,
+    budget: usize,
+    model: Option<String>,
+) -> Result<CompactedContext, String> {
+    let pool = crate::db::pool(&app).await?;
+    let model = match model {
+        Some(model) => model,
+        None => load_config(&pool)
+            .await?
+            .map(|c| c.model)
+            .unwrap_or_default(),
+    };
+    compacted_context(&pool, &conversation_id, budget, &model).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::chat::Conversation;
+
+    fn message(id: &str, role: MessageRole, content: &str, timestamp: i64) -> Message {
+        Message {
+            id: id.to_string(),
+            role,
+            content: content.to_string(),
+            timestamp,
+            attached_files: None,
+            truncated: false,
+        }
+    }
+
+    #[test]
+    fn split_keeps_recent_tokens_and_the_last_message() {
+        let encoding = tokens::encoding_for("gpt-4o");
+        let messages: Vec<Message> = (0..6)
+            .map(|i| message(&i.to_string(), MessageRole::User, "one two three four", i))
+            .collect();
+        let per_message = tokens::message_tokens(&encoding, &to_chat_message(&messages[0]));
+
+        assert_eq!(split_point(&encoding, &messages, per_message * 2), 4);
+        assert_eq!(split_point(&encoding, &messages, 0), 5);
+        assert_eq!(split_point(&encoding, &messages, usize::MAX), 0);
+    }
+
+    #[tokio::test]
+    async fn context_starts_with_summary_and_skips_covered_messages() {
+        let pool = crate::db::test_pool().await;
+        let conversation = Conversation {
+            id: "c1".into(),
+            title: "Long chat".into(),
+            created_at: 0,
+            updated_at: 0,
+            pinned: false,
+            archived_at: None,
+            deleted_at: None,
+            messages: vec![
+                message("m1", MessageRole::User, "old question", 1),
+                message("m2", MessageRole::Assistant, "old answer", 2),
+                message("m3", MessageRole::User, "new question", 3),
+            ],
+        };
+        crate::db::chat::create(&pool, conversation).await.unwrap();
+        summaries::save(&pool, "c1", "User asked an old question.", 2, 2, 6, None)
+            .await
+            .unwrap();
+
+        let context = compacted_context(&pool, "c1", 1_000, "gpt-4o")
+            .await
+            .unwrap();
+        assert_eq!(context.messages.len(), 2);
+        assert_eq!(context.messages[0].role, ChatRole::System);
+        assert!(context.messages[0]
+            .content
+            .ends_with("User asked an old question."));
+        assert_eq!(context.messages[1].content, "new question");
+        assert_eq!(context.dropped, 0);
+    }
+}
@@ -0,0 +1,3 @@
+//! Prompt context management for long conversations.
+
+pub mod compactor;
@@ -0,0 +1,204 @@
+//! Agent tool calls (migration 22), recorded by [`crate::agent_audit`] as
+//! the CLI reports them.
+
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tauri::AppHandle;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditStatus {
+    /// The call started and no result has come back yet.
+    Pending,
+    Success,
+    Error,
+    /// Refused at the permission prompt.
+    Denied,
+}
+
+impl AuditStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Success => "success",
+            Self::Error => "error",
+            Self::Denied => "denied",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "success" => Self::Success,
+            "error" => Self::Error,
+            "denied" => Self::Denied,
+            _ => Self::Pending,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub id: String,
+    pub conversation_id: String,
+    pub agent_session_id: Option<String>,
+    pub tool_name: String,
+    /// The call's input, with long values shortened.
+    pub arguments: String,
+    pub files: Vec<String>,
+    pub status: AuditStatus,
+    /// Start of the tool's output or error.
+    pub result: Option<String>,
+    pub started_at: i64,
+    pub finished_at: Option<i64>,
+}
+
+#[derive(sqlx::FromRow)]
+struct AuditRow {
+    id: String,
+    conversation_id: String,
+    agent_session_id: Option<String>,
+    tool_name: String,
+    arguments: String,
+    files: String,
+    status: String,
+    result: Option<String>,
+    started_at: i64,
+    finished_at: Option<i64>,
+}
+
+impl From<AuditRow> for AuditEntry {
+    fn from(row: AuditRow) -> Self {
+        AuditEntry {
+            files: serde_json::from_str(&row.files).unwrap_or_default(),
+            status: AuditStatus::parse(&row.status),
+            id: row.id,
+            conversation_id: row.conversation_id,
+            agent_session_id: row.agent_session_id,
+            tool_name: row.tool_name,
+            arguments: row.arguments,
+            result: row.result,
+            started_at: row.started_at,
+            finished_at: row.finished_at,
+        }
+    }
+}
+
+/// A new tool call. A repeated ID (the CLI replaying a resumed session) is
+/// ignored.
+pub(crate) struct NewAuditEntry<'a> {
+    pub id: &'a str,
+    pub conversation_id: &'a str,
+    pub agent_session_id: Option<&'a str>,
+    pub tool_name: &'a str,
+    pub arguments: &'a str,
+    pub files: &'a [String],
+}
+
+pub(crate) async fn record_call(pool: &SqlitePool, entry: NewAuditEntry<'_>) -> Result<(), String> {
+    let files = serde_json::to_string(entry.files)
+        .map_err(|e| format!("Failed to record tool call: {}", e))?;
+    sqlx::query(
+        "INSERT OR IGNORE INTO agent_audit
+             (id, conversation_id, agent_session_id, tool_name, arguments, files, started_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(entry.id)
+    .bind(entry.conversation_id)
+    .bind(entry.agent_session_id)
+    .bind(entry.tool_name)
+    .bind(entry.arguments)
+    .bind(files)
+    .bind(crate::db::now_millis())
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to record tool call: {}", e))?;
+    Ok(())
+}
+
+pub(crate) async fn record_result(
+    pool: &SqlitePool,
+    id: &str,
+    status: AuditStatus,
+    result: Option<&str>,
+) -> Result<(), String> {
+    sqlx::query(
+        "UPDATE agent_audit SET status = ?, result = ?, finished_at = ?
+         WHERE id = ? AND status = 'pending'",
+    )
+    .bind(status.as_str())
+    .bind(result)
+    .bind(crate::db::now_millis())
+    .bind(id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to record tool result: {}", e))?;
+    Ok(())
+}
+
+/// The tool calls of a conversation, oldest first.
+pub(crate) async fn list(
+    pool: &SqlitePool,
+    conversation_id: &str,
+) -> Result<Vec<AuditEntry>, String> {
+    let rows = sqlx::query_as::<_, AuditRow>(
+        "SELECT id, conversation_id, agent_session_id, tool_name, arguments, files, status,
+                result, started_at, finished_at
+         FROM agent_audit
+         WHERE conversation_id = ?
+         ORDER BY started_at, rowid",
+    )
+    .bind(conversation_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load agent audit: {}", e))?;
+    Ok(rows.into_iter().map(AuditEntry::from).collect())
+}
+
+/// Everything the agent did in `conversation_id`, in order.
+#[tauri::command]
+pub async fn get_agent_audit(
+    app: AppHandle,
+    conversation_id: String,
+) -> Result<Vec<AuditEntry>, String> {
+    let pool = crate::db::pool(&app).await?;
+    list(&pool, &conversation_id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn calls_are_recorded_once_and_resolved() {
+        let pool = crate::db::test_pool().await;
+        let files = vec!["/repo/src/main.rs".to_string()];
+        let call = || NewAuditEntry {
+            id: "toolu_1",
+            conversation_id: "conv",
+            agent_session_id: Some("cli-1"),
+            tool_name: "Edit",
+            arguments: r#"{"file_path":"/repo/src/main.rs"}"#,
+            files: &files,
+        };
+        record_call(&pool, call()).await.unwrap();
+        record_call(&pool, call()).await.unwrap();
+
+        let entries = list(&pool, "conv").await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].status, AuditStatus::Pending);
+        assert_eq!(entries[0].files, files);
+
+        record_result(&pool, "toolu_1", AuditStatus::Success, Some("ok"))
+            .await
+            .unwrap();
+        // A replayed result doesn't overwrite the first one
+        record_result(&pool, "toolu_1", AuditStatus::Error, None)
+            .await
+            .unwrap();
+        let entry = &list(&pool, "conv").await.unwrap()[0];
+        assert_eq!(entry.status, AuditStatus::Success);
+        assert_eq!(entry.result.as_deref(), Some("ok"));
+        assert!(entry.finished_at.is_some());
+    }
+}
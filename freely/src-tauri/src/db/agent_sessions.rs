@@ -0,0 +1,108 @@
+//! Claude CLI session IDs remembered per agent session (migration 21).
+//!
+//! The CLI reports its own session ID in its stream-json output; storing it
+//! here lets `claude_agent::build_command` pass `--resume` when the user
+//! comes back to a conversation after restarting Freely.
+
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tauri::AppHandle;
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentSession {
+    /// Freely's session ID, as sent in `AgentPayload::session_id`.
+    pub session_id: String,
+    /// The CLI's session ID, passed to `--resume`.
+    pub agent_session_id: String,
+    /// Where the CLI ran; it only finds the session from the same directory.
+    pub working_directory: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+pub(crate) async fn get(
+    pool: &SqlitePool,
+    session_id: &str,
+) -> Result<Option<AgentSession>, String> {
+    sqlx::query_as::<_, AgentSession>(
+        "SELECT session_id, agent_session_id, working_directory, created_at, updated_at
+         FROM agent_sessions WHERE session_id = ?",
+    )
+    .bind(session_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to load agent session: {}", e))
+}
+
+/// Remember `agent_session_id` for `session_id`, replacing any earlier one.
+pub(crate) async fn save(
+    pool: &SqlitePool,
+    session_id: &str,
+    agent_session_id: &str,
+    working_directory: Option<&str>,
+) -> Result<(), String> {
+    let now = crate::db::now_millis();
+    sqlx::query(
+        "INSERT INTO agent_sessions
+             (session_id, agent_session_id, working_directory, created_at, updated_at)
+         VALUES (?, ?, ?, ?, ?)
+         ON CONFLICT(session_id) DO UPDATE SET
+             agent_session_id = excluded.agent_session_id,
+             working_directory = excluded.working_directory,
+             updated_at = excluded.updated_at",
+    )
+    .bind(session_id)
+    .bind(agent_session_id)
+    .bind(working_directory)
+    .bind(now)
+    .bind(now)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to save agent session: {}", e))?;
+    Ok(())
+}
+
+pub(crate) async fn forget(pool: &SqlitePool, session_id: &str) -> Result<bool, String> {
+    let result = sqlx::query("DELETE FROM agent_sessions WHERE session_id = ?")
+        .bind(session_id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to forget agent session: {}", e))?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[tauri::command]
+pub async fn get_agent_session(
+    app: AppHandle,
+    session_id: String,
+) -> Result<Option<AgentSession>, String> {
+    let pool = crate::db::pool(&app).await?;
+    get(&pool, &session_id).await
+}
+
+/// Drop the remembered CLI session, so the next run starts a fresh one.
+#[tauri::command]
+pub async fn forget_agent_session(app: AppHandle, session_id: String) -> Result<bool, String> {
+    let pool = crate::db::pool(&app).await?;
+    forget(&pool, &session_id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn the_latest_cli_session_is_kept() {
+        let pool = crate::db::test_pool().await;
+        save(&pool, "s1", "cli-a", Some("/work")).await.unwrap();
+        save(&pool, "s1", "cli-b", Some("/work")).await.unwrap();
+
+        let session = get(&pool, "s1").await.unwrap().unwrap();
+        assert_eq!(session.agent_session_id, "cli-b");
+        assert_eq!(session.working_directory.as_deref(), Some("/work"));
+
+        assert!(forget(&pool, "s1").await.unwrap());
+        assert!(get(&pool, "s1").await.unwrap().is_none());
+    }
+}
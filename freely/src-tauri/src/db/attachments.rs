@@ -0,0 +1,113 @@
+//! Attachment index (migration 13). The files themselves are managed by
+//! [`crate::attachments`].
+
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    pub id: String,
+    pub message_id: Option<String>,
+    /// Hex SHA-256 of the contents, which also names the file on disk.
+    pub hash: String,
+    pub mime_type: String,
+    pub size: i64,
+    pub file_name: Option<String>,
+    pub created_at: i64,
+}
+
+const COLUMNS: &str = "id, message_id, hash, mime_type, size, file_name, created_at";
+
+pub(crate) async fn insert(pool: &SqlitePool, attachment: &Attachment) -> Result<(), String> {
+    sqlx::query(
+        "INSERT INTO attachments (id, message_id, hash, mime_type, size, file_name, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&attachment.id)
+    .bind(&attachment.message_id)
+    .bind(&attachment.hash)
+    .bind(&attachment.mime_type)
+    .bind(attachment.size)
+    .bind(&attachment.file_name)
+    .bind(attachment.created_at)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to save attachment: {}", e))?;
+    Ok(())
+}
+
+pub(crate) async fn get(pool: &SqlitePool, id: &str) -> Result<Option<Attachment>, String> {
+    sqlx::query_as::<_, Attachment>(&format!("SELECT {} FROM attachments WHERE id = ?", COLUMNS))
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Failed to load attachment: {}", e))
+}
+
+pub(crate) async fn list_for_message(
+    pool: &SqlitePool,
+    message_id: &str,
+) -> Result<Vec<Attachment>, String> {
+    sqlx::query_as::<_, Attachment>(&format!(
+        "SELECT {} FROM attachments WHERE message_id = ? ORDER BY created_at ASC",
+        COLUMNS
+    ))
+    .bind(message_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load attachments: {}", e))
+}
+
+/// Point attachments added ahead of their message at it once it is saved.
+pub(crate) async fn link(
+    pool: &SqlitePool,
+    message_id: &str,
+    attachment_ids: &[String],
+) -> Result<u64, String> {
+    let mut linked = 0;
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to link attachments: {}", e))?;
+    for id in attachment_ids {
+        linked += sqlx::query("UPDATE attachments SET message_id = ? WHERE id = ?")
+            .bind(message_id)
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to link attachments: {}", e))?
+            .rows_affected();
+    }
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to link attachments: {}", e))?;
+    Ok(linked)
+}
+
+/// Delete rows created before `cutoff` that belong to no existing message,
+/// returning them.
+pub(crate) async fn delete_orphans(
+    pool: &SqlitePool,
+    cutoff: i64,
+) -> Result<Vec<Attachment>, String> {
+    sqlx::query_as::<_, Attachment>(&format!(
+        "DELETE FROM attachments
+         WHERE created_at < ?
+           AND (message_id IS NULL OR message_id NOT IN (SELECT id FROM messages))
+         RETURNING {}",
+        COLUMNS
+    ))
+    .bind(cutoff)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to delete orphaned attachments: {}", e))
+}
+
+/// Every content hash still referenced by a row.
+pub(crate) async fn hashes(pool: &SqlitePool) -> Result<Vec<String>, String> {
+    sqlx::query_scalar("SELECT DISTINCT hash FROM attachments")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to load attachment hashes: {}", e))
+}
@@ -0,0 +1,603 @@
+//! Copies of the chat database, and restoring from one.
+//!
+//! Both directions go through SQLite's online backup API on a connection
+//! from the live pool, so they're safe while the app is reading and writing:
+//! a backup is a consistent snapshot, and a restore replaces the pages of the
+//! open database in one transaction instead of moving files underneath the
+//! SQL plugin. A restore only starts once the file passes
+//! `PRAGMA integrity_check`, and the current database is copied to the
+//! backups directory first.
+//!
+//! A background task also queues a job to snapshot the database into that
+//! directory once a day, keeping the newest few as configured by the
+//! `backups` setting.
+//!
+//! Backups of an encrypted database are encrypted with the same key, as
+//! SQLCipher can't copy pages between databases keyed differently; for the
+//! same reason a plaintext backup can't be restored into an encrypted one.
+
+use super::cipher::{self, DatabaseKey};
+use libsqlite3_sys as ffi;
+use serde::{Deserialize, Serialize};
+use sqlx::{Connection, SqliteConnection, SqlitePool};
+use std::ffi::{CStr, CString};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::warn;
+
+/// Settings key for [`BackupSchedule`].
+const BACKUP_SETTING_KEY: &str = "backups";
+const SCHEDULED_PREFIX: &str = "freely-";
+const PRE_RESTORE_PREFIX: &str = "pre-restore-";
+const BACKUP_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const STARTUP_DELAY: Duration = Duration::from_secs(60);
+const MAX_KEEP: u32 = 365;
+
+/// How many daily backups to keep, stored under the `backups` setting.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct BackupSchedule {
+    pub enabled: bool,
+    pub keep: u32,
+}
+
+impl Default for BackupSchedule {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            keep: 7,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BackupKind {
+    Scheduled,
+    /// Taken automatically before a restore.
+    PreRestore,
+    Other,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupInfo {
+    /// File name within the backups directory.
+    pub id: String,
+    pub path: String,
+    pub kind: BackupKind,
+    pub size: u64,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreResult {
+    /// Latest migration recorded in the restored database.
+    pub schema_version: i64,
+    /// The backup came from an older version; its remaining migrations run
+    /// on the next launch.
+    pub needs_restart: bool,
+    /// Copy of the database as it was before the restore.
+    pub previous_path: String,
+}
+
+fn data_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_local_data_dir()
+        .map_err(|e| format!("Failed to resolve data directory: {}", e))
+}
+
+/// The file behind [`super::DB_URL`] in the app's local data directory.
+pub(crate) fn database_path_in(data_dir: &Path) -> PathBuf {
+    data_dir.join(super::DB_URL.trim_start_matches("sqlite:"))
+}
+
+pub(crate) fn backups_dir_in(data_dir: &Path) -> PathBuf {
+    data_dir.join("backups")
+}
+
+pub(crate) fn database_path(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(database_path_in(&data_dir(app)?))
+}
+
+pub(crate) fn backups_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(backups_dir_in(&data_dir(app)?))
+}
+
+/// A connection opened directly through the C API, closed on drop.
+struct RawDb(*mut ffi::sqlite3);
+
+impl RawDb {
+    /// Open `path`, applying `key` before anything reads the file.
+    fn open(path: &Path, flags: i32, key: Option<&DatabaseKey>) -> Result<Self, String> {
+        let name = CString::new(path.to_string_lossy().as_bytes())
+            .map_err(|_| format!("Invalid database path: {}", path.display()))?;
+        let mut db = std::ptr::null_mut();
+        // SAFETY: `name` is NUL-terminated and `db` is closed by `Drop` even
+        // when opening fails, as SQLite requires.
+        let rc = unsafe { ffi::sqlite3_open_v2(name.as_ptr(), &mut db, flags, std::ptr::null()) };
+        let raw = RawDb(db);
+        if rc != ffi::SQLITE_OK {
+            return Err(format!(
+                "Failed to open {}: {}",
+                path.display(),
+                error_message(raw.0)
+            ));
+        }
+        if let Some(key) = key {
+            raw.exec(&format!("PRAGMA key = {}", key.pragma()))?;
+        }
+        Ok(raw)
+    }
+
+    fn exec(&self, sql: &str) -> Result<(), String> {
+        let sql = CString::new(sql).map_err(|_| "Invalid SQL".to_string())?;
+        // SAFETY: the handle is open and `sql` is NUL-terminated.
+        let rc = unsafe {
+            ffi::sqlite3_exec(
+                self.0,
+                sql.as_ptr(),
+                None,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            )
+        };
+        if rc != ffi::SQLITE_OK {
+            return Err(error_message(self.0));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for RawDb {
+    fn drop(&mut self) {
+        // SAFETY: the handle came from `sqlite3_open_v2` and is closed once.
+        unsafe {
+            ffi::sqlite3_close(self.0);
+        }
+    }
+}
+
+fn error_message(db: *mut ffi::sqlite3) -> String {
+    if db.is_null() {
+        return "out of memory".to_string();
+    }
+    // SAFETY: `db` is a valid handle; the message is copied before any other
+    // call on it.
+    unsafe { CStr::from_ptr(ffi::sqlite3_errmsg(db)) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Copy every page of `source` into `dest` in a single step.
+///
+/// # Safety
+/// Both handles must be open and not used by another thread for the
+/// duration of the call.
+unsafe fn copy_database(dest: *mut ffi::sqlite3, source: *mut ffi::sqlite3) -> Result<(), String> {
+    let main = c"main";
+    let backup = ffi::sqlite3_backup_init(dest, main.as_ptr(), source, main.as_ptr());
+    if backup.is_null() {
+        return Err(error_message(dest));
+    }
+    let step = ffi::sqlite3_backup_step(backup, -1);
+    let finish = ffi::sqlite3_backup_finish(backup);
+    match step {
+        ffi::SQLITE_DONE if finish == ffi::SQLITE_OK => Ok(()),
+        ffi::SQLITE_BUSY | ffi::SQLITE_LOCKED => {
+            Err("Database is busy, try again in a moment".to_string())
+        }
+        _ => Err(error_message(dest)),
+    }
+}
+
+/// Snapshot the database behind `pool` into a new file at `dest`.
+pub(crate) async fn backup_to(pool: &SqlitePool, dest: &Path) -> Result<(), String> {
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create backup directory: {}", e))?;
+    }
+    // Written next to the destination first so a failed backup never
+    // leaves a truncated file under the requested name
+    let partial = dest.with_extension("partial");
+    let _ = std::fs::remove_file(&partial);
+
+    let mut conn = pool
+        .acquire()
+        .await
+        .map_err(|e| format!("Failed to acquire database connection: {}", e))?;
+    let result = {
+        let mut handle = conn
+            .lock_handle()
+            .await
+            .map_err(|e| format!("Failed to lock database connection: {}", e))?;
+        RawDb::open(
+            &partial,
+            ffi::SQLITE_OPEN_READWRITE | ffi::SQLITE_OPEN_CREATE,
+            cipher::active_key().as_ref(),
+        )
+        .and_then(
+            // SAFETY: the live handle is locked for this block and `target`
+            // is private to it.
+            |target| unsafe { copy_database(target.0, handle.as_raw_handle().as_ptr()) },
+        )
+    };
+    drop(conn);
+
+    match result {
+        Ok(()) => {
+            std::fs::rename(&partial, dest).map_err(|e| format!("Failed to write backup: {}", e))
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(&partial);
+            Err(format!("Failed to back up database: {}", e))
+        }
+    }
+}
+
+/// Check that `path` is an intact Freely database this version can open,
+/// returning its latest migration.
+pub(crate) async fn validate_backup(path: &Path) -> Result<i64, String> {
+    if !path.is_file() {
+        return Err(format!("Backup not found: {}", path.display()));
+    }
+    let options = cipher::connect_options(path).read_only(true);
+    let mut conn = SqliteConnection::connect_with(&options)
+        .await
+        .map_err(|e| format!("Failed to open backup: {}", e))?;
+
+    let problems: Vec<String> = sqlx::query_scalar("PRAGMA integrity_check")
+        .fetch_all(&mut conn)
+        .await
+        .map_err(|e| format!("Not a valid database: {}", e))?;
+    if problems != ["ok"] {
+        return Err(format!(
+            "Backup is corrupted: {}",
+            problems.into_iter().take(3).collect::<Vec<_>>().join("; ")
+        ));
+    }
+
+    let version: Option<i64> = sqlx::query_scalar("SELECT MAX(version) FROM _sqlx_migrations")
+        .fetch_one(&mut conn)
+        .await
+        .map_err(|_| "Not a Freely database backup".to_string())?;
+    let version = version.ok_or("Not a Freely database backup")?;
+    if version > super::schema::latest_version() {
+        return Err(format!(
+            "Backup is from a newer version of Freely (schema {})",
+            version
+        ));
+    }
+    let _ = conn.close().await;
+    Ok(version)
+}
+
+/// Replace the contents of the database behind `pool` with `source`, which
+/// must already have passed [`validate_backup`].
+pub(crate) async fn restore_from(pool: &SqlitePool, source: &Path) -> Result<(), String> {
+    let key = cipher::is_encrypted(source)
+        .then(cipher::active_key)
+        .flatten();
+    let mut conn = pool
+        .acquire()
+        .await
+        .map_err(|e| format!("Failed to acquire database connection: {}", e))?;
+    let result = {
+        let mut handle = conn
+            .lock_handle()
+            .await
+            .map_err(|e| format!("Failed to lock database connection: {}", e))?;
+        RawDb::open(source, ffi::SQLITE_OPEN_READONLY, key.as_ref()).and_then(
+            // SAFETY: as in `backup_to`, with the roles swapped.
+            |backup| unsafe { copy_database(handle.as_raw_handle().as_ptr(), backup.0) },
+        )
+    };
+    result.map_err(|e| format!("Failed to restore database: {}", e))
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// Write a consistent copy of the database to `dest_path`.
+#[tauri::command]
+pub async fn backup_database(app: AppHandle, dest_path: String) -> Result<(), String> {
+    let dest = PathBuf::from(dest_path.trim());
+    if dest.as_os_str().is_empty() {
+        return Err("Backup path must not be empty".to_string());
+    }
+    if dest == database_path(&app)? {
+        return Err("Choose a location other than the live database".to_string());
+    }
+    let pool = super::pool(&app).await?;
+    backup_to(&pool, &dest).await
+}
+
+/// Replace the database with the backup at `src_path` after checking it,
+/// keeping a copy of the current database in the backups directory.
+/// Emits `database-restored` so windows reload their data.
+#[tauri::command]
+pub async fn restore_database(app: AppHandle, src_path: String) -> Result<RestoreResult, String> {
+    restore(&app, Path::new(src_path.trim())).await
+}
+
+/// Every backup in the app's backups directory, newest first.
+#[tauri::command]
+pub async fn list_backups(app: AppHandle) -> Result<Vec<BackupInfo>, String> {
+    list_in(&backups_dir(&app)?)
+}
+
+/// Restore the backup `id` from [`list_backups`], as [`restore_database`] does.
+#[tauri::command]
+pub async fn restore_backup(app: AppHandle, id: String) -> Result<RestoreResult, String> {
+    if Path::new(&id).file_name() != Some(id.as_ref()) || !id.ends_with(".db") {
+        return Err(format!("Invalid backup id: {}", id));
+    }
+    restore(&app, &backups_dir(&app)?.join(&id)).await
+}
+
+async fn restore(app: &AppHandle, source: &Path) -> Result<RestoreResult, String> {
+    let schema_version = validate_backup(source).await?;
+    if schema_version < super::schema::latest_version() {
+        let dry_run = super::schema::dry_run_file(source).await?;
+        if !dry_run.ok {
+            return Err(format!(
+                "Backup can't be upgraded to this version of Freely: {}",
+                dry_run.error.unwrap_or_default()
+            ));
+        }
+    }
+
+    let pool = super::pool(app).await?;
+    let previous =
+        backups_dir(app)?.join(format!("{}{}.db", PRE_RESTORE_PREFIX, super::now_millis()));
+    backup_to(&pool, &previous).await?;
+    restore_from(&pool, source).await?;
+
+    // Cached vectors belong to the old contents
+    crate::embeddings::invalidate_index();
+    crate::knowledge::invalidate_index();
+
+    let result = RestoreResult {
+        schema_version,
+        needs_restart: schema_version < super::schema::latest_version(),
+        previous_path: previous.to_string_lossy().into_owned(),
+    };
+    if let Err(e) = app.emit("database-restored", &result) {
+        warn!("Failed to emit database restore: {}", e);
+    }
+    Ok(result)
+}
+
+// ============================================================================
+// Scheduled backups
+// ============================================================================
+
+fn backup_kind(file_name: &str) -> BackupKind {
+    if file_name.starts_with(SCHEDULED_PREFIX) {
+        BackupKind::Scheduled
+    } else if file_name.starts_with(PRE_RESTORE_PREFIX) {
+        BackupKind::PreRestore
+    } else {
+        BackupKind::Other
+    }
+}
+
+/// `.db` files in `dir`, newest first. A missing directory has none.
+fn list_in(dir: &Path) -> Result<Vec<BackupInfo>, String> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read backups directory: {}", e)),
+    };
+
+    let mut backups = Vec::new();
+    for entry in entries.flatten() {
+        let id = entry.file_name().to_string_lossy().into_owned();
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if !metadata.is_file() || !id.ends_with(".db") {
+            continue;
+        }
+        let created_at = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or(0);
+        backups.push(BackupInfo {
+            kind: backup_kind(&id),
+            path: entry.path().to_string_lossy().into_owned(),
+            size: metadata.len(),
+            created_at,
+            id,
+        });
+    }
+    backups.sort_by(|a, b| (b.created_at, &b.id).cmp(&(a.created_at, &a.id)));
+    Ok(backups)
+}
+
+/// Delete scheduled backups beyond the newest `keep`. Others are left for
+/// the user to manage.
+fn prune_in(dir: &Path, keep: usize) -> Result<usize, String> {
+    let stale: Vec<_> = list_in(dir)?
+        .into_iter()
+        .filter(|b| b.kind == BackupKind::Scheduled)
+        .skip(keep)
+        .collect();
+    for backup in &stale {
+        std::fs::remove_file(&backup.path)
+            .map_err(|e| format!("Failed to delete backup {}: {}", backup.id, e))?;
+    }
+    Ok(stale.len())
+}
+
+fn is_due(backups: &[BackupInfo], now: i64) -> bool {
+    backups
+        .iter()
+        .find(|b| b.kind == BackupKind::Scheduled)
+        .is_none_or(|latest| now - latest.created_at >= BACKUP_INTERVAL.as_millis() as i64)
+}
+
+async fn load_schedule(pool: &SqlitePool) -> Result<BackupSchedule, String> {
+    Ok(crate::settings::get_setting(pool, BACKUP_SETTING_KEY)
+        .await?
+        .unwrap_or_default())
+}
+
+/// Snapshot the database into the backups directory and prune old
+/// snapshots. Runs as the `backup` job. Returns the new file's path.
+pub(crate) async fn take_scheduled_backup(app: &AppHandle) -> Result<PathBuf, String> {
+    let pool = super::pool(app).await?;
+    let schedule = load_schedule(&pool).await?;
+    let dir = backups_dir(app)?;
+
+    let name = format!(
+        "{}{}.db",
+        SCHEDULED_PREFIX,
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    );
+    let path = dir.join(name);
+    backup_to(&pool, &path).await?;
+    prune_in(&dir, schedule.keep.clamp(1, MAX_KEEP) as usize)?;
+    Ok(path)
+}
+
+async fn run_scheduled(app: &AppHandle) -> Result<(), String> {
+    let pool = super::pool(app).await?;
+    if !load_schedule(&pool).await?.enabled {
+        return Ok(());
+    }
+    if !is_due(&list_in(&backups_dir(app)?)?, super::now_millis()) {
+        return Ok(());
+    }
+    crate::jobs::enqueue(app, crate::jobs::JobSpec::Backup).await?;
+    Ok(())
+}
+
+/// Queue a daily snapshot into the backups directory. Called once from
+/// `setup`; checks hourly so a laptop that sleeps through the night still
+/// gets its backup soon after waking.
+pub fn start_backup_scheduler(app: AppHandle) {
+    crate::jobs::run_periodically(
+        app,
+        "Scheduled backup",
+        CHECK_INTERVAL,
+        STARTUP_DELAY,
+        |app| async move { run_scheduled(&app).await },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    async fn conversation_titles(pool: &SqlitePool) -> Vec<String> {
+        sqlx::query_scalar("SELECT title FROM conversations ORDER BY title")
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    async fn add_conversation(pool: &SqlitePool, title: &str) {
+        sqlx::query(
+            "INSERT INTO conversations (id, title, created_at, updated_at) VALUES (?, ?, 0, 0)",
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(title)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    async fn record_migrations(pool: &SqlitePool) {
+        // The SQL plugin's migrator keeps this table; test pools skip it
+        sqlx::raw_sql(
+            "CREATE TABLE _sqlx_migrations (version BIGINT PRIMARY KEY, description TEXT);
+             INSERT INTO _sqlx_migrations VALUES (1, 'create_system_prompts_table');",
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn backup_round_trips_through_restore() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join("nested").join("freely-backup.db");
+
+        let pool = crate::db::test_pool().await;
+        record_migrations(&pool).await;
+        add_conversation(&pool, "kept").await;
+        backup_to(&pool, &path).await.unwrap();
+        assert!(!path.with_extension("partial").exists());
+        assert_eq!(validate_backup(&path).await.unwrap(), 1);
+
+        sqlx::query("DELETE FROM conversations")
+            .execute(&pool)
+            .await
+            .unwrap();
+        add_conversation(&pool, "after backup").await;
+        restore_from(&pool, &path).await.unwrap();
+        assert_eq!(conversation_titles(&pool).await, ["kept"]);
+    }
+
+    #[tokio::test]
+    async fn invalid_backups_are_rejected() {
+        let tmp = TempDir::new().unwrap();
+        let garbage = tmp.path().join("garbage.db");
+        std::fs::write(&garbage, b"definitely not sqlite").unwrap();
+        assert!(validate_backup(&garbage).await.is_err());
+        assert!(validate_backup(&tmp.path().join("missing.db"))
+            .await
+            .is_err());
+
+        // A database without migration history isn't one of ours
+        let foreign = tmp.path().join("foreign.db");
+        backup_to(&crate::db::test_pool().await, &foreign)
+            .await
+            .unwrap();
+        assert!(validate_backup(&foreign).await.is_err());
+    }
+
+    #[test]
+    fn pruning_keeps_the_newest_scheduled_backups() {
+        let tmp = TempDir::new().unwrap();
+        let day = std::time::Duration::from_secs(24 * 60 * 60);
+        let now = std::time::SystemTime::now();
+        for (i, name) in [
+            "freely-20260101-090000.db",
+            "freely-20260102-090000.db",
+            "freely-20260103-090000.db",
+            "pre-restore-1.db",
+            "notes.txt",
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let file = std::fs::File::create(tmp.path().join(name)).unwrap();
+            file.set_modified(now - day * (5 - i as u32)).unwrap();
+        }
+
+        let backups = list_in(tmp.path()).unwrap();
+        assert_eq!(backups.len(), 4);
+        assert_eq!(backups[0].kind, BackupKind::PreRestore);
+        assert!(is_due(&backups, crate::db::now_millis()));
+        assert!(!is_due(&backups, backups[1].created_at + 1000));
+
+        assert_eq!(prune_in(tmp.path(), 1).unwrap(), 2);
+        let ids: Vec<_> = list_in(tmp.path())
+            .unwrap()
+            .into_iter()
+            .map(|b| b.id)
+            .collect();
+        assert_eq!(ids, ["pre-restore-1.db", "freely-20260103-090000.db"]);
+        assert!(tmp.path().join("notes.txt").exists());
+        assert!(list_in(&tmp.path().join("missing")).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,738 @@
+//! Native chat history commands.
+//!
+//! Typed CRUD over the `conversations` and `messages` tables (migration 2) so
+//! the frontend no longer builds SQL strings itself. Writes that touch more
+//! than one row run inside a transaction, and every payload is validated here
+//! before it reaches the database.
+//!
+//! Deleting a conversation only moves it to the trash (migration 17): it is
+//! hidden from lists and search until restored, and removed for good by
+//! `purge_deleted` or the daily purge once it has been in the trash longer
+//! than the `trash` setting's `retentionDays`.
+
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::time::Duration;
+use tauri::AppHandle;
+
+const MAX_TITLE_LEN: usize = 500;
+pub(crate) const DEFAULT_PAGE_SIZE: u32 = 100;
+/// Settings key for [`TrashConfig`].
+const TRASH_SETTING_KEY: &str = "trash";
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+const PURGE_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+const PURGE_STARTUP_DELAY: Duration = Duration::from_secs(120);
+
+/// How long deleted conversations stay recoverable, stored under the `trash`
+/// setting. `0` keeps them until purged by hand.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub(crate) struct TrashConfig {
+    pub retention_days: u32,
+}
+
+impl Default for TrashConfig {
+    fn default() -> Self {
+        Self { retention_days: 30 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageRole {
+    User,
+    Assistant,
+    System,
+}
+
+impl MessageRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::Assistant => "assistant",
+            Self::System => "system",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "user" => Ok(Self::User),
+            "assistant" => Ok(Self::Assistant),
+            "system" => Ok(Self::System),
+            other => Err(format!("Unknown message role: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Message {
+    pub id: String,
+    pub role: MessageRole,
+    pub content: String,
+    pub timestamp: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attached_files: Option<serde_json::Value>,
+    /// A reply stopped with `abort_completion` before it finished.
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Conversation {
+    pub id: String,
+    pub title: String,
+    #[serde(default)]
+    pub created_at: i64,
+    #[serde(default)]
+    pub updated_at: i64,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub archived_at: Option<i64>,
+    /// Set while the conversation is in the trash.
+    #[serde(default)]
+    pub deleted_at: Option<i64>,
+    #[serde(default)]
+    pub messages: Vec<Message>,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversationSummary {
+    pub id: String,
+    pub title: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub message_count: i64,
+    pub pinned: bool,
+    pub archived_at: Option<i64>,
+    pub deleted_at: Option<i64>,
+}
+
+#[derive(sqlx::FromRow)]
+pub(crate) struct MessageRow {
+    id: String,
+    role: String,
+    content: String,
+    timestamp: i64,
+    attached_files: Option<String>,
+    truncated: bool,
+}
+
+impl TryFrom<MessageRow> for Message {
+    type Error = String;
+
+    fn try_from(row: MessageRow) -> Result<Self, Self::Error> {
+        Ok(Message {
+            id: row.id,
+            role: MessageRole::parse(&row.role)?,
+            content: row.content,
+            timestamp: row.timestamp,
+            // A malformed JSON column should not make the whole conversation unreadable
+            attached_files: row
+                .attached_files
+                .and_then(|raw| serde_json::from_str(&raw).ok()),
+            truncated: row.truncated,
+        })
+    }
+}
+
+// ============================================================================
+// Validation
+// ============================================================================
+
+fn validate_id(kind: &str, id: &str) -> Result<(), String> {
+    if id.trim().is_empty() {
+        return Err(format!("Invalid {}: id must not be empty", kind));
+    }
+    Ok(())
+}
+
+fn validate_title(title: &str) -> Result<(), String> {
+    if title.trim().is_empty() {
+        return Err("Invalid conversation: title must not be empty".to_string());
+    }
+    if title.chars().count() > MAX_TITLE_LEN {
+        return Err(format!(
+            "Invalid conversation: title exceeds {} characters",
+            MAX_TITLE_LEN
+        ));
+    }
+    Ok(())
+}
+
+fn validate_message(message: &Message) -> Result<(), String> {
+    validate_id("message", &message.id)?;
+    if message.timestamp < 0 {
+        return Err(format!(
+            "Invalid message {}: timestamp must not be negative",
+            message.id
+        ));
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_conversation(conversation: &Conversation) -> Result<(), String> {
+    validate_id("conversation", &conversation.id)?;
+    validate_title(&conversation.title)?;
+    for message in &conversation.messages {
+        validate_message(message)?;
+    }
+    Ok(())
+}
+
+// ============================================================================
+// Queries
+// ============================================================================
+
+/// Selects [`ConversationSummary`] columns from `conversations c`, for
+/// queries to add their own filters and ordering to. Queries for live
+/// conversations must filter out `c.deleted_at IS NOT NULL` themselves.
+pub(crate) const SUMMARY_SELECT: &str = "SELECT c.id, c.title, c.created_at, c.updated_at,
+            (SELECT COUNT(*) FROM messages m WHERE m.conversation_id = c.id) AS message_count,
+            c.pinned, c.archived_at, c.deleted_at
+     FROM conversations c";
+
+/// Pinned conversations first, then the most recently updated.
+pub(crate) const SUMMARY_ORDER: &str = "ORDER BY c.pinned DESC, c.updated_at DESC";
+
+pub(crate) async fn insert_message(
+    conn: &mut sqlx::SqliteConnection,
+    conversation_id: &str,
+    message: &Message,
+) -> Result<(), sqlx::Error> {
+    let attached_files = message
+        .attached_files
+        .as_ref()
+        .map(|files| files.to_string());
+
+    sqlx::query(
+        "INSERT INTO messages
+             (id, conversation_id, role, content, timestamp, attached_files, truncated)
+         VALUES (?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&message.id)
+    .bind(conversation_id)
+    .bind(message.role.as_str())
+    .bind(&message.content)
+    .bind(message.timestamp)
+    .bind(attached_files)
+    .bind(message.truncated)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+pub(crate) async fn create(
+    pool: &SqlitePool,
+    mut conversation: Conversation,
+) -> Result<Conversation, String> {
+    validate_conversation(&conversation)?;
+
+    let now = super::now_millis();
+    if conversation.created_at <= 0 {
+        conversation.created_at = now;
+    }
+    if conversation.updated_at <= 0 {
+        conversation.updated_at = conversation.created_at;
+    }
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    sqlx::query(
+        "INSERT INTO conversations (id, title, created_at, updated_at, pinned, archived_at)
+         VALUES (?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&conversation.id)
+    .bind(&conversation.title)
+    .bind(conversation.created_at)
+    .bind(conversation.updated_at)
+    .bind(conversation.pinned)
+    .bind(conversation.archived_at)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to create conversation: {}", e))?;
+
+    for message in &conversation.messages {
+        insert_message(&mut tx, &conversation.id, message)
+            .await
+            .map_err(|e| format!("Failed to insert message {}: {}", message.id, e))?;
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit conversation: {}", e))?;
+
+    Ok(conversation)
+}
+
+pub(crate) async fn append(
+    pool: &SqlitePool,
+    conversation_id: &str,
+    message: &Message,
+) -> Result<(), String> {
+    validate_id("conversation", conversation_id)?;
+    validate_message(message)?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let exists: Option<(String,)> = sqlx::query_as("SELECT id FROM conversations WHERE id = ?")
+        .bind(conversation_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to look up conversation: {}", e))?;
+    if exists.is_none() {
+        return Err(format!("Conversation not found: {}", conversation_id));
+    }
+
+    insert_message(&mut tx, conversation_id, message)
+        .await
+        .map_err(|e| format!("Failed to append message: {}", e))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit message: {}", e))
+}
+
+/// Conversations outside the trash: the archived ones with `archived`, the
+/// rest without.
+pub(crate) async fn list(
+    pool: &SqlitePool,
+    archived: bool,
+    limit: u32,
+    offset: u32,
+) -> Result<Vec<ConversationSummary>, String> {
+    sqlx::query_as::<_, ConversationSummary>(&format!(
+        "{} WHERE c.deleted_at IS NULL AND (c.archived_at IS NOT NULL) = ? {} LIMIT ? OFFSET ?",
+        SUMMARY_SELECT, SUMMARY_ORDER
+    ))
+    .bind(archived)
+    .bind(limit as i64)
+    .bind(offset as i64)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to list conversations: {}", e))
+}
+
+pub(crate) async fn get(pool: &SqlitePool, id: &str) -> Result<Option<Conversation>, String> {
+    type Header = (String, String, i64, i64, bool, Option<i64>, Option<i64>);
+    let header: Option<Header> = sqlx::query_as(
+        "SELECT id, title, created_at, updated_at, pinned, archived_at, deleted_at
+         FROM conversations WHERE id = ?",
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to load conversation: {}", e))?;
+
+    let Some((id, title, created_at, updated_at, pinned, archived_at, deleted_at)) = header else {
+        return Ok(None);
+    };
+
+    let rows = sqlx::query_as::<_, MessageRow>(
+        "SELECT id, role, content, timestamp, attached_files, truncated
+         FROM messages WHERE conversation_id = ? ORDER BY timestamp ASC",
+    )
+    .bind(&id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load messages: {}", e))?;
+
+    let messages = rows
+        .into_iter()
+        .map(Message::try_from)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Some(Conversation {
+        id,
+        title,
+        created_at,
+        updated_at,
+        pinned,
+        archived_at,
+        deleted_at,
+        messages,
+    }))
+}
+
+/// Conversations in the trash, most recently deleted first.
+pub(crate) async fn list_deleted(
+    pool: &SqlitePool,
+    limit: u32,
+    offset: u32,
+) -> Result<Vec<ConversationSummary>, String> {
+    sqlx::query_as::<_, ConversationSummary>(&format!(
+        "{} WHERE c.deleted_at IS NOT NULL ORDER BY c.deleted_at DESC LIMIT ? OFFSET ?",
+        SUMMARY_SELECT
+    ))
+    .bind(limit as i64)
+    .bind(offset as i64)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to list deleted conversations: {}", e))
+}
+
+/// Update one lifecycle column. `updated_at` is left alone so pinning or
+/// archiving doesn't reorder the list.
+async fn set_state(
+    pool: &SqlitePool,
+    id: &str,
+    assignment: &str,
+    value: Option<i64>,
+) -> Result<bool, String> {
+    let result = sqlx::query(&format!(
+        "UPDATE conversations SET {} WHERE id = ?",
+        assignment
+    ))
+    .bind(value)
+    .bind(id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to update conversation: {}", e))?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub(crate) async fn set_pinned(pool: &SqlitePool, id: &str, pinned: bool) -> Result<bool, String> {
+    set_state(pool, id, "pinned = ?", Some(pinned as i64)).await
+}
+
+pub(crate) async fn archive(pool: &SqlitePool, id: &str) -> Result<bool, String> {
+    set_state(
+        pool,
+        id,
+        "archived_at = COALESCE(archived_at, ?)",
+        Some(super::now_millis()),
+    )
+    .await
+}
+
+/// Move a conversation to the trash. Returns `false` if it did not exist or
+/// is already there.
+pub(crate) async fn delete(pool: &SqlitePool, id: &str) -> Result<bool, String> {
+    let result =
+        sqlx::query("UPDATE conversations SET deleted_at = ? WHERE id = ? AND deleted_at IS NULL")
+            .bind(super::now_millis())
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(|e| format!("Failed to delete conversation: {}", e))?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Bring a conversation back from the trash or the archive.
+pub(crate) async fn restore(pool: &SqlitePool, id: &str) -> Result<bool, String> {
+    set_state(pool, id, "deleted_at = ?, archived_at = NULL", None).await
+}
+
+/// Permanently delete one conversation, in the trash or not.
+pub(crate) async fn purge(pool: &SqlitePool, id: &str) -> Result<bool, String> {
+    // Messages are removed by the ON DELETE CASCADE foreign key
+    let result = sqlx::query("DELETE FROM conversations WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to delete conversation: {}", e))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Permanently delete conversations that went into the trash before `cutoff`.
+pub(crate) async fn purge_deleted_before(pool: &SqlitePool, cutoff: i64) -> Result<u64, String> {
+    let result =
+        sqlx::query("DELETE FROM conversations WHERE deleted_at IS NOT NULL AND deleted_at < ?")
+            .bind(cutoff)
+            .execute(pool)
+            .await
+            .map_err(|e| format!("Failed to purge deleted conversations: {}", e))?;
+    Ok(result.rows_affected())
+}
+
+async fn run_scheduled_purge(app: &AppHandle) -> Result<(), String> {
+    let pool = super::pool(app).await?;
+    let config: TrashConfig = crate::settings::get_setting(&pool, TRASH_SETTING_KEY)
+        .await?
+        .unwrap_or_default();
+    if config.retention_days == 0 {
+        return Ok(());
+    }
+    let cutoff = super::now_millis() - config.retention_days as i64 * DAY_MS;
+    purge_deleted_before(&pool, cutoff).await?;
+    Ok(())
+}
+
+/// Empty the trash of conversations older than the retention period, shortly
+/// after startup and then daily. Called once from `setup`.
+pub fn start_trash_purge(app: AppHandle) {
+    crate::jobs::run_periodically(
+        app,
+        "Scheduled trash purge",
+        PURGE_INTERVAL,
+        PURGE_STARTUP_DELAY,
+        |app| async move { run_scheduled_purge(&app).await },
+    );
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// Create a conversation together with any initial messages, atomically.
+#[tauri::command]
+pub async fn create_conversation(
+    app: AppHandle,
+    conversation: Conversation,
+) -> Result<Conversation, String> {
+    let pool = super::pool(&app).await?;
+    create(&pool, conversation).await
+}
+
+/// Append a single message to an existing conversation.
+#[tauri::command]
+pub async fn append_message(
+    app: AppHandle,
+    conversation_id: String,
+    message: Message,
+) -> Result<(), String> {
+    let pool = super::pool(&app).await?;
+    append(&pool, &conversation_id, &message).await
+}
+
+/// List conversations without their messages, pinned ones first and then
+/// the most recently updated. Only archived conversations are listed with
+/// `archived`, and none from the trash.
+#[tauri::command]
+pub async fn list_conversations(
+    app: AppHandle,
+    limit: Option<u32>,
+    offset: Option<u32>,
+    archived: Option<bool>,
+) -> Result<Vec<ConversationSummary>, String> {
+    let pool = super::pool(&app).await?;
+    list(
+        &pool,
+        archived.unwrap_or(false),
+        limit.unwrap_or(DEFAULT_PAGE_SIZE),
+        offset.unwrap_or(0),
+    )
+    .await
+}
+
+/// Load one conversation with all of its messages in chronological order.
+#[tauri::command]
+pub async fn get_conversation(app: AppHandle, id: String) -> Result<Option<Conversation>, String> {
+    let pool = super::pool(&app).await?;
+    get(&pool, &id).await
+}
+
+/// Move a conversation to the trash, where it stays recoverable with
+/// `restore_conversation` until purged. Returns `false` if it did not exist.
+#[tauri::command]
+pub async fn delete_conversation(app: AppHandle, id: String) -> Result<bool, String> {
+    let pool = super::pool(&app).await?;
+    delete(&pool, &id).await
+}
+
+/// Conversations in the trash, most recently deleted first.
+#[tauri::command]
+pub async fn list_deleted_conversations(
+    app: AppHandle,
+    limit: Option<u32>,
+    offset: Option<u32>,
+) -> Result<Vec<ConversationSummary>, String> {
+    let pool = super::pool(&app).await?;
+    list_deleted(
+        &pool,
+        limit.unwrap_or(DEFAULT_PAGE_SIZE),
+        offset.unwrap_or(0),
+    )
+    .await
+}
+
+#[tauri::command]
+pub async fn pin_conversation(app: AppHandle, id: String, pinned: bool) -> Result<bool, String> {
+    let pool = super::pool(&app).await?;
+    set_pinned(&pool, &id, pinned).await
+}
+
+/// Hide a conversation from the main list without deleting it.
+#[tauri::command]
+pub async fn archive_conversation(app: AppHandle, id: String) -> Result<bool, String> {
+    let pool = super::pool(&app).await?;
+    archive(&pool, &id).await
+}
+
+/// Move a conversation out of the trash or the archive, back into the main
+/// list.
+#[tauri::command]
+pub async fn restore_conversation(app: AppHandle, id: String) -> Result<bool, String> {
+    let pool = super::pool(&app).await?;
+    restore(&pool, &id).await
+}
+
+/// Permanently delete conversations that have been in the trash for at least
+/// `older_than_days` days; `0` empties it. Returns how many were removed.
+#[tauri::command]
+pub async fn purge_deleted(app: AppHandle, older_than_days: u32) -> Result<u64, String> {
+    let pool = super::pool(&app).await?;
+    let cutoff = if older_than_days == 0 {
+        i64::MAX
+    } else {
+        super::now_millis() - older_than_days as i64 * DAY_MS
+    };
+    purge_deleted_before(&pool, cutoff).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(id: &str, timestamp: i64) -> Message {
+        Message {
+            id: id.to_string(),
+            role: MessageRole::User,
+            content: format!("content of {}", id),
+            timestamp,
+            attached_files: None,
+            truncated: false,
+        }
+    }
+
+    fn conversation(id: &str, messages: Vec<Message>) -> Conversation {
+        Conversation {
+            id: id.to_string(),
+            title: "Interview prep".to_string(),
+            created_at: 0,
+            updated_at: 0,
+            pinned: false,
+            archived_at: None,
+            deleted_at: None,
+            messages,
+        }
+    }
+
+    #[tokio::test]
+    async fn create_then_get_round_trips_messages() {
+        let pool = crate::db::test_pool().await;
+        let mut first = message("m1", 10);
+        first.attached_files = Some(serde_json::json!([{ "name": "shot.png" }]));
+        create(&pool, conversation("c1", vec![first, message("m2", 20)]))
+            .await
+            .unwrap();
+
+        let loaded = get(&pool, "c1")
+            .await
+            .unwrap()
+            .expect("conversation exists");
+        assert_eq!(loaded.messages.len(), 2);
+        assert_eq!(loaded.messages[0].id, "m1");
+        assert!(loaded.messages[0].attached_files.is_some());
+        assert!(loaded.created_at > 0, "missing timestamps default to now");
+    }
+
+    #[tokio::test]
+    async fn create_is_atomic_when_a_message_fails() {
+        let pool = crate::db::test_pool().await;
+        // Duplicate message ids violate the primary key on the second insert
+        let result = create(
+            &pool,
+            conversation("c1", vec![message("m1", 1), message("m1", 2)]),
+        )
+        .await;
+        assert!(result.is_err());
+        assert!(get(&pool, "c1").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn append_requires_existing_conversation() {
+        let pool = crate::db::test_pool().await;
+        let err = append(&pool, "missing", &message("m1", 1))
+            .await
+            .unwrap_err();
+        assert!(err.contains("not found"));
+    }
+
+    #[tokio::test]
+    async fn append_bumps_updated_at_and_count() {
+        let pool = crate::db::test_pool().await;
+        create(&pool, conversation("c1", vec![])).await.unwrap();
+        append(&pool, "c1", &message("m1", i64::MAX / 2))
+            .await
+            .unwrap();
+
+        let summaries = list(&pool, false, 10, 0).await.unwrap();
+        assert_eq!(summaries[0].message_count, 1);
+        assert_eq!(summaries[0].updated_at, i64::MAX / 2);
+    }
+
+    #[tokio::test]
+    async fn delete_reports_whether_row_existed() {
+        let pool = crate::db::test_pool().await;
+        create(&pool, conversation("c1", vec![message("m1", 1)]))
+            .await
+            .unwrap();
+        assert!(delete(&pool, "c1").await.unwrap());
+        assert!(!delete(&pool, "c1").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn deleted_conversations_are_recoverable_until_purged() {
+        let pool = crate::db::test_pool().await;
+        for id in ["c1", "c2", "c3"] {
+            create(
+                &pool,
+                conversation(id, vec![message(&format!("{}-m", id), 1)]),
+            )
+            .await
+            .unwrap();
+        }
+        assert!(set_pinned(&pool, "c3", true).await.unwrap());
+        assert!(archive(&pool, "c2").await.unwrap());
+        assert!(delete(&pool, "c1").await.unwrap());
+
+        let ids = |summaries: Vec<ConversationSummary>| -> Vec<String> {
+            summaries.into_iter().map(|c| c.id).collect()
+        };
+        assert_eq!(ids(list(&pool, false, 10, 0).await.unwrap()), ["c3"]);
+        assert_eq!(ids(list(&pool, true, 10, 0).await.unwrap()), ["c2"]);
+        assert_eq!(ids(list_deleted(&pool, 10, 0).await.unwrap()), ["c1"]);
+        // Still readable from the trash
+        assert!(get(&pool, "c1")
+            .await
+            .unwrap()
+            .unwrap()
+            .deleted_at
+            .is_some());
+
+        assert!(restore(&pool, "c1").await.unwrap());
+        assert!(restore(&pool, "c2").await.unwrap());
+        assert_eq!(
+            ids(list(&pool, false, 10, 0).await.unwrap()),
+            ["c3", "c1", "c2"]
+        );
+
+        delete(&pool, "c1").await.unwrap();
+        let deleted_at = get(&pool, "c1").await.unwrap().unwrap().deleted_at.unwrap();
+        assert_eq!(purge_deleted_before(&pool, deleted_at).await.unwrap(), 0);
+        assert_eq!(
+            purge_deleted_before(&pool, deleted_at + 1).await.unwrap(),
+            1
+        );
+        assert!(get(&pool, "c1").await.unwrap().is_none());
+        assert!(purge(&pool, "c2").await.unwrap());
+    }
+
+    #[test]
+    fn rejects_blank_title() {
+        assert!(validate_title("   ").is_err());
+        assert!(validate_title("ok").is_ok());
+    }
+}
@@ -0,0 +1,391 @@
+//! Encryption of the chat database at rest with SQLCipher.
+//!
+//! A database is encrypted once, by `encrypt_existing_database`: the pool is
+//! closed so nothing more is written to it, the contents are exported with
+//! `sqlcipher_export` into a keyed copy next to `freely.db`, the copy is
+//! checked, and it then takes the original's place. The plaintext file is
+//! removed once the encrypted one is open.
+//!
+//! The key is derived from a random secret kept in the OS credential store
+//! (see [`crate::secrets`]), so nothing has to be typed at startup. Whether a
+//! file is encrypted is read from its header rather than from the
+//! `database_encryption` setting, which lives inside the database and can't
+//! be read before the key is applied; the setting only records when the
+//! migration ran, for the settings screen.
+
+use serde::Serialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{Connection, SqliteConnection};
+use std::io::Read;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{AppHandle, Emitter};
+use tracing::warn;
+
+/// Settings key recording that the database was encrypted.
+pub(crate) const SETTING_KEY: &str = "database_encryption";
+/// Name of the data key among the app's internal secrets.
+const SECRET_NAME: &str = "database";
+const KEY_CONTEXT: &[u8] = b"freely-sqlcipher-v1";
+/// Every plaintext SQLite file starts with this; SQLCipher files don't.
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+static ACTIVE_KEY: parking_lot::RwLock<Option<DatabaseKey>> = parking_lot::RwLock::new(None);
+static ENCRYPTING: AtomicBool = AtomicBool::new(false);
+
+/// A raw 256-bit SQLCipher key.
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) struct DatabaseKey(String);
+
+impl DatabaseKey {
+    pub(crate) fn derive(secret: &[u8]) -> Self {
+        let digest = Sha256::new()
+            .chain_update(KEY_CONTEXT)
+            .chain_update(secret)
+            .finalize();
+        Self(digest.iter().map(|b| format!("{:02x}", b)).collect())
+    }
+
+    /// The key as SQLCipher's raw key literal. The secret is already random,
+    /// so SQLCipher's own passphrase derivation is skipped.
+    pub(crate) fn literal(&self) -> String {
+        format!("x'{}'", self.0)
+    }
+
+    /// Value for `PRAGMA key`.
+    pub(crate) fn pragma(&self) -> String {
+        format!("\"{}\"", self.literal())
+    }
+}
+
+impl std::fmt::Debug for DatabaseKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("DatabaseKey(..)")
+    }
+}
+
+/// Key of the open database, if it is encrypted. Backups of it are written
+/// with the same key.
+pub(crate) fn active_key() -> Option<DatabaseKey> {
+    ACTIVE_KEY.read().clone()
+}
+
+pub(crate) fn set_active_key(key: Option<DatabaseKey>) {
+    *ACTIVE_KEY.write() = key;
+}
+
+/// Whether the file at `path` is a SQLCipher database. Missing and empty
+/// files are not.
+pub(crate) fn is_encrypted(path: &Path) -> bool {
+    let mut header = [0u8; 16];
+    match std::fs::File::open(path).and_then(|mut file| file.read_exact(&mut header)) {
+        Ok(()) => &header != SQLITE_HEADER,
+        Err(_) => false,
+    }
+}
+
+/// Options for opening the file at `path`, keyed with [`active_key`] when
+/// the file is encrypted.
+pub(crate) fn connect_options(path: &Path) -> SqliteConnectOptions {
+    let options = SqliteConnectOptions::new().filename(path);
+    match active_key() {
+        Some(key) if is_encrypted(path) => options.pragma("key", key.pragma()),
+        _ => options,
+    }
+}
+
+/// The key for this install's database, creating its secret on first use.
+pub(crate) async fn database_key(app: &AppHandle) -> Result<DatabaseKey, String> {
+    let secret = crate::secrets::load_or_create_data_key(app, SECRET_NAME).await?;
+    Ok(DatabaseKey::derive(secret.as_slice()))
+}
+
+/// The key for the database in `data_dir`, for callers without an
+/// [`AppHandle`]. Fails if the secret was never created.
+pub(crate) fn database_key_in(data_dir: &Path) -> Result<DatabaseKey, String> {
+    crate::secrets::load_data_key_in(data_dir, SECRET_NAME)?
+        .map(|secret| DatabaseKey::derive(secret.as_slice()))
+        .ok_or_else(|| "The database is encrypted but its key is missing".to_string())
+}
+
+/// Write an encrypted copy of the database open on `conn` to `dest`.
+pub(crate) async fn export_encrypted(
+    conn: &mut SqliteConnection,
+    dest: &Path,
+    key: &DatabaseKey,
+) -> Result<(), String> {
+    sqlx::query("ATTACH DATABASE ? AS encrypted KEY ?")
+        .bind(dest.to_string_lossy().into_owned())
+        .bind(key.literal())
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to create encrypted copy: {}", e))?;
+    let exported = sqlx::query("SELECT sqlcipher_export('encrypted')")
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to export database: {}", e));
+    // Detach even after a failed export so the connection goes back clean
+    let detached = sqlx::query("DETACH DATABASE encrypted")
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to detach encrypted copy: {}", e));
+    exported.and(detached).map(|_| ())
+}
+
+/// Open the encrypted file at `path` with `key` and check it is intact,
+/// returning how many messages it holds.
+pub(crate) async fn verify_encrypted(path: &Path, key: &DatabaseKey) -> Result<i64, String> {
+    let options = SqliteConnectOptions::new()
+        .filename(path)
+        .read_only(true)
+        .pragma("key", key.pragma());
+    let mut conn = SqliteConnection::connect_with(&options)
+        .await
+        .map_err(|e| format!("Failed to open encrypted copy: {}", e))?;
+
+    let problems: Vec<String> = sqlx::query_scalar("PRAGMA integrity_check")
+        .fetch_all(&mut conn)
+        .await
+        .map_err(|e| format!("Encrypted copy is unreadable: {}", e))?;
+    if problems != ["ok"] {
+        return Err(format!(
+            "Encrypted copy is corrupted: {}",
+            problems.join("; ")
+        ));
+    }
+    let messages = count_messages(&mut conn).await?;
+    let _ = conn.close().await;
+    Ok(messages)
+}
+
+async fn count_messages(conn: &mut SqliteConnection) -> Result<i64, String> {
+    sqlx::query_scalar("SELECT COUNT(*) FROM messages")
+        .fetch_one(conn)
+        .await
+        .map_err(|e| format!("Failed to count messages: {}", e))
+}
+
+// ============================================================================
+// Migration
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EncryptionStage {
+    Exporting,
+    Verifying,
+    Swapping,
+    Done,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EncryptionProgress {
+    stage: EncryptionStage,
+    /// 0.0 to 1.0.
+    progress: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptionStatus {
+    pub encrypted: bool,
+    /// When `encrypt_existing_database` ran, if it has.
+    pub encrypted_at: Option<i64>,
+}
+
+fn emit_progress(app: &AppHandle, stage: EncryptionStage, progress: f64) {
+    if let Err(e) = app.emit(
+        "database-encryption-progress",
+        EncryptionProgress { stage, progress },
+    ) {
+        warn!("Failed to emit encryption progress: {}", e);
+    }
+}
+
+/// Clears [`ENCRYPTING`] however the migration ends.
+struct EncryptingGuard;
+
+impl Drop for EncryptingGuard {
+    fn drop(&mut self) {
+        ENCRYPTING.store(false, Ordering::SeqCst);
+    }
+}
+
+async fn encrypt_in_place(app: &AppHandle) -> Result<(), String> {
+    let path = super::backup::database_path(app)?;
+    if is_encrypted(&path) {
+        return Err("The database is already encrypted".to_string());
+    }
+    let key = database_key(app).await?;
+
+    emit_progress(app, EncryptionStage::Exporting, 0.0);
+    // Anything written to the plaintext file after the export would be lost
+    // with it, so the pool stays closed until the swap is done
+    super::close_pool(app).await;
+    if let Err(e) = swap_in_encrypted(app, &path, &key).await {
+        if let Err(reopen) = super::register_pool(app, &path, None).await {
+            warn!("Failed to reopen the plaintext database: {}", reopen);
+        }
+        return Err(e);
+    }
+
+    crate::settings::set_setting(
+        app,
+        SETTING_KEY,
+        &json!({ "enabled": true, "encryptedAt": super::now_millis() }),
+    )
+    .await?;
+    emit_progress(app, EncryptionStage::Done, 1.0);
+    Ok(())
+}
+
+/// Export the closed plaintext database at `path`, check the copy, and open
+/// it in the original's place. On failure the plaintext file is left at
+/// `path`.
+async fn swap_in_encrypted(app: &AppHandle, path: &Path, key: &DatabaseKey) -> Result<(), String> {
+    let staging = super::legacy::with_suffix(path, ".encrypting");
+    super::legacy::remove_database(&staging);
+    let exported = async {
+        let mut conn = SqliteConnection::connect_with(&connect_options(path))
+            .await
+            .map_err(|e| format!("Failed to open database: {}", e))?;
+        let exported = export_encrypted(&mut conn, &staging, key).await;
+        let expected = count_messages(&mut conn).await;
+        let _ = conn.close().await;
+        exported.and(expected)
+    }
+    .await;
+    let expected = match exported {
+        Ok(expected) => expected,
+        Err(e) => {
+            super::legacy::remove_database(&staging);
+            return Err(e);
+        }
+    };
+
+    emit_progress(app, EncryptionStage::Verifying, 0.6);
+    match verify_encrypted(&staging, key).await {
+        Ok(messages) if messages == expected => {}
+        Ok(messages) => {
+            super::legacy::remove_database(&staging);
+            return Err(format!(
+                "Encrypted copy has {} messages, expected {}",
+                messages, expected
+            ));
+        }
+        Err(e) => {
+            super::legacy::remove_database(&staging);
+            return Err(e);
+        }
+    }
+
+    emit_progress(app, EncryptionStage::Swapping, 0.8);
+    let plaintext = super::legacy::with_suffix(path, ".plaintext");
+    super::legacy::remove_database(&plaintext);
+    if let Err(e) = super::legacy::rename_database(path, &plaintext) {
+        super::legacy::remove_database(&staging);
+        return Err(format!("Failed to switch to the encrypted database: {}", e));
+    }
+    let reopened = match super::legacy::rename_database(&staging, path) {
+        Ok(()) => super::register_pool(app, path, Some(key)).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = reopened {
+        // Put the plaintext database back so the app keeps working
+        super::close_pool(app).await;
+        super::legacy::remove_database(&staging);
+        super::legacy::remove_database(path);
+        if let Err(restore) = super::legacy::rename_database(&plaintext, path) {
+            warn!("Failed to put the plaintext database back: {}", restore);
+        }
+        return Err(format!("Failed to switch to the encrypted database: {}", e));
+    }
+    super::legacy::remove_database(&plaintext);
+    Ok(())
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// Whether the database is encrypted, and since when.
+#[tauri::command]
+pub async fn get_database_encryption(app: AppHandle) -> Result<EncryptionStatus, String> {
+    let path = super::backup::database_path(&app)?;
+    let pool = super::pool(&app).await?;
+    let stored: Option<serde_json::Value> = crate::db::settings::get(&pool, SETTING_KEY).await?;
+    Ok(EncryptionStatus {
+        encrypted: is_encrypted(&path),
+        encrypted_at: stored
+            .as_ref()
+            .and_then(|value| value.get("encryptedAt"))
+            .and_then(|value| value.as_i64()),
+    })
+}
+
+/// Re-encrypt `freely.db` in place, emitting `database-encryption-progress`
+/// as it goes. The database is unavailable while it is exported and the
+/// files are swapped. Backups taken before this stay unencrypted.
+#[tauri::command]
+pub async fn encrypt_existing_database(app: AppHandle) -> Result<(), String> {
+    if ENCRYPTING.swap(true, Ordering::SeqCst) {
+        return Err("The database is already being encrypted".to_string());
+    }
+    let _guard = EncryptingGuard;
+    encrypt_in_place(&app).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+    use tempfile::TempDir;
+
+    #[test]
+    fn keys_are_derived_deterministically() {
+        let key = DatabaseKey::derive(&[7u8; 32]);
+        assert_eq!(key, DatabaseKey::derive(&[7u8; 32]));
+        assert_ne!(key, DatabaseKey::derive(&[8u8; 32]));
+        assert_eq!(key.literal().len(), "x''".len() + 64);
+        assert!(!format!("{:?}", key).contains(&key.0));
+    }
+
+    #[tokio::test]
+    async fn export_produces_a_keyed_copy() {
+        let tmp = TempDir::new().unwrap();
+        let plain = tmp.path().join("freely.db");
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(
+                SqliteConnectOptions::new()
+                    .filename(&plain)
+                    .create_if_missing(true),
+            )
+            .await
+            .unwrap();
+        sqlx::raw_sql(
+            "CREATE TABLE messages (content TEXT); INSERT INTO messages VALUES ('secret');",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        assert!(!is_encrypted(&plain));
+        assert!(!is_encrypted(&tmp.path().join("missing.db")));
+
+        let key = DatabaseKey::derive(b"test secret");
+        let encrypted = tmp.path().join("freely.db.encrypting");
+        let mut conn = pool.acquire().await.unwrap();
+        export_encrypted(&mut conn, &encrypted, &key).await.unwrap();
+        assert!(is_encrypted(&encrypted));
+        assert_eq!(verify_encrypted(&encrypted, &key).await.unwrap(), 1);
+
+        let raw = std::fs::read(&encrypted).unwrap();
+        assert!(!raw.windows(6).any(|w| w == b"secret"));
+        assert!(verify_encrypted(&encrypted, &DatabaseKey::derive(b"wrong"))
+            .await
+            .is_err());
+    }
+}
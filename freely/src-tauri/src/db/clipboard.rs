@@ -0,0 +1,57 @@
+//! Clipboard history rows (migration 12). Contents are opaque encrypted
+//! blobs here; [`crate::clipboard`] encrypts and decrypts them.
+
+use sqlx::SqlitePool;
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub(crate) struct ClipboardRow {
+    pub id: String,
+    pub content: Vec<u8>,
+    pub created_at: i64,
+}
+
+pub(crate) async fn insert(pool: &SqlitePool, row: &ClipboardRow) -> Result<(), String> {
+    sqlx::query("INSERT INTO clipboard_history (id, content, created_at) VALUES (?, ?, ?)")
+        .bind(&row.id)
+        .bind(&row.content)
+        .bind(row.created_at)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to save clipboard entry: {}", e))?;
+    Ok(())
+}
+
+/// Newest first.
+pub(crate) async fn list(pool: &SqlitePool, limit: i64) -> Result<Vec<ClipboardRow>, String> {
+    sqlx::query_as::<_, ClipboardRow>(
+        "SELECT id, content, created_at FROM clipboard_history
+         ORDER BY created_at DESC, rowid DESC
+         LIMIT ?",
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load clipboard history: {}", e))
+}
+
+/// Delete all but the newest `keep` entries.
+pub(crate) async fn prune(pool: &SqlitePool, keep: i64) -> Result<u64, String> {
+    let result = sqlx::query(
+        "DELETE FROM clipboard_history WHERE id NOT IN (
+             SELECT id FROM clipboard_history ORDER BY created_at DESC, rowid DESC LIMIT ?
+         )",
+    )
+    .bind(keep)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to prune clipboard history: {}", e))?;
+    Ok(result.rows_affected())
+}
+
+pub(crate) async fn clear(pool: &SqlitePool) -> Result<u64, String> {
+    let result = sqlx::query("DELETE FROM clipboard_history")
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to clear clipboard history: {}", e))?;
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,340 @@
+//! Conversation folders (migration 16).
+//!
+//! Folders form a tree through `parent_id`; top-level folders have none. The
+//! tree is returned flat and assembled by the frontend. A conversation is in
+//! at most one folder, and conversations in none are "unfiled".
+
+use super::chat::{ConversationSummary, DEFAULT_PAGE_SIZE, SUMMARY_ORDER, SUMMARY_SELECT};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tauri::AppHandle;
+
+const MAX_NAME_LEN: usize = 100;
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Folder {
+    pub id: String,
+    pub name: String,
+    pub parent_id: Option<String>,
+    pub created_at: i64,
+    /// Conversations directly in this folder, not counting subfolders or
+    /// the trash.
+    pub conversation_count: i64,
+}
+
+const FOLDER_SELECT: &str = "SELECT f.id, f.name, f.parent_id, f.created_at,
+            (SELECT COUNT(*) FROM conversation_folders cf
+             JOIN conversations c ON c.id = cf.conversation_id
+             WHERE cf.folder_id = f.id AND c.deleted_at IS NULL) AS conversation_count
+     FROM folders f";
+
+fn validate_name(name: &str) -> Result<&str, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Invalid folder: name must not be empty".to_string());
+    }
+    if name.chars().count() > MAX_NAME_LEN {
+        return Err(format!(
+            "Invalid folder: name exceeds {} characters",
+            MAX_NAME_LEN
+        ));
+    }
+    Ok(name)
+}
+
+pub(crate) async fn get(pool: &SqlitePool, id: &str) -> Result<Option<Folder>, String> {
+    sqlx::query_as::<_, Folder>(&format!("{} WHERE f.id = ?", FOLDER_SELECT))
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Failed to load folder: {}", e))
+}
+
+async fn require(pool: &SqlitePool, id: &str) -> Result<Folder, String> {
+    get(pool, id)
+        .await?
+        .ok_or_else(|| format!("Folder not found: {}", id))
+}
+
+pub(crate) async fn create(
+    pool: &SqlitePool,
+    name: &str,
+    parent_id: Option<&str>,
+) -> Result<Folder, String> {
+    let name = validate_name(name)?;
+    if let Some(parent_id) = parent_id {
+        require(pool, parent_id).await?;
+    }
+
+    let id = uuid::Uuid::new_v4().to_string();
+    sqlx::query("INSERT INTO folders (id, name, parent_id, created_at) VALUES (?, ?, ?, ?)")
+        .bind(&id)
+        .bind(name)
+        .bind(parent_id)
+        .bind(super::now_millis())
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to create folder: {}", e))?;
+    require(pool, &id).await
+}
+
+pub(crate) async fn rename(pool: &SqlitePool, id: &str, name: &str) -> Result<Folder, String> {
+    let name = validate_name(name)?;
+    sqlx::query("UPDATE folders SET name = ? WHERE id = ?")
+        .bind(name)
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to rename folder: {}", e))?;
+    require(pool, id).await
+}
+
+/// Move folder `id` under `parent_id`, or to the top level. Refuses to move
+/// a folder into itself or one of its own subfolders.
+pub(crate) async fn move_to(
+    pool: &SqlitePool,
+    id: &str,
+    parent_id: Option<&str>,
+) -> Result<Folder, String> {
+    require(pool, id).await?;
+    if let Some(parent_id) = parent_id {
+        require(pool, parent_id).await?;
+        let cycle: bool = sqlx::query_scalar(
+            "WITH RECURSIVE ancestors(id) AS (
+                 SELECT ?
+                 UNION
+                 SELECT f.parent_id FROM folders f JOIN ancestors a ON f.id = a.id
+                 WHERE f.parent_id IS NOT NULL
+             )
+             SELECT EXISTS (SELECT 1 FROM ancestors WHERE id = ?)",
+        )
+        .bind(parent_id)
+        .bind(id)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("Failed to check folder hierarchy: {}", e))?;
+        if cycle {
+            return Err("A folder cannot be moved into itself or its subfolders".to_string());
+        }
+    }
+
+    sqlx::query("UPDATE folders SET parent_id = ? WHERE id = ?")
+        .bind(parent_id)
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to move folder: {}", e))?;
+    require(pool, id).await
+}
+
+/// Delete a folder and its subfolders. Their conversations are kept, unfiled.
+pub(crate) async fn delete(pool: &SqlitePool, id: &str) -> Result<bool, String> {
+    let result = sqlx::query("DELETE FROM folders WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to delete folder: {}", e))?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Every folder, sorted by name.
+pub(crate) async fn list(pool: &SqlitePool) -> Result<Vec<Folder>, String> {
+    sqlx::query_as::<_, Folder>(&format!("{} ORDER BY f.name COLLATE NOCASE", FOLDER_SELECT))
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to list folders: {}", e))
+}
+
+/// File a conversation in `folder_id`, or unfile it with `None`.
+pub(crate) async fn file_conversation(
+    pool: &SqlitePool,
+    conversation_id: &str,
+    folder_id: Option<&str>,
+) -> Result<(), String> {
+    let exists: Option<(String,)> = sqlx::query_as("SELECT id FROM conversations WHERE id = ?")
+        .bind(conversation_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Failed to look up conversation: {}", e))?;
+    if exists.is_none() {
+        return Err(format!("Conversation not found: {}", conversation_id));
+    }
+
+    match folder_id {
+        Some(folder_id) => {
+            require(pool, folder_id).await?;
+            sqlx::query(
+                "INSERT INTO conversation_folders (conversation_id, folder_id) VALUES (?, ?)
+                 ON CONFLICT(conversation_id) DO UPDATE SET folder_id = excluded.folder_id",
+            )
+            .bind(conversation_id)
+            .bind(folder_id)
+            .execute(pool)
+            .await
+        }
+        None => {
+            sqlx::query("DELETE FROM conversation_folders WHERE conversation_id = ?")
+                .bind(conversation_id)
+                .execute(pool)
+                .await
+        }
+    }
+    .map_err(|e| format!("Failed to move conversation: {}", e))?;
+    Ok(())
+}
+
+/// Conversations directly in `folder_id`, or the unfiled ones for `None`.
+pub(crate) async fn conversations(
+    pool: &SqlitePool,
+    folder_id: Option<&str>,
+    limit: u32,
+    offset: u32,
+) -> Result<Vec<ConversationSummary>, String> {
+    sqlx::query_as::<_, ConversationSummary>(&format!(
+        "{} LEFT JOIN conversation_folders cf ON cf.conversation_id = c.id
+         WHERE cf.folder_id IS ? AND c.deleted_at IS NULL
+         {}
+         LIMIT ? OFFSET ?",
+        SUMMARY_SELECT, SUMMARY_ORDER
+    ))
+    .bind(folder_id)
+    .bind(limit as i64)
+    .bind(offset as i64)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to list conversations: {}", e))
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+#[tauri::command]
+pub async fn create_folder(
+    app: AppHandle,
+    name: String,
+    parent_id: Option<String>,
+) -> Result<Folder, String> {
+    let pool = super::pool(&app).await?;
+    create(&pool, &name, parent_id.as_deref()).await
+}
+
+#[tauri::command]
+pub async fn rename_folder(app: AppHandle, id: String, name: String) -> Result<Folder, String> {
+    let pool = super::pool(&app).await?;
+    rename(&pool, &id, &name).await
+}
+
+/// Move a folder under another one, or to the top level without `parent_id`.
+#[tauri::command]
+pub async fn move_folder(
+    app: AppHandle,
+    id: String,
+    parent_id: Option<String>,
+) -> Result<Folder, String> {
+    let pool = super::pool(&app).await?;
+    move_to(&pool, &id, parent_id.as_deref()).await
+}
+
+/// Delete a folder and its subfolders, keeping their conversations unfiled.
+/// Returns `false` if it did not exist.
+#[tauri::command]
+pub async fn delete_folder(app: AppHandle, id: String) -> Result<bool, String> {
+    let pool = super::pool(&app).await?;
+    delete(&pool, &id).await
+}
+
+/// Every folder as a flat list; `parentId` links them into a tree.
+#[tauri::command]
+pub async fn list_folders(app: AppHandle) -> Result<Vec<Folder>, String> {
+    let pool = super::pool(&app).await?;
+    list(&pool).await
+}
+
+/// Move a conversation into a folder, or out of any folder without
+/// `folder_id`.
+#[tauri::command]
+pub async fn move_conversation_to_folder(
+    app: AppHandle,
+    conversation_id: String,
+    folder_id: Option<String>,
+) -> Result<(), String> {
+    let pool = super::pool(&app).await?;
+    file_conversation(&pool, &conversation_id, folder_id.as_deref()).await
+}
+
+/// Conversations in a folder, or the unfiled ones without `folder_id`, pinned
+/// ones first and then the most recently updated.
+#[tauri::command]
+pub async fn list_conversations_in_folder(
+    app: AppHandle,
+    folder_id: Option<String>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+) -> Result<Vec<ConversationSummary>, String> {
+    let pool = super::pool(&app).await?;
+    conversations(
+        &pool,
+        folder_id.as_deref(),
+        limit.unwrap_or(DEFAULT_PAGE_SIZE),
+        offset.unwrap_or(0),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::chat::{self, Conversation};
+
+    #[tokio::test]
+    async fn folders_nest_without_cycles() {
+        let pool = crate::db::test_pool().await;
+        for id in ["a", "b"] {
+            chat::create(
+                &pool,
+                Conversation {
+                    id: id.to_string(),
+                    title: id.to_string(),
+                    created_at: 1,
+                    updated_at: 1,
+                    pinned: false,
+                    archived_at: None,
+                    deleted_at: None,
+                    messages: Vec::new(),
+                },
+            )
+            .await
+            .unwrap();
+        }
+
+        let work = create(&pool, "Work", None).await.unwrap();
+        let clients = create(&pool, "Clients", Some(&work.id)).await.unwrap();
+        let acme = create(&pool, "Acme", Some(&clients.id)).await.unwrap();
+        assert!(move_to(&pool, &work.id, Some(&acme.id)).await.is_err());
+        assert!(move_to(&pool, &work.id, Some(&work.id)).await.is_err());
+        let moved = move_to(&pool, &acme.id, None).await.unwrap();
+        assert_eq!(moved.parent_id, None);
+
+        file_conversation(&pool, "a", Some(&clients.id))
+            .await
+            .unwrap();
+        file_conversation(&pool, "a", Some(&work.id)).await.unwrap();
+        let filed = conversations(&pool, Some(&work.id), 10, 0).await.unwrap();
+        assert_eq!(filed.len(), 1);
+        let unfiled = conversations(&pool, None, 10, 0).await.unwrap();
+        assert_eq!(unfiled[0].id, "b");
+
+        // Deleting a folder takes its subfolders and unfiles conversations
+        assert!(delete(&pool, &work.id).await.unwrap());
+        let names: Vec<_> = list(&pool)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|f| f.name)
+            .collect();
+        assert_eq!(names, ["Acme"]);
+        assert_eq!(conversations(&pool, None, 10, 0).await.unwrap().len(), 2);
+    }
+}
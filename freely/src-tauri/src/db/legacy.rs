@@ -0,0 +1,196 @@
+//! One-time migration of the pre-rename `pluely.db` to `freely.db`, so
+//! existing users keep their conversation history.
+//!
+//! The old database is copied rather than renamed: the copy (with its WAL,
+//! which may hold committed transactions not yet in the main file) must pass
+//! `PRAGMA integrity_check` and is checkpointed before it takes the new name.
+//! Only then are the originals moved aside to `pluely.db.bak`, whose sidecars
+//! keep the matching `-wal`/`-shm` suffixes so SQLite can still open it. Any
+//! failure removes the partial copy and leaves `pluely.db` untouched.
+//!
+//! The `.bak` files are kept for one release in case a migrated database
+//! turns out to be missing something.
+
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::{Connection, SqliteConnection};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+use tracing::{info, warn};
+
+const LEGACY_NAME: &str = "pluely.db";
+const BACKUP_SUFFIX: &str = ".bak";
+const SIDECARS: [&str; 2] = ["-wal", "-shm"];
+
+pub(super) fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// `path` and its sidecar files.
+fn database_files(path: &Path) -> Vec<PathBuf> {
+    std::iter::once(path.to_path_buf())
+        .chain(SIDECARS.iter().map(|s| with_suffix(path, s)))
+        .collect()
+}
+
+pub(super) fn remove_database(path: &Path) {
+    for file in database_files(path) {
+        let _ = std::fs::remove_file(file);
+    }
+}
+
+async fn verify_and_checkpoint(path: &Path) -> Result<(), String> {
+    let options = SqliteConnectOptions::new().filename(path);
+    let mut conn = SqliteConnection::connect_with(&options)
+        .await
+        .map_err(|e| format!("Failed to open copy: {}", e))?;
+
+    let problems: Vec<String> = sqlx::query_scalar("PRAGMA integrity_check")
+        .fetch_all(&mut conn)
+        .await
+        .map_err(|e| format!("Integrity check failed: {}", e))?;
+    if problems != ["ok"] {
+        return Err(format!("Copy is corrupted: {}", problems.join("; ")));
+    }
+
+    // Fold the WAL into the main file so the copy stands on its own
+    sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+        .execute(&mut conn)
+        .await
+        .map_err(|e| format!("Failed to checkpoint copy: {}", e))?;
+    conn.close()
+        .await
+        .map_err(|e| format!("Failed to close copy: {}", e))
+}
+
+async fn copy_and_verify(old_path: &Path, staging: &Path) -> Result<(), String> {
+    std::fs::copy(old_path, staging).map_err(|e| format!("Failed to copy database: {}", e))?;
+    // The shared-memory index is rebuilt on open; only the WAL holds data
+    let old_wal = with_suffix(old_path, "-wal");
+    if old_wal.exists() {
+        std::fs::copy(&old_wal, with_suffix(staging, "-wal"))
+            .map_err(|e| format!("Failed to copy WAL: {}", e))?;
+    }
+    verify_and_checkpoint(staging).await
+}
+
+/// Move `from` and whichever sidecars exist to `to`, keeping their suffixes.
+pub(super) fn rename_database(from: &Path, to: &Path) -> Result<(), String> {
+    for (source, target) in database_files(from).into_iter().zip(database_files(to)) {
+        if source.exists() {
+            std::fs::rename(&source, &target).map_err(|e| {
+                format!(
+                    "Failed to rename {} to {}: {}",
+                    source.display(),
+                    target.display(),
+                    e
+                )
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Migrate `pluely.db` in `dir` to `db_name`. Returns whether anything was
+/// migrated; an existing `db_name` is never overwritten.
+pub(crate) async fn migrate_in(dir: &Path, db_name: &str) -> Result<bool, String> {
+    let old_path = dir.join(LEGACY_NAME);
+    let new_path = dir.join(db_name);
+    if !old_path.exists() || new_path.exists() {
+        return Ok(false);
+    }
+
+    let staging = with_suffix(&new_path, ".migrating");
+    remove_database(&staging);
+    if let Err(e) = copy_and_verify(&old_path, &staging).await {
+        remove_database(&staging);
+        return Err(e);
+    }
+    if let Err(e) = rename_database(&staging, &new_path) {
+        remove_database(&staging);
+        remove_database(&new_path);
+        return Err(e);
+    }
+
+    // The new database is in place; a failure here only leaves the old
+    // files where they were
+    if let Err(e) = rename_database(&old_path, &with_suffix(&old_path, BACKUP_SUFFIX)) {
+        warn!("Failed to move {} aside: {}", LEGACY_NAME, e);
+    }
+    Ok(true)
+}
+
+/// Run the migration before `db::open_database` opens the database for the
+/// first time.
+pub fn migrate_legacy_db(app: &AppHandle) {
+    let dir = match app.path().app_local_data_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            warn!("Could not resolve app_local_data_dir: {}", e);
+            return;
+        }
+    };
+    let db_name = super::DB_URL.trim_start_matches("sqlite:");
+    match tauri::async_runtime::block_on(migrate_in(&dir, db_name)) {
+        Ok(true) => info!("Migrated {} to {}", LEGACY_NAME, db_name),
+        Ok(false) => {}
+        Err(e) => warn!(
+            "Failed to migrate {}, leaving it in place: {}",
+            LEGACY_NAME, e
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn legacy_database_is_copied_verified_and_kept_as_backup() {
+        let tmp = TempDir::new().unwrap();
+        let old_path = tmp.path().join(LEGACY_NAME);
+        let options = SqliteConnectOptions::new()
+            .filename(&old_path)
+            .create_if_missing(true);
+        let mut conn = SqliteConnection::connect_with(&options).await.unwrap();
+        sqlx::raw_sql("CREATE TABLE notes (body TEXT); INSERT INTO notes VALUES ('kept');")
+            .execute(&mut conn)
+            .await
+            .unwrap();
+        conn.close().await.unwrap();
+
+        assert!(migrate_in(tmp.path(), "freely.db").await.unwrap());
+        assert!(!old_path.exists());
+        assert!(tmp.path().join("pluely.db.bak").exists());
+        assert!(!tmp.path().join("freely.db.migrating").exists());
+
+        let options = SqliteConnectOptions::new().filename(tmp.path().join("freely.db"));
+        let mut conn = SqliteConnection::connect_with(&options).await.unwrap();
+        let body: String = sqlx::query_scalar("SELECT body FROM notes")
+            .fetch_one(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(body, "kept");
+
+        // Nothing left to do the second time
+        assert!(!migrate_in(tmp.path(), "freely.db").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn corrupted_legacy_database_is_left_alone() {
+        let tmp = TempDir::new().unwrap();
+        let old_path = tmp.path().join(LEGACY_NAME);
+        std::fs::write(&old_path, b"not a database at all").unwrap();
+
+        assert!(migrate_in(tmp.path(), "freely.db").await.is_err());
+        assert_eq!(std::fs::read(&old_path).unwrap(), b"not a database at all");
+        let names: Vec<_> = std::fs::read_dir(tmp.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(names, [LEGACY_NAME]);
+    }
+}
@@ -1,6 +1,7 @@
 use tauri_plugin_sql::{Migration, MigrationKind};
 
-/// Returns all database migrations
+/// Returns all database migrations. Each `Up` is followed by the `Down`
+/// script that reverts it.
 pub fn migrations() -> Vec<Migration> {
     vec![
         // Migration 1: Create system_prompts table with indexes and triggers
@@ -10,6 +11,12 @@ pub fn migrations() -> Vec<Migration> {
             sql: include_str!("migrations/system-prompts.sql"),
             kind: MigrationKind::Up,
         },
+        Migration {
+            version: 1,
+            description: "create_system_prompts_table",
+            sql: include_str!("migrations/down/system-prompts.sql"),
+            kind: MigrationKind::Down,
+        },
         // Migration 2: Create chat history tables (conversations and messages)
         Migration {
             version: 2,
@@ -17,5 +24,375 @@ pub fn migrations() -> Vec<Migration> {
             sql: include_str!("migrations/chat-history.sql"),
             kind: MigrationKind::Up,
         },
+        Migration {
+            version: 2,
+            description: "create_chat_history_tables",
+            sql: include_str!("migrations/down/chat-history.sql"),
+            kind: MigrationKind::Down,
+        },
+        // Migration 3: Create FTS5 index over message content with sync triggers
+        Migration {
+            version: 3,
+            description: "create_messages_fts_index",
+            sql: include_str!("migrations/chat-search.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 3,
+            description: "create_messages_fts_index",
+            sql: include_str!("migrations/down/chat-search.sql"),
+            kind: MigrationKind::Down,
+        },
+        // Migration 4: Create transcripts table with source and timing metadata
+        Migration {
+            version: 4,
+            description: "create_transcripts_table",
+            sql: include_str!("migrations/transcripts.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 4,
+            description: "create_transcripts_table",
+            sql: include_str!("migrations/down/transcripts.sql"),
+            kind: MigrationKind::Down,
+        },
+        // Migration 5: Create key/value settings table
+        Migration {
+            version: 5,
+            description: "create_settings_table",
+            sql: include_str!("migrations/settings.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 5,
+            description: "create_settings_table",
+            sql: include_str!("migrations/down/settings.sql"),
+            kind: MigrationKind::Down,
+        },
+        // Migration 6: Create rolling conversation summaries for context compaction
+        Migration {
+            version: 6,
+            description: "create_conversation_summaries_table",
+            sql: include_str!("migrations/conversation-summaries.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 6,
+            description: "create_conversation_summaries_table",
+            sql: include_str!("migrations/down/conversation-summaries.sql"),
+            kind: MigrationKind::Down,
+        },
+        // Migration 7: Create message embeddings table for semantic search
+        Migration {
+            version: 7,
+            description: "create_message_embeddings_table",
+            sql: include_str!("migrations/message-embeddings.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 7,
+            description: "create_message_embeddings_table",
+            sql: include_str!("migrations/down/message-embeddings.sql"),
+            kind: MigrationKind::Down,
+        },
+        // Migration 8: Create knowledge folder, file and chunk tables for RAG
+        Migration {
+            version: 8,
+            description: "create_knowledge_tables",
+            sql: include_str!("migrations/knowledge.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 8,
+            description: "create_knowledge_tables",
+            sql: include_str!("migrations/down/knowledge.sql"),
+            kind: MigrationKind::Down,
+        },
+        // Migration 9: Create projects table for per-project .claude workspaces
+        Migration {
+            version: 9,
+            description: "create_projects_table",
+            sql: include_str!("migrations/projects.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 9,
+            description: "create_projects_table",
+            sql: include_str!("migrations/down/projects.sql"),
+            kind: MigrationKind::Down,
+        },
+        // Migration 10: Add speaker labels to transcripts for diarization
+        Migration {
+            version: 10,
+            description: "add_transcript_speaker_labels",
+            sql: include_str!("migrations/transcript-speakers.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 10,
+            description: "add_transcript_speaker_labels",
+            sql: include_str!("migrations/down/transcript-speakers.sql"),
+            kind: MigrationKind::Down,
+        },
+        // Migration 11: Allow transcripts of the mixed audio stream
+        Migration {
+            version: 11,
+            description: "allow_mixed_transcript_source",
+            sql: include_str!("migrations/transcript-mixed-source.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 11,
+            description: "allow_mixed_transcript_source",
+            sql: include_str!("migrations/down/transcript-mixed-source.sql"),
+            kind: MigrationKind::Down,
+        },
+        // Migration 12: Create encrypted clipboard history
+        Migration {
+            version: 12,
+            description: "create_clipboard_history_table",
+            sql: include_str!("migrations/clipboard-history.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 12,
+            description: "create_clipboard_history_table",
+            sql: include_str!("migrations/down/clipboard-history.sql"),
+            kind: MigrationKind::Down,
+        },
+        // Migration 13: Create attachments index
+        Migration {
+            version: 13,
+            description: "create_attachments_table",
+            sql: include_str!("migrations/attachments.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 13,
+            description: "create_attachments_table",
+            sql: include_str!("migrations/down/attachments.sql"),
+            kind: MigrationKind::Down,
+        },
+        // Migration 14: Keep a version history of system prompts
+        Migration {
+            version: 14,
+            description: "create_system_prompt_versions_table",
+            sql: include_str!("migrations/system-prompt-versions.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 14,
+            description: "create_system_prompt_versions_table",
+            sql: include_str!("migrations/down/system-prompt-versions.sql"),
+            kind: MigrationKind::Down,
+        },
+        // Migration 15: Record provider usage for the usage dashboard
+        Migration {
+            version: 15,
+            description: "create_usage_events_table",
+            sql: include_str!("migrations/usage-events.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 15,
+            description: "create_usage_events_table",
+            sql: include_str!("migrations/down/usage-events.sql"),
+            kind: MigrationKind::Down,
+        },
+        // Migration 16: Organize conversations with tags and folders
+        Migration {
+            version: 16,
+            description: "create_tags_and_folders_tables",
+            sql: include_str!("migrations/conversation-organization.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 16,
+            description: "create_tags_and_folders_tables",
+            sql: include_str!("migrations/down/conversation-organization.sql"),
+            kind: MigrationKind::Down,
+        },
+        // Migration 17: Pin, archive and soft-delete conversations
+        Migration {
+            version: 17,
+            description: "add_conversation_pinned_archived_deleted",
+            sql: include_str!("migrations/conversation-lifecycle.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 17,
+            description: "add_conversation_pinned_archived_deleted",
+            sql: include_str!("migrations/down/conversation-lifecycle.sql"),
+            kind: MigrationKind::Down,
+        },
+        // Migration 18: Keep earlier contents of edited and regenerated messages
+        Migration {
+            version: 18,
+            description: "create_message_revisions_table",
+            sql: include_str!("migrations/message-revisions.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 18,
+            description: "create_message_revisions_table",
+            sql: include_str!("migrations/down/message-revisions.sql"),
+            kind: MigrationKind::Down,
+        },
+        // Migration 19: Persistent background job queue
+        Migration {
+            version: 19,
+            description: "create_jobs_table",
+            sql: include_str!("migrations/jobs.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 19,
+            description: "create_jobs_table",
+            sql: include_str!("migrations/down/jobs.sql"),
+            kind: MigrationKind::Down,
+        },
+        // Migration 20: Named OpenAI-compatible endpoint profiles
+        Migration {
+            version: 20,
+            description: "create_provider_profiles_table",
+            sql: include_str!("migrations/provider-profiles.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 20,
+            description: "create_provider_profiles_table",
+            sql: include_str!("migrations/down/provider-profiles.sql"),
+            kind: MigrationKind::Down,
+        },
+        // Migration 21: Claude CLI session IDs for --resume across launches
+        Migration {
+            version: 21,
+            description: "create_agent_sessions_table",
+            sql: include_str!("migrations/agent-sessions.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 21,
+            description: "create_agent_sessions_table",
+            sql: include_str!("migrations/down/agent-sessions.sql"),
+            kind: MigrationKind::Down,
+        },
+        // Migration 22: Audit log of agent tool calls
+        Migration {
+            version: 22,
+            description: "create_agent_audit_table",
+            sql: include_str!("migrations/agent-audit.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 22,
+            description: "create_agent_audit_table",
+            sql: include_str!("migrations/down/agent-audit.sql"),
+            kind: MigrationKind::Down,
+        },
+        // Migration 23: Scheduled prompts and reminders
+        Migration {
+            version: 23,
+            description: "create_scheduled_tasks_table",
+            sql: include_str!("migrations/scheduled-tasks.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 23,
+            description: "create_scheduled_tasks_table",
+            sql: include_str!("migrations/down/scheduled-tasks.sql"),
+            kind: MigrationKind::Down,
+        },
+        // Migration 24: Meeting sessions with running notes and minutes
+        Migration {
+            version: 24,
+            description: "create_meetings_table",
+            sql: include_str!("migrations/meetings.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 24,
+            description: "create_meetings_table",
+            sql: include_str!("migrations/down/meetings.sql"),
+            kind: MigrationKind::Down,
+        },
+        // Migration 25: Translations of final transcripts
+        Migration {
+            version: 25,
+            description: "add_transcript_translations",
+            sql: include_str!("migrations/transcript-translations.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 25,
+            description: "add_transcript_translations",
+            sql: include_str!("migrations/down/transcript-translations.sql"),
+            kind: MigrationKind::Down,
+        },
+        // Migration 26: Device sync state and system prompt sync ids
+        Migration {
+            version: 26,
+            description: "create_sync_tables",
+            sql: include_str!("migrations/sync.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 26,
+            description: "create_sync_tables",
+            sql: include_str!("migrations/down/sync.sql"),
+            kind: MigrationKind::Down,
+        },
+        // Migration 27: Events from calendar feeds
+        Migration {
+            version: 27,
+            description: "create_calendar_events_table",
+            sql: include_str!("migrations/calendar-events.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 27,
+            description: "create_calendar_events_table",
+            sql: include_str!("migrations/down/calendar-events.sql"),
+            kind: MigrationKind::Down,
+        },
+        // Migration 28: Flag for replies stopped before they finished
+        Migration {
+            version: 28,
+            description: "add_message_truncated",
+            sql: include_str!("migrations/message-truncated.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 28,
+            description: "add_message_truncated",
+            sql: include_str!("migrations/down/message-truncated.sql"),
+            kind: MigrationKind::Down,
+        },
+        // Migration 29: Side-by-side model comparisons
+        Migration {
+            version: 29,
+            description: "create_comparison_tables",
+            sql: include_str!("migrations/model-comparisons.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 29,
+            description: "create_comparison_tables",
+            sql: include_str!("migrations/down/model-comparisons.sql"),
+            kind: MigrationKind::Down,
+        },
+        // Migration 30: Cache of completed responses
+        Migration {
+            version: 30,
+            description: "create_response_cache_table",
+            sql: include_str!("migrations/response-cache.sql"),
+            kind: MigrationKind::Up,
+        },
+        Migration {
+            version: 30,
+            description: "create_response_cache_table",
+            sql: include_str!("migrations/down/response-cache.sql"),
+            kind: MigrationKind::Down,
+        },
     ]
 }
@@ -0,0 +1,324 @@
+//! Edit history for messages (migration 18).
+//!
+//! Editing a prompt or regenerating a response goes through [`revise`],
+//! which files the current content as a revision before replacing it, so
+//! nothing is lost in place. Restoring a revision is itself a change and
+//! keeps the content it replaces as well.
+
+use super::chat::{Message, MessageRole, MessageRow};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tauri::AppHandle;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RevisionKind {
+    /// The user rewrote the message.
+    Edit,
+    /// A new response replaced an assistant message.
+    Regenerate,
+    /// An earlier revision was brought back.
+    Restore,
+}
+
+impl RevisionKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Edit => "edit",
+            Self::Regenerate => "regenerate",
+            Self::Restore => "restore",
+        }
+    }
+
+    fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "edit" => Ok(Self::Edit),
+            "regenerate" => Ok(Self::Regenerate),
+            "restore" => Ok(Self::Restore),
+            other => Err(format!("Unknown revision kind: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageRevision {
+    pub message_id: String,
+    pub revision: i64,
+    /// What the message said before this change.
+    pub content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attached_files: Option<serde_json::Value>,
+    /// The change that replaced `content`.
+    pub kind: RevisionKind,
+    pub created_at: i64,
+}
+
+#[derive(sqlx::FromRow)]
+struct RevisionRow {
+    message_id: String,
+    revision: i64,
+    content: String,
+    attached_files: Option<String>,
+    kind: String,
+    created_at: i64,
+}
+
+impl TryFrom<RevisionRow> for MessageRevision {
+    type Error = String;
+
+    fn try_from(row: RevisionRow) -> Result<Self, Self::Error> {
+        Ok(MessageRevision {
+            message_id: row.message_id,
+            revision: row.revision,
+            content: row.content,
+            attached_files: row
+                .attached_files
+                .and_then(|raw| serde_json::from_str(&raw).ok()),
+            kind: RevisionKind::parse(&row.kind)?,
+            created_at: row.created_at,
+        })
+    }
+}
+
+/// Replace the content of `message_id`, keeping what it said before as a
+/// new revision. Only assistant messages can be regenerated, and only user
+/// and system messages edited. Unchanged content is left as it is.
+pub(crate) async fn revise(
+    pool: &SqlitePool,
+    message_id: &str,
+    content: &str,
+    kind: RevisionKind,
+) -> Result<Message, String> {
+    if content.trim().is_empty() {
+        return Err("Invalid message: content must not be empty".to_string());
+    }
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let current = sqlx::query_as::<_, MessageRow>(
+        "SELECT id, role, content, timestamp, attached_files, truncated FROM messages WHERE id = ?",
+    )
+    .bind(message_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to load message: {}", e))?
+    .ok_or_else(|| format!("Message not found: {}", message_id))?;
+    let current = Message::try_from(current)?;
+
+    match (kind, current.role) {
+        (RevisionKind::Regenerate, MessageRole::User | MessageRole::System) => {
+            return Err("Only assistant messages can be regenerated".to_string());
+        }
+        (RevisionKind::Edit, MessageRole::Assistant) => {
+            return Err("Regenerate assistant messages instead of editing them".to_string());
+        }
+        _ => {}
+    }
+    if current.content == content {
+        return Ok(current);
+    }
+
+    sqlx::query(
+        "INSERT INTO message_revisions
+             (message_id, revision, content, attached_files, kind, created_at)
+         VALUES (?, (SELECT COALESCE(MAX(revision), 0) + 1 FROM message_revisions
+                     WHERE message_id = ?), ?, ?, ?, ?)",
+    )
+    .bind(message_id)
+    .bind(message_id)
+    .bind(&current.content)
+    .bind(
+        current
+            .attached_files
+            .as_ref()
+            .map(|files| files.to_string()),
+    )
+    .bind(kind.as_str())
+    .bind(super::now_millis())
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to save message revision: {}", e))?;
+
+    // An edited or regenerated reply is complete again
+    sqlx::query("UPDATE messages SET content = ?, truncated = 0 WHERE id = ?")
+        .bind(content)
+        .bind(message_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to update message: {}", e))?;
+    // The embedding describes the old text; drop it so it is indexed again
+    sqlx::query("DELETE FROM message_embeddings WHERE message_id = ?")
+        .bind(message_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to update message: {}", e))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit message revision: {}", e))?;
+
+    Ok(Message {
+        content: content.to_string(),
+        truncated: false,
+        ..current
+    })
+}
+
+/// Revisions of `message_id`, newest first.
+pub(crate) async fn list(
+    pool: &SqlitePool,
+    message_id: &str,
+) -> Result<Vec<MessageRevision>, String> {
+    sqlx::query_as::<_, RevisionRow>(
+        "SELECT message_id, revision, content, attached_files, kind, created_at
+         FROM message_revisions WHERE message_id = ? ORDER BY revision DESC",
+    )
+    .bind(message_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load message revisions: {}", e))?
+    .into_iter()
+    .map(MessageRevision::try_from)
+    .collect()
+}
+
+/// Put the content of `revision` back, keeping the current content as a
+/// revision of its own.
+pub(crate) async fn restore(
+    pool: &SqlitePool,
+    message_id: &str,
+    revision: i64,
+) -> Result<Message, String> {
+    let content: Option<String> = sqlx::query_scalar(
+        "SELECT content FROM message_revisions WHERE message_id = ? AND revision = ?",
+    )
+    .bind(message_id)
+    .bind(revision)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to load message revision: {}", e))?;
+    let content = content
+        .ok_or_else(|| format!("Revision {} of message {} not found", revision, message_id))?;
+    revise(pool, message_id, &content, RevisionKind::Restore).await
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// Replace a user message's text, keeping the previous text as a revision.
+#[tauri::command]
+pub async fn edit_message(
+    app: AppHandle,
+    message_id: String,
+    content: String,
+) -> Result<Message, String> {
+    let pool = super::pool(&app).await?;
+    revise(&pool, &message_id, &content, RevisionKind::Edit).await
+}
+
+/// Store a regenerated response in place of an assistant message, keeping
+/// the previous response as a revision.
+#[tauri::command]
+pub async fn regenerate_message(
+    app: AppHandle,
+    message_id: String,
+    content: String,
+) -> Result<Message, String> {
+    let pool = super::pool(&app).await?;
+    revise(&pool, &message_id, &content, RevisionKind::Regenerate).await
+}
+
+/// Earlier versions of a message, newest first.
+#[tauri::command]
+pub async fn list_message_revisions(
+    app: AppHandle,
+    message_id: String,
+) -> Result<Vec<MessageRevision>, String> {
+    let pool = super::pool(&app).await?;
+    list(&pool, &message_id).await
+}
+
+#[tauri::command]
+pub async fn restore_message_revision(
+    app: AppHandle,
+    message_id: String,
+    revision: i64,
+) -> Result<Message, String> {
+    let pool = super::pool(&app).await?;
+    restore(&pool, &message_id, revision).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::chat::{self, Conversation};
+
+    fn message(id: &str, role: MessageRole, content: &str) -> Message {
+        Message {
+            id: id.to_string(),
+            role,
+            content: content.to_string(),
+            timestamp: 1,
+            attached_files: None,
+            truncated: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn edits_keep_earlier_content() {
+        let pool = crate::db::test_pool().await;
+        chat::create(
+            &pool,
+            Conversation {
+                id: "c1".to_string(),
+                title: "Revisions".to_string(),
+                created_at: 1,
+                updated_at: 1,
+                pinned: false,
+                archived_at: None,
+                deleted_at: None,
+                messages: vec![
+                    message("q", MessageRole::User, "What is Rust?"),
+                    message("a", MessageRole::Assistant, "A language."),
+                ],
+            },
+        )
+        .await
+        .unwrap();
+
+        revise(&pool, "q", "What is Rust used for?", RevisionKind::Edit)
+            .await
+            .unwrap();
+        revise(&pool, "q", "What is Rust used for?", RevisionKind::Edit)
+            .await
+            .unwrap();
+        assert!(revise(&pool, "a", "x", RevisionKind::Edit).await.is_err());
+        assert!(revise(&pool, "q", "x", RevisionKind::Regenerate)
+            .await
+            .is_err());
+        revise(&pool, "a", "Systems programming.", RevisionKind::Regenerate)
+            .await
+            .unwrap();
+
+        let revisions = list(&pool, "q").await.unwrap();
+        assert_eq!(revisions.len(), 1, "unchanged content adds no revision");
+        assert_eq!(revisions[0].content, "What is Rust?");
+        assert_eq!(revisions[0].kind, RevisionKind::Edit);
+
+        let restored = restore(&pool, "q", 1).await.unwrap();
+        assert_eq!(restored.content, "What is Rust?");
+        let revisions = list(&pool, "q").await.unwrap();
+        assert_eq!(revisions[0].revision, 2);
+        assert_eq!(revisions[0].content, "What is Rust used for?");
+        assert_eq!(revisions[0].kind, RevisionKind::Restore);
+
+        let answer = chat::get(&pool, "c1").await.unwrap().unwrap().messages;
+        assert_eq!(answer[1].content, "Systems programming.");
+        assert_eq!(list(&pool, "a").await.unwrap()[0].content, "A language.");
+    }
+}
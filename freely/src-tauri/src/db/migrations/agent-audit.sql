@@ -0,0 +1,18 @@
+-- Every tool call made by an agent session, for reviewing what the agent did.
+-- `id` is the CLI's tool_use ID. `files` is a JSON array of the paths the
+-- call named; `status` stays 'pending' until the CLI reports the result.
+CREATE TABLE IF NOT EXISTS agent_audit (
+    id TEXT PRIMARY KEY,
+    conversation_id TEXT NOT NULL,
+    agent_session_id TEXT,
+    tool_name TEXT NOT NULL,
+    arguments TEXT NOT NULL,
+    files TEXT NOT NULL DEFAULT '[]',
+    status TEXT NOT NULL DEFAULT 'pending'
+        CHECK(status IN ('pending', 'success', 'error', 'denied')),
+    result TEXT,
+    started_at INTEGER NOT NULL,
+    finished_at INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS idx_agent_audit_conversation ON agent_audit(conversation_id, started_at);
@@ -0,0 +1,11 @@
+-- Claude Code CLI session IDs per Freely agent session, so reopening a
+-- conversation resumes the CLI's own context with `--resume`. The CLI keeps
+-- sessions per working directory, so the directory the session was started
+-- in is stored alongside.
+CREATE TABLE IF NOT EXISTS agent_sessions (
+    session_id TEXT PRIMARY KEY,
+    agent_session_id TEXT NOT NULL,
+    working_directory TEXT,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
@@ -0,0 +1,17 @@
+-- Index of message attachments. The files live under `attachments/` in the
+-- app's local data directory, named by content hash, so identical uploads
+-- share one file. `message_id` is NULL for attachments added before their
+-- message was saved, and after the message is deleted.
+CREATE TABLE IF NOT EXISTS attachments (
+    id TEXT PRIMARY KEY,
+    message_id TEXT,
+    hash TEXT NOT NULL,
+    mime_type TEXT NOT NULL,
+    size INTEGER NOT NULL CHECK(size >= 0),
+    file_name TEXT,
+    created_at INTEGER NOT NULL,
+    FOREIGN KEY (message_id) REFERENCES messages(id) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_attachments_message_id ON attachments(message_id);
+CREATE INDEX IF NOT EXISTS idx_attachments_hash ON attachments(hash);
@@ -0,0 +1,21 @@
+-- Upcoming events from the user's ICS calendar feeds (integrations::calendar).
+-- Recurring events are stored one row per occurrence, keyed by feed, UID and
+-- start, so `reminded_at` and `meeting_id` survive refreshes of the feed.
+CREATE TABLE IF NOT EXISTS calendar_events (
+    id TEXT PRIMARY KEY,
+    feed_id TEXT NOT NULL,
+    uid TEXT NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT,
+    location TEXT,
+    url TEXT,
+    starts_at INTEGER NOT NULL,
+    ends_at INTEGER NOT NULL,
+    all_day INTEGER NOT NULL DEFAULT 0 CHECK(all_day IN (0, 1)),
+    reminded_at INTEGER,
+    meeting_id TEXT,
+    fetched_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_calendar_events_starts_at ON calendar_events(starts_at);
+CREATE INDEX IF NOT EXISTS idx_calendar_events_feed_id ON calendar_events(feed_id);
@@ -0,0 +1,35 @@
+-- Full-text index over message content.
+-- External-content FTS5 table: the text lives in `messages`, the index is keyed
+-- by the implicit rowid of each message row.
+CREATE VIRTUAL TABLE IF NOT EXISTS messages_fts USING fts5(
+    content,
+    content='messages',
+    content_rowid='rowid',
+    tokenize='unicode61 remove_diacritics 2'
+);
+
+-- Index messages that existed before this migration
+INSERT INTO messages_fts(messages_fts) VALUES ('rebuild');
+
+-- Triggers to keep the index in sync with the messages table
+CREATE TRIGGER IF NOT EXISTS messages_fts_after_insert
+AFTER INSERT ON messages
+FOR EACH ROW
+BEGIN
+    INSERT INTO messages_fts(rowid, content) VALUES (NEW.rowid, NEW.content);
+END;
+
+CREATE TRIGGER IF NOT EXISTS messages_fts_after_delete
+AFTER DELETE ON messages
+FOR EACH ROW
+BEGIN
+    INSERT INTO messages_fts(messages_fts, rowid, content) VALUES ('delete', OLD.rowid, OLD.content);
+END;
+
+CREATE TRIGGER IF NOT EXISTS messages_fts_after_update
+AFTER UPDATE OF content ON messages
+FOR EACH ROW
+BEGIN
+    INSERT INTO messages_fts(messages_fts, rowid, content) VALUES ('delete', OLD.rowid, OLD.content);
+    INSERT INTO messages_fts(rowid, content) VALUES (NEW.rowid, NEW.content);
+END;
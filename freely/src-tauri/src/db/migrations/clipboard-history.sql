@@ -0,0 +1,9 @@
+-- Clipboard history, opt-in. Entries are encrypted before they are written,
+-- so `content` holds nonce || AES-256-GCM ciphertext rather than text.
+CREATE TABLE IF NOT EXISTS clipboard_history (
+    id TEXT PRIMARY KEY,
+    content BLOB NOT NULL,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_clipboard_history_created_at ON clipboard_history(created_at DESC);
@@ -0,0 +1,8 @@
+-- Pinning, archiving and soft delete for conversations. Deleting sets
+-- `deleted_at` and keeps the rows until they are purged, either explicitly
+-- or by the daily purge once they are older than the `trash` setting allows.
+ALTER TABLE conversations ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0 CHECK(pinned IN (0, 1));
+ALTER TABLE conversations ADD COLUMN archived_at INTEGER;
+ALTER TABLE conversations ADD COLUMN deleted_at INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_conversations_deleted_at ON conversations(deleted_at);
@@ -0,0 +1,16 @@
+-- Rolling summaries of older conversation history, used for context compaction.
+-- Each summary folds in the previous one, so the newest row per conversation
+-- covers every message up to covers_until.
+CREATE TABLE IF NOT EXISTS conversation_summaries (
+    id TEXT PRIMARY KEY,
+    conversation_id TEXT NOT NULL,
+    summary TEXT NOT NULL,
+    covers_until INTEGER NOT NULL,
+    message_count INTEGER NOT NULL CHECK(message_count > 0),
+    token_count INTEGER NOT NULL,
+    model TEXT,
+    created_at INTEGER NOT NULL,
+    FOREIGN KEY (conversation_id) REFERENCES conversations(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_conversation_summaries_covers ON conversation_summaries(conversation_id, covers_until DESC);
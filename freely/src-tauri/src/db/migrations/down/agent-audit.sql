@@ -0,0 +1,3 @@
+-- Revert migration 22
+DROP INDEX IF EXISTS idx_agent_audit_conversation;
+DROP TABLE IF EXISTS agent_audit;
@@ -0,0 +1,2 @@
+-- Revert migration 21
+DROP TABLE IF EXISTS agent_sessions;
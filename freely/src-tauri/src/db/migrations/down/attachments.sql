@@ -0,0 +1,2 @@
+-- Revert migration 13
+DROP TABLE IF EXISTS attachments;
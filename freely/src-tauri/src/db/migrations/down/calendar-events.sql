@@ -0,0 +1,4 @@
+-- Revert migration 27
+DROP INDEX IF EXISTS idx_calendar_events_feed_id;
+DROP INDEX IF EXISTS idx_calendar_events_starts_at;
+DROP TABLE IF EXISTS calendar_events;
@@ -0,0 +1,5 @@
+-- Revert migration 2
+DROP TRIGGER IF EXISTS update_conversation_timestamp_on_message_update;
+DROP TRIGGER IF EXISTS update_conversation_timestamp_on_message_insert;
+DROP TABLE IF EXISTS messages;
+DROP TABLE IF EXISTS conversations;
@@ -0,0 +1,5 @@
+-- Revert migration 3
+DROP TRIGGER IF EXISTS messages_fts_after_update;
+DROP TRIGGER IF EXISTS messages_fts_after_delete;
+DROP TRIGGER IF EXISTS messages_fts_after_insert;
+DROP TABLE IF EXISTS messages_fts;
@@ -0,0 +1,2 @@
+-- Revert migration 12
+DROP TABLE IF EXISTS clipboard_history;
@@ -0,0 +1,7 @@
+-- Revert migration 17
+-- Soft-deleted conversations would reappear once the column is gone
+DELETE FROM conversations WHERE deleted_at IS NOT NULL;
+DROP INDEX IF EXISTS idx_conversations_deleted_at;
+ALTER TABLE conversations DROP COLUMN deleted_at;
+ALTER TABLE conversations DROP COLUMN archived_at;
+ALTER TABLE conversations DROP COLUMN pinned;
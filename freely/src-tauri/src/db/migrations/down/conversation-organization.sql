@@ -0,0 +1,8 @@
+-- Revert migration 16
+DROP INDEX IF EXISTS idx_conversation_folders_folder_id;
+DROP TABLE IF EXISTS conversation_folders;
+DROP INDEX IF EXISTS idx_folders_parent_id;
+DROP TABLE IF EXISTS folders;
+DROP INDEX IF EXISTS idx_conversation_tags_tag_id;
+DROP TABLE IF EXISTS conversation_tags;
+DROP TABLE IF EXISTS tags;
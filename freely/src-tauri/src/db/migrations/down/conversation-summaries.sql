@@ -0,0 +1,2 @@
+-- Revert migration 6
+DROP TABLE IF EXISTS conversation_summaries;
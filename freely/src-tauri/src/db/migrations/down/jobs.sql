@@ -0,0 +1,3 @@
+-- Revert migration 19
+DROP INDEX IF EXISTS idx_jobs_status;
+DROP TABLE IF EXISTS jobs;
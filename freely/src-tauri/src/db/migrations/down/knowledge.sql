@@ -0,0 +1,4 @@
+-- Revert migration 8
+DROP TABLE IF EXISTS knowledge_chunks;
+DROP TABLE IF EXISTS knowledge_files;
+DROP TABLE IF EXISTS knowledge_folders;
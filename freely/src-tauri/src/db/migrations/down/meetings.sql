@@ -0,0 +1,3 @@
+-- Revert migration 24
+DROP INDEX IF EXISTS idx_meetings_started_at;
+DROP TABLE IF EXISTS meetings;
@@ -0,0 +1,2 @@
+-- Revert migration 7
+DROP TABLE IF EXISTS message_embeddings;
@@ -0,0 +1,2 @@
+-- Revert migration 18
+DROP TABLE IF EXISTS message_revisions;
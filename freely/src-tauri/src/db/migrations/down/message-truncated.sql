@@ -0,0 +1,2 @@
+-- Revert migration 28
+ALTER TABLE messages DROP COLUMN truncated;
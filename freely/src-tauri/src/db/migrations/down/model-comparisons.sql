@@ -0,0 +1,4 @@
+-- Revert migration 29
+DROP INDEX IF EXISTS idx_comparisons_created_at;
+DROP TABLE IF EXISTS comparison_results;
+DROP TABLE IF EXISTS comparisons;
@@ -0,0 +1,2 @@
+-- Revert migration 9
+DROP TABLE IF EXISTS projects;
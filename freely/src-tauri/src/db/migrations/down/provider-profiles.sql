@@ -0,0 +1,2 @@
+-- Revert migration 20
+DROP TABLE IF EXISTS provider_profiles;
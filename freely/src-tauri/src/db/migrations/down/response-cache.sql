@@ -0,0 +1,3 @@
+-- Revert migration 30
+DROP INDEX IF EXISTS idx_response_cache_used_at;
+DROP TABLE IF EXISTS response_cache;
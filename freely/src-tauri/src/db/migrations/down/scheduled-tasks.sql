@@ -0,0 +1,3 @@
+-- Revert migration 23
+DROP INDEX IF EXISTS idx_scheduled_tasks_next_run;
+DROP TABLE IF EXISTS scheduled_tasks;
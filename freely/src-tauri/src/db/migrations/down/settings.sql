@@ -0,0 +1,2 @@
+-- Revert migration 5
+DROP TABLE IF EXISTS settings;
@@ -0,0 +1,8 @@
+-- Revert migration 26
+DROP INDEX IF EXISTS idx_sync_conflicts_created_at;
+DROP TABLE IF EXISTS sync_conflicts;
+DROP TABLE IF EXISTS sync_devices;
+DROP TABLE IF EXISTS sync_records;
+DROP TRIGGER IF EXISTS assign_system_prompt_sync_id;
+DROP INDEX IF EXISTS idx_system_prompts_sync_id;
+ALTER TABLE system_prompts DROP COLUMN sync_id;
@@ -0,0 +1,4 @@
+-- Revert migration 14
+DROP TRIGGER IF EXISTS record_system_prompt_version_on_update;
+DROP TRIGGER IF EXISTS record_system_prompt_version_on_insert;
+DROP TABLE IF EXISTS system_prompt_versions;
@@ -0,0 +1,4 @@
+-- Revert migration 1
+DROP TRIGGER IF EXISTS update_system_prompts_timestamp;
+DROP INDEX IF EXISTS idx_system_prompts_name;
+DROP TABLE IF EXISTS system_prompts;
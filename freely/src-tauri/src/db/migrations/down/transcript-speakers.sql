@@ -0,0 +1,2 @@
+-- Revert migration 10
+ALTER TABLE transcripts DROP COLUMN speaker_label;
@@ -0,0 +1,3 @@
+-- Revert migration 25
+ALTER TABLE transcripts DROP COLUMN translation_language;
+ALTER TABLE transcripts DROP COLUMN translated_text;
@@ -0,0 +1,2 @@
+-- Revert migration 4
+DROP TABLE IF EXISTS transcripts;
@@ -0,0 +1,3 @@
+-- Revert migration 15
+DROP INDEX IF EXISTS idx_usage_events_created_at;
+DROP TABLE IF EXISTS usage_events;
@@ -0,0 +1,22 @@
+-- Background work that outlives a single command: indexing, summarization,
+-- backups and model downloads. `payload` is the JSON job spec and `kind`
+-- its type, kept separately for filtering. Jobs still `running` when the
+-- app quits are queued again on the next start.
+CREATE TABLE IF NOT EXISTS jobs (
+    id TEXT PRIMARY KEY,
+    kind TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'queued'
+        CHECK(status IN ('queued', 'running', 'completed', 'failed', 'cancelled')),
+    progress REAL,
+    message TEXT,
+    error TEXT,
+    result TEXT,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    cancel_requested INTEGER NOT NULL DEFAULT 0,
+    created_at INTEGER NOT NULL,
+    started_at INTEGER,
+    finished_at INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS idx_jobs_status ON jobs(status, created_at);
@@ -0,0 +1,35 @@
+-- Folders the user has added as knowledge sources, the files indexed from
+-- them and their embedded chunks. Vectors use the same encoding as
+-- message_embeddings; a file is re-chunked when its size, mtime or the
+-- embedding model changes.
+CREATE TABLE IF NOT EXISTS knowledge_folders (
+    id TEXT PRIMARY KEY,
+    path TEXT NOT NULL UNIQUE,
+    name TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    indexed_at INTEGER
+);
+
+CREATE TABLE IF NOT EXISTS knowledge_files (
+    id TEXT PRIMARY KEY,
+    folder_id TEXT NOT NULL,
+    path TEXT NOT NULL UNIQUE,
+    size INTEGER NOT NULL,
+    modified_at INTEGER NOT NULL,
+    model TEXT NOT NULL,
+    indexed_at INTEGER NOT NULL,
+    FOREIGN KEY (folder_id) REFERENCES knowledge_folders(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS knowledge_chunks (
+    id TEXT PRIMARY KEY,
+    file_id TEXT NOT NULL,
+    start_line INTEGER NOT NULL,
+    end_line INTEGER NOT NULL,
+    content TEXT NOT NULL,
+    vector BLOB NOT NULL,
+    FOREIGN KEY (file_id) REFERENCES knowledge_files(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_knowledge_files_folder ON knowledge_files(folder_id);
+CREATE INDEX IF NOT EXISTS idx_knowledge_chunks_file ON knowledge_chunks(file_id);
@@ -0,0 +1,20 @@
+-- Meeting sessions. The transcript lives in `transcripts` under the
+-- meeting's conversation; `notes` is the running summary and `minutes` the
+-- document written when the meeting ends. `options` is the JSON
+-- MeetingOptions the session was started with.
+CREATE TABLE IF NOT EXISTS meetings (
+    id TEXT PRIMARY KEY,
+    conversation_id TEXT NOT NULL,
+    title TEXT NOT NULL,
+    status TEXT NOT NULL CHECK(status IN ('recording', 'finishing', 'completed', 'failed')),
+    options TEXT NOT NULL,
+    notes TEXT,
+    minutes TEXT,
+    error TEXT,
+    started_at INTEGER NOT NULL,
+    ended_at INTEGER,
+    updated_at INTEGER NOT NULL,
+    FOREIGN KEY (conversation_id) REFERENCES conversations(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_meetings_started_at ON meetings(started_at DESC);
@@ -0,0 +1,15 @@
+-- One embedding vector per message for semantic search. Vectors are
+-- L2-normalised little-endian f32 blobs; re-embedding with another model
+-- replaces the row.
+CREATE TABLE IF NOT EXISTS message_embeddings (
+    message_id TEXT PRIMARY KEY,
+    conversation_id TEXT NOT NULL,
+    model TEXT NOT NULL,
+    dimensions INTEGER NOT NULL CHECK(dimensions > 0),
+    vector BLOB NOT NULL,
+    created_at INTEGER NOT NULL,
+    FOREIGN KEY (message_id) REFERENCES messages(id) ON DELETE CASCADE,
+    FOREIGN KEY (conversation_id) REFERENCES conversations(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_message_embeddings_model ON message_embeddings(model);
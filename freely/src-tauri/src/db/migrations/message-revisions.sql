@@ -0,0 +1,13 @@
+-- Earlier contents of edited and regenerated messages. The current content
+-- stays in `messages`; each row here is what a message said before the
+-- change described by `kind` replaced it, numbered from 1 per message.
+CREATE TABLE IF NOT EXISTS message_revisions (
+    message_id TEXT NOT NULL,
+    revision INTEGER NOT NULL CHECK(revision > 0),
+    content TEXT NOT NULL,
+    attached_files TEXT,
+    kind TEXT NOT NULL CHECK(kind IN ('edit', 'regenerate', 'restore')),
+    created_at INTEGER NOT NULL,
+    PRIMARY KEY (message_id, revision),
+    FOREIGN KEY (message_id) REFERENCES messages(id) ON DELETE CASCADE
+);
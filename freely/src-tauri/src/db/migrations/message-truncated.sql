@@ -0,0 +1,2 @@
+-- Assistant replies stopped with `abort_completion` before they finished.
+ALTER TABLE messages ADD COLUMN truncated INTEGER NOT NULL DEFAULT 0;
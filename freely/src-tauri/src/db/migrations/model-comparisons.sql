@@ -0,0 +1,27 @@
+-- One prompt sent to several models side by side (providers::compare), with
+-- each model's answer. `winner` is the position the user preferred.
+CREATE TABLE IF NOT EXISTS comparisons (
+    id TEXT PRIMARY KEY,
+    prompt TEXT NOT NULL,
+    system_prompt TEXT,
+    winner INTEGER,
+    created_at INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS comparison_results (
+    comparison_id TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    request_id TEXT NOT NULL,
+    provider TEXT NOT NULL,
+    model TEXT NOT NULL,
+    text TEXT NOT NULL DEFAULT '',
+    finish_reason TEXT,
+    error TEXT,
+    input_tokens INTEGER,
+    output_tokens INTEGER,
+    latency_ms INTEGER NOT NULL,
+    PRIMARY KEY (comparison_id, position),
+    FOREIGN KEY (comparison_id) REFERENCES comparisons(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_comparisons_created_at ON comparisons(created_at DESC);
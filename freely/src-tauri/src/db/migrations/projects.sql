@@ -0,0 +1,10 @@
+-- Project directories registered as agent workspaces. Each has its own
+-- .claude/ config inside the directory; the active project is kept in the
+-- settings table under "active_project_id".
+CREATE TABLE IF NOT EXISTS projects (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    path TEXT NOT NULL UNIQUE,
+    created_at INTEGER NOT NULL,
+    last_opened_at INTEGER
+);
@@ -0,0 +1,19 @@
+-- Named OpenAI-compatible endpoints (LiteLLM, vLLM, LM Studio, corporate
+-- gateways). `models` is a JSON array of model names offered by the
+-- endpoint and `headers` a JSON object sent with every request. The API
+-- key itself lives in the secrets store under `api_key_name`; `auth_scheme`
+-- says how it is sent: as a bearer token, in the `auth_header` header, or
+-- not at all.
+CREATE TABLE IF NOT EXISTS provider_profiles (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+    base_url TEXT NOT NULL,
+    models TEXT NOT NULL DEFAULT '[]',
+    headers TEXT NOT NULL DEFAULT '{}',
+    auth_scheme TEXT NOT NULL DEFAULT 'bearer'
+        CHECK(auth_scheme IN ('bearer', 'header', 'none')),
+    auth_header TEXT,
+    api_key_name TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
@@ -0,0 +1,16 @@
+-- Completed responses keyed by a hash of the model and normalized prompt
+-- (providers::cache), answered again for repeated prompts. Bounded by the
+-- `response_cache` setting; the least recently used rows go first.
+CREATE TABLE IF NOT EXISTS response_cache (
+    key TEXT PRIMARY KEY,
+    provider TEXT NOT NULL,
+    model TEXT NOT NULL,
+    text TEXT NOT NULL,
+    response_model TEXT,
+    finish_reason TEXT,
+    hits INTEGER NOT NULL DEFAULT 0,
+    created_at INTEGER NOT NULL,
+    used_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_response_cache_used_at ON response_cache(used_at DESC);
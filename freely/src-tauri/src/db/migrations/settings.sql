@@ -0,0 +1,6 @@
+-- Create settings table for app preferences that Rust needs at startup
+CREATE TABLE IF NOT EXISTS settings (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL,
+    updated_at INTEGER NOT NULL
+);
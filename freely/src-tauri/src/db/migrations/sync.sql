@@ -0,0 +1,48 @@
+-- Device sync through a shared folder (sync module). System prompts get a
+-- random `sync_id` that identifies them across devices, since their
+-- integer ids are only unique locally.
+ALTER TABLE system_prompts ADD COLUMN sync_id TEXT;
+UPDATE system_prompts SET sync_id = lower(hex(randomblob(16))) WHERE sync_id IS NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_system_prompts_sync_id ON system_prompts(sync_id);
+
+CREATE TRIGGER IF NOT EXISTS assign_system_prompt_sync_id
+AFTER INSERT ON system_prompts
+FOR EACH ROW
+WHEN NEW.sync_id IS NULL
+BEGIN
+    UPDATE system_prompts SET sync_id = lower(hex(randomblob(16))) WHERE id = NEW.id;
+END;
+
+-- The last version of each record exchanged with the sync folder, so only
+-- records changed since are exported.
+CREATE TABLE IF NOT EXISTS sync_records (
+    kind TEXT NOT NULL CHECK(kind IN ('conversation', 'prompt', 'setting')),
+    record_id TEXT NOT NULL,
+    updated_at INTEGER NOT NULL,
+    fingerprint TEXT NOT NULL,
+    deleted INTEGER NOT NULL DEFAULT 0 CHECK(deleted IN (0, 1)),
+    PRIMARY KEY (kind, record_id)
+);
+
+-- Change sets read from (or, for this device, written to) the sync folder.
+CREATE TABLE IF NOT EXISTS sync_devices (
+    device_id TEXT PRIMARY KEY,
+    device_name TEXT NOT NULL,
+    last_seq INTEGER NOT NULL DEFAULT 0,
+    synced_at INTEGER NOT NULL
+);
+
+-- Records changed on two devices; the losing version is kept in `discarded`.
+CREATE TABLE IF NOT EXISTS sync_conflicts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kind TEXT NOT NULL,
+    record_id TEXT NOT NULL,
+    device_name TEXT NOT NULL,
+    kept TEXT NOT NULL CHECK(kept IN ('local', 'remote')),
+    local_updated_at INTEGER,
+    remote_updated_at INTEGER NOT NULL,
+    discarded TEXT,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_sync_conflicts_created_at ON sync_conflicts(created_at DESC);
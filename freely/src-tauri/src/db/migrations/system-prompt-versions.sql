@@ -0,0 +1,43 @@
+-- History of every saved state of each system prompt, so an overwritten
+-- prompt can be restored. Triggers record versions, which covers every
+-- writer, including the frontend's direct SQL.
+CREATE TABLE IF NOT EXISTS system_prompt_versions (
+    prompt_id INTEGER NOT NULL,
+    version INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    prompt TEXT NOT NULL,
+    created_at TEXT DEFAULT (datetime('now')) NOT NULL,
+    PRIMARY KEY (prompt_id, version),
+    FOREIGN KEY (prompt_id) REFERENCES system_prompts(id) ON DELETE CASCADE
+);
+
+-- Existing prompts start at version 1
+INSERT OR IGNORE INTO system_prompt_versions (prompt_id, version, name, prompt, created_at)
+SELECT id, 1, name, prompt, updated_at FROM system_prompts;
+
+CREATE TRIGGER IF NOT EXISTS record_system_prompt_version_on_insert
+AFTER INSERT ON system_prompts
+FOR EACH ROW
+BEGIN
+    INSERT INTO system_prompt_versions (prompt_id, version, name, prompt)
+    VALUES (NEW.id, 1, NEW.name, NEW.prompt);
+END;
+
+-- Only real edits create a version; the updated_at trigger doesn't. The
+-- oldest versions beyond the newest 50 are dropped.
+CREATE TRIGGER IF NOT EXISTS record_system_prompt_version_on_update
+AFTER UPDATE OF name, prompt ON system_prompts
+FOR EACH ROW
+WHEN OLD.name IS NOT NEW.name OR OLD.prompt IS NOT NEW.prompt
+BEGIN
+    INSERT INTO system_prompt_versions (prompt_id, version, name, prompt)
+    VALUES (
+        NEW.id,
+        COALESCE((SELECT MAX(version) FROM system_prompt_versions WHERE prompt_id = NEW.id), 0) + 1,
+        NEW.name,
+        NEW.prompt
+    );
+    DELETE FROM system_prompt_versions
+    WHERE prompt_id = NEW.id
+      AND version <= (SELECT MAX(version) FROM system_prompt_versions WHERE prompt_id = NEW.id) - 50;
+END;
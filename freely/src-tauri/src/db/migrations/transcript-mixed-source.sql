@@ -0,0 +1,24 @@
+-- Allow transcripts of the mixed microphone + system audio stream. SQLite
+-- can't alter a CHECK constraint, so the table is rebuilt.
+CREATE TABLE transcripts_new (
+    id TEXT PRIMARY KEY,
+    conversation_id TEXT,
+    source TEXT NOT NULL CHECK(source IN ('microphone', 'system_audio', 'mixed')),
+    text TEXT NOT NULL,
+    started_at INTEGER NOT NULL,
+    ended_at INTEGER NOT NULL,
+    confidence REAL CHECK(confidence IS NULL OR (confidence >= 0 AND confidence <= 1)),
+    created_at INTEGER NOT NULL,
+    speaker_label TEXT,
+    CHECK(ended_at >= started_at),
+    FOREIGN KEY (conversation_id) REFERENCES conversations(id) ON DELETE CASCADE
+);
+
+INSERT INTO transcripts_new (id, conversation_id, source, text, started_at, ended_at, confidence, created_at, speaker_label)
+SELECT id, conversation_id, source, text, started_at, ended_at, confidence, created_at, speaker_label FROM transcripts;
+
+DROP TABLE transcripts;
+ALTER TABLE transcripts_new RENAME TO transcripts;
+
+CREATE INDEX IF NOT EXISTS idx_transcripts_conversation_started ON transcripts(conversation_id, started_at ASC);
+CREATE INDEX IF NOT EXISTS idx_transcripts_started_at ON transcripts(started_at DESC);
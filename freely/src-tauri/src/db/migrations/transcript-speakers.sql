@@ -0,0 +1,3 @@
+-- Speaker labels ("Speaker 1", or a name the user gave) for diarized
+-- system-audio transcripts. NULL when the speaker is unknown.
+ALTER TABLE transcripts ADD COLUMN speaker_label TEXT;
@@ -0,0 +1,5 @@
+-- Translations of final transcripts (stt::translate). Both stay NULL until
+-- a translation is made; `translation_language` is the target it was made
+-- for, as configured then.
+ALTER TABLE transcripts ADD COLUMN translated_text TEXT;
+ALTER TABLE transcripts ADD COLUMN translation_language TEXT;
@@ -0,0 +1,17 @@
+-- Create transcripts table for speech-to-text output
+CREATE TABLE IF NOT EXISTS transcripts (
+    id TEXT PRIMARY KEY,
+    conversation_id TEXT,
+    source TEXT NOT NULL CHECK(source IN ('microphone', 'system_audio')),
+    text TEXT NOT NULL,
+    started_at INTEGER NOT NULL,
+    ended_at INTEGER NOT NULL,
+    confidence REAL CHECK(confidence IS NULL OR (confidence >= 0 AND confidence <= 1)),
+    created_at INTEGER NOT NULL,
+    CHECK(ended_at >= started_at),
+    FOREIGN KEY (conversation_id) REFERENCES conversations(id) ON DELETE CASCADE
+);
+
+-- Transcripts are read back in capture order, usually per conversation
+CREATE INDEX IF NOT EXISTS idx_transcripts_conversation_started ON transcripts(conversation_id, started_at ASC);
+CREATE INDEX IF NOT EXISTS idx_transcripts_started_at ON transcripts(started_at DESC);
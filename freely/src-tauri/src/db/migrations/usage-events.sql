@@ -0,0 +1,18 @@
+-- One row per completed provider request, for the usage dashboard.
+-- `estimated` marks token counts computed locally because the provider
+-- didn't report usage; `cost_usd` is NULL when the model has no known price.
+CREATE TABLE IF NOT EXISTS usage_events (
+    id TEXT PRIMARY KEY,
+    request_id TEXT,
+    provider TEXT NOT NULL,
+    model TEXT NOT NULL,
+    source TEXT NOT NULL,
+    input_tokens INTEGER NOT NULL CHECK(input_tokens >= 0),
+    output_tokens INTEGER NOT NULL CHECK(output_tokens >= 0),
+    estimated INTEGER NOT NULL DEFAULT 0 CHECK(estimated IN (0, 1)),
+    latency_ms INTEGER NOT NULL CHECK(latency_ms >= 0),
+    cost_usd REAL,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_usage_events_created_at ON usage_events(created_at);
@@ -1,3 +1,24 @@
+pub mod agent_audit;
+pub mod agent_sessions;
+pub mod attachments;
+pub mod backup;
+pub mod chat;
+pub mod cipher;
+pub mod clipboard;
+pub mod folders;
+pub mod legacy;
+pub mod message_revisions;
 mod main;
+mod pool;
+pub mod projects;
+pub mod schema;
+pub mod search;
+pub mod settings;
+pub mod summaries;
+pub mod system_prompts;
+pub mod tags;
+pub mod transcripts;
+pub mod usage;
 
 pub use main::*;
+pub use pool::*;
@@ -0,0 +1,180 @@
+use super::cipher::{self, DatabaseKey};
+use futures_util::future::BoxFuture;
+use sqlx::error::BoxDynError;
+use sqlx::migrate::{MigrationSource, MigrationType, Migrator};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::{Pool, Sqlite};
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_sql::{DbInstances, DbPool, Migration, MigrationKind};
+use tracing::warn;
+
+/// Connection string shared by the SQL plugin and the Rust-side commands.
+pub const DB_URL: &str = "sqlite:freely.db";
+
+/// The pool Rust commands use. It's also registered with `tauri-plugin-sql`
+/// for the frontend, except while the app is locked.
+#[derive(Default)]
+pub struct OpenDatabase(tokio::sync::RwLock<Option<Pool<Sqlite>>>);
+
+/// Returns the app's SQLite pool.
+///
+/// [`open_database`] opens [`DB_URL`] at startup and runs the migrations, so
+/// Rust commands and the frontend (through the plugin) share one pool.
+pub async fn pool(app: &AppHandle) -> Result<Pool<Sqlite>, String> {
+    app.state::<OpenDatabase>()
+        .0
+        .read()
+        .await
+        .clone()
+        .ok_or_else(|| "Database is not loaded yet".to_string())
+}
+
+/// [`super::migrations`] in the form sqlx's migrator reads, as the SQL
+/// plugin would pass them.
+struct Migrations(Vec<Migration>);
+
+impl MigrationSource<'static> for Migrations {
+    fn resolve(self) -> BoxFuture<'static, Result<Vec<sqlx::migrate::Migration>, BoxDynError>> {
+        Box::pin(async move {
+            Ok(self
+                .0
+                .into_iter()
+                .map(|m| {
+                    let kind = match m.kind {
+                        MigrationKind::Up => MigrationType::ReversibleUp,
+                        MigrationKind::Down => MigrationType::ReversibleDown,
+                    };
+                    sqlx::migrate::Migration::new(
+                        m.version,
+                        m.description.into(),
+                        kind,
+                        m.sql.into(),
+                        false,
+                    )
+                })
+                .collect())
+        })
+    }
+}
+
+/// Open the database at `path`, keyed when `key` is given, and bring it up
+/// to the latest migration.
+pub(crate) async fn connect(
+    path: &Path,
+    key: Option<&DatabaseKey>,
+) -> Result<Pool<Sqlite>, String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create data directory: {}", e))?;
+    }
+    let mut options = SqliteConnectOptions::new()
+        .filename(path)
+        .create_if_missing(true);
+    if let Some(key) = key {
+        options = options.pragma("key", key.pragma());
+    }
+    let pool = SqlitePoolOptions::new()
+        .connect_with(options)
+        .await
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+
+    let migrator = Migrator::new(Migrations(super::migrations()))
+        .await
+        .map_err(|e| format!("Failed to load migrations: {}", e))?;
+    migrator
+        .run(&pool)
+        .await
+        .map_err(|e| format!("Failed to migrate database: {}", e))?;
+    Ok(pool)
+}
+
+/// Open `path` and make it the app's pool.
+pub(crate) async fn register_pool(
+    app: &AppHandle,
+    path: &Path,
+    key: Option<&DatabaseKey>,
+) -> Result<(), String> {
+    let pool = connect(path, key).await?;
+    cipher::set_active_key(key.cloned());
+    *app.state::<OpenDatabase>().0.write().await = Some(pool);
+    sync_frontend_access(app).await
+}
+
+/// Register the pool with the SQL plugin while the app is unlocked, or
+/// withdraw it so the webview can't query the database. The lock state is
+/// read under the plugin's lock, so racing calls settle on the latest one.
+pub(crate) async fn sync_frontend_access(app: &AppHandle) -> Result<(), String> {
+    let instances = app.state::<DbInstances>();
+    let mut instances = instances.0.write().await;
+    if !crate::lock::is_locked() {
+        instances.insert(DB_URL.to_string(), DbPool::Sqlite(pool(app).await?));
+    } else {
+        instances.remove(DB_URL);
+    }
+    Ok(())
+}
+
+/// Unregister and close the pool, e.g. before its file is replaced.
+/// Commands fail with "not loaded" until it is registered again.
+pub(crate) async fn close_pool(app: &AppHandle) {
+    app.state::<DbInstances>().0.write().await.remove(DB_URL);
+    let removed = app.state::<OpenDatabase>().0.write().await.take();
+    if let Some(pool) = removed {
+        pool.close().await;
+    }
+}
+
+/// Open the app database before anything reads it. Called once from
+/// `setup`, after the SQL plugin is initialised and the legacy database
+/// migrated. An encrypted database is opened with the key from the
+/// credential store.
+pub fn open_database(app: &AppHandle) -> Result<(), String> {
+    let path = super::backup::database_path(app)?;
+    tauri::async_runtime::block_on(async {
+        let key = if cipher::is_encrypted(&path) {
+            Some(cipher::database_key(app).await?)
+        } else {
+            None
+        };
+        register_pool(app, &path, key.as_ref()).await?;
+
+        let pool = pool(app).await?;
+        let stored: Option<serde_json::Value> =
+            super::settings::get(&pool, cipher::SETTING_KEY).await?;
+        if key.is_none() && stored.is_some() {
+            warn!("Database was encrypted before but is now plaintext; was a backup restored?");
+        }
+        Ok(())
+    })
+}
+
+/// Current time in milliseconds since the Unix epoch, matching the
+/// `Date.now()` values the frontend stores in timestamp columns.
+pub fn now_millis() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// In-memory database with every migration applied, for unit tests.
+#[cfg(test)]
+pub(crate) async fn test_pool() -> Pool<Sqlite> {
+    let pool = sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .expect("failed to open in-memory database");
+
+    for migration in super::migrations() {
+        if matches!(migration.kind, tauri_plugin_sql::MigrationKind::Up) {
+            sqlx::raw_sql(migration.sql)
+                .execute(&pool)
+                .await
+                .unwrap_or_else(|e| panic!("migration {} failed: {}", migration.version, e));
+        }
+    }
+
+    pool
+}
@@ -0,0 +1,216 @@
+//! Project directories the agent can work in (migration 9).
+//!
+//! Registering a project initializes a `.claude/` config inside it, so each
+//! project gets its own `CLAUDE.md` and `settings.json`. The active project
+//! becomes the agent's working directory whenever a request doesn't name
+//! one explicitly.
+
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::path::PathBuf;
+use tauri::AppHandle;
+
+pub(crate) const ACTIVE_PROJECT_KEY: &str = "active_project_id";
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Project {
+    pub id: String,
+    pub name: String,
+    pub path: String,
+    pub created_at: i64,
+    pub last_opened_at: Option<i64>,
+    #[sqlx(skip)]
+    pub is_active: bool,
+}
+
+pub(crate) async fn insert(pool: &SqlitePool, name: &str, path: &str) -> Result<Project, String> {
+    if name.trim().is_empty() {
+        return Err("Invalid project: name must not be empty".to_string());
+    }
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM projects WHERE path = ?)")
+        .bind(path)
+        .fetch_one(pool)
+        .await
+        .map_err(|e| format!("Failed to check projects: {}", e))?;
+    if exists {
+        return Err(format!("{} is already a project", path));
+    }
+
+    let project = Project {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: name.trim().to_string(),
+        path: path.to_string(),
+        created_at: super::now_millis(),
+        last_opened_at: None,
+        is_active: false,
+    };
+    sqlx::query("INSERT INTO projects (id, name, path, created_at) VALUES (?, ?, ?, ?)")
+        .bind(&project.id)
+        .bind(&project.name)
+        .bind(&project.path)
+        .bind(project.created_at)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to add project: {}", e))?;
+    Ok(project)
+}
+
+async fn active_id(pool: &SqlitePool) -> Result<Option<String>, String> {
+    // Cleared by storing null
+    let id: Option<Option<String>> = super::settings::get(pool, ACTIVE_PROJECT_KEY).await?;
+    Ok(id.flatten())
+}
+
+/// Every project, most recently opened first.
+pub(crate) async fn list(pool: &SqlitePool) -> Result<Vec<Project>, String> {
+    let mut projects = sqlx::query_as::<_, Project>(
+        "SELECT id, name, path, created_at, last_opened_at FROM projects
+         ORDER BY COALESCE(last_opened_at, created_at) DESC",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to list projects: {}", e))?;
+
+    let active = active_id(pool).await?;
+    for project in &mut projects {
+        project.is_active = active.as_deref() == Some(project.id.as_str());
+    }
+    Ok(projects)
+}
+
+pub(crate) async fn active(pool: &SqlitePool) -> Result<Option<Project>, String> {
+    let Some(id) = active_id(pool).await? else {
+        return Ok(None);
+    };
+    let project = sqlx::query_as::<_, Project>(
+        "SELECT id, name, path, created_at, last_opened_at FROM projects WHERE id = ?",
+    )
+    .bind(&id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to load project: {}", e))?;
+
+    Ok(project.map(|project| Project {
+        is_active: true,
+        ..project
+    }))
+}
+
+/// Make `id` the active project, or clear it with `None`.
+pub(crate) async fn set_active(pool: &SqlitePool, id: Option<&str>) -> Result<(), String> {
+    if let Some(id) = id {
+        let result = sqlx::query("UPDATE projects SET last_opened_at = ? WHERE id = ?")
+            .bind(super::now_millis())
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(|e| format!("Failed to update project: {}", e))?;
+        if result.rows_affected() == 0 {
+            return Err(format!("Project not found: {}", id));
+        }
+    }
+    super::settings::set(pool, ACTIVE_PROJECT_KEY, &id).await
+}
+
+/// Forget a project. Its `.claude/` directory is left on disk.
+pub(crate) async fn delete(pool: &SqlitePool, id: &str) -> Result<bool, String> {
+    let result = sqlx::query("DELETE FROM projects WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to remove project: {}", e))?;
+    if active_id(pool).await?.as_deref() == Some(id) {
+        set_active(pool, None).await?;
+    }
+    Ok(result.rows_affected() > 0)
+}
+
+/// Working directory for the agent when a request doesn't specify one.
+pub(crate) async fn active_path(app: &AppHandle) -> Result<Option<PathBuf>, String> {
+    let pool = super::pool(app).await?;
+    Ok(active(&pool)
+        .await?
+        .map(|project| PathBuf::from(project.path)))
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// Register `path` as a project and create its `.claude/` config if it
+/// doesn't have one yet. `name` defaults to the directory name.
+#[tauri::command]
+pub async fn add_project(
+    app: AppHandle,
+    path: String,
+    name: Option<String>,
+) -> Result<Project, String> {
+    let dir = PathBuf::from(path.trim())
+        .canonicalize()
+        .map_err(|e| format!("Failed to open project directory: {}", e))?;
+    if !dir.is_dir() {
+        return Err(format!("Not a directory: {}", dir.display()));
+    }
+    let name = name
+        .filter(|n| !n.trim().is_empty())
+        .or_else(|| dir.file_name().map(|n| n.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| dir.to_string_lossy().into_owned());
+
+    let pool = super::pool(&app).await?;
+    let path = dir.to_string_lossy().into_owned();
+    crate::claude_config::init_claude_config_in(dir)?;
+    insert(&pool, &name, &path).await
+}
+
+#[tauri::command]
+pub async fn list_projects(app: AppHandle) -> Result<Vec<Project>, String> {
+    let pool = super::pool(&app).await?;
+    list(&pool).await
+}
+
+#[tauri::command]
+pub async fn get_active_project(app: AppHandle) -> Result<Option<Project>, String> {
+    let pool = super::pool(&app).await?;
+    active(&pool).await
+}
+
+/// Switch the agent's default working directory to project `id`, or back to
+/// the app's own `.claude/` directory when `id` is omitted.
+#[tauri::command]
+pub async fn set_active_project(app: AppHandle, id: Option<String>) -> Result<(), String> {
+    let pool = super::pool(&app).await?;
+    set_active(&pool, id.as_deref()).await
+}
+
+#[tauri::command]
+pub async fn remove_project(app: AppHandle, id: String) -> Result<bool, String> {
+    let pool = super::pool(&app).await?;
+    delete(&pool, &id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn active_project_follows_set_and_delete() {
+        let pool = crate::db::test_pool().await;
+        let api = insert(&pool, "api", "/src/api").await.unwrap();
+        let web = insert(&pool, "web", "/src/web").await.unwrap();
+        assert!(insert(&pool, "again", "/src/api").await.is_err());
+        assert!(active(&pool).await.unwrap().is_none());
+
+        set_active(&pool, Some(&api.id)).await.unwrap();
+        assert!(set_active(&pool, Some("missing")).await.is_err());
+        let projects = list(&pool).await.unwrap();
+        assert_eq!(projects[0].id, api.id, "most recently opened first");
+        assert!(projects[0].is_active && !projects[1].is_active);
+        assert_eq!(active(&pool).await.unwrap().unwrap().path, "/src/api");
+
+        assert!(delete(&pool, &web.id).await.unwrap());
+        assert!(active(&pool).await.unwrap().is_some());
+        assert!(delete(&pool, &api.id).await.unwrap());
+        assert!(active(&pool).await.unwrap().is_none());
+    }
+}
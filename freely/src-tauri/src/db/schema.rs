@@ -0,0 +1,331 @@
+//! Which migrations the database has applied, and whether the pending ones
+//! would succeed.
+//!
+//! sqlx's migrator records applied migrations in `_sqlx_migrations` when
+//! the database is opened at startup. A dry run applies the pending `Up`
+//! scripts to a throwaway copy, so a broken migration is reported without
+//! touching the real file.
+//!
+//! The live database has already been migrated by the time it can be
+//! asked, so it normally has nothing pending. Backups from older versions
+//! do: restoring one dry-runs its pending migrations first, and refuses a
+//! backup the next launch couldn't migrate.
+
+use serde::Serialize;
+use sqlx::{Connection, Executor, SqliteConnection, SqlitePool};
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+use tauri_plugin_sql::{Migration, MigrationKind};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationInfo {
+    pub version: i64,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DryRunResult {
+    pub ok: bool,
+    /// First pending migration that failed on the copy.
+    pub failed_version: Option<i64>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationStatus {
+    /// 0 for a database with no migrations applied.
+    pub current_version: i64,
+    pub latest_version: i64,
+    pub applied: Vec<MigrationInfo>,
+    pub pending: Vec<MigrationInfo>,
+    /// Only present when a dry run was requested.
+    pub dry_run: Option<DryRunResult>,
+}
+
+/// Highest migration version this build knows about.
+pub(crate) fn latest_version() -> i64 {
+    super::migrations()
+        .iter()
+        .map(|m| m.version)
+        .max()
+        .unwrap_or_default()
+}
+
+/// Successfully applied migrations, oldest first.
+pub(crate) async fn applied(pool: &SqlitePool) -> Result<Vec<MigrationInfo>, String> {
+    let tracked: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations')",
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Failed to read migrations: {}", e))?;
+    if !tracked {
+        return Ok(Vec::new());
+    }
+
+    let rows: Vec<(i64, String)> = sqlx::query_as(
+        "SELECT version, description FROM _sqlx_migrations WHERE success = 1 ORDER BY version",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to read migrations: {}", e))?;
+    Ok(rows
+        .into_iter()
+        .map(|(version, description)| MigrationInfo {
+            version,
+            description,
+        })
+        .collect())
+}
+
+/// The parts of a [`Migration`] a dry run needs.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Script {
+    pub version: i64,
+    pub description: &'static str,
+    pub sql: &'static str,
+}
+
+/// `Up` migrations in `all` that aren't in `applied`, in order.
+fn pending(all: &[Migration], applied: &[MigrationInfo]) -> Vec<Script> {
+    let mut pending: Vec<_> = all
+        .iter()
+        .filter(|m| matches!(m.kind, MigrationKind::Up))
+        .filter(|m| !applied.iter().any(|a| a.version == m.version))
+        .map(|m| Script {
+            version: m.version,
+            description: m.description,
+            sql: m.sql,
+        })
+        .collect();
+    pending.sort_by_key(|m| m.version);
+    pending
+}
+
+async fn apply_one(conn: &mut SqliteConnection, sql: &'static str) -> Result<(), sqlx::Error> {
+    let mut tx = conn.begin().await?;
+    tx.execute(sql).await?;
+    tx.commit().await
+}
+
+/// Apply `scripts` to the database file at `path`, each in its own
+/// transaction like sqlx's `Migrator` in `db/pool.rs` does.
+async fn apply_to(path: &Path, scripts: &[Script]) -> DryRunResult {
+    let options = super::cipher::connect_options(path);
+    let mut conn = match SqliteConnection::connect_with(&options).await {
+        Ok(conn) => conn,
+        Err(e) => {
+            return DryRunResult {
+                ok: false,
+                failed_version: None,
+                error: Some(format!("Failed to open database copy: {}", e)),
+            }
+        }
+    };
+
+    for script in scripts {
+        if let Err(e) = apply_one(&mut conn, script.sql).await {
+            return DryRunResult {
+                ok: false,
+                failed_version: Some(script.version),
+                error: Some(e.to_string()),
+            };
+        }
+    }
+    let _ = conn.close().await;
+    DryRunResult {
+        ok: true,
+        failed_version: None,
+        error: None,
+    }
+}
+
+fn scratch_path() -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "freely-dry-run-{}-{}.db",
+        std::process::id(),
+        super::now_millis()
+    ))
+}
+
+fn remove_scratch(copy: &Path) {
+    for suffix in ["", "-wal", "-shm"] {
+        let mut path = copy.to_path_buf().into_os_string();
+        path.push(suffix);
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// Run `scripts` against a temporary copy of the database behind `pool`,
+/// made with the online backup API.
+pub(crate) async fn dry_run(pool: &SqlitePool, scripts: &[Script]) -> Result<DryRunResult, String> {
+    let copy = scratch_path();
+    super::backup::backup_to(pool, &copy).await?;
+    let result = apply_to(&copy, scripts).await;
+    remove_scratch(&copy);
+    Ok(result)
+}
+
+/// Run the migrations the database file at `path`, e.g. a backup about to
+/// be restored, is missing against a temporary copy of it.
+pub(crate) async fn dry_run_file(path: &Path) -> Result<DryRunResult, String> {
+    let copy = scratch_path();
+    std::fs::copy(path, &copy).map_err(|e| format!("Failed to copy database: {}", e))?;
+    let result = async {
+        let pool = SqlitePool::connect_with(super::cipher::connect_options(&copy))
+            .await
+            .map_err(|e| format!("Failed to open database copy: {}", e))?;
+        let applied = applied(&pool).await;
+        pool.close().await;
+        let pending = pending(&super::migrations(), &applied?);
+        Ok(apply_to(&copy, &pending).await)
+    }
+    .await;
+    remove_scratch(&copy);
+    result
+}
+
+pub(crate) async fn status(
+    pool: &SqlitePool,
+    with_dry_run: bool,
+) -> Result<MigrationStatus, String> {
+    let migrations = super::migrations();
+    let applied = applied(pool).await?;
+    let pending = pending(&migrations, &applied);
+
+    let dry_run = if with_dry_run {
+        Some(dry_run(pool, &pending).await?)
+    } else {
+        None
+    };
+    Ok(MigrationStatus {
+        current_version: applied.iter().map(|m| m.version).max().unwrap_or(0),
+        latest_version: latest_version(),
+        pending: pending
+            .iter()
+            .map(|m| MigrationInfo {
+                version: m.version,
+                description: m.description.to_string(),
+            })
+            .collect(),
+        applied,
+        dry_run,
+    })
+}
+
+/// Report applied and pending migrations. With `dry_run`, also try the
+/// pending ones on a temporary copy of the database; there are normally
+/// none, since the database is migrated when it is opened.
+#[tauri::command]
+pub async fn migration_status(
+    app: AppHandle,
+    dry_run: Option<bool>,
+) -> Result<MigrationStatus, String> {
+    let pool = super::pool(&app).await?;
+    status(&pool, dry_run.unwrap_or(false)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn user_tables(pool: &SqlitePool) -> Vec<String> {
+        sqlx::query_scalar(
+            "SELECT name FROM sqlite_master WHERE type = 'table'
+             AND name NOT LIKE 'sqlite_%' AND name NOT LIKE 'messages_fts_%'",
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn down_migrations_revert_every_up() {
+        let pool = crate::db::test_pool().await;
+        let mut downs: Vec<_> = crate::db::migrations()
+            .into_iter()
+            .filter(|m| matches!(m.kind, MigrationKind::Down))
+            .collect();
+        assert_eq!(downs.len() as i64, latest_version());
+
+        downs.sort_by_key(|m| std::cmp::Reverse(m.version));
+        for migration in &downs {
+            sqlx::raw_sql(migration.sql)
+                .execute(&pool)
+                .await
+                .unwrap_or_else(|e| panic!("down migration {} failed: {}", migration.version, e));
+        }
+        assert_eq!(user_tables(&pool).await, Vec::<String>::new());
+    }
+
+    #[tokio::test]
+    async fn status_lists_pending_migrations_and_dry_runs_them() {
+        // A database from a build that only had the first two migrations
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        for migration in crate::db::migrations() {
+            if migration.version <= 2 && matches!(migration.kind, MigrationKind::Up) {
+                sqlx::raw_sql(migration.sql).execute(&pool).await.unwrap();
+            }
+        }
+        sqlx::raw_sql(
+            "CREATE TABLE _sqlx_migrations (version BIGINT PRIMARY KEY, description TEXT, success BOOLEAN);
+             INSERT INTO _sqlx_migrations VALUES (1, 'create_system_prompts_table', 1);
+             INSERT INTO _sqlx_migrations VALUES (2, 'create_chat_history_tables', 1);",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let report = status(&pool, true).await.unwrap();
+        assert_eq!(report.current_version, 2);
+        assert_eq!(report.pending.first().map(|m| m.version), Some(3));
+        assert_eq!(report.pending.len() as i64, latest_version() - 2);
+        let applied = report.dry_run.unwrap();
+        assert!(applied.ok, "{:?}", applied.error);
+
+        let broken = Script {
+            version: 99,
+            description: "broken",
+            sql: "CREATE TABLE settings_v2 (key TEXT); ALTER TABLE missing ADD COLUMN x;",
+        };
+        let result = dry_run(&pool, &[broken]).await.unwrap();
+        assert!(!result.ok);
+        assert_eq!(result.failed_version, Some(99));
+        assert!(!user_tables(&pool)
+            .await
+            .contains(&"settings_v2".to_string()));
+    }
+
+    #[tokio::test]
+    async fn dry_runs_a_file_without_changing_it() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join("old.db");
+        let options = crate::db::cipher::connect_options(&path).create_if_missing(true);
+        let pool = SqlitePool::connect_with(options).await.unwrap();
+        for migration in crate::db::migrations() {
+            if migration.version == 1 && matches!(migration.kind, MigrationKind::Up) {
+                sqlx::raw_sql(migration.sql).execute(&pool).await.unwrap();
+            }
+        }
+        sqlx::raw_sql(
+            "CREATE TABLE _sqlx_migrations (version BIGINT PRIMARY KEY, description TEXT, success BOOLEAN);
+             INSERT INTO _sqlx_migrations VALUES (1, 'create_system_prompts_table', 1);",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        pool.close().await;
+        let before = std::fs::read(&path).unwrap();
+
+        let result = dry_run_file(&path).await.unwrap();
+        assert!(result.ok, "{:?}", result.error);
+        assert_eq!(std::fs::read(&path).unwrap(), before);
+    }
+}
@@ -0,0 +1,165 @@
+//! Full-text search over chat history.
+//!
+//! Backed by the `messages_fts` FTS5 table created in migration 3, which is
+//! kept in sync with `messages` by triggers. Results are ranked with BM25 and
+//! carry a highlighted snippet plus the owning conversation's title.
+
+use serde::Serialize;
+use tauri::AppHandle;
+
+const DEFAULT_LIMIT: u32 = 50;
+const MAX_LIMIT: u32 = 200;
+
+/// Markers wrapped around matched terms in [`MessageSearchResult::snippet`].
+pub const HIGHLIGHT_START: &str = "<mark>";
+pub const HIGHLIGHT_END: &str = "</mark>";
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageSearchResult {
+    pub message_id: String,
+    pub conversation_id: String,
+    pub conversation_title: String,
+    pub role: String,
+    pub snippet: String,
+    pub timestamp: i64,
+    /// BM25 relevance, higher is better.
+    pub score: f64,
+}
+
+/// Turn free-form user input into a safe FTS5 MATCH expression.
+///
+/// Every whitespace-separated term is quoted so punctuation and FTS operators
+/// (`-`, `:`, `AND`, ...) are matched literally instead of raising a syntax
+/// error. The last term is a prefix match so results update while typing.
+/// Returns `None` when the input has no searchable terms.
+pub(crate) fn build_fts_query(input: &str) -> Option<String> {
+    let terms: Vec<String> = input
+        .split_whitespace()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect();
+
+    if terms.is_empty() {
+        return None;
+    }
+
+    Some(format!("{}*", terms.join(" ")))
+}
+
+pub(crate) async fn search(
+    pool: &sqlx::SqlitePool,
+    query: &str,
+    limit: u32,
+) -> Result<Vec<MessageSearchResult>, sqlx::Error> {
+    let Some(fts_query) = build_fts_query(query) else {
+        return Ok(Vec::new());
+    };
+
+    sqlx::query_as::<_, MessageSearchResult>(
+        "SELECT m.id AS message_id,
+                m.conversation_id,
+                c.title AS conversation_title,
+                m.role,
+                snippet(messages_fts, 0, ?2, ?3, '…', 16) AS snippet,
+                m.timestamp,
+                -bm25(messages_fts) AS score
+         FROM messages_fts
+         JOIN messages m ON m.rowid = messages_fts.rowid
+         JOIN conversations c ON c.id = m.conversation_id
+         WHERE messages_fts MATCH ?1 AND c.deleted_at IS NULL
+         ORDER BY score DESC, m.timestamp DESC
+         LIMIT ?4",
+    )
+    .bind(fts_query)
+    .bind(HIGHLIGHT_START)
+    .bind(HIGHLIGHT_END)
+    .bind(limit.clamp(1, MAX_LIMIT) as i64)
+    .fetch_all(pool)
+    .await
+}
+
+/// Search all messages, returning ranked matches with context snippets.
+#[tauri::command]
+pub async fn search_messages(
+    app: AppHandle,
+    query: String,
+    limit: Option<u32>,
+) -> Result<Vec<MessageSearchResult>, String> {
+    let pool = super::pool(&app).await?;
+
+    search(&pool, &query, limit.unwrap_or(DEFAULT_LIMIT))
+        .await
+        .map_err(|e| format!("Failed to search messages: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn seed(pool: &sqlx::SqlitePool) {
+        sqlx::raw_sql(
+            "INSERT INTO conversations (id, title, created_at, updated_at)
+                 VALUES ('c1', 'Binary trees', 1, 1), ('c2', 'Cooking', 1, 1);
+             INSERT INTO messages (id, conversation_id, role, content, timestamp)
+                 VALUES ('m1', 'c1', 'user', 'How do I balance a binary tree?', 10),
+                        ('m2', 'c1', 'assistant', 'Use rotations, as in an AVL tree.', 11),
+                        ('m3', 'c2', 'user', 'Best way to cook rice?', 12);",
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    #[test]
+    fn fts_query_quotes_terms_and_prefixes_last() {
+        assert_eq!(
+            build_fts_query("binary tre").as_deref(),
+            Some("\"binary\" \"tre\"*")
+        );
+    }
+
+    #[test]
+    fn fts_query_escapes_quotes_and_operators() {
+        assert_eq!(
+            build_fts_query("say \"hi\" -x").as_deref(),
+            Some("\"say\" \"\"\"hi\"\"\" \"-x\"*")
+        );
+    }
+
+    #[test]
+    fn fts_query_blank_input_is_none() {
+        assert!(build_fts_query("   ").is_none());
+    }
+
+    #[tokio::test]
+    async fn search_finds_matches_with_snippets() {
+        let pool = crate::db::test_pool().await;
+        seed(&pool).await;
+
+        let results = search(&pool, "tree", 10).await.unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results
+            .iter()
+            .all(|r| r.conversation_title == "Binary trees"));
+        assert!(results[0].snippet.contains(HIGHLIGHT_START));
+    }
+
+    #[tokio::test]
+    async fn index_follows_updates_and_deletes() {
+        let pool = crate::db::test_pool().await;
+        seed(&pool).await;
+
+        sqlx::query("UPDATE messages SET content = 'Steam it' WHERE id = 'm3'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert!(search(&pool, "rice", 10).await.unwrap().is_empty());
+        assert_eq!(search(&pool, "steam", 10).await.unwrap().len(), 1);
+
+        sqlx::query("DELETE FROM conversations WHERE id = 'c1'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert!(search(&pool, "tree", 10).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,85 @@
+//! Key/value settings stored as JSON in the `settings` table.
+//!
+//! Holds both values the Rust side must read before any window has loaded,
+//! such as global shortcut bindings, and the app preferences served by
+//! [`crate::settings`].
+
+use serde::{de::DeserializeOwned, Serialize};
+use sqlx::SqlitePool;
+
+/// Read and deserialize `key`, or `None` if it has never been written.
+pub(crate) async fn get<T: DeserializeOwned>(
+    pool: &SqlitePool,
+    key: &str,
+) -> Result<Option<T>, String> {
+    let value: Option<String> = sqlx::query_scalar("SELECT value FROM settings WHERE key = ?")
+        .bind(key)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Failed to read setting {}: {}", key, e))?;
+
+    value
+        .map(|json| {
+            serde_json::from_str(&json).map_err(|e| format!("Invalid setting {}: {}", key, e))
+        })
+        .transpose()
+}
+
+pub(crate) async fn set<T: Serialize + ?Sized>(
+    pool: &SqlitePool,
+    key: &str,
+    value: &T,
+) -> Result<(), String> {
+    let json = serde_json::to_string(value)
+        .map_err(|e| format!("Failed to serialize setting {}: {}", key, e))?;
+
+    sqlx::query(
+        "INSERT INTO settings (key, value, updated_at) VALUES (?, ?, ?)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+    )
+    .bind(key)
+    .bind(json)
+    .bind(super::now_millis())
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to save setting {}: {}", key, e))?;
+
+    Ok(())
+}
+
+/// Remove `key`. Returns false if it was never written.
+pub(crate) async fn delete(pool: &SqlitePool, key: &str) -> Result<bool, String> {
+    let result = sqlx::query("DELETE FROM settings WHERE key = ?")
+        .bind(key)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to delete setting {}: {}", key, e))?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Every stored key with its raw JSON value.
+pub(crate) async fn all(pool: &SqlitePool) -> Result<Vec<(String, String)>, String> {
+    sqlx::query_as("SELECT key, value FROM settings ORDER BY key")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to read settings: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn set_overwrites_and_get_round_trips() {
+        let pool = crate::db::test_pool().await;
+
+        assert_eq!(get::<Vec<String>>(&pool, "k").await.unwrap(), None);
+
+        set(&pool, "k", &vec!["a".to_string()]).await.unwrap();
+        set(&pool, "k", &vec!["b".to_string()]).await.unwrap();
+        assert_eq!(
+            get::<Vec<String>>(&pool, "k").await.unwrap(),
+            Some(vec!["b".to_string()])
+        );
+    }
+}
@@ -0,0 +1,110 @@
+//! Rolling conversation summaries (migration 6), written by the context
+//! compactor. Only the newest summary per conversation is read back; older
+//! rows are kept so a bad summary can be inspected or rolled back.
+
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextSummary {
+    pub id: String,
+    pub conversation_id: String,
+    pub summary: String,
+    /// Timestamp of the newest message folded into this summary.
+    pub covers_until: i64,
+    /// Messages covered in total, including those from earlier summaries.
+    pub message_count: i64,
+    pub token_count: i64,
+    pub model: Option<String>,
+    pub created_at: i64,
+}
+
+pub(crate) async fn latest(
+    pool: &SqlitePool,
+    conversation_id: &str,
+) -> Result<Option<ContextSummary>, String> {
+    sqlx::query_as::<_, ContextSummary>(
+        "SELECT id, conversation_id, summary, covers_until, message_count, token_count, model, created_at
+         FROM conversation_summaries
+         WHERE conversation_id = ?
+         ORDER BY covers_until DESC, created_at DESC
+         LIMIT 1",
+    )
+    .bind(conversation_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to load conversation summary: {}", e))
+}
+
+pub(crate) async fn save(
+    pool: &SqlitePool,
+    conversation_id: &str,
+    summary: &str,
+    covers_until: i64,
+    message_count: i64,
+    token_count: i64,
+    model: Option<&str>,
+) -> Result<ContextSummary, String> {
+    if summary.trim().is_empty() {
+        return Err("Invalid summary: text must not be empty".to_string());
+    }
+
+    let row = ContextSummary {
+        id: uuid::Uuid::new_v4().to_string(),
+        conversation_id: conversation_id.to_string(),
+        summary: summary.to_string(),
+        covers_until,
+        message_count,
+        token_count,
+        model: model.map(str::to_string),
+        created_at: super::now_millis(),
+    };
+
+    sqlx::query(
+        "INSERT INTO conversation_summaries
+             (id, conversation_id, summary, covers_until, message_count, token_count, model, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&row.id)
+    .bind(&row.conversation_id)
+    .bind(&row.summary)
+    .bind(row.covers_until)
+    .bind(row.message_count)
+    .bind(row.token_count)
+    .bind(&row.model)
+    .bind(row.created_at)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to save conversation summary: {}", e))?;
+
+    Ok(row)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn latest_returns_the_furthest_summary() {
+        let pool = crate::db::test_pool().await;
+        sqlx::query("INSERT INTO conversations (id, title, created_at, updated_at) VALUES ('c1', 't', 0, 0)")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        assert!(latest(&pool, "c1").await.unwrap().is_none());
+        save(&pool, "c1", "first", 100, 4, 50, None).await.unwrap();
+        save(&pool, "c1", "second", 200, 9, 80, Some("gpt-4o-mini"))
+            .await
+            .unwrap();
+        assert!(save(&pool, "c1", "  ", 300, 1, 0, None).await.is_err());
+
+        let summary = latest(&pool, "c1").await.unwrap().unwrap();
+        assert_eq!(
+            (summary.summary.as_str(), summary.covers_until),
+            ("second", 200)
+        );
+        assert_eq!(summary.model.as_deref(), Some("gpt-4o-mini"));
+    }
+}
@@ -0,0 +1,160 @@
+//! Saved system prompts (migration 1) and their version history
+//! (migration 14).
+//!
+//! The frontend writes prompts with direct SQL, so versions are recorded by
+//! triggers on `system_prompts` rather than here: every insert is version 1
+//! and every change to the name or text adds the next one, keeping the
+//! newest 50.
+
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tauri::AppHandle;
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct SystemPrompt {
+    pub id: i64,
+    pub name: String,
+    pub prompt: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptVersion {
+    pub prompt_id: i64,
+    pub version: i64,
+    pub name: String,
+    pub prompt: String,
+    pub created_at: String,
+}
+
+pub(crate) async fn get(pool: &SqlitePool, id: i64) -> Result<Option<SystemPrompt>, String> {
+    sqlx::query_as::<_, SystemPrompt>(
+        "SELECT id, name, prompt, created_at, updated_at FROM system_prompts WHERE id = ?",
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to load system prompt: {}", e))
+}
+
+/// Versions of prompt `id`, newest first.
+pub(crate) async fn list_versions(
+    pool: &SqlitePool,
+    id: i64,
+) -> Result<Vec<PromptVersion>, String> {
+    sqlx::query_as::<_, PromptVersion>(
+        "SELECT prompt_id, version, name, prompt, created_at FROM system_prompt_versions
+         WHERE prompt_id = ? ORDER BY version DESC",
+    )
+    .bind(id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load system prompt versions: {}", e))
+}
+
+/// Put the name and text of `version` back on prompt `id`. The restore is
+/// itself recorded as a new version, so it can be undone the same way.
+pub(crate) async fn restore_version(
+    pool: &SqlitePool,
+    id: i64,
+    version: i64,
+) -> Result<SystemPrompt, String> {
+    let result = sqlx::query(
+        "UPDATE system_prompts
+         SET (name, prompt) = (
+             SELECT name, prompt FROM system_prompt_versions WHERE prompt_id = ? AND version = ?
+         )
+         WHERE id = ?
+           AND EXISTS (SELECT 1 FROM system_prompt_versions WHERE prompt_id = ? AND version = ?)",
+    )
+    .bind(id)
+    .bind(version)
+    .bind(id)
+    .bind(id)
+    .bind(version)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to restore system prompt: {}", e))?;
+    if result.rows_affected() == 0 {
+        return Err(format!(
+            "Version {} of system prompt {} not found",
+            version, id
+        ));
+    }
+
+    get(pool, id)
+        .await?
+        .ok_or_else(|| format!("System prompt not found: {}", id))
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// Saved versions of a system prompt, newest first.
+#[tauri::command]
+pub async fn list_prompt_versions(app: AppHandle, id: i64) -> Result<Vec<PromptVersion>, String> {
+    let pool = super::pool(&app).await?;
+    list_versions(&pool, id).await
+}
+
+/// Restore a system prompt to an earlier version and return the result.
+#[tauri::command]
+pub async fn restore_prompt_version(
+    app: AppHandle,
+    id: i64,
+    version: i64,
+) -> Result<SystemPrompt, String> {
+    let pool = super::pool(&app).await?;
+    restore_version(&pool, id, version).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn edits_are_versioned_and_restorable() {
+        let pool = crate::db::test_pool().await;
+        let id = sqlx::query("INSERT INTO system_prompts (name, prompt) VALUES ('Tutor', 'v1')")
+            .execute(&pool)
+            .await
+            .unwrap()
+            .last_insert_rowid();
+        for text in ["v2", "v2", "v3"] {
+            sqlx::query("UPDATE system_prompts SET prompt = ? WHERE id = ?")
+                .bind(text)
+                .bind(id)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        // Saving unchanged text doesn't add a version
+        let versions = list_versions(&pool, id).await.unwrap();
+        let texts: Vec<_> = versions.iter().map(|v| v.prompt.as_str()).collect();
+        assert_eq!(texts, ["v3", "v2", "v1"]);
+        assert_eq!(versions[0].version, 3);
+
+        let restored = restore_version(&pool, id, 1).await.unwrap();
+        assert_eq!(restored.prompt, "v1");
+        let latest = &list_versions(&pool, id).await.unwrap()[0];
+        assert_eq!((latest.version, latest.prompt.as_str()), (4, "v1"));
+        assert!(restore_version(&pool, id, 99).await.is_err());
+
+        for n in 0..60 {
+            sqlx::query("UPDATE system_prompts SET prompt = ? WHERE id = ?")
+                .bind(format!("edit {}", n))
+                .bind(id)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        let versions = list_versions(&pool, id).await.unwrap();
+        assert_eq!(versions.len(), 50);
+        assert_eq!(versions[0].version, 64);
+    }
+}
@@ -0,0 +1,313 @@
+//! Conversation tags (migration 16).
+//!
+//! Tags are addressed by name, matched case-insensitively, and created the
+//! first time a conversation is tagged with them, so the frontend never has
+//! to manage tag ids.
+
+use super::chat::{ConversationSummary, DEFAULT_PAGE_SIZE, SUMMARY_ORDER, SUMMARY_SELECT};
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tauri::AppHandle;
+
+const MAX_TAG_LEN: usize = 50;
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct Tag {
+    pub id: String,
+    pub name: String,
+    pub created_at: i64,
+    /// Conversations carrying the tag, not counting the trash.
+    pub conversation_count: i64,
+}
+
+const TAG_SELECT: &str = "SELECT t.id, t.name, t.created_at,
+            (SELECT COUNT(*) FROM conversation_tags ct
+             JOIN conversations c ON c.id = ct.conversation_id
+             WHERE ct.tag_id = t.id AND c.deleted_at IS NULL) AS conversation_count
+     FROM tags t";
+
+fn normalize_name(name: &str) -> Result<&str, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Invalid tag: name must not be empty".to_string());
+    }
+    if name.chars().count() > MAX_TAG_LEN {
+        return Err(format!(
+            "Invalid tag: name exceeds {} characters",
+            MAX_TAG_LEN
+        ));
+    }
+    Ok(name)
+}
+
+async fn find(pool: &SqlitePool, name: &str) -> Result<Option<Tag>, String> {
+    sqlx::query_as::<_, Tag>(&format!("{} WHERE t.name = ?", TAG_SELECT))
+        .bind(name)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Failed to load tag: {}", e))
+}
+
+pub(crate) async fn tag(
+    pool: &SqlitePool,
+    conversation_id: &str,
+    name: &str,
+) -> Result<Tag, String> {
+    let name = normalize_name(name)?;
+    let now = super::now_millis();
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let exists: Option<(String,)> = sqlx::query_as("SELECT id FROM conversations WHERE id = ?")
+        .bind(conversation_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to look up conversation: {}", e))?;
+    if exists.is_none() {
+        return Err(format!("Conversation not found: {}", conversation_id));
+    }
+
+    sqlx::query(
+        "INSERT INTO tags (id, name, created_at) VALUES (?, ?, ?) ON CONFLICT(name) DO NOTHING",
+    )
+    .bind(uuid::Uuid::new_v4().to_string())
+    .bind(name)
+    .bind(now)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to create tag: {}", e))?;
+    sqlx::query(
+        "INSERT OR IGNORE INTO conversation_tags (conversation_id, tag_id, created_at)
+         SELECT ?, id, ? FROM tags WHERE name = ?",
+    )
+    .bind(conversation_id)
+    .bind(now)
+    .bind(name)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to tag conversation: {}", e))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit tag: {}", e))?;
+
+    find(pool, name)
+        .await?
+        .ok_or_else(|| format!("Tag not found: {}", name))
+}
+
+/// Returns `false` if the conversation didn't have the tag.
+pub(crate) async fn untag(
+    pool: &SqlitePool,
+    conversation_id: &str,
+    name: &str,
+) -> Result<bool, String> {
+    let result = sqlx::query(
+        "DELETE FROM conversation_tags
+         WHERE conversation_id = ? AND tag_id = (SELECT id FROM tags WHERE name = ?)",
+    )
+    .bind(conversation_id)
+    .bind(name.trim())
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to untag conversation: {}", e))?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Every tag, alphabetically.
+pub(crate) async fn list(pool: &SqlitePool) -> Result<Vec<Tag>, String> {
+    sqlx::query_as::<_, Tag>(&format!("{} ORDER BY t.name COLLATE NOCASE", TAG_SELECT))
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to list tags: {}", e))
+}
+
+pub(crate) async fn for_conversation(
+    pool: &SqlitePool,
+    conversation_id: &str,
+) -> Result<Vec<Tag>, String> {
+    sqlx::query_as::<_, Tag>(&format!(
+        "{} JOIN conversation_tags ct2 ON ct2.tag_id = t.id
+         WHERE ct2.conversation_id = ?
+         ORDER BY t.name COLLATE NOCASE",
+        TAG_SELECT
+    ))
+    .bind(conversation_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load conversation tags: {}", e))
+}
+
+pub(crate) async fn conversations(
+    pool: &SqlitePool,
+    name: &str,
+    limit: u32,
+    offset: u32,
+) -> Result<Vec<ConversationSummary>, String> {
+    sqlx::query_as::<_, ConversationSummary>(&format!(
+        "{} JOIN conversation_tags ct ON ct.conversation_id = c.id
+         JOIN tags t ON t.id = ct.tag_id
+         WHERE t.name = ? AND c.deleted_at IS NULL
+         {}
+         LIMIT ? OFFSET ?",
+        SUMMARY_SELECT, SUMMARY_ORDER
+    ))
+    .bind(name.trim())
+    .bind(limit as i64)
+    .bind(offset as i64)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to list conversations: {}", e))
+}
+
+/// Remove a tag from every conversation. Returns `false` if it did not exist.
+pub(crate) async fn delete(pool: &SqlitePool, name: &str) -> Result<bool, String> {
+    let result = sqlx::query("DELETE FROM tags WHERE name = ?")
+        .bind(name.trim())
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to delete tag: {}", e))?;
+    Ok(result.rows_affected() > 0)
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// Tag a conversation, creating the tag if it is new.
+#[tauri::command]
+pub async fn tag_conversation(
+    app: AppHandle,
+    conversation_id: String,
+    tag: String,
+) -> Result<Tag, String> {
+    let pool = super::pool(&app).await?;
+    self::tag(&pool, &conversation_id, &tag).await
+}
+
+/// Remove a tag from a conversation. Returns `false` if it wasn't tagged.
+#[tauri::command]
+pub async fn untag_conversation(
+    app: AppHandle,
+    conversation_id: String,
+    tag: String,
+) -> Result<bool, String> {
+    let pool = super::pool(&app).await?;
+    untag(&pool, &conversation_id, &tag).await
+}
+
+/// Conversations carrying `tag`, pinned ones first and then the most
+/// recently updated.
+#[tauri::command]
+pub async fn list_conversations_by_tag(
+    app: AppHandle,
+    tag: String,
+    limit: Option<u32>,
+    offset: Option<u32>,
+) -> Result<Vec<ConversationSummary>, String> {
+    let pool = super::pool(&app).await?;
+    conversations(
+        &pool,
+        &tag,
+        limit.unwrap_or(DEFAULT_PAGE_SIZE),
+        offset.unwrap_or(0),
+    )
+    .await
+}
+
+/// Every tag with how many conversations carry it.
+#[tauri::command]
+pub async fn list_tags(app: AppHandle) -> Result<Vec<Tag>, String> {
+    let pool = super::pool(&app).await?;
+    list(&pool).await
+}
+
+#[tauri::command]
+pub async fn get_conversation_tags(
+    app: AppHandle,
+    conversation_id: String,
+) -> Result<Vec<Tag>, String> {
+    let pool = super::pool(&app).await?;
+    for_conversation(&pool, &conversation_id).await
+}
+
+/// Delete a tag, removing it from every conversation.
+#[tauri::command]
+pub async fn delete_tag(app: AppHandle, tag: String) -> Result<bool, String> {
+    let pool = super::pool(&app).await?;
+    delete(&pool, &tag).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::chat::{self, Conversation};
+
+    async fn conversation(pool: &SqlitePool, id: &str) {
+        chat::create(
+            pool,
+            Conversation {
+                id: id.to_string(),
+                title: format!("Conversation {}", id),
+                created_at: 1,
+                updated_at: 1,
+                pinned: false,
+                archived_at: None,
+                deleted_at: None,
+                messages: Vec::new(),
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn tags_are_shared_by_name() {
+        let pool = crate::db::test_pool().await;
+        conversation(&pool, "a").await;
+        conversation(&pool, "b").await;
+
+        let work = tag(&pool, "a", "Work").await.unwrap();
+        let again = tag(&pool, "b", " work ").await.unwrap();
+        assert_eq!(work.id, again.id);
+        assert_eq!(again.name, "Work");
+        assert_eq!(again.conversation_count, 2);
+        // Tagging twice is a no-op
+        tag(&pool, "b", "Work").await.unwrap();
+        tag(&pool, "b", "Ideas").await.unwrap();
+
+        let ids: Vec<_> = conversations(&pool, "WORK", 10, 0)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|c| c.id)
+            .collect();
+        assert_eq!(ids.len(), 2);
+        let names: Vec<_> = for_conversation(&pool, "b")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|t| t.name)
+            .collect();
+        assert_eq!(names, ["Ideas", "Work"]);
+
+        assert!(untag(&pool, "a", "work").await.unwrap());
+        assert!(!untag(&pool, "a", "work").await.unwrap());
+        assert!(tag(&pool, "missing", "Work").await.is_err());
+        assert!(tag(&pool, "a", "  ").await.is_err());
+
+        // Conversations in the trash aren't counted, and purging drops tags
+        chat::delete(&pool, "b").await.unwrap();
+        assert_eq!(conversations(&pool, "work", 10, 0).await.unwrap().len(), 0);
+        chat::purge(&pool, "b").await.unwrap();
+        assert!(list(&pool)
+            .await
+            .unwrap()
+            .iter()
+            .all(|t| t.conversation_count == 0));
+    }
+}
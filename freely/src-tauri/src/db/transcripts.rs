@@ -0,0 +1,437 @@
+//! Persisted speech-to-text output.
+//!
+//! Each row is one finalized utterance from the microphone or system audio,
+//! with capture timing so transcripts can be replayed alongside a
+//! conversation after a reload, and its translation once
+//! [`crate::stt::translate`] has made one.
+
+use crate::audio::AudioSource;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use tauri::AppHandle;
+
+const DEFAULT_PAGE_SIZE: u32 = 200;
+const MAX_PAGE_SIZE: u32 = 1000;
+const TRANSCRIPT_COLUMNS: &str = "id, conversation_id, source, text, started_at, ended_at,
+     confidence, speaker_label, translated_text, translation_language, created_at";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Transcript {
+    /// Generated when omitted.
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub conversation_id: Option<String>,
+    pub source: AudioSource,
+    pub text: String,
+    pub started_at: i64,
+    pub ended_at: i64,
+    #[serde(default)]
+    pub confidence: Option<f64>,
+    /// Who spoke, for diarized system audio.
+    #[serde(default)]
+    pub speaker_label: Option<String>,
+    #[serde(default)]
+    pub translated_text: Option<String>,
+    /// Target language of `translated_text`.
+    #[serde(default)]
+    pub translation_language: Option<String>,
+    #[serde(default)]
+    pub created_at: i64,
+}
+
+#[derive(sqlx::FromRow)]
+struct TranscriptRow {
+    id: String,
+    conversation_id: Option<String>,
+    source: String,
+    text: String,
+    started_at: i64,
+    ended_at: i64,
+    confidence: Option<f64>,
+    speaker_label: Option<String>,
+    translated_text: Option<String>,
+    translation_language: Option<String>,
+    created_at: i64,
+}
+
+fn source_str(source: AudioSource) -> &'static str {
+    match source {
+        AudioSource::Microphone => "microphone",
+        AudioSource::SystemAudio => "system_audio",
+        AudioSource::Mixed => "mixed",
+    }
+}
+
+impl TryFrom<TranscriptRow> for Transcript {
+    type Error = String;
+
+    fn try_from(row: TranscriptRow) -> Result<Self, Self::Error> {
+        let source = match row.source.as_str() {
+            "microphone" => AudioSource::Microphone,
+            "system_audio" => AudioSource::SystemAudio,
+            "mixed" => AudioSource::Mixed,
+            other => return Err(format!("Unknown transcript source: {}", other)),
+        };
+
+        Ok(Transcript {
+            id: row.id,
+            conversation_id: row.conversation_id,
+            source,
+            text: row.text,
+            started_at: row.started_at,
+            ended_at: row.ended_at,
+            confidence: row.confidence,
+            speaker_label: row.speaker_label,
+            translated_text: row.translated_text,
+            translation_language: row.translation_language,
+            created_at: row.created_at,
+        })
+    }
+}
+
+fn validate(transcript: &Transcript) -> Result<(), String> {
+    if transcript.text.trim().is_empty() {
+        return Err("Invalid transcript: text must not be empty".to_string());
+    }
+    if transcript.started_at < 0 || transcript.ended_at < transcript.started_at {
+        return Err("Invalid transcript: ended_at must not precede started_at".to_string());
+    }
+    if let Some(confidence) = transcript.confidence {
+        if !(0.0..=1.0).contains(&confidence) {
+            return Err("Invalid transcript: confidence must be between 0 and 1".to_string());
+        }
+    }
+    Ok(())
+}
+
+pub(crate) async fn save(
+    pool: &SqlitePool,
+    mut transcript: Transcript,
+) -> Result<Transcript, String> {
+    validate(&transcript)?;
+
+    if transcript.id.trim().is_empty() {
+        transcript.id = uuid::Uuid::new_v4().to_string();
+    }
+    transcript.created_at = super::now_millis();
+
+    sqlx::query(
+        "INSERT INTO transcripts
+             (id, conversation_id, source, text, started_at, ended_at, confidence,
+              speaker_label, translated_text, translation_language, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&transcript.id)
+    .bind(&transcript.conversation_id)
+    .bind(source_str(transcript.source))
+    .bind(&transcript.text)
+    .bind(transcript.started_at)
+    .bind(transcript.ended_at)
+    .bind(transcript.confidence)
+    .bind(&transcript.speaker_label)
+    .bind(&transcript.translated_text)
+    .bind(&transcript.translation_language)
+    .bind(transcript.created_at)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to save transcript: {}", e))?;
+
+    Ok(transcript)
+}
+
+pub(crate) async fn get(pool: &SqlitePool, id: &str) -> Result<Option<Transcript>, String> {
+    sqlx::query_as::<_, TranscriptRow>(&format!(
+        "SELECT {} FROM transcripts WHERE id = ?",
+        TRANSCRIPT_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to load transcript: {}", e))?
+    .map(Transcript::try_from)
+    .transpose()
+}
+
+/// Store the translation of transcript `id` into `language`.
+pub(crate) async fn set_translation(
+    pool: &SqlitePool,
+    id: &str,
+    translated_text: &str,
+    language: &str,
+) -> Result<bool, String> {
+    let result = sqlx::query(
+        "UPDATE transcripts SET translated_text = ?, translation_language = ? WHERE id = ?",
+    )
+    .bind(translated_text)
+    .bind(language)
+    .bind(id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to save translation: {}", e))?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Transcripts in capture order, optionally narrowed to one conversation
+/// and/or source.
+pub(crate) async fn list(
+    pool: &SqlitePool,
+    conversation_id: Option<&str>,
+    source: Option<AudioSource>,
+    limit: u32,
+    offset: u32,
+) -> Result<Vec<Transcript>, String> {
+    let rows = sqlx::query_as::<_, TranscriptRow>(&format!(
+        "SELECT {} FROM transcripts
+         WHERE (?1 IS NULL OR conversation_id = ?1)
+           AND (?2 IS NULL OR source = ?2)
+         ORDER BY started_at ASC
+         LIMIT ?3 OFFSET ?4",
+        TRANSCRIPT_COLUMNS
+    ))
+    .bind(conversation_id)
+    .bind(source.map(source_str))
+    .bind(limit.clamp(1, MAX_PAGE_SIZE) as i64)
+    .bind(offset as i64)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to list transcripts: {}", e))?;
+
+    rows.into_iter().map(Transcript::try_from).collect()
+}
+
+/// The last `limit` transcripts of the most recent recording, meaning the
+/// conversation (or lack of one) of the newest transcript, in capture order.
+pub(crate) async fn latest(pool: &SqlitePool, limit: u32) -> Result<Vec<Transcript>, String> {
+    let mut rows = sqlx::query_as::<_, TranscriptRow>(&format!(
+        "SELECT {} FROM transcripts
+         WHERE conversation_id IS (
+             SELECT conversation_id FROM transcripts ORDER BY ended_at DESC LIMIT 1
+         )
+         ORDER BY started_at DESC
+         LIMIT ?",
+        TRANSCRIPT_COLUMNS
+    ))
+    .bind(limit.clamp(1, MAX_PAGE_SIZE) as i64)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load latest transcript: {}", e))?;
+
+    rows.reverse();
+    rows.into_iter().map(Transcript::try_from).collect()
+}
+
+/// Relabel every transcript in `conversation_id` spoken by `from`. Returns
+/// how many rows changed.
+pub(crate) async fn rename_speaker(
+    pool: &SqlitePool,
+    conversation_id: &str,
+    from: &str,
+    to: &str,
+) -> Result<u64, String> {
+    let to = to.trim();
+    if to.is_empty() {
+        return Err("Speaker name must not be empty".to_string());
+    }
+    let result = sqlx::query(
+        "UPDATE transcripts SET speaker_label = ? WHERE conversation_id = ? AND speaker_label = ?",
+    )
+    .bind(to)
+    .bind(conversation_id)
+    .bind(from)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to rename speaker: {}", e))?;
+    Ok(result.rows_affected())
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// Save a transcript. Unlabelled system-audio transcripts get a speaker
+/// label while diarization is running, and a translation follows when
+/// translation is on.
+#[tauri::command]
+pub async fn save_transcript(app: AppHandle, transcript: Transcript) -> Result<Transcript, String> {
+    let transcript = crate::stt::diarization::label(transcript);
+    let pool = super::pool(&app).await?;
+    let saved = save(&pool, transcript).await?;
+    crate::stt::translate::translate_in_background(&app, &saved);
+    Ok(saved)
+}
+
+#[tauri::command]
+pub async fn list_transcripts(
+    app: AppHandle,
+    conversation_id: Option<String>,
+    source: Option<AudioSource>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+) -> Result<Vec<Transcript>, String> {
+    let pool = super::pool(&app).await?;
+    list(
+        &pool,
+        conversation_id.as_deref(),
+        source,
+        limit.unwrap_or(DEFAULT_PAGE_SIZE),
+        offset.unwrap_or(0),
+    )
+    .await
+}
+
+/// Give a diarized speaker a name (e.g. "Speaker 2" -> "Dana") across a
+/// conversation.
+#[tauri::command]
+pub async fn rename_transcript_speaker(
+    app: AppHandle,
+    conversation_id: String,
+    from: String,
+    to: String,
+) -> Result<u64, String> {
+    let pool = super::pool(&app).await?;
+    rename_speaker(&pool, &conversation_id, &from, &to).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transcript(source: AudioSource, started_at: i64) -> Transcript {
+        Transcript {
+            id: String::new(),
+            conversation_id: Some("c1".into()),
+            source,
+            text: "hello there".into(),
+            started_at,
+            ended_at: started_at + 1500,
+            confidence: Some(0.9),
+            speaker_label: None,
+            translated_text: None,
+            translation_language: None,
+            created_at: 0,
+        }
+    }
+
+    async fn pool_with_conversation() -> SqlitePool {
+        let pool = crate::db::test_pool().await;
+        sqlx::query(
+            "INSERT INTO conversations (id, title, created_at, updated_at) VALUES ('c1', 'Call', 1, 1)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        pool
+    }
+
+    #[tokio::test]
+    async fn save_then_list_filters_and_orders() {
+        let pool = pool_with_conversation().await;
+
+        save(&pool, transcript(AudioSource::SystemAudio, 2000))
+            .await
+            .unwrap();
+        let saved = save(&pool, transcript(AudioSource::Microphone, 1000))
+            .await
+            .unwrap();
+        assert!(!saved.id.is_empty(), "id should be generated");
+
+        let all = list(&pool, Some("c1"), None, 10, 0).await.unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].source, AudioSource::Microphone);
+
+        let mic = list(&pool, None, Some(AudioSource::Microphone), 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(mic.len(), 1);
+        assert_eq!(mic[0].id, saved.id);
+    }
+
+    #[tokio::test]
+    async fn latest_returns_the_newest_recording() {
+        let pool = pool_with_conversation().await;
+        assert!(latest(&pool, 10).await.unwrap().is_empty());
+
+        let mut loose = transcript(AudioSource::Microphone, 500);
+        loose.conversation_id = None;
+        save(&pool, loose).await.unwrap();
+        for started_at in [3000, 1000, 2000] {
+            save(&pool, transcript(AudioSource::SystemAudio, started_at))
+                .await
+                .unwrap();
+        }
+
+        let starts: Vec<i64> = latest(&pool, 2)
+            .await
+            .unwrap()
+            .iter()
+            .map(|t| t.started_at)
+            .collect();
+        assert_eq!(starts, [2000, 3000]);
+
+        let mut newer = transcript(AudioSource::Microphone, 9000);
+        newer.conversation_id = None;
+        save(&pool, newer).await.unwrap();
+        let loose: Vec<i64> = latest(&pool, 10)
+            .await
+            .unwrap()
+            .iter()
+            .map(|t| t.started_at)
+            .collect();
+        assert_eq!(loose, [500, 9000]);
+    }
+
+    #[tokio::test]
+    async fn save_rejects_invalid_timing_and_confidence() {
+        let pool = pool_with_conversation().await;
+
+        let mut backwards = transcript(AudioSource::Microphone, 1000);
+        backwards.ended_at = 500;
+        assert!(save(&pool, backwards).await.is_err());
+
+        let mut overconfident = transcript(AudioSource::Microphone, 1000);
+        overconfident.confidence = Some(1.5);
+        assert!(save(&pool, overconfident).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn speakers_are_renamed_within_a_conversation() {
+        let pool = pool_with_conversation().await;
+        for (started_at, speaker) in [
+            (1000, "Speaker 1"),
+            (2000, "Speaker 2"),
+            (3000, "Speaker 1"),
+        ] {
+            let mut t = transcript(AudioSource::SystemAudio, started_at);
+            t.speaker_label = Some(speaker.into());
+            save(&pool, t).await.unwrap();
+        }
+
+        assert_eq!(
+            rename_speaker(&pool, "c1", "Speaker 1", "Dana")
+                .await
+                .unwrap(),
+            2
+        );
+        let labels: Vec<_> = list(&pool, Some("c1"), None, 10, 0)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|t| t.speaker_label.unwrap())
+            .collect();
+        assert_eq!(labels, ["Dana", "Speaker 2", "Dana"]);
+        assert!(rename_speaker(&pool, "c1", "Speaker 2", " ").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn transcripts_are_deleted_with_their_conversation() {
+        let pool = pool_with_conversation().await;
+        save(&pool, transcript(AudioSource::Microphone, 1000))
+            .await
+            .unwrap();
+
+        crate::db::chat::purge(&pool, "c1").await.unwrap();
+        assert!(list(&pool, None, None, 10, 0).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,107 @@
+//! Provider usage events (migration 15). Costs are worked out by
+//! [`crate::usage`] when an event is recorded.
+
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub(crate) struct UsageEvent {
+    pub id: String,
+    pub request_id: Option<String>,
+    pub provider: String,
+    pub model: String,
+    /// What made the request: `chat`, `compaction`, ...
+    pub source: String,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub estimated: bool,
+    pub latency_ms: i64,
+    pub cost_usd: Option<f64>,
+    pub created_at: i64,
+}
+
+/// Totals for one provider and model on one local calendar day.
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyUsage {
+    /// `YYYY-MM-DD` in local time.
+    pub date: String,
+    pub provider: String,
+    pub model: String,
+    pub requests: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    /// Sum over the requests that have a known price.
+    pub cost_usd: f64,
+    /// Requests whose model has no known price, so `cost_usd` undercounts.
+    pub unpriced_requests: i64,
+    pub avg_latency_ms: f64,
+}
+
+pub(crate) async fn insert(pool: &SqlitePool, event: &UsageEvent) -> Result<(), String> {
+    sqlx::query(
+        "INSERT INTO usage_events (id, request_id, provider, model, source, input_tokens,
+             output_tokens, estimated, latency_ms, cost_usd, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&event.id)
+    .bind(&event.request_id)
+    .bind(&event.provider)
+    .bind(&event.model)
+    .bind(&event.source)
+    .bind(event.input_tokens)
+    .bind(event.output_tokens)
+    .bind(event.estimated)
+    .bind(event.latency_ms)
+    .bind(event.cost_usd)
+    .bind(event.created_at)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to record usage: {}", e))?;
+    Ok(())
+}
+
+/// Daily totals for events in `[from, to)`, oldest day first.
+pub(crate) async fn daily(
+    pool: &SqlitePool,
+    from: i64,
+    to: i64,
+) -> Result<Vec<DailyUsage>, String> {
+    sqlx::query_as::<_, DailyUsage>(
+        "SELECT date(created_at / 1000, 'unixepoch', 'localtime') AS date,
+                provider,
+                model,
+                COUNT(*) AS requests,
+                SUM(input_tokens) AS input_tokens,
+                SUM(output_tokens) AS output_tokens,
+                TOTAL(cost_usd) AS cost_usd,
+                SUM(cost_usd IS NULL) AS unpriced_requests,
+                AVG(latency_ms) AS avg_latency_ms
+         FROM usage_events
+         WHERE created_at >= ? AND created_at < ?
+         GROUP BY date, provider, model
+         ORDER BY date ASC, cost_usd DESC, provider, model",
+    )
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load usage: {}", e))
+}
+
+/// Total known cost of events since `from`, for one provider or all of them.
+pub(crate) async fn spend_since(
+    pool: &SqlitePool,
+    from: i64,
+    provider: Option<&str>,
+) -> Result<f64, String> {
+    sqlx::query_scalar(
+        "SELECT TOTAL(cost_usd) FROM usage_events
+         WHERE created_at >= ?1 AND (?2 IS NULL OR provider = ?2)",
+    )
+    .bind(from)
+    .bind(provider)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Failed to load spend: {}", e))
+}
@@ -0,0 +1,155 @@
+//! `freely://` links from notes apps, browser extensions and the like.
+//!
+//! The scheme is registered by `tauri-plugin-deep-link` (`plugins.deep-link`
+//! in `tauri.conf.json`). Two kinds of link are understood:
+//!
+//! - `freely://conversation/<id>` opens a saved conversation in the
+//!   dashboard.
+//! - `freely://new?prompt=...` starts a new chat in the main window with the
+//!   prompt filled in. Any web page can open a link, so the prompt is only
+//!   ever prefilled, never sent.
+//!
+//! Each link shows its window and is emitted as `deep-link`. A link that
+//! launched the app arrives before the frontend listens, so links are held
+//! until the first `take_pending_deep_links` call.
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Url};
+use tauri_plugin_deep_link::DeepLinkExt;
+use tracing::warn;
+
+const SCHEME: &str = "freely";
+/// Longest prompt taken from a link, in characters.
+const MAX_PROMPT_CHARS: usize = 8_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum DeepLink {
+    Conversation { id: String },
+    NewConversation { prompt: Option<String> },
+}
+
+#[derive(Default)]
+struct Inbox {
+    /// Set once the frontend has asked for pending links.
+    ready: bool,
+    pending: Vec<DeepLink>,
+}
+
+static INBOX: Lazy<Mutex<Inbox>> = Lazy::new(|| Mutex::new(Inbox::default()));
+
+fn parse(url: &Url) -> Result<DeepLink, String> {
+    if url.scheme() != SCHEME {
+        return Err(format!("Not a {}:// link: {}", SCHEME, url));
+    }
+    let segments: Vec<&str> = url
+        .path_segments()
+        .map(|segments| segments.filter(|s| !s.is_empty()).collect())
+        .unwrap_or_default();
+    match (url.host_str(), segments.as_slice()) {
+        (Some("conversation"), [id]) => Ok(DeepLink::Conversation { id: id.to_string() }),
+        (Some("new"), []) => {
+            let prompt = url
+                .query_pairs()
+                .find(|(key, _)| key == "prompt")
+                .map(|(_, value)| value.trim().chars().take(MAX_PROMPT_CHARS).collect())
+                .filter(|prompt: &String| !prompt.is_empty());
+            Ok(DeepLink::NewConversation { prompt })
+        }
+        _ => Err(format!("Unsupported link: {}", url)),
+    }
+}
+
+fn route(app: &AppHandle, link: &DeepLink) -> Result<(), String> {
+    match link {
+        DeepLink::Conversation { .. } => crate::window::show_dashboard_window(app)?,
+        DeepLink::NewConversation { .. } => crate::window::show_main_window(app)?,
+    }
+    app.emit("deep-link", link)
+        .map_err(|e| format!("Failed to emit deep link: {}", e))
+}
+
+fn handle_urls(app: &AppHandle, urls: Vec<Url>) {
+    for url in urls {
+        let link = match parse(&url) {
+            Ok(link) => link,
+            Err(e) => {
+                warn!("{}", e);
+                continue;
+            }
+        };
+        {
+            let mut inbox = INBOX.lock();
+            if !inbox.ready {
+                inbox.pending.push(link);
+                continue;
+            }
+        }
+        if let Err(e) = route(app, &link) {
+            warn!("{}", e);
+        }
+    }
+}
+
+/// Start handling `freely://` links, including one the app was launched
+/// with. Called once from `setup`.
+pub fn setup_deep_links(app: &AppHandle) {
+    // Bundles register the scheme on install; dev builds and AppImages
+    // don't
+    #[cfg(any(windows, target_os = "linux"))]
+    if let Err(e) = app.deep_link().register_all() {
+        warn!("Failed to register {}:// links: {}", SCHEME, e);
+    }
+
+    let handle = app.clone();
+    app.deep_link()
+        .on_open_url(move |event| handle_urls(&handle, event.urls()));
+    match app.deep_link().get_current() {
+        Ok(Some(urls)) => handle_urls(app, urls),
+        Ok(None) => {}
+        Err(e) => warn!("Failed to read launch link: {}", e),
+    }
+}
+
+/// Links that arrived before the frontend was listening. Later links are
+/// only emitted as `deep-link`.
+#[tauri::command]
+pub fn take_pending_deep_links() -> Vec<DeepLink> {
+    let mut inbox = INBOX.lock();
+    inbox.ready = true;
+    std::mem::take(&mut inbox.pending)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_str(url: &str) -> Result<DeepLink, String> {
+        parse(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn links_are_parsed() {
+        assert_eq!(
+            parse_str("freely://conversation/abc-123"),
+            Ok(DeepLink::Conversation {
+                id: "abc-123".to_string()
+            })
+        );
+        assert_eq!(
+            parse_str("freely://new?prompt=Summarize%20this%20page&source=ext"),
+            Ok(DeepLink::NewConversation {
+                prompt: Some("Summarize this page".to_string())
+            })
+        );
+        assert_eq!(
+            parse_str("freely://new/?prompt=%20"),
+            Ok(DeepLink::NewConversation { prompt: None })
+        );
+        assert!(parse_str("freely://conversation").is_err());
+        assert!(parse_str("freely://settings/general").is_err());
+        assert!(parse_str("https://conversation/abc").is_err());
+    }
+}
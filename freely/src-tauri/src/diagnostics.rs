@@ -0,0 +1,253 @@
+//! Crash reports for panics, kept in `crashes/` in the app's local data
+//! directory.
+//!
+//! The panic hook writes one JSON report per panic (message, location,
+//! backtrace, app version and platform) before the default hook runs, so the
+//! report survives even when the panic takes the app down. Reports stay
+//! unreviewed until the user has been asked about them, which lets the
+//! frontend offer to share new ones on the next launch.
+
+use serde::{Deserialize, Serialize};
+use std::panic::PanicHookInfo;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+const LOG_LINES_IN_EXPORT: usize = 200;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashReport {
+    pub id: String,
+    pub created_at: i64,
+    pub message: String,
+    /// `file:line:column` of the panic, when known.
+    pub location: Option<String>,
+    pub thread: Option<String>,
+    pub backtrace: String,
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    /// Set once the user has been asked whether to share the report.
+    #[serde(default)]
+    pub reviewed: bool,
+}
+
+/// A report without its backtrace, for listing.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashReportSummary {
+    pub id: String,
+    pub created_at: i64,
+    pub message: String,
+    pub location: Option<String>,
+    pub app_version: String,
+    pub reviewed: bool,
+}
+
+impl From<&CrashReport> for CrashReportSummary {
+    fn from(report: &CrashReport) -> Self {
+        Self {
+            id: report.id.clone(),
+            created_at: report.created_at,
+            message: report.message.clone(),
+            location: report.location.clone(),
+            app_version: report.app_version.clone(),
+            reviewed: report.reviewed,
+        }
+    }
+}
+
+/// What [`export_crash_report`] writes: the report plus the log lines that
+/// led up to it.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CrashExport<'a> {
+    #[serde(flatten)]
+    report: &'a CrashReport,
+    recent_logs: Vec<String>,
+}
+
+pub(crate) fn crashes_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_local_data_dir()
+        .map_err(|e| format!("Failed to resolve data directory: {}", e))?;
+    Ok(dir.join("crashes"))
+}
+
+fn panic_message(info: &PanicHookInfo<'_>) -> String {
+    let payload = info.payload();
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "Box<dyn Any>".to_string())
+}
+
+fn report_path(dir: &Path, id: &str) -> PathBuf {
+    dir.join(format!("{}.json", id))
+}
+
+fn save_in(dir: &Path, report: &CrashReport) -> Result<(), String> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| format!("Failed to create crashes directory: {}", e))?;
+    let json = serde_json::to_string_pretty(report)
+        .map_err(|e| format!("Failed to serialize crash report: {}", e))?;
+    std::fs::write(report_path(dir, &report.id), json)
+        .map_err(|e| format!("Failed to write crash report: {}", e))
+}
+
+fn load_in(dir: &Path, id: &str) -> Result<CrashReport, String> {
+    if id.is_empty() || id.contains(['/', '\\', '.']) {
+        return Err(format!("Invalid crash report id: {}", id));
+    }
+    let raw = std::fs::read_to_string(report_path(dir, id))
+        .map_err(|e| format!("Failed to read crash report {}: {}", id, e))?;
+    serde_json::from_str(&raw).map_err(|e| format!("Invalid crash report {}: {}", id, e))
+}
+
+/// Every readable report in `dir`, newest first.
+fn list_in(dir: &Path) -> Result<Vec<CrashReport>, String> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read crashes directory: {}", e)),
+    };
+    let mut reports: Vec<CrashReport> = entries
+        .flatten()
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|entry| std::fs::read_to_string(entry.path()).ok())
+        .filter_map(|raw| serde_json::from_str(&raw).ok())
+        .collect();
+    reports.sort_by_key(|r| std::cmp::Reverse(r.created_at));
+    Ok(reports)
+}
+
+/// Record a crash report for every panic, then hand over to the previous
+/// hook (which prints the message as before). Called once from `setup`.
+pub fn install_panic_hook(app: &AppHandle) {
+    let dir = match crashes_dir(app) {
+        Ok(dir) => dir,
+        Err(e) => {
+            tracing::warn!("Crash reports disabled: {}", e);
+            return;
+        }
+    };
+    let app_version = app.package_info().version.to_string();
+    let previous = std::panic::take_hook();
+
+    std::panic::set_hook(Box::new(move |info| {
+        let created_at = crate::db::now_millis();
+        let report = CrashReport {
+            id: format!("crash-{}", created_at),
+            created_at,
+            message: panic_message(info),
+            location: info
+                .location()
+                .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column())),
+            thread: std::thread::current().name().map(str::to_string),
+            backtrace: std::backtrace::Backtrace::force_capture().to_string(),
+            app_version: app_version.clone(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            reviewed: false,
+        };
+        tracing::error!(
+            "Panic at {}: {}",
+            report.location.as_deref().unwrap_or("unknown location"),
+            report.message
+        );
+        if let Err(e) = save_in(&dir, &report) {
+            tracing::error!("{}", e);
+        }
+        previous(info);
+    }));
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// Crash reports, newest first. Those with `reviewed: false` haven't been
+/// shown to the user yet.
+#[tauri::command]
+pub async fn list_crash_reports(app: AppHandle) -> Result<Vec<CrashReportSummary>, String> {
+    let reports = list_in(&crashes_dir(&app)?)?;
+    Ok(reports.iter().map(CrashReportSummary::from).collect())
+}
+
+/// Write report `id` with the most recent log lines to `dest_path`, ready to
+/// attach to a bug report.
+#[tauri::command]
+pub async fn export_crash_report(
+    app: AppHandle,
+    id: String,
+    dest_path: String,
+) -> Result<(), String> {
+    let report = load_in(&crashes_dir(&app)?, &id)?;
+    let export = CrashExport {
+        report: &report,
+        recent_logs: crate::logging::recent_lines(&app, LOG_LINES_IN_EXPORT).unwrap_or_default(),
+    };
+    let json = serde_json::to_string_pretty(&export)
+        .map_err(|e| format!("Failed to serialize crash report: {}", e))?;
+    std::fs::write(dest_path.trim(), json)
+        .map_err(|e| format!("Failed to export crash report: {}", e))
+}
+
+/// Mark every report as reviewed so the share prompt isn't shown again.
+#[tauri::command]
+pub async fn dismiss_crash_reports(app: AppHandle) -> Result<(), String> {
+    let dir = crashes_dir(&app)?;
+    for mut report in list_in(&dir)?.into_iter().filter(|r| !r.reviewed) {
+        report.reviewed = true;
+        save_in(&dir, &report)?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_crash_report(app: AppHandle, id: String) -> Result<(), String> {
+    let dir = crashes_dir(&app)?;
+    // Validates the id before touching the filesystem
+    load_in(&dir, &id)?;
+    std::fs::remove_file(report_path(&dir, &id))
+        .map_err(|e| format!("Failed to delete crash report: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn report(created_at: i64) -> CrashReport {
+        CrashReport {
+            id: format!("crash-{}", created_at),
+            created_at,
+            message: "index out of bounds".to_string(),
+            location: Some("src/lib.rs:1:1".to_string()),
+            thread: Some("main".to_string()),
+            backtrace: String::new(),
+            app_version: "0.1.0".to_string(),
+            os: "linux".to_string(),
+            arch: "x86_64".to_string(),
+            reviewed: false,
+        }
+    }
+
+    #[test]
+    fn reports_round_trip_newest_first() {
+        let tmp = TempDir::new().unwrap();
+        let dir = tmp.path().join("crashes");
+        assert!(list_in(&dir).unwrap().is_empty());
+
+        save_in(&dir, &report(1)).unwrap();
+        save_in(&dir, &report(2)).unwrap();
+        std::fs::write(dir.join("notes.txt"), "ignored").unwrap();
+
+        let ids: Vec<_> = list_in(&dir).unwrap().into_iter().map(|r| r.id).collect();
+        assert_eq!(ids, ["crash-2", "crash-1"]);
+        assert_eq!(load_in(&dir, "crash-1").unwrap().created_at, 1);
+        assert!(load_in(&dir, "../crash-1").is_err());
+    }
+}
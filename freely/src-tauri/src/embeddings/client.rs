@@ -0,0 +1,162 @@
+//! Embedding requests to OpenAI-style `/embeddings` endpoints and Ollama.
+
+use super::EmbeddingConfig;
+use crate::net::client::http_client;
+use crate::providers::middleware::Retry;
+use crate::providers::{error_for_status, ollama, openai, ProviderKind};
+use serde_json::{json, Value};
+use tauri::AppHandle;
+
+/// Inputs per request; OpenAI accepts up to 2048 but large batches make a
+/// single failure expensive.
+pub(crate) const BATCH_SIZE: usize = 64;
+
+/// Pull the vectors out of an OpenAI-style response, in input order.
+pub(crate) fn parse_openai_response(body: &Value) -> Result<Vec<Vec<f32>>, String> {
+    let data = body["data"]
+        .as_array()
+        .ok_or("Embedding response has no data")?;
+
+    let mut indexed: Vec<(u64, Vec<f32>)> = data
+        .iter()
+        .map(|item| {
+            let index = item["index"].as_u64().unwrap_or(0);
+            (index, parse_vector(&item["embedding"]))
+        })
+        .map(|(index, vector)| vector.map(|v| (index, v)))
+        .collect::<Result<_, _>>()?;
+    indexed.sort_by_key(|(index, _)| *index);
+    Ok(indexed.into_iter().map(|(_, v)| v).collect())
+}
+
+pub(crate) fn parse_ollama_response(body: &Value) -> Result<Vec<Vec<f32>>, String> {
+    body["embeddings"]
+        .as_array()
+        .ok_or("Embedding response has no embeddings")?
+        .iter()
+        .map(parse_vector)
+        .collect()
+}
+
+fn parse_vector(value: &Value) -> Result<Vec<f32>, String> {
+    value
+        .as_array()
+        .ok_or("Embedding is not an array")?
+        .iter()
+        .map(|x| {
+            x.as_f64()
+                .map(|x| x as f32)
+                .ok_or("Embedding has a non-numeric value")
+        })
+        .collect::<Result<_, _>>()
+        .map_err(str::to_string)
+}
+
+/// Embed `texts` with the configured model, one vector per input.
+pub(crate) async fn embed(
+    app: &AppHandle,
+    config: &EmbeddingConfig,
+    texts: &[String],
+) -> Result<Vec<Vec<f32>>, String> {
+    if texts.is_empty() {
+        return Ok(Vec::new());
+    }
+    crate::net::connectivity::require_online_for(config.provider, config.base_url.as_deref())?;
+
+    let client = http_client(Some(config.provider))?;
+    let retry = Retry::default();
+    let base_url = config
+        .base_url
+        .as_deref()
+        .map(|url| url.trim().trim_end_matches('/').to_string());
+
+    let vectors = match config.provider {
+        ProviderKind::Ollama => {
+            let base_url = base_url.unwrap_or_else(|| ollama::DEFAULT_BASE_URL.to_string());
+            let request = client
+                .post(format!("{}/api/embed", base_url))
+                .json(&json!({ "model": config.model, "input": texts }));
+            let response = error_for_status(retry.send(request).await?).await?;
+            let body: Value = response
+                .json()
+                .await
+                .map_err(|e| format!("Invalid embedding response: {}", e))?;
+            parse_ollama_response(&body)?
+        }
+        ProviderKind::OpenAi | ProviderKind::OpenAiCompatible => {
+            let base_url = match config.provider {
+                ProviderKind::OpenAi => {
+                    base_url.unwrap_or_else(|| openai::DEFAULT_BASE_URL.to_string())
+                }
+                _ => base_url
+                    .filter(|url| !url.is_empty())
+                    .ok_or("An OpenAI-compatible provider needs a base URL")?,
+            };
+            let key_name = config
+                .api_key_name
+                .as_deref()
+                .unwrap_or(match config.provider {
+                    ProviderKind::OpenAi => "openai",
+                    _ => "openai-compatible",
+                });
+            let api_key = crate::secrets::load_api_key(app, key_name).await?;
+            if config.provider == ProviderKind::OpenAi && api_key.is_none() {
+                return Err("No API key stored for OpenAI".to_string());
+            }
+
+            let mut request = client
+                .post(format!("{}/embeddings", base_url))
+                .json(&json!({ "model": config.model, "input": texts }));
+            if let Some(key) = api_key.filter(|k| !k.is_empty()) {
+                request = request.bearer_auth(key);
+            }
+            let response = error_for_status(retry.send(request).await?).await?;
+            let body: Value = response
+                .json()
+                .await
+                .map_err(|e| format!("Invalid embedding response: {}", e))?;
+            parse_openai_response(&body)?
+        }
+        ProviderKind::Anthropic => {
+            return Err("Anthropic does not offer an embeddings API".to_string());
+        }
+        ProviderKind::LlamaLocal => {
+            return Err("Local GGUF models can't be used for embeddings".to_string());
+        }
+    };
+
+    if vectors.len() != texts.len() {
+        return Err(format!(
+            "Expected {} embeddings, got {}",
+            texts.len(),
+            vectors.len()
+        ));
+    }
+    Ok(vectors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn openai_vectors_come_back_in_input_order() {
+        let body = json!({
+            "data": [
+                { "index": 1, "embedding": [0.5, 0.25] },
+                { "index": 0, "embedding": [1.0, -1.0] }
+            ]
+        });
+        assert_eq!(
+            parse_openai_response(&body).unwrap(),
+            vec![vec![1.0, -1.0], vec![0.5, 0.25]]
+        );
+        assert!(parse_openai_response(&json!({ "data": [{ "embedding": ["x"] }] })).is_err());
+    }
+
+    #[test]
+    fn ollama_vectors_parse() {
+        let body = json!({ "model": "nomic-embed-text", "embeddings": [[0.1, 0.2], [0.3, 0.4]] });
+        assert_eq!(parse_ollama_response(&body).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,322 @@
+//! Semantic search over chat history.
+//!
+//! Messages are embedded with the configured provider (OpenAI, any
+//! OpenAI-compatible server, or a local Ollama model such as
+//! `nomic-embed-text`) and stored in `message_embeddings` (migration 7).
+//! Indexing is incremental: `semantic_search` embeds whatever is new before
+//! searching, and `index_chat_history` backfills older history with
+//! `embedding-index-progress` events.
+
+pub(crate) mod client;
+pub(crate) mod store;
+
+use crate::providers::ProviderKind;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use store::FlatIndex;
+use tauri::{AppHandle, Emitter};
+use tracing::warn;
+
+pub(crate) const CONFIG_SETTING_KEY: &str = "embeddings";
+/// Embedding models cap input length; messages are cut to this first.
+const MAX_INPUT_TOKENS: usize = 8_000;
+/// New messages embedded inline by `semantic_search` before searching.
+const INLINE_INDEX_LIMIT: u32 = 256;
+const SNIPPET_CHARS: usize = 300;
+const DEFAULT_K: usize = 10;
+const MAX_K: usize = 100;
+
+/// Vectors for the configured model, loaded on first search.
+static INDEX: Lazy<Mutex<Option<FlatIndex>>> = Lazy::new(|| Mutex::new(None));
+
+pub(crate) fn invalidate_index() {
+    *INDEX.lock() = None;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddingConfig {
+    pub provider: ProviderKind,
+    pub model: String,
+    #[serde(default)]
+    pub base_url: Option<String>,
+    #[serde(default)]
+    pub api_key_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SemanticSearchResult {
+    pub conversation_id: String,
+    pub conversation_title: String,
+    pub message_id: String,
+    pub role: String,
+    pub snippet: String,
+    pub timestamp: i64,
+    /// Cosine similarity in [-1, 1].
+    pub score: f32,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexProgress {
+    indexed: i64,
+    remaining: i64,
+}
+
+pub(crate) async fn load_config(pool: &SqlitePool) -> Result<Option<EmbeddingConfig>, String> {
+    crate::db::settings::get(pool, CONFIG_SETTING_KEY).await
+}
+
+pub(crate) async fn require_config(pool: &SqlitePool) -> Result<EmbeddingConfig, String> {
+    load_config(pool)
+        .await?
+        .filter(|c| !c.model.trim().is_empty())
+        .ok_or_else(|| "Embeddings are not configured".to_string())
+}
+
+/// Embed everything not indexed yet, batch by batch, calling `on_batch`
+/// with the running total and what remains after each one. An error from
+/// `on_batch` stops indexing. Returns the number indexed.
+pub(crate) async fn index_all(
+    app: &AppHandle,
+    pool: &SqlitePool,
+    config: &EmbeddingConfig,
+    mut on_batch: impl FnMut(i64, i64) -> Result<(), String>,
+) -> Result<i64, String> {
+    let mut indexed = 0i64;
+    loop {
+        let count = index_pending(app, pool, config, client::BATCH_SIZE as u32).await?;
+        if count == 0 {
+            break;
+        }
+        indexed += count as i64;
+        let remaining = store::count_pending(pool, &config.model).await?;
+        on_batch(indexed, remaining)?;
+    }
+    Ok(indexed)
+}
+
+/// Embed and normalise `texts`, cutting each to the model's input limit.
+pub(crate) async fn embed_normalized(
+    app: &AppHandle,
+    config: &EmbeddingConfig,
+    texts: &[&str],
+) -> Result<Vec<Vec<f32>>, String> {
+    let encoding = crate::tokens::encoding_for(&config.model);
+    let inputs: Vec<String> = texts
+        .iter()
+        .map(|text| encoding.truncate(text, MAX_INPUT_TOKENS))
+        .collect();
+
+    let mut vectors = Vec::with_capacity(inputs.len());
+    for batch in inputs.chunks(client::BATCH_SIZE) {
+        vectors.extend(client::embed(app, config, batch).await?);
+    }
+    vectors.iter_mut().for_each(|v| store::normalize(v));
+    Ok(vectors)
+}
+
+/// Embed up to `limit` messages that have no vector for the configured
+/// model yet. Returns how many were embedded.
+async fn index_pending(
+    app: &AppHandle,
+    pool: &SqlitePool,
+    config: &EmbeddingConfig,
+    limit: u32,
+) -> Result<usize, String> {
+    let pending = store::pending(pool, &config.model, limit).await?;
+    if pending.is_empty() {
+        return Ok(0);
+    }
+
+    let texts: Vec<&str> = pending.iter().map(|m| m.content.as_str()).collect();
+    let vectors = embed_normalized(app, config, &texts).await?;
+    let rows: Vec<_> = pending.iter().zip(vectors).collect();
+    store::save(pool, &config.model, &rows).await?;
+
+    let mut index = INDEX.lock();
+    if let Some(index) = index.as_mut().filter(|i| i.model == config.model) {
+        for (message, vector) in &rows {
+            index.upsert(&message.id, vector)?;
+        }
+    }
+    Ok(rows.len())
+}
+
+async fn search(
+    pool: &SqlitePool,
+    config: &EmbeddingConfig,
+    query: &[f32],
+    k: usize,
+) -> Result<Vec<(String, f32)>, String> {
+    let loaded = INDEX
+        .lock()
+        .as_ref()
+        .is_some_and(|index| index.model == config.model);
+    if !loaded {
+        let index = store::load_index(pool, &config.model).await?;
+        *INDEX.lock() = Some(index);
+    }
+
+    Ok(INDEX
+        .lock()
+        .as_ref()
+        .map(|index| index.search(query, k))
+        .unwrap_or_default())
+}
+
+fn snippet(content: &str) -> String {
+    let mut snippet: String = content.chars().take(SNIPPET_CHARS).collect();
+    if content.chars().count() > SNIPPET_CHARS {
+        snippet.push('…');
+    }
+    snippet
+}
+
+async fn describe(
+    pool: &SqlitePool,
+    hits: Vec<(String, f32)>,
+) -> Result<Vec<SemanticSearchResult>, String> {
+    let mut results = Vec::with_capacity(hits.len());
+    for (message_id, score) in hits {
+        let row: Option<(String, String, String, String, i64)> = sqlx::query_as(
+            "SELECT m.conversation_id, c.title, m.role, m.content, m.timestamp
+             FROM messages m JOIN conversations c ON c.id = m.conversation_id
+             WHERE m.id = ? AND c.deleted_at IS NULL",
+        )
+        .bind(&message_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Failed to load search result: {}", e))?;
+
+        // Deleted or in the trash since it was indexed
+        let Some((conversation_id, conversation_title, role, content, timestamp)) = row else {
+            continue;
+        };
+        results.push(SemanticSearchResult {
+            conversation_id,
+            conversation_title,
+            message_id,
+            role,
+            snippet: snippet(&content),
+            timestamp,
+            score,
+        });
+    }
+    Ok(results)
+}
+
+#[tauri::command]
+pub async fn get_embedding_config(app: AppHandle) -> Result<Option<EmbeddingConfig>, String> {
+    let pool = crate::db::pool(&app).await?;
+    load_config(&pool).await
+}
+
+#[tauri::command]
+pub async fn set_embedding_config(app: AppHandle, config: EmbeddingConfig) -> Result<(), String> {
+    match config.provider {
+        ProviderKind::Anthropic => {
+            return Err("Anthropic does not offer an embeddings API".to_string());
+        }
+        ProviderKind::LlamaLocal => {
+            return Err("Local GGUF models can't be used for embeddings".to_string());
+        }
+        _ => {}
+    }
+    let pool = crate::db::pool(&app).await?;
+    crate::db::settings::set(&pool, CONFIG_SETTING_KEY, &config).await?;
+    // Vectors from another model aren't comparable
+    invalidate_index();
+    Ok(())
+}
+
+/// Embed every message that isn't indexed yet, emitting
+/// `embedding-index-progress` after each batch. Returns the number indexed.
+#[tauri::command]
+pub async fn index_chat_history(app: AppHandle) -> Result<i64, String> {
+    let pool = crate::db::pool(&app).await?;
+    let config = require_config(&pool).await?;
+
+    index_all(&app, &pool, &config, |indexed, remaining| {
+        if let Err(e) = app.emit(
+            "embedding-index-progress",
+            IndexProgress { indexed, remaining },
+        ) {
+            warn!("Failed to emit index progress: {}", e);
+        }
+        Ok(())
+    })
+    .await
+}
+
+/// The `k` messages across all conversations closest in meaning to `query`.
+#[tauri::command]
+pub async fn semantic_search(
+    app: AppHandle,
+    query: String,
+    k: Option<usize>,
+) -> Result<Vec<SemanticSearchResult>, String> {
+    if query.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let pool = crate::db::pool(&app).await?;
+    let config = require_config(&pool).await?;
+
+    // Catch up on recent messages; a failure still leaves older ones searchable
+    if let Err(e) = index_pending(&app, &pool, &config, INLINE_INDEX_LIMIT).await {
+        warn!("Failed to index new messages: {}", e);
+    }
+
+    let query_vector = embed_normalized(&app, &config, &[query.as_str()])
+        .await?
+        .pop()
+        .ok_or("No embedding returned for query")?;
+    // Over-fetch so results deleted since indexing don't shrink the page
+    let k = k.unwrap_or(DEFAULT_K).clamp(1, MAX_K);
+    let hits = search(&pool, &config, &query_vector, k * 2).await?;
+
+    let mut results = describe(&pool, hits).await?;
+    results.truncate(k);
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn pending_skips_messages_embedded_with_the_current_model() {
+        let pool = crate::db::test_pool().await;
+        sqlx::raw_sql(
+            "INSERT INTO conversations (id, title, created_at, updated_at) VALUES ('c1', 'Trees', 0, 0);
+             INSERT INTO messages (id, conversation_id, role, content, timestamp)
+             VALUES ('m1', 'c1', 'user', 'binary trees?', 1),
+                    ('m2', 'c1', 'assistant', 'A binary tree is...', 2),
+                    ('m3', 'c1', 'user', '   ', 3);",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let pending = store::pending(&pool, "model-a", 10).await.unwrap();
+        assert_eq!(pending.len(), 2);
+        store::save(&pool, "model-a", &[(&pending[0], vec![1.0, 0.0])])
+            .await
+            .unwrap();
+
+        assert_eq!(store::count_pending(&pool, "model-a").await.unwrap(), 1);
+        assert_eq!(store::count_pending(&pool, "model-b").await.unwrap(), 2);
+
+        let index = store::load_index(&pool, "model-a").await.unwrap();
+        assert_eq!(index.len(), 1);
+
+        let results = describe(&pool, vec![("m1".into(), 0.9), ("gone".into(), 0.8)])
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].conversation_title, "Trees");
+    }
+}
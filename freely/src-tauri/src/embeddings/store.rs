@@ -0,0 +1,245 @@
+//! Message vectors in SQLite plus an in-memory flat index.
+//!
+//! Vectors are stored L2-normalised as little-endian `f32` blobs, so cosine
+//! similarity is a plain dot product. A brute-force scan over a contiguous
+//! buffer handles tens of thousands of messages in milliseconds, which is
+//! far beyond a typical chat history, so no approximate index is needed.
+
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+pub(crate) fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|x| *x /= norm);
+    }
+}
+
+pub(crate) fn encode_vector(vector: &[f32]) -> Vec<u8> {
+    vector.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+pub(crate) fn decode_vector(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+/// Every stored vector for one model, row-major.
+#[derive(Default)]
+pub(crate) struct FlatIndex {
+    pub model: String,
+    dimensions: usize,
+    ids: Vec<String>,
+    rows: HashMap<String, usize>,
+    vectors: Vec<f32>,
+}
+
+impl FlatIndex {
+    pub fn new(model: &str) -> Self {
+        Self {
+            model: model.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Add or replace the vector for `id`. Vectors with a different
+    /// dimension than the index are rejected.
+    pub fn upsert(&mut self, id: &str, vector: &[f32]) -> Result<(), String> {
+        if self.dimensions == 0 {
+            self.dimensions = vector.len();
+        }
+        if vector.len() != self.dimensions {
+            return Err(format!(
+                "Embedding has {} dimensions, index has {}",
+                vector.len(),
+                self.dimensions
+            ));
+        }
+
+        match self.rows.get(id) {
+            Some(&row) => {
+                let start = row * self.dimensions;
+                self.vectors[start..start + self.dimensions].copy_from_slice(vector);
+            }
+            None => {
+                self.rows.insert(id.to_string(), self.ids.len());
+                self.ids.push(id.to_string());
+                self.vectors.extend_from_slice(vector);
+            }
+        }
+        Ok(())
+    }
+
+    /// The `k` ids most similar to `query` (already normalised), best first.
+    pub fn search(&self, query: &[f32], k: usize) -> Vec<(String, f32)> {
+        if self.dimensions == 0 || query.len() != self.dimensions {
+            return Vec::new();
+        }
+
+        let mut scored: Vec<(usize, f32)> = self
+            .vectors
+            .chunks_exact(self.dimensions)
+            .map(|row| row.iter().zip(query).map(|(a, b)| a * b).sum::<f32>())
+            .enumerate()
+            .collect();
+        let k = k.min(scored.len());
+        if k == 0 {
+            return Vec::new();
+        }
+        scored.select_nth_unstable_by(k - 1, |a, b| b.1.total_cmp(&a.1));
+        scored.truncate(k);
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        scored
+            .into_iter()
+            .map(|(row, score)| (self.ids[row].clone(), score))
+            .collect()
+    }
+}
+
+pub(crate) struct PendingMessage {
+    pub id: String,
+    pub conversation_id: String,
+    pub content: String,
+}
+
+/// Messages without a vector for `model`, oldest first.
+pub(crate) async fn pending(
+    pool: &SqlitePool,
+    model: &str,
+    limit: u32,
+) -> Result<Vec<PendingMessage>, String> {
+    let rows: Vec<(String, String, String)> = sqlx::query_as(
+        "SELECT m.id, m.conversation_id, m.content
+         FROM messages m
+         LEFT JOIN message_embeddings e ON e.message_id = m.id
+         WHERE (e.message_id IS NULL OR e.model != ?) AND TRIM(m.content) != ''
+         ORDER BY m.timestamp ASC
+         LIMIT ?",
+    )
+    .bind(model)
+    .bind(limit as i64)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to find messages to embed: {}", e))?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(id, conversation_id, content)| PendingMessage {
+            id,
+            conversation_id,
+            content,
+        })
+        .collect())
+}
+
+pub(crate) async fn count_pending(pool: &SqlitePool, model: &str) -> Result<i64, String> {
+    sqlx::query_scalar(
+        "SELECT COUNT(*) FROM messages m
+         LEFT JOIN message_embeddings e ON e.message_id = m.id
+         WHERE (e.message_id IS NULL OR e.model != ?) AND TRIM(m.content) != ''",
+    )
+    .bind(model)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Failed to count messages to embed: {}", e))
+}
+
+/// Store normalised vectors, replacing any from a previous model.
+pub(crate) async fn save(
+    pool: &SqlitePool,
+    model: &str,
+    rows: &[(&PendingMessage, Vec<f32>)],
+) -> Result<(), String> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+    let now = crate::db::now_millis();
+
+    for (message, vector) in rows {
+        sqlx::query(
+            "INSERT INTO message_embeddings
+                 (message_id, conversation_id, model, dimensions, vector, created_at)
+             VALUES (?, ?, ?, ?, ?, ?)
+             ON CONFLICT(message_id) DO UPDATE SET
+                 model = excluded.model,
+                 dimensions = excluded.dimensions,
+                 vector = excluded.vector,
+                 created_at = excluded.created_at",
+        )
+        .bind(&message.id)
+        .bind(&message.conversation_id)
+        .bind(model)
+        .bind(vector.len() as i64)
+        .bind(encode_vector(vector))
+        .bind(now)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to save embedding: {}", e))?;
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit embeddings: {}", e))
+}
+
+pub(crate) async fn load_index(pool: &SqlitePool, model: &str) -> Result<FlatIndex, String> {
+    let rows: Vec<(String, Vec<u8>)> =
+        sqlx::query_as("SELECT message_id, vector FROM message_embeddings WHERE model = ?")
+            .bind(model)
+            .fetch_all(pool)
+            .await
+            .map_err(|e| format!("Failed to load embeddings: {}", e))?;
+
+    let mut index = FlatIndex::new(model);
+    for (id, bytes) in rows {
+        // Skip rows left over from a model revision with another dimension
+        let _ = index.upsert(&id, &decode_vector(&bytes));
+    }
+    Ok(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vectors_round_trip_through_blobs() {
+        let vector = vec![0.25f32, -1.5, 3.0];
+        assert_eq!(decode_vector(&encode_vector(&vector)), vector);
+    }
+
+    #[test]
+    fn search_ranks_by_cosine_similarity() {
+        let mut index = FlatIndex::new("m");
+        for (id, mut v) in [
+            ("east", vec![1.0, 0.0]),
+            ("north", vec![0.0, 1.0]),
+            ("northeast", vec![1.0, 1.0]),
+        ] {
+            normalize(&mut v);
+            index.upsert(id, &v).unwrap();
+        }
+        assert!(index.upsert("bad", &[1.0, 0.0, 0.0]).is_err());
+
+        let mut query = vec![0.9, 0.1];
+        normalize(&mut query);
+        let ids: Vec<String> = index
+            .search(&query, 2)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(ids, ["east", "northeast"]);
+
+        index.upsert("east", &[0.0, -1.0]).unwrap();
+        assert_eq!(index.len(), 3);
+        assert_eq!(index.search(&query, 10).last().unwrap().0, "east");
+    }
+}
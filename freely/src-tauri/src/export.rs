@@ -0,0 +1,1132 @@
+//! Conversation export and import.
+//!
+//! Renders a stored conversation as Markdown (speaker labels and local
+//! timestamps, for reading and archiving), as a self-contained HTML page
+//! for sharing, as a paginated PDF with its transcripts and meeting notes,
+//! or as a versioned JSON document that `import_conversations` can load
+//! back. Any export can go through a saved redaction profile first.
+//! Imports are deduplicated by conversation and message id, so
+//! re-importing a backup is harmless.
+
+use crate::db::{self, chat::Conversation, transcripts::Transcript};
+use crate::redaction::{self, Redactor};
+use chrono::{Local, TimeZone};
+use printpdf::{BuiltinFont, Color, IndirectFontRef, Line, Mm, PdfDocument, Point, Rect, Rgb};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::path::PathBuf;
+use tauri::AppHandle;
+use tauri_plugin_dialog::DialogExt;
+
+/// Bumped whenever [`ExportDocument`] changes shape.
+pub(crate) const EXPORT_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Markdown,
+    Json,
+    Html,
+    Pdf,
+}
+
+impl ExportFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Json => "json",
+            Self::Html => "html",
+            Self::Pdf => "pdf",
+        }
+    }
+
+    pub(crate) fn filter_name(&self) -> &'static str {
+        match self {
+            Self::Markdown => "Markdown",
+            Self::Json => "JSON",
+            Self::Html => "HTML",
+            Self::Pdf => "PDF",
+        }
+    }
+}
+
+/// Top-level JSON export / backup document.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportDocument {
+    pub version: u32,
+    pub exported_at: i64,
+    pub conversations: Vec<Conversation>,
+}
+
+fn format_local(millis: i64, format: &str) -> String {
+    match Local.timestamp_millis_opt(millis).single() {
+        Some(time) => time.format(format).to_string(),
+        None => millis.to_string(),
+    }
+}
+
+/// Local date and time of `millis`, for headings and message labels.
+pub(crate) fn format_timestamp(millis: i64) -> String {
+    format_local(millis, "%Y-%m-%d %H:%M:%S")
+}
+
+/// [`format_timestamp`] without seconds, for issues and Notion pages.
+pub(crate) fn format_timestamp_minutes(millis: i64) -> String {
+    format_local(millis, "%Y-%m-%d %H:%M")
+}
+
+pub(crate) fn speaker_label(role: db::chat::MessageRole) -> &'static str {
+    match role {
+        db::chat::MessageRole::User => "User",
+        db::chat::MessageRole::Assistant => "Assistant",
+        db::chat::MessageRole::System => "System",
+    }
+}
+
+pub(crate) fn render_markdown(conversation: &Conversation) -> String {
+    let mut out = format!("# {}\n\n", conversation.title.trim());
+    out.push_str(&format!(
+        "_Exported from Freely · {} messages · started {}_\n",
+        conversation.messages.len(),
+        format_timestamp(conversation.created_at)
+    ));
+
+    for message in &conversation.messages {
+        out.push_str(&format!(
+            "\n---\n\n**{}** · {}\n\n{}\n",
+            speaker_label(message.role),
+            format_timestamp(message.timestamp),
+            message.content.trim_end()
+        ));
+
+        if let Some(files) = message.attached_files.as_ref().and_then(|f| f.as_array()) {
+            let names: Vec<&str> = files
+                .iter()
+                .filter_map(|f| f.get("name").and_then(|n| n.as_str()))
+                .collect();
+            if !names.is_empty() {
+                out.push_str(&format!("\n> Attachments: {}\n", names.join(", ")));
+            }
+        }
+    }
+
+    out
+}
+
+pub(crate) fn render_json(conversations: Vec<Conversation>) -> Result<String, String> {
+    let document = ExportDocument {
+        version: EXPORT_FORMAT_VERSION,
+        exported_at: db::now_millis(),
+        conversations,
+    };
+
+    serde_json::to_string_pretty(&document)
+        .map_err(|e| format!("Failed to serialize export: {}", e))
+}
+
+/// What to strip from an HTML export before it leaves the machine.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HtmlExportOptions {
+    /// Write here instead of asking with a save dialog.
+    pub path: Option<String>,
+    /// Replace provider keys and tokens (`sk-…`, `AIza…`, `ghp_…`, …).
+    pub redact_api_keys: bool,
+    pub redact_emails: bool,
+    /// Saved redaction profile to apply as well.
+    pub redaction_profile: Option<String>,
+}
+
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Message text, split at fenced code blocks.
+#[derive(Debug, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    Code(String),
+}
+
+fn segments(content: &str) -> Vec<Segment> {
+    let mut out = Vec::new();
+    let mut text = Vec::new();
+    let mut code: Option<Vec<&str>> = None;
+
+    fn flush_text(out: &mut Vec<Segment>, text: &mut Vec<&str>) {
+        let joined = text.join("\n");
+        if !joined.trim().is_empty() {
+            out.push(Segment::Text(joined.trim_matches('\n').to_string()));
+        }
+        text.clear();
+    }
+
+    for line in content.lines() {
+        let fence = line.trim_start().starts_with("```");
+        match code.as_mut() {
+            Some(lines) if fence => {
+                out.push(Segment::Code(lines.join("\n")));
+                code = None;
+            }
+            Some(lines) => lines.push(line),
+            None if fence => {
+                flush_text(&mut out, &mut text);
+                code = Some(Vec::new());
+            }
+            None => text.push(line),
+        }
+    }
+    flush_text(&mut out, &mut text);
+    // An unclosed fence still renders as code
+    if let Some(lines) = code {
+        out.push(Segment::Code(lines.join("\n")));
+    }
+    out
+}
+
+/// Message text as HTML: fenced code blocks become `<pre>`, everything
+/// else is kept as written (line breaks included) and escaped.
+fn render_html_body(content: &str) -> String {
+    segments(content)
+        .into_iter()
+        .map(|segment| match segment {
+            Segment::Text(text) => format!("<div class=\"text\">{}</div>\n", escape_html(&text)),
+            Segment::Code(code) => format!("<pre><code>{}</code></pre>\n", escape_html(&code)),
+        })
+        .collect()
+}
+
+const HTML_STYLE: &str = "\
+body{margin:0;background:#f6f7f9;color:#1f2328;font:15px/1.6 -apple-system,BlinkMacSystemFont,'Segoe UI',Roboto,sans-serif}
+main{max-width:820px;margin:0 auto;padding:32px 20px}
+h1{font-size:24px;margin:0 0 4px}
+.meta{color:#656d76;font-size:13px;margin:0 0 24px}
+.message{background:#fff;border:1px solid #d0d7de;border-radius:8px;padding:14px 18px;margin:0 0 14px}
+.message.user{border-left:4px solid #0969da}
+.message.assistant{border-left:4px solid #8250df}
+.message.system{border-left:4px solid #9a6700}
+.message header{display:flex;justify-content:space-between;font-size:13px;color:#656d76;margin-bottom:6px}
+.speaker{font-weight:600;color:#1f2328}
+.text{white-space:pre-wrap;overflow-wrap:anywhere}
+pre{background:#f6f8fa;border-radius:6px;padding:12px;overflow-x:auto;font:13px/1.45 ui-monospace,SFMono-Regular,Menlo,Consolas,monospace}
+.attachments{font-size:13px;color:#656d76;margin-top:8px}
+@media (prefers-color-scheme:dark){body{background:#0d1117;color:#e6edf3}.message{background:#161b22;border-color:#30363d}.speaker{color:#e6edf3}pre{background:#0d1117}}
+";
+
+/// One self-contained page (inline CSS, no scripts or external resources)
+/// for sharing a conversation.
+pub(crate) fn render_html(conversation: &Conversation) -> String {
+    let title = escape_html(conversation.title.trim());
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+<title>{}</title>\n<style>\n{}</style>\n</head>\n<body>\n<main>\n<h1>{}</h1>\n\
+<p class=\"meta\">Exported from Freely · {} messages · started {}</p>\n",
+        title,
+        HTML_STYLE,
+        title,
+        conversation.messages.len(),
+        format_timestamp(conversation.created_at)
+    );
+
+    for message in &conversation.messages {
+        let label = speaker_label(message.role);
+        out.push_str(&format!(
+            "<article class=\"message {}\">\n<header><span class=\"speaker\">{}</span>\
+<time>{}</time></header>\n{}",
+            label.to_lowercase(),
+            label,
+            format_timestamp(message.timestamp),
+            render_html_body(&message.content)
+        ));
+
+        if let Some(files) = message.attached_files.as_ref().and_then(|f| f.as_array()) {
+            let names: Vec<String> = files
+                .iter()
+                .filter_map(|f| f.get("name").and_then(|n| n.as_str()))
+                .map(escape_html)
+                .collect();
+            if !names.is_empty() {
+                out.push_str(&format!(
+                    "<p class=\"attachments\">Attachments: {}</p>\n",
+                    names.join(", ")
+                ));
+            }
+        }
+        out.push_str("</article>\n");
+    }
+
+    out.push_str("</main>\n</body>\n</html>\n");
+    out
+}
+
+// ============================================================================
+// PDF
+// ============================================================================
+
+// A4 portrait, in millimetres from the bottom-left corner
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 18.0;
+const HEADER_BASELINE: f32 = PAGE_HEIGHT - 11.0;
+const HEADER_RULE: f32 = PAGE_HEIGHT - 13.5;
+const BODY_TOP: f32 = PAGE_HEIGHT - 20.0;
+const CODE_INDENT: f32 = 3.0;
+const PT_PER_MM: f32 = 2.834_646;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PdfStyle {
+    Title,
+    Meta,
+    Section,
+    Speaker,
+    Text,
+    Code,
+}
+
+impl PdfStyle {
+    fn size(self) -> f32 {
+        match self {
+            Self::Title => 18.0,
+            Self::Meta => 9.0,
+            Self::Section => 13.0,
+            Self::Speaker => 10.0,
+            Self::Text => 10.0,
+            Self::Code => 8.5,
+        }
+    }
+
+    fn line_height(self) -> f32 {
+        self.size() / PT_PER_MM * 1.4
+    }
+
+    fn space_before(self) -> f32 {
+        match self {
+            Self::Title | Self::Meta => 0.0,
+            Self::Section => 7.0,
+            Self::Speaker => 4.5,
+            Self::Text | Self::Code => 1.0,
+        }
+    }
+
+    fn indent(self) -> f32 {
+        match self {
+            Self::Code => CODE_INDENT,
+            _ => 0.0,
+        }
+    }
+
+    /// Characters that fit on a line. Courier is exactly 0.6 em per glyph;
+    /// for Helvetica this is a conservative average, as the built-in fonts
+    /// come without metrics.
+    fn max_chars(self) -> usize {
+        let em = match self {
+            Self::Title | Self::Section | Self::Speaker | Self::Code => 0.6,
+            Self::Meta | Self::Text => 0.52,
+        };
+        let width = (PAGE_WIDTH - 2.0 * MARGIN - 2.0 * self.indent()) * PT_PER_MM;
+        (width / (self.size() * em)) as usize
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct PdfLine {
+    style: PdfStyle,
+    text: String,
+    /// Baseline, in mm from the bottom of the page.
+    y: f32,
+}
+
+/// The built-in PDF fonts only cover WinAnsi (roughly Latin-1), and
+/// anything outside it would silently disappear.
+fn pdf_safe(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\t' => ' ',
+            c if (' '..='\u{ff}').contains(&c) && !('\u{7f}'..'\u{a0}').contains(&c) => c,
+            '‘' | '’' | '“' | '”' | '–' | '—' | '•' | '…' | '€' | '™' => c,
+            _ => '?',
+        })
+        .collect()
+}
+
+fn split_chars(line: &str, max: usize) -> Vec<String> {
+    let chars: Vec<char> = line.chars().collect();
+    if chars.is_empty() {
+        return vec![String::new()];
+    }
+    chars
+        .chunks(max.max(1))
+        .map(|c| c.iter().collect())
+        .collect()
+}
+
+/// Word-wrap `text` to `max` characters per line, keeping its line breaks.
+/// Code keeps its spacing and is broken mid-line instead.
+fn wrap(text: &str, max: usize, code: bool) -> Vec<String> {
+    let mut lines = Vec::new();
+    for line in text.lines() {
+        let line = pdf_safe(&line.replace('\t', "    "));
+        if code {
+            lines.extend(split_chars(line.trim_end(), max));
+            continue;
+        }
+        let mut current = String::new();
+        for word in line.split_whitespace() {
+            let fits = current.chars().count() + 1 + word.chars().count() <= max;
+            if !current.is_empty() && !fits {
+                lines.push(std::mem::take(&mut current));
+            }
+            if word.chars().count() > max {
+                let mut pieces = split_chars(word, max);
+                current = pieces.pop().unwrap_or_default();
+                lines.extend(pieces);
+            } else {
+                if !current.is_empty() {
+                    current.push(' ');
+                }
+                current.push_str(word);
+            }
+        }
+        lines.push(current);
+    }
+    lines
+}
+
+/// The blocks of a conversation's PDF: its messages, then the meeting
+/// notes and the transcript, when it has them.
+fn pdf_blocks(
+    conversation: &Conversation,
+    notes: Option<&str>,
+    transcripts: &[Transcript],
+) -> Vec<(PdfStyle, String)> {
+    let mut blocks = vec![
+        (PdfStyle::Title, conversation.title.trim().to_string()),
+        (
+            PdfStyle::Meta,
+            format!(
+                "Exported from Freely · {} messages · started {}",
+                conversation.messages.len(),
+                format_timestamp(conversation.created_at)
+            ),
+        ),
+    ];
+
+    for message in &conversation.messages {
+        blocks.push((
+            PdfStyle::Speaker,
+            format!(
+                "{} · {}",
+                speaker_label(message.role),
+                format_timestamp(message.timestamp)
+            ),
+        ));
+        for segment in segments(&message.content) {
+            blocks.push(match segment {
+                Segment::Text(text) => (PdfStyle::Text, text),
+                Segment::Code(code) => (PdfStyle::Code, code),
+            });
+        }
+        if let Some(files) = message.attached_files.as_ref().and_then(|f| f.as_array()) {
+            let names: Vec<&str> = files
+                .iter()
+                .filter_map(|f| f.get("name").and_then(|n| n.as_str()))
+                .collect();
+            if !names.is_empty() {
+                blocks.push((PdfStyle::Meta, format!("Attachments: {}", names.join(", "))));
+            }
+        }
+    }
+
+    if let Some(notes) = notes.filter(|notes| !notes.trim().is_empty()) {
+        blocks.push((PdfStyle::Section, "Notes".to_string()));
+        blocks.push((PdfStyle::Text, notes.trim().to_string()));
+    }
+    if !transcripts.is_empty() {
+        blocks.push((PdfStyle::Section, "Transcript".to_string()));
+        for transcript in transcripts {
+            blocks.push((
+                PdfStyle::Text,
+                format!(
+                    "[{}] {}: {}",
+                    format_timestamp(transcript.started_at),
+                    crate::meeting::notes::speaker(transcript),
+                    transcript.text.trim()
+                ),
+            ));
+        }
+    }
+    blocks
+}
+
+/// Break `blocks` into lines and the lines into pages.
+fn layout(blocks: &[(PdfStyle, String)]) -> Vec<Vec<PdfLine>> {
+    let mut pages = vec![Vec::new()];
+    let mut y = BODY_TOP;
+
+    for (style, text) in blocks {
+        let style = *style;
+        let lines = wrap(text, style.max_chars(), style == PdfStyle::Code);
+        if y < BODY_TOP {
+            y -= style.space_before();
+        }
+        // Keep headings with at least two lines of what follows
+        let keep = match style {
+            PdfStyle::Section | PdfStyle::Speaker => {
+                style.line_height() + 2.0 * PdfStyle::Text.line_height()
+            }
+            _ => style.line_height(),
+        };
+        if y - keep < MARGIN && !pages.last().is_some_and(|page| page.is_empty()) {
+            pages.push(Vec::new());
+            y = BODY_TOP;
+        }
+
+        for text in lines {
+            if y - style.line_height() < MARGIN {
+                pages.push(Vec::new());
+                y = BODY_TOP;
+            }
+            y -= style.line_height();
+            if let Some(page) = pages.last_mut() {
+                page.push(PdfLine { style, text, y });
+            }
+        }
+    }
+    pages
+}
+
+fn grey(level: f32) -> Color {
+    Color::Rgb(Rgb::new(level, level, level, None))
+}
+
+/// A4 PDF of `blocks`, with the title and page numbers as a header on
+/// every page.
+fn render_pdf(title: &str, blocks: &[(PdfStyle, String)]) -> Result<Vec<u8>, String> {
+    let title = pdf_safe(title.trim());
+    let (doc, first_page, first_layer) =
+        PdfDocument::new(&title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Content");
+    let font = |builtin| {
+        doc.add_builtin_font(builtin)
+            .map_err(|e| format!("Failed to load PDF font: {}", e))
+    };
+    let regular = font(BuiltinFont::Helvetica)?;
+    let bold = font(BuiltinFont::HelveticaBold)?;
+    let mono = font(BuiltinFont::Courier)?;
+    let font_for = |style: PdfStyle| -> &IndirectFontRef {
+        match style {
+            PdfStyle::Title | PdfStyle::Section | PdfStyle::Speaker => &bold,
+            PdfStyle::Code => &mono,
+            PdfStyle::Meta | PdfStyle::Text => &regular,
+        }
+    };
+
+    let pages = layout(blocks);
+    let total = pages.len();
+    let header: String = title.chars().take(90).collect();
+    for (index, lines) in pages.into_iter().enumerate() {
+        let layer = if index == 0 {
+            doc.get_page(first_page).get_layer(first_layer)
+        } else {
+            let (page, layer) = doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Content");
+            doc.get_page(page).get_layer(layer)
+        };
+
+        layer.set_fill_color(grey(0.45));
+        layer.use_text(&header, 8.0, Mm(MARGIN), Mm(HEADER_BASELINE), &regular);
+        let number = format!("Page {} of {}", index + 1, total);
+        let number_width = number.len() as f32 * 8.0 * 0.52 / PT_PER_MM;
+        layer.use_text(
+            &number,
+            8.0,
+            Mm(PAGE_WIDTH - MARGIN - number_width),
+            Mm(HEADER_BASELINE),
+            &regular,
+        );
+        layer.set_outline_color(grey(0.8));
+        layer.set_outline_thickness(0.5);
+        layer.add_line(Line {
+            points: vec![
+                (Point::new(Mm(MARGIN), Mm(HEADER_RULE)), false),
+                (Point::new(Mm(PAGE_WIDTH - MARGIN), Mm(HEADER_RULE)), false),
+            ],
+            is_closed: false,
+        });
+
+        for line in lines {
+            if line.style == PdfStyle::Code {
+                // Shade the full line height, so a block reads as one box
+                layer.set_fill_color(grey(0.95));
+                let descent = line.style.line_height() * 0.3;
+                layer.add_rect(Rect::new(
+                    Mm(MARGIN),
+                    Mm(line.y - descent),
+                    Mm(PAGE_WIDTH - MARGIN),
+                    Mm(line.y - descent + line.style.line_height()),
+                ));
+            }
+            layer.set_fill_color(match line.style {
+                PdfStyle::Meta => grey(0.4),
+                _ => grey(0.1),
+            });
+            layer.use_text(
+                &line.text,
+                line.style.size(),
+                Mm(MARGIN + line.style.indent()),
+                Mm(line.y),
+                font_for(line.style),
+            );
+        }
+    }
+
+    doc.save_to_bytes()
+        .map_err(|e| format!("Failed to write PDF: {}", e))
+}
+
+/// Every transcript of a conversation, in capture order.
+async fn all_transcripts(
+    pool: &SqlitePool,
+    conversation_id: &str,
+) -> Result<Vec<Transcript>, String> {
+    const PAGE_SIZE: u32 = 1000;
+    let mut transcripts = Vec::new();
+    loop {
+        let offset = transcripts.len() as u32;
+        let page =
+            db::transcripts::list(pool, Some(conversation_id), None, PAGE_SIZE, offset).await?;
+        let done = (page.len() as u32) < PAGE_SIZE;
+        transcripts.extend(page);
+        if done {
+            return Ok(transcripts);
+        }
+    }
+}
+
+/// `conversation` (already redacted) with its transcripts and meeting
+/// notes, which `redactor` is applied to.
+async fn render_pdf_export(
+    pool: &SqlitePool,
+    conversation: &Conversation,
+    redactor: &Redactor,
+) -> Result<Vec<u8>, String> {
+    let mut transcripts = all_transcripts(pool, &conversation.id).await?;
+    for transcript in &mut transcripts {
+        transcript.text = redactor.apply(&transcript.text);
+        transcript.speaker_label = transcript
+            .speaker_label
+            .as_deref()
+            .map(|l| redactor.apply(l));
+    }
+    let notes = crate::meeting::store::for_conversation(pool, &conversation.id)
+        .await?
+        .and_then(|meeting| meeting.notes)
+        .map(|notes| redactor.apply(&notes));
+    render_pdf(
+        &conversation.title,
+        &pdf_blocks(conversation, notes.as_deref(), &transcripts),
+    )
+}
+
+/// `conversation` rendered in `format`, with `redactor` applied.
+pub(crate) async fn render(
+    pool: &SqlitePool,
+    conversation: &Conversation,
+    format: ExportFormat,
+    redactor: &Redactor,
+) -> Result<Vec<u8>, String> {
+    let conversation = redactor.conversation(conversation);
+    Ok(match format {
+        ExportFormat::Markdown => render_markdown(&conversation).into_bytes(),
+        ExportFormat::Json => render_json(vec![conversation])?.into_bytes(),
+        ExportFormat::Html => render_html(&conversation).into_bytes(),
+        ExportFormat::Pdf => render_pdf_export(pool, &conversation, redactor).await?,
+    })
+}
+
+/// Suggested file name for the save dialog, derived from the title.
+pub(crate) fn default_file_name(title: &str, format: ExportFormat) -> String {
+    let stem: String = title
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                ' '
+            }
+        })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-");
+
+    let stem = if stem.is_empty() {
+        "conversation"
+    } else {
+        &stem
+    };
+    format!(
+        "{}.{}",
+        stem.chars().take(80).collect::<String>(),
+        format.extension()
+    )
+}
+
+/// Ask the user where to save the export. `None` means the dialog was cancelled.
+async fn pick_save_path(
+    app: &AppHandle,
+    file_name: String,
+    format: ExportFormat,
+) -> Result<Option<PathBuf>, String> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    app.dialog()
+        .file()
+        .set_file_name(file_name)
+        .add_filter(format.filter_name(), &[format.extension()])
+        .save_file(move |path| {
+            let _ = tx.send(path);
+        });
+
+    match rx.await.map_err(|e| format!("Save dialog failed: {}", e))? {
+        Some(path) => path
+            .into_path()
+            .map(Some)
+            .map_err(|e| format!("Invalid save path: {}", e)),
+        None => Ok(None),
+    }
+}
+
+/// Export a conversation to `path`, or to a location chosen in a save dialog
+/// when `path` is omitted, redacted with the saved profile
+/// `redaction_profile` if given. Returns the written path, or `None` if the
+/// user cancelled the dialog.
+#[tauri::command]
+pub async fn export_conversation(
+    app: AppHandle,
+    id: String,
+    format: ExportFormat,
+    path: Option<String>,
+    redaction_profile: Option<String>,
+) -> Result<Option<String>, String> {
+    let redactor = match redaction_profile {
+        Some(name) => redaction::load(&app, &name).await?,
+        None => Redactor::none(),
+    };
+    let pool = db::pool(&app).await?;
+    let conversation = load_conversation(&pool, &id).await?;
+    let Some(path) = resolve_save_path(&app, path, &conversation.title, format).await? else {
+        return Ok(None);
+    };
+    write_export(path, render(&pool, &conversation, format, &redactor).await?).await
+}
+
+/// Export a conversation as a single HTML page for sharing, with the
+/// redactions in `options`. Returns the written path, or `None` if the user
+/// cancelled the save dialog.
+#[tauri::command]
+pub async fn export_conversation_html(
+    app: AppHandle,
+    id: String,
+    options: HtmlExportOptions,
+) -> Result<Option<String>, String> {
+    let mut profile = match &options.redaction_profile {
+        Some(name) => redaction::profile(&app, name).await?,
+        None => redaction::RedactionProfile {
+            emails: false,
+            phone_numbers: false,
+            api_keys: false,
+            ..Default::default()
+        },
+    };
+    profile.api_keys |= options.redact_api_keys;
+    profile.emails |= options.redact_emails;
+    let redactor = Redactor::new(&profile)?;
+    let pool = db::pool(&app).await?;
+    let conversation = load_conversation(&pool, &id).await?;
+    let Some(path) = resolve_save_path(
+        &app,
+        options.path.clone(),
+        &conversation.title,
+        ExportFormat::Html,
+    )
+    .await?
+    else {
+        return Ok(None);
+    };
+    write_export(
+        path,
+        render(&pool, &conversation, ExportFormat::Html, &redactor).await?,
+    )
+    .await
+}
+
+async fn load_conversation(pool: &SqlitePool, id: &str) -> Result<Conversation, String> {
+    db::chat::get(pool, id)
+        .await?
+        .ok_or_else(|| format!("Conversation not found: {}", id))
+}
+
+/// `path` if given, otherwise the user's pick in a save dialog.
+async fn resolve_save_path(
+    app: &AppHandle,
+    path: Option<String>,
+    title: &str,
+    format: ExportFormat,
+) -> Result<Option<PathBuf>, String> {
+    match path {
+        Some(path) => Ok(Some(PathBuf::from(path))),
+        None => pick_save_path(app, default_file_name(title, format), format).await,
+    }
+}
+
+async fn write_export(path: PathBuf, content: Vec<u8>) -> Result<Option<String>, String> {
+    tokio::fs::write(&path, content)
+        .await
+        .map_err(|e| format!("Failed to write export: {}", e))?;
+
+    Ok(Some(path.to_string_lossy().into_owned()))
+}
+
+// ============================================================================
+// Import
+// ============================================================================
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub conversations_inserted: u32,
+    pub conversations_skipped: u32,
+    pub messages_inserted: u32,
+    pub messages_skipped: u32,
+}
+
+/// Parse and validate a JSON backup without touching the database.
+pub(crate) fn parse_backup(raw: &str) -> Result<ExportDocument, String> {
+    let document: ExportDocument =
+        serde_json::from_str(raw).map_err(|e| format!("Invalid backup file: {}", e))?;
+
+    if document.version > EXPORT_FORMAT_VERSION {
+        return Err(format!(
+            "Backup format version {} is newer than this app supports ({})",
+            document.version, EXPORT_FORMAT_VERSION
+        ));
+    }
+    for conversation in &document.conversations {
+        db::chat::validate_conversation(conversation)?;
+    }
+
+    Ok(document)
+}
+
+async fn exists(
+    conn: &mut sqlx::SqliteConnection,
+    table: &str,
+    id: &str,
+) -> Result<bool, sqlx::Error> {
+    let row: Option<(i64,)> = sqlx::query_as(&format!("SELECT 1 FROM {} WHERE id = ?", table))
+        .bind(id)
+        .fetch_optional(conn)
+        .await?;
+    Ok(row.is_some())
+}
+
+/// Insert every conversation and message from `document` whose id is not
+/// already present, in a single transaction. Messages of an existing
+/// conversation are still merged in, so a newer backup fills in gaps.
+pub(crate) async fn import(
+    pool: &SqlitePool,
+    document: ExportDocument,
+) -> Result<ImportReport, String> {
+    let mut report = ImportReport::default();
+    let now = db::now_millis();
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    for conversation in document.conversations {
+        let found = exists(&mut tx, "conversations", &conversation.id)
+            .await
+            .map_err(|e| format!("Failed to look up conversation: {}", e))?;
+
+        if found {
+            report.conversations_skipped += 1;
+        } else {
+            let created_at = if conversation.created_at > 0 {
+                conversation.created_at
+            } else {
+                now
+            };
+            let updated_at = conversation.updated_at.max(created_at);
+
+            sqlx::query(
+                "INSERT INTO conversations (id, title, created_at, updated_at) VALUES (?, ?, ?, ?)",
+            )
+            .bind(&conversation.id)
+            .bind(&conversation.title)
+            .bind(created_at)
+            .bind(updated_at)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to import conversation {}: {}", conversation.id, e))?;
+            report.conversations_inserted += 1;
+        }
+
+        for message in &conversation.messages {
+            let found = exists(&mut tx, "messages", &message.id)
+                .await
+                .map_err(|e| format!("Failed to look up message: {}", e))?;
+            if found {
+                report.messages_skipped += 1;
+                continue;
+            }
+
+            db::chat::insert_message(&mut tx, &conversation.id, message)
+                .await
+                .map_err(|e| format!("Failed to import message {}: {}", message.id, e))?;
+            report.messages_inserted += 1;
+        }
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit import: {}", e))?;
+
+    Ok(report)
+}
+
+/// Import conversations from a JSON backup at `path`, or from a file chosen
+/// in an open dialog when `path` is omitted. Returns `None` if the user
+/// cancelled the dialog.
+#[tauri::command]
+pub async fn import_conversations(
+    app: AppHandle,
+    path: Option<String>,
+) -> Result<Option<ImportReport>, String> {
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => match pick_open_path(&app).await? {
+            Some(path) => path,
+            None => return Ok(None),
+        },
+    };
+
+    let raw = tokio::fs::read_to_string(&path)
+        .await
+        .map_err(|e| format!("Failed to read backup file: {}", e))?;
+    let document = parse_backup(&raw)?;
+
+    let pool = db::pool(&app).await?;
+    import(&pool, document).await.map(Some)
+}
+
+async fn pick_open_path(app: &AppHandle) -> Result<Option<PathBuf>, String> {
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    app.dialog()
+        .file()
+        .add_filter(
+            ExportFormat::Json.filter_name(),
+            &[ExportFormat::Json.extension()],
+        )
+        .pick_file(move |path| {
+            let _ = tx.send(path);
+        });
+
+    match rx.await.map_err(|e| format!("Open dialog failed: {}", e))? {
+        Some(path) => path
+            .into_path()
+            .map(Some)
+            .map_err(|e| format!("Invalid file path: {}", e)),
+        None => Ok(None),
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::chat::{Message, MessageRole};
+
+    fn conversation() -> Conversation {
+        Conversation {
+            id: "c1".into(),
+            title: "System design interview".into(),
+            created_at: 1_700_000_000_000,
+            updated_at: 1_700_000_060_000,
+            pinned: false,
+            archived_at: None,
+            deleted_at: None,
+            messages: vec![
+                Message {
+                    id: "m1".into(),
+                    role: MessageRole::User,
+                    content: "Design a URL shortener.".into(),
+                    timestamp: 1_700_000_000_000,
+                    attached_files: Some(serde_json::json!([{ "name": "notes.txt" }])),
+                    truncated: false,
+                },
+                Message {
+                    id: "m2".into(),
+                    role: MessageRole::Assistant,
+                    content: "Start with the read path.\n".into(),
+                    timestamp: 1_700_000_060_000,
+                    attached_files: None,
+                    truncated: false,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn markdown_has_title_speakers_and_attachments() {
+        let md = render_markdown(&conversation());
+        assert!(md.starts_with("# System design interview\n"));
+        assert!(md.contains("**User** · "));
+        assert!(md.contains("**Assistant** · "));
+        assert!(md.contains("Design a URL shortener."));
+        assert!(md.contains("> Attachments: notes.txt"));
+        assert!(md.find("**User**").unwrap() < md.find("**Assistant**").unwrap());
+    }
+
+    #[test]
+    fn html_is_escaped_and_redacted() {
+        let mut conversation = conversation();
+        conversation.messages[0].content = "Mail jane.doe@example.com, key \
+sk-proj-abcdefghijklmnopqrstuvwx <br>\n```\nlet a = 1 < 2;\n```"
+            .into();
+
+        let html = render_html(&conversation);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<style>"));
+        assert!(html.contains("jane.doe@example.com"));
+        assert!(html.contains("&lt;br&gt;"));
+        assert!(html.contains("<pre><code>let a = 1 &lt; 2;</code></pre>"));
+        assert!(html.contains("Attachments: notes.txt"));
+
+        let redactor = Redactor::new(&redaction::RedactionProfile {
+            phone_numbers: false,
+            ..Default::default()
+        })
+        .unwrap();
+        let html = render_html(&redactor.conversation(&conversation));
+        assert!(!html.contains("example.com"));
+        assert!(!html.contains("sk-proj"));
+        assert!(html.contains("Mail [redacted email], key [redacted API key]"));
+    }
+
+    #[test]
+    fn pdf_text_wraps_and_paginates() {
+        assert_eq!(
+            wrap("one two three\n\nfour", 9, false),
+            vec!["one two", "three", "", "four"]
+        );
+        assert_eq!(wrap("abcdefghij", 4, false), vec!["abcd", "efgh", "ij"]);
+        assert_eq!(wrap("  if x {\ty }", 8, true), vec!["  if x {", "    y }"]);
+        assert_eq!(pdf_safe("café – 東京"), "café – ??");
+
+        let mut long = conversation();
+        long.messages[1].content = "```\nfn main() {}\n```\n".repeat(200);
+        let blocks = pdf_blocks(&long, Some("- Shorten URLs"), &[]);
+        let pages = layout(&blocks);
+        assert!(pages.len() > 1);
+        assert_eq!(pages[0][0].style, PdfStyle::Title);
+        assert!(pages
+            .iter()
+            .flatten()
+            .all(|line| line.y >= MARGIN && line.y <= BODY_TOP));
+        let sections: Vec<&str> = blocks
+            .iter()
+            .filter(|(style, _)| *style == PdfStyle::Section)
+            .map(|(_, text)| text.as_str())
+            .collect();
+        assert_eq!(sections, vec!["Notes"]);
+
+        let pdf = render_pdf(&long.title, &blocks).unwrap();
+        assert!(pdf.starts_with(b"%PDF-"));
+    }
+
+    #[test]
+    fn json_export_round_trips() {
+        let raw = render_json(vec![conversation()]).unwrap();
+        let document: ExportDocument = serde_json::from_str(&raw).unwrap();
+        assert_eq!(document.version, EXPORT_FORMAT_VERSION);
+        assert_eq!(document.conversations.len(), 1);
+        assert_eq!(document.conversations[0].messages[1].id, "m2");
+    }
+
+    #[test]
+    fn default_file_name_is_filesystem_safe() {
+        assert_eq!(
+            default_file_name("Q3: plans / ideas?", ExportFormat::Markdown),
+            "Q3-plans-ideas.md"
+        );
+        assert_eq!(
+            default_file_name("///", ExportFormat::Json),
+            "conversation.json"
+        );
+    }
+
+    #[test]
+    fn parse_backup_rejects_invalid_documents() {
+        assert!(parse_backup("not json").is_err());
+        assert!(parse_backup(r#"{"version": 99, "exportedAt": 0, "conversations": []}"#).is_err());
+
+        let empty_title =
+            r#"{"version": 1, "exportedAt": 0, "conversations": [{"id": "c1", "title": " "}]}"#;
+        assert!(parse_backup(empty_title).is_err());
+    }
+
+    #[tokio::test]
+    async fn import_is_transactional_and_deduplicates() {
+        let pool = crate::db::test_pool().await;
+        let raw = render_json(vec![conversation()]).unwrap();
+
+        let first = import(&pool, parse_backup(&raw).unwrap()).await.unwrap();
+        assert_eq!(first.conversations_inserted, 1);
+        assert_eq!(first.messages_inserted, 2);
+
+        // A newer backup of the same conversation only adds the new message
+        let mut newer = conversation();
+        newer.messages.push(Message {
+            id: "m3".into(),
+            role: MessageRole::User,
+            content: "What about analytics?".into(),
+            timestamp: 1_700_000_120_000,
+            attached_files: None,
+            truncated: false,
+        });
+        let second = import(
+            &pool,
+            parse_backup(&render_json(vec![newer]).unwrap()).unwrap(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            second,
+            ImportReport {
+                conversations_inserted: 0,
+                conversations_skipped: 1,
+                messages_inserted: 1,
+                messages_skipped: 2,
+            }
+        );
+
+        let stored = db::chat::get(&pool, "c1").await.unwrap().unwrap();
+        assert_eq!(stored.messages.len(), 3);
+    }
+}
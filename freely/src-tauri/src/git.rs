@@ -0,0 +1,267 @@
+//! Git commands for the active project.
+//!
+//! Runs the `git` binary in the active project's directory, so the user's
+//! own configuration (identity, hooks, signing, credential helpers) applies
+//! exactly as in a terminal. Prompts are disabled: a command that would ask
+//! for input fails instead of hanging.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+use tokio::process::Command;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitFileStatus {
+    pub path: String,
+    /// The old path of a renamed or copied file.
+    pub orig_path: Option<String>,
+    /// Porcelain status code in the index, e.g. "M", "A", "?".
+    pub index: String,
+    /// Porcelain status code in the working tree.
+    pub worktree: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitStatus {
+    /// `None` on a detached HEAD.
+    pub branch: Option<String>,
+    pub upstream: Option<String>,
+    pub ahead: u32,
+    pub behind: u32,
+    pub files: Vec<GitFileStatus>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitBranch {
+    pub name: String,
+    pub current: bool,
+    pub upstream: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GitCommit {
+    pub hash: String,
+    pub summary: String,
+}
+
+async fn project_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    crate::db::projects::active_path(app)
+        .await?
+        .ok_or_else(|| "No active project".to_string())
+}
+
+async fn run_git(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        // "nothing to commit" and similar go to stdout
+        let message = if stderr.trim().is_empty() {
+            stdout.trim()
+        } else {
+            stderr.trim()
+        };
+        return Err(format!("git {} failed: {}", args[0], message));
+    }
+    Ok(stdout)
+}
+
+/// The `## branch...upstream [ahead 1, behind 2]` header of
+/// `git status --branch`.
+fn parse_branch_header(header: &str, status: &mut GitStatus) {
+    let (names, counts) = match header.split_once(" [") {
+        Some((names, counts)) => (names, counts.trim_end_matches(']')),
+        None => (header, ""),
+    };
+    let (branch, upstream) = match names.split_once("...") {
+        Some((branch, upstream)) => (branch, Some(upstream)),
+        None => (names, None),
+    };
+    let branch = branch.strip_prefix("No commits yet on ").unwrap_or(branch);
+    status.branch = (!branch.starts_with("HEAD (")).then(|| branch.to_string());
+    status.upstream = upstream.map(str::to_string);
+    for count in counts.split(", ") {
+        if let Some(n) = count.strip_prefix("ahead ") {
+            status.ahead = n.parse().unwrap_or(0);
+        } else if let Some(n) = count.strip_prefix("behind ") {
+            status.behind = n.parse().unwrap_or(0);
+        }
+    }
+}
+
+/// Output of `git status --porcelain=v1 --branch -z`.
+fn parse_status(output: &str) -> GitStatus {
+    let mut status = GitStatus::default();
+    let mut entries = output.split('\0').filter(|entry| !entry.is_empty());
+    while let Some(entry) = entries.next() {
+        if let Some(header) = entry.strip_prefix("## ") {
+            parse_branch_header(header, &mut status);
+            continue;
+        }
+        if entry.len() < 4 || !entry.is_char_boundary(3) {
+            continue;
+        }
+        let (codes, path) = entry.split_at(3);
+        let index = &codes[..1];
+        let worktree = &codes[1..2];
+        // Renames and copies are followed by the old path
+        let orig_path = if matches!(index, "R" | "C") {
+            entries.next().map(str::to_string)
+        } else {
+            None
+        };
+        status.files.push(GitFileStatus {
+            path: path.to_string(),
+            orig_path,
+            index: index.to_string(),
+            worktree: worktree.to_string(),
+        });
+    }
+    status
+}
+
+const BRANCH_FORMAT: &str = "--format=%(HEAD)%00%(refname:short)%00%(upstream:short)";
+
+/// Output of `git for-each-ref` with [`BRANCH_FORMAT`].
+fn parse_branches(output: &str) -> Vec<GitBranch> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\0');
+            let head = fields.next()?;
+            let name = fields.next()?;
+            let upstream = fields.next().filter(|u| !u.is_empty());
+            Some(GitBranch {
+                name: name.to_string(),
+                current: head == "*",
+                upstream: upstream.map(str::to_string),
+            })
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub async fn git_status(app: AppHandle) -> Result<GitStatus, String> {
+    let dir = project_dir(&app).await?;
+    let output = run_git(&dir, &["status", "--porcelain=v1", "--branch", "-z"]).await?;
+    Ok(parse_status(&output))
+}
+
+/// Unstaged changes, or staged ones with `staged`, optionally limited to
+/// `path`.
+#[tauri::command]
+pub async fn git_diff(
+    app: AppHandle,
+    staged: Option<bool>,
+    path: Option<String>,
+) -> Result<String, String> {
+    let dir = project_dir(&app).await?;
+    let mut args = vec!["diff", "--no-color", "--no-ext-diff"];
+    if staged.unwrap_or(false) {
+        args.push("--cached");
+    }
+    if let Some(path) = path.as_deref() {
+        args.extend(["--", path]);
+    }
+    run_git(&dir, &args).await
+}
+
+/// Stage `paths`, or every change when `None`, and commit them.
+#[tauri::command]
+pub async fn git_commit(
+    app: AppHandle,
+    message: String,
+    paths: Option<Vec<String>>,
+) -> Result<GitCommit, String> {
+    if message.trim().is_empty() {
+        return Err("Commit message must not be empty".to_string());
+    }
+    let dir = project_dir(&app).await?;
+    match &paths {
+        Some(paths) if paths.is_empty() => return Err("No files to commit".to_string()),
+        Some(paths) => {
+            let mut args = vec!["add", "--"];
+            args.extend(paths.iter().map(String::as_str));
+            run_git(&dir, &args).await?;
+        }
+        None => {
+            run_git(&dir, &["add", "--all"]).await?;
+        }
+    }
+    run_git(&dir, &["commit", "--message", &message]).await?;
+    let hash = run_git(&dir, &["rev-parse", "HEAD"]).await?;
+    Ok(GitCommit {
+        hash: hash.trim().to_string(),
+        summary: message
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_string(),
+    })
+}
+
+#[tauri::command]
+pub async fn git_branch_list(app: AppHandle) -> Result<Vec<GitBranch>, String> {
+    let dir = project_dir(&app).await?;
+    let output = run_git(&dir, &["for-each-ref", BRANCH_FORMAT, "refs/heads"]).await?;
+    Ok(parse_branches(&output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn porcelain_output_is_parsed() {
+        let status = parse_status(
+            "## main...origin/main [ahead 2, behind 1]\0 M src/lib.rs\0R  new.rs\0old.rs\0?? notes.md\0",
+        );
+        assert_eq!(status.branch.as_deref(), Some("main"));
+        assert_eq!(status.upstream.as_deref(), Some("origin/main"));
+        assert_eq!((status.ahead, status.behind), (2, 1));
+        assert_eq!(status.files.len(), 3);
+        assert_eq!(status.files[0].path, "src/lib.rs");
+        assert_eq!(
+            (
+                status.files[0].index.as_str(),
+                status.files[0].worktree.as_str()
+            ),
+            (" ", "M")
+        );
+        assert_eq!(status.files[1].orig_path.as_deref(), Some("old.rs"));
+        assert_eq!(status.files[2].index, "?");
+
+        let detached = parse_status("## HEAD (no branch)\0");
+        assert_eq!(detached.branch, None);
+        let fresh = parse_status("## No commits yet on main\0");
+        assert_eq!(fresh.branch.as_deref(), Some("main"));
+
+        let branches = parse_branches("*\0main\0origin/main\n \0feature/x\0\n");
+        assert_eq!(
+            branches,
+            [
+                GitBranch {
+                    name: "main".to_string(),
+                    current: true,
+                    upstream: Some("origin/main".to_string()),
+                },
+                GitBranch {
+                    name: "feature/x".to_string(),
+                    current: false,
+                    upstream: None,
+                },
+            ]
+        );
+    }
+}
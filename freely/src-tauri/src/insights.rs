@@ -0,0 +1,295 @@
+//! Local-only statistics for the insights dashboard.
+//!
+//! Everything here is computed on demand from tables the app already keeps
+//! (conversations, messages, transcripts and `usage_events`); nothing is
+//! stored separately and nothing leaves the machine. Weeks start on Monday
+//! in local time.
+
+use crate::usage::UsageRange;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tauri::AppHandle;
+
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+const DEFAULT_RANGE_DAYS: i64 = 90;
+const TOP_PROMPTS: i64 = 10;
+/// Longer prompts are cut to this many characters in the results.
+const PROMPT_PREVIEW_CHARS: usize = 200;
+
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct WeeklyCount {
+    /// Monday of the week, `YYYY-MM-DD` in local time.
+    pub week: String,
+    pub conversations: i64,
+    pub messages: i64,
+}
+
+/// A user message sent more than once, compared ignoring case and
+/// surrounding whitespace.
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptUse {
+    pub prompt: String,
+    pub uses: i64,
+    pub last_used_at: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct SttMinutes {
+    /// `microphone`, `system_audio` or `mixed`.
+    pub source: String,
+    pub minutes: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelLatency {
+    pub provider: String,
+    pub model: String,
+    pub requests: i64,
+    pub avg_latency_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Insights {
+    pub from: i64,
+    pub to: i64,
+    pub weeks: Vec<WeeklyCount>,
+    pub top_prompts: Vec<PromptUse>,
+    pub stt_minutes: Vec<SttMinutes>,
+    pub total_stt_minutes: f64,
+    /// Over every completion in the range; `None` when there were none.
+    pub avg_response_latency_ms: Option<f64>,
+    pub latency_by_model: Vec<ModelLatency>,
+}
+
+/// Conversations started and messages sent per week in `[from, to)`,
+/// skipping deleted conversations.
+async fn weekly_counts(pool: &SqlitePool, from: i64, to: i64) -> Result<Vec<WeeklyCount>, String> {
+    sqlx::query_as::<_, WeeklyCount>(
+        "WITH activity AS (
+             SELECT c.created_at AS at, 1 AS conversation, 0 AS message
+             FROM conversations c
+             WHERE c.deleted_at IS NULL AND c.created_at >= ?1 AND c.created_at < ?2
+             UNION ALL
+             SELECT m.timestamp, 0, 1
+             FROM messages m JOIN conversations c ON c.id = m.conversation_id
+             WHERE c.deleted_at IS NULL AND m.role = 'user'
+               AND m.timestamp >= ?1 AND m.timestamp < ?2
+         )
+         SELECT date(at / 1000, 'unixepoch', 'localtime', 'weekday 0', '-6 days') AS week,
+                SUM(conversation) AS conversations,
+                SUM(message) AS messages
+         FROM activity
+         GROUP BY week
+         ORDER BY week ASC",
+    )
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load weekly activity: {}", e))
+}
+
+async fn top_prompts(pool: &SqlitePool, from: i64, to: i64) -> Result<Vec<PromptUse>, String> {
+    let mut prompts = sqlx::query_as::<_, PromptUse>(
+        "SELECT TRIM(MAX(content)) AS prompt, COUNT(*) AS uses, MAX(timestamp) AS last_used_at
+         FROM messages
+         WHERE role = 'user' AND timestamp >= ? AND timestamp < ? AND TRIM(content) <> ''
+         GROUP BY LOWER(TRIM(content))
+         HAVING COUNT(*) > 1
+         ORDER BY uses DESC, last_used_at DESC
+         LIMIT ?",
+    )
+    .bind(from)
+    .bind(to)
+    .bind(TOP_PROMPTS)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load prompts: {}", e))?;
+
+    for prompt in &mut prompts {
+        if let Some((cut, _)) = prompt.prompt.char_indices().nth(PROMPT_PREVIEW_CHARS) {
+            prompt.prompt.truncate(cut);
+            prompt.prompt.push('…');
+        }
+    }
+    Ok(prompts)
+}
+
+async fn stt_minutes(pool: &SqlitePool, from: i64, to: i64) -> Result<Vec<SttMinutes>, String> {
+    sqlx::query_as::<_, SttMinutes>(
+        "SELECT source, TOTAL(ended_at - started_at) / 60000.0 AS minutes
+         FROM transcripts
+         WHERE started_at >= ? AND started_at < ?
+         GROUP BY source
+         ORDER BY minutes DESC",
+    )
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load transcription time: {}", e))
+}
+
+async fn latency_by_model(
+    pool: &SqlitePool,
+    from: i64,
+    to: i64,
+) -> Result<Vec<ModelLatency>, String> {
+    sqlx::query_as::<_, ModelLatency>(
+        "SELECT provider, model, COUNT(*) AS requests, AVG(latency_ms) AS avg_latency_ms
+         FROM usage_events
+         WHERE created_at >= ? AND created_at < ?
+         GROUP BY provider, model
+         ORDER BY requests DESC, provider, model",
+    )
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load response latency: {}", e))
+}
+
+pub(crate) async fn compute(pool: &SqlitePool, from: i64, to: i64) -> Result<Insights, String> {
+    let stt_minutes = stt_minutes(pool, from, to).await?;
+    let latency_by_model = latency_by_model(pool, from, to).await?;
+
+    let requests: i64 = latency_by_model.iter().map(|m| m.requests).sum();
+    let avg_response_latency_ms = (requests > 0).then(|| {
+        latency_by_model
+            .iter()
+            .map(|m| m.avg_latency_ms * m.requests as f64)
+            .sum::<f64>()
+            / requests as f64
+    });
+
+    Ok(Insights {
+        from,
+        to,
+        weeks: weekly_counts(pool, from, to).await?,
+        top_prompts: top_prompts(pool, from, to).await?,
+        total_stt_minutes: stt_minutes.iter().map(|s| s.minutes).sum(),
+        stt_minutes,
+        avg_response_latency_ms,
+        latency_by_model,
+    })
+}
+
+/// Dashboard statistics over `range`, by default the last 90 days.
+#[tauri::command]
+pub async fn get_insights(app: AppHandle, range: Option<UsageRange>) -> Result<Insights, String> {
+    let range = range.unwrap_or_default();
+    let to = range.to.unwrap_or_else(crate::db::now_millis);
+    let from = range.from.unwrap_or(to - DEFAULT_RANGE_DAYS * DAY_MS);
+    if from >= to {
+        return Err("Insights range must end after it starts".to_string());
+    }
+
+    let pool = crate::db::pool(&app).await?;
+    compute(&pool, from, to).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn conversation(pool: &SqlitePool, id: &str, created_at: i64) {
+        sqlx::query(
+            "INSERT INTO conversations (id, title, created_at, updated_at) VALUES (?, ?, ?, ?)",
+        )
+        .bind(id)
+        .bind(id)
+        .bind(created_at)
+        .bind(created_at)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    async fn message(pool: &SqlitePool, conversation_id: &str, content: &str, timestamp: i64) {
+        sqlx::query(
+            "INSERT INTO messages (id, conversation_id, role, content, timestamp)
+             VALUES (?, ?, 'user', ?, ?)",
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(conversation_id)
+        .bind(content)
+        .bind(timestamp)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn insights_summarise_existing_tables() {
+        let pool = crate::db::test_pool().await;
+        let start = chrono::NaiveDate::from_ymd_opt(2024, 5, 6)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+            .and_local_timezone(chrono::Local)
+            .unwrap()
+            .timestamp_millis();
+
+        conversation(&pool, "a", start).await;
+        conversation(&pool, "b", start + 8 * DAY_MS).await;
+        message(&pool, "a", "Make flashcards", start).await;
+        message(&pool, "a", "  make FLASHCARDS ", start + 1000).await;
+        message(&pool, "b", "Make flashcards", start + 8 * DAY_MS).await;
+        message(&pool, "b", "Only once", start + 8 * DAY_MS).await;
+
+        sqlx::query(
+            "INSERT INTO transcripts (id, source, text, started_at, ended_at, created_at)
+             VALUES ('t1', 'microphone', 'hi', ?1, ?1 + 90000, ?1),
+                    ('t2', 'system_audio', 'hello', ?1, ?1 + 30000, ?1)",
+        )
+        .bind(start)
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO usage_events (id, provider, model, source, input_tokens, output_tokens,
+                 latency_ms, created_at)
+             VALUES ('u1', 'openai', 'gpt-4o', 'chat', 1, 1, 1000, ?1),
+                    ('u2', 'openai', 'gpt-4o', 'chat', 1, 1, 2000, ?1),
+                    ('u3', 'ollama', 'llama3', 'chat', 1, 1, 400, ?1)",
+        )
+        .bind(start)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let insights = compute(&pool, start - DAY_MS, start + 30 * DAY_MS)
+            .await
+            .unwrap();
+        assert_eq!(
+            insights.weeks,
+            [
+                WeeklyCount {
+                    week: "2024-05-06".to_string(),
+                    conversations: 1,
+                    messages: 2,
+                },
+                WeeklyCount {
+                    week: "2024-05-13".to_string(),
+                    conversations: 1,
+                    messages: 2,
+                },
+            ]
+        );
+        assert_eq!(insights.top_prompts.len(), 1);
+        assert_eq!(insights.top_prompts[0].uses, 3);
+        assert_eq!(insights.total_stt_minutes, 2.0);
+        assert_eq!(insights.stt_minutes[0].source, "microphone");
+        assert_eq!(insights.latency_by_model[0].avg_latency_ms, 1500.0);
+        assert_eq!(insights.avg_response_latency_ms, Some(3400.0 / 3.0));
+
+        let empty = compute(&pool, 0, 1).await.unwrap();
+        assert!(empty.weeks.is_empty());
+        assert_eq!(empty.avg_response_latency_ms, None);
+    }
+}
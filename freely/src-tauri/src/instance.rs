@@ -0,0 +1,58 @@
+//! Single-instance handling.
+//!
+//! Two processes would share the SQLite database, audio devices and global
+//! shortcuts, so `tauri-plugin-single-instance` makes a second launch exit
+//! right away and hands its arguments to the running app instead. The
+//! plugin passes `freely://` links on to [`crate::deeplink`] itself; the
+//! running app then shows its main window and emits the remaining
+//! arguments as `second-instance`.
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter};
+use tracing::warn;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecondInstance {
+    /// Arguments after the executable, without deep links.
+    pub args: Vec<String>,
+    /// Working directory of the second launch.
+    pub cwd: String,
+}
+
+/// Arguments worth forwarding: the executable path and deep links (which
+/// arrive through `deep-link` anyway) are dropped.
+fn forwarded_args(argv: Vec<String>) -> Vec<String> {
+    argv.into_iter()
+        .skip(1)
+        .filter(|arg| !arg.starts_with("freely://"))
+        .collect()
+}
+
+/// Called in the running app with the argv and working directory of a
+/// second launch.
+pub fn on_second_instance(app: &AppHandle, argv: Vec<String>, cwd: String) {
+    if let Err(e) = crate::window::show_main_window(app) {
+        warn!("{}", e);
+    }
+    let event = SecondInstance {
+        args: forwarded_args(argv),
+        cwd,
+    };
+    if let Err(e) = app.emit("second-instance", &event) {
+        warn!("Failed to emit second-instance: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn executable_and_links_are_not_forwarded() {
+        let argv = ["/usr/bin/freely", "--ask", "hello", "freely://new?prompt=x"]
+            .map(String::from)
+            .to_vec();
+        assert_eq!(forwarded_args(argv), ["--ask", "hello"]);
+    }
+}
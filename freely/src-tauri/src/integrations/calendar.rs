@@ -0,0 +1,1292 @@
+//! Calendar awareness for meeting mode, from ICS feeds.
+//!
+//! The `calendar` setting lists the user's feed URLs (a calendar's "secret
+//! address in iCal format", `webcal://` links included). Enabled feeds are
+//! fetched every 15 minutes and their events for the next two weeks are
+//! stored in `calendar_events` (migration 27), recurring events expanded
+//! into one row per occurrence. `get_upcoming_events` reads them back.
+//!
+//! A few minutes before a timed event starts (`leadMinutes`), the
+//! `reminder` setting decides what happens: nothing, a notification
+//! offering to record it, or recording it straight away with the saved
+//! `meetingOptions`. Either way `calendar-event-starting` is emitted with
+//! the event, and each occurrence is handled once.
+//!
+//! Parsing covers what calendar apps publish for meetings: `TZID` zones
+//! (IANA names; others are read as local time), all-day events, `EXDATE`,
+//! moved or cancelled occurrences (`RECURRENCE-ID`), and `RRULE`s with
+//! `FREQ`, `INTERVAL`, `COUNT`, `UNTIL` and plain `BYDAY`. Events whose rule
+//! uses anything else only get their first occurrence.
+
+use crate::meeting::MeetingOptions;
+use crate::notify::{self, NotificationAction};
+use chrono::{
+    Datelike, Duration as Span, Local, LocalResult, NaiveDate, NaiveDateTime, TimeZone, Utc,
+    Weekday,
+};
+use chrono_tz::Tz;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tracing::{info, warn};
+
+/// Settings key for [`CalendarConfig`].
+pub(crate) const SETTING_KEY: &str = "calendar";
+const POLL_INTERVAL: Duration = Duration::from_secs(15 * 60);
+const REMINDER_INTERVAL: Duration = Duration::from_secs(30);
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_FEED_BYTES: usize = 10 * 1024 * 1024;
+const LOOKAHEAD_DAYS: i64 = 14;
+/// Events that started this recently are still offered, e.g. when the app
+/// was opened a little late.
+const LATE_GRACE_MS: i64 = 10 * 60 * 1000;
+const MAX_LEAD_MINUTES: u32 = 120;
+const MAX_FEEDS: usize = 20;
+/// Bounds the expansion of one recurrence rule.
+const MAX_OCCURRENCES: usize = 5000;
+const DEFAULT_UPCOMING_HOURS: u32 = 24;
+
+/// Why each feed's last fetch failed, by feed id.
+static FEED_ERRORS: Lazy<Mutex<HashMap<String, String>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_lead_minutes() -> u32 {
+    2
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarFeed {
+    /// Assigned when the feed is first saved.
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    pub url: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+/// What to do when a calendar event is about to start.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MeetingReminder {
+    Off,
+    /// Notify, with a button that starts recording.
+    #[default]
+    Prompt,
+    /// Start recording with `meetingOptions`.
+    AutoStart,
+}
+
+/// Stored under the `calendar` setting.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarConfig {
+    #[serde(default)]
+    pub feeds: Vec<CalendarFeed>,
+    #[serde(default)]
+    pub reminder: MeetingReminder,
+    #[serde(default = "default_lead_minutes")]
+    pub lead_minutes: u32,
+    /// How meetings started from the calendar are recorded; the title is
+    /// taken from the event.
+    #[serde(default)]
+    pub meeting_options: Option<MeetingOptions>,
+}
+
+impl Default for CalendarConfig {
+    fn default() -> Self {
+        Self {
+            feeds: Vec::new(),
+            reminder: MeetingReminder::default(),
+            lead_minutes: default_lead_minutes(),
+            meeting_options: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarEvent {
+    /// Feed id, UID and start, so each occurrence has its own.
+    pub id: String,
+    pub feed_id: String,
+    pub uid: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub location: Option<String>,
+    /// The event's link, or the first link in its location or description,
+    /// which is usually the video call.
+    pub url: Option<String>,
+    pub starts_at: i64,
+    pub ends_at: i64,
+    pub all_day: bool,
+    pub reminded_at: Option<i64>,
+    /// The meeting recorded for the event, if any.
+    pub meeting_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedStatus {
+    pub feed_id: String,
+    pub name: String,
+    pub events: Option<usize>,
+    pub error: Option<String>,
+}
+
+// ============================================================================
+// ICS parsing
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Zone {
+    Utc,
+    Named(Tz),
+    /// No zone given, or one we don't know: the user's local time.
+    Floating,
+}
+
+/// A `DATE` or `DATE-TIME` value, kept as wall-clock time so recurrences
+/// stay at the same local hour across DST changes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct IcsTime {
+    local: NaiveDateTime,
+    zone: Zone,
+    all_day: bool,
+}
+
+fn resolve<T: TimeZone>(zone: &T, local: NaiveDateTime) -> Option<i64> {
+    match zone.from_local_datetime(&local) {
+        LocalResult::Single(time) => Some(time.timestamp_millis()),
+        LocalResult::Ambiguous(earliest, _) => Some(earliest.timestamp_millis()),
+        // Skipped by a DST change; the wall clock reads an hour later
+        LocalResult::None => zone
+            .from_local_datetime(&(local + Span::hours(1)))
+            .earliest()
+            .map(|time| time.timestamp_millis()),
+    }
+}
+
+impl IcsTime {
+    fn at(&self, local: NaiveDateTime) -> Option<i64> {
+        match self.zone {
+            Zone::Utc => Some(Utc.from_utc_datetime(&local).timestamp_millis()),
+            Zone::Named(tz) => resolve(&tz, local),
+            Zone::Floating => resolve(&Local, local),
+        }
+    }
+
+    fn millis(&self) -> Option<i64> {
+        self.at(self.local)
+    }
+}
+
+/// `Europe/Berlin`, also out of prefixed ids such as
+/// `/mozilla.org/20050126_1/Europe/Berlin`.
+fn parse_tz(tzid: &str) -> Option<Tz> {
+    let tzid = tzid.trim();
+    std::iter::once(tzid)
+        .chain(tzid.match_indices('/').map(|(i, _)| &tzid[i + 1..]))
+        .find_map(|candidate| candidate.parse::<Tz>().ok())
+}
+
+fn parse_time(value: &str, tzid: Option<&str>) -> Option<IcsTime> {
+    let value = value.trim();
+    if value.len() == 8 {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+        return Some(IcsTime {
+            local: date.and_hms_opt(0, 0, 0)?,
+            zone: Zone::Floating,
+            all_day: true,
+        });
+    }
+    let (value, utc) = match value.strip_suffix('Z') {
+        Some(value) => (value, true),
+        None => (value, false),
+    };
+    let local = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    let zone = if utc {
+        Zone::Utc
+    } else {
+        tzid.and_then(parse_tz)
+            .map(Zone::Named)
+            .unwrap_or(Zone::Floating)
+    };
+    Some(IcsTime {
+        local,
+        zone,
+        all_day: false,
+    })
+}
+
+/// An ISO 8601 duration such as `PT1H30M`, `P1D` or `-PT15M`.
+fn parse_duration(value: &str) -> Option<Span> {
+    let value = value.trim();
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let mut rest = value.strip_prefix('P')?;
+    let mut total = Span::zero();
+    let mut in_time = false;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('T') {
+            in_time = true;
+            rest = after;
+            continue;
+        }
+        let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+        let amount: i64 = rest[..digits].parse().ok()?;
+        let unit = rest[digits..].chars().next()?;
+        total += match (unit, in_time) {
+            ('W', false) => Span::weeks(amount),
+            ('D', false) => Span::days(amount),
+            ('H', true) => Span::hours(amount),
+            ('M', true) => Span::minutes(amount),
+            ('S', true) => Span::seconds(amount),
+            _ => return None,
+        };
+        rest = &rest[digits + unit.len_utf8()..];
+    }
+    Some(if negative { -total } else { total })
+}
+
+fn unescape_text(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    out
+}
+
+/// Content lines with folded continuations joined back on.
+fn unfold(ics: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in ics.lines() {
+        if let Some(rest) = line.strip_prefix([' ', '\t']) {
+            if let Some(last) = lines.last_mut() {
+                last.push_str(rest);
+                continue;
+            }
+        }
+        lines.push(line.to_string());
+    }
+    lines
+}
+
+struct Property<'a> {
+    name: String,
+    params: Vec<(String, String)>,
+    value: &'a str,
+}
+
+impl Property<'_> {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+fn parse_property(line: &str) -> Option<Property<'_>> {
+    // Parameter values can be quoted, and may contain ':' then
+    let mut quoted = false;
+    let (colon, _) = line.char_indices().find(|(_, c)| {
+        if *c == '"' {
+            quoted = !quoted;
+        }
+        *c == ':' && !quoted
+    })?;
+    let mut parts = line[..colon].split(';');
+    let name = parts.next()?.trim().to_ascii_uppercase();
+    let params = parts
+        .filter_map(|param| param.split_once('='))
+        .map(|(key, value)| {
+            (
+                key.to_ascii_uppercase(),
+                value.trim_matches('"').to_string(),
+            )
+        })
+        .collect();
+    Some(Property {
+        name,
+        params,
+        value: &line[colon + 1..],
+    })
+}
+
+#[derive(Debug, Default)]
+struct RawEvent {
+    uid: String,
+    summary: Option<String>,
+    description: Option<String>,
+    location: Option<String>,
+    url: Option<String>,
+    start: Option<IcsTime>,
+    end: Option<IcsTime>,
+    duration: Option<Span>,
+    rrule: Option<String>,
+    exdates: Vec<IcsTime>,
+    recurrence_id: Option<IcsTime>,
+    cancelled: bool,
+}
+
+fn parse_events(ics: &str) -> Vec<RawEvent> {
+    let mut events = Vec::new();
+    let mut current: Option<RawEvent> = None;
+    // Components inside the event, such as VALARM, whose properties aren't the event's
+    let mut nested = 0usize;
+    for line in unfold(ics) {
+        let Some(property) = parse_property(&line) else {
+            continue;
+        };
+        let value = property.value;
+        match (property.name.as_str(), current.as_mut()) {
+            ("BEGIN", None) if value.eq_ignore_ascii_case("VEVENT") => {
+                current = Some(RawEvent::default());
+            }
+            ("BEGIN", Some(_)) => nested += 1,
+            ("END", Some(_)) if nested > 0 => nested -= 1,
+            ("END", Some(_)) if value.eq_ignore_ascii_case("VEVENT") => {
+                events.extend(current.take());
+            }
+            (_, Some(_)) if nested > 0 => {}
+            (name, Some(event)) => {
+                let tzid = property.param("TZID");
+                match name {
+                    "UID" => event.uid = value.trim().to_string(),
+                    "SUMMARY" => event.summary = Some(unescape_text(value)),
+                    "DESCRIPTION" => event.description = Some(unescape_text(value)),
+                    "LOCATION" => event.location = Some(unescape_text(value)),
+                    "URL" => event.url = Some(value.trim().to_string()),
+                    "DTSTART" => event.start = parse_time(value, tzid),
+                    "DTEND" => event.end = parse_time(value, tzid),
+                    "DURATION" => event.duration = parse_duration(value),
+                    "RRULE" => event.rrule = Some(value.trim().to_string()),
+                    "EXDATE" => event
+                        .exdates
+                        .extend(value.split(',').filter_map(|v| parse_time(v, tzid))),
+                    "RECURRENCE-ID" => event.recurrence_id = parse_time(value, tzid),
+                    "STATUS" => event.cancelled = value.trim().eq_ignore_ascii_case("CANCELLED"),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    events
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    frequency: Frequency,
+    interval: u32,
+    count: Option<usize>,
+    until: Option<IcsTime>,
+    by_day: Vec<Weekday>,
+}
+
+fn parse_weekday(value: &str) -> Option<Weekday> {
+    Some(match value {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    })
+}
+
+/// `None` for rules using parts we don't expand.
+fn parse_rule(rrule: &str) -> Option<Rule> {
+    let mut rule = Rule {
+        frequency: Frequency::Daily,
+        interval: 1,
+        count: None,
+        until: None,
+        by_day: Vec::new(),
+    };
+    let mut frequency = None;
+    for part in rrule.split(';') {
+        let (key, value) = part.split_once('=')?;
+        match key.to_ascii_uppercase().as_str() {
+            "FREQ" => {
+                frequency = Some(match value.to_ascii_uppercase().as_str() {
+                    "DAILY" => Frequency::Daily,
+                    "WEEKLY" => Frequency::Weekly,
+                    "MONTHLY" => Frequency::Monthly,
+                    "YEARLY" => Frequency::Yearly,
+                    _ => return None,
+                })
+            }
+            "INTERVAL" => rule.interval = value.parse().ok().filter(|i| *i > 0)?,
+            "COUNT" => rule.count = Some(value.parse().ok()?),
+            "UNTIL" => rule.until = Some(parse_time(value, None)?),
+            // An ordinal such as 1MO (first Monday) isn't supported
+            "BYDAY" => {
+                rule.by_day = value
+                    .split(',')
+                    .map(|day| parse_weekday(&day.to_ascii_uppercase()))
+                    .collect::<Option<_>>()?
+            }
+            "WKST" => {}
+            _ => return None,
+        }
+    }
+    rule.frequency = frequency?;
+    if !rule.by_day.is_empty() && !matches!(rule.frequency, Frequency::Daily | Frequency::Weekly) {
+        return None;
+    }
+    Some(rule)
+}
+
+fn add_months(start: NaiveDateTime, months: i64) -> Option<NaiveDateTime> {
+    let index = start.year() as i64 * 12 + start.month0() as i64 + months;
+    let date = NaiveDate::from_ymd_opt(
+        i32::try_from(index.div_euclid(12)).ok()?,
+        index.rem_euclid(12) as u32 + 1,
+        start.day(),
+    )?;
+    Some(date.and_time(start.time()))
+}
+
+/// Wall-clock starts of `rule` from `start` on, in order. Stops at
+/// `horizon`, and skips whole periods before `from` when the rule has no
+/// count to keep.
+fn expand(
+    start: NaiveDateTime,
+    rule: &Rule,
+    from: NaiveDateTime,
+    horizon: NaiveDateTime,
+) -> Vec<NaiveDateTime> {
+    let interval = rule.interval as i64;
+    let period_days = match rule.frequency {
+        Frequency::Daily => interval,
+        Frequency::Weekly => 7 * interval,
+        _ => 0,
+    };
+    let mut period = 0i64;
+    if rule.count.is_none() && period_days > 0 && from > start {
+        period = ((from - start).num_days() / period_days - 1).max(0);
+    }
+
+    let week_start = start.date() - Span::days(start.weekday().num_days_from_monday() as i64);
+    let mut days = rule.by_day.clone();
+    days.sort_by_key(|day| day.num_days_from_monday());
+    let mut starts = Vec::new();
+    let mut seen = 0usize;
+    while starts.len() < MAX_OCCURRENCES {
+        let candidates: Vec<NaiveDateTime> = match rule.frequency {
+            Frequency::Daily => {
+                let at = start + Span::days(period * interval);
+                let matches = days.is_empty() || days.contains(&at.weekday());
+                if matches {
+                    vec![at]
+                } else {
+                    Vec::new()
+                }
+            }
+            Frequency::Weekly => {
+                let week = week_start + Span::weeks(period * interval);
+                if days.is_empty() {
+                    vec![start + Span::weeks(period * interval)]
+                } else {
+                    days.iter()
+                        .map(|day| {
+                            (week + Span::days(day.num_days_from_monday() as i64))
+                                .and_time(start.time())
+                        })
+                        .filter(|at| *at >= start)
+                        .collect()
+                }
+            }
+            // Months without the start's day (the 31st, say) are skipped
+            Frequency::Monthly => add_months(start, period * interval).into_iter().collect(),
+            Frequency::Yearly => add_months(start, period * interval * 12)
+                .into_iter()
+                .collect(),
+        };
+        let past_horizon = match rule.frequency {
+            Frequency::Daily => start + Span::days(period * interval) > horizon,
+            Frequency::Weekly => week_start + Span::weeks(period * interval) > horizon.date(),
+            Frequency::Monthly => period * interval > 12 * 200,
+            Frequency::Yearly => period * interval > 200,
+        };
+        if past_horizon {
+            break;
+        }
+        for at in candidates {
+            if at > horizon {
+                return starts;
+            }
+            if rule.count.is_some_and(|count| seen >= count) {
+                return starts;
+            }
+            seen += 1;
+            starts.push(at);
+        }
+        period += 1;
+    }
+    starts
+}
+
+/// An event occurrence ready to store.
+#[derive(Debug, Clone, PartialEq)]
+struct Occurrence {
+    uid: String,
+    title: String,
+    description: Option<String>,
+    location: Option<String>,
+    url: Option<String>,
+    starts_at: i64,
+    ends_at: i64,
+    all_day: bool,
+}
+
+/// The first `https://` link in `text`.
+fn find_link(text: &str) -> Option<String> {
+    let start = text.find("https://")?;
+    let link: String = text[start..]
+        .chars()
+        .take_while(|c| !c.is_whitespace() && !"<>\"'".contains(*c))
+        .collect();
+    Some(link.trim_end_matches([')', '.', ',', ';']).to_string())
+}
+
+/// Occurrences of the events in `ics` that overlap `from..until`.
+fn occurrences(ics: &str, from: i64, until: i64) -> Vec<Occurrence> {
+    let events = parse_events(ics);
+    // Occurrences moved or cancelled individually, by UID and original start
+    let overridden: HashSet<(String, i64)> = events
+        .iter()
+        .filter_map(|event| {
+            let original = event.recurrence_id?.millis()?;
+            Some((event.uid.clone(), original))
+        })
+        .collect();
+    let from_local = Local
+        .timestamp_millis_opt(from)
+        .single()
+        .map(|t| t.naive_local());
+    let horizon_local = Local
+        .timestamp_millis_opt(until)
+        .single()
+        .map(|t| t.naive_local());
+    let (Some(from_local), Some(horizon_local)) = (from_local, horizon_local) else {
+        return Vec::new();
+    };
+
+    let mut found = Vec::new();
+    for event in &events {
+        let Some(start) = event.start else {
+            continue;
+        };
+        if event.cancelled {
+            continue;
+        }
+        let length = match (event.end, event.duration) {
+            (Some(end), _) => end.local - start.local,
+            (None, Some(duration)) => duration,
+            (None, None) if start.all_day => Span::days(1),
+            (None, None) => Span::zero(),
+        };
+        let rule = event
+            .rrule
+            .as_deref()
+            .filter(|_| event.recurrence_id.is_none())
+            .and_then(parse_rule);
+        let starts = match &rule {
+            // Zones are a day apart at most, so a day's margin covers them
+            Some(rule) => expand(
+                start.local,
+                rule,
+                from_local - length - Span::days(1),
+                horizon_local + Span::days(1),
+            ),
+            None => vec![start.local],
+        };
+        let until_millis = rule
+            .as_ref()
+            .and_then(|rule| rule.until)
+            .and_then(|until| until.millis());
+        let excluded: HashSet<i64> = event.exdates.iter().filter_map(IcsTime::millis).collect();
+
+        for local in starts {
+            let (Some(starts_at), Some(ends_at)) = (start.at(local), start.at(local + length))
+            else {
+                continue;
+            };
+            if until_millis.is_some_and(|until| starts_at > until) {
+                break;
+            }
+            let is_instance = rule.is_some();
+            if is_instance
+                && (excluded.contains(&starts_at)
+                    || overridden.contains(&(event.uid.clone(), starts_at)))
+            {
+                continue;
+            }
+            if starts_at >= until || ends_at.max(starts_at) < from {
+                continue;
+            }
+            let url = event.url.clone().or_else(|| {
+                event
+                    .location
+                    .as_deref()
+                    .and_then(find_link)
+                    .or_else(|| event.description.as_deref().and_then(find_link))
+            });
+            found.push(Occurrence {
+                uid: event.uid.clone(),
+                title: event
+                    .summary
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|title| !title.is_empty())
+                    .unwrap_or("Untitled event")
+                    .to_string(),
+                description: event.description.clone(),
+                location: event.location.clone(),
+                url,
+                starts_at,
+                ends_at: ends_at.max(starts_at),
+                all_day: start.all_day,
+            });
+        }
+    }
+    found.sort_by_key(|occurrence| occurrence.starts_at);
+    found
+}
+
+// ============================================================================
+// Storage
+// ============================================================================
+
+const EVENT_COLUMNS: &str = "id, feed_id, uid, title, description, location, url, starts_at,
+     ends_at, all_day, reminded_at, meeting_id";
+
+/// Replace `feed_id`'s events with `occurrences`, keeping what was already
+/// recorded about occurrences that are still there.
+async fn store_events(
+    pool: &SqlitePool,
+    feed_id: &str,
+    occurrences: &[Occurrence],
+    fetched_at: i64,
+) -> Result<(), String> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+    for occurrence in occurrences {
+        sqlx::query(
+            "INSERT INTO calendar_events
+                 (id, feed_id, uid, title, description, location, url, starts_at, ends_at,
+                  all_day, fetched_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT(id) DO UPDATE SET
+                 title = excluded.title,
+                 description = excluded.description,
+                 location = excluded.location,
+                 url = excluded.url,
+                 ends_at = excluded.ends_at,
+                 all_day = excluded.all_day,
+                 fetched_at = excluded.fetched_at",
+        )
+        .bind(format!(
+            "{}:{}:{}",
+            feed_id, occurrence.uid, occurrence.starts_at
+        ))
+        .bind(feed_id)
+        .bind(&occurrence.uid)
+        .bind(&occurrence.title)
+        .bind(&occurrence.description)
+        .bind(&occurrence.location)
+        .bind(&occurrence.url)
+        .bind(occurrence.starts_at)
+        .bind(occurrence.ends_at)
+        .bind(occurrence.all_day)
+        .bind(fetched_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to save calendar event: {}", e))?;
+    }
+    sqlx::query("DELETE FROM calendar_events WHERE feed_id = ? AND fetched_at < ?")
+        .bind(feed_id)
+        .bind(fetched_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to remove old calendar events: {}", e))?;
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to save calendar events: {}", e))
+}
+
+/// Forget the events of feeds that were removed or disabled.
+async fn prune_feeds(pool: &SqlitePool, enabled_ids: &[String]) -> Result<(), String> {
+    let ids = serde_json::to_string(enabled_ids).map_err(|e| e.to_string())?;
+    sqlx::query(
+        "DELETE FROM calendar_events WHERE feed_id NOT IN (SELECT value FROM json_each(?))",
+    )
+    .bind(ids)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to remove calendar events: {}", e))?;
+    Ok(())
+}
+
+/// Events that haven't ended by `now` and start before `until`, soonest
+/// first.
+async fn upcoming(pool: &SqlitePool, now: i64, until: i64) -> Result<Vec<CalendarEvent>, String> {
+    sqlx::query_as::<_, CalendarEvent>(&format!(
+        "SELECT {} FROM calendar_events
+         WHERE ends_at > ?1 AND starts_at < ?2
+         ORDER BY starts_at ASC, title ASC",
+        EVENT_COLUMNS
+    ))
+    .bind(now)
+    .bind(until)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to list calendar events: {}", e))
+}
+
+/// Timed events starting within `lead_ms` (or that just started) that
+/// haven't been handled yet.
+async fn due(pool: &SqlitePool, now: i64, lead_ms: i64) -> Result<Vec<CalendarEvent>, String> {
+    sqlx::query_as::<_, CalendarEvent>(&format!(
+        "SELECT {} FROM calendar_events
+         WHERE all_day = 0 AND reminded_at IS NULL
+           AND starts_at <= ?1 AND starts_at > ?2
+         ORDER BY starts_at ASC",
+        EVENT_COLUMNS
+    ))
+    .bind(now + lead_ms)
+    .bind(now - LATE_GRACE_MS)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to list due calendar events: {}", e))
+}
+
+async fn get_event(pool: &SqlitePool, id: &str) -> Result<Option<CalendarEvent>, String> {
+    sqlx::query_as::<_, CalendarEvent>(&format!(
+        "SELECT {} FROM calendar_events WHERE id = ?",
+        EVENT_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to load calendar event: {}", e))
+}
+
+async fn mark_reminded(
+    pool: &SqlitePool,
+    id: &str,
+    now: i64,
+    meeting_id: Option<&str>,
+) -> Result<(), String> {
+    sqlx::query(
+        "UPDATE calendar_events
+         SET reminded_at = COALESCE(reminded_at, ?), meeting_id = COALESCE(?, meeting_id)
+         WHERE id = ?",
+    )
+    .bind(now)
+    .bind(meeting_id)
+    .bind(id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to update calendar event: {}", e))?;
+    Ok(())
+}
+
+// ============================================================================
+// Polling and reminders
+// ============================================================================
+
+async fn load_config(pool: &SqlitePool) -> Result<CalendarConfig, String> {
+    Ok(crate::settings::get_setting(pool, SETTING_KEY)
+        .await?
+        .unwrap_or_default())
+}
+
+/// `webcal://` is how calendar apps link feeds; it is plain HTTPS.
+fn feed_url(url: &str) -> String {
+    let url = url.trim();
+    match url.strip_prefix("webcal://") {
+        Some(rest) => format!("https://{}", rest),
+        None => url.to_string(),
+    }
+}
+
+async fn fetch_feed(client: &reqwest::Client, url: &str) -> Result<String, String> {
+    let mut response = client
+        .get(feed_url(url))
+        .timeout(FETCH_TIMEOUT)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch calendar: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Calendar server answered {}", response.status()));
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("Failed to fetch calendar: {}", e))?
+    {
+        body.extend_from_slice(&chunk);
+        if body.len() > MAX_FEED_BYTES {
+            return Err("Calendar feed is too large".to_string());
+        }
+    }
+    String::from_utf8(body).map_err(|_| "Calendar feed is not UTF-8 text".to_string())
+}
+
+async fn refresh(app: &AppHandle) -> Result<Vec<FeedStatus>, String> {
+    let pool = crate::db::pool(app).await?;
+    let config = load_config(&pool).await?;
+    let feeds: Vec<&CalendarFeed> = config.feeds.iter().filter(|feed| feed.enabled).collect();
+    prune_feeds(
+        &pool,
+        &feeds.iter().map(|feed| feed.id.clone()).collect::<Vec<_>>(),
+    )
+    .await?;
+    if feeds.is_empty() {
+        return Ok(Vec::new());
+    }
+    crate::net::connectivity::require_online()?;
+    let client = crate::net::client::http_client(None)?;
+
+    let now = crate::db::now_millis();
+    let until = now + LOOKAHEAD_DAYS * 24 * 60 * 60 * 1000;
+    let mut statuses = Vec::new();
+    for feed in feeds {
+        let result = async {
+            let ics = fetch_feed(&client, &feed.url).await?;
+            let found = occurrences(&ics, now, until);
+            store_events(&pool, &feed.id, &found, crate::db::now_millis()).await?;
+            Ok::<_, String>(found.len())
+        }
+        .await;
+        let (events, error) = match result {
+            Ok(count) => {
+                FEED_ERRORS.lock().remove(&feed.id);
+                (Some(count), None)
+            }
+            Err(e) => {
+                warn!("Calendar {} failed to refresh: {}", feed.name, e);
+                FEED_ERRORS.lock().insert(feed.id.clone(), e.clone());
+                (None, Some(e))
+            }
+        };
+        statuses.push(FeedStatus {
+            feed_id: feed.id.clone(),
+            name: feed.name.clone(),
+            events,
+            error,
+        });
+    }
+    if let Err(e) = app.emit("calendar-updated", &statuses) {
+        warn!("Failed to emit calendar-updated: {}", e);
+    }
+    Ok(statuses)
+}
+
+fn format_time(millis: i64) -> String {
+    Local
+        .timestamp_millis_opt(millis)
+        .single()
+        .map(|time| time.format("%H:%M").to_string())
+        .unwrap_or_default()
+}
+
+/// Record `event_id` as a meeting, titled after the event.
+pub(crate) async fn start_meeting_for(
+    app: &AppHandle,
+    event_id: &str,
+    options: Option<MeetingOptions>,
+) -> Result<crate::meeting::store::Meeting, String> {
+    let pool = crate::db::pool(app).await?;
+    let event = get_event(&pool, event_id)
+        .await?
+        .ok_or_else(|| format!("Calendar event not found: {}", event_id))?;
+    let mut options = match options {
+        Some(options) => options,
+        None => load_config(&pool)
+            .await?
+            .meeting_options
+            .ok_or("Choose how calendar meetings are recorded first")?,
+    };
+    options.title = Some(event.title.clone());
+    let meeting = crate::meeting::start_meeting_session(app.clone(), options).await?;
+    mark_reminded(
+        &pool,
+        &event.id,
+        crate::db::now_millis(),
+        Some(meeting.id.as_str()),
+    )
+    .await?;
+    Ok(meeting)
+}
+
+async fn remind(app: &AppHandle, config: &CalendarConfig, event: &CalendarEvent) {
+    if let Err(e) = app.emit("calendar-event-starting", event) {
+        warn!("Failed to emit calendar-event-starting: {}", e);
+    }
+    let when = format!("{} at {}", event.title, format_time(event.starts_at));
+    let recording = crate::meeting::get_active_meeting().is_some();
+    if config.reminder == MeetingReminder::AutoStart && !recording {
+        match start_meeting_for(app, &event.id, None).await {
+            Ok(meeting) => {
+                let open = NotificationAction::OpenConversation {
+                    conversation_id: meeting.conversation_id,
+                };
+                notify::show(app, "Recording meeting", &when, vec![open]);
+                return;
+            }
+            Err(e) => warn!("Failed to start meeting for {}: {}", event.title, e),
+        }
+    }
+    let actions = if recording {
+        Vec::new()
+    } else {
+        vec![NotificationAction::StartCalendarMeeting {
+            event_id: event.id.clone(),
+        }]
+    };
+    notify::show(app, "Meeting starting soon", &when, actions);
+}
+
+async fn check_reminders(app: &AppHandle) -> Result<(), String> {
+    let pool = crate::db::pool(app).await?;
+    let config = load_config(&pool).await?;
+    if config.reminder == MeetingReminder::Off || config.feeds.is_empty() {
+        return Ok(());
+    }
+    let now = crate::db::now_millis();
+    let lead_ms = config.lead_minutes as i64 * 60 * 1000;
+    for event in due(&pool, now, lead_ms).await? {
+        // Marked first, so a failure can't repeat the reminder every tick
+        mark_reminded(&pool, &event.id, now, None).await?;
+        remind(app, &config, &event).await;
+    }
+    Ok(())
+}
+
+/// Refresh the feeds every 15 minutes and check for events about to start
+/// every 30 seconds. Called once from `setup`.
+pub fn start_calendar(app: AppHandle) {
+    let poller = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            if !crate::net::connectivity::is_online() {
+                continue;
+            }
+            match refresh(&poller).await {
+                Ok(statuses) if !statuses.is_empty() => info!(
+                    "Refreshed {} calendar feed(s)",
+                    statuses.iter().filter(|s| s.error.is_none()).count()
+                ),
+                Ok(_) => {}
+                Err(e) => warn!("Calendar refresh failed: {}", e),
+            }
+        }
+    });
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(REMINDER_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = check_reminders(&app).await {
+                warn!("Calendar reminders failed: {}", e);
+            }
+        }
+    });
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+#[tauri::command]
+pub async fn get_calendar_config(app: AppHandle) -> Result<CalendarConfig, String> {
+    let pool = crate::db::pool(&app).await?;
+    load_config(&pool).await
+}
+
+/// Save the feeds and reminder settings, then refresh the feeds. New feeds
+/// get an id; the returned config has them.
+#[tauri::command]
+pub async fn set_calendar_config(
+    app: AppHandle,
+    mut config: CalendarConfig,
+) -> Result<CalendarConfig, String> {
+    if config.feeds.len() > MAX_FEEDS {
+        return Err(format!(
+            "At most {} calendar feeds are supported",
+            MAX_FEEDS
+        ));
+    }
+    if config.lead_minutes > MAX_LEAD_MINUTES {
+        return Err(format!(
+            "Reminders can be at most {} minutes ahead",
+            MAX_LEAD_MINUTES
+        ));
+    }
+    if config.reminder == MeetingReminder::AutoStart && config.meeting_options.is_none() {
+        return Err("Choose how calendar meetings are recorded first".to_string());
+    }
+    for feed in &mut config.feeds {
+        feed.name = feed.name.trim().to_string();
+        feed.url = feed.url.trim().to_string();
+        let url = reqwest::Url::parse(&feed_url(&feed.url))
+            .map_err(|e| format!("Invalid calendar URL {}: {}", feed.url, e))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(format!(
+                "Calendar URLs must use https or webcal: {}",
+                feed.url
+            ));
+        }
+        if feed.name.is_empty() {
+            feed.name = url.host_str().unwrap_or("Calendar").to_string();
+        }
+        if feed.id.is_empty() {
+            feed.id = uuid::Uuid::new_v4().to_string();
+        }
+    }
+    crate::settings::set_setting(&app, SETTING_KEY, &config).await?;
+
+    if let Err(e) = refresh(&app).await {
+        warn!("Calendar refresh failed: {}", e);
+    }
+    Ok(config)
+}
+
+/// Fetch every enabled feed now.
+#[tauri::command]
+pub async fn refresh_calendars(app: AppHandle) -> Result<Vec<FeedStatus>, String> {
+    refresh(&app).await
+}
+
+/// Why each feed's last refresh failed, by feed id.
+#[tauri::command]
+pub fn get_calendar_errors() -> HashMap<String, String> {
+    FEED_ERRORS.lock().clone()
+}
+
+/// Events that haven't ended and start within `hours` (24 by default),
+/// soonest first.
+#[tauri::command]
+pub async fn get_upcoming_events(
+    app: AppHandle,
+    hours: Option<u32>,
+) -> Result<Vec<CalendarEvent>, String> {
+    let pool = crate::db::pool(&app).await?;
+    let now = crate::db::now_millis();
+    let hours = hours
+        .unwrap_or(DEFAULT_UPCOMING_HOURS)
+        .min(LOOKAHEAD_DAYS as u32 * 24);
+    upcoming(&pool, now, now + hours as i64 * 60 * 60 * 1000).await
+}
+
+/// Record a calendar event as a meeting, with `options` or the saved
+/// calendar meeting options.
+#[tauri::command]
+pub async fn start_calendar_meeting(
+    app: AppHandle,
+    event_id: String,
+    options: Option<MeetingOptions>,
+) -> Result<crate::meeting::store::Meeting, String> {
+    start_meeting_for(&app, &event_id, options).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(rfc3339: &str) -> i64 {
+        chrono::DateTime::parse_from_rfc3339(rfc3339)
+            .unwrap()
+            .timestamp_millis()
+    }
+
+    const FEED: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+BEGIN:VTIMEZONE\r
+TZID:Europe/Berlin\r
+END:VTIMEZONE\r
+BEGIN:VEVENT\r
+UID:standup\r
+SUMMARY:Stand\\, up\r
+DTSTART;TZID=Europe/Berlin:20261019T093000\r
+DTEND;TZID=Europe/Berlin:20261019T094500\r
+RRULE:FREQ=WEEKLY;BYDAY=MO,WE;COUNT=6\r
+EXDATE;TZID=Europe/Berlin:20261021T093000\r
+DESCRIPTION:Join: https://meet.example.com/abc-def\\nSee you\r
+BEGIN:VALARM\r
+TRIGGER:-PT5M\r
+DESCRIPTION:Reminder\r
+END:VALARM\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:standup\r
+RECURRENCE-ID;TZID=Europe/Berlin:20261026T093000\r
+SUMMARY:Stand up (moved)\r
+DTSTART;TZID=Europe/Berlin:20261026T110000\r
+DURATION:PT15M\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:review\r
+SUMMARY:Design\r
+  review\r
+DTSTART:20261020T150000Z\r
+DTEND:20261020T160000Z\r
+STATUS:CANCELLED\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:offsite\r
+SUMMARY:Offsite\r
+DTSTART;VALUE=DATE:20261022\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    #[test]
+    fn feeds_expand_into_occurrences() {
+        let found = occurrences(
+            FEED,
+            millis("2026-10-18T00:00:00Z"),
+            millis("2026-11-01T00:00:00Z"),
+        );
+        let standups: Vec<(&str, i64)> = found
+            .iter()
+            .filter(|o| o.uid == "standup")
+            .map(|o| (o.title.as_str(), o.starts_at))
+            .collect();
+        // 28 Oct is the last of six: the 21st is excluded and the 26th moved.
+        // Berlin leaves summer time on 25 Oct, so 09:30 is 07:30 then 08:30 UTC
+        assert_eq!(
+            standups,
+            [
+                ("Stand, up", millis("2026-10-19T07:30:00Z")),
+                ("Stand up (moved)", millis("2026-10-26T10:00:00Z")),
+                ("Stand, up", millis("2026-10-28T08:30:00Z")),
+            ]
+        );
+        let first = found.iter().find(|o| o.uid == "standup").unwrap();
+        assert_eq!(first.ends_at - first.starts_at, 15 * 60 * 1000);
+        assert_eq!(
+            first.url.as_deref(),
+            Some("https://meet.example.com/abc-def")
+        );
+        assert_eq!(
+            first.description.as_deref(),
+            Some("Join: https://meet.example.com/abc-def\nSee you")
+        );
+
+        assert!(found.iter().all(|o| o.uid != "review"));
+        let offsite = found.iter().find(|o| o.uid == "offsite").unwrap();
+        assert!(offsite.all_day);
+        assert_eq!(offsite.ends_at - offsite.starts_at, 24 * 60 * 60 * 1000);
+    }
+
+    #[test]
+    fn rules_and_durations_are_parsed() {
+        assert_eq!(parse_duration("PT1H30M"), Some(Span::minutes(90)));
+        assert_eq!(parse_duration("P1DT2H"), Some(Span::hours(26)));
+        assert_eq!(parse_duration("-P1W"), Some(Span::weeks(-1)));
+        assert_eq!(parse_duration("1H"), None);
+        assert!(parse_rule("FREQ=MONTHLY;BYDAY=1MO").is_none());
+        assert!(parse_rule("FREQ=WEEKLY;BYSETPOS=1").is_none());
+        assert_eq!(
+            parse_rule("FREQ=DAILY;INTERVAL=2;UNTIL=20261231T235959Z")
+                .unwrap()
+                .interval,
+            2
+        );
+        assert_eq!(
+            parse_tz("/mozilla.org/20050126_1/Europe/Berlin"),
+            Some(chrono_tz::Europe::Berlin)
+        );
+
+        // A long-running daily rule only expands around the window
+        let start = NaiveDate::from_ymd_opt(2010, 1, 1)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap();
+        let from = NaiveDate::from_ymd_opt(2026, 10, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        let daily = parse_rule("FREQ=DAILY").unwrap();
+        let starts = expand(start, &daily, from, from + Span::days(3));
+        assert!(starts.len() <= 5);
+        assert!(starts.contains(&(from + Span::hours(9))));
+        assert_eq!(
+            starts.last(),
+            Some(&(from + Span::days(2) + Span::hours(9)))
+        );
+    }
+
+    #[tokio::test]
+    async fn refreshes_keep_reminders_and_drop_stale_events() {
+        let pool = crate::db::test_pool().await;
+        let event = |uid: &str, starts_at: i64| Occurrence {
+            uid: uid.to_string(),
+            title: uid.to_string(),
+            description: None,
+            location: None,
+            url: None,
+            starts_at,
+            ends_at: starts_at + 60_000,
+            all_day: false,
+        };
+        store_events(&pool, "feed", &[event("a", 1000), event("b", 5000)], 1)
+            .await
+            .unwrap();
+        let due_now = due(&pool, 900, 200).await.unwrap();
+        assert_eq!(due_now.len(), 1);
+        mark_reminded(&pool, &due_now[0].id, 900, None)
+            .await
+            .unwrap();
+        assert!(due(&pool, 900, 200).await.unwrap().is_empty());
+
+        store_events(&pool, "feed", &[event("a", 1000)], 2)
+            .await
+            .unwrap();
+        let events = upcoming(&pool, 0, 10_000).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].reminded_at, Some(900));
+
+        prune_feeds(&pool, &[]).await.unwrap();
+        assert!(upcoming(&pool, 0, 10_000).await.unwrap().is_empty());
+    }
+}
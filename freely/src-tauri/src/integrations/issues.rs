@@ -0,0 +1,665 @@
+//! Filing a chat message as a GitHub or Jira issue.
+//!
+//! `create_issue_from_message` turns a message into an issue: the title is
+//! the question that was asked (or the message's first line), and the body
+//! quotes the question, then the message, then where it came from. GitHub
+//! gets the Markdown as is; Jira Cloud gets it converted to its wiki
+//! markup.
+//!
+//! GitHub needs a token with access to the repository's issues (kept as
+//! `github-token`). Jira Cloud needs the site, the account's email and an
+//! API token (kept as `jira-token`); the site and email are the `issues`
+//! setting.
+
+use crate::db::chat::{Conversation, Message, MessageRole};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use tauri::AppHandle;
+
+/// Settings key for [`IssueTrackers`].
+pub(crate) const SETTING_KEY: &str = "issues";
+const GITHUB_API: &str = "https://api.github.com";
+const GITHUB_TOKEN_NAME: &str = "github-token";
+const JIRA_TOKEN_NAME: &str = "jira-token";
+/// Longer titles are cut at a word and end in an ellipsis.
+const MAX_TITLE_CHARS: usize = 80;
+/// Jira rejects summaries longer than this.
+const MAX_JIRA_SUMMARY_CHARS: usize = 255;
+
+/// Stored under the `issues` setting; tokens are in the credential store.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IssueTrackers {
+    /// e.g. `https://acme.atlassian.net`
+    #[serde(default)]
+    pub jira_site: Option<String>,
+    #[serde(default)]
+    pub jira_email: Option<String>,
+}
+
+/// Where to file the issue.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "provider", rename_all = "camelCase")]
+pub enum IssueTarget {
+    #[serde(rename_all = "camelCase")]
+    Github {
+        /// `owner/name`
+        repo: String,
+        #[serde(default)]
+        title: Option<String>,
+        #[serde(default)]
+        labels: Vec<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Jira {
+        /// Project key, e.g. `ENG`.
+        project: String,
+        /// "Task" when omitted.
+        #[serde(default)]
+        issue_type: Option<String>,
+        #[serde(default)]
+        title: Option<String>,
+        #[serde(default)]
+        labels: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedIssue {
+    /// `#42` on GitHub, `ENG-42` on Jira.
+    pub key: String,
+    pub url: String,
+}
+
+/// Which trackers are connected.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IssueTrackerStatus {
+    pub github: bool,
+    pub jira_site: Option<String>,
+    pub jira_email: Option<String>,
+    pub jira: bool,
+}
+
+/// The message and what it answers or was answered with.
+struct IssueDraft {
+    title: String,
+    body: String,
+}
+
+/// First line of `text` with Markdown markers removed, cut to
+/// [`MAX_TITLE_CHARS`].
+fn title_from(text: &str) -> Option<String> {
+    let line = text
+        .lines()
+        .map(|line| {
+            line.trim()
+                .trim_start_matches(['#', '>', '-', '*', ' '])
+                .trim()
+        })
+        .find(|line| !line.is_empty() && !line.starts_with("```"))?;
+    let line = line.replace(['*', '`'], "");
+    if line.chars().count() <= MAX_TITLE_CHARS {
+        return Some(line);
+    }
+    let cut: String = line.chars().take(MAX_TITLE_CHARS).collect();
+    let cut = match cut.rfind(' ') {
+        Some(space) if space > MAX_TITLE_CHARS / 2 => &cut[..space],
+        _ => &cut,
+    };
+    Some(format!(
+        "{}…",
+        cut.trim_end_matches([' ', ',', '.', ':', ';'])
+    ))
+}
+
+fn quote(text: &str) -> String {
+    text.trim()
+        .lines()
+        .map(|line| format!("> {}", line).trim_end().to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The issue for `message_id`, with the question before an assistant reply
+/// (or the reply after a user message) as context.
+fn draft(
+    conversation: &Conversation,
+    message_id: &str,
+    title: Option<&str>,
+) -> Result<IssueDraft, String> {
+    let index = conversation
+        .messages
+        .iter()
+        .position(|message| message.id == message_id)
+        .ok_or_else(|| format!("Message not found: {}", message_id))?;
+    let message = &conversation.messages[index];
+    if message.content.trim().is_empty() {
+        return Err("The message is empty".to_string());
+    }
+    let question: Option<&Message> = match message.role {
+        MessageRole::Assistant => conversation.messages[..index]
+            .iter()
+            .rev()
+            .find(|m| m.role == MessageRole::User),
+        _ => None,
+    };
+    let reply: Option<&Message> = match message.role {
+        MessageRole::User => conversation.messages[index + 1..]
+            .iter()
+            .find(|m| m.role != MessageRole::System)
+            .filter(|m| m.role == MessageRole::Assistant),
+        _ => None,
+    };
+
+    let title = title
+        .map(str::trim)
+        .filter(|title| !title.is_empty())
+        .map(str::to_string)
+        .or_else(|| question.and_then(|q| title_from(&q.content)))
+        .or_else(|| title_from(&message.content))
+        .unwrap_or_else(|| conversation.title.trim().to_string());
+
+    let mut body = String::new();
+    if let Some(question) = question {
+        body.push_str(&format!("{}\n\n", quote(&question.content)));
+    }
+    body.push_str(message.content.trim());
+    body.push_str("\n\n");
+    if let Some(reply) = reply {
+        body.push_str(&format!(
+            "**{}:**\n\n{}\n\n",
+            crate::export::speaker_label(reply.role),
+            reply.content.trim()
+        ));
+    }
+    body.push_str(&format!(
+        "---\nFrom the Freely conversation \"{}\", {} message of {}.",
+        conversation.title.trim(),
+        crate::export::speaker_label(message.role).to_lowercase(),
+        crate::export::format_timestamp_minutes(message.timestamp)
+    ));
+    Ok(IssueDraft { title, body })
+}
+
+/// Jira's wiki markup for the Markdown that chat messages use: headings,
+/// code fences, quotes, bold and inline code. Bullet lists are the same.
+fn jira_markup(markdown: &str) -> String {
+    let mut out = Vec::new();
+    let mut in_code = false;
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if let Some(info) = trimmed.strip_prefix("```") {
+            out.push(match (in_code, info.trim()) {
+                (true, _) => "{code}".to_string(),
+                (false, "") => "{code}".to_string(),
+                (false, lang) => format!("{{code:{}}}", lang),
+            });
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            out.push(line.to_string());
+            continue;
+        }
+        let level = trimmed.chars().take_while(|c| *c == '#').count();
+        let line = if (1..=6).contains(&level) && trimmed[level..].starts_with(' ') {
+            format!("h{}. {}", level, trimmed[level..].trim())
+        } else if let Some(rest) = trimmed.strip_prefix("> ") {
+            format!("bq. {}", rest)
+        } else if trimmed == "---" {
+            "----".to_string()
+        } else {
+            line.to_string()
+        };
+        out.push(inline_markup(&line));
+    }
+    if in_code {
+        out.push("{code}".to_string());
+    }
+    out.join("\n")
+}
+
+/// Bold and inline code within one line.
+fn inline_markup(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut in_monospace = false;
+    for (i, part) in line.replace("**", "*").split('`').enumerate() {
+        if i > 0 {
+            out.push_str(if in_monospace { "}}" } else { "{{" });
+            in_monospace = !in_monospace;
+        }
+        out.push_str(part);
+    }
+    out
+}
+
+fn jira_site(site: &str) -> Result<String, String> {
+    let site = site.trim().trim_end_matches('/');
+    let site = if site.contains("://") {
+        site.to_string()
+    } else {
+        format!("https://{}", site)
+    };
+    let url = reqwest::Url::parse(&site).map_err(|e| format!("Invalid Jira site: {}", e))?;
+    if url.scheme() != "https" || url.host_str().is_none() {
+        return Err(format!("The Jira site must be an https URL: {}", site));
+    }
+    Ok(url.origin().ascii_serialization())
+}
+
+async fn error_message(response: reqwest::Response) -> String {
+    let status = response.status();
+    let body: Value = response.json().await.unwrap_or_default();
+    // GitHub says `message`; Jira lists `errorMessages` and per-field `errors`
+    let mut messages: Vec<String> = body["message"]
+        .as_str()
+        .map(str::to_string)
+        .into_iter()
+        .collect();
+    messages.extend(
+        body["errorMessages"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .map(str::to_string),
+    );
+    if let Some(errors) = body["errors"].as_object() {
+        messages.extend(
+            errors
+                .iter()
+                .filter_map(|(field, error)| Some(format!("{}: {}", field, error.as_str()?))),
+        );
+    }
+    if messages.is_empty() {
+        status.to_string()
+    } else {
+        format!("{} ({})", status, messages.join("; "))
+    }
+}
+
+fn user_agent() -> String {
+    format!("Freely/{}", env!("CARGO_PKG_VERSION"))
+}
+
+async fn github_request(
+    token: &str,
+    method: reqwest::Method,
+    path: &str,
+    body: Option<Value>,
+) -> Result<Value, String> {
+    crate::net::connectivity::require_online()?;
+    let mut request = crate::net::client::http_client(None)?
+        .request(method, format!("{}{}", GITHUB_API, path))
+        .bearer_auth(token)
+        .header("Accept", "application/vnd.github+json")
+        .header("X-GitHub-Api-Version", "2022-11-28")
+        .header("User-Agent", user_agent());
+    if let Some(body) = body {
+        request = request.json(&body);
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach GitHub: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "GitHub request failed: {}",
+            error_message(response).await
+        ));
+    }
+    response
+        .json()
+        .await
+        .map_err(|e| format!("Invalid response from GitHub: {}", e))
+}
+
+struct Jira {
+    site: String,
+    email: String,
+    token: String,
+}
+
+impl Jira {
+    async fn connect(app: &AppHandle, pool: &SqlitePool) -> Result<Self, String> {
+        let trackers = load_trackers(pool).await?;
+        let (Some(site), Some(email)) = (trackers.jira_site, trackers.jira_email) else {
+            return Err("Connect Jira first".to_string());
+        };
+        let token = crate::secrets::load_api_key(app, JIRA_TOKEN_NAME)
+            .await?
+            .ok_or("Connect Jira first")?;
+        Ok(Self { site, email, token })
+    }
+
+    async fn request(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<Value>,
+    ) -> Result<Value, String> {
+        crate::net::connectivity::require_online()?;
+        let mut request = crate::net::client::http_client(None)?
+            .request(method, format!("{}/rest/api/2{}", self.site, path))
+            .basic_auth(&self.email, Some(&self.token))
+            .header("Accept", "application/json");
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("Failed to reach Jira: {}", e))?;
+        if !response.status().is_success() {
+            return Err(format!(
+                "Jira request failed: {}",
+                error_message(response).await
+            ));
+        }
+        response
+            .json()
+            .await
+            .map_err(|e| format!("Invalid response from Jira: {}", e))
+    }
+}
+
+async fn load_trackers(pool: &SqlitePool) -> Result<IssueTrackers, String> {
+    Ok(crate::settings::get_setting(pool, SETTING_KEY)
+        .await?
+        .unwrap_or_default())
+}
+
+async fn conversation_of(pool: &SqlitePool, message_id: &str) -> Result<Conversation, String> {
+    let conversation_id: Option<String> =
+        sqlx::query_scalar("SELECT conversation_id FROM messages WHERE id = ?")
+            .bind(message_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| format!("Failed to load message: {}", e))?;
+    let conversation_id =
+        conversation_id.ok_or_else(|| format!("Message not found: {}", message_id))?;
+    crate::db::chat::get(pool, &conversation_id)
+        .await?
+        .ok_or_else(|| format!("Conversation not found: {}", conversation_id))
+}
+
+fn clean_labels(labels: &[String], no_spaces: bool) -> Vec<String> {
+    labels
+        .iter()
+        .map(|label| label.trim())
+        .filter(|label| !label.is_empty())
+        .map(|label| {
+            if no_spaces {
+                label.split_whitespace().collect::<Vec<_>>().join("-")
+            } else {
+                label.to_string()
+            }
+        })
+        .collect()
+}
+
+async fn create_github_issue(
+    app: &AppHandle,
+    repo: &str,
+    draft: IssueDraft,
+    labels: &[String],
+) -> Result<CreatedIssue, String> {
+    let repo = repo.trim().trim_matches('/');
+    let valid = repo.split('/').count() == 2
+        && repo.split('/').all(|part| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+        });
+    if !valid {
+        return Err(format!("Expected a repository as owner/name: {}", repo));
+    }
+    let token = crate::secrets::load_api_key(app, GITHUB_TOKEN_NAME)
+        .await?
+        .ok_or("Connect GitHub first")?;
+    let mut body = json!({ "title": draft.title, "body": draft.body });
+    let labels = clean_labels(labels, false);
+    if !labels.is_empty() {
+        body["labels"] = json!(labels);
+    }
+    let issue = github_request(
+        &token,
+        reqwest::Method::POST,
+        &format!("/repos/{}/issues", repo),
+        Some(body),
+    )
+    .await?;
+    Ok(CreatedIssue {
+        key: format!("#{}", issue["number"].as_u64().unwrap_or_default()),
+        url: issue["html_url"]
+            .as_str()
+            .ok_or("GitHub did not return the issue's URL")?
+            .to_string(),
+    })
+}
+
+async fn create_jira_issue(
+    app: &AppHandle,
+    pool: &SqlitePool,
+    project: &str,
+    issue_type: Option<&str>,
+    draft: IssueDraft,
+    labels: &[String],
+) -> Result<CreatedIssue, String> {
+    let project = project.trim().to_uppercase();
+    if project.is_empty() {
+        return Err("A Jira project key is required".to_string());
+    }
+    let jira = Jira::connect(app, pool).await?;
+    let summary: String = draft.title.chars().take(MAX_JIRA_SUMMARY_CHARS).collect();
+    let mut fields = json!({
+        "project": { "key": project },
+        "summary": summary,
+        "description": jira_markup(&draft.body),
+        "issuetype": { "name": issue_type.map(str::trim).filter(|t| !t.is_empty()).unwrap_or("Task") },
+    });
+    let labels = clean_labels(labels, true);
+    if !labels.is_empty() {
+        fields["labels"] = json!(labels);
+    }
+    let issue = jira
+        .request(
+            reqwest::Method::POST,
+            "/issue",
+            Some(json!({ "fields": fields })),
+        )
+        .await?;
+    let key = issue["key"]
+        .as_str()
+        .ok_or("Jira did not return the issue's key")?
+        .to_string();
+    Ok(CreatedIssue {
+        url: format!("{}/browse/{}", jira.site, key),
+        key,
+    })
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+#[tauri::command]
+pub async fn get_issue_trackers(app: AppHandle) -> Result<IssueTrackerStatus, String> {
+    let pool = crate::db::pool(&app).await?;
+    let trackers = load_trackers(&pool).await?;
+    Ok(IssueTrackerStatus {
+        github: crate::secrets::load_api_key(&app, GITHUB_TOKEN_NAME)
+            .await?
+            .is_some(),
+        jira: crate::secrets::load_api_key(&app, JIRA_TOKEN_NAME)
+            .await?
+            .is_some(),
+        jira_site: trackers.jira_site,
+        jira_email: trackers.jira_email,
+    })
+}
+
+/// Check `token` with GitHub and store it, returning the account's login.
+/// `None` disconnects.
+#[tauri::command]
+pub async fn set_github_token(
+    app: AppHandle,
+    token: Option<String>,
+) -> Result<Option<String>, String> {
+    let Some(token) = token
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+    else {
+        crate::secrets::delete_api_key(app, GITHUB_TOKEN_NAME.to_string()).await?;
+        return Ok(None);
+    };
+    let me = github_request(&token, reqwest::Method::GET, "/user", None).await?;
+    crate::secrets::set_api_key(app, GITHUB_TOKEN_NAME.to_string(), token).await?;
+    Ok(me["login"].as_str().map(str::to_string))
+}
+
+/// Check the Jira Cloud credentials and store them, returning the account's
+/// display name. A `None` token disconnects.
+#[tauri::command]
+pub async fn connect_jira(
+    app: AppHandle,
+    site: String,
+    email: String,
+    token: Option<String>,
+) -> Result<Option<String>, String> {
+    let Some(token) = token
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+    else {
+        crate::secrets::delete_api_key(app.clone(), JIRA_TOKEN_NAME.to_string()).await?;
+        crate::settings::set_setting(&app, SETTING_KEY, &IssueTrackers::default()).await?;
+        return Ok(None);
+    };
+    let trackers = IssueTrackers {
+        jira_site: Some(jira_site(&site)?),
+        jira_email: Some(email.trim().to_string()).filter(|e| !e.is_empty()),
+    };
+    let (Some(site), Some(email)) = (trackers.jira_site.clone(), trackers.jira_email.clone())
+    else {
+        return Err("The Jira account's email is required".to_string());
+    };
+    let jira = Jira {
+        site,
+        email,
+        token: token.clone(),
+    };
+    let me = jira.request(reqwest::Method::GET, "/myself", None).await?;
+    crate::secrets::set_api_key(app.clone(), JIRA_TOKEN_NAME.to_string(), token).await?;
+    crate::settings::set_setting(&app, SETTING_KEY, &trackers).await?;
+    Ok(me["displayName"].as_str().map(str::to_string))
+}
+
+/// File a message as an issue in `target`, returning its key and link.
+#[tauri::command]
+pub async fn create_issue_from_message(
+    app: AppHandle,
+    message_id: String,
+    target: IssueTarget,
+) -> Result<CreatedIssue, String> {
+    let pool = crate::db::pool(&app).await?;
+    let conversation = conversation_of(&pool, &message_id).await?;
+    match target {
+        IssueTarget::Github {
+            repo,
+            title,
+            labels,
+        } => {
+            let draft = draft(&conversation, &message_id, title.as_deref())?;
+            create_github_issue(&app, &repo, draft, &labels).await
+        }
+        IssueTarget::Jira {
+            project,
+            issue_type,
+            title,
+            labels,
+        } => {
+            let draft = draft(&conversation, &message_id, title.as_deref())?;
+            create_jira_issue(&app, &pool, &project, issue_type.as_deref(), draft, &labels).await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(id: &str, role: MessageRole, content: &str) -> Message {
+        Message {
+            id: id.to_string(),
+            role,
+            content: content.to_string(),
+            timestamp: 0,
+            attached_files: None,
+            truncated: false,
+        }
+    }
+
+    #[test]
+    fn drafts_quote_the_question_and_name_the_source() {
+        let conversation = Conversation {
+            id: "c1".to_string(),
+            title: "Build failures".to_string(),
+            created_at: 0,
+            updated_at: 0,
+            pinned: false,
+            archived_at: None,
+            deleted_at: None,
+            messages: vec![
+                message("m0", MessageRole::System, "Be brief."),
+                message(
+                    "m1",
+                    MessageRole::User,
+                    "## Why does `cargo build` fail on **CI**?",
+                ),
+                message(
+                    "m2",
+                    MessageRole::Assistant,
+                    "The lockfile is stale.\n\n```sh\ncargo update\n```",
+                ),
+            ],
+        };
+
+        let issue = draft(&conversation, "m2", None).unwrap();
+        assert_eq!(issue.title, "Why does cargo build fail on CI?");
+        assert!(issue
+            .body
+            .starts_with("> ## Why does `cargo build` fail on **CI**?\n\nThe lockfile is stale."));
+        assert!(issue.body.contains(
+            "---\nFrom the Freely conversation \"Build failures\", assistant message of"
+        ));
+
+        let issue = draft(&conversation, "m1", Some("  Stale lockfile ")).unwrap();
+        assert_eq!(issue.title, "Stale lockfile");
+        assert!(issue
+            .body
+            .contains("**Assistant:**\n\nThe lockfile is stale."));
+        assert!(draft(&conversation, "missing", None).is_err());
+
+        let long = "word ".repeat(40);
+        let title = title_from(&long).unwrap();
+        assert!(title.ends_with("word…"));
+        assert!(title.chars().count() <= MAX_TITLE_CHARS + 1);
+    }
+
+    #[test]
+    fn markdown_becomes_jira_markup() {
+        assert_eq!(
+            jira_markup("# Fix\n> quoted\nRun `cargo update` and **retry**\n```sh\n# not a heading\n```\n---"),
+            "h1. Fix\nbq. quoted\nRun {{cargo update}} and *retry*\n{code:sh}\n# not a heading\n{code}\n----"
+        );
+        assert_eq!(
+            jira_site("acme.atlassian.net/").unwrap(),
+            "https://acme.atlassian.net"
+        );
+        assert!(jira_site("http://acme.atlassian.net").is_err());
+    }
+}
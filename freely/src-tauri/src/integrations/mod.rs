@@ -0,0 +1,6 @@
+//! Connections to other apps the user keeps their notes and work in.
+
+pub(crate) mod calendar;
+pub(crate) mod issues;
+pub(crate) mod notion;
+pub(crate) mod obsidian;
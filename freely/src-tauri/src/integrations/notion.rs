@@ -0,0 +1,642 @@
+//! Pushing conversations and meeting summaries into a Notion database.
+//!
+//! The integration token (an internal integration's secret) is kept in the
+//! credential store as `notion-token`; the databases it can see are the
+//! ones shared with the integration in Notion. Each push creates one page
+//! whose title property is the conversation or meeting title, and whose
+//! date property, if the database has one called "Date", is when it
+//! started. Markdown in the content is mapped to Notion blocks: headings,
+//! code fences (with their language), lists, to-dos, quotes and dividers.
+//! Inline formatting is kept as plain text.
+
+use crate::db::chat::MessageRole;
+use chrono::{Local, TimeZone};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::AppHandle;
+
+const API_BASE: &str = "https://api.notion.com/v1";
+const API_VERSION: &str = "2022-06-28";
+const TOKEN_NAME: &str = "notion-token";
+/// Notion's limits on one text object, a rich text array and the children
+/// sent in one request.
+const MAX_TEXT_UTF16: usize = 2000;
+const MAX_RICH_TEXT_ITEMS: usize = 100;
+const MAX_CHILDREN: usize = 100;
+const MAX_SEARCH_PAGES: usize = 10;
+
+/// Code block languages Notion knows, besides "plain text".
+const LANGUAGES: &[&str] = &[
+    "bash",
+    "c",
+    "c#",
+    "c++",
+    "css",
+    "dart",
+    "diff",
+    "docker",
+    "elixir",
+    "go",
+    "graphql",
+    "haskell",
+    "html",
+    "java",
+    "javascript",
+    "json",
+    "kotlin",
+    "lua",
+    "makefile",
+    "markdown",
+    "objective-c",
+    "perl",
+    "php",
+    "powershell",
+    "python",
+    "r",
+    "ruby",
+    "rust",
+    "scala",
+    "shell",
+    "sql",
+    "swift",
+    "typescript",
+    "xml",
+    "yaml",
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotionDatabase {
+    pub id: String,
+    pub title: String,
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotionPage {
+    pub id: String,
+    pub url: Option<String>,
+}
+
+fn language(info: &str) -> &'static str {
+    let name = info
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    let name = match name.as_str() {
+        "js" | "jsx" | "mjs" => "javascript",
+        "ts" | "tsx" => "typescript",
+        "py" => "python",
+        "rs" => "rust",
+        "rb" => "ruby",
+        "sh" | "zsh" | "console" => "shell",
+        "ps1" => "powershell",
+        "yml" => "yaml",
+        "cpp" | "cc" | "cxx" | "hpp" => "c++",
+        "cs" | "csharp" => "c#",
+        "md" => "markdown",
+        "golang" => "go",
+        "kt" => "kotlin",
+        "dockerfile" => "docker",
+        "make" => "makefile",
+        "objc" => "objective-c",
+        "htm" => "html",
+        other => other,
+    };
+    LANGUAGES
+        .iter()
+        .find(|known| **known == name)
+        .copied()
+        .unwrap_or("plain text")
+}
+
+/// `text` as text objects within Notion's length limit.
+fn rich_text(text: &str) -> Vec<Value> {
+    let mut chunks = Vec::new();
+    let mut chunk = String::new();
+    let mut units = 0;
+    for c in text.chars() {
+        if units + c.len_utf16() > MAX_TEXT_UTF16 {
+            chunks.push(std::mem::take(&mut chunk));
+            units = 0;
+        }
+        chunk.push(c);
+        units += c.len_utf16();
+    }
+    if !chunk.is_empty() {
+        chunks.push(chunk);
+    }
+    chunks
+        .into_iter()
+        .map(|content| json!({ "type": "text", "text": { "content": content } }))
+        .collect()
+}
+
+/// Blocks of `kind` holding `text`, split when it is too long for one.
+fn text_blocks(kind: &str, text: &str, extra: Value) -> Vec<Value> {
+    let rich_text = rich_text(text);
+    if rich_text.is_empty() {
+        return Vec::new();
+    }
+    rich_text
+        .chunks(MAX_RICH_TEXT_ITEMS)
+        .map(|chunk| {
+            let mut body = json!({ "rich_text": chunk });
+            if let (Some(body), Value::Object(extra)) = (body.as_object_mut(), &extra) {
+                body.extend(extra.clone());
+            }
+            json!({ "object": "block", "type": kind, kind: body })
+        })
+        .collect()
+}
+
+fn heading(level: usize, text: &str) -> Vec<Value> {
+    let kind = match level {
+        1 => "heading_1",
+        2 => "heading_2",
+        _ => "heading_3",
+    };
+    text_blocks(kind, text, Value::Null)
+}
+
+/// `line` without an ordered list marker such as `12. `.
+fn strip_number(line: &str) -> Option<&str> {
+    let digits = line.bytes().take_while(u8::is_ascii_digit).count();
+    if digits == 0 {
+        return None;
+    }
+    line[digits..].strip_prefix(". ")
+}
+
+fn code_block(lang: &str, lines: &[&str]) -> Vec<Value> {
+    text_blocks(
+        "code",
+        &lines.join("\n"),
+        json!({ "language": language(lang) }),
+    )
+}
+
+/// Notion blocks for a Markdown document.
+fn markdown_blocks(markdown: &str) -> Vec<Value> {
+    let mut blocks = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    // The opening fence and language, and the code so far
+    let mut code: Option<(&str, &str, Vec<&str>)> = None;
+
+    let flush = |paragraph: &mut Vec<&str>, blocks: &mut Vec<Value>| {
+        if !paragraph.is_empty() {
+            blocks.extend(text_blocks("paragraph", &paragraph.join("\n"), Value::Null));
+            paragraph.clear();
+        }
+    };
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if let Some((fence, lang, lines)) = &mut code {
+            if trimmed.starts_with(*fence) && trimmed.trim_start_matches(*fence).trim().is_empty() {
+                blocks.extend(code_block(lang, lines));
+                code = None;
+            } else {
+                lines.push(line);
+            }
+            continue;
+        }
+        if let Some(fence) = ["```", "~~~"].into_iter().find(|f| trimmed.starts_with(f)) {
+            flush(&mut paragraph, &mut blocks);
+            code = Some((fence, trimmed[fence.len()..].trim(), Vec::new()));
+            continue;
+        }
+        if trimmed.is_empty() {
+            flush(&mut paragraph, &mut blocks);
+            continue;
+        }
+
+        let hashes = trimmed.bytes().take_while(|b| *b == b'#').count();
+        let block = if (1..=6).contains(&hashes) && trimmed[hashes..].starts_with(' ') {
+            heading(hashes, trimmed[hashes..].trim())
+        } else if ["---", "***", "___"].contains(&trimmed.trim_end()) {
+            vec![json!({ "object": "block", "type": "divider", "divider": {} })]
+        } else if let Some(rest) = ["- [ ] ", "* [ ] "]
+            .iter()
+            .find_map(|marker| trimmed.strip_prefix(marker))
+        {
+            text_blocks("to_do", rest, json!({ "checked": false }))
+        } else if let Some(rest) = ["- [x] ", "- [X] ", "* [x] ", "* [X] "]
+            .iter()
+            .find_map(|marker| trimmed.strip_prefix(marker))
+        {
+            text_blocks("to_do", rest, json!({ "checked": true }))
+        } else if let Some(rest) = ["- ", "* ", "+ "]
+            .iter()
+            .find_map(|marker| trimmed.strip_prefix(marker))
+        {
+            text_blocks("bulleted_list_item", rest, Value::Null)
+        } else if let Some(rest) = strip_number(trimmed) {
+            text_blocks("numbered_list_item", rest, Value::Null)
+        } else if let Some(rest) = trimmed.strip_prefix('>') {
+            text_blocks("quote", rest.trim_start(), Value::Null)
+        } else {
+            paragraph.push(line);
+            continue;
+        };
+        flush(&mut paragraph, &mut blocks);
+        blocks.extend(block);
+    }
+    flush(&mut paragraph, &mut blocks);
+    // An unclosed fence runs to the end, as in most renderers
+    if let Some((_, lang, lines)) = code {
+        blocks.extend(code_block(lang, &lines));
+    }
+    blocks
+}
+
+fn conversation_blocks(conversation: &crate::db::chat::Conversation) -> Vec<Value> {
+    let mut blocks = Vec::new();
+    for message in &conversation.messages {
+        if message.role == MessageRole::System {
+            continue;
+        }
+        blocks.extend(heading(
+            3,
+            &format!(
+                "{} · {}",
+                crate::export::speaker_label(message.role),
+                crate::export::format_timestamp_minutes(message.timestamp)
+            ),
+        ));
+        blocks.extend(markdown_blocks(&message.content));
+    }
+    blocks
+}
+
+fn meeting_blocks(meeting: &crate::meeting::store::Meeting) -> Vec<Value> {
+    let mut blocks = Vec::new();
+    for (title, text) in [("Minutes", &meeting.minutes), ("Notes", &meeting.notes)] {
+        if let Some(text) = text.as_deref().filter(|text| !text.trim().is_empty()) {
+            blocks.extend(heading(2, title));
+            blocks.extend(markdown_blocks(text));
+        }
+    }
+    blocks
+}
+
+/// The id in a database id or link, which Notion accepts with or without
+/// dashes.
+fn parse_database_id(raw: &str) -> Result<String, String> {
+    let path = raw.trim().split(['?', '#']).next().unwrap_or_default();
+    // Links end in the page title and the id: `.../Meetings-0123…cdef`
+    let last = path
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or_default();
+    let compact: Vec<char> = last.chars().filter(|c| *c != '-').collect();
+    let id: String = compact[compact.len().saturating_sub(32)..].iter().collect();
+    if id.len() == 32 && id.chars().all(|c| c.is_ascii_hexdigit()) {
+        Ok(id.to_lowercase())
+    } else {
+        Err(format!("Not a Notion database id or link: {}", raw.trim()))
+    }
+}
+
+fn plain_title(title: &Value) -> String {
+    title
+        .as_array()
+        .map(|parts| {
+            parts
+                .iter()
+                .filter_map(|part| part.get("plain_text").and_then(Value::as_str))
+                .collect::<String>()
+        })
+        .unwrap_or_default()
+}
+
+struct Notion {
+    client: reqwest::Client,
+    token: String,
+}
+
+async fn error_message(response: reqwest::Response) -> String {
+    let status = response.status();
+    let body: Value = response.json().await.unwrap_or_default();
+    match body.get("message").and_then(Value::as_str) {
+        Some(message) => format!("{} ({})", status, message),
+        None => status.to_string(),
+    }
+}
+
+impl Notion {
+    fn new(token: String) -> Result<Self, String> {
+        Ok(Self {
+            client: crate::net::client::http_client(None)?,
+            token,
+        })
+    }
+
+    async fn connect(app: &AppHandle) -> Result<Self, String> {
+        crate::net::connectivity::require_online()?;
+        let token = crate::secrets::load_api_key(app, TOKEN_NAME)
+            .await?
+            .ok_or("Connect Notion first")?;
+        Self::new(token)
+    }
+
+    async fn request(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<Value>,
+    ) -> Result<Value, String> {
+        let mut request = self
+            .client
+            .request(method, format!("{}{}", API_BASE, path))
+            .bearer_auth(&self.token)
+            .header("Notion-Version", API_VERSION);
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("Failed to reach Notion: {}", e))?;
+        if !response.status().is_success() {
+            return Err(format!(
+                "Notion request failed: {}",
+                error_message(response).await
+            ));
+        }
+        response
+            .json()
+            .await
+            .map_err(|e| format!("Invalid response from Notion: {}", e))
+    }
+
+    async fn databases(&self) -> Result<Vec<NotionDatabase>, String> {
+        let mut databases = Vec::new();
+        let mut cursor: Option<String> = None;
+        for _ in 0..MAX_SEARCH_PAGES {
+            let mut body = json!({
+                "filter": { "property": "object", "value": "database" },
+                "page_size": 100,
+            });
+            if let Some(cursor) = &cursor {
+                body["start_cursor"] = json!(cursor);
+            }
+            let page = self
+                .request(reqwest::Method::POST, "/search", Some(body))
+                .await?;
+            for result in page["results"].as_array().into_iter().flatten() {
+                let Some(id) = result["id"].as_str() else {
+                    continue;
+                };
+                databases.push(NotionDatabase {
+                    id: id.to_string(),
+                    title: plain_title(&result["title"]),
+                    url: result["url"].as_str().map(str::to_string),
+                });
+            }
+            cursor = page["next_cursor"].as_str().map(str::to_string);
+            if page["has_more"].as_bool() != Some(true) || cursor.is_none() {
+                break;
+            }
+        }
+        Ok(databases)
+    }
+
+    /// Create a page in `database_id`, appending children past the first
+    /// hundred in further requests.
+    async fn create_page(
+        &self,
+        database_id: &str,
+        title: &str,
+        started_at: i64,
+        blocks: Vec<Value>,
+    ) -> Result<NotionPage, String> {
+        let database = self
+            .request(
+                reqwest::Method::GET,
+                &format!("/databases/{}", database_id),
+                None,
+            )
+            .await?;
+        let schema = database["properties"]
+            .as_object()
+            .cloned()
+            .unwrap_or_default();
+        let title_property = schema
+            .iter()
+            .find(|(_, property)| property["type"] == "title")
+            .map(|(name, _)| name.clone())
+            .ok_or("The Notion database has no title property")?;
+        let mut properties = json!({ title_property: { "title": rich_text(title) } });
+        let date_property = schema.iter().find(|(name, property)| {
+            name.eq_ignore_ascii_case("date") && property["type"] == "date"
+        });
+        if let (Some((name, _)), Some(start)) = (
+            date_property,
+            Local.timestamp_millis_opt(started_at).single(),
+        ) {
+            properties[name] = json!({ "date": { "start": start.to_rfc3339() } });
+        }
+
+        let mut chunks = blocks.chunks(MAX_CHILDREN);
+        let page = self
+            .request(
+                reqwest::Method::POST,
+                "/pages",
+                Some(json!({
+                    "parent": { "database_id": database_id },
+                    "properties": properties,
+                    "children": chunks.next().unwrap_or_default(),
+                })),
+            )
+            .await?;
+        let id = page["id"]
+            .as_str()
+            .ok_or("Notion did not return a page id")?
+            .to_string();
+        for chunk in chunks {
+            self.request(
+                reqwest::Method::PATCH,
+                &format!("/blocks/{}/children", id),
+                Some(json!({ "children": chunk })),
+            )
+            .await?;
+        }
+        Ok(NotionPage {
+            id,
+            url: page["url"].as_str().map(str::to_string),
+        })
+    }
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// Check `token` with Notion and store it, returning the integration's
+/// name. `None` disconnects.
+#[tauri::command]
+pub async fn set_notion_token(
+    app: AppHandle,
+    token: Option<String>,
+) -> Result<Option<String>, String> {
+    let Some(token) = token
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+    else {
+        crate::secrets::delete_api_key(app, TOKEN_NAME.to_string()).await?;
+        return Ok(None);
+    };
+    crate::net::connectivity::require_online()?;
+    let me = Notion::new(token.clone())?
+        .request(reqwest::Method::GET, "/users/me", None)
+        .await?;
+    crate::secrets::set_api_key(app, TOKEN_NAME.to_string(), token).await?;
+    Ok(Some(
+        me["name"]
+            .as_str()
+            .unwrap_or("Notion integration")
+            .to_string(),
+    ))
+}
+
+/// Databases shared with the integration.
+#[tauri::command]
+pub async fn list_notion_databases(app: AppHandle) -> Result<Vec<NotionDatabase>, String> {
+    Notion::connect(&app).await?.databases().await
+}
+
+/// Create a page for a conversation in the database `database_id`, which
+/// may also be a link to it.
+#[tauri::command]
+pub async fn push_conversation_to_notion(
+    app: AppHandle,
+    conversation_id: String,
+    database_id: String,
+) -> Result<NotionPage, String> {
+    let database_id = parse_database_id(&database_id)?;
+    let pool = crate::db::pool(&app).await?;
+    let conversation = crate::db::chat::get(&pool, &conversation_id)
+        .await?
+        .ok_or_else(|| format!("Conversation not found: {}", conversation_id))?;
+    Notion::connect(&app)
+        .await?
+        .create_page(
+            &database_id,
+            conversation.title.trim(),
+            conversation.created_at,
+            conversation_blocks(&conversation),
+        )
+        .await
+}
+
+/// Create a page with a meeting's minutes and notes in the database
+/// `database_id`, which may also be a link to it.
+#[tauri::command]
+pub async fn push_meeting_to_notion(
+    app: AppHandle,
+    meeting_id: String,
+    database_id: String,
+) -> Result<NotionPage, String> {
+    let database_id = parse_database_id(&database_id)?;
+    let pool = crate::db::pool(&app).await?;
+    let meeting = crate::meeting::store::get(&pool, &meeting_id)
+        .await?
+        .ok_or_else(|| format!("Meeting not found: {}", meeting_id))?;
+    let blocks = meeting_blocks(&meeting);
+    if blocks.is_empty() {
+        return Err("The meeting has no notes or minutes yet".to_string());
+    }
+    Notion::connect(&app)
+        .await?
+        .create_page(
+            &database_id,
+            meeting.title.trim(),
+            meeting.started_at,
+            blocks,
+        )
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(blocks: &[Value]) -> Vec<&str> {
+        blocks
+            .iter()
+            .map(|block| block["type"].as_str().unwrap())
+            .collect()
+    }
+
+    fn text(block: &Value) -> String {
+        let kind = block["type"].as_str().unwrap();
+        block[kind]["rich_text"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|part| part["text"]["content"].as_str().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn markdown_maps_to_notion_blocks() {
+        let blocks = markdown_blocks(
+            "# Plan\n\nFirst line\nsecond line\n\n```ts\nconst a = 1;\n\nexport {}\n```\n\
+             - item\n1. step\n- [x] done\n> quoted\n---\n#### Deep\n~~~\nunclosed",
+        );
+        assert_eq!(
+            kinds(&blocks),
+            [
+                "heading_1",
+                "paragraph",
+                "code",
+                "bulleted_list_item",
+                "numbered_list_item",
+                "to_do",
+                "quote",
+                "divider",
+                "heading_3",
+                "code"
+            ]
+        );
+        assert_eq!(text(&blocks[1]), "First line\nsecond line");
+        assert_eq!(text(&blocks[2]), "const a = 1;\n\nexport {}");
+        assert_eq!(blocks[2]["code"]["language"], "typescript");
+        assert_eq!(blocks[5]["to_do"]["checked"], true);
+        assert_eq!(text(&blocks[8]), "Deep");
+        assert_eq!(blocks[9]["code"]["language"], "plain text");
+        assert_eq!(text(&blocks[9]), "unclosed");
+
+        // Long text is split into text objects Notion accepts
+        let long = "é".repeat(4500);
+        let blocks = markdown_blocks(&long);
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(
+            blocks[0]["paragraph"]["rich_text"]
+                .as_array()
+                .unwrap()
+                .len(),
+            3
+        );
+        assert_eq!(text(&blocks[0]), long);
+    }
+
+    #[test]
+    fn database_ids_are_taken_from_links() {
+        let id = "0123456789abcdef0123456789abcdef";
+        assert_eq!(parse_database_id(id).unwrap(), id);
+        assert_eq!(
+            parse_database_id("01234567-89ab-cdef-0123-456789ABCDEF").unwrap(),
+            id
+        );
+        assert_eq!(
+            parse_database_id(&format!("https://www.notion.so/team/Meetings-{}?v=42", id)).unwrap(),
+            id
+        );
+        assert!(parse_database_id("https://www.notion.so/team/Meetings").is_err());
+    }
+}
@@ -0,0 +1,781 @@
+//! Mirror of conversations and meeting notes into an Obsidian vault, or any
+//! folder of Markdown files, configured by the `obsidian` setting.
+//!
+//! Each conversation and each meeting with notes becomes one Markdown file
+//! with YAML frontmatter (title, dates, tags, participants and the Freely
+//! id), at a path rendered from a filename template such as
+//! `Freely/{{date}} {{title}}`. Templates can use `title`, `date`, `time`,
+//! `year`, `month` and `id`, and `/` to make subfolders.
+//!
+//! While enabled, the vault is brought up to date every minute: a file is
+//! rewritten when its record changes, moved when its rendered path changes
+//! and removed when the record is deleted (or archived, unless archived
+//! conversations are kept). The mirror is one-way, so edits made in the
+//! vault are lost at the record's next change. Which file belongs to which
+//! record is kept in `obsidian-index.json` in the app data directory, and
+//! files the mirror didn't write are never overwritten or removed.
+
+use crate::db::chat::MessageRole;
+use crate::meeting::store::Meeting;
+use chrono::{Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tracing::{info, warn};
+
+/// Settings key for [`ObsidianConfig`].
+pub(crate) const SETTING_KEY: &str = "obsidian";
+const INDEX_FILE: &str = "obsidian-index.json";
+const MIRROR_INTERVAL: Duration = Duration::from_secs(60);
+const TEMPLATE_VARIABLES: &[&str] = &["title", "date", "time", "year", "month", "id"];
+/// Characters Windows or Obsidian links don't allow in file names.
+const FORBIDDEN_CHARS: &str = "\\/:*?\"<>|#^[]";
+const MAX_COMPONENT_CHARS: usize = 120;
+const MAX_TRANSCRIPTS: u32 = 1000;
+const FREELY_TAG: &str = "freely";
+
+static MIRRORING: AtomicBool = AtomicBool::new(false);
+
+fn default_conversation_template() -> String {
+    "Freely/{{date}} {{title}}".to_string()
+}
+
+fn default_meeting_template() -> String {
+    "Freely/Meetings/{{date}} {{title}}".to_string()
+}
+
+/// Stored under the `obsidian` setting.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ObsidianConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// The vault's root folder.
+    #[serde(default)]
+    pub vault: Option<String>,
+    #[serde(default = "default_conversation_template")]
+    pub conversation_template: String,
+    #[serde(default = "default_meeting_template")]
+    pub meeting_template: String,
+    /// Keep archived conversations in the vault.
+    #[serde(default)]
+    pub include_archived: bool,
+}
+
+impl Default for ObsidianConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            vault: None,
+            conversation_template: default_conversation_template(),
+            meeting_template: default_meeting_template(),
+            include_archived: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MirrorReport {
+    pub written: usize,
+    pub removed: usize,
+    pub unchanged: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexEntry {
+    /// Relative to the vault, with `/` separators.
+    path: String,
+    /// Changes whenever the file's content would.
+    version: String,
+}
+
+/// The files the mirror wrote, by `conversation:<id>` or `meeting:<id>`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MirrorIndex {
+    vault: String,
+    entries: HashMap<String, IndexEntry>,
+}
+
+#[derive(sqlx::FromRow)]
+struct ConversationRow {
+    id: String,
+    title: String,
+    created_at: i64,
+    updated_at: i64,
+    /// Separated by U+001F.
+    tags: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct MeetingRow {
+    id: String,
+    conversation_id: String,
+    title: String,
+    started_at: i64,
+    updated_at: i64,
+    speakers: Option<String>,
+}
+
+fn split_tags(tags: Option<String>) -> Vec<String> {
+    let mut tags: Vec<String> = tags
+        .map(|tags| tags.split('\u{1f}').map(str::to_string).collect())
+        .unwrap_or_default();
+    tags.sort_by_key(|tag| tag.to_lowercase());
+    tags
+}
+
+fn local_time(millis: i64) -> chrono::DateTime<Local> {
+    Local
+        .timestamp_millis_opt(millis)
+        .single()
+        .unwrap_or_else(Local::now)
+}
+
+/// A date Obsidian reads as a date-time property.
+fn yaml_time(millis: i64) -> String {
+    local_time(millis).format("%Y-%m-%dT%H:%M:%S").to_string()
+}
+
+/// A double-quoted YAML scalar; JSON string escapes are valid YAML.
+fn yaml_string(value: &str) -> String {
+    serde_json::Value::from(value).to_string()
+}
+
+fn yaml_list(values: &[String]) -> String {
+    let items: Vec<String> = values.iter().map(|value| yaml_string(value)).collect();
+    format!("[{}]", items.join(", "))
+}
+
+/// Obsidian tags can't contain spaces.
+fn obsidian_tag(tag: &str) -> String {
+    tag.trim_start_matches('#')
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn frontmatter(fields: &[(&str, String)]) -> String {
+    let mut out = String::from("---\n");
+    for (name, value) in fields {
+        out.push_str(&format!("{}: {}\n", name, value));
+    }
+    out.push_str("---\n\n");
+    out
+}
+
+fn sanitize_component(raw: &str) -> String {
+    let cleaned: String = raw
+        .chars()
+        .map(|c| {
+            if c.is_control() || FORBIDDEN_CHARS.contains(c) {
+                ' '
+            } else {
+                c
+            }
+        })
+        .collect();
+    let cleaned = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+    // Leading dots hide files; trailing dots and spaces are dropped by Windows
+    cleaned
+        .trim_matches(|c| c == '.' || c == ' ')
+        .chars()
+        .take(MAX_COMPONENT_CHARS)
+        .collect::<String>()
+        .trim_end_matches(['.', ' '])
+        .to_string()
+}
+
+/// The vault-relative path `template` gives a record, with `.md`.
+fn note_path(template: &str, title: &str, at: i64, id: &str) -> String {
+    let time = local_time(at);
+    let title = title.trim();
+    let vars: HashMap<String, String> = [
+        (
+            "title",
+            if title.is_empty() { "Untitled" } else { title }.replace(['/', '\\'], " "),
+        ),
+        ("date", time.format("%Y-%m-%d").to_string()),
+        ("time", time.format("%H-%M").to_string()),
+        ("year", time.format("%Y").to_string()),
+        ("month", time.format("%m").to_string()),
+        ("id", id.chars().take(8).collect()),
+    ]
+    .into_iter()
+    .map(|(name, value)| (name.to_string(), value))
+    .collect();
+    let rendered = crate::prompt_template::render(template, &vars).prompt;
+    let components: Vec<String> = rendered
+        .split(['/', '\\'])
+        .map(sanitize_component)
+        .filter(|component| !component.is_empty())
+        .collect();
+    if components.is_empty() {
+        return format!("{}.md", sanitize_component(id));
+    }
+    format!("{}.md", components.join("/"))
+}
+
+fn validate_template(template: &str) -> Result<(), String> {
+    if template.trim().is_empty() {
+        return Err("Filename template must not be empty".to_string());
+    }
+    let unknown: Vec<&str> = crate::prompt_template::variables(template)
+        .into_iter()
+        .filter(|name| !TEMPLATE_VARIABLES.contains(name))
+        .collect();
+    if !unknown.is_empty() {
+        return Err(format!(
+            "Unknown template variables: {}. Use {}",
+            unknown.join(", "),
+            TEMPLATE_VARIABLES.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+async fn render_conversation(
+    pool: &SqlitePool,
+    id: &str,
+    tags: &[String],
+) -> Result<Option<String>, String> {
+    let Some(conversation) = crate::db::chat::get(pool, id).await? else {
+        return Ok(None);
+    };
+    let mut participants: Vec<String> = Vec::new();
+    for message in &conversation.messages {
+        let label = crate::export::speaker_label(message.role).to_string();
+        if message.role != MessageRole::System && !participants.contains(&label) {
+            participants.push(label);
+        }
+    }
+    let mut all_tags = vec![FREELY_TAG.to_string()];
+    all_tags.extend(tags.iter().map(|tag| obsidian_tag(tag)));
+
+    let mut out = frontmatter(&[
+        ("title", yaml_string(conversation.title.trim())),
+        ("type", "conversation".to_string()),
+        ("date", yaml_time(conversation.created_at)),
+        ("updated", yaml_time(conversation.updated_at)),
+        ("tags", yaml_list(&all_tags)),
+        ("participants", yaml_list(&participants)),
+        ("freely-id", yaml_string(&conversation.id)),
+    ]);
+    out.push_str(&crate::export::render_markdown(&conversation));
+    Ok(Some(out))
+}
+
+fn render_meeting(
+    meeting: &Meeting,
+    tags: &[String],
+    participants: &[String],
+    conversation_path: Option<&str>,
+) -> String {
+    let mut all_tags = vec![FREELY_TAG.to_string(), "meeting".to_string()];
+    all_tags.extend(tags.iter().map(|tag| obsidian_tag(tag)));
+    let mut fields = vec![
+        ("title", yaml_string(meeting.title.trim())),
+        ("type", "meeting".to_string()),
+        ("date", yaml_time(meeting.started_at)),
+    ];
+    if let Some(ended_at) = meeting.ended_at {
+        fields.push(("ended", yaml_time(ended_at)));
+    }
+    fields.extend([
+        ("status", meeting.status.as_str().to_string()),
+        ("tags", yaml_list(&all_tags)),
+        ("participants", yaml_list(participants)),
+        ("freely-id", yaml_string(&meeting.id)),
+    ]);
+
+    let mut out = frontmatter(&fields);
+    out.push_str(&format!("# {}\n", meeting.title.trim()));
+    if let Some(path) = conversation_path {
+        out.push_str(&format!(
+            "\nTranscript: [[{}]]\n",
+            path.trim_end_matches(".md")
+        ));
+    }
+    for (heading, text) in [("Minutes", &meeting.minutes), ("Notes", &meeting.notes)] {
+        if let Some(text) = text.as_deref().filter(|text| !text.trim().is_empty()) {
+            out.push_str(&format!("\n## {}\n\n{}\n", heading, text.trim_end()));
+        }
+    }
+    out
+}
+
+async fn meeting_participants(
+    pool: &SqlitePool,
+    conversation_id: &str,
+) -> Result<Vec<String>, String> {
+    let transcripts =
+        crate::db::transcripts::list(pool, Some(conversation_id), None, MAX_TRANSCRIPTS, 0).await?;
+    let mut participants: Vec<String> = Vec::new();
+    for transcript in &transcripts {
+        let speaker = crate::meeting::notes::speaker(transcript).to_string();
+        if !participants.contains(&speaker) {
+            participants.push(speaker);
+        }
+    }
+    Ok(participants)
+}
+
+fn remove_file(vault: &Path, path: &str) -> Result<(), String> {
+    match std::fs::remove_file(vault.join(path)) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("Failed to remove {}: {}", path, e)),
+    }
+}
+
+fn write_file(vault: &Path, path: &str, content: &str) -> Result<(), String> {
+    let target = vault.join(path);
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    std::fs::write(&target, content).map_err(|e| format!("Failed to write {}: {}", path, e))
+}
+
+/// A record about to be written, before its content is rendered.
+struct Pending {
+    key: String,
+    id: String,
+    desired_path: String,
+    version: String,
+}
+
+/// Picks paths so two records never share a file and the mirror never
+/// takes over a note it didn't write.
+struct Paths<'a> {
+    vault: &'a Path,
+    /// Lowercased, since vaults often live on case-insensitive disks.
+    claimed: HashMap<String, String>,
+}
+
+impl Paths<'_> {
+    fn claim(&mut self, key: &str, id: &str, desired: String, current: Option<&str>) -> String {
+        let free = |path: &str| {
+            self.claimed
+                .get(&path.to_lowercase())
+                .is_none_or(|owner| owner == key)
+                && (current == Some(path) || !self.vault.join(path).exists())
+        };
+        let path = if free(&desired) {
+            desired
+        } else {
+            format!(
+                "{} ({}).md",
+                desired.trim_end_matches(".md"),
+                id.chars().take(8).collect::<String>()
+            )
+        };
+        self.claimed.insert(path.to_lowercase(), key.to_string());
+        path
+    }
+}
+
+/// Bring `vault` up to date with the database.
+async fn mirror(
+    pool: &SqlitePool,
+    config: &ObsidianConfig,
+    vault: &Path,
+    index: &mut MirrorIndex,
+) -> Result<MirrorReport, String> {
+    let conversations = sqlx::query_as::<_, ConversationRow>(
+        "SELECT c.id, c.title, c.created_at, c.updated_at,
+                (SELECT group_concat(t.name, char(31)) FROM conversation_tags ct
+                 JOIN tags t ON t.id = ct.tag_id
+                 WHERE ct.conversation_id = c.id) AS tags
+         FROM conversations c
+         WHERE c.deleted_at IS NULL AND (?1 OR c.archived_at IS NULL)
+         ORDER BY c.created_at ASC",
+    )
+    .bind(config.include_archived)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to list conversations: {}", e))?;
+    let meetings = sqlx::query_as::<_, MeetingRow>(
+        "SELECT m.id, m.conversation_id, m.title, m.started_at, m.updated_at,
+                (SELECT group_concat(DISTINCT speaker_label) FROM transcripts
+                 WHERE conversation_id = m.conversation_id) AS speakers
+         FROM meetings m
+         WHERE (m.notes IS NOT NULL OR m.minutes IS NOT NULL)
+           AND m.conversation_id NOT IN
+               (SELECT id FROM conversations WHERE deleted_at IS NOT NULL)
+         ORDER BY m.started_at ASC",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to list meetings: {}", e))?;
+
+    let mut report = MirrorReport::default();
+    let live: HashSet<String> = conversations
+        .iter()
+        .map(|c| format!("conversation:{}", c.id))
+        .chain(meetings.iter().map(|m| format!("meeting:{}", m.id)))
+        .collect();
+    let gone: Vec<String> = index
+        .entries
+        .keys()
+        .filter(|key| !live.contains(*key))
+        .cloned()
+        .collect();
+    for key in gone {
+        if let Some(entry) = index.entries.remove(&key) {
+            remove_file(vault, &entry.path)?;
+            report.removed += 1;
+        }
+    }
+
+    let mut paths = Paths {
+        vault,
+        claimed: index
+            .entries
+            .iter()
+            .map(|(key, entry)| (entry.path.to_lowercase(), key.clone()))
+            .collect(),
+    };
+    let mut tags_by_conversation: HashMap<String, Vec<String>> = HashMap::new();
+    let mut pending = Vec::new();
+    for row in conversations {
+        let tags = split_tags(row.tags);
+        pending.push(Pending {
+            key: format!("conversation:{}", row.id),
+            desired_path: note_path(
+                &config.conversation_template,
+                &row.title,
+                row.created_at,
+                &row.id,
+            ),
+            version: format!("{}|{}", row.updated_at, tags.join("\u{1f}")),
+            id: row.id.clone(),
+        });
+        tags_by_conversation.insert(row.id, tags);
+    }
+
+    // Conversations go first, so meetings can link to where they ended up
+    let mut conversation_paths: HashMap<String, String> = HashMap::new();
+    for item in pending {
+        let current = index.entries.get(&item.key).cloned();
+        let path = paths.claim(
+            &item.key,
+            &item.id,
+            item.desired_path,
+            current.as_ref().map(|entry| entry.path.as_str()),
+        );
+        conversation_paths.insert(item.id.clone(), path.clone());
+        let entry = IndexEntry {
+            path,
+            version: item.version,
+        };
+        if current.as_ref() == Some(&entry) {
+            report.unchanged += 1;
+            continue;
+        }
+        let tags = tags_by_conversation
+            .get(&item.id)
+            .cloned()
+            .unwrap_or_default();
+        let Some(content) = render_conversation(pool, &item.id, &tags).await? else {
+            continue;
+        };
+        write_file(vault, &entry.path, &content)?;
+        if let Some(old) = current.filter(|old| old.path != entry.path) {
+            remove_file(vault, &old.path)?;
+        }
+        index.entries.insert(item.key, entry);
+        report.written += 1;
+    }
+
+    for row in meetings {
+        let key = format!("meeting:{}", row.id);
+        let conversation_path = conversation_paths.get(&row.conversation_id).cloned();
+        let current = index.entries.get(&key).cloned();
+        let path = paths.claim(
+            &key,
+            &row.id,
+            note_path(
+                &config.meeting_template,
+                &row.title,
+                row.started_at,
+                &row.id,
+            ),
+            current.as_ref().map(|entry| entry.path.as_str()),
+        );
+        let entry = IndexEntry {
+            path,
+            version: format!(
+                "{}|{}|{}",
+                row.updated_at,
+                row.speakers.unwrap_or_default(),
+                conversation_path.as_deref().unwrap_or_default()
+            ),
+        };
+        if current.as_ref() == Some(&entry) {
+            report.unchanged += 1;
+            continue;
+        }
+        let Some(meeting) = crate::meeting::store::get(pool, &row.id).await? else {
+            continue;
+        };
+        let tags = tags_by_conversation
+            .get(&row.conversation_id)
+            .cloned()
+            .unwrap_or_default();
+        let participants = meeting_participants(pool, &row.conversation_id).await?;
+        let content = render_meeting(&meeting, &tags, &participants, conversation_path.as_deref());
+        write_file(vault, &entry.path, &content)?;
+        if let Some(old) = current.filter(|old| old.path != entry.path) {
+            remove_file(vault, &old.path)?;
+        }
+        index.entries.insert(key, entry);
+        report.written += 1;
+    }
+    Ok(report)
+}
+
+fn index_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app
+        .path()
+        .app_local_data_dir()
+        .map_err(|e| format!("Could not resolve app_local_data_dir: {}", e))?;
+    Ok(data_dir.join(INDEX_FILE))
+}
+
+/// The index for `vault`; a different vault starts from scratch, leaving
+/// the old one's files alone.
+fn load_index(path: &Path, vault: &str) -> MirrorIndex {
+    std::fs::read(path)
+        .ok()
+        .and_then(|raw| serde_json::from_slice::<MirrorIndex>(&raw).ok())
+        .filter(|index| index.vault == vault)
+        .unwrap_or_else(|| MirrorIndex {
+            vault: vault.to_string(),
+            entries: HashMap::new(),
+        })
+}
+
+fn save_index(path: &Path, index: &MirrorIndex) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let raw = serde_json::to_vec(index).map_err(|e| e.to_string())?;
+    std::fs::write(path, raw).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+async fn load_config(pool: &SqlitePool) -> Result<ObsidianConfig, String> {
+    Ok(crate::settings::get_setting(pool, SETTING_KEY)
+        .await?
+        .unwrap_or_default())
+}
+
+async fn mirror_once(app: &AppHandle) -> Result<MirrorReport, String> {
+    let pool = crate::db::pool(app).await?;
+    let config = load_config(&pool).await?;
+    let vault = config.vault.clone().ok_or("Choose a vault folder first")?;
+    if !Path::new(&vault).is_dir() {
+        return Err(format!("Vault folder not found: {}", vault));
+    }
+    let index_path = index_path(app)?;
+    let mut index = load_index(&index_path, &vault);
+    let result = mirror(&pool, &config, Path::new(&vault), &mut index).await;
+    // Files written before a failure are recorded, so they aren't orphaned
+    save_index(&index_path, &index)?;
+    result
+}
+
+async fn run_mirror(app: &AppHandle) -> Result<MirrorReport, String> {
+    if MIRRORING.swap(true, Ordering::SeqCst) {
+        return Err("The vault is already being updated".to_string());
+    }
+    let result = mirror_once(app).await;
+    MIRRORING.store(false, Ordering::SeqCst);
+    result
+}
+
+/// Keep the vault up to date while the mirror is enabled. Called once from
+/// `setup`.
+pub fn start_obsidian_mirror(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(MIRROR_INTERVAL);
+        loop {
+            interval.tick().await;
+            let enabled = match crate::db::pool(&app).await {
+                Ok(pool) => load_config(&pool).await.map(|config| config.enabled),
+                Err(e) => Err(e),
+            };
+            match enabled {
+                Ok(true) if !MIRRORING.load(Ordering::SeqCst) => {}
+                Ok(_) => continue,
+                Err(e) => {
+                    warn!("Failed to load Obsidian settings: {}", e);
+                    continue;
+                }
+            }
+            match run_mirror(&app).await {
+                Ok(report) if report.written > 0 || report.removed > 0 => info!(
+                    "Updated Obsidian vault: {} written, {} removed",
+                    report.written, report.removed
+                ),
+                Ok(_) => {}
+                Err(e) => warn!("Obsidian mirror failed: {}", e),
+            }
+        }
+    });
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+#[tauri::command]
+pub async fn get_obsidian_config(app: AppHandle) -> Result<ObsidianConfig, String> {
+    let pool = crate::db::pool(&app).await?;
+    load_config(&pool).await
+}
+
+/// Save the mirror settings, and bring the vault up to date right away if
+/// the mirror is enabled.
+#[tauri::command]
+pub async fn configure_obsidian(
+    app: AppHandle,
+    mut config: ObsidianConfig,
+) -> Result<Option<MirrorReport>, String> {
+    config.vault = config
+        .vault
+        .map(|vault| vault.trim().to_string())
+        .filter(|vault| !vault.is_empty());
+    if let Some(vault) = &config.vault {
+        if !Path::new(vault).is_dir() {
+            return Err(format!("Vault folder not found: {}", vault));
+        }
+    } else if config.enabled {
+        return Err("Choose a vault folder first".to_string());
+    }
+    validate_template(&config.conversation_template)?;
+    validate_template(&config.meeting_template)?;
+    crate::settings::set_setting(&app, SETTING_KEY, &config).await?;
+
+    if config.enabled {
+        run_mirror(&app).await.map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+/// Update the vault now, whether or not the mirror is enabled.
+#[tauri::command]
+pub async fn mirror_obsidian_now(app: AppHandle) -> Result<MirrorReport, String> {
+    run_mirror(&app).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::chat::{Conversation, Message};
+    use tempfile::TempDir;
+
+    fn conversation(id: &str, title: &str) -> Conversation {
+        Conversation {
+            id: id.to_string(),
+            title: title.to_string(),
+            created_at: 1_700_000_000_000,
+            updated_at: 1_700_000_000_000,
+            pinned: false,
+            archived_at: None,
+            deleted_at: None,
+            messages: vec![Message {
+                id: format!("{}-m1", id),
+                role: MessageRole::User,
+                content: "What did we decide?".to_string(),
+                timestamp: 1_700_000_000_000,
+                attached_files: None,
+                truncated: false,
+            }],
+        }
+    }
+
+    #[test]
+    fn templates_render_safe_relative_paths() {
+        let date = local_time(1_700_000_000_000).format("%Y-%m-%d").to_string();
+        assert_eq!(
+            note_path(
+                "Freely/{{date}} {{title}}",
+                "Q3: plan/budget?",
+                1_700_000_000_000,
+                "abc"
+            ),
+            format!("Freely/{} Q3 plan budget.md", date)
+        );
+        assert_eq!(
+            note_path("../{{title}}/..", "..hidden", 0, "abc"),
+            "hidden.md"
+        );
+        assert_eq!(note_path("/", "x", 0, "abcdef123456"), "abcdef123456.md");
+        assert!(validate_template("{{title}} {{author}}").is_err());
+        assert!(validate_template("{{year}}/{{month}}/{{title}} {{id}}").is_ok());
+    }
+
+    #[tokio::test]
+    async fn mirror_follows_changes_and_spares_other_notes() {
+        let pool = crate::db::test_pool().await;
+        let tmp = TempDir::new().unwrap();
+        let vault = tmp.path();
+        let config = ObsidianConfig {
+            enabled: true,
+            vault: Some(vault.to_string_lossy().into_owned()),
+            conversation_template: "{{title}}".to_string(),
+            ..Default::default()
+        };
+        let mut index = MirrorIndex::default();
+
+        crate::db::chat::create(&pool, conversation("c1", "Planning"))
+            .await
+            .unwrap();
+        crate::db::chat::create(&pool, conversation("c2", "Mine"))
+            .await
+            .unwrap();
+        crate::db::tags::tag(&pool, "c1", "Work stuff")
+            .await
+            .unwrap();
+        // A note the user wrote themselves, where c2 would go
+        std::fs::write(vault.join("Mine.md"), "my own note").unwrap();
+
+        let report = mirror(&pool, &config, vault, &mut index).await.unwrap();
+        assert_eq!(report.written, 2);
+        let planning = std::fs::read_to_string(vault.join("Planning.md")).unwrap();
+        assert!(planning.starts_with("---\ntitle: \"Planning\"\n"));
+        assert!(planning.contains("tags: [\"freely\", \"Work-stuff\"]\n"));
+        assert!(planning.contains("participants: [\"User\"]\n"));
+        assert!(planning.contains("What did we decide?"));
+        assert_eq!(
+            std::fs::read_to_string(vault.join("Mine.md")).unwrap(),
+            "my own note"
+        );
+        assert!(vault.join("Mine (c2).md").is_file());
+
+        let report = mirror(&pool, &config, vault, &mut index).await.unwrap();
+        assert_eq!(report.unchanged, 2);
+
+        sqlx::query("UPDATE conversations SET title = 'Roadmap', updated_at = ? WHERE id = 'c1'")
+            .bind(1_700_000_100_000i64)
+            .execute(&pool)
+            .await
+            .unwrap();
+        crate::db::chat::delete(&pool, "c2").await.unwrap();
+        let report = mirror(&pool, &config, vault, &mut index).await.unwrap();
+        assert_eq!((report.written, report.removed), (1, 1));
+        assert!(!vault.join("Planning.md").exists());
+        assert!(vault.join("Roadmap.md").is_file());
+        assert!(!vault.join("Mine (c2).md").exists());
+        assert!(vault.join("Mine.md").is_file());
+    }
+}
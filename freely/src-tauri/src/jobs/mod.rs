@@ -0,0 +1,499 @@
+//! Background jobs that survive restarts.
+//!
+//! Long-running work (indexing chat history or a knowledge folder,
+//! summarizing a conversation, scheduled backups and prompts, model
+//! downloads, file transcription, retention cleanup) is queued in the
+//! `jobs` table (migration 19) and picked up by a small pool of workers on
+//! the async runtime. Workers report `job-progress` while a job runs and
+//! `job-updated` whenever its status changes. A job cut short by quitting the app is queued again on the next
+//! start, so every handler must be safe to run twice; all of them pick up
+//! where the work left off.
+//!
+//! Cancelling a running job sets a flag that its handler checks between
+//! batches, files or download chunks.
+//!
+//! A job that fails because Freely is offline goes back in the queue and is
+//! skipped until the connectivity monitor sees the network return.
+//!
+//! A job that ran for longer than [`NOTIFY_AFTER`] shows a notification when
+//! it completes or fails, since the user has likely moved on by then.
+
+pub(crate) mod store;
+
+use crate::context::compactor;
+use crate::embeddings;
+use crate::knowledge::{self, indexer};
+use crate::models::ModelKind;
+use crate::notify::{self, NotificationAction};
+use crate::speaker::local_whisper::WhisperModel;
+use crate::stt::file::{self, TranscribeFileOptions};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use store::{Job, JobStatus};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::Notify;
+use tracing::{info, warn};
+
+const WORKERS: usize = 2;
+/// How often idle workers look for jobs even without a wake-up.
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+/// Minimum time between two progress writes for one job; events are not
+/// throttled.
+const PROGRESS_SAVE_INTERVAL: Duration = Duration::from_secs(1);
+/// Finished jobs are forgotten after this long.
+const RETENTION: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+const DEFAULT_LIST_LIMIT: u32 = 100;
+const NOTIFY_AFTER: Duration = Duration::from_secs(60);
+const CANCELLED: &str = "Job cancelled";
+const WAITING_FOR_NETWORK: &str = "Waiting for network";
+
+static WAKE: Lazy<Notify> = Lazy::new(Notify::new);
+/// Cancellation flags of the jobs running in this process.
+static RUNNING: Lazy<Mutex<HashMap<String, Arc<AtomicBool>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+/// Jobs deferred until the network is back.
+static WAITING: Lazy<Mutex<HashSet<String>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// What a job does. Stored as JSON in `jobs.payload`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum JobSpec {
+    /// Embed every message not indexed for the configured model yet.
+    IndexChatHistory,
+    #[serde(rename_all = "camelCase")]
+    IndexKnowledgeFolder {
+        folder_id: String,
+    },
+    /// Fold the older part of a conversation into its running summary.
+    #[serde(rename_all = "camelCase")]
+    CompactConversation {
+        conversation_id: String,
+    },
+    /// Snapshot the database into the backups directory.
+    Backup,
+    DownloadWhisperModel {
+        model: WhisperModel,
+    },
+    /// Fetch a model file, resuming a partial download.
+    DownloadModel {
+        kind: ModelKind,
+        url: String,
+        filename: String,
+        sha256: Option<String>,
+    },
+    /// Run a scheduled prompt or reminder.
+    #[serde(rename_all = "camelCase")]
+    RunScheduledTask {
+        task_id: String,
+    },
+    /// Transcribe an audio or video file into a new conversation.
+    TranscribeFile {
+        path: String,
+        options: TranscribeFileOptions,
+    },
+    /// Delete data older than the `retention` policy allows.
+    RetentionCleanup,
+}
+
+impl JobSpec {
+    pub(crate) fn kind(&self) -> &'static str {
+        match self {
+            Self::IndexChatHistory => "indexChatHistory",
+            Self::IndexKnowledgeFolder { .. } => "indexKnowledgeFolder",
+            Self::CompactConversation { .. } => "compactConversation",
+            Self::Backup => "backup",
+            Self::DownloadWhisperModel { .. } => "downloadWhisperModel",
+            Self::DownloadModel { .. } => "downloadModel",
+            Self::RunScheduledTask { .. } => "runScheduledTask",
+            Self::TranscribeFile { .. } => "transcribeFile",
+            Self::RetentionCleanup => "retentionCleanup",
+        }
+    }
+
+    /// What the job is called in notifications, or `None` for jobs that
+    /// show their own.
+    fn notice_name(&self) -> Option<&'static str> {
+        match self {
+            Self::IndexChatHistory => Some("Chat history indexing"),
+            Self::IndexKnowledgeFolder { .. } => Some("Knowledge folder indexing"),
+            Self::CompactConversation { .. } => Some("Conversation summary"),
+            Self::Backup => Some("Backup"),
+            Self::DownloadWhisperModel { .. } | Self::DownloadModel { .. } => {
+                Some("Model download")
+            }
+            Self::RunScheduledTask { .. } => None,
+            Self::TranscribeFile { .. } => Some("Transcription"),
+            Self::RetentionCleanup => Some("Retention cleanup"),
+        }
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JobProgress<'a> {
+    id: &'a str,
+    kind: &'static str,
+    progress: Option<f64>,
+    message: Option<&'a str>,
+}
+
+/// Handed to a running job for reporting progress and noticing
+/// cancellation.
+pub(crate) struct JobContext {
+    app: AppHandle,
+    pool: SqlitePool,
+    id: String,
+    kind: &'static str,
+    cancelled: Arc<AtomicBool>,
+    last_saved: Mutex<Option<Instant>>,
+}
+
+impl JobContext {
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// `Err` once the job has been cancelled, for handlers to `?` on.
+    pub(crate) fn check_cancelled(&self) -> Result<(), String> {
+        if self.is_cancelled() {
+            return Err(CANCELLED.to_string());
+        }
+        Ok(())
+    }
+
+    /// Report `progress` (a fraction in [0, 1], if known) and return
+    /// [`check_cancelled`](Self::check_cancelled), so progress callbacks
+    /// double as cancellation points.
+    pub(crate) fn progress(
+        &self,
+        progress: Option<f64>,
+        message: Option<String>,
+    ) -> Result<(), String> {
+        let progress = progress.map(|p| p.clamp(0.0, 1.0));
+        let event = JobProgress {
+            id: &self.id,
+            kind: self.kind,
+            progress,
+            message: message.as_deref(),
+        };
+        if let Err(e) = self.app.emit("job-progress", event) {
+            warn!("Failed to emit job progress: {}", e);
+        }
+
+        let due = {
+            let mut last_saved = self.last_saved.lock();
+            let due = last_saved.is_none_or(|at| at.elapsed() >= PROGRESS_SAVE_INTERVAL);
+            if due {
+                *last_saved = Some(Instant::now());
+            }
+            due
+        };
+        if due {
+            let pool = self.pool.clone();
+            let id = self.id.clone();
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = store::set_progress(&pool, &id, progress, message.as_deref()).await
+                {
+                    warn!("{}", e);
+                }
+            });
+        }
+        self.check_cancelled()
+    }
+}
+
+fn emit_updated(app: &AppHandle, job: &Job) {
+    if let Err(e) = app.emit("job-updated", job) {
+        warn!("Failed to emit job update: {}", e);
+    }
+}
+
+/// Queue `spec` and wake a worker. Work that is already queued or running
+/// isn't queued again; the existing job is returned instead.
+pub(crate) async fn enqueue(app: &AppHandle, spec: JobSpec) -> Result<Job, String> {
+    let pool = crate::db::pool(app).await?;
+    let job = store::insert(&pool, &spec).await?;
+    if job.status == JobStatus::Queued {
+        emit_updated(app, &job);
+        WAKE.notify_one();
+    }
+    Ok(job)
+}
+
+/// Let the jobs deferred while offline run again.
+pub(crate) fn resume_waiting() {
+    let resumed = std::mem::take(&mut *WAITING.lock());
+    if !resumed.is_empty() {
+        info!("Resuming {} job(s) after reconnecting", resumed.len());
+        WAKE.notify_waiters();
+    }
+}
+
+fn to_value(value: impl Serialize) -> Result<Value, String> {
+    serde_json::to_value(value).map_err(|e| format!("Failed to serialize job result: {}", e))
+}
+
+fn download_fraction(downloaded_bytes: u64, total_bytes: Option<u64>) -> Option<f64> {
+    total_bytes.map(|total| downloaded_bytes as f64 / total.max(1) as f64)
+}
+
+async fn execute(ctx: &JobContext, spec: &JobSpec) -> Result<Value, String> {
+    let (app, pool) = (&ctx.app, &ctx.pool);
+    match spec {
+        JobSpec::IndexChatHistory => {
+            let config = embeddings::require_config(pool).await?;
+            let indexed = embeddings::index_all(app, pool, &config, |indexed, remaining| {
+                let total = (indexed + remaining).max(1) as f64;
+                ctx.progress(
+                    Some(indexed as f64 / total),
+                    Some(format!("{} messages indexed", indexed)),
+                )
+            })
+            .await?;
+            Ok(json!({ "indexed": indexed }))
+        }
+        JobSpec::IndexKnowledgeFolder { folder_id } => {
+            let config = embeddings::require_config(pool).await?;
+            let folder = knowledge::store::get_folder(pool, folder_id)
+                .await?
+                .ok_or_else(|| format!("Knowledge folder not found: {}", folder_id))?;
+            let stats = indexer::index_folder(app, pool, &config, &folder, |done, total| {
+                ctx.progress(
+                    Some(done as f64 / total.max(1) as f64),
+                    Some(format!("{} of {} files", done, total)),
+                )
+            })
+            .await?;
+            to_value(stats)
+        }
+        JobSpec::CompactConversation { conversation_id } => {
+            let config = compactor::load_config(pool)
+                .await?
+                .ok_or("Context compaction is not configured")?;
+            let summary = compactor::compact(app, pool, &config, conversation_id, false).await?;
+            Ok(json!({ "summarized": summary.is_some() }))
+        }
+        JobSpec::Backup => {
+            let path = crate::db::backup::take_scheduled_backup(app).await?;
+            Ok(json!({ "path": path.to_string_lossy() }))
+        }
+        JobSpec::DownloadWhisperModel { model } => {
+            let info = crate::stt::local::download_model(app, *model, |progress| {
+                let fraction = download_fraction(progress.downloaded_bytes, progress.total_bytes);
+                ctx.progress(fraction, None)
+            })
+            .await?;
+            to_value(info)
+        }
+        JobSpec::DownloadModel {
+            kind,
+            url,
+            filename,
+            sha256,
+        } => {
+            let model =
+                crate::models::download(app, *kind, url, filename, sha256.as_deref(), |progress| {
+                    let fraction =
+                        download_fraction(progress.downloaded_bytes, progress.total_bytes);
+                    ctx.progress(fraction, None)
+                })
+                .await?;
+            to_value(model)
+        }
+        JobSpec::RunScheduledTask { task_id } => {
+            crate::scheduler::run_task(app, pool, task_id).await
+        }
+        JobSpec::RetentionCleanup => to_value(crate::retention::run_cleanup(app, pool).await?),
+        JobSpec::TranscribeFile { path, options } => {
+            if crate::db::chat::get(pool, &ctx.id).await?.is_some() {
+                return Ok(json!({ "conversationId": ctx.id }));
+            }
+            let path = std::path::Path::new(path);
+            let result = file::transcribe(app, pool, &ctx.id, path, options, |fraction| {
+                ctx.progress(Some(fraction), None)
+            })
+            .await?;
+            to_value(result)
+        }
+    }
+}
+
+/// Tell the user that a long job completed or failed.
+fn notify_finished(app: &AppHandle, spec: &JobSpec, outcome: &Result<Value, String>) {
+    let Some(name) = spec.notice_name() else {
+        return;
+    };
+    let (title, body) = match outcome {
+        Ok(_) => (format!("{} finished", name), "Done.".to_string()),
+        Err(e) => (format!("{} failed", name), e.clone()),
+    };
+    let action = match spec {
+        JobSpec::CompactConversation { conversation_id } => NotificationAction::OpenConversation {
+            conversation_id: conversation_id.clone(),
+        },
+        _ => NotificationAction::ShowJobs,
+    };
+    notify::show(app, &title, &body, vec![action]);
+}
+
+async fn run(app: &AppHandle, pool: &SqlitePool, job: Job) {
+    emit_updated(app, &job);
+    let started = Instant::now();
+    let cancelled = Arc::new(AtomicBool::new(false));
+    RUNNING.lock().insert(job.id.clone(), cancelled.clone());
+
+    let ctx = JobContext {
+        app: app.clone(),
+        pool: pool.clone(),
+        id: job.id.clone(),
+        kind: job.spec.kind(),
+        cancelled,
+        last_saved: Mutex::new(None),
+    };
+    let outcome = execute(&ctx, &job.spec).await;
+    RUNNING.lock().remove(&job.id);
+
+    let finished = match &outcome {
+        Ok(result) => store::finish(pool, &job.id, JobStatus::Completed, None, Some(result)).await,
+        Err(_) if ctx.is_cancelled() => {
+            store::finish(pool, &job.id, JobStatus::Cancelled, None, None).await
+        }
+        Err(e) if crate::net::connectivity::is_offline_error(e) => {
+            WAITING.lock().insert(job.id.clone());
+            store::defer(pool, &job.id, WAITING_FOR_NETWORK).await
+        }
+        Err(e) => {
+            warn!("Job {} ({}) failed: {}", job.id, ctx.kind, e);
+            store::finish(pool, &job.id, JobStatus::Failed, Some(e), None).await
+        }
+    };
+    let deferred = WAITING.lock().contains(&job.id);
+    if started.elapsed() >= NOTIFY_AFTER && !ctx.is_cancelled() && !deferred {
+        notify_finished(app, &job.spec, &outcome);
+    }
+    if let Err(e) = finished {
+        warn!("{}", e);
+    }
+    match store::get(pool, &job.id).await {
+        Ok(Some(job)) => emit_updated(app, &job),
+        Ok(None) => {}
+        Err(e) => warn!("{}", e),
+    }
+}
+
+async fn work(app: AppHandle) {
+    loop {
+        let waiting: Vec<String> = WAITING.lock().iter().cloned().collect();
+        // Looked up per claim, since encrypting the database replaces the pool
+        let claimed = async {
+            let pool = crate::db::pool(&app).await?;
+            let job = store::claim_next(&pool, &waiting).await?;
+            Ok::<_, String>(job.map(|job| (pool, job)))
+        }
+        .await;
+        match claimed {
+            Ok(Some((pool, job))) => run(&app, &pool, job).await,
+            Ok(None) => {
+                let _ = tokio::time::timeout(POLL_INTERVAL, WAKE.notified()).await;
+            }
+            Err(e) => {
+                warn!("{}", e);
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        }
+    }
+}
+
+/// Call `f` every `period`, the first time after `startup_delay`, logging
+/// failures as "`what` failed". For the periodic checks started from
+/// `setup`.
+pub(crate) fn run_periodically<F, Fut>(
+    app: AppHandle,
+    what: &'static str,
+    period: Duration,
+    startup_delay: Duration,
+    f: F,
+) where
+    F: Fn(AppHandle) -> Fut + Send + 'static,
+    Fut: std::future::Future<Output = Result<(), String>> + Send,
+{
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(startup_delay).await;
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            if let Err(e) = f(app.clone()).await {
+                warn!("{} failed: {}", what, e);
+            }
+        }
+    });
+}
+
+/// Recover jobs interrupted by the last shutdown and start the workers.
+/// Called once from `setup`.
+pub fn start_job_workers(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let recovered = async {
+            let pool = crate::db::pool(&app).await?;
+            match store::requeue_interrupted(&pool).await? {
+                0 => {}
+                count => info!("Resuming {} interrupted job(s)", count),
+            }
+            let before = crate::db::now_millis() - RETENTION.as_millis() as i64;
+            store::prune_finished(&pool, before).await
+        }
+        .await;
+        if let Err(e) = recovered {
+            warn!("{}", e);
+        }
+
+        for _ in 0..WORKERS {
+            tauri::async_runtime::spawn(work(app.clone()));
+        }
+    });
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// Queue a job, or return the one already queued or running for the same
+/// work.
+#[tauri::command]
+pub async fn enqueue_job(app: AppHandle, spec: JobSpec) -> Result<Job, String> {
+    enqueue(&app, spec).await
+}
+
+/// Jobs with `status`, or all of them, newest first.
+#[tauri::command]
+pub async fn list_jobs(
+    app: AppHandle,
+    status: Option<JobStatus>,
+    limit: Option<u32>,
+) -> Result<Vec<Job>, String> {
+    let pool = crate::db::pool(&app).await?;
+    store::list(&pool, status, limit.unwrap_or(DEFAULT_LIST_LIMIT)).await
+}
+
+/// Cancel a queued job, or ask a running one to stop. Finished jobs are
+/// returned unchanged.
+#[tauri::command]
+pub async fn cancel_job(app: AppHandle, id: String) -> Result<Job, String> {
+    let pool = crate::db::pool(&app).await?;
+    let job = store::request_cancel(&pool, &id).await?;
+    if job.status == JobStatus::Running {
+        if let Some(flag) = RUNNING.lock().get(&id) {
+            flag.store(true, Ordering::Relaxed);
+        }
+    } else if job.status == JobStatus::Cancelled {
+        emit_updated(&app, &job);
+    }
+    Ok(job)
+}
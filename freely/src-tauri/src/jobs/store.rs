@@ -0,0 +1,346 @@
+//! The `jobs` table (migration 19).
+
+use super::JobSpec;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+const JOB_COLUMNS: &str = "id, payload, status, progress, message, error, result, attempts,
+     created_at, started_at, finished_at";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Queued => "queued",
+            Self::Running => "running",
+            Self::Completed => "completed",
+            Self::Failed => "failed",
+            Self::Cancelled => "cancelled",
+        }
+    }
+
+    fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "queued" => Ok(Self::Queued),
+            "running" => Ok(Self::Running),
+            "completed" => Ok(Self::Completed),
+            "failed" => Ok(Self::Failed),
+            "cancelled" => Ok(Self::Cancelled),
+            other => Err(format!("Unknown job status: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Job {
+    pub id: String,
+    pub spec: JobSpec,
+    pub status: JobStatus,
+    /// Fraction done in [0, 1], when the job can tell.
+    pub progress: Option<f64>,
+    pub message: Option<String>,
+    pub error: Option<String>,
+    pub result: Option<serde_json::Value>,
+    /// Times the job was started, counting runs cut short by a restart.
+    pub attempts: i64,
+    pub created_at: i64,
+    pub started_at: Option<i64>,
+    pub finished_at: Option<i64>,
+}
+
+#[derive(sqlx::FromRow)]
+struct JobRow {
+    id: String,
+    payload: String,
+    status: String,
+    progress: Option<f64>,
+    message: Option<String>,
+    error: Option<String>,
+    result: Option<String>,
+    attempts: i64,
+    created_at: i64,
+    started_at: Option<i64>,
+    finished_at: Option<i64>,
+}
+
+impl TryFrom<JobRow> for Job {
+    type Error = String;
+
+    fn try_from(row: JobRow) -> Result<Self, Self::Error> {
+        Ok(Job {
+            spec: serde_json::from_str(&row.payload)
+                .map_err(|e| format!("Failed to parse job {}: {}", row.id, e))?,
+            id: row.id,
+            status: JobStatus::parse(&row.status)?,
+            progress: row.progress,
+            message: row.message,
+            error: row.error,
+            result: row.result.and_then(|raw| serde_json::from_str(&raw).ok()),
+            attempts: row.attempts,
+            created_at: row.created_at,
+            started_at: row.started_at,
+            finished_at: row.finished_at,
+        })
+    }
+}
+
+fn payload(spec: &JobSpec) -> Result<String, String> {
+    serde_json::to_string(spec).map_err(|e| format!("Failed to serialize job: {}", e))
+}
+
+pub(crate) async fn get(pool: &SqlitePool, id: &str) -> Result<Option<Job>, String> {
+    sqlx::query_as::<_, JobRow>(&format!("SELECT {} FROM jobs WHERE id = ?", JOB_COLUMNS))
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Failed to load job: {}", e))?
+        .map(Job::try_from)
+        .transpose()
+}
+
+/// Queue `spec`, or return the job already queued or running for it so the
+/// same work is never lined up twice.
+pub(crate) async fn insert(pool: &SqlitePool, spec: &JobSpec) -> Result<Job, String> {
+    let payload = payload(spec)?;
+    let existing = sqlx::query_as::<_, JobRow>(&format!(
+        "SELECT {} FROM jobs WHERE payload = ? AND status IN ('queued', 'running')",
+        JOB_COLUMNS
+    ))
+    .bind(&payload)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to look up job: {}", e))?;
+    if let Some(row) = existing {
+        return Job::try_from(row);
+    }
+
+    let id = uuid::Uuid::new_v4().to_string();
+    sqlx::query("INSERT INTO jobs (id, kind, payload, created_at) VALUES (?, ?, ?, ?)")
+        .bind(&id)
+        .bind(spec.kind())
+        .bind(&payload)
+        .bind(crate::db::now_millis())
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to queue job: {}", e))?;
+    get(pool, &id)
+        .await?
+        .ok_or_else(|| format!("Job not found: {}", id))
+}
+
+/// Mark the oldest queued job not in `skip` running and return it. The
+/// update is a single statement, so two workers never claim the same job.
+pub(crate) async fn claim_next(pool: &SqlitePool, skip: &[String]) -> Result<Option<Job>, String> {
+    let skip = serde_json::to_string(skip).map_err(|e| format!("Failed to claim job: {}", e))?;
+    sqlx::query_as::<_, JobRow>(&format!(
+        "UPDATE jobs SET status = 'running', started_at = ?, attempts = attempts + 1,
+             progress = NULL, message = NULL
+         WHERE id = (SELECT id FROM jobs
+                     WHERE status = 'queued' AND id NOT IN (SELECT value FROM json_each(?))
+                     ORDER BY created_at, rowid LIMIT 1)
+         RETURNING {}",
+        JOB_COLUMNS
+    ))
+    .bind(crate::db::now_millis())
+    .bind(skip)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to claim job: {}", e))?
+    .map(Job::try_from)
+    .transpose()
+}
+
+pub(crate) async fn set_progress(
+    pool: &SqlitePool,
+    id: &str,
+    progress: Option<f64>,
+    message: Option<&str>,
+) -> Result<(), String> {
+    sqlx::query("UPDATE jobs SET progress = ?, message = ? WHERE id = ? AND status = 'running'")
+        .bind(progress)
+        .bind(message)
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to update job progress: {}", e))?;
+    Ok(())
+}
+
+/// Record how a running job ended.
+pub(crate) async fn finish(
+    pool: &SqlitePool,
+    id: &str,
+    status: JobStatus,
+    error: Option<&str>,
+    result: Option<&serde_json::Value>,
+) -> Result<(), String> {
+    sqlx::query(
+        "UPDATE jobs SET status = ?, error = ?, result = ?, finished_at = ?,
+             progress = CASE WHEN ? = 'completed' THEN 1.0 ELSE progress END
+         WHERE id = ?",
+    )
+    .bind(status.as_str())
+    .bind(error)
+    .bind(result.map(|value| value.to_string()))
+    .bind(crate::db::now_millis())
+    .bind(status.as_str())
+    .bind(id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to finish job: {}", e))?;
+    Ok(())
+}
+
+/// Cancel a queued job outright, or flag a running one so its worker stops
+/// at the next checkpoint. Returns the job as it now stands.
+pub(crate) async fn request_cancel(pool: &SqlitePool, id: &str) -> Result<Job, String> {
+    sqlx::query(
+        "UPDATE jobs SET status = 'cancelled', finished_at = ? WHERE id = ? AND status = 'queued'",
+    )
+    .bind(crate::db::now_millis())
+    .bind(id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to cancel job: {}", e))?;
+    sqlx::query("UPDATE jobs SET cancel_requested = 1 WHERE id = ? AND status = 'running'")
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to cancel job: {}", e))?;
+    get(pool, id)
+        .await?
+        .ok_or_else(|| format!("Job not found: {}", id))
+}
+
+/// Put a running job back in the queue with `message` as its status line,
+/// e.g. to wait for the network.
+pub(crate) async fn defer(pool: &SqlitePool, id: &str, message: &str) -> Result<(), String> {
+    sqlx::query(
+        "UPDATE jobs SET status = 'queued', started_at = NULL, progress = NULL, message = ?
+         WHERE id = ? AND status = 'running'",
+    )
+    .bind(message)
+    .bind(id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to defer job: {}", e))?;
+    Ok(())
+}
+
+/// Put jobs interrupted by the last shutdown back in the queue, except those
+/// that were being cancelled. Returns how many were queued again.
+pub(crate) async fn requeue_interrupted(pool: &SqlitePool) -> Result<u64, String> {
+    sqlx::query(
+        "UPDATE jobs SET status = 'cancelled', finished_at = ?
+         WHERE status = 'running' AND cancel_requested = 1",
+    )
+    .bind(crate::db::now_millis())
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to recover jobs: {}", e))?;
+    let result = sqlx::query("UPDATE jobs SET status = 'queued' WHERE status = 'running'")
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to recover jobs: {}", e))?;
+    Ok(result.rows_affected())
+}
+
+/// Forget finished jobs that ended before `before` (milliseconds).
+pub(crate) async fn prune_finished(pool: &SqlitePool, before: i64) -> Result<u64, String> {
+    let result = sqlx::query(
+        "DELETE FROM jobs
+         WHERE status IN ('completed', 'failed', 'cancelled') AND finished_at < ?",
+    )
+    .bind(before)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to prune jobs: {}", e))?;
+    Ok(result.rows_affected())
+}
+
+/// Jobs with `status`, or all of them, newest first.
+pub(crate) async fn list(
+    pool: &SqlitePool,
+    status: Option<JobStatus>,
+    limit: u32,
+) -> Result<Vec<Job>, String> {
+    sqlx::query_as::<_, JobRow>(&format!(
+        "SELECT {} FROM jobs WHERE ?1 IS NULL OR status = ?1
+         ORDER BY created_at DESC, rowid DESC LIMIT ?2",
+        JOB_COLUMNS
+    ))
+    .bind(status.map(JobStatus::as_str))
+    .bind(limit as i64)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to list jobs: {}", e))?
+    .into_iter()
+    .map(Job::try_from)
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn jobs_are_claimed_once_and_survive_restarts() {
+        let pool = crate::db::test_pool().await;
+        let backup = insert(&pool, &JobSpec::Backup).await.unwrap();
+        let again = insert(&pool, &JobSpec::Backup).await.unwrap();
+        assert_eq!(again.id, backup.id, "the same work is queued once");
+        let index = insert(&pool, &JobSpec::IndexChatHistory).await.unwrap();
+
+        let skipped = std::slice::from_ref(&backup.id);
+        let claimed = claim_next(&pool, skipped).await.unwrap().unwrap();
+        assert_eq!(claimed.id, index.id, "skipped jobs are left queued");
+        defer(&pool, &index.id, "Waiting for network")
+            .await
+            .unwrap();
+        let deferred = get(&pool, &index.id).await.unwrap().unwrap();
+        assert_eq!(deferred.status, JobStatus::Queued);
+        assert_eq!(deferred.message.as_deref(), Some("Waiting for network"));
+
+        let claimed = claim_next(&pool, &[]).await.unwrap().unwrap();
+        assert_eq!(claimed.id, backup.id);
+        assert_eq!(claimed.status, JobStatus::Running);
+        assert_eq!(claimed.attempts, 1);
+
+        // Queued jobs are cancelled at once; running ones only flagged
+        let cancelled = request_cancel(&pool, &index.id).await.unwrap();
+        assert_eq!(cancelled.status, JobStatus::Cancelled);
+        assert!(claim_next(&pool, &[]).await.unwrap().is_none());
+
+        // A restart mid-run queues the job again
+        assert_eq!(requeue_interrupted(&pool).await.unwrap(), 1);
+        let retried = claim_next(&pool, &[]).await.unwrap().unwrap();
+        assert_eq!(retried.id, backup.id);
+        assert_eq!(retried.attempts, 2);
+
+        finish(&pool, &backup.id, JobStatus::Completed, None, None)
+            .await
+            .unwrap();
+        let done = get(&pool, &backup.id).await.unwrap().unwrap();
+        assert_eq!(done.progress, Some(1.0));
+        assert_eq!(
+            list(&pool, Some(JobStatus::Completed), 10)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(list(&pool, None, 10).await.unwrap().len(), 2);
+
+        assert_eq!(prune_finished(&pool, i64::MAX).await.unwrap(), 2);
+    }
+}
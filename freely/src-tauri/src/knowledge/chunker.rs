@@ -0,0 +1,118 @@
+//! Line-based chunking for text and source files.
+//!
+//! Chunks never split a line, so every chunk maps back to an exact line
+//! range. When a chunk fills up, the cut moves back to the last blank line
+//! in its second half, which keeps paragraphs and most functions together,
+//! and consecutive chunks overlap by a few lines so a match at a boundary
+//! still carries its context.
+
+use crate::tokens::Encoding;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Chunk {
+    /// First line, 1-based.
+    pub start_line: usize,
+    /// Last line, inclusive.
+    pub end_line: usize,
+    pub content: String,
+}
+
+pub(crate) fn chunk_text(
+    text: &str,
+    encoding: Encoding,
+    max_tokens: usize,
+    overlap_lines: usize,
+) -> Vec<Chunk> {
+    let lines: Vec<&str> = text.lines().collect();
+    // +1 for the newline joining it to the next line
+    let counts: Vec<usize> = lines.iter().map(|line| encoding.count(line) + 1).collect();
+
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < lines.len() {
+        // Always take at least one line, even if it alone is over budget
+        let mut end = start;
+        let mut tokens = 0;
+        while end < lines.len() && (end == start || tokens + counts[end] <= max_tokens) {
+            tokens += counts[end];
+            end += 1;
+        }
+
+        if end < lines.len() {
+            let midpoint = start + (end - start) / 2;
+            if let Some(blank) = (midpoint.max(start + 1)..end)
+                .rev()
+                .find(|&i| lines[i].trim().is_empty())
+            {
+                end = blank + 1;
+            }
+        }
+
+        let content = lines[start..end].join("\n");
+        if !content.trim().is_empty() {
+            chunks.push(Chunk {
+                start_line: start + 1,
+                end_line: end,
+                content,
+            });
+        }
+        if end >= lines.len() {
+            break;
+        }
+        start = end.saturating_sub(overlap_lines).max(start + 1);
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_cover_every_line_and_prefer_blank_lines() {
+        let encoding = crate::tokens::encoding_for("gpt-4o");
+        let text = (1..=40)
+            .map(|i| {
+                if i % 10 == 0 {
+                    String::new()
+                } else {
+                    format!("line number {}", i)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let chunks = chunk_text(&text, encoding, 60, 2);
+        assert!(chunks.len() > 1);
+        assert_eq!(chunks[0].start_line, 1);
+        assert_eq!(chunks.last().unwrap().end_line, text.lines().count());
+        for pair in chunks.windows(2) {
+            // Overlapping, but always moving forward
+            assert!(pair[1].start_line > pair[0].start_line);
+            assert!(pair[1].start_line <= pair[0].end_line + 1);
+        }
+        for chunk in &chunks[..chunks.len() - 1] {
+            assert_eq!(chunk.end_line % 10, 0, "cut at {}", chunk.end_line);
+        }
+
+        let first = &chunks[0];
+        let expected: Vec<&str> = text
+            .lines()
+            .skip(first.start_line - 1)
+            .take(first.end_line - first.start_line + 1)
+            .collect();
+        assert_eq!(first.content, expected.join("\n"));
+    }
+
+    #[test]
+    fn oversized_lines_become_their_own_chunk() {
+        let encoding = crate::tokens::encoding_for("gpt-4o");
+        let long = "word ".repeat(200);
+        let text = format!("short\n{}\nshort", long);
+
+        let chunks = chunk_text(&text, encoding, 20, 0);
+        let ranges: Vec<_> = chunks.iter().map(|c| (c.start_line, c.end_line)).collect();
+        assert_eq!(ranges, [(1, 1), (2, 2), (3, 3)]);
+        assert!(chunk_text("\n  \n", encoding, 20, 0).is_empty());
+    }
+}
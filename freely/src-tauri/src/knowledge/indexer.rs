@@ -0,0 +1,366 @@
+//! Walking knowledge folders and keeping their chunks in sync with disk.
+//!
+//! The walk honours `.gitignore`/`.ignore` files (even outside a git repo)
+//! and skips hidden entries, dependency and build directories, lockfiles,
+//! minified bundles, files over 1 MB and anything that isn't UTF-8 text.
+//! A file is only re-read when its size, mtime or the embedding model has
+//! changed since it was last indexed.
+
+use super::chunker;
+use super::store::{self, IndexedFile, KnowledgeFolder};
+use crate::embeddings::{self, EmbeddingConfig};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tauri::{AppHandle, Emitter};
+use tracing::warn;
+
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+const CHUNK_TOKENS: usize = 400;
+const OVERLAP_LINES: usize = 3;
+/// Prefix checked for NUL bytes to tell binary files apart
+const SNIFF_BYTES: usize = 8192;
+const SKIPPED_DIRS: &[&str] = &[
+    "node_modules",
+    "target",
+    "dist",
+    "build",
+    "out",
+    "vendor",
+    "venv",
+    "__pycache__",
+];
+const SKIPPED_SUFFIXES: &[&str] = &[
+    ".lock",
+    "-lock.json",
+    "-lock.yaml",
+    ".min.js",
+    ".min.css",
+    ".map",
+    ".svg",
+];
+
+/// Serialises indexing so a rescan and single-file updates never interleave.
+static INDEX_LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexStats {
+    pub indexed: usize,
+    pub removed: usize,
+    pub unchanged: usize,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexProgress<'a> {
+    folder_id: &'a str,
+    processed: usize,
+    total: usize,
+}
+
+struct FileStat {
+    path: PathBuf,
+    size: u64,
+    modified_at: i64,
+}
+
+pub(crate) fn path_key(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+/// Whether a file at `path`, relative to its knowledge folder, should be
+/// indexed at all. Ignore files are checked separately.
+pub(crate) fn is_indexable(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return false;
+    };
+    !SKIPPED_SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
+        && !path
+            .components()
+            .filter_map(|c| c.as_os_str().to_str())
+            .any(|c| c.starts_with('.') || SKIPPED_DIRS.contains(&c))
+}
+
+/// Whether `.gitignore`/`.ignore` files between `root` and `path` exclude
+/// it, matching what the folder walk would skip. The deepest file with an
+/// opinion wins, so nested negations behave as in git.
+pub(crate) fn is_ignored(root: &Path, path: &Path) -> bool {
+    let dirs: Vec<&Path> = path
+        .ancestors()
+        .skip(1)
+        .take_while(|dir| dir.starts_with(root))
+        .collect();
+    for dir in dirs {
+        for name in [".ignore", ".gitignore"] {
+            let file = dir.join(name);
+            if !file.is_file() {
+                continue;
+            }
+            let (matcher, _) = ignore::gitignore::Gitignore::new(&file);
+            let matched = matcher.matched_path_or_any_parents(path, false);
+            if !matched.is_none() {
+                return matched.is_ignore();
+            }
+        }
+    }
+    false
+}
+
+fn stat(path: &Path) -> Option<FileStat> {
+    let metadata = std::fs::metadata(path).ok()?;
+    if !metadata.is_file() || metadata.len() > MAX_FILE_BYTES {
+        return None;
+    }
+    let modified_at = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0);
+    Some(FileStat {
+        path: path.to_path_buf(),
+        size: metadata.len(),
+        modified_at,
+    })
+}
+
+fn walk(root: &Path) -> Vec<FileStat> {
+    ignore::WalkBuilder::new(root)
+        .hidden(true)
+        .require_git(false)
+        .filter_entry(|entry| {
+            entry.depth() == 0
+                || !entry.file_type().is_some_and(|t| t.is_dir())
+                || !entry
+                    .file_name()
+                    .to_str()
+                    .is_some_and(|name| SKIPPED_DIRS.contains(&name))
+        })
+        .build()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+        .filter(|entry| is_indexable(entry.path().strip_prefix(root).unwrap_or(entry.path())))
+        .filter_map(|entry| stat(entry.path()))
+        .collect()
+}
+
+/// Text content, or `None` for binary and non-UTF-8 files.
+fn decode_text(bytes: Vec<u8>) -> Option<String> {
+    if bytes[..bytes.len().min(SNIFF_BYTES)].contains(&0) {
+        return None;
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// Chunk, embed and store one file. Unreadable files are logged and skipped.
+async fn index_file(
+    app: &AppHandle,
+    pool: &SqlitePool,
+    config: &EmbeddingConfig,
+    folder_id: &str,
+    file: &FileStat,
+) -> Result<(), String> {
+    let bytes = match tokio::fs::read(&file.path).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            warn!("Failed to read {}: {}", file.path.display(), e);
+            return Ok(());
+        }
+    };
+
+    let chunks = match decode_text(bytes) {
+        Some(text) => chunker::chunk_text(
+            &text,
+            crate::tokens::encoding_for(&config.model),
+            CHUNK_TOKENS,
+            OVERLAP_LINES,
+        ),
+        None => Vec::new(),
+    };
+    let texts: Vec<&str> = chunks.iter().map(|c| c.content.as_str()).collect();
+    let vectors = embeddings::embed_normalized(app, config, &texts).await?;
+    let rows: Vec<_> = chunks.into_iter().zip(vectors).collect();
+
+    let record = IndexedFile {
+        path: path_key(&file.path),
+        size: file.size as i64,
+        modified_at: file.modified_at,
+        model: config.model.clone(),
+    };
+    store::replace_file(pool, folder_id, &record, &rows).await
+}
+
+fn is_current(file: &FileStat, indexed: &IndexedFile, model: &str) -> bool {
+    indexed.size == file.size as i64
+        && indexed.modified_at == file.modified_at
+        && indexed.model == model
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FileChange {
+    Indexed,
+    Removed,
+    Unchanged,
+}
+
+/// Re-index or drop a single file under `folder` after it changed on disk.
+pub(crate) async fn sync_file(
+    app: &AppHandle,
+    pool: &SqlitePool,
+    config: &EmbeddingConfig,
+    folder: &KnowledgeFolder,
+    path: &Path,
+) -> Result<FileChange, String> {
+    let _guard = INDEX_LOCK.lock().await;
+
+    let root = Path::new(&folder.path);
+    let relative = path
+        .strip_prefix(root)
+        .map_err(|_| format!("{} is outside {}", path.display(), folder.path))?;
+    let file = if is_indexable(relative) && !is_ignored(root, path) {
+        stat(path)
+    } else {
+        None
+    };
+
+    let key = path_key(path);
+    let change = match file {
+        None if store::remove_file(pool, &key).await? => FileChange::Removed,
+        None => FileChange::Unchanged,
+        Some(file) => {
+            let current = store::file(pool, &key)
+                .await?
+                .is_some_and(|indexed| is_current(&file, &indexed, &config.model));
+            if current {
+                FileChange::Unchanged
+            } else {
+                index_file(app, pool, config, &folder.id, &file).await?;
+                FileChange::Indexed
+            }
+        }
+    };
+
+    if change != FileChange::Unchanged {
+        super::invalidate_index();
+    }
+    Ok(change)
+}
+
+/// Drop every indexed file under a directory that was deleted or moved away.
+pub(crate) async fn remove_dir(pool: &SqlitePool, dir: &Path) -> Result<usize, String> {
+    let _guard = INDEX_LOCK.lock().await;
+    let prefix = format!("{}{}", path_key(dir), std::path::MAIN_SEPARATOR);
+    let removed = store::remove_under(pool, &prefix).await?;
+    if removed > 0 {
+        super::invalidate_index();
+    }
+    Ok(removed)
+}
+
+/// Bring every file under `folder` up to date, emitting
+/// `knowledge-index-progress` as files are processed. `on_progress` gets the
+/// files processed so far and the total; an error from it stops indexing.
+pub(crate) async fn index_folder(
+    app: &AppHandle,
+    pool: &SqlitePool,
+    config: &EmbeddingConfig,
+    folder: &KnowledgeFolder,
+    mut on_progress: impl FnMut(usize, usize) -> Result<(), String>,
+) -> Result<IndexStats, String> {
+    let _guard = INDEX_LOCK.lock().await;
+
+    let root = PathBuf::from(&folder.path);
+    if !root.is_dir() {
+        return Err(format!("Folder no longer exists: {}", folder.path));
+    }
+    let files = tauri::async_runtime::spawn_blocking(move || walk(&root))
+        .await
+        .map_err(|e| format!("Failed to scan folder: {}", e))?;
+
+    let known: HashMap<String, IndexedFile> = store::files(pool, &folder.id)
+        .await?
+        .into_iter()
+        .map(|f| (f.path.clone(), f))
+        .collect();
+
+    let mut stats = IndexStats::default();
+    let mut seen = HashSet::with_capacity(files.len());
+    for (i, file) in files.iter().enumerate() {
+        let key = path_key(&file.path);
+        let current = known
+            .get(&key)
+            .is_some_and(|indexed| is_current(file, indexed, &config.model));
+        seen.insert(key);
+        if current {
+            stats.unchanged += 1;
+            on_progress(i + 1, files.len())?;
+            continue;
+        }
+
+        index_file(app, pool, config, &folder.id, file).await?;
+        stats.indexed += 1;
+        let progress = IndexProgress {
+            folder_id: &folder.id,
+            processed: i + 1,
+            total: files.len(),
+        };
+        if let Err(e) = app.emit("knowledge-index-progress", progress) {
+            warn!("Failed to emit knowledge progress: {}", e);
+        }
+        on_progress(i + 1, files.len())?;
+    }
+
+    for path in known.keys().filter(|path| !seen.contains(*path)) {
+        if store::remove_file(pool, path).await? {
+            stats.removed += 1;
+        }
+    }
+    store::mark_indexed(pool, &folder.id).await?;
+
+    if stats.indexed > 0 || stats.removed > 0 {
+        super::invalidate_index();
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_generated_and_hidden_files() {
+        assert!(is_indexable(Path::new("src/main.rs")));
+        assert!(is_indexable(Path::new("docs/notes.md")));
+        assert!(!is_indexable(Path::new("Cargo.lock")));
+        assert!(!is_indexable(Path::new("web/package-lock.json")));
+        assert!(!is_indexable(Path::new("web/node_modules/react/index.js")));
+        assert!(!is_indexable(Path::new("assets/app.min.js")));
+        assert!(!is_indexable(Path::new(".env")));
+        assert!(!is_indexable(Path::new(".git/HEAD")));
+
+        assert_eq!(
+            decode_text(b"fn main() {}".to_vec()).as_deref(),
+            Some("fn main() {}")
+        );
+        assert!(decode_text(vec![0x89, b'P', b'N', b'G', 0, 0]).is_none());
+    }
+
+    #[test]
+    fn ignore_files_apply_to_single_paths() {
+        let root = std::env::temp_dir().join(format!("freely-knowledge-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("docs/drafts")).unwrap();
+        std::fs::write(root.join(".gitignore"), "*.log\ndrafts/\n").unwrap();
+        std::fs::write(root.join("docs/.gitignore"), "!keep.log\n").unwrap();
+
+        assert!(!is_ignored(&root, &root.join("docs/guide.md")));
+        assert!(is_ignored(&root, &root.join("server.log")));
+        assert!(is_ignored(&root, &root.join("docs/drafts/idea.md")));
+        assert!(!is_ignored(&root, &root.join("docs/keep.log")));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,216 @@
+//! Retrieval over folders the user adds as knowledge sources.
+//!
+//! Text and source files under each folder are split into line-ranged
+//! chunks, embedded with the model configured for semantic search and
+//! stored in the `knowledge_*` tables (migration 8). `query_knowledge`
+//! returns the closest chunks with their file path and line range so the
+//! frontend can cite them when injecting context into a prompt. Folders are
+//! watched for edits so results don't go stale (see [`watcher`]).
+
+pub(crate) mod chunker;
+pub(crate) mod indexer;
+pub(crate) mod store;
+pub(crate) mod watcher;
+
+use crate::embeddings::store::FlatIndex;
+use crate::embeddings::{self, EmbeddingConfig};
+use crate::jobs::{self, JobSpec};
+use indexer::IndexStats;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
+use store::KnowledgeFolder;
+use tauri::AppHandle;
+use tracing::warn;
+
+const DEFAULT_K: usize = 8;
+const MAX_K: usize = 50;
+
+/// Chunk vectors for the configured model, loaded on first query and
+/// dropped whenever indexing changes anything.
+static INDEX: Lazy<Mutex<Option<FlatIndex>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KnowledgeChunk {
+    pub folder_id: String,
+    pub path: String,
+    pub start_line: i64,
+    pub end_line: i64,
+    pub content: String,
+    /// Cosine similarity in [-1, 1].
+    pub score: f32,
+}
+
+pub(crate) fn invalidate_index() {
+    *INDEX.lock() = None;
+}
+
+/// Why `candidate` can't be added next to `existing`, if it overlaps one.
+fn overlap_error(candidate: &Path, existing: &[KnowledgeFolder]) -> Option<String> {
+    existing.iter().find_map(|folder| {
+        let path = Path::new(&folder.path);
+        if candidate == path {
+            Some(format!("{} is already a knowledge folder", folder.path))
+        } else if candidate.starts_with(path) {
+            Some(format!(
+                "{} is inside knowledge folder {}",
+                candidate.display(),
+                folder.path
+            ))
+        } else if path.starts_with(candidate) {
+            Some(format!(
+                "{} contains knowledge folder {}",
+                candidate.display(),
+                folder.path
+            ))
+        } else {
+            None
+        }
+    })
+}
+
+async fn search(
+    pool: &SqlitePool,
+    config: &EmbeddingConfig,
+    query: &[f32],
+    k: usize,
+) -> Result<Vec<(String, f32)>, String> {
+    let loaded = INDEX
+        .lock()
+        .as_ref()
+        .is_some_and(|index| index.model == config.model);
+    if !loaded {
+        let index = store::load_index(pool, &config.model).await?;
+        *INDEX.lock() = Some(index);
+    }
+
+    Ok(INDEX
+        .lock()
+        .as_ref()
+        .map(|index| index.search(query, k))
+        .unwrap_or_default())
+}
+
+#[tauri::command]
+pub async fn list_knowledge_folders(app: AppHandle) -> Result<Vec<KnowledgeFolder>, String> {
+    let pool = crate::db::pool(&app).await?;
+    store::list_folders(&pool).await
+}
+
+/// Register `path` as a knowledge folder and queue a job to index it if
+/// embeddings are configured.
+#[tauri::command]
+pub async fn add_knowledge_folder(app: AppHandle, path: String) -> Result<KnowledgeFolder, String> {
+    let root = PathBuf::from(path.trim())
+        .canonicalize()
+        .map_err(|e| format!("Failed to open folder: {}", e))?;
+    if !root.is_dir() {
+        return Err(format!("Not a folder: {}", root.display()));
+    }
+
+    let pool = crate::db::pool(&app).await?;
+    if let Some(error) = overlap_error(&root, &store::list_folders(&pool).await?) {
+        return Err(error);
+    }
+    let name = root
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| indexer::path_key(&root));
+    let folder = store::insert_folder(&pool, &indexer::path_key(&root), &name).await?;
+    watcher::watch(&folder.path);
+
+    if embeddings::require_config(&pool).await.is_ok() {
+        let spec = JobSpec::IndexKnowledgeFolder {
+            folder_id: folder.id.clone(),
+        };
+        if let Err(e) = jobs::enqueue(&app, spec).await {
+            warn!("Failed to queue indexing of {}: {}", folder.path, e);
+        }
+    }
+    Ok(folder)
+}
+
+#[tauri::command]
+pub async fn remove_knowledge_folder(app: AppHandle, id: String) -> Result<(), String> {
+    let pool = crate::db::pool(&app).await?;
+    let folder = store::get_folder(&pool, &id)
+        .await?
+        .ok_or_else(|| format!("Knowledge folder not found: {}", id))?;
+    watcher::unwatch(&folder.path);
+    store::delete_folder(&pool, &id).await?;
+    invalidate_index();
+    Ok(())
+}
+
+/// Re-scan a folder, embedding new and changed files and dropping deleted ones.
+#[tauri::command]
+pub async fn reindex_knowledge_folder(app: AppHandle, id: String) -> Result<IndexStats, String> {
+    let pool = crate::db::pool(&app).await?;
+    let config = embeddings::require_config(&pool).await?;
+    let folder = store::get_folder(&pool, &id)
+        .await?
+        .ok_or_else(|| format!("Knowledge folder not found: {}", id))?;
+    indexer::index_folder(&app, &pool, &config, &folder, |_, _| Ok(())).await
+}
+
+/// The `k` chunks across all knowledge folders closest to `question`.
+#[tauri::command]
+pub async fn query_knowledge(
+    app: AppHandle,
+    question: String,
+    k: Option<usize>,
+) -> Result<Vec<KnowledgeChunk>, String> {
+    if question.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let pool = crate::db::pool(&app).await?;
+    let config = embeddings::require_config(&pool).await?;
+
+    let query_vector = embeddings::embed_normalized(&app, &config, &[question.as_str()])
+        .await?
+        .pop()
+        .ok_or("No embedding returned for query")?;
+    let k = k.unwrap_or(DEFAULT_K).clamp(1, MAX_K);
+
+    let mut results = Vec::with_capacity(k);
+    for (id, score) in search(&pool, &config, &query_vector, k).await? {
+        // Gone if the file was re-indexed after the index was loaded
+        if let Some(chunk) = store::chunk(&pool, &id).await? {
+            results.push(KnowledgeChunk {
+                folder_id: chunk.folder_id,
+                path: chunk.path,
+                start_line: chunk.start_line,
+                end_line: chunk.end_line,
+                content: chunk.content,
+                score,
+            });
+        }
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_folders_are_rejected() {
+        let existing = vec![KnowledgeFolder {
+            id: "f1".into(),
+            path: "/home/me/notes".into(),
+            name: "notes".into(),
+            created_at: 0,
+            indexed_at: None,
+            file_count: 0,
+            chunk_count: 0,
+        }];
+
+        assert!(overlap_error(Path::new("/home/me/notes"), &existing).is_some());
+        assert!(overlap_error(Path::new("/home/me/notes/work"), &existing).is_some());
+        assert!(overlap_error(Path::new("/home/me"), &existing).is_some());
+        assert!(overlap_error(Path::new("/home/me/notes-old"), &existing).is_none());
+    }
+}
@@ -0,0 +1,300 @@
+//! Knowledge folders, files and chunk vectors in SQLite (migration 8).
+
+use super::chunker::Chunk;
+use crate::embeddings::store::{decode_vector, encode_vector, FlatIndex};
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+const FOLDER_COLUMNS: &str = "SELECT f.id, f.path, f.name, f.created_at, f.indexed_at,
+        (SELECT COUNT(*) FROM knowledge_files kf WHERE kf.folder_id = f.id) AS file_count,
+        (SELECT COUNT(*) FROM knowledge_chunks c
+         JOIN knowledge_files kf ON kf.id = c.file_id
+         WHERE kf.folder_id = f.id) AS chunk_count
+     FROM knowledge_folders f";
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct KnowledgeFolder {
+    pub id: String,
+    pub path: String,
+    pub name: String,
+    pub created_at: i64,
+    pub indexed_at: Option<i64>,
+    pub file_count: i64,
+    pub chunk_count: i64,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub(crate) struct IndexedFile {
+    pub path: String,
+    pub size: i64,
+    pub modified_at: i64,
+    pub model: String,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub(crate) struct StoredChunk {
+    pub folder_id: String,
+    pub path: String,
+    pub start_line: i64,
+    pub end_line: i64,
+    pub content: String,
+}
+
+pub(crate) async fn list_folders(pool: &SqlitePool) -> Result<Vec<KnowledgeFolder>, String> {
+    sqlx::query_as::<_, KnowledgeFolder>(&format!(
+        "{} ORDER BY f.name COLLATE NOCASE",
+        FOLDER_COLUMNS
+    ))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to list knowledge folders: {}", e))
+}
+
+pub(crate) async fn get_folder(
+    pool: &SqlitePool,
+    id: &str,
+) -> Result<Option<KnowledgeFolder>, String> {
+    sqlx::query_as::<_, KnowledgeFolder>(&format!("{} WHERE f.id = ?", FOLDER_COLUMNS))
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Failed to load knowledge folder: {}", e))
+}
+
+pub(crate) async fn insert_folder(
+    pool: &SqlitePool,
+    path: &str,
+    name: &str,
+) -> Result<KnowledgeFolder, String> {
+    let folder = KnowledgeFolder {
+        id: uuid::Uuid::new_v4().to_string(),
+        path: path.to_string(),
+        name: name.to_string(),
+        created_at: crate::db::now_millis(),
+        indexed_at: None,
+        file_count: 0,
+        chunk_count: 0,
+    };
+
+    sqlx::query("INSERT INTO knowledge_folders (id, path, name, created_at) VALUES (?, ?, ?, ?)")
+        .bind(&folder.id)
+        .bind(&folder.path)
+        .bind(&folder.name)
+        .bind(folder.created_at)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to add knowledge folder: {}", e))?;
+    Ok(folder)
+}
+
+/// Delete a folder with its files and chunks. Returns false if it didn't exist.
+pub(crate) async fn delete_folder(pool: &SqlitePool, id: &str) -> Result<bool, String> {
+    let result = sqlx::query("DELETE FROM knowledge_folders WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to remove knowledge folder: {}", e))?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub(crate) async fn mark_indexed(pool: &SqlitePool, id: &str) -> Result<(), String> {
+    sqlx::query("UPDATE knowledge_folders SET indexed_at = ? WHERE id = ?")
+        .bind(crate::db::now_millis())
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to update knowledge folder: {}", e))?;
+    Ok(())
+}
+
+pub(crate) async fn files(pool: &SqlitePool, folder_id: &str) -> Result<Vec<IndexedFile>, String> {
+    sqlx::query_as::<_, IndexedFile>(
+        "SELECT path, size, modified_at, model FROM knowledge_files WHERE folder_id = ?",
+    )
+    .bind(folder_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to list indexed files: {}", e))
+}
+
+pub(crate) async fn file(pool: &SqlitePool, path: &str) -> Result<Option<IndexedFile>, String> {
+    sqlx::query_as::<_, IndexedFile>(
+        "SELECT path, size, modified_at, model FROM knowledge_files WHERE path = ?",
+    )
+    .bind(path)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to load indexed file: {}", e))
+}
+
+/// Replace everything stored for `file` with `chunks`. Files with no chunks
+/// (empty or binary) are still recorded so they aren't re-read every scan.
+pub(crate) async fn replace_file(
+    pool: &SqlitePool,
+    folder_id: &str,
+    file: &IndexedFile,
+    chunks: &[(Chunk, Vec<f32>)],
+) -> Result<(), String> {
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    sqlx::query("DELETE FROM knowledge_files WHERE path = ?")
+        .bind(&file.path)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to clear indexed file: {}", e))?;
+
+    let file_id = uuid::Uuid::new_v4().to_string();
+    sqlx::query(
+        "INSERT INTO knowledge_files (id, folder_id, path, size, modified_at, model, indexed_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&file_id)
+    .bind(folder_id)
+    .bind(&file.path)
+    .bind(file.size)
+    .bind(file.modified_at)
+    .bind(&file.model)
+    .bind(crate::db::now_millis())
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to save indexed file: {}", e))?;
+
+    for (chunk, vector) in chunks {
+        sqlx::query(
+            "INSERT INTO knowledge_chunks (id, file_id, start_line, end_line, content, vector)
+             VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(&file_id)
+        .bind(chunk.start_line as i64)
+        .bind(chunk.end_line as i64)
+        .bind(&chunk.content)
+        .bind(encode_vector(vector))
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to save chunk: {}", e))?;
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit indexed file: {}", e))
+}
+
+pub(crate) async fn remove_file(pool: &SqlitePool, path: &str) -> Result<bool, String> {
+    let result = sqlx::query("DELETE FROM knowledge_files WHERE path = ?")
+        .bind(path)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to remove indexed file: {}", e))?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Delete every file whose path starts with `prefix`.
+pub(crate) async fn remove_under(pool: &SqlitePool, prefix: &str) -> Result<usize, String> {
+    let result = sqlx::query("DELETE FROM knowledge_files WHERE substr(path, 1, length(?1)) = ?1")
+        .bind(prefix)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to remove indexed files: {}", e))?;
+    Ok(result.rows_affected() as usize)
+}
+
+pub(crate) async fn load_index(pool: &SqlitePool, model: &str) -> Result<FlatIndex, String> {
+    let rows: Vec<(String, Vec<u8>)> = sqlx::query_as(
+        "SELECT c.id, c.vector FROM knowledge_chunks c
+         JOIN knowledge_files f ON f.id = c.file_id
+         WHERE f.model = ?",
+    )
+    .bind(model)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load knowledge index: {}", e))?;
+
+    let mut index = FlatIndex::new(model);
+    for (id, bytes) in rows {
+        let _ = index.upsert(&id, &decode_vector(&bytes));
+    }
+    Ok(index)
+}
+
+pub(crate) async fn chunk(pool: &SqlitePool, id: &str) -> Result<Option<StoredChunk>, String> {
+    sqlx::query_as::<_, StoredChunk>(
+        "SELECT f.folder_id, f.path, c.start_line, c.end_line, c.content
+         FROM knowledge_chunks c JOIN knowledge_files f ON f.id = c.file_id
+         WHERE c.id = ?",
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to load chunk: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_chunk(start_line: usize, end_line: usize, content: &str) -> Chunk {
+        Chunk {
+            start_line,
+            end_line,
+            content: content.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn replacing_a_file_drops_its_old_chunks() {
+        let pool = crate::db::test_pool().await;
+        let folder = insert_folder(&pool, "/notes", "notes").await.unwrap();
+        let mut file = IndexedFile {
+            path: "/notes/a.md".to_string(),
+            size: 10,
+            modified_at: 1,
+            model: "m".to_string(),
+        };
+
+        replace_file(
+            &pool,
+            &folder.id,
+            &file,
+            &[
+                (text_chunk(1, 3, "one"), vec![1.0, 0.0]),
+                (text_chunk(4, 6, "two"), vec![0.0, 1.0]),
+            ],
+        )
+        .await
+        .unwrap();
+        file.modified_at = 2;
+        replace_file(
+            &pool,
+            &folder.id,
+            &file,
+            &[(text_chunk(1, 2, "new"), vec![1.0, 0.0])],
+        )
+        .await
+        .unwrap();
+
+        let folder = get_folder(&pool, &folder.id).await.unwrap().unwrap();
+        assert_eq!((folder.file_count, folder.chunk_count), (1, 1));
+        assert_eq!(
+            super::file(&pool, "/notes/a.md")
+                .await
+                .unwrap()
+                .unwrap()
+                .modified_at,
+            2
+        );
+
+        let index = load_index(&pool, "m").await.unwrap();
+        let (id, _) = index.search(&[1.0, 0.0], 1).pop().unwrap();
+        let stored = super::chunk(&pool, &id).await.unwrap().unwrap();
+        assert_eq!((stored.content.as_str(), stored.end_line), ("new", 2));
+        assert_eq!(load_index(&pool, "other").await.unwrap().len(), 0);
+
+        assert!(delete_folder(&pool, &folder.id).await.unwrap());
+        assert!(super::file(&pool, "/notes/a.md").await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,245 @@
+//! Keeps the knowledge index in step with edits on disk.
+//!
+//! One recursive `notify` watcher covers every registered folder. Changed
+//! paths are collected until the folder has been quiet for a moment (or a
+//! burst has run for too long), then each file is re-indexed or dropped on
+//! its own. New or renamed directories trigger an incremental rescan of
+//! their folder, since no per-file events arrive for their contents. A
+//! `knowledge-index-updated` event is emitted for every folder that changed.
+
+use super::indexer::{self, FileChange, IndexStats};
+use super::store::{self, KnowledgeFolder};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::mpsc::{self, UnboundedReceiver};
+use tokio::time::Instant;
+use tracing::warn;
+
+/// How long a folder must be quiet before its changes are indexed.
+const QUIET_PERIOD: Duration = Duration::from_millis(1500);
+/// Upper bound on the delay during a continuous stream of changes.
+const MAX_DELAY: Duration = Duration::from_secs(10);
+
+static WATCHER: Lazy<Mutex<Option<RecommendedWatcher>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexUpdated<'a> {
+    folder_id: &'a str,
+    #[serde(flatten)]
+    stats: &'a IndexStats,
+}
+
+pub(crate) fn watch(path: &str) {
+    if let Some(watcher) = WATCHER.lock().as_mut() {
+        if let Err(e) = watcher.watch(Path::new(path), RecursiveMode::Recursive) {
+            warn!("Failed to watch knowledge folder {}: {}", path, e);
+        }
+    }
+}
+
+pub(crate) fn unwatch(path: &str) {
+    if let Some(watcher) = WATCHER.lock().as_mut() {
+        if let Err(e) = watcher.unwatch(Path::new(path)) {
+            warn!("Failed to unwatch knowledge folder {}: {}", path, e);
+        }
+    }
+}
+
+/// Wait for a change, then gather everything that follows until the stream
+/// goes quiet for `quiet_period` or `max_delay` has passed. `None` once the
+/// watcher is gone.
+async fn next_batch(
+    rx: &mut UnboundedReceiver<PathBuf>,
+    quiet_period: Duration,
+    max_delay: Duration,
+) -> Option<HashSet<PathBuf>> {
+    let first = rx.recv().await?;
+    let deadline = Instant::now() + max_delay;
+    let mut batch = HashSet::from([first]);
+
+    loop {
+        let wait = quiet_period.min(deadline.saturating_duration_since(Instant::now()));
+        match tokio::time::timeout(wait, rx.recv()).await {
+            Ok(Some(path)) => {
+                batch.insert(path);
+            }
+            Ok(None) | Err(_) => return Some(batch),
+        }
+    }
+}
+
+/// The registered folder containing `path`; folders never overlap.
+fn owning_folder<'a>(folders: &'a [KnowledgeFolder], path: &Path) -> Option<&'a KnowledgeFolder> {
+    folders.iter().find(|folder| path.starts_with(&folder.path))
+}
+
+async fn sync_folder(
+    app: &AppHandle,
+    pool: &sqlx::SqlitePool,
+    config: &crate::embeddings::EmbeddingConfig,
+    folder: &KnowledgeFolder,
+    paths: &[PathBuf],
+) -> Result<IndexStats, String> {
+    // A directory appeared (created, or renamed into place): only a walk
+    // finds the files inside it
+    if paths
+        .iter()
+        .any(|path| path != Path::new(&folder.path) && path.is_dir())
+    {
+        return indexer::index_folder(app, pool, config, folder, |_, _| Ok(())).await;
+    }
+
+    let mut stats = IndexStats::default();
+    for path in paths {
+        if !path.exists() {
+            let removed = indexer::remove_dir(pool, path).await?;
+            stats.removed += removed;
+        }
+        match indexer::sync_file(app, pool, config, folder, path).await? {
+            FileChange::Indexed => stats.indexed += 1,
+            FileChange::Removed => stats.removed += 1,
+            FileChange::Unchanged => stats.unchanged += 1,
+        }
+    }
+    Ok(stats)
+}
+
+async fn process(app: &AppHandle, batch: HashSet<PathBuf>) -> Result<(), String> {
+    let pool = crate::db::pool(app).await?;
+    // Nothing can be embedded until semantic search is configured
+    let Ok(config) = crate::embeddings::require_config(&pool).await else {
+        return Ok(());
+    };
+    let folders = store::list_folders(&pool).await?;
+
+    let mut by_folder: HashMap<&str, (&KnowledgeFolder, Vec<PathBuf>)> = HashMap::new();
+    for path in batch {
+        if let Some(folder) = owning_folder(&folders, &path) {
+            by_folder
+                .entry(folder.id.as_str())
+                .or_insert_with(|| (folder, Vec::new()))
+                .1
+                .push(path);
+        }
+    }
+
+    for (folder, paths) in by_folder.into_values() {
+        let stats = match sync_folder(app, &pool, &config, folder, &paths).await {
+            Ok(stats) => stats,
+            Err(e) => {
+                warn!("Failed to update knowledge folder {}: {}", folder.path, e);
+                continue;
+            }
+        };
+        if stats.indexed == 0 && stats.removed == 0 {
+            continue;
+        }
+        let event = IndexUpdated {
+            folder_id: &folder.id,
+            stats: &stats,
+        };
+        if let Err(e) = app.emit("knowledge-index-updated", event) {
+            warn!("Failed to emit knowledge update: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// Watch every registered folder and re-index changes as they happen.
+/// Folders added later are picked up through [`watch`].
+pub fn start_knowledge_watcher(app: AppHandle) {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let watcher = notify::recommended_watcher(move |result: notify::Result<Event>| match result {
+        Ok(event) if !matches!(event.kind, EventKind::Access(_)) => {
+            for path in event.paths {
+                let _ = tx.send(path);
+            }
+        }
+        Ok(_) => {}
+        Err(e) => warn!("Knowledge watcher error: {}", e),
+    });
+    match watcher {
+        Ok(watcher) => *WATCHER.lock() = Some(watcher),
+        Err(e) => {
+            warn!("Failed to start knowledge watcher: {}", e);
+            return;
+        }
+    }
+
+    tauri::async_runtime::spawn(async move {
+        match crate::db::pool(&app).await {
+            Ok(pool) => match store::list_folders(&pool).await {
+                Ok(folders) => folders.iter().for_each(|folder| watch(&folder.path)),
+                Err(e) => warn!("Failed to load knowledge folders: {}", e),
+            },
+            Err(e) => warn!("Failed to open database for knowledge watcher: {}", e),
+        }
+
+        while let Some(batch) = next_batch(&mut rx, QUIET_PERIOD, MAX_DELAY).await {
+            if let Err(e) = process(&app, batch).await {
+                warn!("Failed to process knowledge changes: {}", e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn bursts_are_collected_into_one_batch() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        for name in ["a.md", "b.md", "a.md"] {
+            tx.send(PathBuf::from(name)).unwrap();
+        }
+        let sender = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            tx.send(PathBuf::from("c.md")).unwrap();
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            tx.send(PathBuf::from("later.md")).unwrap();
+        });
+
+        let quiet = Duration::from_millis(80);
+        let batch = next_batch(&mut rx, quiet, Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(batch.len(), 3);
+        assert!(batch.contains(Path::new("c.md")));
+
+        let batch = next_batch(&mut rx, quiet, Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(batch, HashSet::from([PathBuf::from("later.md")]));
+        sender.await.unwrap();
+        assert!(next_batch(&mut rx, quiet, quiet).await.is_none());
+    }
+
+    #[test]
+    fn changes_map_to_their_folder() {
+        let folder = |id: &str, path: &str| KnowledgeFolder {
+            id: id.into(),
+            path: path.into(),
+            name: id.into(),
+            created_at: 0,
+            indexed_at: None,
+            file_count: 0,
+            chunk_count: 0,
+        };
+        let folders = [
+            folder("notes", "/home/me/notes"),
+            folder("work", "/home/me/work"),
+        ];
+
+        let owner = owning_folder(&folders, Path::new("/home/me/work/todo.md")).unwrap();
+        assert_eq!(owner.id, "work");
+        assert!(owning_folder(&folders, Path::new("/home/me/notes-old/a.md")).is_none());
+    }
+}
@@ -1,11 +1,61 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
+mod agent_audit;
+mod agent_permissions;
 mod agents;
 mod api;
+mod api_server;
+mod attachments;
+mod audio;
+mod automations;
+mod bridge;
+mod claude_agent;
 mod claude_config;
 mod capture;
+mod cli;
+mod clipboard;
+mod context;
 mod db;
+mod deeplink;
+mod diagnostics;
+mod embeddings;
+mod export;
+mod git;
+mod insights;
+mod instance;
+mod integrations;
+mod jobs;
+mod knowledge;
+mod lock;
+mod logging;
+mod mcp;
+mod meeting;
+mod models;
+mod net;
+mod notify;
+mod ocr;
+mod prompt_template;
+mod providers;
+mod plugins;
+mod pty;
+mod redaction;
+mod retention;
+mod runner;
+mod scheduler;
+mod screenshot;
+mod secrets;
+mod settings;
 mod shortcuts;
+mod stt;
+mod sync;
+mod tokens;
+mod tray;
+mod tts;
+mod updater;
+mod usage;
 mod window;
+mod window_modes;
+mod window_state;
+mod workspace;
 use std::sync::{Arc, Mutex};
 use parking_lot::Mutex as PLMutex;
 use tauri::{AppHandle, Manager};
@@ -39,21 +89,39 @@ fn get_app_version() -> String {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    if let Some(code) = bridge::native_messaging::run_from_args() {
+        std::process::exit(code);
+    }
+    if let Some(code) = cli::run_from_args() {
+        std::process::exit(code);
+    }
+
     // Get PostHog API key
     let posthog_api_key = option_env!("POSTHOG_API_KEY").unwrap_or("").to_string();
-    #[allow(unused_mut)]
-    let mut builder = tauri::Builder::default()
-        .plugin(
-            tauri_plugin_sql::Builder::default()
-                .add_migrations("sqlite:freely.db", db::migrations())
-                .build(),
-        )
+    let mut builder = tauri::Builder::default();
+    // Registered first so a second launch exits before touching the database
+    #[cfg(desktop)]
+    {
+        builder = builder.plugin(tauri_plugin_single_instance::init(
+            instance::on_second_instance,
+        ));
+    }
+    builder = builder
+        // The database itself is opened and migrated by `db::open_database`
+        .plugin(tauri_plugin_sql::Builder::default().build())
+        .manage(db::OpenDatabase::default())
         .manage(AudioState::default())
         .manage(CaptureState::default())
         .manage(WhisperState {
             engine: PLMutex::new(None),
         })
         .manage(agents::AgentProcessRegistry::default())
+        .manage(claude_agent::ClaudeAgentManager::default())
+        .manage(audio::capture::MicCaptureState::default())
+        .manage(audio::loopback::LoopbackState::default())
+        .manage(audio::dsp::MixState::default())
+        .manage(stt::vad::VadState::default())
+        .manage(tray::TrayState::default())
         .manage(shortcuts::WindowVisibility {
             is_hidden: Mutex::new(false),
         })
@@ -64,6 +132,9 @@ pub fn run() {
         .plugin(tauri_plugin_http::init())
         .plugin(tauri_plugin_keychain::init())
         .plugin(tauri_plugin_shell::init()) // Add shell plugin
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_deep_link::init())
         .plugin(posthog_init(PostHogConfig {
             api_key: posthog_api_key,
             options: Some(PostHogOptions {
@@ -82,58 +153,344 @@ pub fn run() {
     {
         builder = builder.plugin(tauri_nspanel::init());
     }
+    let handler = tauri::generate_handler![
+        get_app_version,
+        window::set_window_height,
+        window::open_dashboard,
+        window::toggle_dashboard,
+        window::move_window,
+        window_state::reset_window_state,
+        window_modes::get_window_modes,
+        window_modes::set_overlay_mode,
+        window_modes::set_click_through,
+        window_modes::get_content_protection_support,
+        window_modes::set_content_protected,
+        capture::capture_to_base64,
+        capture::start_screen_capture,
+        capture::capture_selected_area,
+        capture::close_overlay_window,
+        screenshot::capture_screen,
+        screenshot::capture_window,
+        screenshot::capture_region,
+        screenshot::list_capture_windows,
+        ocr::extract_text_from_image,
+        shortcuts::check_shortcuts_registered,
+        shortcuts::get_registered_shortcuts,
+        shortcuts::update_shortcuts,
+        shortcuts::get_shortcuts,
+        shortcuts::set_shortcut,
+        shortcuts::validate_shortcut_key,
+        shortcuts::set_app_icon_visibility,
+        shortcuts::set_always_on_top,
+        shortcuts::exit_app,
+        tray::set_recording_indicator,
+        api::transcribe_audio,
+        api::chat_stream_response,
+        api::fetch_models,
+        api::check_license_status,
+        speaker::start_system_audio_capture,
+        speaker::stop_system_audio_capture,
+        speaker::manual_stop_continuous,
+        speaker::check_system_audio_access,
+        speaker::request_system_audio_access,
+        speaker::get_vad_config,
+        speaker::update_vad_config,
+        speaker::get_capture_status,
+        speaker::get_audio_sample_rate,
+        speaker::get_input_devices,
+        speaker::get_output_devices,
+        agents::check_tool_installed,
+        agents::check_claude_authenticated,
+        agents::open_terminal_for_login,
+        agents::load_env_file,
+        agents::run_claude,
+        agents::run_codex,
+        agents::run_gemini,
+        agents::kill_agent_process,
+        claude_agent::start_claude_agent,
+        claude_agent::cancel_claude_agent,
+        claude_agent::list_claude_agents,
+        claude_config::get_claude_md,
+        claude_config::update_claude_md,
+        claude_config::get_claude_settings,
+        claude_config::update_claude_settings,
+        claude_config::list_skills,
+        claude_config::create_skill,
+        claude_config::update_skill,
+        claude_config::delete_skill,
+        db::search::search_messages,
+        db::chat::create_conversation,
+        db::chat::append_message,
+        db::chat::list_conversations,
+        db::chat::get_conversation,
+        db::chat::delete_conversation,
+        db::chat::list_deleted_conversations,
+        db::chat::pin_conversation,
+        db::chat::archive_conversation,
+        db::chat::restore_conversation,
+        db::chat::purge_deleted,
+        db::message_revisions::edit_message,
+        db::message_revisions::regenerate_message,
+        db::message_revisions::list_message_revisions,
+        db::message_revisions::restore_message_revision,
+        jobs::enqueue_job,
+        jobs::list_jobs,
+        jobs::cancel_job,
+        models::list_local_models,
+        models::download_local_model,
+        models::delete_local_model,
+        providers::llama_local::get_inference_capabilities,
+        providers::probe::probe_provider,
+        providers::profiles::list_provider_profiles,
+        providers::profiles::create_provider_profile,
+        providers::profiles::update_provider_profile,
+        providers::profiles::delete_provider_profile,
+        net::client::get_proxy_config,
+        net::client::set_proxy_config,
+        net::client::get_tls_config,
+        net::client::set_custom_ca,
+        net::client::set_pinned_certificate,
+        net::connectivity::get_connectivity,
+        net::connectivity::check_connectivity,
+        db::agent_sessions::get_agent_session,
+        db::agent_sessions::forget_agent_session,
+        agent_permissions::list_agent_permission_requests,
+        agent_permissions::respond_agent_permission,
+        db::agent_audit::get_agent_audit,
+        workspace::diff::get_pending_diffs,
+        workspace::diff::revert_file,
+        workspace::fs::list_dir,
+        workspace::fs::read_file,
+        workspace::fs::stat,
+        git::git_status,
+        git::git_diff,
+        git::git_commit,
+        git::git_branch_list,
+        runner::run_snippet,
+        runner::cancel_snippet,
+        pty::pty_spawn,
+        pty::pty_write,
+        pty::pty_resize,
+        pty::pty_kill,
+        scheduler::list_scheduled_tasks,
+        scheduler::create_scheduled_task,
+        scheduler::update_scheduled_task,
+        scheduler::delete_scheduled_task,
+        scheduler::run_scheduled_task_now,
+        notify::run_notification_action,
+        deeplink::take_pending_deep_links,
+        stt::file::transcribe_file,
+        stt::file::transcribe_files,
+        meeting::start_meeting_session,
+        meeting::stop_meeting_session,
+        meeting::get_active_meeting,
+        meeting::get_meeting,
+        meeting::list_meetings,
+        tts::speak,
+        tts::stop_speaking,
+        tts::list_system_voices,
+        tts::get_tts_config,
+        tts::set_tts_config,
+        stt::translate::get_translation_config,
+        stt::translate::set_translation_config,
+        stt::translate::translate_transcript,
+        redaction::get_redaction_settings,
+        redaction::set_redaction_settings,
+        redaction::redact_text,
+        db::transcripts::save_transcript,
+        db::transcripts::list_transcripts,
+        db::transcripts::rename_transcript_speaker,
+        stt::diarization::start_diarization,
+        stt::diarization::stop_diarization,
+        db::projects::add_project,
+        db::projects::list_projects,
+        db::projects::get_active_project,
+        db::projects::set_active_project,
+        db::projects::remove_project,
+        db::backup::backup_database,
+        db::backup::restore_database,
+        db::backup::list_backups,
+        db::backup::restore_backup,
+        db::schema::migration_status,
+        db::cipher::get_database_encryption,
+        db::cipher::encrypt_existing_database,
+        logging::get_recent_logs,
+        logging::get_log_level,
+        logging::set_log_level,
+        diagnostics::list_crash_reports,
+        diagnostics::export_crash_report,
+        diagnostics::dismiss_crash_reports,
+        diagnostics::delete_crash_report,
+        updater::check_for_update,
+        updater::download_update,
+        updater::install_on_quit,
+        settings::get_app_setting,
+        settings::get_app_settings,
+        settings::set_app_setting,
+        settings::reset_app_setting,
+        export::export_conversation,
+        export::export_conversation_html,
+        export::import_conversations,
+        secrets::set_api_key,
+        secrets::get_api_key,
+        secrets::delete_api_key,
+        providers::stream_completion,
+        providers::abort_completion,
+        providers::compare::compare_completions,
+        providers::compare::list_comparisons,
+        providers::compare::get_comparison,
+        providers::compare::pick_comparison_winner,
+        providers::compare::delete_comparison,
+        providers::cache::get_response_cache_config,
+        providers::cache::set_response_cache_config,
+        providers::cache::get_response_cache_stats,
+        providers::cache::clear_response_cache,
+        providers::ollama::ollama_status,
+        providers::ollama::list_ollama_models,
+        providers::ollama::pull_ollama_model,
+        providers::ollama::delete_ollama_model,
+        tokens::count_tokens,
+        tokens::trim_messages_to_budget,
+        context::compactor::get_compaction_config,
+        context::compactor::set_compaction_config,
+        context::compactor::compact_conversation,
+        context::compactor::get_compacted_context,
+        embeddings::get_embedding_config,
+        embeddings::set_embedding_config,
+        embeddings::index_chat_history,
+        embeddings::semantic_search,
+        knowledge::list_knowledge_folders,
+        knowledge::add_knowledge_folder,
+        knowledge::remove_knowledge_folder,
+        knowledge::reindex_knowledge_folder,
+        knowledge::query_knowledge,
+        mcp::list_mcp_servers,
+        mcp::add_mcp_server,
+        mcp::remove_mcp_server,
+        mcp::test_mcp_server,
+        audio::capture::list_microphones,
+        audio::devices::list_audio_devices,
+        audio::capture::start_microphone_capture,
+        audio::capture::stop_microphone_capture,
+        audio::capture::get_microphone_capture_status,
+        audio::denoise::get_noise_suppression,
+        audio::denoise::set_noise_suppression,
+        clipboard::get_clipboard_history,
+        clipboard::clear_clipboard_history,
+        attachments::add_attachment,
+        attachments::get_attachment,
+        attachments::list_message_attachments,
+        attachments::link_attachments,
+        attachments::collect_attachment_garbage,
+        prompt_template::render_system_prompt,
+        db::system_prompts::list_prompt_versions,
+        db::system_prompts::restore_prompt_version,
+        usage::get_usage_summary,
+        usage::get_budget_status,
+        insights::get_insights,
+        retention::get_retention_policy,
+        retention::set_retention_policy,
+        retention::preview_retention_cleanup,
+        sync::get_sync_config,
+        sync::configure_sync,
+        sync::configure_sync_remote,
+        sync::get_sync_status,
+        sync::sync_now,
+        sync::list_sync_conflicts,
+        sync::clear_sync_conflicts,
+        plugins::list_plugins,
+        plugins::install_plugin,
+        plugins::uninstall_plugin,
+        plugins::set_plugin_enabled,
+        plugins::reload_plugin,
+        plugins::call_plugin_tool,
+        plugins::run_plugin_command,
+        automations::list_webhooks,
+        automations::save_webhook,
+        automations::delete_webhook,
+        automations::reveal_webhook_secret,
+        automations::test_webhook,
+        automations::list_webhook_deliveries,
+        api_server::get_api_server_status,
+        api_server::set_api_server_config,
+        api_server::get_api_token,
+        api_server::regenerate_api_token,
+        bridge::respond_browser_request,
+        bridge::native_messaging::install_browser_bridge,
+        integrations::obsidian::get_obsidian_config,
+        integrations::obsidian::configure_obsidian,
+        integrations::obsidian::mirror_obsidian_now,
+        integrations::notion::set_notion_token,
+        integrations::notion::list_notion_databases,
+        integrations::notion::push_conversation_to_notion,
+        integrations::notion::push_meeting_to_notion,
+        integrations::calendar::get_calendar_config,
+        integrations::calendar::set_calendar_config,
+        integrations::calendar::refresh_calendars,
+        integrations::calendar::get_calendar_errors,
+        integrations::calendar::get_upcoming_events,
+        integrations::calendar::start_calendar_meeting,
+        integrations::issues::get_issue_trackers,
+        integrations::issues::set_github_token,
+        integrations::issues::connect_jira,
+        integrations::issues::create_issue_from_message,
+        db::tags::tag_conversation,
+        db::tags::untag_conversation,
+        db::tags::list_conversations_by_tag,
+        db::tags::list_tags,
+        db::tags::get_conversation_tags,
+        db::tags::delete_tag,
+        db::folders::create_folder,
+        db::folders::rename_folder,
+        db::folders::move_folder,
+        db::folders::delete_folder,
+        db::folders::list_folders,
+        db::folders::move_conversation_to_folder,
+        db::folders::list_conversations_in_folder,
+        audio::loopback::start_system_audio_stream,
+        audio::loopback::stop_system_audio_stream,
+        audio::loopback::get_system_audio_stream_status,
+        audio::dsp::start_audio_mix,
+        audio::dsp::stop_audio_mix,
+        audio::dsp::get_audio_mix_status,
+        speaker::init_local_whisper,
+        speaker::transcribe_local,
+        speaker::get_local_whisper_status,
+        stt::local::list_whisper_models,
+        stt::local::download_whisper_model,
+        stt::local::delete_whisper_model,
+        stt::local::load_whisper_model,
+        stt::local::transcribe_local_buffered,
+        stt::vad::start_vad,
+        stt::vad::stop_vad,
+        stt::streaming::start_streaming_stt,
+        stt::streaming::stop_streaming_stt,
+        audio::recorder::start_session_recording,
+        audio::recorder::stop_session_recording,
+        audio::recorder::list_recordings,
+        audio::recorder::delete_recording,
+        lock::get_lock_status,
+        lock::get_lock_config,
+        lock::set_lock_config,
+        lock::set_lock_passcode,
+        lock::remove_lock_passcode,
+        lock::lock_app,
+        lock::unlock_app,
+        lock::unlock_with_biometrics,
+        lock::report_activity,
+    ];
     #[allow(unused_mut)]
     let mut builder = builder
-        .invoke_handler(tauri::generate_handler![
-            get_app_version,
-            window::set_window_height,
-            window::open_dashboard,
-            window::toggle_dashboard,
-            window::move_window,
-            capture::capture_to_base64,
-            capture::start_screen_capture,
-            capture::capture_selected_area,
-            capture::close_overlay_window,
-            shortcuts::check_shortcuts_registered,
-            shortcuts::get_registered_shortcuts,
-            shortcuts::update_shortcuts,
-            shortcuts::validate_shortcut_key,
-            shortcuts::set_app_icon_visibility,
-            shortcuts::set_always_on_top,
-            shortcuts::exit_app,
-            api::transcribe_audio,
-            api::chat_stream_response,
-            api::fetch_models,
-            api::check_license_status,
-            speaker::start_system_audio_capture,
-            speaker::stop_system_audio_capture,
-            speaker::manual_stop_continuous,
-            speaker::check_system_audio_access,
-            speaker::request_system_audio_access,
-            speaker::get_vad_config,
-            speaker::update_vad_config,
-            speaker::get_capture_status,
-            speaker::get_audio_sample_rate,
-            speaker::get_input_devices,
-            speaker::get_output_devices,
-            agents::check_tool_installed,
-            agents::check_claude_authenticated,
-            agents::open_terminal_for_login,
-            agents::load_env_file,
-            agents::run_claude,
-            agents::run_codex,
-            agents::run_gemini,
-            agents::kill_agent_process,
-            claude_config::get_claude_md,
-            claude_config::update_claude_md,
-            speaker::init_local_whisper,
-            speaker::transcribe_local,
-            speaker::get_local_whisper_status,
-        ])
+        .invoke_handler(move |invoke| lock::guard_invoke(invoke, &handler))
         .setup(|app| {
-            // Migrate pluely.db → freely.db for existing users before the SQL plugin
-            // opens the database for the first time.
-            migrate_pluely_db(app.handle());
+            logging::init_logging(app.handle());
+            diagnostics::install_panic_hook(app.handle());
+
+            // Migrate pluely.db → freely.db for existing users before the
+            // database is opened for the first time.
+            db::legacy::migrate_legacy_db(app.handle());
+            db::open_database(app.handle())?;
+            lock::start_lock_watcher(app.handle().clone());
 
             // Setup main window positioning
             window::setup_main_window(app).expect("Failed to setup main window");
@@ -141,11 +498,25 @@ pub fn run() {
             init(app.app_handle());
             let app_handle = app.handle();
             if app_handle.get_webview_window("dashboard").is_none() {
-                if let Err(e) = window::create_dashboard_window(app_handle) {
-                    eprintln!("Failed to pre-create dashboard window on startup: {}", e);
+                match window::create_dashboard_window(app_handle) {
+                    Ok(dashboard) => window_state::manage(app_handle, &dashboard, true),
+                    Err(e) => {
+                        tracing::warn!("Failed to pre-create dashboard window on startup: {}", e)
+                    }
                 }
             }
 
+            window_modes::restore_content_protection(app.handle().clone());
+            net::client::restore_settings(app.handle().clone());
+            stt::push_to_talk::start_push_to_talk(app.handle());
+
+            // Notify the frontend when audio devices are plugged in or removed
+            audio::devices::start_device_watcher(app.handle().clone());
+
+            if let Err(e) = tray::create_tray(app.handle()) {
+                tracing::warn!("Failed to create tray icon: {}", e);
+            }
+
             #[cfg(desktop)]
             {
                 use tauri_plugin_autostart::MacosLauncher;
@@ -155,7 +526,7 @@ pub fn run() {
                     MacosLauncher::LaunchAgent,
                     Some(vec![]),
                 )) {
-                    eprintln!("Failed to initialize autostart plugin: {}", e);
+                    tracing::warn!("Failed to initialize autostart plugin: {}", e);
                 }
             }
 
@@ -171,7 +542,7 @@ pub fn run() {
                                 let registered = match state.shortcuts.lock() {
                                     Ok(guard) => guard,
                                     Err(poisoned) => {
-                                        eprintln!("Mutex poisoned in handler, recovering...");
+                                        tracing::warn!("Mutex poisoned in handler, recovering...");
                                         poisoned.into_inner()
                                     }
                                 };
@@ -194,7 +565,7 @@ pub fn run() {
                                         {
                                             shortcuts::start_move_window(app, direction);
                                         } else {
-                                            eprintln!("Shortcut triggered: {}", action_id);
+                                            tracing::debug!("Shortcut triggered: {}", action_id);
                                             shortcuts::handle_shortcut_action(app, &action_id);
                                         }
                                     }
@@ -203,6 +574,8 @@ pub fn run() {
                                             action_id.strip_prefix("move_window_")
                                         {
                                             shortcuts::stop_move_window(app, direction);
+                                        } else {
+                                            shortcuts::handle_shortcut_release(app, &action_id);
                                         }
                                     }
                                 }
@@ -212,8 +585,27 @@ pub fn run() {
                 )
                 .expect("Failed to initialize global shortcut plugin");
             if let Err(e) = shortcuts::setup_global_shortcuts(app.handle()) {
-                eprintln!("Failed to setup global shortcuts: {}", e);
+                tracing::warn!("Failed to setup global shortcuts: {}", e);
             }
+            shortcuts::restore_persisted_shortcuts(app.handle().clone());
+            context::compactor::start_compactor(app.handle().clone());
+            knowledge::watcher::start_knowledge_watcher(app.handle().clone());
+            db::backup::start_backup_scheduler(app.handle().clone());
+            jobs::start_job_workers(app.handle().clone());
+            scheduler::start_scheduler(app.handle().clone());
+            net::connectivity::start_connectivity_monitor(app.handle().clone());
+            db::chat::start_trash_purge(app.handle().clone());
+            retention::start_retention_scheduler(app.handle().clone());
+            sync::start_sync_scheduler(app.handle().clone());
+            plugins::start_plugins(app.handle().clone());
+            api_server::start_api_server(app.handle().clone());
+            bridge::start_bridge(app.handle().clone());
+            integrations::obsidian::start_obsidian_mirror(app.handle().clone());
+            integrations::calendar::start_calendar(app.handle().clone());
+            updater::start_update_checker(app.handle().clone());
+            clipboard::start_clipboard_monitor(app.handle().clone());
+            deeplink::setup_deep_links(app.handle());
+            meeting::recover_meetings(app.handle().clone());
             Ok(())
         });
 
@@ -224,58 +616,20 @@ pub fn run() {
     }
 
     builder
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
-}
-
-/// One-time migration: rename `pluely.db` to `freely.db` so existing users
-/// retain their conversation history after the rename.
-///
-/// The tauri-plugin-sql stores SQLite files in `app_local_data_dir()`.
-/// We rename before the plugin opens the file (which happens lazily on first JS access).
-/// If `freely.db` already exists we leave both files untouched to avoid overwriting data.
-fn migrate_pluely_db(app: &AppHandle) {
-    let data_dir = match app.path().app_local_data_dir() {
-        Ok(dir) => dir,
-        Err(e) => {
-            eprintln!("[migrate_pluely_db] Could not resolve app_local_data_dir: {}", e);
-            return;
-        }
-    };
-
-    let old_path = data_dir.join("pluely.db");
-    let new_path = data_dir.join("freely.db");
-
-    if old_path.exists() && !new_path.exists() {
-        match std::fs::rename(&old_path, &new_path) {
-            Ok(()) => println!(
-                "[migrate_pluely_db] Renamed {:?} → {:?}",
-                old_path, new_path
-            ),
-            Err(e) => eprintln!(
-                "[migrate_pluely_db] Failed to rename {:?} → {:?}: {}",
-                old_path, new_path, e
-            ),
-        }
-
-        // Also rename SQLite WAL sidecar files if they exist.
-        for suffix in &["-wal", "-shm"] {
-            let old_sidecar = data_dir.join(format!("pluely.db{}", suffix));
-            let new_sidecar = data_dir.join(format!("freely.db{}", suffix));
-            if old_sidecar.exists() {
-                match std::fs::rename(&old_sidecar, &new_sidecar) {
-                    Ok(()) => println!(
-                        "[migrate_pluely_db] Renamed {:?} → {:?}",
-                        old_sidecar, new_sidecar
-                    ),
-                    Err(e) => eprintln!(
-                        "[migrate_pluely_db] Failed to rename {:?} → {:?}: {}",
-                        old_sidecar, new_sidecar, e
-                    ),
-                }
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app_handle, event| {
+            if let tauri::RunEvent::Exit = event {
+                // Don't leave CLI agents running once the window is gone
+                claude_agent::shutdown_all(app_handle);
+                runner::shutdown_all();
+                pty::shutdown_all();
+                plugins::shutdown_all();
+                bridge::shutdown_all();
+                agents::kill_all_agent_processes(&app_handle.state::<agents::AgentProcessRegistry>());
+                updater::install_pending();
             }
-        }
-    }
+        });
 }
 
 #[cfg(target_os = "macos")]
@@ -297,10 +651,10 @@ fn init(app_handle: &AppHandle) {
             "window_did_become_key" => {
                 let app_name = handle.package_info().name.to_owned();
 
-                println!("[info]: {:?} panel becomes key window!", app_name);
+                tracing::debug!("{:?} panel becomes key window!", app_name);
             }
             "window_did_resign_key" => {
-                println!("[info]: panel resigned from key window!");
+                tracing::debug!("panel resigned from key window!");
             }
             _ => (),
         }
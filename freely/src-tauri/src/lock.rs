@@ -0,0 +1,464 @@
+//! App lock: hides everything behind a passcode after a period of idleness.
+//!
+//! The passcode is stored as an Argon2 hash in the secrets store, never in
+//! the database. When the lock is enabled the app starts locked, and locks
+//! again once no `report_activity` has arrived for `idleMinutes`. Touch ID
+//! (macOS) or Windows Hello can unlock instead of the passcode where the
+//! machine supports it.
+//!
+//! While locked, every command not in [`ALLOWED_WHILE_LOCKED`] is rejected
+//! before it runs (see [`guard_invoke`]), and the database is withdrawn from
+//! the SQL plugin so the webview can't query it directly. Rust-side work
+//! such as a running meeting keeps its pool and carries on.
+
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tauri::ipc::Invoke;
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+use tracing::warn;
+
+/// Settings key for [`LockConfig`].
+pub(crate) const CONFIG_SETTING_KEY: &str = "app_lock";
+/// Name of the passcode hash among the app's internal secrets.
+const PASSCODE_SECRET: &str = "app-lock-passcode";
+const MIN_PASSCODE_LEN: usize = 4;
+const MAX_IDLE_MINUTES: u32 = 24 * 60;
+const CHECK_INTERVAL: Duration = Duration::from_secs(15);
+/// Wrong passcodes allowed before unlocking is paused for [`BACKOFF`].
+const MAX_ATTEMPTS: u32 = 5;
+const BACKOFF: Duration = Duration::from_secs(30);
+const BIOMETRIC_REASON: &str = "unlock Freely";
+
+/// Commands the lock screen itself needs, plus window housekeeping that
+/// reveals nothing.
+const ALLOWED_WHILE_LOCKED: &[&str] = &[
+    "get_app_version",
+    "get_lock_status",
+    "unlock_app",
+    "unlock_with_biometrics",
+    "report_activity",
+    "set_window_height",
+    "move_window",
+    "open_dashboard",
+    "toggle_dashboard",
+    "get_window_modes",
+    "exit_app",
+];
+
+static LOCKED: AtomicBool = AtomicBool::new(false);
+static LAST_ACTIVITY: Lazy<Mutex<Instant>> = Lazy::new(|| Mutex::new(Instant::now()));
+static FAILURES: Lazy<Mutex<Failures>> = Lazy::new(Default::default);
+
+#[derive(Default)]
+struct Failures {
+    count: u32,
+    blocked_until: Option<Instant>,
+}
+
+/// Stored under the `app_lock` setting.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LockConfig {
+    pub enabled: bool,
+    pub idle_minutes: u32,
+    /// Offer Touch ID / Windows Hello on the lock screen.
+    pub biometric: bool,
+}
+
+impl Default for LockConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            idle_minutes: 5,
+            biometric: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LockStatus {
+    pub locked: bool,
+    pub enabled: bool,
+    pub has_passcode: bool,
+    /// Biometric unlock is enabled and the machine supports it.
+    pub biometric_available: bool,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LockChanged {
+    locked: bool,
+}
+
+pub(crate) fn is_locked() -> bool {
+    LOCKED.load(Ordering::SeqCst)
+}
+
+fn validate_config(config: &LockConfig) -> Result<(), String> {
+    if config.idle_minutes == 0 || config.idle_minutes > MAX_IDLE_MINUTES {
+        return Err(format!(
+            "Idle time must be between 1 and {} minutes",
+            MAX_IDLE_MINUTES
+        ));
+    }
+    Ok(())
+}
+
+fn validate_passcode(passcode: &str) -> Result<(), String> {
+    if passcode.chars().count() < MIN_PASSCODE_LEN {
+        return Err(format!(
+            "Passcode must be at least {} characters",
+            MIN_PASSCODE_LEN
+        ));
+    }
+    Ok(())
+}
+
+fn hash_passcode(passcode: &str) -> Result<String, String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(passcode.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| format!("Failed to hash passcode: {}", e))
+}
+
+fn passcode_matches(passcode: &str, hash: &str) -> bool {
+    match PasswordHash::new(hash) {
+        Ok(parsed) => Argon2::default()
+            .verify_password(passcode.as_bytes(), &parsed)
+            .is_ok(),
+        Err(e) => {
+            warn!("Stored passcode hash is invalid: {}", e);
+            false
+        }
+    }
+}
+
+/// Count a wrong passcode, pausing attempts after [`MAX_ATTEMPTS`].
+fn record_failure(now: Instant) {
+    let mut failures = FAILURES.lock();
+    failures.count += 1;
+    if failures.count >= MAX_ATTEMPTS {
+        failures.count = 0;
+        failures.blocked_until = Some(now + BACKOFF);
+    }
+}
+
+fn check_not_blocked(now: Instant) -> Result<(), String> {
+    match FAILURES.lock().blocked_until {
+        Some(until) if until > now => Err(format!(
+            "Too many attempts, try again in {} seconds",
+            (until - now).as_secs().max(1)
+        )),
+        _ => Ok(()),
+    }
+}
+
+async fn load_config(app: &AppHandle) -> Result<LockConfig, String> {
+    let pool = crate::db::pool(app).await?;
+    Ok(crate::db::settings::get(&pool, CONFIG_SETTING_KEY)
+        .await?
+        .unwrap_or_default())
+}
+
+async fn stored_hash(app: &AppHandle) -> Result<Option<String>, String> {
+    crate::secrets::load_secret(app, PASSCODE_SECRET).await
+}
+
+fn set_locked(app: &AppHandle, locked: bool) {
+    if LOCKED.swap(locked, Ordering::SeqCst) == locked {
+        return;
+    }
+    *LAST_ACTIVITY.lock() = Instant::now();
+    let app_handle = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = crate::db::sync_frontend_access(&app_handle).await {
+            warn!("Failed to update database access: {}", e);
+        }
+    });
+    if let Err(e) = app.emit("app-lock-changed", LockChanged { locked }) {
+        warn!("Failed to emit lock change: {}", e);
+    }
+}
+
+/// Reject commands outside [`ALLOWED_WHILE_LOCKED`] while the app is
+/// locked, and hand everything else to `handler`.
+pub fn guard_invoke<R: Runtime>(invoke: Invoke<R>, handler: &impl Fn(Invoke<R>) -> bool) -> bool {
+    if is_locked() && !ALLOWED_WHILE_LOCKED.contains(&invoke.message.command()) {
+        invoke.resolver.reject("Freely is locked");
+        return true;
+    }
+    handler(invoke)
+}
+
+// ============================================================================
+// Biometrics
+// ============================================================================
+
+#[cfg(target_os = "macos")]
+fn biometric_supported() -> bool {
+    use cidre::la;
+    la::Context::new()
+        .can_evaluate_policy(la::Policy::DeviceOwnerAuthWithBiometrics)
+        .is_ok()
+}
+
+#[cfg(target_os = "macos")]
+fn verify_biometric() -> Result<bool, String> {
+    use cidre::{la, ns};
+    let context = la::Context::new();
+    let reason = ns::String::with_str(BIOMETRIC_REASON);
+    match tauri::async_runtime::block_on(
+        context.evaluate_policy(la::Policy::DeviceOwnerAuthWithBiometrics, &reason),
+    ) {
+        Ok(()) => Ok(true),
+        Err(e) => {
+            tracing::debug!("Touch ID failed: {}", e.localized_description());
+            Ok(false)
+        }
+    }
+}
+
+#[cfg(windows)]
+fn biometric_supported() -> bool {
+    use windows::Security::Credentials::UI::{
+        UserConsentVerifier, UserConsentVerifierAvailability,
+    };
+    UserConsentVerifier::CheckAvailabilityAsync()
+        .and_then(|op| op.get())
+        .is_ok_and(|availability| availability == UserConsentVerifierAvailability::Available)
+}
+
+#[cfg(windows)]
+fn verify_biometric() -> Result<bool, String> {
+    use windows::core::HSTRING;
+    use windows::Security::Credentials::UI::{UserConsentVerificationResult, UserConsentVerifier};
+    let result = UserConsentVerifier::RequestVerificationAsync(&HSTRING::from(BIOMETRIC_REASON))
+        .and_then(|op| op.get())
+        .map_err(|e| format!("Windows Hello failed: {}", e))?;
+    Ok(result == UserConsentVerificationResult::Verified)
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn biometric_supported() -> bool {
+    false
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn verify_biometric() -> Result<bool, String> {
+    Err("Biometric unlock isn't available on this platform".to_string())
+}
+
+// ============================================================================
+// Idle watcher
+// ============================================================================
+
+async fn check_idle(app: &AppHandle) -> Result<(), String> {
+    if is_locked() {
+        return Ok(());
+    }
+    let config = load_config(app).await?;
+    let idle = Duration::from_secs(u64::from(config.idle_minutes) * 60);
+    if config.enabled && LAST_ACTIVITY.lock().elapsed() >= idle {
+        set_locked(app, true);
+    }
+    Ok(())
+}
+
+/// Lock at startup when the lock is enabled, then watch for idleness.
+/// Called once from `setup`, after the database is open.
+pub fn start_lock_watcher(app: AppHandle) {
+    let enabled = tauri::async_runtime::block_on(load_config(&app)).map(|config| config.enabled);
+    match enabled {
+        Ok(true) => set_locked(&app, true),
+        Ok(false) => {}
+        Err(e) => warn!("Failed to read lock settings: {}", e),
+    }
+
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = check_idle(&app).await {
+                warn!("Idle lock check failed: {}", e);
+            }
+        }
+    });
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+#[tauri::command]
+pub async fn get_lock_status(app: AppHandle) -> Result<LockStatus, String> {
+    let config = load_config(&app).await?;
+    let biometric_available = config.biometric
+        && tokio::task::spawn_blocking(biometric_supported)
+            .await
+            .unwrap_or(false);
+    Ok(LockStatus {
+        locked: is_locked(),
+        enabled: config.enabled,
+        has_passcode: stored_hash(&app).await?.is_some(),
+        biometric_available,
+    })
+}
+
+#[tauri::command]
+pub async fn get_lock_config(app: AppHandle) -> Result<LockConfig, String> {
+    load_config(&app).await
+}
+
+/// Save the lock settings. Enabling needs a passcode to be set first.
+#[tauri::command]
+pub async fn set_lock_config(app: AppHandle, config: LockConfig) -> Result<(), String> {
+    validate_config(&config)?;
+    if config.enabled && stored_hash(&app).await?.is_none() {
+        return Err("Set a passcode before enabling the lock".to_string());
+    }
+    *LAST_ACTIVITY.lock() = Instant::now();
+    crate::settings::set_setting(&app, CONFIG_SETTING_KEY, &config).await
+}
+
+/// Set or change the passcode. Changing it needs the `current` one.
+#[tauri::command]
+pub async fn set_lock_passcode(
+    app: AppHandle,
+    current: Option<String>,
+    passcode: String,
+) -> Result<(), String> {
+    validate_passcode(&passcode)?;
+    if let Some(hash) = stored_hash(&app).await? {
+        check_not_blocked(Instant::now())?;
+        if !current.is_some_and(|current| passcode_matches(&current, &hash)) {
+            record_failure(Instant::now());
+            return Err("Current passcode is incorrect".to_string());
+        }
+    }
+    let hash = tokio::task::spawn_blocking(move || hash_passcode(&passcode))
+        .await
+        .map_err(|e| format!("Passcode task failed: {}", e))??;
+    crate::secrets::set_secret(&app, PASSCODE_SECRET, hash).await
+}
+
+/// Forget the passcode and turn the lock off.
+#[tauri::command]
+pub async fn remove_lock_passcode(app: AppHandle, current: String) -> Result<(), String> {
+    let Some(hash) = stored_hash(&app).await? else {
+        return Ok(());
+    };
+    check_not_blocked(Instant::now())?;
+    if !passcode_matches(&current, &hash) {
+        record_failure(Instant::now());
+        return Err("Current passcode is incorrect".to_string());
+    }
+    let config = LockConfig {
+        enabled: false,
+        ..load_config(&app).await?
+    };
+    crate::settings::set_setting(&app, CONFIG_SETTING_KEY, &config).await?;
+    crate::secrets::delete_secret(&app, PASSCODE_SECRET).await
+}
+
+/// Lock now, e.g. from the tray or a shortcut.
+#[tauri::command]
+pub async fn lock_app(app: AppHandle) -> Result<(), String> {
+    if stored_hash(&app).await?.is_none() {
+        return Err("Set a passcode before locking".to_string());
+    }
+    set_locked(&app, true);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn unlock_app(app: AppHandle, passcode: String) -> Result<(), String> {
+    if !is_locked() {
+        return Ok(());
+    }
+    check_not_blocked(Instant::now())?;
+    let hash = stored_hash(&app).await?.ok_or("No passcode is set")?;
+    let matches = tokio::task::spawn_blocking(move || passcode_matches(&passcode, &hash))
+        .await
+        .map_err(|e| format!("Passcode task failed: {}", e))?;
+    if !matches {
+        record_failure(Instant::now());
+        return Err("Incorrect passcode".to_string());
+    }
+    *FAILURES.lock() = Failures::default();
+    set_locked(&app, false);
+    Ok(())
+}
+
+/// Unlock with Touch ID or Windows Hello, prompting the user.
+#[tauri::command]
+pub async fn unlock_with_biometrics(app: AppHandle) -> Result<(), String> {
+    if !is_locked() {
+        return Ok(());
+    }
+    if !load_config(&app).await?.biometric {
+        return Err("Biometric unlock is turned off".to_string());
+    }
+    let verified = tokio::task::spawn_blocking(verify_biometric)
+        .await
+        .map_err(|e| format!("Biometric task failed: {}", e))??;
+    if !verified {
+        return Err("Biometric verification failed".to_string());
+    }
+    set_locked(&app, false);
+    Ok(())
+}
+
+/// Called by the frontend on user input to postpone the idle lock.
+#[tauri::command]
+pub fn report_activity() {
+    if !is_locked() {
+        *LAST_ACTIVITY.lock() = Instant::now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passcodes_are_hashed_and_verified() {
+        let hash = hash_passcode("2468").unwrap();
+        assert!(hash.starts_with("$argon2"));
+        assert!(!hash.contains("2468"));
+        assert!(passcode_matches("2468", &hash));
+        assert!(!passcode_matches("1357", &hash));
+        assert!(!passcode_matches("2468", "not a hash"));
+
+        assert!(validate_passcode("123").is_err());
+        assert!(validate_passcode("1234").is_ok());
+    }
+
+    #[test]
+    fn repeated_failures_pause_attempts() {
+        let now = Instant::now();
+        assert!(check_not_blocked(now).is_ok());
+        for _ in 0..MAX_ATTEMPTS {
+            record_failure(now);
+        }
+        assert!(check_not_blocked(now).is_err());
+        assert!(check_not_blocked(now + BACKOFF + Duration::from_secs(1)).is_ok());
+    }
+
+    #[test]
+    fn idle_time_is_bounded() {
+        assert!(validate_config(&LockConfig::default()).is_ok());
+        let config = LockConfig {
+            idle_minutes: 0,
+            ..Default::default()
+        };
+        assert!(validate_config(&config).is_err());
+    }
+}
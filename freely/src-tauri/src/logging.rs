@@ -0,0 +1,283 @@
+//! `tracing` output to stderr and to `logs/freely.log` in the app's local
+//! data directory.
+//!
+//! The log file rotates by size: when it would grow past [`MAX_FILE_BYTES`],
+//! it is renamed to `freely.log.1` (shifting older files up) and a fresh one
+//! is started, keeping [`KEEP_ROTATED`] old files. Writes go through a
+//! background thread so logging never blocks a command. The level is stored
+//! under the `log_level` setting and can be changed without a restart.
+
+use once_cell::sync::OnceCell;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, Registry};
+
+pub(crate) const LOG_LEVEL_SETTING_KEY: &str = "log_level";
+const LOG_FILE: &str = "freely.log";
+const MAX_FILE_BYTES: u64 = 5 * 1024 * 1024;
+const KEEP_ROTATED: usize = 3;
+const DEFAULT_LEVEL: LevelFilter = LevelFilter::INFO;
+const DEFAULT_LINES: usize = 200;
+const MAX_LINES: usize = 5000;
+
+/// Flushes the background writer when dropped; kept for the app's lifetime.
+static GUARD: OnceCell<WorkerGuard> = OnceCell::new();
+static LEVEL: OnceCell<reload::Handle<LevelFilter, Registry>> = OnceCell::new();
+
+/// A log file that moves itself aside once it reaches `max_bytes`.
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_bytes: u64,
+    keep: usize,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf, max_bytes: u64, keep: usize) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            file,
+            size,
+            max_bytes,
+            keep,
+        })
+    }
+
+    fn rotated(&self, n: usize) -> PathBuf {
+        rotated_path(&self.path, n)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let _ = std::fs::remove_file(self.rotated(self.keep));
+        for n in (1..self.keep).rev() {
+            let from = self.rotated(n);
+            if from.exists() {
+                std::fs::rename(&from, self.rotated(n + 1))?;
+            }
+        }
+        std::fs::rename(&self.path, self.rotated(1))?;
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+pub(crate) fn log_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_local_data_dir()
+        .map_err(|e| format!("Failed to resolve data directory: {}", e))?;
+    Ok(dir.join("logs"))
+}
+
+fn parse_level(level: &str) -> Result<LevelFilter, String> {
+    level.trim().parse().map_err(|_| {
+        format!(
+            "Invalid log level {:?}: use error, warn, info, debug, trace or off",
+            level
+        )
+    })
+}
+
+fn apply_level(level: LevelFilter) {
+    if let Some(handle) = LEVEL.get() {
+        if let Err(e) = handle.modify(|filter| *filter = level) {
+            tracing::warn!("Failed to change log level: {}", e);
+        }
+    }
+}
+
+/// The last `count` lines across the current log and the newest rotated one.
+fn tail_lines(path: &Path, count: usize) -> Result<Vec<String>, String> {
+    let mut lines = Vec::new();
+    for file in [rotated_path(path, 1), path.to_path_buf()] {
+        match std::fs::read(&file) {
+            Ok(bytes) => lines.extend(String::from_utf8_lossy(&bytes).lines().map(str::to_string)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("Failed to read log file: {}", e)),
+        }
+    }
+    let skip = lines.len().saturating_sub(count);
+    Ok(lines.split_off(skip))
+}
+
+pub(crate) fn recent_lines(app: &AppHandle, count: usize) -> Result<Vec<String>, String> {
+    tail_lines(&log_dir(app)?.join(LOG_FILE), count)
+}
+
+/// Install the global subscriber. Called first thing in `setup` so later
+/// startup steps are captured; logging to the file is skipped (with a
+/// warning on stderr) if the log directory can't be created.
+pub fn init_logging(app: &AppHandle) {
+    let (filter, handle) = reload::Layer::new(DEFAULT_LEVEL);
+
+    let file = log_dir(app).and_then(|dir| {
+        RotatingFile::open(dir.join(LOG_FILE), MAX_FILE_BYTES, KEEP_ROTATED)
+            .map_err(|e| format!("Failed to open log file: {}", e))
+    });
+    let file_layer = match file {
+        Ok(file) => {
+            let (writer, guard) = tracing_appender::non_blocking(file);
+            let _ = GUARD.set(guard);
+            Some(fmt::layer().with_ansi(false).with_writer(writer))
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            None
+        }
+    };
+
+    let installed = tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_writer(io::stderr))
+        .with(file_layer)
+        .try_init();
+    if installed.is_err() {
+        return;
+    }
+    let _ = LEVEL.set(handle);
+
+    restore_level(app.clone());
+}
+
+/// Apply the stored level once the database is available.
+fn restore_level(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut pool = None;
+        for _ in 0..20 {
+            match crate::db::pool(&app).await {
+                Ok(p) => {
+                    pool = Some(p);
+                    break;
+                }
+                Err(_) => tokio::time::sleep(Duration::from_millis(250)).await,
+            }
+        }
+        let Some(pool) = pool else {
+            return;
+        };
+
+        match crate::db::settings::get::<String>(&pool, LOG_LEVEL_SETTING_KEY).await {
+            Ok(Some(level)) => match parse_level(&level) {
+                Ok(level) => apply_level(level),
+                Err(e) => tracing::warn!("{}", e),
+            },
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Failed to load log level: {}", e),
+        }
+    });
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// The most recent `lines` lines of the log file, oldest first, for
+/// attaching to bug reports.
+#[tauri::command]
+pub async fn get_recent_logs(app: AppHandle, lines: Option<usize>) -> Result<Vec<String>, String> {
+    let count = lines.unwrap_or(DEFAULT_LINES).clamp(1, MAX_LINES);
+    tauri::async_runtime::spawn_blocking(move || recent_lines(&app, count))
+        .await
+        .map_err(|e| format!("Failed to read logs: {}", e))?
+}
+
+#[tauri::command]
+pub async fn get_log_level(app: AppHandle) -> Result<String, String> {
+    let pool = crate::db::pool(&app).await?;
+    Ok(crate::db::settings::get(&pool, LOG_LEVEL_SETTING_KEY)
+        .await?
+        .unwrap_or_else(|| DEFAULT_LEVEL.to_string().to_lowercase()))
+}
+
+/// Change the log level now and persist it for the next launch.
+#[tauri::command]
+pub async fn set_log_level(app: AppHandle, level: String) -> Result<(), String> {
+    let filter = parse_level(&level)?;
+    let pool = crate::db::pool(&app).await?;
+    crate::db::settings::set(
+        &pool,
+        LOG_LEVEL_SETTING_KEY,
+        &filter.to_string().to_lowercase(),
+    )
+    .await?;
+    apply_level(filter);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn rotation_keeps_a_bounded_number_of_files() {
+        let tmp = TempDir::new().unwrap();
+        let path = tmp.path().join(LOG_FILE);
+        let mut file = RotatingFile::open(path.clone(), 20, 2).unwrap();
+        for n in 0..5 {
+            writeln!(file, "line {:02} padding", n).unwrap();
+        }
+        file.flush().unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "line 04 padding\n");
+        assert_eq!(
+            std::fs::read_to_string(rotated_path(&path, 1)).unwrap(),
+            "line 03 padding\n"
+        );
+        assert!(rotated_path(&path, 2).exists());
+        assert!(!rotated_path(&path, 3).exists());
+
+        assert_eq!(
+            tail_lines(&path, 2).unwrap(),
+            ["line 03 padding", "line 04 padding"]
+        );
+        assert!(tail_lines(&tmp.path().join("missing.log"), 5)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn levels_parse_case_insensitively() {
+        assert_eq!(parse_level("DEBUG").unwrap(), LevelFilter::DEBUG);
+        assert_eq!(parse_level(" off ").unwrap(), LevelFilter::OFF);
+        assert!(parse_level("verbose").is_err());
+    }
+}
@@ -0,0 +1,387 @@
+//! Just enough of the MCP client handshake to health-check a server:
+//! `initialize`, `notifications/initialized`, then `tools/list` when the
+//! server advertises tools.
+//!
+//! Stdio servers are spawned as child processes and spoken to with
+//! newline-delimited JSON-RPC; streamable HTTP servers may answer each POST
+//! with plain JSON or an event stream. The legacy SSE transport is not
+//! tested.
+
+use super::config::{McpServerConfig, McpTransport};
+use crate::net::client::http_client;
+use crate::providers::{error_for_status, sse};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader, Lines};
+use tokio::process::Command;
+
+pub(crate) const PROTOCOL_VERSION: &str = "2025-06-18";
+/// Generous because `npx`/`uvx` servers may download themselves on first run.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(60);
+const STDERR_TAIL_LINES: usize = 20;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpTool {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpHealth {
+    pub server_name: Option<String>,
+    pub server_version: Option<String>,
+    pub protocol_version: Option<String>,
+    pub tools: Vec<McpTool>,
+    pub latency_ms: u64,
+}
+
+fn message(id: u64, method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+}
+
+fn initialize_params() -> Value {
+    json!({
+        "protocolVersion": PROTOCOL_VERSION,
+        "capabilities": {},
+        "clientInfo": { "name": "freely", "version": env!("CARGO_PKG_VERSION") }
+    })
+}
+
+fn initialized() -> Value {
+    json!({ "jsonrpc": "2.0", "method": "notifications/initialized" })
+}
+
+/// The result of the response to request `id`, or `None` if `message` is
+/// something else (a notification, or a request from the server).
+pub(crate) fn response_result(message: &Value, id: u64) -> Option<Result<Value, String>> {
+    if message.get("method").is_some() || message["id"].as_u64() != Some(id) {
+        return None;
+    }
+    if let Some(error) = message.get("error") {
+        return Some(Err(format!(
+            "MCP server returned an error: {} (code {})",
+            error["message"].as_str().unwrap_or("unknown error"),
+            error["code"]
+        )));
+    }
+    Some(Ok(message.get("result").cloned().unwrap_or(Value::Null)))
+}
+
+fn health(initialize: &Value, tools: &Value, started: Instant) -> McpHealth {
+    let info = &initialize["serverInfo"];
+    McpHealth {
+        server_name: info["name"].as_str().map(str::to_string),
+        server_version: info["version"].as_str().map(str::to_string),
+        protocol_version: initialize["protocolVersion"].as_str().map(str::to_string),
+        tools: tools["tools"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|tool| {
+                Some(McpTool {
+                    name: tool["name"].as_str()?.to_string(),
+                    description: tool["description"].as_str().map(str::to_string),
+                })
+            })
+            .collect(),
+        latency_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+fn has_tools(initialize: &Value) -> bool {
+    initialize["capabilities"].get("tools").is_some()
+}
+
+// ============================================================================
+// stdio
+// ============================================================================
+
+async fn send_line<W: AsyncWrite + Unpin>(stdin: &mut W, line: &str) -> Result<(), String> {
+    stdin
+        .write_all(format!("{}\n", line).as_bytes())
+        .await
+        .map_err(|e| format!("Failed to write to MCP server: {}", e))?;
+    stdin
+        .flush()
+        .await
+        .map_err(|e| format!("Failed to write to MCP server: {}", e))
+}
+
+async fn call_stdio<W, R>(
+    stdin: &mut W,
+    stdout: &mut Lines<R>,
+    id: u64,
+    method: &str,
+    params: Value,
+) -> Result<Value, String>
+where
+    W: AsyncWrite + Unpin,
+    R: AsyncBufRead + Unpin,
+{
+    send_line(stdin, &message(id, method, params).to_string()).await?;
+    loop {
+        let line = stdout
+            .next_line()
+            .await
+            .map_err(|e| format!("Failed to read from MCP server: {}", e))?
+            .ok_or("MCP server exited before responding")?;
+        // Servers sometimes log to stdout; skip anything that isn't JSON-RPC
+        let Ok(message) = serde_json::from_str::<Value>(&line) else {
+            continue;
+        };
+        if let Some(result) = response_result(&message, id) {
+            return result;
+        }
+    }
+}
+
+async fn handshake_stdio<W, R>(
+    stdin: &mut W,
+    stdout: &mut Lines<R>,
+) -> Result<(Value, Value), String>
+where
+    W: AsyncWrite + Unpin,
+    R: AsyncBufRead + Unpin,
+{
+    let initialize = call_stdio(stdin, stdout, 1, "initialize", initialize_params()).await?;
+    send_line(stdin, &initialized().to_string()).await?;
+    let tools = if has_tools(&initialize) {
+        call_stdio(stdin, stdout, 2, "tools/list", json!({})).await?
+    } else {
+        Value::Null
+    };
+    Ok((initialize, tools))
+}
+
+async fn check_stdio(config: &McpServerConfig, started: Instant) -> Result<McpHealth, String> {
+    let command = config.command.as_deref().unwrap_or_default();
+    let binary = crate::agents::resolve_binary(command)
+        .await
+        .map_err(|_| format!("{} is not installed or not on PATH", command))?;
+
+    let mut child = Command::new(binary)
+        .args(&config.args)
+        .envs(&config.env)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to start MCP server: {}", e))?;
+
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or("Failed to open MCP server stdin")?;
+    let stdout = child.stdout.take().ok_or("Failed to capture stdout")?;
+    let stderr = child.stderr.take().ok_or("Failed to capture stderr")?;
+
+    let stderr_tail = tokio::spawn(async move {
+        let mut lines = BufReader::new(stderr).lines();
+        let mut tail = std::collections::VecDeque::new();
+        while let Ok(Some(line)) = lines.next_line().await {
+            if tail.len() == STDERR_TAIL_LINES {
+                tail.pop_front();
+            }
+            tail.push_back(line);
+        }
+        Vec::from(tail).join("\n")
+    });
+
+    let mut stdout = BufReader::new(stdout).lines();
+    let result = handshake_stdio(&mut stdin, &mut stdout).await;
+    let _ = child.kill().await;
+
+    match result {
+        Ok((initialize, tools)) => Ok(health(&initialize, &tools, started)),
+        Err(e) => {
+            // A grandchild may still hold stderr open, so don't wait long
+            let stderr = tokio::time::timeout(Duration::from_secs(1), stderr_tail)
+                .await
+                .ok()
+                .and_then(Result::ok)
+                .unwrap_or_default();
+            if stderr.trim().is_empty() {
+                Err(e)
+            } else {
+                Err(format!("{}\n{}", e, stderr.trim()))
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Streamable HTTP
+// ============================================================================
+
+async fn post_http(
+    client: &reqwest::Client,
+    config: &McpServerConfig,
+    session_id: Option<&str>,
+    body: &Value,
+) -> Result<reqwest::Response, String> {
+    let mut request = client
+        .post(config.url.as_deref().unwrap_or_default())
+        .header("Accept", "application/json, text/event-stream")
+        .json(body);
+    for (name, value) in &config.headers {
+        request = request.header(name, value);
+    }
+    if let Some(session_id) = session_id {
+        request = request
+            .header("Mcp-Session-Id", session_id)
+            .header("MCP-Protocol-Version", PROTOCOL_VERSION);
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach MCP server: {}", e))?;
+    error_for_status(response).await
+}
+
+async fn read_http_result(response: reqwest::Response, id: u64) -> Result<Value, String> {
+    let is_stream = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("text/event-stream"));
+
+    if !is_stream {
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| format!("Invalid MCP response: {}", e))?;
+        return response_result(&body, id)
+            .unwrap_or_else(|| Err("MCP response has no result".into()));
+    }
+
+    let mut result = None;
+    sse::read_events(response, |event| {
+        if let Ok(message) = serde_json::from_str::<Value>(&event.data) {
+            result = response_result(&message, id);
+        }
+        Ok(result.is_none())
+    })
+    .await?;
+    result.unwrap_or_else(|| Err("MCP server closed the stream before responding".into()))
+}
+
+async fn check_http(config: &McpServerConfig, started: Instant) -> Result<McpHealth, String> {
+    let client = http_client(None)?;
+
+    let response = post_http(
+        &client,
+        config,
+        None,
+        &message(1, "initialize", initialize_params()),
+    )
+    .await?;
+    let session_id = response
+        .headers()
+        .get("mcp-session-id")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let initialize = read_http_result(response, 1).await?;
+
+    post_http(&client, config, session_id.as_deref(), &initialized()).await?;
+
+    let tools = if has_tools(&initialize) {
+        let response = post_http(
+            &client,
+            config,
+            session_id.as_deref(),
+            &message(2, "tools/list", json!({})),
+        )
+        .await?;
+        read_http_result(response, 2).await?
+    } else {
+        Value::Null
+    };
+    Ok(health(&initialize, &tools, started))
+}
+
+/// Connect to the server described by `config`, complete the handshake and
+/// list its tools.
+pub(crate) async fn check(config: &McpServerConfig) -> Result<McpHealth, String> {
+    config.validate()?;
+    let started = Instant::now();
+    let check = async {
+        match config.transport() {
+            McpTransport::Stdio => check_stdio(config, started).await,
+            McpTransport::Http => check_http(config, started).await,
+            McpTransport::Sse => Err(
+                "Testing legacy SSE servers is not supported; use the HTTP transport".to_string(),
+            ),
+        }
+    };
+    tokio::time::timeout(CHECK_TIMEOUT, check)
+        .await
+        .map_err(|_| {
+            format!(
+                "MCP server did not respond within {}s",
+                CHECK_TIMEOUT.as_secs()
+            )
+        })?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn responses_are_matched_by_id() {
+        let ok = json!({ "jsonrpc": "2.0", "id": 2, "result": { "tools": [] } });
+        assert!(response_result(&ok, 2).unwrap().is_ok());
+        assert!(response_result(&ok, 1).is_none());
+
+        let ping = json!({ "jsonrpc": "2.0", "id": 2, "method": "ping" });
+        assert!(response_result(&ping, 2).is_none());
+
+        let error = json!({ "jsonrpc": "2.0", "id": 1, "error": { "code": -32602, "message": "bad version" } });
+        let err = response_result(&error, 1).unwrap().unwrap_err();
+        assert!(
+            err.contains("bad version") && err.contains("-32602"),
+            "{}",
+            err
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn stdio_handshake_lists_tools() {
+        let script = r#"
+            read line
+            echo 'starting up...'
+            echo '{"jsonrpc":"2.0","id":1,"result":{"protocolVersion":"2025-06-18","capabilities":{"tools":{}},"serverInfo":{"name":"fake","version":"1.2.0"}}}'
+            read line
+            read line
+            echo '{"jsonrpc":"2.0","id":2,"result":{"tools":[{"name":"echo","description":"Echo input"},{"name":"sum"}]}}'
+        "#;
+        let config = McpServerConfig {
+            command: Some("sh".to_string()),
+            args: vec!["-c".to_string(), script.to_string()],
+            ..Default::default()
+        };
+
+        let health = check(&config).await.unwrap();
+        assert_eq!(health.server_name.as_deref(), Some("fake"));
+        assert_eq!(health.protocol_version.as_deref(), Some(PROTOCOL_VERSION));
+        let tools: Vec<&str> = health.tools.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(tools, ["echo", "sum"]);
+
+        let crashing = McpServerConfig {
+            command: Some("sh".to_string()),
+            args: vec![
+                "-c".to_string(),
+                "echo 'missing API key' >&2; exit 1".to_string(),
+            ],
+            ..Default::default()
+        };
+        let err = check(&crashing).await.unwrap_err();
+        assert!(err.contains("missing API key"), "{}", err);
+    }
+}
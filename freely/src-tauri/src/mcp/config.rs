@@ -0,0 +1,189 @@
+//! `mcp.json` in the app's local data directory, next to `.claude/`.
+//!
+//! The file uses the same shape as a project `.mcp.json`, so it can be
+//! passed straight to the Claude CLI with `--mcp-config`. Keys Freely doesn't
+//! know about, at the top level or inside a server entry, are kept when the
+//! file is rewritten.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+pub(crate) const FILE_NAME: &str = "mcp.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum McpTransport {
+    Stdio,
+    Http,
+    Sse,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpServerConfig {
+    /// Omitted for stdio servers in most hand-written configs.
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub transport: Option<McpTransport>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl McpServerConfig {
+    pub fn transport(&self) -> McpTransport {
+        self.transport.unwrap_or(if self.url.is_some() {
+            McpTransport::Http
+        } else {
+            McpTransport::Stdio
+        })
+    }
+
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        match self.transport() {
+            McpTransport::Stdio => {
+                if self.command.as_deref().is_none_or(|c| c.trim().is_empty()) {
+                    return Err("Invalid MCP server: stdio servers need a command".to_string());
+                }
+            }
+            McpTransport::Http | McpTransport::Sse => {
+                let url = self.url.as_deref().unwrap_or_default();
+                if !(url.starts_with("http://") || url.starts_with("https://")) {
+                    return Err(
+                        "Invalid MCP server: url must start with http:// or https://".to_string(),
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct McpFile {
+    #[serde(rename = "mcpServers", default)]
+    pub servers: BTreeMap<String, McpServerConfig>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+pub(crate) fn config_path_in(data_dir: &Path) -> PathBuf {
+    data_dir.join(FILE_NAME)
+}
+
+/// Server names end up in tool names (`mcp__<name>__<tool>`), so keep them
+/// to characters the CLI accepts.
+pub(crate) fn validate_name(name: &str) -> Result<(), String> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "Invalid MCP server name {:?}: use letters, digits, '-' and '_'",
+            name
+        ));
+    }
+    Ok(())
+}
+
+/// Read `path`, treating a missing file as an empty config.
+pub(crate) fn load(path: &Path) -> Result<McpFile, String> {
+    let raw = match std::fs::read_to_string(path) {
+        Ok(raw) => raw,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(McpFile::default()),
+        Err(e) => return Err(format!("Failed to read {}: {}", FILE_NAME, e)),
+    };
+    serde_json::from_str(&raw).map_err(|e| format!("Failed to parse {}: {}", FILE_NAME, e))
+}
+
+pub(crate) fn save(path: &Path, file: &McpFile) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create config directory: {}", e))?;
+    }
+    let mut content = serde_json::to_string_pretty(file)
+        .map_err(|e| format!("Failed to serialize {}: {}", FILE_NAME, e))?;
+    content.push('\n');
+    std::fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", FILE_NAME, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn rewriting_keeps_unknown_keys() {
+        let tmp = TempDir::new().unwrap();
+        let path = config_path_in(tmp.path());
+        std::fs::write(
+            &path,
+            r#"{
+              "mcpServers": {
+                "fs": { "command": "npx", "args": ["-y", "@modelcontextprotocol/server-filesystem"], "timeout": 5 }
+              },
+              "comment": "hand edited"
+            }"#,
+        )
+        .unwrap();
+
+        let mut file = load(&path).unwrap();
+        let fs = &file.servers["fs"];
+        assert_eq!(fs.transport(), McpTransport::Stdio);
+        assert_eq!(fs.extra["timeout"], 5);
+
+        file.servers.insert(
+            "docs".to_string(),
+            McpServerConfig {
+                url: Some("https://example.com/mcp".to_string()),
+                ..Default::default()
+            },
+        );
+        save(&path, &file).unwrap();
+
+        let reloaded: Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(reloaded["comment"], "hand edited");
+        assert_eq!(reloaded["mcpServers"]["fs"]["timeout"], 5);
+        assert_eq!(
+            reloaded["mcpServers"]["docs"]["url"],
+            "https://example.com/mcp"
+        );
+        assert!(load(&tmp.path().join("missing.json"))
+            .unwrap()
+            .servers
+            .is_empty());
+    }
+
+    #[test]
+    fn validation_checks_transport_fields_and_names() {
+        let stdio = McpServerConfig {
+            command: Some("uvx".to_string()),
+            ..Default::default()
+        };
+        assert!(stdio.validate().is_ok());
+        assert!(McpServerConfig::default().validate().is_err());
+
+        let sse = McpServerConfig {
+            transport: Some(McpTransport::Sse),
+            url: Some("ftp://example.com".to_string()),
+            ..Default::default()
+        };
+        assert!(sse.validate().is_err());
+
+        assert!(validate_name("github_tools-2").is_ok());
+        assert!(validate_name("my server").is_err());
+        assert!(validate_name("").is_err());
+    }
+}
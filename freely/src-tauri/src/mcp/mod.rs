@@ -0,0 +1,94 @@
+//! MCP (Model Context Protocol) server configuration for the Claude agent.
+//!
+//! Servers live in `mcp.json` beside the `.claude/` config directory and
+//! are handed to the Claude CLI with `--mcp-config` on every run. Servers
+//! can be health-checked before use: `test_mcp_server` starts the server,
+//! performs the MCP handshake and reports the tools it exposes.
+
+pub(crate) mod client;
+pub(crate) mod config;
+
+use client::McpHealth;
+use config::{McpFile, McpServerConfig, McpTransport};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpServer {
+    pub name: String,
+    pub transport: McpTransport,
+    pub config: McpServerConfig,
+}
+
+fn config_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app
+        .path()
+        .app_local_data_dir()
+        .map_err(|e| format!("Could not resolve app_local_data_dir: {}", e))?;
+    Ok(config::config_path_in(&data_dir))
+}
+
+/// The `mcp.json` to pass to the CLI for a given `.claude/` directory, if
+/// any servers are configured.
+pub(crate) fn cli_config_path(claude_dir: &Path) -> Option<PathBuf> {
+    let path = config::config_path_in(claude_dir.parent()?);
+    config::load(&path)
+        .ok()
+        .filter(|file| !file.servers.is_empty())
+        .map(|_| path)
+}
+
+fn load_server(app: &AppHandle, name: &str) -> Result<McpServerConfig, String> {
+    config::load(&config_path(app)?)?
+        .servers
+        .remove(name)
+        .ok_or_else(|| format!("MCP server not found: {}", name))
+}
+
+#[tauri::command]
+pub fn list_mcp_servers(app: AppHandle) -> Result<Vec<McpServer>, String> {
+    let file: McpFile = config::load(&config_path(&app)?)?;
+    Ok(file
+        .servers
+        .into_iter()
+        .map(|(name, config)| McpServer {
+            name,
+            transport: config.transport(),
+            config,
+        })
+        .collect())
+}
+
+/// Add a server, replacing any existing server with the same name.
+#[tauri::command]
+pub fn add_mcp_server(app: AppHandle, name: String, config: McpServerConfig) -> Result<(), String> {
+    let name = name.trim().to_string();
+    config::validate_name(&name)?;
+    config.validate()?;
+
+    let path = config_path(&app)?;
+    let mut file = config::load(&path)?;
+    file.servers.insert(name, config);
+    config::save(&path, &file)
+}
+
+#[tauri::command]
+pub fn remove_mcp_server(app: AppHandle, name: String) -> Result<(), String> {
+    let path = config_path(&app)?;
+    let mut file = config::load(&path)?;
+    if file.servers.remove(&name).is_none() {
+        return Err(format!("MCP server not found: {}", name));
+    }
+    config::save(&path, &file)
+}
+
+/// Start a configured server (or connect to it, for HTTP servers), run the
+/// MCP handshake and list its tools. Fails with the server's stderr when it
+/// crashes during startup.
+#[tauri::command]
+pub async fn test_mcp_server(app: AppHandle, name: String) -> Result<McpHealth, String> {
+    let config = load_server(&app, &name)?;
+    client::check(&config).await
+}
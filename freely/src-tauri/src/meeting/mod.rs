@@ -0,0 +1,544 @@
+//! Meeting mode: one session that records a meeting end to end.
+//!
+//! `start_meeting_session` starts whatever the meeting needs that isn't
+//! running yet (microphone capture, the system audio stream, diarization)
+//! and a streaming STT session per source, then creates a conversation for
+//! the meeting. Final transcripts are saved under that conversation as they
+//! arrive, system audio labelled by speaker, and emitted as
+//! `meeting-transcript`. Every `notesIntervalSecs` the new part of the
+//! transcript is folded into running notes by the configured model.
+//!
+//! `stop_meeting_session` stops the STT sessions, waits for their last
+//! results and releases what the meeting started. The minutes are then
+//! written from the notes and added to the conversation, and a notification
+//! links to them. The session's state (`recording`, `finishing`,
+//! `completed`, `failed`) is kept in the `meetings` table and every change
+//! is emitted as `meeting-updated`. A meeting cut short by quitting is
+//! marked failed on the next start, with its transcript and notes intact.
+
+pub(crate) mod notes;
+pub(crate) mod store;
+
+pub use notes::SummarizerConfig;
+
+use crate::audio::AudioSource;
+use crate::db::chat::{self, Conversation, Message, MessageRole};
+use crate::db::transcripts::{self, Transcript};
+use crate::notify::{self, NotificationAction};
+use crate::stt::streaming::{self, StreamingOptions, StreamingProvider, StreamingTranscript};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use store::{Meeting, MeetingStatus};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::{broadcast, oneshot};
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+/// Bounds for `notesIntervalSecs`.
+const MIN_NOTES_INTERVAL_SECS: u64 = 60;
+const MAX_NOTES_INTERVAL_SECS: u64 = 60 * 60;
+/// How long to wait for final results after stopping STT; a little longer
+/// than the STT sessions wait for their provider.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(4);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MeetingOptions {
+    /// Title of the meeting's conversation; the start time when omitted.
+    #[serde(default)]
+    pub title: Option<String>,
+    pub stt_provider: StreamingProvider,
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub stt_model: Option<String>,
+    /// Transcribe the microphone (the user's side of the call).
+    #[serde(default = "default_true")]
+    pub microphone: bool,
+    #[serde(default)]
+    pub microphone_device_id: Option<String>,
+    /// Transcribe system audio (everyone else on the call).
+    #[serde(default = "default_true")]
+    pub system_audio: bool,
+    #[serde(default)]
+    pub system_audio_device_id: Option<String>,
+    /// Label system audio by speaker.
+    #[serde(default = "default_true")]
+    pub diarize: bool,
+    pub summarizer: SummarizerConfig,
+    #[serde(default = "default_notes_interval_secs")]
+    pub notes_interval_secs: u64,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_notes_interval_secs() -> u64 {
+    5 * 60
+}
+
+impl MeetingOptions {
+    fn sources(&self) -> Vec<AudioSource> {
+        let mut sources = Vec::new();
+        if self.microphone {
+            sources.push(AudioSource::Microphone);
+        }
+        if self.system_audio {
+            sources.push(AudioSource::SystemAudio);
+        }
+        sources
+    }
+
+    fn notes_interval(&self) -> Duration {
+        Duration::from_secs(
+            self.notes_interval_secs
+                .clamp(MIN_NOTES_INTERVAL_SECS, MAX_NOTES_INTERVAL_SECS),
+        )
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MeetingTranscript<'a> {
+    meeting_id: &'a str,
+    transcript: &'a Transcript,
+}
+
+struct ActiveMeeting {
+    id: String,
+    stop: oneshot::Sender<()>,
+}
+
+static ACTIVE: Lazy<Mutex<Option<ActiveMeeting>>> = Lazy::new(|| Mutex::new(None));
+
+/// What a meeting started, so stopping it leaves anything that was
+/// already running alone.
+#[derive(Default)]
+struct Started {
+    microphone: bool,
+    system_audio: bool,
+    diarization: bool,
+    streams: Vec<AudioSource>,
+}
+
+async fn acquire(
+    app: &AppHandle,
+    options: &MeetingOptions,
+    started: &mut Started,
+) -> Result<(), String> {
+    use crate::audio::{capture, loopback};
+
+    if options.microphone && capture::get_microphone_capture_status(app.clone())?.is_none() {
+        let device_id = options.microphone_device_id.clone();
+        capture::start_microphone_capture(app.clone(), device_id, None).await?;
+        started.microphone = true;
+    }
+    if options.system_audio && loopback::get_system_audio_stream_status(app.clone())?.is_none() {
+        let device_id = options.system_audio_device_id.clone();
+        loopback::start_system_audio_stream(app.clone(), device_id, None).await?;
+        started.system_audio = true;
+    }
+    if options.system_audio && options.diarize && !crate::stt::diarization::is_running() {
+        crate::stt::diarization::start_diarization(app.clone(), None)?;
+        started.diarization = true;
+    }
+    for source in options.sources() {
+        let stt = StreamingOptions {
+            provider: options.stt_provider,
+            source,
+            language: options.language.clone(),
+            model: options.stt_model.clone(),
+        };
+        streaming::start_streaming_stt(app.clone(), stt).await?;
+        started.streams.push(source);
+    }
+    Ok(())
+}
+
+async fn release(app: &AppHandle, started: &Started) {
+    for source in &started.streams {
+        let _ = streaming::stop_streaming_stt(*source);
+    }
+    let stopped = async {
+        if started.diarization {
+            crate::stt::diarization::stop_diarization()?;
+        }
+        if started.system_audio {
+            crate::audio::loopback::stop_system_audio_stream(app.clone())?;
+        }
+        if started.microphone {
+            crate::audio::capture::stop_microphone_capture(app.clone()).await?;
+        }
+        Ok::<_, String>(())
+    };
+    if let Err(e) = stopped.await {
+        warn!("Failed to release meeting audio: {}", e);
+    }
+}
+
+fn clear_active(id: &str) {
+    let mut active = ACTIVE.lock();
+    if active.as_ref().is_some_and(|meeting| meeting.id == id) {
+        *active = None;
+    }
+}
+
+async fn emit_updated(app: &AppHandle, id: &str) {
+    let meeting = async { store::get(&crate::db::pool(app).await?, id).await }.await;
+    match meeting {
+        Ok(Some(meeting)) => {
+            if let Err(e) = app.emit("meeting-updated", &meeting) {
+                warn!("Failed to emit meeting-updated: {}", e);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => warn!("{}", e),
+    }
+}
+
+fn default_title(started_at: i64) -> String {
+    use chrono::TimeZone;
+    match chrono::Local.timestamp_millis_opt(started_at).single() {
+        Some(at) => format!("Meeting {}", at.format("%Y-%m-%d %H:%M")),
+        None => "Meeting".to_string(),
+    }
+}
+
+/// Record a status change, logging rather than failing.
+async fn save_status(
+    app: &AppHandle,
+    id: &str,
+    status: MeetingStatus,
+    minutes: Option<&str>,
+    error: Option<&str>,
+) {
+    let saved =
+        async { store::set_status(&crate::db::pool(app).await?, id, status, minutes, error).await }
+            .await;
+    if let Err(e) = saved {
+        warn!("{}", e);
+    }
+}
+
+/// A meeting that is recording: its transcript so far and the part not in
+/// the notes yet. The pool is looked up for each write, since encrypting
+/// the database replaces it.
+struct Session {
+    app: AppHandle,
+    meeting: Meeting,
+    notes: Option<String>,
+    pending: Vec<Transcript>,
+}
+
+impl Session {
+    async fn record(&mut self, streamed: StreamingTranscript) {
+        let now = crate::db::now_millis();
+        let ended_at = streamed.ended_at.unwrap_or(now);
+        let transcript = Transcript {
+            id: String::new(),
+            conversation_id: Some(self.meeting.conversation_id.clone()),
+            source: streamed.source,
+            text: streamed.text.trim().to_string(),
+            started_at: streamed.started_at.unwrap_or(ended_at).min(ended_at),
+            ended_at,
+            confidence: streamed.confidence.map(|c| c.clamp(0.0, 1.0)),
+            speaker_label: None,
+            translated_text: None,
+            translation_language: None,
+            created_at: 0,
+        };
+        let transcript = crate::stt::diarization::label(transcript);
+        let saved = async {
+            let pool = crate::db::pool(&self.app).await?;
+            transcripts::save(&pool, transcript).await
+        }
+        .await;
+        match saved {
+            Ok(transcript) => {
+                crate::stt::translate::translate_in_background(&self.app, &transcript);
+                let event = MeetingTranscript {
+                    meeting_id: &self.meeting.id,
+                    transcript: &transcript,
+                };
+                if let Err(e) = self.app.emit("meeting-transcript", event) {
+                    warn!("Failed to emit meeting-transcript: {}", e);
+                }
+                self.pending.push(transcript);
+            }
+            Err(e) => warn!("{}", e),
+        }
+    }
+
+    /// Fold the pending transcript into the notes. On failure it stays
+    /// pending for the next try.
+    async fn refresh_notes(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let lines = notes::render_lines(&self.pending, self.meeting.started_at);
+        let config = &self.meeting.options.summarizer;
+        match notes::update_notes(&self.app, config, self.notes.as_deref(), &lines).await {
+            Ok(updated) => {
+                let saved = async {
+                    let pool = crate::db::pool(&self.app).await?;
+                    store::set_notes(&pool, &self.meeting.id, &updated).await
+                }
+                .await;
+                if let Err(e) = saved {
+                    warn!("{}", e);
+                }
+                self.notes = Some(updated);
+                self.pending.clear();
+                emit_updated(&self.app, &self.meeting.id).await;
+            }
+            Err(e) => warn!("Failed to update meeting notes: {}", e),
+        }
+    }
+
+    /// Write the minutes and add them to the conversation.
+    async fn finish(&mut self) -> Result<String, String> {
+        if self.notes.is_none() && self.pending.is_empty() {
+            return Err("Nothing was transcribed".to_string());
+        }
+        let lines = notes::render_lines(&self.pending, self.meeting.started_at);
+        let minutes = notes::write_minutes(
+            &self.app,
+            &self.meeting.options.summarizer,
+            &self.meeting.title,
+            self.notes.as_deref(),
+            &lines,
+        )
+        .await?;
+        let message = Message {
+            id: uuid::Uuid::new_v4().to_string(),
+            role: MessageRole::Assistant,
+            content: minutes.clone(),
+            timestamp: crate::db::now_millis(),
+            attached_files: None,
+            truncated: false,
+        };
+        let pool = crate::db::pool(&self.app).await?;
+        chat::append(&pool, &self.meeting.conversation_id, &message).await?;
+        Ok(minutes)
+    }
+}
+
+async fn run(
+    mut session: Session,
+    started: Started,
+    mut finals: broadcast::Receiver<StreamingTranscript>,
+    mut stop: oneshot::Receiver<()>,
+) {
+    let id = session.meeting.id.clone();
+    let period = session.meeting.options.notes_interval();
+    let mut interval = tokio::time::interval_at(Instant::now() + period, period);
+    loop {
+        tokio::select! {
+            // A dropped sender stops the meeting too
+            _ = &mut stop => break,
+            streamed = finals.recv() => match streamed {
+                Ok(streamed) => {
+                    if started.streams.contains(&streamed.source) {
+                        session.record(streamed).await;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("Meeting fell behind, skipped {} transcripts", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = interval.tick() => session.refresh_notes().await,
+        }
+    }
+
+    let app = session.app.clone();
+    save_status(&app, &id, MeetingStatus::Finishing, None, None).await;
+    emit_updated(&app, &id).await;
+
+    for source in &started.streams {
+        let _ = streaming::stop_streaming_stt(*source);
+    }
+    let deadline = Instant::now() + DRAIN_TIMEOUT;
+    loop {
+        match tokio::time::timeout_at(deadline, finals.recv()).await {
+            Ok(Ok(streamed)) => {
+                if started.streams.contains(&streamed.source) {
+                    session.record(streamed).await;
+                }
+            }
+            Ok(Err(broadcast::error::RecvError::Lagged(_))) => {}
+            Ok(Err(broadcast::error::RecvError::Closed)) | Err(_) => break,
+        }
+    }
+    release(&app, &started).await;
+    clear_active(&id);
+
+    let title = session.meeting.title.clone();
+    let open = NotificationAction::OpenConversation {
+        conversation_id: session.meeting.conversation_id.clone(),
+    };
+    match session.finish().await {
+        Ok(minutes) => {
+            save_status(&app, &id, MeetingStatus::Completed, Some(&minutes), None).await;
+            notify::show(&app, "Meeting minutes ready", &title, vec![open]);
+            crate::automations::fire(
+                &app,
+                crate::automations::Trigger::MeetingSummaryReady,
+                serde_json::json!({
+                    "meetingId": id,
+                    "conversationId": session.meeting.conversation_id,
+                    "title": title,
+                    "startedAt": session.meeting.started_at,
+                    "content": minutes,
+                }),
+            );
+        }
+        Err(e) => {
+            warn!("Failed to write meeting minutes: {}", e);
+            save_status(&app, &id, MeetingStatus::Failed, None, Some(&e)).await;
+            notify::show(&app, "Meeting minutes failed", &e, vec![open]);
+        }
+    }
+    emit_updated(&app, &id).await;
+}
+
+/// Mark meetings interrupted by the last quit as failed. Called once from
+/// `setup`.
+pub fn recover_meetings(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let pool = match crate::db::pool(&app).await {
+            Ok(pool) => pool,
+            Err(e) => {
+                warn!("{}", e);
+                return;
+            }
+        };
+        match store::fail_interrupted(&pool).await {
+            Ok(0) => {}
+            Ok(count) => info!("Marked {} interrupted meeting(s) as failed", count),
+            Err(e) => warn!("{}", e),
+        }
+    });
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// Start recording a meeting. Only one meeting runs at a time.
+#[tauri::command]
+pub async fn start_meeting_session(
+    app: AppHandle,
+    options: MeetingOptions,
+) -> Result<Meeting, String> {
+    if options.sources().is_empty() {
+        return Err("A meeting needs the microphone, system audio or both".to_string());
+    }
+    if options.summarizer.model.trim().is_empty() {
+        return Err("A model for the meeting notes is required".to_string());
+    }
+    let id = uuid::Uuid::new_v4().to_string();
+    let (stop_tx, stop_rx) = oneshot::channel();
+    {
+        let mut active = ACTIVE.lock();
+        if active.is_some() {
+            return Err("A meeting is already running".to_string());
+        }
+        *active = Some(ActiveMeeting {
+            id: id.clone(),
+            stop: stop_tx,
+        });
+    }
+
+    let created = async {
+        let pool = crate::db::pool(&app).await?;
+        let started_at = crate::db::now_millis();
+        let title = options
+            .title
+            .clone()
+            .filter(|title| !title.trim().is_empty())
+            .unwrap_or_else(|| default_title(started_at));
+        let conversation = chat::create(
+            &pool,
+            Conversation {
+                id: uuid::Uuid::new_v4().to_string(),
+                title: title.clone(),
+                created_at: started_at,
+                updated_at: started_at,
+                pinned: false,
+                archived_at: None,
+                deleted_at: None,
+                messages: Vec::new(),
+            },
+        )
+        .await?;
+        let meeting =
+            store::insert(&pool, &id, &conversation.id, &title, &options, started_at).await?;
+        Ok::<_, String>(meeting)
+    };
+    let meeting = match created.await {
+        Ok(created) => created,
+        Err(e) => {
+            clear_active(&id);
+            return Err(e);
+        }
+    };
+
+    // Subscribed before STT starts, so no early result is missed
+    let finals = streaming::subscribe_finals();
+    let mut started = Started::default();
+    if let Err(e) = acquire(&app, &options, &mut started).await {
+        release(&app, &started).await;
+        clear_active(&id);
+        save_status(&app, &id, MeetingStatus::Failed, None, Some(&e)).await;
+        return Err(e);
+    }
+
+    let session = Session {
+        app: app.clone(),
+        meeting: meeting.clone(),
+        notes: None,
+        pending: Vec::new(),
+    };
+    tauri::async_runtime::spawn(run(session, started, finals, stop_rx));
+    if let Err(e) = app.emit("meeting-updated", &meeting) {
+        warn!("Failed to emit meeting-updated: {}", e);
+    }
+    Ok(meeting)
+}
+
+/// Stop the running meeting. The minutes are written in the background;
+/// `meeting-updated` reports when they are done.
+#[tauri::command]
+pub fn stop_meeting_session() -> Result<(), String> {
+    let active = ACTIVE.lock().take();
+    match active {
+        Some(meeting) => {
+            let _ = meeting.stop.send(());
+            Ok(())
+        }
+        None => Err("No meeting is running".to_string()),
+    }
+}
+
+/// The running meeting's ID, if any.
+#[tauri::command]
+pub fn get_active_meeting() -> Option<String> {
+    ACTIVE.lock().as_ref().map(|meeting| meeting.id.clone())
+}
+
+#[tauri::command]
+pub async fn get_meeting(app: AppHandle, id: String) -> Result<Option<Meeting>, String> {
+    let pool = crate::db::pool(&app).await?;
+    store::get(&pool, &id).await
+}
+
+/// Meetings, newest first.
+#[tauri::command]
+pub async fn list_meetings(app: AppHandle, limit: Option<u32>) -> Result<Vec<Meeting>, String> {
+    let pool = crate::db::pool(&app).await?;
+    store::list(&pool, limit).await
+}
@@ -0,0 +1,183 @@
+//! Running notes and closing minutes, written by the configured model.
+
+use crate::audio::AudioSource;
+use crate::db::transcripts::Transcript;
+use crate::providers::middleware::Retry;
+use crate::providers::{ChatMessage, ChatRole, CompletionRequest, ProviderKind};
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+use tauri::AppHandle;
+
+const NOTES_MAX_TOKENS: u32 = 1024;
+const MINUTES_MAX_TOKENS: u32 = 2048;
+
+const NOTES_PROMPT: &str = "You take notes during a meeting from its live transcript. Merge the \
+previous notes (if any) with the new part of the transcript into one updated set of notes. Keep \
+topics discussed, decisions, action items with their owners, numbers, dates and open questions. \
+Write compact bullet points in the third person, without preamble. The transcript comes from \
+speech recognition, so fix obvious misrecognitions silently.";
+
+const MINUTES_PROMPT: &str = "Write the minutes of a meeting from the notes taken during it and \
+the last part of its transcript. Use Markdown with these sections: Summary (a short paragraph), \
+Decisions, Action items (with owner and due date when stated), and Open questions. Leave out \
+sections with nothing in them. Don't invent anything that isn't in the notes or transcript.";
+
+/// The model that writes the notes and minutes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SummarizerConfig {
+    pub provider: ProviderKind,
+    pub model: String,
+    #[serde(default)]
+    pub base_url: Option<String>,
+    #[serde(default)]
+    pub api_key_name: Option<String>,
+    #[serde(default)]
+    pub profile_id: Option<String>,
+}
+
+pub(crate) fn speaker(transcript: &Transcript) -> &str {
+    match (&transcript.speaker_label, transcript.source) {
+        (Some(label), _) => label,
+        (None, AudioSource::Microphone) => "You",
+        (None, _) => "Others",
+    }
+}
+
+/// `[mm:ss] Speaker: text` lines, timed from `started_at`.
+pub(crate) fn render_lines(transcripts: &[Transcript], started_at: i64) -> String {
+    transcripts
+        .iter()
+        .map(|transcript| {
+            let secs = (transcript.started_at - started_at).max(0) / 1000;
+            format!(
+                "[{:02}:{:02}] {}: {}",
+                secs / 60,
+                secs % 60,
+                speaker(transcript),
+                transcript.text.trim()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+async fn complete(
+    app: &AppHandle,
+    config: &SummarizerConfig,
+    system_prompt: &str,
+    content: String,
+    max_tokens: u32,
+) -> Result<String, String> {
+    let request = CompletionRequest {
+        provider: config.provider,
+        model: config.model.clone(),
+        messages: vec![ChatMessage {
+            role: ChatRole::User,
+            content,
+            images: Vec::new(),
+        }],
+        system_prompt: Some(system_prompt.to_string()),
+        temperature: Some(0.2),
+        max_tokens: Some(max_tokens),
+        base_url: config.base_url.clone(),
+        api_key_name: config.api_key_name.clone(),
+        retry: None,
+        profile_id: config.profile_id.clone(),
+        skip_cache: false,
+    };
+    if let Some(exceeded) = crate::usage::budget_exceeded(app, request.provider).await {
+        return Err(exceeded.to_string());
+    }
+    let client = match &config.profile_id {
+        Some(profile_id) => {
+            crate::providers::profiles::connect(app, profile_id, Retry::default()).await?
+        }
+        None => {
+            crate::providers::connect(
+                app,
+                request.provider,
+                request.base_url.as_deref(),
+                request.api_key_name.as_deref(),
+                Retry::default(),
+            )
+            .await?
+        }
+    };
+    let started = Instant::now();
+    let output = client.stream_completion(&request, &|_| {}).await?;
+    crate::usage::record(app, "meeting", None, &request, &output, started.elapsed()).await;
+    let text = output.text.trim();
+    if text.is_empty() {
+        return Err("The model returned an empty response".to_string());
+    }
+    Ok(text.to_string())
+}
+
+/// `previous` notes with `lines` of new transcript folded in.
+pub(crate) async fn update_notes(
+    app: &AppHandle,
+    config: &SummarizerConfig,
+    previous: Option<&str>,
+    lines: &str,
+) -> Result<String, String> {
+    let mut prompt = String::new();
+    if let Some(previous) = previous {
+        prompt.push_str(&format!("Previous notes:\n{}\n\n", previous));
+    }
+    prompt.push_str(&format!("New transcript:\n{}", lines));
+    complete(app, config, NOTES_PROMPT, prompt, NOTES_MAX_TOKENS).await
+}
+
+/// Markdown minutes from the meeting's notes and the transcript `lines`
+/// not folded into them yet.
+pub(crate) async fn write_minutes(
+    app: &AppHandle,
+    config: &SummarizerConfig,
+    title: &str,
+    notes: Option<&str>,
+    lines: &str,
+) -> Result<String, String> {
+    let mut prompt = format!("Meeting: {}\n\n", title);
+    if let Some(notes) = notes {
+        prompt.push_str(&format!("Notes:\n{}\n\n", notes));
+    }
+    if !lines.is_empty() {
+        prompt.push_str(&format!("Last part of the transcript:\n{}", lines));
+    }
+    complete(app, config, MINUTES_PROMPT, prompt, MINUTES_MAX_TOKENS).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transcript(source: AudioSource, label: Option<&str>, at: i64, text: &str) -> Transcript {
+        Transcript {
+            id: String::new(),
+            conversation_id: None,
+            source,
+            text: text.to_string(),
+            started_at: at,
+            ended_at: at + 1_000,
+            confidence: None,
+            speaker_label: label.map(String::from),
+            translated_text: None,
+            translation_language: None,
+            created_at: 0,
+        }
+    }
+
+    #[test]
+    fn lines_are_timed_from_the_start_of_the_meeting() {
+        let transcripts = [
+            transcript(AudioSource::Microphone, None, 10_000, "Shall we start? "),
+            transcript(AudioSource::SystemAudio, Some("Speaker 1"), 75_500, "Sure."),
+            transcript(AudioSource::SystemAudio, None, 3_610_000, "Bye."),
+        ];
+        assert_eq!(
+            render_lines(&transcripts, 10_000),
+            "[00:00] You: Shall we start?\n[01:05] Speaker 1: Sure.\n[60:00] Others: Bye."
+        );
+    }
+}
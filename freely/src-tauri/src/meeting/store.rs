@@ -0,0 +1,279 @@
+//! The `meetings` table (migration 24).
+
+use super::MeetingOptions;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+const MEETING_COLUMNS: &str = "id, conversation_id, title, status, options, notes, minutes, error,
+     started_at, ended_at, updated_at";
+const DEFAULT_LIST_LIMIT: u32 = 50;
+const MAX_LIST_LIMIT: u32 = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MeetingStatus {
+    /// Capturing and transcribing.
+    Recording,
+    /// Stopped; the last results and the minutes are being written.
+    Finishing,
+    Completed,
+    /// The minutes couldn't be written. The transcript and notes are kept.
+    Failed,
+}
+
+impl MeetingStatus {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Recording => "recording",
+            Self::Finishing => "finishing",
+            Self::Completed => "completed",
+            Self::Failed => "failed",
+        }
+    }
+
+    fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "recording" => Ok(Self::Recording),
+            "finishing" => Ok(Self::Finishing),
+            "completed" => Ok(Self::Completed),
+            "failed" => Ok(Self::Failed),
+            other => Err(format!("Unknown meeting status: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Meeting {
+    pub id: String,
+    /// Holds the transcript and, once written, the minutes.
+    pub conversation_id: String,
+    pub title: String,
+    pub status: MeetingStatus,
+    pub options: MeetingOptions,
+    /// Running summary, updated while the meeting goes on.
+    pub notes: Option<String>,
+    /// Markdown minutes, written when the meeting ends.
+    pub minutes: Option<String>,
+    pub error: Option<String>,
+    pub started_at: i64,
+    pub ended_at: Option<i64>,
+    pub updated_at: i64,
+}
+
+#[derive(sqlx::FromRow)]
+struct MeetingRow {
+    id: String,
+    conversation_id: String,
+    title: String,
+    status: String,
+    options: String,
+    notes: Option<String>,
+    minutes: Option<String>,
+    error: Option<String>,
+    started_at: i64,
+    ended_at: Option<i64>,
+    updated_at: i64,
+}
+
+impl TryFrom<MeetingRow> for Meeting {
+    type Error = String;
+
+    fn try_from(row: MeetingRow) -> Result<Self, Self::Error> {
+        Ok(Meeting {
+            options: serde_json::from_str(&row.options)
+                .map_err(|e| format!("Failed to parse meeting {}: {}", row.id, e))?,
+            id: row.id,
+            conversation_id: row.conversation_id,
+            title: row.title,
+            status: MeetingStatus::parse(&row.status)?,
+            notes: row.notes,
+            minutes: row.minutes,
+            error: row.error,
+            started_at: row.started_at,
+            ended_at: row.ended_at,
+            updated_at: row.updated_at,
+        })
+    }
+}
+
+pub(crate) async fn get(pool: &SqlitePool, id: &str) -> Result<Option<Meeting>, String> {
+    sqlx::query_as::<_, MeetingRow>(&format!(
+        "SELECT {} FROM meetings WHERE id = ?",
+        MEETING_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to load meeting: {}", e))?
+    .map(Meeting::try_from)
+    .transpose()
+}
+
+/// The meeting recorded into `conversation_id`, if any.
+pub(crate) async fn for_conversation(
+    pool: &SqlitePool,
+    conversation_id: &str,
+) -> Result<Option<Meeting>, String> {
+    sqlx::query_as::<_, MeetingRow>(&format!(
+        "SELECT {} FROM meetings WHERE conversation_id = ? ORDER BY started_at DESC LIMIT 1",
+        MEETING_COLUMNS
+    ))
+    .bind(conversation_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to load meeting: {}", e))?
+    .map(Meeting::try_from)
+    .transpose()
+}
+
+/// Meetings, newest first.
+pub(crate) async fn list(pool: &SqlitePool, limit: Option<u32>) -> Result<Vec<Meeting>, String> {
+    let limit = limit.unwrap_or(DEFAULT_LIST_LIMIT).clamp(1, MAX_LIST_LIMIT);
+    let rows = sqlx::query_as::<_, MeetingRow>(&format!(
+        "SELECT {} FROM meetings ORDER BY started_at DESC LIMIT ?",
+        MEETING_COLUMNS
+    ))
+    .bind(limit as i64)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to list meetings: {}", e))?;
+    rows.into_iter().map(Meeting::try_from).collect()
+}
+
+pub(crate) async fn insert(
+    pool: &SqlitePool,
+    id: &str,
+    conversation_id: &str,
+    title: &str,
+    options: &MeetingOptions,
+    started_at: i64,
+) -> Result<Meeting, String> {
+    let options = serde_json::to_string(options)
+        .map_err(|e| format!("Failed to serialize meeting options: {}", e))?;
+    sqlx::query(
+        "INSERT INTO meetings
+             (id, conversation_id, title, status, options, started_at, updated_at)
+         VALUES (?, ?, ?, 'recording', ?, ?, ?)",
+    )
+    .bind(id)
+    .bind(conversation_id)
+    .bind(title)
+    .bind(&options)
+    .bind(started_at)
+    .bind(started_at)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to save meeting: {}", e))?;
+    get(pool, id)
+        .await?
+        .ok_or_else(|| format!("Meeting not found: {}", id))
+}
+
+pub(crate) async fn set_notes(pool: &SqlitePool, id: &str, notes: &str) -> Result<(), String> {
+    sqlx::query("UPDATE meetings SET notes = ?, updated_at = ? WHERE id = ?")
+        .bind(notes)
+        .bind(crate::db::now_millis())
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to save meeting notes: {}", e))?;
+    Ok(())
+}
+
+/// Move meeting `id` to `status`. `ended_at` is set the first time it
+/// leaves `recording`; `minutes` and `error` are only overwritten when
+/// given.
+pub(crate) async fn set_status(
+    pool: &SqlitePool,
+    id: &str,
+    status: MeetingStatus,
+    minutes: Option<&str>,
+    error: Option<&str>,
+) -> Result<(), String> {
+    let now = crate::db::now_millis();
+    sqlx::query(
+        "UPDATE meetings
+         SET status = ?1,
+             minutes = COALESCE(?2, minutes),
+             error = COALESCE(?3, error),
+             ended_at = CASE WHEN ?1 = 'recording' THEN ended_at ELSE COALESCE(ended_at, ?4) END,
+             updated_at = ?4
+         WHERE id = ?5",
+    )
+    .bind(status.as_str())
+    .bind(minutes)
+    .bind(error)
+    .bind(now)
+    .bind(id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to update meeting: {}", e))?;
+    Ok(())
+}
+
+/// Fail meetings left recording or finishing by a previous run. Returns how
+/// many there were.
+pub(crate) async fn fail_interrupted(pool: &SqlitePool) -> Result<u64, String> {
+    let now = crate::db::now_millis();
+    let result = sqlx::query(
+        "UPDATE meetings
+         SET status = 'failed',
+             error = 'Interrupted before the minutes were written',
+             ended_at = COALESCE(ended_at, ?1),
+             updated_at = ?1
+         WHERE status IN ('recording', 'finishing')",
+    )
+    .bind(now)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to recover meetings: {}", e))?;
+    Ok(result.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::chat::{self, Conversation};
+    use crate::providers::ProviderKind;
+    use crate::stt::streaming::StreamingProvider;
+
+    fn options() -> MeetingOptions {
+        serde_json::from_value(serde_json::json!({
+            "sttProvider": "deepgram",
+            "summarizer": { "provider": "openai", "model": "gpt-4o-mini" }
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn interrupted_meetings_fail_and_keep_their_notes() {
+        let pool = crate::db::test_pool().await;
+        let conversation = Conversation {
+            id: "c1".to_string(),
+            title: "Standup".to_string(),
+            created_at: 1_000,
+            updated_at: 1_000,
+            pinned: false,
+            archived_at: None,
+            deleted_at: None,
+            messages: Vec::new(),
+        };
+        chat::create(&pool, conversation).await.unwrap();
+        let meeting = insert(&pool, "m1", "c1", "Standup", &options(), 1_000)
+            .await
+            .unwrap();
+        assert_eq!(meeting.status, MeetingStatus::Recording);
+        assert!(meeting.options.microphone && meeting.options.diarize);
+        assert_eq!(meeting.options.stt_provider, StreamingProvider::Deepgram);
+        assert_eq!(meeting.options.summarizer.provider, ProviderKind::OpenAi);
+
+        set_notes(&pool, "m1", "- Release on Friday").await.unwrap();
+        assert_eq!(fail_interrupted(&pool).await.unwrap(), 1);
+        let meeting = get(&pool, "m1").await.unwrap().unwrap();
+        assert_eq!(meeting.status, MeetingStatus::Failed);
+        assert_eq!(meeting.notes.as_deref(), Some("- Release on Friday"));
+        assert!(meeting.ended_at.is_some());
+        assert_eq!(fail_interrupted(&pool).await.unwrap(), 0);
+    }
+}
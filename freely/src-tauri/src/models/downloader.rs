@@ -0,0 +1,191 @@
+//! Resumable, verified file downloads.
+//!
+//! Bytes go to `<dest>.part` and the file is only renamed into place once it
+//! is complete and, when a checksum is known, matches it. An interrupted
+//! download leaves the `.part` file behind, and the next attempt asks the
+//! server for the remaining range instead of starting over.
+
+use reqwest::header::{CONTENT_RANGE, RANGE};
+use reqwest::StatusCode;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+/// Minimum bytes between two progress callbacks.
+pub(crate) const PROGRESS_STEP_BYTES: u64 = 512 * 1024;
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Progress {
+    pub downloaded_bytes: u64,
+    pub total_bytes: Option<u64>,
+}
+
+pub(crate) fn part_path(dest: &Path) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    dest.with_file_name(name)
+}
+
+/// Total size from a `Content-Range: bytes <start>-<end>/<total>` header.
+fn range_total(header: &str) -> Option<u64> {
+    header
+        .strip_prefix("bytes ")?
+        .split('/')
+        .nth(1)?
+        .parse()
+        .ok()
+}
+
+/// Hash what an earlier attempt already wrote, so the final digest covers
+/// the whole file.
+async fn hash_existing(path: &Path) -> Result<(Sha256, u64), String> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let mut hasher = Sha256::new();
+        let mut file = match std::fs::File::open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((hasher, 0)),
+            Err(e) => return Err(format!("Failed to read partial download: {}", e)),
+        };
+        let len = std::io::copy(&mut file, &mut hasher)
+            .map_err(|e| format!("Failed to read partial download: {}", e))?;
+        Ok((hasher, len))
+    })
+    .await
+    .map_err(|e| format!("Failed to read partial download: {}", e))?
+}
+
+/// Download `url` to `dest`, resuming a partial download if one exists, and
+/// return the file's SHA-256 as lowercase hex. With `sha256` set, a file that
+/// doesn't match is deleted and the download fails. `on_progress` is called
+/// every [`PROGRESS_STEP_BYTES`]; an error from it stops the download and
+/// keeps what was fetched for next time.
+pub(crate) async fn download(
+    url: &str,
+    dest: &Path,
+    sha256: Option<&str>,
+    mut on_progress: impl FnMut(Progress) -> Result<(), String>,
+) -> Result<String, String> {
+    if let Some(dir) = dest.parent() {
+        tokio::fs::create_dir_all(dir)
+            .await
+            .map_err(|e| format!("Failed to create models directory: {}", e))?;
+    }
+    crate::net::connectivity::require_online()?;
+    let part = part_path(dest);
+    let client = crate::net::client::http_client(None)?;
+
+    let (mut hasher, mut offset) = hash_existing(&part).await?;
+    let mut response = loop {
+        let mut request = client.get(url);
+        if offset > 0 {
+            request = request.header(RANGE, format!("bytes={}-", offset));
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("Failed to download model: {}", e))?;
+
+        match response.status() {
+            // The partial file is as long as the remote one or longer;
+            // it can't be trusted, so start over
+            StatusCode::RANGE_NOT_SATISFIABLE if offset > 0 => {
+                (hasher, offset) = (Sha256::new(), 0);
+                let _ = tokio::fs::remove_file(&part).await;
+            }
+            _ => {
+                break response
+                    .error_for_status()
+                    .map_err(|e| format!("Failed to download model: {}", e))?
+            }
+        }
+    };
+
+    let resumed = offset > 0 && response.status() == StatusCode::PARTIAL_CONTENT;
+    let total_bytes = if resumed {
+        response
+            .headers()
+            .get(CONTENT_RANGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(range_total)
+            .or_else(|| response.content_length().map(|len| len + offset))
+    } else {
+        // The server ignored the range and is sending the whole file
+        (hasher, offset) = (Sha256::new(), 0);
+        response.content_length()
+    };
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resumed)
+        .truncate(!resumed)
+        .open(&part)
+        .await
+        .map_err(|e| format!("Failed to create model file: {}", e))?;
+
+    let mut downloaded_bytes = offset;
+    let mut last_reported = offset;
+    while let Some(chunk) = response.chunk().await.transpose() {
+        let chunk = chunk.map_err(|e| format!("Model download interrupted: {}", e))?;
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| format!("Failed to write model file: {}", e))?;
+        hasher.update(&chunk);
+        downloaded_bytes += chunk.len() as u64;
+
+        if downloaded_bytes - last_reported >= PROGRESS_STEP_BYTES {
+            last_reported = downloaded_bytes;
+            on_progress(Progress {
+                downloaded_bytes,
+                total_bytes,
+            })?;
+        }
+    }
+    file.flush()
+        .await
+        .map_err(|e| format!("Failed to write model file: {}", e))?;
+    drop(file);
+
+    if let Some(total) = total_bytes {
+        if downloaded_bytes != total {
+            return Err(format!(
+                "Model download incomplete: got {} of {} bytes",
+                downloaded_bytes, total
+            ));
+        }
+    }
+
+    let digest = format!("{:x}", hasher.finalize());
+    if let Some(expected) = sha256 {
+        if !digest.eq_ignore_ascii_case(expected.trim()) {
+            let _ = tokio::fs::remove_file(&part).await;
+            return Err(format!(
+                "Model checksum mismatch: expected {}, got {}",
+                expected.trim(),
+                digest
+            ));
+        }
+    }
+
+    tokio::fs::rename(&part, dest)
+        .await
+        .map_err(|e| format!("Failed to finalize model file: {}", e))?;
+    Ok(digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_range_gives_the_full_size() {
+        assert_eq!(range_total("bytes 100-199/200"), Some(200));
+        assert_eq!(range_total("bytes 100-199/*"), None);
+        assert_eq!(range_total("items 0-1/2"), None);
+        assert_eq!(
+            part_path(Path::new("/models/gguf/llama.gguf")),
+            Path::new("/models/gguf/llama.gguf.part")
+        );
+    }
+}
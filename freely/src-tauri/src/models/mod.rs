@@ -0,0 +1,237 @@
+//! Model files kept on disk under `<app_local_data_dir>/models`.
+//!
+//! Each [`ModelKind`] has its own subdirectory: Whisper ggml files (also
+//! managed by `stt::local`), local embedding models and GGUF language
+//! models. Downloads run as background jobs through [`downloader`], which
+//! resumes interrupted transfers and checks the SHA-256 when one is given,
+//! emitting `model-download-progress` as bytes arrive.
+
+pub(crate) mod downloader;
+
+use crate::jobs::store::Job;
+use crate::jobs::{self, JobSpec};
+use downloader::Progress;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::warn;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelKind {
+    Whisper,
+    Embedding,
+    Gguf,
+}
+
+impl ModelKind {
+    pub const ALL: [ModelKind; 3] = [Self::Whisper, Self::Embedding, Self::Gguf];
+
+    pub(crate) fn dir_name(self) -> &'static str {
+        match self {
+            Self::Whisper => "whisper",
+            Self::Embedding => "embeddings",
+            Self::Gguf => "gguf",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalModel {
+    pub kind: ModelKind,
+    pub filename: String,
+    pub path: String,
+    pub size_bytes: u64,
+    pub modified_at: Option<i64>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ModelDownloadProgress<'a> {
+    kind: ModelKind,
+    filename: &'a str,
+    downloaded_bytes: u64,
+    total_bytes: Option<u64>,
+}
+
+/// Directory holding models of `kind`.
+pub(crate) fn models_dir(app: &AppHandle, kind: ModelKind) -> Result<PathBuf, String> {
+    let data_dir = app
+        .path()
+        .app_local_data_dir()
+        .map_err(|e| format!("Could not resolve app_local_data_dir: {}", e))?;
+    Ok(data_dir.join("models").join(kind.dir_name()))
+}
+
+/// A bare file name, so a model can't be written or deleted outside its
+/// directory.
+pub(crate) fn validate_filename(filename: &str) -> Result<&str, String> {
+    let filename = filename.trim();
+    let plain = Path::new(filename)
+        .file_name()
+        .is_some_and(|name| name == filename);
+    if !plain || filename.starts_with('.') {
+        return Err(format!("Invalid model file name: {}", filename));
+    }
+    if filename.ends_with(".part") {
+        return Err("Model file names cannot end in .part".to_string());
+    }
+    Ok(filename)
+}
+
+/// The last path segment of `url`, without query or fragment.
+fn filename_from_url(url: &str) -> Option<&str> {
+    let path = url.split(['?', '#']).next()?;
+    path.rsplit('/').next().filter(|name| !name.is_empty())
+}
+
+fn local_model(kind: ModelKind, path: &Path) -> Option<LocalModel> {
+    let metadata = std::fs::metadata(path).ok().filter(|m| m.is_file())?;
+    let modified_at = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as i64);
+    Some(LocalModel {
+        kind,
+        filename: path.file_name()?.to_string_lossy().into_owned(),
+        path: path.to_string_lossy().into_owned(),
+        size_bytes: metadata.len(),
+        modified_at,
+    })
+}
+
+/// Complete model files in `dir`, sorted by name. Partial downloads are
+/// left out.
+fn list_in(dir: &Path, kind: ModelKind) -> Result<Vec<LocalModel>, String> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read models directory: {}", e)),
+    };
+    let mut models: Vec<LocalModel> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_none_or(|ext| ext != "part"))
+        .filter_map(|path| local_model(kind, &path))
+        .collect();
+    models.sort_by(|a, b| a.filename.cmp(&b.filename));
+    Ok(models)
+}
+
+/// Download `url` as `filename` into the directory for `kind`, emitting
+/// `model-download-progress` and passing progress on to `on_progress`.
+pub(crate) async fn download(
+    app: &AppHandle,
+    kind: ModelKind,
+    url: &str,
+    filename: &str,
+    sha256: Option<&str>,
+    mut on_progress: impl FnMut(Progress) -> Result<(), String>,
+) -> Result<LocalModel, String> {
+    let filename = validate_filename(filename)?;
+    let dest = models_dir(app, kind)?.join(filename);
+    downloader::download(url, &dest, sha256, |progress| {
+        let event = ModelDownloadProgress {
+            kind,
+            filename,
+            downloaded_bytes: progress.downloaded_bytes,
+            total_bytes: progress.total_bytes,
+        };
+        if let Err(e) = app.emit("model-download-progress", event) {
+            warn!("Failed to emit download progress: {}", e);
+        }
+        on_progress(progress)
+    })
+    .await?;
+    local_model(kind, &dest).ok_or_else(|| format!("Downloaded model is missing: {}", filename))
+}
+
+#[tauri::command]
+pub fn list_local_models(
+    app: AppHandle,
+    kind: Option<ModelKind>,
+) -> Result<Vec<LocalModel>, String> {
+    let kinds = kind.map_or(ModelKind::ALL.to_vec(), |kind| vec![kind]);
+    let mut models = Vec::new();
+    for kind in kinds {
+        models.extend(list_in(&models_dir(&app, kind)?, kind)?);
+    }
+    Ok(models)
+}
+
+/// Queue a download of `url`. `filename` defaults to the last segment of
+/// the URL; with `sha256` the file is only kept if it matches.
+#[tauri::command]
+pub async fn download_local_model(
+    app: AppHandle,
+    kind: ModelKind,
+    url: String,
+    filename: Option<String>,
+    sha256: Option<String>,
+) -> Result<Job, String> {
+    let url = url.trim().to_string();
+    if !url.starts_with("https://") && !url.starts_with("http://") {
+        return Err(format!("Invalid model URL: {}", url));
+    }
+    let filename = match filename.as_deref().or_else(|| filename_from_url(&url)) {
+        Some(filename) => validate_filename(filename)?.to_string(),
+        None => return Err("Model file name is required for this URL".to_string()),
+    };
+    let spec = JobSpec::DownloadModel {
+        kind,
+        url,
+        filename,
+        sha256: sha256.filter(|hash| !hash.trim().is_empty()),
+    };
+    jobs::enqueue(&app, spec).await
+}
+
+/// Delete a model file along with any partial download of it.
+#[tauri::command]
+pub fn delete_local_model(app: AppHandle, kind: ModelKind, filename: String) -> Result<(), String> {
+    let path = models_dir(&app, kind)?.join(validate_filename(&filename)?);
+    if kind == ModelKind::Whisper {
+        crate::stt::local::unload_if_loaded(&app, &path);
+    }
+    for path in [downloader::part_path(&path), path] {
+        match std::fs::remove_file(&path) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("Failed to delete model: {}", e)),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn model_files_stay_in_their_directory() {
+        assert!(validate_filename("llama-3.2-1b.Q4_K_M.gguf").is_ok());
+        assert!(validate_filename("../secrets.db").is_err());
+        assert!(validate_filename("sub/model.bin").is_err());
+        assert!(validate_filename(".hidden").is_err());
+        assert!(validate_filename("model.gguf.part").is_err());
+        assert_eq!(
+            filename_from_url("https://huggingface.co/x/y/resolve/main/m.gguf?download=true"),
+            Some("m.gguf")
+        );
+        assert_eq!(filename_from_url("https://example.com/"), None);
+
+        let dir = std::env::temp_dir().join(format!("freely-models-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("b.gguf"), b"bb").unwrap();
+        std::fs::write(dir.join("a.gguf"), b"a").unwrap();
+        std::fs::write(dir.join("c.gguf.part"), b"c").unwrap();
+        let models = list_in(&dir, ModelKind::Gguf).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let names: Vec<_> = models.iter().map(|m| m.filename.as_str()).collect();
+        assert_eq!(names, ["a.gguf", "b.gguf"]);
+        assert_eq!(models[1].size_bytes, 2);
+    }
+}
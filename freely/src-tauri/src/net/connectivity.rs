@@ -0,0 +1,190 @@
+//! Whether Freely can reach the internet.
+//!
+//! A monitor sends a small request through the shared client every
+//! [`ONLINE_INTERVAL`] (more often while offline), so the proxy and TLS
+//! settings apply and a network that only lets the proxy out still counts
+//! as online. Any HTTP answer means online. Changes are broadcast as
+//! `connectivity-changed`.
+//!
+//! While offline, requests to remote providers fail fast with
+//! [`OFFLINE_ERROR`] instead of waiting for a timeout. Loopback endpoints
+//! (Ollama, LM Studio, in-process models) keep working, as does everything
+//! that never leaves the machine. Background jobs that hit the offline
+//! error are put back in the queue and resume once the network returns.
+
+use crate::providers::ProviderKind;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tracing::{info, warn};
+
+/// Start of every error returned while offline; see [`is_offline_error`].
+pub(crate) const OFFLINE_ERROR: &str = "Freely is offline";
+
+const ONLINE_INTERVAL: Duration = Duration::from_secs(30);
+const OFFLINE_INTERVAL: Duration = Duration::from_secs(5);
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Tried in order until one answers. Both return tiny bodies and are
+/// served from many regions.
+const CHECK_URLS: &[&str] = &[
+    "https://www.gstatic.com/generate_204",
+    "https://cloudflare.com/cdn-cgi/trace",
+];
+
+static ONLINE: AtomicBool = AtomicBool::new(true);
+static LAST_CHECKED_AT: AtomicI64 = AtomicI64::new(0);
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Connectivity {
+    pub online: bool,
+    /// When the monitor last checked, in milliseconds; `None` before the
+    /// first check.
+    pub last_checked_at: Option<i64>,
+}
+
+fn current() -> Connectivity {
+    let checked = LAST_CHECKED_AT.load(Ordering::Relaxed);
+    Connectivity {
+        online: is_online(),
+        last_checked_at: (checked > 0).then_some(checked),
+    }
+}
+
+pub(crate) fn is_online() -> bool {
+    ONLINE.load(Ordering::Relaxed)
+}
+
+pub(crate) fn is_offline_error(error: &str) -> bool {
+    error.contains(OFFLINE_ERROR)
+}
+
+/// Whether `url` points at this machine.
+pub(crate) fn is_loopback_url(url: &str) -> bool {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host_port = authority.rsplit('@').next().unwrap_or_default();
+    let host = match host_port.strip_prefix('[') {
+        Some(bracketed) => bracketed.split(']').next().unwrap_or_default(),
+        None => host_port.split(':').next().unwrap_or_default(),
+    };
+    host.eq_ignore_ascii_case("localhost")
+        || host
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback())
+}
+
+/// `Err` when offline, for work that needs the network to `?` on.
+pub(crate) fn require_online() -> Result<(), String> {
+    if is_online() {
+        return Ok(());
+    }
+    Err(format!(
+        "{}: cloud providers are unavailable until the connection is back",
+        OFFLINE_ERROR
+    ))
+}
+
+/// [`require_online`] unless `kind` at `base_url` runs on this machine.
+pub(crate) fn require_online_for(kind: ProviderKind, base_url: Option<&str>) -> Result<(), String> {
+    let local = match base_url {
+        _ if kind == ProviderKind::LlamaLocal => true,
+        Some(url) => is_loopback_url(url),
+        None => kind.is_local(),
+    };
+    if local {
+        return Ok(());
+    }
+    require_online()
+}
+
+async fn probe() -> bool {
+    let Ok(client) = super::client::http_client(None) else {
+        return false;
+    };
+    for url in CHECK_URLS {
+        if client
+            .head(*url)
+            .timeout(CHECK_TIMEOUT)
+            .send()
+            .await
+            .is_ok()
+        {
+            return true;
+        }
+    }
+    false
+}
+
+/// Check now, record the result and announce a change.
+async fn check(app: &AppHandle) -> Connectivity {
+    let online = probe().await;
+    LAST_CHECKED_AT.store(crate::db::now_millis(), Ordering::Relaxed);
+    let was_online = ONLINE.swap(online, Ordering::Relaxed);
+    let status = current();
+    if online != was_online {
+        info!(
+            "Connectivity changed: {}",
+            if online { "online" } else { "offline" }
+        );
+        if let Err(e) = app.emit("connectivity-changed", status) {
+            warn!("Failed to emit connectivity change: {}", e);
+        }
+        if online {
+            crate::jobs::resume_waiting();
+        }
+    }
+    status
+}
+
+/// Keep checking connectivity for the life of the app. Called once from
+/// `setup`.
+pub fn start_connectivity_monitor(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let status = check(&app).await;
+            let interval = if status.online {
+                ONLINE_INTERVAL
+            } else {
+                OFFLINE_INTERVAL
+            };
+            tokio::time::sleep(interval).await;
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_connectivity() -> Connectivity {
+    current()
+}
+
+/// Check right away instead of waiting for the monitor, e.g. from a retry
+/// button.
+#[tauri::command]
+pub async fn check_connectivity(app: AppHandle) -> Result<Connectivity, String> {
+    Ok(check(&app).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loopback_endpoints_work_offline() {
+        assert!(is_loopback_url("http://localhost:11434"));
+        assert!(is_loopback_url("http://127.0.0.1:1234/v1"));
+        assert!(is_loopback_url("http://[::1]:8000/v1"));
+        assert!(is_loopback_url("http://user:pw@LOCALHOST/v1"));
+        assert!(!is_loopback_url("https://api.openai.com/v1"));
+        assert!(!is_loopback_url("http://192.168.1.20:11434"));
+        assert!(!is_loopback_url("https://localhost.example.com"));
+
+        assert!(is_offline_error(&format!(
+            "Failed to embed: {}: no network",
+            OFFLINE_ERROR
+        )));
+        assert!(!is_offline_error("Provider returned 503"));
+    }
+}
@@ -0,0 +1,9 @@
+//! Outbound networking shared by every module that talks HTTP.
+//!
+//! [`client`] builds the reqwest clients used for providers, embeddings,
+//! MCP servers and model downloads, so proxy and TLS settings apply to all
+//! of them. [`connectivity`] tracks whether the internet is reachable, so
+//! cloud requests fail fast while offline and queued jobs wait it out.
+
+pub mod client;
+pub mod connectivity;
@@ -0,0 +1,142 @@
+//! Native notifications, with action buttons where the platform allows.
+//!
+//! On Linux a notification goes straight to the desktop's notification
+//! server, so it can carry buttons: pressing one runs its
+//! [`NotificationAction`], and clicking the notification itself opens the
+//! dashboard. The notification plugin can't report clicks on macOS or
+//! Windows, so there only the title and body are shown.
+//!
+//! Running an action opens the dashboard and emits `notification-action`
+//! with the action, for the dashboard to navigate to whatever the
+//! notification was about. `run_notification_action` runs the same actions
+//! from the frontend, e.g. for an in-app copy of a notification.
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use tracing::warn;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum NotificationAction {
+    #[serde(rename_all = "camelCase")]
+    OpenConversation {
+        conversation_id: String,
+    },
+    /// Download the available update and install it when the app quits.
+    InstallUpdate,
+    ShowJobs,
+    /// Record the calendar event as a meeting with the saved calendar
+    /// meeting options.
+    #[serde(rename_all = "camelCase")]
+    StartCalendarMeeting {
+        event_id: String,
+    },
+}
+
+impl NotificationAction {
+    #[cfg(target_os = "linux")]
+    fn label(&self) -> &'static str {
+        match self {
+            Self::OpenConversation { .. } => "Open conversation",
+            Self::InstallUpdate => "Install on quit",
+            Self::ShowJobs => "Show jobs",
+            Self::StartCalendarMeeting { .. } => "Start recording",
+        }
+    }
+}
+
+/// Open the dashboard and run `action`, if any.
+async fn activate(app: &AppHandle, action: Option<&NotificationAction>) -> Result<(), String> {
+    crate::window::show_dashboard_window(app)?;
+    let Some(action) = action else {
+        return Ok(());
+    };
+    if *action == NotificationAction::InstallUpdate {
+        crate::updater::download_update(app.clone()).await?;
+        crate::updater::install_on_quit(Some(true)).await?;
+    }
+    if let NotificationAction::StartCalendarMeeting { event_id } = action {
+        crate::integrations::calendar::start_meeting_for(app, event_id, None).await?;
+    }
+    app.emit("notification-action", action)
+        .map_err(|e| format!("Failed to emit notification action: {}", e))
+}
+
+#[cfg(target_os = "linux")]
+fn show_native(
+    app: &AppHandle,
+    title: &str,
+    body: &str,
+    actions: Vec<NotificationAction>,
+) -> Result<(), String> {
+    let mut notification = notify_rust::Notification::new();
+    notification
+        .appname("Freely")
+        .summary(title)
+        .body(body)
+        .auto_icon()
+        .action("default", "Open");
+    for (index, action) in actions.iter().enumerate() {
+        notification.action(&index.to_string(), action.label());
+    }
+    let app = app.clone();
+    // Both sending and waiting for a click block on D-Bus
+    std::thread::spawn(move || {
+        let handle = match notification.show() {
+            Ok(handle) => handle,
+            Err(e) => {
+                warn!("Failed to show notification: {}", e);
+                return;
+            }
+        };
+        handle.wait_for_action(|key| {
+            let action = match key {
+                "default" => None,
+                key => match key.parse::<usize>().ok().and_then(|i| actions.get(i)) {
+                    Some(action) => Some(action.clone()),
+                    // Dismissed, or closed by the server
+                    None => return,
+                },
+            };
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = activate(&app, action.as_ref()).await {
+                    warn!("Failed to run notification action: {}", e);
+                }
+            });
+        });
+    });
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn show_native(
+    app: &AppHandle,
+    title: &str,
+    body: &str,
+    _actions: Vec<NotificationAction>,
+) -> Result<(), String> {
+    use tauri_plugin_notification::NotificationExt;
+
+    app.notification()
+        .builder()
+        .title(title)
+        .body(body)
+        .show()
+        .map_err(|e| format!("Failed to show notification: {}", e))
+}
+
+/// Show a notification offering `actions`. Failures are logged, since a
+/// missing notification shouldn't fail the work it reports on.
+pub(crate) fn show(app: &AppHandle, title: &str, body: &str, actions: Vec<NotificationAction>) {
+    if let Err(e) = show_native(app, title, body, actions) {
+        warn!("{}", e);
+    }
+}
+
+#[tauri::command]
+pub async fn run_notification_action(
+    app: AppHandle,
+    action: NotificationAction,
+) -> Result<(), String> {
+    activate(&app, Some(&action)).await
+}
@@ -0,0 +1,324 @@
+//! Text extraction from screenshots.
+//!
+//! On macOS the Vision framework is used, which needs no extra install. On
+//! other platforms, or if Vision fails, the `tesseract` CLI is run with TSV
+//! output so per-word confidences can be averaged. Sending the recognized
+//! text instead of the image is much cheaper for providers that bill images
+//! by tile.
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::process::Command;
+
+/// Where the image comes from: a file on disk (e.g. from `capture_screen`
+/// with file output) or base64 image bytes.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum OcrSource {
+    Path(String),
+    Base64(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OcrEngine {
+    Vision,
+    Tesseract,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OcrResult {
+    pub text: String,
+    pub engine: OcrEngine,
+    /// Mean confidence in [0, 1], when the engine reports one.
+    pub confidence: Option<f32>,
+}
+
+/// Temp copy of base64 input; both engines read images from disk.
+struct TempImage(PathBuf);
+
+impl Drop for TempImage {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn write_temp_image(image_b64: &str) -> Result<TempImage, String> {
+    // Accept data URLs as produced by the frontend
+    let data = image_b64
+        .split_once(";base64,")
+        .map(|(_, data)| data)
+        .unwrap_or(image_b64);
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(data.trim())
+        .map_err(|e| format!("Base64 decode error: {}", e))?;
+
+    let path = std::env::temp_dir().join(format!("freely-ocr-{}.img", uuid::Uuid::new_v4()));
+    std::fs::write(&path, bytes).map_err(|e| format!("Failed to write temp image: {}", e))?;
+    Ok(TempImage(path))
+}
+
+/// Language codes are passed straight to the engine, so keep them to the
+/// characters Tesseract (`chi_sim`) and Vision (`en-US`) actually use.
+fn validate_languages(languages: &[String]) -> Result<(), String> {
+    for lang in languages {
+        let valid = !lang.is_empty()
+            && lang
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !valid {
+            return Err(format!("Invalid OCR language: {}", lang));
+        }
+    }
+    Ok(())
+}
+
+/// Rebuild text from `tesseract ... tsv` output, one line per detected text
+/// line, plus the mean word confidence.
+pub(crate) fn parse_tesseract_tsv(tsv: &str) -> (String, Option<f32>) {
+    let mut lines: Vec<String> = Vec::new();
+    let mut current_line: Option<(u32, u32, u32, u32)> = None;
+    let mut confidences: Vec<f32> = Vec::new();
+
+    for row in tsv.lines().skip(1) {
+        let cols: Vec<&str> = row.splitn(12, '\t').collect();
+        if cols.len() < 12 || cols[0] != "5" {
+            continue;
+        }
+        let word = cols[11].trim();
+        if word.is_empty() {
+            continue;
+        }
+
+        let key = (
+            cols[1].parse().unwrap_or(0),
+            cols[2].parse().unwrap_or(0),
+            cols[3].parse().unwrap_or(0),
+            cols[4].parse().unwrap_or(0),
+        );
+        if current_line != Some(key) {
+            // A new paragraph or block gets a blank line before it
+            if let Some(prev) = current_line {
+                if (prev.0, prev.1, prev.2) != (key.0, key.1, key.2) {
+                    lines.push(String::new());
+                }
+            }
+            lines.push(String::new());
+            current_line = Some(key);
+        }
+
+        let line = lines.last_mut().expect("line pushed above");
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+
+        if let Ok(conf) = cols[10].parse::<f32>() {
+            if conf >= 0.0 {
+                confidences.push(conf / 100.0);
+            }
+        }
+    }
+
+    let confidence = if confidences.is_empty() {
+        None
+    } else {
+        Some(confidences.iter().sum::<f32>() / confidences.len() as f32)
+    };
+    (lines.join("\n"), confidence)
+}
+
+async fn resolve_tesseract() -> Result<String, String> {
+    #[cfg(target_os = "windows")]
+    {
+        let default = r"C:\Program Files\Tesseract-OCR\tesseract.exe";
+        if Path::new(default).exists() {
+            return Ok(default.to_string());
+        }
+    }
+
+    crate::agents::resolve_binary("tesseract")
+        .await
+        .map_err(|_| {
+            "Tesseract is not installed or not on PATH. Install it to enable OCR \
+         (e.g. `brew install tesseract`, `apt install tesseract-ocr`)"
+                .to_string()
+        })
+}
+
+async fn recognize_with_tesseract(path: &Path, languages: &[String]) -> Result<OcrResult, String> {
+    let binary = resolve_tesseract().await?;
+
+    let mut cmd = Command::new(binary);
+    cmd.arg(path).arg("stdout");
+    if !languages.is_empty() {
+        cmd.arg("-l").arg(languages.join("+"));
+    }
+    cmd.arg("tsv")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let output = cmd
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run tesseract: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "Tesseract failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let (text, confidence) = parse_tesseract_tsv(&String::from_utf8_lossy(&output.stdout));
+    Ok(OcrResult {
+        text,
+        engine: OcrEngine::Tesseract,
+        confidence,
+    })
+}
+
+#[cfg(target_os = "macos")]
+fn recognize_with_vision(path: &Path, languages: &[String]) -> Result<OcrResult, String> {
+    use cidre::{arc, ns, objc, vn};
+
+    let path_str = path.to_str().ok_or("Image path is not valid UTF-8")?;
+
+    objc::ar_pool(|| {
+        let url = ns::Url::with_fs_path_str(path_str, false);
+        let mut request = vn::RecognizeTextRequest::new();
+        request.set_recognition_level(vn::RequestTextRecognitionLevel::Accurate);
+        request.set_uses_lang_correction(true);
+        if !languages.is_empty() {
+            let langs: Vec<arc::R<ns::String>> =
+                languages.iter().map(|l| ns::String::with_str(l)).collect();
+            request.set_recognition_langs(&ns::Array::from_slice_retained(&langs));
+        }
+
+        let handler = vn::ImageRequestHandler::with_url(&url, None);
+        let requests: &[&vn::Request] = &[&request];
+        handler
+            .perform(&ns::Array::from_slice(requests))
+            .map_err(|e| {
+                let reason = e.localized_description().to_string();
+                format!("Vision text recognition failed: {}", reason)
+            })?;
+
+        let mut lines = Vec::new();
+        let mut confidences = Vec::new();
+        if let Some(results) = request.results() {
+            for observation in results.iter() {
+                let candidates = observation.top_candidates(1);
+                if let Some(candidate) = candidates.first() {
+                    lines.push(candidate.string().to_string());
+                    confidences.push(candidate.confidence());
+                }
+            }
+        }
+
+        let confidence = if confidences.is_empty() {
+            None
+        } else {
+            Some(confidences.iter().sum::<f32>() / confidences.len() as f32)
+        };
+        Ok(OcrResult {
+            text: lines.join("\n"),
+            engine: OcrEngine::Vision,
+            confidence,
+        })
+    })
+}
+
+/// Extract text from an image, e.g. a screenshot, so it can be added to a
+/// prompt as text. `languages` are engine language codes; empty means the
+/// engine default (English for Tesseract, automatic for Vision).
+#[tauri::command]
+pub async fn extract_text_from_image(
+    source: OcrSource,
+    languages: Option<Vec<String>>,
+) -> Result<OcrResult, String> {
+    let languages = languages.unwrap_or_default();
+    validate_languages(&languages)?;
+
+    let (path, _temp) = match source {
+        OcrSource::Path(path) => {
+            let path = PathBuf::from(path);
+            if !path.is_file() {
+                return Err(format!("Image not found: {}", path.display()));
+            }
+            (path, None)
+        }
+        OcrSource::Base64(data) => {
+            let temp = write_temp_image(&data)?;
+            (temp.0.clone(), Some(temp))
+        }
+    };
+
+    #[cfg(target_os = "macos")]
+    {
+        let vision_path = path.clone();
+        let vision_langs = languages.clone();
+        let vision =
+            tokio::task::spawn_blocking(move || recognize_with_vision(&vision_path, &vision_langs))
+                .await
+                .map_err(|e| format!("OCR task failed: {}", e))?;
+
+        match vision {
+            Ok(result) => return Ok(result),
+            Err(e) => tracing::warn!("{}; falling back to tesseract", e),
+        }
+    }
+
+    recognize_with_tesseract(&path, &languages).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str =
+        "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext";
+
+    #[test]
+    fn tsv_groups_words_into_lines_and_paragraphs() {
+        let tsv = [
+            HEADER,
+            "1\t1\t0\t0\t0\t0\t0\t0\t800\t600\t-1\t",
+            "5\t1\t1\t1\t1\t1\t10\t10\t50\t20\t90\tHello",
+            "5\t1\t1\t1\t1\t2\t70\t10\t50\t20\t80\tworld",
+            "5\t1\t1\t1\t2\t1\t10\t40\t50\t20\t70\tagain",
+            "5\t1\t2\t1\t1\t1\t10\t90\t50\t20\t60\tNext",
+            "5\t1\t2\t1\t1\t2\t70\t90\t50\t20\t-1\t ",
+        ]
+        .join("\n");
+
+        let (text, confidence) = parse_tesseract_tsv(&tsv);
+        assert_eq!(text, "Hello world\nagain\n\nNext");
+        assert!((confidence.unwrap() - 0.75).abs() < 1e-6);
+    }
+
+    #[test]
+    fn tsv_without_words_is_empty() {
+        assert_eq!(parse_tesseract_tsv(HEADER), (String::new(), None));
+    }
+
+    #[test]
+    fn languages_reject_argument_injection() {
+        assert!(validate_languages(&["eng".into(), "chi_sim".into(), "en-US".into()]).is_ok());
+        assert!(validate_languages(&["eng --psm 0".into()]).is_err());
+        assert!(validate_languages(&[String::new()]).is_err());
+    }
+
+    #[test]
+    fn base64_input_accepts_data_urls() {
+        let temp = write_temp_image("data:image/png;base64,aGVsbG8=").unwrap();
+        assert_eq!(std::fs::read(&temp.0).unwrap(), b"hello");
+        let path = temp.0.clone();
+        drop(temp);
+        assert!(!path.exists());
+    }
+}
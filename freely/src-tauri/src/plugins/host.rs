@@ -0,0 +1,546 @@
+//! A running plugin process and the JSON-RPC conversation with it.
+//!
+//! Messages are newline-delimited JSON-RPC 2.0 on stdin/stdout, as with MCP
+//! stdio servers. Freely sends `initialize` (answered with the plugin's
+//! tools and commands), `tools/call`, `commands/run` and a final `shutdown`
+//! notification. The plugin may call back into the host with `fs/read`,
+//! `fs/write`, `fs/list` and `http/fetch`, each checked against its
+//! [`Sandbox`], and send `log` notifications.
+//!
+//! The process starts in the plugin directory with an environment emptied
+//! down to [`INHERITED_ENV`], so API keys in Freely's environment don't
+//! leak to it. That is not an OS sandbox: the manifest governs what the
+//! host does on a plugin's behalf, and enabling a plugin still means
+//! trusting its code.
+
+use super::manifest::{PluginManifest, Sandbox};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, Command};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{info, warn};
+
+pub(crate) const PROTOCOL_VERSION: u32 = 1;
+const START_TIMEOUT: Duration = Duration::from_secs(30);
+const CALL_TIMEOUT: Duration = Duration::from_secs(120);
+const STOP_GRACE: Duration = Duration::from_secs(1);
+const MAX_FILE_BYTES: u64 = 10 * 1024 * 1024;
+const MAX_RESPONSE_BYTES: usize = 10 * 1024 * 1024;
+const MAX_REDIRECTS: usize = 5;
+/// Variables passed through to plugin processes; everything else is cleared.
+const INHERITED_ENV: &[&str] = &["PATH", "LANG", "TMPDIR", "TEMP", "TMP", "SYSTEMROOT"];
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const REQUEST_FAILED: i64 = -32000;
+const PERMISSION_DENIED: i64 = -32001;
+
+type RpcError = (i64, String);
+type Pending = Arc<Mutex<HashMap<u64, oneshot::Sender<Result<Value, String>>>>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginTool {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// JSON Schema of the tool's arguments.
+    #[serde(default)]
+    pub input_schema: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginCommand {
+    pub name: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct InitializeResult {
+    #[serde(default)]
+    tools: Vec<PluginTool>,
+    #[serde(default)]
+    commands: Vec<PluginCommand>,
+}
+
+pub(crate) struct PluginProcess {
+    pub(crate) id: String,
+    pub(crate) tools: Vec<PluginTool>,
+    pub(crate) commands: Vec<PluginCommand>,
+    stdin: mpsc::UnboundedSender<String>,
+    pending: Pending,
+    next_id: AtomicU64,
+    exited: Arc<AtomicBool>,
+    child: tokio::sync::Mutex<Child>,
+}
+
+fn response_result(message: &Value) -> Result<Value, String> {
+    if let Some(error) = message.get("error") {
+        return Err(format!(
+            "{} (code {})",
+            error["message"].as_str().unwrap_or("unknown error"),
+            error["code"]
+        ));
+    }
+    Ok(message.get("result").cloned().unwrap_or(Value::Null))
+}
+
+impl PluginProcess {
+    pub(crate) fn is_running(&self) -> bool {
+        !self.exited.load(Ordering::SeqCst)
+    }
+
+    fn not_running(&self) -> String {
+        format!("Plugin {} is not running", self.id)
+    }
+
+    async fn request(
+        &self,
+        method: &str,
+        params: Value,
+        timeout: Duration,
+    ) -> Result<Value, String> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (sender, receiver) = oneshot::channel();
+        self.pending.lock().insert(id, sender);
+        // Checked after registering, so an exit can't slip in between
+        if !self.is_running() {
+            self.pending.lock().remove(&id);
+            return Err(self.not_running());
+        }
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        if self.stdin.send(message.to_string()).is_err() {
+            self.pending.lock().remove(&id);
+            return Err(self.not_running());
+        }
+        match tokio::time::timeout(timeout, receiver).await {
+            Ok(Ok(result)) => result.map_err(|e| format!("Plugin {}: {}", self.id, e)),
+            Ok(Err(_)) => Err(self.not_running()),
+            Err(_) => {
+                self.pending.lock().remove(&id);
+                Err(format!(
+                    "Plugin {} did not respond within {}s",
+                    self.id,
+                    timeout.as_secs()
+                ))
+            }
+        }
+    }
+
+    pub(crate) async fn call_tool(&self, name: &str, arguments: Value) -> Result<Value, String> {
+        if !self.tools.iter().any(|tool| tool.name == name) {
+            return Err(format!("Plugin {} has no tool {}", self.id, name));
+        }
+        self.request(
+            "tools/call",
+            json!({ "name": name, "arguments": arguments }),
+            CALL_TIMEOUT,
+        )
+        .await
+    }
+
+    pub(crate) async fn run_command(&self, name: &str, args: Value) -> Result<Value, String> {
+        if !self.commands.iter().any(|command| command.name == name) {
+            return Err(format!("Plugin {} has no command {}", self.id, name));
+        }
+        self.request(
+            "commands/run",
+            json!({ "name": name, "args": args }),
+            CALL_TIMEOUT,
+        )
+        .await
+    }
+
+    /// Ask the plugin to exit, and kill it if it doesn't promptly.
+    pub(crate) async fn stop(&self) {
+        let _ = self
+            .stdin
+            .send(json!({ "jsonrpc": "2.0", "method": "shutdown" }).to_string());
+        let mut child = self.child.lock().await;
+        if tokio::time::timeout(STOP_GRACE, child.wait())
+            .await
+            .is_err()
+        {
+            let _ = child.kill().await;
+        }
+    }
+}
+
+/// Start the plugin in `dir`, complete the handshake and learn its tools.
+/// `on_exit` runs once the process has gone, however it ended.
+pub(crate) async fn spawn(
+    manifest: &PluginManifest,
+    dir: &Path,
+    on_exit: impl FnOnce() + Send + 'static,
+) -> Result<PluginProcess, String> {
+    let program = match manifest.bundled_program(dir) {
+        Some(path) => path.to_string_lossy().into_owned(),
+        None => crate::agents::resolve_binary(manifest.command.trim())
+            .await
+            .map_err(|_| format!("{} is not installed or not on PATH", manifest.command))?,
+    };
+
+    let mut command = Command::new(program);
+    command.args(&manifest.args).current_dir(dir).env_clear();
+    for name in INHERITED_ENV {
+        if let Some(value) = std::env::var_os(name) {
+            command.env(name, value);
+        }
+    }
+    let mut child = command
+        .env("FREELY_PLUGIN_DIR", dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to start plugin {}: {}", manifest.id, e))?;
+
+    let mut stdin = child.stdin.take().ok_or("Failed to open plugin stdin")?;
+    let stdout = child.stdout.take().ok_or("Failed to capture stdout")?;
+    let stderr = child.stderr.take().ok_or("Failed to capture stderr")?;
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<String>();
+    tokio::spawn(async move {
+        while let Some(line) = rx.recv().await {
+            let written = stdin.write_all(format!("{}\n", line).as_bytes()).await;
+            if written.is_err() || stdin.flush().await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let plugin_id = manifest.id.clone();
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(stderr).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            info!("Plugin {}: {}", plugin_id, line);
+        }
+    });
+
+    let pending: Pending = Arc::new(Mutex::new(HashMap::new()));
+    let exited = Arc::new(AtomicBool::new(false));
+    let sandbox = Arc::new(Sandbox::new(manifest, dir));
+    {
+        let plugin_id = manifest.id.clone();
+        let pending = pending.clone();
+        let exited = exited.clone();
+        let tx = tx.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                // Plugins sometimes print to stdout; skip anything that isn't JSON-RPC
+                let Ok(message) = serde_json::from_str::<Value>(&line) else {
+                    continue;
+                };
+                handle_message(&plugin_id, message, &pending, &sandbox, &tx);
+            }
+            exited.store(true, Ordering::SeqCst);
+            for (_, sender) in pending.lock().drain() {
+                let _ = sender.send(Err("Plugin exited".to_string()));
+            }
+            info!("Plugin {} exited", plugin_id);
+            on_exit();
+        });
+    }
+
+    let mut process = PluginProcess {
+        id: manifest.id.clone(),
+        tools: Vec::new(),
+        commands: Vec::new(),
+        stdin: tx,
+        pending,
+        next_id: AtomicU64::new(1),
+        exited,
+        child: tokio::sync::Mutex::new(child),
+    };
+    let params = json!({
+        "protocolVersion": PROTOCOL_VERSION,
+        "hostVersion": env!("CARGO_PKG_VERSION"),
+        "pluginDir": dir,
+        "permissions": manifest.permissions,
+    });
+    let result = process.request("initialize", params, START_TIMEOUT).await?;
+    let init: InitializeResult = serde_json::from_value(result).map_err(|e| {
+        format!(
+            "Plugin {} sent an invalid initialize result: {}",
+            manifest.id, e
+        )
+    })?;
+    process.tools = init.tools;
+    process.commands = init.commands;
+    Ok(process)
+}
+
+/// Route one message from the plugin: a response to one of our requests, a
+/// request for a host service, or a notification.
+fn handle_message(
+    plugin_id: &str,
+    message: Value,
+    pending: &Pending,
+    sandbox: &Arc<Sandbox>,
+    tx: &mpsc::UnboundedSender<String>,
+) {
+    let Some(method) = message["method"].as_str() else {
+        if let Some(sender) = message["id"]
+            .as_u64()
+            .and_then(|id| pending.lock().remove(&id))
+        {
+            let _ = sender.send(response_result(&message));
+        }
+        return;
+    };
+    let Some(id) = message.get("id").cloned() else {
+        if method == "log" {
+            info!(
+                "Plugin {}: {}",
+                plugin_id,
+                message["params"]["message"].as_str().unwrap_or_default()
+            );
+        }
+        return;
+    };
+
+    let method = method.to_string();
+    let params = message["params"].clone();
+    let sandbox = sandbox.clone();
+    let tx = tx.clone();
+    let plugin_id = plugin_id.to_string();
+    tokio::spawn(async move {
+        let reply = match host_request(&sandbox, &method, params).await {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => {
+                if code == PERMISSION_DENIED {
+                    warn!("Plugin {}: {}", plugin_id, message);
+                }
+                json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+            }
+        };
+        let _ = tx.send(reply.to_string());
+    });
+}
+
+fn params<T: serde::de::DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| (INVALID_PARAMS, e.to_string()))
+}
+
+fn denied(e: String) -> RpcError {
+    (PERMISSION_DENIED, e)
+}
+
+fn failed(e: String) -> RpcError {
+    (REQUEST_FAILED, e)
+}
+
+#[derive(Deserialize)]
+struct PathParams {
+    path: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct WriteParams {
+    path: PathBuf,
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct FetchParams {
+    url: String,
+    #[serde(default)]
+    method: Option<String>,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    #[serde(default)]
+    body: Option<String>,
+}
+
+async fn host_request(sandbox: &Sandbox, method: &str, raw: Value) -> Result<Value, RpcError> {
+    match method {
+        "fs/read" => {
+            let PathParams { path } = params(raw)?;
+            let path = sandbox.check_read(&path).map_err(denied)?;
+            let size = tokio::fs::metadata(&path)
+                .await
+                .map_err(|e| failed(format!("Failed to read {}: {}", path.display(), e)))?
+                .len();
+            if size > MAX_FILE_BYTES {
+                return Err(failed(format!("{} is too large", path.display())));
+            }
+            let content = tokio::fs::read_to_string(&path)
+                .await
+                .map_err(|e| failed(format!("Failed to read {}: {}", path.display(), e)))?;
+            Ok(json!({ "content": content }))
+        }
+        "fs/write" => {
+            let WriteParams { path, content } = params(raw)?;
+            let path = sandbox.check_write(&path).map_err(denied)?;
+            tokio::fs::write(&path, content)
+                .await
+                .map_err(|e| failed(format!("Failed to write {}: {}", path.display(), e)))?;
+            Ok(json!({}))
+        }
+        "fs/list" => {
+            let PathParams { path } = params(raw)?;
+            let path = sandbox.check_read(&path).map_err(denied)?;
+            let mut dir = tokio::fs::read_dir(&path)
+                .await
+                .map_err(|e| failed(format!("Failed to list {}: {}", path.display(), e)))?;
+            let mut entries = Vec::new();
+            while let Ok(Some(entry)) = dir.next_entry().await {
+                let is_dir = entry.file_type().await.is_ok_and(|t| t.is_dir());
+                entries.push(json!({
+                    "name": entry.file_name().to_string_lossy(),
+                    "isDir": is_dir,
+                }));
+            }
+            entries.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+            Ok(json!({ "entries": entries }))
+        }
+        "http/fetch" => fetch(sandbox, params(raw)?).await,
+        _ => Err((METHOD_NOT_FOUND, format!("Unknown method {}", method))),
+    }
+}
+
+/// Redirects are followed here rather than by reqwest, so every hop is
+/// checked against the allowed hosts.
+async fn fetch(sandbox: &Sandbox, request: FetchParams) -> Result<Value, RpcError> {
+    crate::net::connectivity::require_online().map_err(failed)?;
+    let client = crate::net::client::http_client_without_redirects().map_err(failed)?;
+    let mut method = request
+        .method
+        .as_deref()
+        .unwrap_or("GET")
+        .parse::<reqwest::Method>()
+        .map_err(|e| (INVALID_PARAMS, format!("Invalid method: {}", e)))?;
+    let mut body = request.body;
+    let mut url = sandbox.check_url(&request.url).map_err(denied)?;
+
+    for _ in 0..=MAX_REDIRECTS {
+        let mut builder = client.request(method.clone(), url.clone());
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        if let Some(body) = &body {
+            builder = builder.body(body.clone());
+        }
+        let response = builder
+            .send()
+            .await
+            .map_err(|e| failed(format!("Request to {} failed: {}", url, e)))?;
+
+        let status = response.status();
+        let location = response
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|v| v.to_str().ok());
+        if let (true, Some(location)) = (status.is_redirection(), location) {
+            let next = url
+                .join(location)
+                .map_err(|e| failed(format!("Invalid redirect: {}", e)))?;
+            url = sandbox.check_url(next.as_str()).map_err(denied)?;
+            if !matches!(status.as_u16(), 307 | 308) {
+                method = reqwest::Method::GET;
+                body = None;
+            }
+            continue;
+        }
+
+        let headers: BTreeMap<String, String> = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|e| failed(format!("Failed to read response: {}", e)))?;
+        if bytes.len() > MAX_RESPONSE_BYTES {
+            return Err(failed("Response is too large".to_string()));
+        }
+        return Ok(json!({
+            "status": status.as_u16(),
+            "headers": headers,
+            "body": String::from_utf8_lossy(&bytes),
+        }));
+    }
+    Err(failed(format!("Too many redirects from {}", request.url)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn plugins_reach_files_only_through_their_sandbox() {
+        let tmp = TempDir::new().unwrap();
+        let notes = tmp.path().join("notes");
+        std::fs::create_dir_all(&notes).unwrap();
+        std::fs::write(notes.join("today.md"), "three small words").unwrap();
+        let secret = tmp.path().join("secret.txt");
+        std::fs::write(&secret, "hunter2").unwrap();
+        let plugin_dir = tmp.path().join("plugin");
+        std::fs::create_dir_all(&plugin_dir).unwrap();
+
+        let script = r#"
+            read init
+            echo 'warming up'
+            echo '{"jsonrpc":"2.0","id":1,"result":{"tools":[{"name":"peek"}],"commands":[{"name":"hello","title":"Say hello"}]}}'
+            read call
+            echo "{\"jsonrpc\":\"2.0\",\"id\":\"a\",\"method\":\"fs/read\",\"params\":{\"path\":\"$1\"}}"
+            read allowed
+            echo "{\"jsonrpc\":\"2.0\",\"id\":\"b\",\"method\":\"fs/read\",\"params\":{\"path\":\"$2\"}}"
+            read denied
+            echo "{\"jsonrpc\":\"2.0\",\"id\":2,\"result\":{\"allowed\":$allowed,\"denied\":$denied}}"
+            read shutdown
+        "#;
+        let manifest: PluginManifest = serde_json::from_value(json!({
+            "id": "peek",
+            "name": "Peek",
+            "command": "sh",
+            "args": [
+                "-c",
+                script,
+                "sh",
+                notes.join("today.md"),
+                secret,
+            ],
+            "permissions": { "read": [notes] },
+        }))
+        .unwrap();
+
+        let (exit_tx, exit_rx) = oneshot::channel();
+        let process = spawn(&manifest, &plugin_dir, move || {
+            let _ = exit_tx.send(());
+        })
+        .await
+        .unwrap();
+        assert_eq!(process.tools[0].name, "peek");
+        assert_eq!(process.commands[0].title.as_deref(), Some("Say hello"));
+        assert!(process.call_tool("missing", json!({})).await.is_err());
+
+        let result = process.call_tool("peek", json!({})).await.unwrap();
+        assert_eq!(result["allowed"]["result"]["content"], "three small words");
+        assert_eq!(result["denied"]["error"]["code"], PERMISSION_DENIED);
+        assert!(!result["denied"].to_string().contains("hunter2"));
+
+        process.stop().await;
+        exit_rx.await.unwrap();
+        assert!(!process.is_running());
+        let err = process.call_tool("peek", json!({})).await.unwrap_err();
+        assert!(err.contains("not running"), "{}", err);
+    }
+}
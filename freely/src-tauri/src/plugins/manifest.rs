@@ -0,0 +1,304 @@
+//! `plugin.json`, and the sandbox its permissions describe.
+//!
+//! A plugin's own directory is always readable. Anything else has to be
+//! listed: `read` and `write` hold directories (absolute, `~/…` or
+//! `$PLUGIN_DIR/…`), `network` holds hosts, either exact or `*.example.com`
+//! for subdomains. Writing a directory implies reading it.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+pub(crate) const MANIFEST_FILE: &str = "plugin.json";
+const MAX_ID_LEN: usize = 64;
+const PLUGIN_DIR_VAR: &str = "$PLUGIN_DIR";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginPermissions {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub read: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub write: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub network: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginManifest {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Program to run: a path inside the plugin directory (`./server`), or
+    /// a command on PATH (`node`, `python3`).
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub permissions: PluginPermissions,
+}
+
+pub(crate) fn validate_id(id: &str) -> Result<(), String> {
+    if id.is_empty()
+        || id.len() > MAX_ID_LEN
+        || !id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '.')
+        || id.starts_with('.')
+    {
+        return Err(format!(
+            "Invalid plugin id {:?}: use lowercase letters, digits, '-' and '.'",
+            id
+        ));
+    }
+    Ok(())
+}
+
+fn validate_host(pattern: &str) -> Result<(), String> {
+    let host = pattern.strip_prefix("*.").unwrap_or(pattern);
+    let valid = !host.is_empty()
+        && host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+    if !valid {
+        return Err(format!(
+            "Invalid network permission {:?}: use a host name or *.domain",
+            pattern
+        ));
+    }
+    Ok(())
+}
+
+impl PluginManifest {
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        validate_id(&self.id)?;
+        if self.name.trim().is_empty() {
+            return Err(format!("Plugin {} has no name", self.id));
+        }
+        if self.command.trim().is_empty() {
+            return Err(format!("Plugin {} has no command", self.id));
+        }
+        for path in self.permissions.read.iter().chain(&self.permissions.write) {
+            if resolve_root(path, Path::new("/")).is_none() {
+                return Err(format!(
+                    "Invalid file permission {:?}: use an absolute path, ~/ or {}/",
+                    path, PLUGIN_DIR_VAR
+                ));
+            }
+        }
+        for host in &self.permissions.network {
+            validate_host(host)?;
+        }
+        Ok(())
+    }
+
+    /// The program to spawn, if it lives inside the plugin directory.
+    pub(crate) fn bundled_program(&self, dir: &Path) -> Option<PathBuf> {
+        let command = self.command.trim();
+        (command.contains('/') || command.contains('\\')).then(|| dir.join(command))
+    }
+}
+
+/// Read and validate `<dir>/plugin.json`.
+pub(crate) fn load(dir: &Path) -> Result<PluginManifest, String> {
+    let path = dir.join(MANIFEST_FILE);
+    let raw = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let manifest: PluginManifest =
+        serde_json::from_str(&raw).map_err(|e| format!("Invalid {}: {}", path.display(), e))?;
+    manifest.validate()?;
+    Ok(manifest)
+}
+
+/// `raw` as an absolute path, expanding `~` and `$PLUGIN_DIR`. `None` for
+/// relative paths and anything that climbs with `..`.
+fn resolve_root(raw: &str, plugin_dir: &Path) -> Option<PathBuf> {
+    let path = if let Some(rest) = raw.strip_prefix(PLUGIN_DIR_VAR) {
+        plugin_dir.join(rest.trim_start_matches('/'))
+    } else if raw == "~" {
+        dirs::home_dir()?
+    } else if let Some(rest) = raw.strip_prefix("~/") {
+        dirs::home_dir()?.join(rest)
+    } else {
+        PathBuf::from(raw)
+    };
+    let climbs = path.components().any(|c| c == Component::ParentDir);
+    (path.is_absolute() && !climbs).then_some(path)
+}
+
+/// `path` with symlinks resolved. Paths that don't exist yet (files about
+/// to be written) resolve through their parent directory.
+fn canonical_target(path: &Path) -> Result<PathBuf, String> {
+    if !path.is_absolute() {
+        return Err(format!("Plugin paths must be absolute: {}", path.display()));
+    }
+    if let Ok(path) = path.canonicalize() {
+        return Ok(path);
+    }
+    let name = match path.components().next_back() {
+        Some(Component::Normal(name)) => name,
+        _ => return Err(format!("Invalid path: {}", path.display())),
+    };
+    let parent = path
+        .parent()
+        .and_then(|parent| parent.canonicalize().ok())
+        .ok_or_else(|| format!("No such directory: {}", path.display()))?;
+    Ok(parent.join(name))
+}
+
+/// What a running plugin may reach through the host.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Sandbox {
+    read: Vec<PathBuf>,
+    write: Vec<PathBuf>,
+    hosts: Vec<String>,
+}
+
+impl Sandbox {
+    /// Directories that don't exist are dropped rather than granted later.
+    pub(crate) fn new(manifest: &PluginManifest, plugin_dir: &Path) -> Self {
+        let roots = |paths: &[String]| -> Vec<PathBuf> {
+            paths
+                .iter()
+                .filter_map(|raw| resolve_root(raw, plugin_dir))
+                .filter_map(|path| path.canonicalize().ok())
+                .collect()
+        };
+        let write = roots(&manifest.permissions.write);
+        let mut read = roots(&manifest.permissions.read);
+        read.extend(plugin_dir.canonicalize().ok());
+        read.extend(write.iter().cloned());
+        Self {
+            read,
+            write,
+            hosts: manifest
+                .permissions
+                .network
+                .iter()
+                .map(|host| host.to_ascii_lowercase())
+                .collect(),
+        }
+    }
+
+    fn check_path(roots: &[PathBuf], path: &Path, action: &str) -> Result<PathBuf, String> {
+        let target = canonical_target(path)?;
+        if roots.iter().any(|root| target.starts_with(root)) {
+            Ok(target)
+        } else {
+            Err(format!(
+                "Plugin is not allowed to {} {}",
+                action,
+                path.display()
+            ))
+        }
+    }
+
+    pub(crate) fn check_read(&self, path: &Path) -> Result<PathBuf, String> {
+        Self::check_path(&self.read, path, "read")
+    }
+
+    pub(crate) fn check_write(&self, path: &Path) -> Result<PathBuf, String> {
+        Self::check_path(&self.write, path, "write")
+    }
+
+    pub(crate) fn check_url(&self, url: &str) -> Result<reqwest::Url, String> {
+        let parsed = reqwest::Url::parse(url).map_err(|e| format!("Invalid URL {}: {}", url, e))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(format!("Plugin requests must use http or https: {}", url));
+        }
+        let host = parsed.host_str().unwrap_or_default().to_ascii_lowercase();
+        let allowed = self
+            .hosts
+            .iter()
+            .any(|pattern| match pattern.strip_prefix("*.") {
+                Some(domain) => host
+                    .strip_suffix(domain)
+                    .is_some_and(|sub| sub.ends_with('.') && sub.len() > 1),
+                None => pattern == "*" || *pattern == host,
+            });
+        if !allowed {
+            return Err(format!("Plugin is not allowed to reach {}", host));
+        }
+        Ok(parsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn manifest(permissions: serde_json::Value) -> PluginManifest {
+        serde_json::from_value(json!({
+            "id": "word-count",
+            "name": "Word count",
+            "command": "./run.sh",
+            "permissions": permissions,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn sandbox_grants_only_what_the_manifest_lists() {
+        let tmp = TempDir::new().unwrap();
+        let plugin_dir = tmp.path().join("plugin");
+        let notes = tmp.path().join("notes");
+        let secret = tmp.path().join("secret");
+        for dir in [&plugin_dir, &notes, &secret] {
+            std::fs::create_dir_all(dir).unwrap();
+        }
+        std::fs::write(notes.join("a.md"), "hi").unwrap();
+        std::fs::write(secret.join("key"), "shh").unwrap();
+
+        let manifest = manifest(json!({
+            "read": [notes.to_string_lossy()],
+            "write": ["$PLUGIN_DIR/out"],
+            "network": ["api.example.com", "*.githubusercontent.com"],
+        }));
+        manifest.validate().unwrap();
+        std::fs::create_dir_all(plugin_dir.join("out")).unwrap();
+        let sandbox = Sandbox::new(&manifest, &plugin_dir);
+
+        assert!(sandbox.check_read(&notes.join("a.md")).is_ok());
+        assert!(sandbox.check_read(&plugin_dir.join("plugin.json")).is_ok());
+        assert!(sandbox.check_read(&secret.join("key")).is_err());
+        assert!(sandbox.check_read(&notes.join("../secret/key")).is_err());
+        assert!(sandbox.check_write(&notes.join("b.md")).is_err());
+        assert!(sandbox.check_write(&plugin_dir.join("out/new.txt")).is_ok());
+        assert!(sandbox.check_read(Path::new("relative.txt")).is_err());
+
+        assert!(sandbox.check_url("https://api.example.com/v1").is_ok());
+        assert!(sandbox
+            .check_url("https://evil.com/?api.example.com")
+            .is_err());
+        assert!(sandbox
+            .check_url("https://raw.githubusercontent.com/x")
+            .is_ok());
+        assert!(sandbox
+            .check_url("https://githubusercontent.com/x")
+            .is_err());
+        assert!(sandbox
+            .check_url("https://notgithubusercontent.com/x")
+            .is_err());
+        assert!(sandbox.check_url("file:///etc/passwd").is_err());
+
+        assert_eq!(
+            manifest.bundled_program(&plugin_dir),
+            Some(plugin_dir.join("./run.sh"))
+        );
+        assert!(manifest(json!({ "read": ["relative"] }))
+            .validate()
+            .is_err());
+        assert!(manifest(json!({ "read": ["/tmp/../etc"] }))
+            .validate()
+            .is_err());
+        assert!(manifest(json!({ "network": ["https://x.com"] }))
+            .validate()
+            .is_err());
+        assert!(validate_id("../escape").is_err());
+    }
+}
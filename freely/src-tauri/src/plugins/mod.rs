@@ -0,0 +1,331 @@
+//! User plugins that add tools and commands without forking Freely.
+//!
+//! Each plugin is a directory under `<app local data>/plugins/` with a
+//! `plugin.json` manifest naming the program to run and the files and hosts
+//! it may reach. Enabled plugins run as child processes speaking JSON-RPC
+//! over stdio (see [`host`]); the tools and commands they report during the
+//! handshake are registered while they run and listed by `list_plugins`.
+//! Every change to the registry emits `plugins-changed`.
+//!
+//! Plugins start disabled. The enabled list is stored under the `plugins`
+//! setting and restored at startup by [`start_plugins`].
+
+pub(crate) mod host;
+pub(crate) mod manifest;
+
+use host::{PluginCommand, PluginProcess, PluginTool};
+use manifest::{PluginManifest, MANIFEST_FILE};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::warn;
+
+pub(crate) const SETTING_KEY: &str = "plugins";
+const PLUGINS_DIR: &str = "plugins";
+
+/// Running plugins, by id.
+static RUNNING: Lazy<Mutex<HashMap<String, Arc<PluginProcess>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+/// Why each plugin that failed to start didn't.
+static START_ERRORS: Lazy<Mutex<HashMap<String, String>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginsConfig {
+    #[serde(default)]
+    pub enabled: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginInfo {
+    /// The directory name, which matches the manifest id when it is valid.
+    pub id: String,
+    pub dir: PathBuf,
+    pub manifest: Option<PluginManifest>,
+    pub enabled: bool,
+    pub running: bool,
+    pub tools: Vec<PluginTool>,
+    pub commands: Vec<PluginCommand>,
+    /// An invalid manifest, or why the plugin failed to start.
+    pub error: Option<String>,
+}
+
+fn plugins_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app
+        .path()
+        .app_local_data_dir()
+        .map_err(|e| format!("Could not resolve app_local_data_dir: {}", e))?;
+    Ok(data_dir.join(PLUGINS_DIR))
+}
+
+/// A manifest is only used from the directory named after its id.
+fn load_manifest(dir: &Path) -> Result<PluginManifest, String> {
+    let manifest = manifest::load(dir)?;
+    if dir.file_name().and_then(|name| name.to_str()) != Some(manifest.id.as_str()) {
+        return Err(format!(
+            "Plugin {} must be installed in a directory named {}",
+            manifest.id, manifest.id
+        ));
+    }
+    Ok(manifest)
+}
+
+fn plugin_dirs(root: &Path) -> Result<Vec<PathBuf>, String> {
+    let entries = match std::fs::read_dir(root) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read {}: {}", root.display(), e)),
+    };
+    let mut dirs: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.join(MANIFEST_FILE).is_file())
+        .collect();
+    dirs.sort();
+    Ok(dirs)
+}
+
+async fn load_config(app: &AppHandle) -> Result<PluginsConfig, String> {
+    let pool = crate::db::pool(app).await?;
+    Ok(crate::settings::get_setting(&pool, SETTING_KEY)
+        .await?
+        .unwrap_or_default())
+}
+
+fn emit_changed(app: &AppHandle) {
+    if let Err(e) = app.emit("plugins-changed", ()) {
+        warn!("Failed to emit plugins-changed: {}", e);
+    }
+}
+
+fn running(id: &str) -> Option<Arc<PluginProcess>> {
+    RUNNING
+        .lock()
+        .get(id)
+        .filter(|process| process.is_running())
+        .cloned()
+}
+
+async fn start(app: &AppHandle, id: &str) -> Result<(), String> {
+    stop(id).await;
+    let dir = plugins_dir(app)?.join(id);
+    let result = async {
+        let manifest = load_manifest(&dir)?;
+        let on_exit = {
+            let app = app.clone();
+            move || emit_changed(&app)
+        };
+        host::spawn(&manifest, &dir, on_exit).await
+    }
+    .await;
+    match result {
+        Ok(process) => {
+            START_ERRORS.lock().remove(id);
+            RUNNING.lock().insert(id.to_string(), Arc::new(process));
+            emit_changed(app);
+            Ok(())
+        }
+        Err(e) => {
+            START_ERRORS.lock().insert(id.to_string(), e.clone());
+            emit_changed(app);
+            Err(e)
+        }
+    }
+}
+
+async fn stop(id: &str) {
+    let process = RUNNING.lock().remove(id);
+    if let Some(process) = process {
+        process.stop().await;
+    }
+}
+
+/// Start every enabled plugin. A plugin that fails is reported by
+/// `list_plugins` and doesn't hold up the rest.
+pub fn start_plugins(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let config = match load_config(&app).await {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("Failed to load plugin settings: {}", e);
+                return;
+            }
+        };
+        for id in config.enabled {
+            if let Err(e) = start(&app, &id).await {
+                warn!("Failed to start plugin {}: {}", id, e);
+            }
+        }
+    });
+}
+
+/// Kill every plugin on exit; dropping a process kills it.
+pub fn shutdown_all() {
+    RUNNING.lock().clear();
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<(), String> {
+    std::fs::create_dir_all(to).map_err(|e| format!("Failed to create {}: {}", to.display(), e))?;
+    let entries =
+        std::fs::read_dir(from).map_err(|e| format!("Failed to read {}: {}", from.display(), e))?;
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let source = entry.path();
+        let target = to.join(entry.file_name());
+        let file_type = entry
+            .file_type()
+            .map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+        if file_type.is_dir() {
+            copy_dir(&source, &target)?;
+        } else if file_type.is_file() {
+            std::fs::copy(&source, &target)
+                .map_err(|e| format!("Failed to copy {}: {}", source.display(), e))?;
+        }
+    }
+    Ok(())
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+#[tauri::command]
+pub async fn list_plugins(app: AppHandle) -> Result<Vec<PluginInfo>, String> {
+    let config = load_config(&app).await?;
+    let errors = START_ERRORS.lock().clone();
+    Ok(plugin_dirs(&plugins_dir(&app)?)?
+        .into_iter()
+        .map(|dir| {
+            let id = dir
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let (manifest, error) = match load_manifest(&dir) {
+                Ok(manifest) => (Some(manifest), errors.get(&id).cloned()),
+                Err(e) => (None, Some(e)),
+            };
+            let process = running(&id);
+            PluginInfo {
+                enabled: config.enabled.contains(&id),
+                running: process.is_some(),
+                tools: process
+                    .as_ref()
+                    .map(|p| p.tools.clone())
+                    .unwrap_or_default(),
+                commands: process
+                    .as_ref()
+                    .map(|p| p.commands.clone())
+                    .unwrap_or_default(),
+                id,
+                dir,
+                manifest,
+                error,
+            }
+        })
+        .collect())
+}
+
+/// Copy the plugin directory at `source` into Freely's plugins directory,
+/// replacing an older copy. The plugin stays disabled until enabled.
+#[tauri::command]
+pub async fn install_plugin(app: AppHandle, source: String) -> Result<PluginManifest, String> {
+    let source = PathBuf::from(source);
+    let manifest = manifest::load(&source)?;
+    let target = plugins_dir(&app)?.join(&manifest.id);
+    if source.canonicalize().ok() == target.canonicalize().ok() {
+        return Err(format!("Plugin {} is already installed", manifest.id));
+    }
+
+    let was_running = running(&manifest.id).is_some();
+    stop(&manifest.id).await;
+    if target.exists() {
+        std::fs::remove_dir_all(&target)
+            .map_err(|e| format!("Failed to remove {}: {}", target.display(), e))?;
+    }
+    copy_dir(&source, &target)?;
+    if was_running {
+        start(&app, &manifest.id).await?;
+    }
+    emit_changed(&app);
+    Ok(manifest)
+}
+
+#[tauri::command]
+pub async fn uninstall_plugin(app: AppHandle, id: String) -> Result<(), String> {
+    manifest::validate_id(&id)?;
+    set_plugin_enabled(app.clone(), id.clone(), false).await?;
+    let dir = plugins_dir(&app)?.join(&id);
+    std::fs::remove_dir_all(&dir)
+        .map_err(|e| format!("Failed to remove {}: {}", dir.display(), e))?;
+    START_ERRORS.lock().remove(&id);
+    emit_changed(&app);
+    Ok(())
+}
+
+/// Enable and start a plugin, or stop and disable it. A plugin that fails
+/// to start stays enabled, so fixing it and calling `reload_plugin` works.
+#[tauri::command]
+pub async fn set_plugin_enabled(app: AppHandle, id: String, enabled: bool) -> Result<(), String> {
+    manifest::validate_id(&id)?;
+    let mut config = load_config(&app).await?;
+    config.enabled.retain(|enabled_id| *enabled_id != id);
+    if enabled {
+        load_manifest(&plugins_dir(&app)?.join(&id))?;
+        config.enabled.push(id.clone());
+    }
+    crate::settings::set_setting(&app, SETTING_KEY, &config).await?;
+
+    if enabled {
+        start(&app, &id).await
+    } else {
+        stop(&id).await;
+        START_ERRORS.lock().remove(&id);
+        emit_changed(&app);
+        Ok(())
+    }
+}
+
+/// Restart an enabled plugin, picking up changes to its files.
+#[tauri::command]
+pub async fn reload_plugin(app: AppHandle, id: String) -> Result<(), String> {
+    manifest::validate_id(&id)?;
+    if !load_config(&app).await?.enabled.contains(&id) {
+        return Err(format!("Plugin {} is not enabled", id));
+    }
+    start(&app, &id).await
+}
+
+#[tauri::command]
+pub async fn call_plugin_tool(
+    plugin_id: String,
+    tool: String,
+    arguments: Option<Value>,
+) -> Result<Value, String> {
+    let process =
+        running(&plugin_id).ok_or_else(|| format!("Plugin {} is not running", plugin_id))?;
+    process
+        .call_tool(
+            &tool,
+            arguments.unwrap_or_else(|| Value::Object(Default::default())),
+        )
+        .await
+}
+
+#[tauri::command]
+pub async fn run_plugin_command(
+    plugin_id: String,
+    command: String,
+    args: Option<Value>,
+) -> Result<Value, String> {
+    let process =
+        running(&plugin_id).ok_or_else(|| format!("Plugin {} is not running", plugin_id))?;
+    process
+        .run_command(&command, args.unwrap_or(Value::Null))
+        .await
+}
@@ -0,0 +1,223 @@
+//! Variables in system prompts.
+//!
+//! A saved prompt can reference `{{name}}` placeholders, optionally with a
+//! fallback for when the value is empty or unknown: `{{selection|nothing
+//! selected}}`. Rendering fills them from the caller's `vars` first and then
+//! from the built-ins:
+//!
+//! - `date`, `time`, `datetime`, `weekday` in the local time zone
+//! - `active_project`, `active_project_path`
+//! - `clipboard`, the current clipboard text
+//!
+//! `selection` has no built-in value, since the selected text lives in
+//! whichever app the user was in; the frontend passes it in `vars`.
+//! Built-ins are only computed when a prompt references them, so the
+//! clipboard isn't read for prompts that never use it. Placeholders that
+//! can't be resolved are left in place and reported back.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use tauri::AppHandle;
+use tracing::warn;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenderedPrompt {
+    pub prompt: String,
+    /// Variables that had no value and no fallback.
+    pub missing: Vec<String>,
+}
+
+struct Placeholder<'a> {
+    /// Byte range of the whole `{{...}}`.
+    start: usize,
+    end: usize,
+    name: &'a str,
+    fallback: Option<&'a str>,
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// Every well-formed placeholder in `template`, in order. Anything else
+/// between braces is ordinary text.
+fn placeholders(template: &str) -> Vec<Placeholder<'_>> {
+    let mut found = Vec::new();
+    let mut offset = 0;
+    while let Some(open) = template[offset..].find("{{") {
+        let start = offset + open;
+        let Some(close) = template[start + 2..].find("}}") else {
+            break;
+        };
+        let end = start + 2 + close + 2;
+        let inner = &template[start + 2..end - 2];
+        let (name, fallback) = match inner.split_once('|') {
+            Some((name, fallback)) => (name.trim(), Some(fallback.trim())),
+            None => (inner.trim(), None),
+        };
+
+        if is_valid_name(name) {
+            found.push(Placeholder {
+                start,
+                end,
+                name,
+                fallback,
+            });
+            offset = end;
+        } else {
+            offset = start + 2;
+        }
+    }
+    found
+}
+
+/// Names referenced by `template`, without duplicates.
+pub(crate) fn variables(template: &str) -> Vec<&str> {
+    let mut names: Vec<&str> = Vec::new();
+    for placeholder in placeholders(template) {
+        if !names.contains(&placeholder.name) {
+            names.push(placeholder.name);
+        }
+    }
+    names
+}
+
+pub(crate) fn render(template: &str, vars: &HashMap<String, String>) -> RenderedPrompt {
+    let mut prompt = String::with_capacity(template.len());
+    let mut missing: Vec<String> = Vec::new();
+    let mut copied = 0;
+
+    for placeholder in placeholders(template) {
+        prompt.push_str(&template[copied..placeholder.start]);
+        let value = vars
+            .get(placeholder.name)
+            .map(String::as_str)
+            .filter(|value| !value.is_empty());
+        match (value, placeholder.fallback) {
+            (Some(value), _) => prompt.push_str(value),
+            (None, Some(fallback)) => prompt.push_str(fallback),
+            (None, None) => {
+                prompt.push_str(&template[placeholder.start..placeholder.end]);
+                if !missing.iter().any(|name| name == placeholder.name) {
+                    missing.push(placeholder.name.to_string());
+                }
+            }
+        }
+        copied = placeholder.end;
+    }
+    prompt.push_str(&template[copied..]);
+
+    RenderedPrompt { prompt, missing }
+}
+
+async fn builtin(app: &AppHandle, name: &str) -> Result<Option<String>, String> {
+    let now = chrono::Local::now();
+    let value = match name {
+        "date" => now.format("%Y-%m-%d").to_string(),
+        "time" => now.format("%H:%M").to_string(),
+        "datetime" => now.format("%Y-%m-%d %H:%M").to_string(),
+        "weekday" => now.format("%A").to_string(),
+        "active_project" | "active_project_path" => {
+            let pool = crate::db::pool(app).await?;
+            let Some(project) = crate::db::projects::active(&pool).await? else {
+                return Ok(None);
+            };
+            if name == "active_project" {
+                project.name
+            } else {
+                project.path
+            }
+        }
+        "clipboard" => {
+            let text = tauri::async_runtime::spawn_blocking(|| {
+                arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_text())
+            })
+            .await
+            .map_err(|e| format!("Clipboard task failed: {}", e))?;
+            match text {
+                Ok(text) => text,
+                Err(arboard::Error::ContentNotAvailable) => return Ok(None),
+                Err(e) => return Err(format!("Failed to read clipboard: {}", e)),
+            }
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(value))
+}
+
+/// Render `template`, resolving the built-ins it uses that `vars` doesn't
+/// already provide.
+pub(crate) async fn render_with_builtins(
+    app: &AppHandle,
+    template: &str,
+    mut vars: HashMap<String, String>,
+) -> RenderedPrompt {
+    for name in variables(template) {
+        if vars.contains_key(name) {
+            continue;
+        }
+        match builtin(app, name).await {
+            Ok(Some(value)) => {
+                vars.insert(name.to_string(), value);
+            }
+            Ok(None) => {}
+            // A prompt is still usable without one variable
+            Err(e) => warn!("Failed to resolve prompt variable {}: {}", name, e),
+        }
+    }
+    render(template, &vars)
+}
+
+/// Render the saved system prompt `id`. Values in `vars` take precedence
+/// over the built-ins.
+#[tauri::command]
+pub async fn render_system_prompt(
+    app: AppHandle,
+    id: i64,
+    vars: Option<HashMap<String, String>>,
+) -> Result<RenderedPrompt, String> {
+    let pool = crate::db::pool(&app).await?;
+    let prompt = crate::db::system_prompts::get(&pool, id)
+        .await?
+        .ok_or_else(|| format!("System prompt not found: {}", id))?;
+    Ok(render_with_builtins(&app, &prompt.prompt, vars.unwrap_or_default()).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn placeholders_are_filled_or_reported() {
+        let rendered = render(
+            "Today is {{ date }}. Project: {{active_project|none}}. {{selection}} {{selection}}",
+            &vars(&[("date", "2024-05-01"), ("active_project", "")]),
+        );
+        assert_eq!(
+            rendered.prompt,
+            "Today is 2024-05-01. Project: none. {{selection}} {{selection}}"
+        );
+        assert_eq!(rendered.missing, ["selection"]);
+    }
+
+    #[test]
+    fn malformed_braces_are_left_alone() {
+        let template = "JSON: {{\"a\": 1}} and {{ not valid }} and {{unclosed";
+        let rendered = render(template, &HashMap::new());
+        assert_eq!(rendered.prompt, template);
+        assert!(rendered.missing.is_empty());
+
+        assert_eq!(variables("{{a}}{{b|x}}{{a}} {{c d}}"), ["a", "b"]);
+    }
+}
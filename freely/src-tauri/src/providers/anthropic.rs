@@ -0,0 +1,231 @@
+//! Anthropic Messages API.
+
+use super::middleware::Retry;
+use super::sse::read_events;
+use super::{
+    error_for_status, split_image, ChatMessage, ChatRole, CompletionOutput, CompletionProvider,
+    CompletionRequest, TokenUsage,
+};
+use futures_util::future::BoxFuture;
+use serde_json::{json, Value};
+
+pub const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
+pub(crate) const API_VERSION: &str = "2023-06-01";
+/// The Messages API requires `max_tokens`.
+const DEFAULT_MAX_TOKENS: u32 = 4096;
+
+pub struct AnthropicProvider {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    retry: Retry,
+}
+
+impl AnthropicProvider {
+    pub fn new(client: reqwest::Client, base_url: String, api_key: String, retry: Retry) -> Self {
+        Self {
+            client,
+            base_url,
+            api_key,
+            retry,
+        }
+    }
+}
+
+fn message_json(message: &ChatMessage) -> Value {
+    let mut content: Vec<Value> = message
+        .images
+        .iter()
+        .map(|image| {
+            let (media_type, data) = split_image(image);
+            json!({
+                "type": "image",
+                "source": { "type": "base64", "media_type": media_type, "data": data },
+            })
+        })
+        .collect();
+    content.push(json!({ "type": "text", "text": message.content }));
+    json!({ "role": message.role, "content": content })
+}
+
+pub(crate) fn request_body(request: &CompletionRequest) -> Value {
+    // System messages go in the top-level `system` field
+    let system: Vec<&str> = request
+        .system_prompt
+        .as_deref()
+        .into_iter()
+        .chain(
+            request
+                .messages
+                .iter()
+                .filter(|m| m.role == ChatRole::System)
+                .map(|m| m.content.as_str()),
+        )
+        .filter(|s| !s.is_empty())
+        .collect();
+    let messages: Vec<Value> = request
+        .messages
+        .iter()
+        .filter(|m| m.role != ChatRole::System)
+        .map(message_json)
+        .collect();
+
+    let mut body = json!({
+        "model": request.model,
+        "messages": messages,
+        "max_tokens": request.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+        "stream": true,
+    });
+    if !system.is_empty() {
+        body["system"] = json!(system.join("\n\n"));
+    }
+    if let Some(temperature) = request.temperature {
+        body["temperature"] = json!(temperature);
+    }
+    body
+}
+
+/// Fold one stream event into `output`, returning the text it added.
+pub(crate) fn apply_event(
+    output: &mut CompletionOutput,
+    event: &Value,
+) -> Result<Option<String>, String> {
+    let usage = output.usage.get_or_insert_with(TokenUsage::default);
+    match event["type"].as_str().unwrap_or_default() {
+        "message_start" => {
+            let message = &event["message"];
+            output.model = message["model"].as_str().map(str::to_string);
+            usage.input_tokens = message["usage"]["input_tokens"].as_u64().unwrap_or(0) as u32;
+            usage.output_tokens = message["usage"]["output_tokens"].as_u64().unwrap_or(0) as u32;
+        }
+        "content_block_delta" => {
+            if let Some(text) = event["delta"]["text"].as_str() {
+                output.text.push_str(text);
+                return Ok(Some(text.to_string()));
+            }
+        }
+        "message_delta" => {
+            if let Some(reason) = event["delta"]["stop_reason"].as_str() {
+                output.finish_reason = Some(reason.to_string());
+            }
+            if let Some(tokens) = event["usage"]["output_tokens"].as_u64() {
+                usage.output_tokens = tokens as u32;
+            }
+        }
+        "error" => {
+            let message = event["error"]["message"]
+                .as_str()
+                .unwrap_or("unknown error");
+            return Err(format!("Provider error: {}", message));
+        }
+        _ => {}
+    }
+    Ok(None)
+}
+
+impl CompletionProvider for AnthropicProvider {
+    fn stream_completion<'a>(
+        &'a self,
+        request: &'a CompletionRequest,
+        on_delta: &'a (dyn Fn(&str) + Send + Sync),
+    ) -> BoxFuture<'a, Result<CompletionOutput, String>> {
+        Box::pin(async move {
+            let http = self
+                .client
+                .post(format!("{}/v1/messages", self.base_url))
+                .header("x-api-key", &self.api_key)
+                .header("anthropic-version", API_VERSION)
+                .json(&request_body(request));
+            let response = error_for_status(self.retry.send(http).await?).await?;
+
+            let mut output = CompletionOutput::default();
+            read_events(response, |event| {
+                let data: Value = serde_json::from_str(&event.data)
+                    .map_err(|e| format!("Invalid completion event: {}", e))?;
+                if let Some(delta) = apply_event(&mut output, &data)? {
+                    on_delta(&delta);
+                }
+                Ok(data["type"] != "message_stop")
+            })
+            .await?;
+            Ok(output)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::ProviderKind;
+
+    #[test]
+    fn body_hoists_system_messages_and_puts_images_first() {
+        let request = CompletionRequest {
+            provider: ProviderKind::Anthropic,
+            model: "claude-3-5-sonnet-latest".into(),
+            messages: vec![
+                ChatMessage {
+                    role: ChatRole::System,
+                    content: "Answer in French".into(),
+                    images: vec![],
+                },
+                ChatMessage {
+                    role: ChatRole::User,
+                    content: "Describe".into(),
+                    images: vec!["data:image/jpeg;base64,/9j/".into()],
+                },
+            ],
+            system_prompt: Some("Be brief".into()),
+            temperature: Some(0.2),
+            max_tokens: None,
+            base_url: None,
+            api_key_name: None,
+            retry: None,
+            profile_id: None,
+            skip_cache: false,
+        };
+        let body = request_body(&request);
+
+        assert_eq!(body["system"], "Be brief\n\nAnswer in French");
+        assert_eq!(body["max_tokens"], DEFAULT_MAX_TOKENS);
+        assert_eq!(body["messages"].as_array().unwrap().len(), 1);
+        assert_eq!(
+            body["messages"][0]["content"][0]["source"]["media_type"],
+            "image/jpeg"
+        );
+        assert_eq!(body["messages"][0]["content"][1]["text"], "Describe");
+    }
+
+    #[test]
+    fn events_accumulate_text_and_usage() {
+        let mut output = CompletionOutput::default();
+        let events = [
+            json!({ "type": "message_start", "message": { "model": "claude-3-5-sonnet-20241022", "usage": { "input_tokens": 20, "output_tokens": 1 } } }),
+            json!({ "type": "content_block_start", "index": 0, "content_block": { "type": "text", "text": "" } }),
+            json!({ "type": "content_block_delta", "index": 0, "delta": { "type": "text_delta", "text": "Bon" } }),
+            json!({ "type": "content_block_delta", "index": 0, "delta": { "type": "text_delta", "text": "jour" } }),
+            json!({ "type": "message_delta", "delta": { "stop_reason": "end_turn" }, "usage": { "output_tokens": 3 } }),
+        ];
+        let deltas: Vec<_> = events
+            .iter()
+            .filter_map(|e| apply_event(&mut output, e).unwrap())
+            .collect();
+
+        assert_eq!(deltas, ["Bon", "jour"]);
+        assert_eq!(output.text, "Bonjour");
+        assert_eq!(output.finish_reason.as_deref(), Some("end_turn"));
+        assert_eq!(
+            output.usage,
+            Some(TokenUsage {
+                input_tokens: 20,
+                output_tokens: 3
+            })
+        );
+
+        let error = json!({ "type": "error", "error": { "type": "overloaded_error", "message": "Overloaded" } });
+        assert_eq!(
+            apply_event(&mut output, &error).unwrap_err(),
+            "Provider error: Overloaded"
+        );
+    }
+}
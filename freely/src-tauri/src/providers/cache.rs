@@ -0,0 +1,405 @@
+//! Response cache for repeated prompts.
+//!
+//! With the `response_cache` setting on, completed responses are kept in the
+//! `response_cache` table (migration 30), keyed by a SHA-256 of the model
+//! and the normalized prompt. A later request with the same key is answered
+//! from the cache at once: the text arrives as a single `completion-delta`,
+//! the output has `cached: true`, and nothing is sent to the provider or
+//! counted as usage. Requests with `skipCache` always go to the provider,
+//! and their answer replaces the cached one.
+//!
+//! Normalizing trims messages and collapses runs of whitespace, so prompts
+//! that differ only in spacing share an answer. The key also covers the
+//! endpoint, system prompt, temperature, token limit and images. Aborted and
+//! empty responses aren't cached. At most `maxEntries` responses are kept,
+//! the least recently used dropped first, and entries older than
+//! `maxAgeDays` expire.
+
+use super::{CompletionOutput, CompletionRequest, FINISH_ABORTED};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use tauri::AppHandle;
+use tracing::warn;
+
+/// Settings key for [`CacheConfig`].
+pub(crate) const SETTING_KEY: &str = "response_cache";
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+const MAX_ENTRIES_LIMIT: u32 = 100_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CacheConfig {
+    pub enabled: bool,
+    pub max_entries: u32,
+    /// `0` keeps entries until they are pushed out.
+    pub max_age_days: u32,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_entries: 500,
+            max_age_days: 30,
+        }
+    }
+}
+
+impl CacheConfig {
+    /// Entries created before this are stale.
+    fn cutoff(&self, now: i64) -> i64 {
+        match self.max_age_days {
+            0 => i64::MIN,
+            days => now - days as i64 * DAY_MS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStats {
+    pub entries: i64,
+    /// Requests answered from the cache.
+    pub hits: i64,
+    pub oldest_at: Option<i64>,
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Hash of everything that shapes the answer to `request`.
+pub(crate) fn cache_key(request: &CompletionRequest) -> String {
+    let messages: Vec<Value> = request
+        .messages
+        .iter()
+        .map(|message| {
+            let images: Vec<String> = message
+                .images
+                .iter()
+                .map(|image| hex(&Sha256::digest(image.as_bytes())))
+                .collect();
+            json!({
+                "role": message.role,
+                "content": normalize(&message.content),
+                "images": images,
+            })
+        })
+        .collect();
+    let key = json!({
+        "provider": request.provider,
+        "profileId": request.profile_id,
+        "baseUrl": request.base_url.as_deref().map(|url| url.trim().trim_end_matches('/')),
+        "model": request.model.trim(),
+        "systemPrompt": request
+            .system_prompt
+            .as_deref()
+            .map(normalize)
+            .filter(|prompt| !prompt.is_empty()),
+        "temperature": request.temperature,
+        "maxTokens": request.max_tokens,
+        "messages": messages,
+    });
+    hex(&Sha256::digest(key.to_string().as_bytes()))
+}
+
+// ============================================================================
+// Storage
+// ============================================================================
+
+/// The cached answer for `key`, if it isn't older than `cutoff`. Counts as
+/// a use.
+async fn get(
+    pool: &SqlitePool,
+    key: &str,
+    cutoff: i64,
+    now: i64,
+) -> Result<Option<CompletionOutput>, String> {
+    type Row = (String, Option<String>, Option<String>);
+    let row: Option<Row> = sqlx::query_as(
+        "UPDATE response_cache SET hits = hits + 1, used_at = ?
+         WHERE key = ? AND created_at >= ?
+         RETURNING text, response_model, finish_reason",
+    )
+    .bind(now)
+    .bind(key)
+    .bind(cutoff)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to read response cache: {}", e))?;
+    Ok(row.map(|(text, model, finish_reason)| CompletionOutput {
+        text,
+        model,
+        finish_reason,
+        usage: None,
+        cached: true,
+    }))
+}
+
+/// Drop stale entries, then the least recently used beyond `config`'s
+/// bound.
+async fn prune(pool: &SqlitePool, config: &CacheConfig, now: i64) -> Result<u64, String> {
+    let stale = sqlx::query("DELETE FROM response_cache WHERE created_at < ?")
+        .bind(config.cutoff(now))
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to prune response cache: {}", e))?;
+    let excess = sqlx::query(
+        "DELETE FROM response_cache WHERE key NOT IN
+             (SELECT key FROM response_cache ORDER BY used_at DESC LIMIT ?)",
+    )
+    .bind(config.max_entries as i64)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to prune response cache: {}", e))?;
+    Ok(stale.rows_affected() + excess.rows_affected())
+}
+
+async fn put(
+    pool: &SqlitePool,
+    config: &CacheConfig,
+    key: &str,
+    request: &CompletionRequest,
+    output: &CompletionOutput,
+    now: i64,
+) -> Result<(), String> {
+    sqlx::query(
+        "INSERT INTO response_cache
+             (key, provider, model, text, response_model, finish_reason, created_at, used_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7)
+         ON CONFLICT(key) DO UPDATE SET
+             text = excluded.text,
+             response_model = excluded.response_model,
+             finish_reason = excluded.finish_reason,
+             created_at = excluded.created_at,
+             used_at = excluded.used_at",
+    )
+    .bind(key)
+    .bind(request.provider.as_str())
+    .bind(request.model.trim())
+    .bind(&output.text)
+    .bind(&output.model)
+    .bind(&output.finish_reason)
+    .bind(now)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to write response cache: {}", e))?;
+    prune(pool, config, now).await?;
+    Ok(())
+}
+
+async fn stats(pool: &SqlitePool) -> Result<CacheStats, String> {
+    sqlx::query_as::<_, CacheStats>(
+        "SELECT COUNT(*) AS entries, COALESCE(SUM(hits), 0) AS hits, MIN(created_at) AS oldest_at
+         FROM response_cache",
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Failed to read response cache: {}", e))
+}
+
+async fn clear(pool: &SqlitePool) -> Result<u64, String> {
+    let cleared = sqlx::query("DELETE FROM response_cache")
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to clear response cache: {}", e))?;
+    Ok(cleared.rows_affected())
+}
+
+async fn load_config(pool: &SqlitePool) -> Result<CacheConfig, String> {
+    Ok(crate::settings::get_setting(pool, SETTING_KEY)
+        .await?
+        .unwrap_or_default())
+}
+
+// ============================================================================
+// Provider layer
+// ============================================================================
+
+/// The cache, when it is turned on. It lives as long as a completion, so
+/// the pool is looked up again for each read and write.
+pub(crate) struct ResponseCache {
+    app: AppHandle,
+    config: CacheConfig,
+}
+
+impl ResponseCache {
+    /// `None` when caching is off. Problems reading the setting are logged
+    /// and leave it off, so they never fail a completion.
+    pub(crate) async fn open(app: &AppHandle) -> Option<Self> {
+        let opened = async {
+            let pool = crate::db::pool(app).await?;
+            let config = load_config(&pool).await?;
+            Ok::<_, String>(Self {
+                app: app.clone(),
+                config,
+            })
+        }
+        .await;
+        match opened {
+            Ok(cache) if cache.config.enabled => Some(cache),
+            Ok(_) => None,
+            Err(e) => {
+                warn!("Response cache unavailable: {}", e);
+                None
+            }
+        }
+    }
+
+    pub(crate) async fn get(&self, key: &str) -> Option<CompletionOutput> {
+        let now = crate::db::now_millis();
+        let cached = async {
+            let pool = crate::db::pool(&self.app).await?;
+            get(&pool, key, self.config.cutoff(now), now).await
+        }
+        .await;
+        cached.unwrap_or_else(|e| {
+            warn!("{}", e);
+            None
+        })
+    }
+
+    /// Keep `output` for `key`, unless it is unfinished or empty.
+    pub(crate) async fn put(
+        &self,
+        key: &str,
+        request: &CompletionRequest,
+        output: &CompletionOutput,
+    ) {
+        if output.text.trim().is_empty() || output.finish_reason.as_deref() == Some(FINISH_ABORTED)
+        {
+            return;
+        }
+        let now = crate::db::now_millis();
+        let saved = async {
+            let pool = crate::db::pool(&self.app).await?;
+            put(&pool, &self.config, key, request, output, now).await
+        }
+        .await;
+        if let Err(e) = saved {
+            warn!("{}", e);
+        }
+    }
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+#[tauri::command]
+pub async fn get_response_cache_config(app: AppHandle) -> Result<CacheConfig, String> {
+    let pool = crate::db::pool(&app).await?;
+    load_config(&pool).await
+}
+
+/// Save the cache settings, pruning what no longer fits.
+#[tauri::command]
+pub async fn set_response_cache_config(app: AppHandle, config: CacheConfig) -> Result<(), String> {
+    if config.max_entries == 0 || config.max_entries > MAX_ENTRIES_LIMIT {
+        return Err(format!(
+            "maxEntries must be between 1 and {}",
+            MAX_ENTRIES_LIMIT
+        ));
+    }
+    crate::settings::set_setting(&app, SETTING_KEY, &config).await?;
+    let pool = crate::db::pool(&app).await?;
+    prune(&pool, &config, crate::db::now_millis()).await?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_response_cache_stats(app: AppHandle) -> Result<CacheStats, String> {
+    let pool = crate::db::pool(&app).await?;
+    stats(&pool).await
+}
+
+/// Forget every cached response, returning how many there were.
+#[tauri::command]
+pub async fn clear_response_cache(app: AppHandle) -> Result<u64, String> {
+    let pool = crate::db::pool(&app).await?;
+    clear(&pool).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(prompt: &str) -> CompletionRequest {
+        serde_json::from_value(json!({
+            "provider": "openai",
+            "model": "gpt-4o-mini",
+            "messages": [{ "role": "user", "content": prompt }]
+        }))
+        .unwrap()
+    }
+
+    fn answer(text: &str) -> CompletionOutput {
+        CompletionOutput {
+            text: text.to_string(),
+            finish_reason: Some("stop".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn keys_ignore_spacing_but_not_settings() {
+        let key = cache_key(&request("What is  the capital\nof France?"));
+        assert_eq!(key, cache_key(&request(" What is the capital of France? ")));
+        assert_ne!(key, cache_key(&request("What is the capital of Spain?")));
+
+        let mut warmer = request("What is the capital of France?");
+        warmer.temperature = Some(1.0);
+        assert_ne!(key, cache_key(&warmer));
+        let mut other_model = request("What is the capital of France?");
+        other_model.model = "gpt-4o".to_string();
+        assert_ne!(key, cache_key(&other_model));
+    }
+
+    #[tokio::test]
+    async fn cache_is_bounded_and_expires() {
+        let pool = crate::db::test_pool().await;
+        let config = CacheConfig {
+            enabled: true,
+            max_entries: 2,
+            max_age_days: 1,
+        };
+        for (i, prompt) in ["a", "b", "c"].into_iter().enumerate() {
+            let request = request(prompt);
+            let now = 1000 + i as i64;
+            put(
+                &pool,
+                &config,
+                &cache_key(&request),
+                &request,
+                &answer(prompt),
+                now,
+            )
+            .await
+            .unwrap();
+        }
+        // "a" was the least recently used
+        let a = cache_key(&request("a"));
+        assert!(get(&pool, &a, 0, 2000).await.unwrap().is_none());
+        let b = cache_key(&request("b"));
+        let hit = get(&pool, &b, 0, 2000).await.unwrap().unwrap();
+        assert_eq!(hit.text, "b");
+        assert!(hit.cached);
+
+        let stats = stats(&pool).await.unwrap();
+        assert_eq!((stats.entries, stats.hits), (2, 1));
+
+        let later = 1002 + DAY_MS + 1;
+        assert!(get(&pool, &b, config.cutoff(later), later)
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(prune(&pool, &config, later).await.unwrap(), 2);
+        assert_eq!(clear(&pool).await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,488 @@
+//! Side-by-side model comparisons.
+//!
+//! `compare_completions` sends one prompt to several models at once. Each
+//! model runs as its own completion with the request id
+//! `<comparisonId>:<position>`, so its text streams on its own
+//! `completion-delta` channel and `abort_completion` stops it alone.
+//! `comparison-started` lists the request ids up front, and
+//! `comparison-result` carries each answer as it finishes.
+//!
+//! Every comparison is kept (migration 29) with all of its answers, failures
+//! included, and the user can mark the answer they preferred.
+
+use super::{CompletionFailure, CompletionOutput, CompletionRequest, ProviderKind};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::time::Instant;
+use tauri::{AppHandle, Emitter};
+use tracing::warn;
+
+const MAX_MODELS: usize = 6;
+const DEFAULT_PAGE_SIZE: u32 = 50;
+
+/// One model to compare, with the request options that may differ between
+/// them.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelConfig {
+    pub provider: ProviderKind,
+    pub model: String,
+    #[serde(default)]
+    pub base_url: Option<String>,
+    #[serde(default)]
+    pub api_key_name: Option<String>,
+    #[serde(default)]
+    pub profile_id: Option<String>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+}
+
+impl ModelConfig {
+    fn request(&self, prompt: &str, system_prompt: Option<&str>) -> CompletionRequest {
+        CompletionRequest {
+            provider: self.provider,
+            model: self.model.trim().to_string(),
+            messages: vec![super::ChatMessage {
+                role: super::ChatRole::User,
+                content: prompt.to_string(),
+                images: Vec::new(),
+            }],
+            system_prompt: system_prompt.map(str::to_string),
+            temperature: self.temperature,
+            max_tokens: self.max_tokens,
+            base_url: self.base_url.clone(),
+            api_key_name: self.api_key_name.clone(),
+            retry: None,
+            profile_id: self.profile_id.clone(),
+            skip_cache: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct ComparisonResult {
+    pub position: i64,
+    /// Deltas for this answer arrive as `completion-delta` with this id.
+    pub request_id: String,
+    pub provider: String,
+    /// As reported by the server when it did, else as requested.
+    pub model: String,
+    pub text: String,
+    /// `"aborted"` when stopped with `abort_completion`; `text` is then
+    /// what had arrived.
+    pub finish_reason: Option<String>,
+    pub error: Option<String>,
+    pub input_tokens: Option<i64>,
+    pub output_tokens: Option<i64>,
+    pub latency_ms: i64,
+}
+
+impl ComparisonResult {
+    fn new(
+        position: usize,
+        request_id: &str,
+        request: &CompletionRequest,
+        result: Result<CompletionOutput, CompletionFailure>,
+        latency_ms: i64,
+    ) -> Self {
+        let mut compared = Self {
+            position: position as i64,
+            request_id: request_id.to_string(),
+            provider: request.provider.as_str().to_string(),
+            model: request.model.clone(),
+            text: String::new(),
+            finish_reason: None,
+            error: None,
+            input_tokens: None,
+            output_tokens: None,
+            latency_ms,
+        };
+        match result {
+            Ok(output) => {
+                if let Some(model) = output.model.filter(|model| !model.is_empty()) {
+                    compared.model = model;
+                }
+                compared.text = output.text;
+                compared.finish_reason = output.finish_reason;
+                compared.input_tokens = output.usage.as_ref().map(|u| u.input_tokens as i64);
+                compared.output_tokens = output.usage.as_ref().map(|u| u.output_tokens as i64);
+            }
+            Err(failure) => compared.error = Some(failure.to_string()),
+        }
+        compared
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Comparison {
+    pub id: String,
+    pub prompt: String,
+    pub system_prompt: Option<String>,
+    /// Position of the preferred answer.
+    pub winner: Option<i64>,
+    pub created_at: i64,
+    pub results: Vec<ComparisonResult>,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct ComparisonSummary {
+    pub id: String,
+    pub prompt: String,
+    pub winner: Option<i64>,
+    pub created_at: i64,
+    pub model_count: i64,
+}
+
+/// A model's channel, as announced in `comparison-started`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ComparisonChannel<'a> {
+    position: usize,
+    request_id: &'a str,
+    provider: ProviderKind,
+    model: &'a str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ComparisonStarted<'a> {
+    comparison_id: &'a str,
+    channels: Vec<ComparisonChannel<'a>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ComparisonFinished<'a> {
+    comparison_id: &'a str,
+    result: &'a ComparisonResult,
+}
+
+// ============================================================================
+// Storage
+// ============================================================================
+
+const RESULT_COLUMNS: &str = "position, request_id, provider, model, text, finish_reason, error,
+     input_tokens, output_tokens, latency_ms";
+
+async fn insert_comparison(
+    pool: &SqlitePool,
+    id: &str,
+    prompt: &str,
+    system_prompt: Option<&str>,
+    created_at: i64,
+) -> Result<(), String> {
+    sqlx::query(
+        "INSERT INTO comparisons (id, prompt, system_prompt, created_at) VALUES (?, ?, ?, ?)",
+    )
+    .bind(id)
+    .bind(prompt)
+    .bind(system_prompt)
+    .bind(created_at)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to save comparison: {}", e))?;
+    Ok(())
+}
+
+async fn insert_result(
+    pool: &SqlitePool,
+    comparison_id: &str,
+    result: &ComparisonResult,
+) -> Result<(), String> {
+    sqlx::query(&format!(
+        "INSERT INTO comparison_results (comparison_id, {})
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        RESULT_COLUMNS
+    ))
+    .bind(comparison_id)
+    .bind(result.position)
+    .bind(&result.request_id)
+    .bind(&result.provider)
+    .bind(&result.model)
+    .bind(&result.text)
+    .bind(&result.finish_reason)
+    .bind(&result.error)
+    .bind(result.input_tokens)
+    .bind(result.output_tokens)
+    .bind(result.latency_ms)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to save comparison result: {}", e))?;
+    Ok(())
+}
+
+async fn get(pool: &SqlitePool, id: &str) -> Result<Option<Comparison>, String> {
+    type Header = (String, String, Option<String>, Option<i64>, i64);
+    let header: Option<Header> = sqlx::query_as(
+        "SELECT id, prompt, system_prompt, winner, created_at FROM comparisons WHERE id = ?",
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to load comparison: {}", e))?;
+    let Some((id, prompt, system_prompt, winner, created_at)) = header else {
+        return Ok(None);
+    };
+    let results = sqlx::query_as::<_, ComparisonResult>(&format!(
+        "SELECT {} FROM comparison_results WHERE comparison_id = ? ORDER BY position ASC",
+        RESULT_COLUMNS
+    ))
+    .bind(&id)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load comparison results: {}", e))?;
+    Ok(Some(Comparison {
+        id,
+        prompt,
+        system_prompt,
+        winner,
+        created_at,
+        results,
+    }))
+}
+
+async fn list(
+    pool: &SqlitePool,
+    limit: u32,
+    offset: u32,
+) -> Result<Vec<ComparisonSummary>, String> {
+    sqlx::query_as::<_, ComparisonSummary>(
+        "SELECT c.id, c.prompt, c.winner, c.created_at,
+                (SELECT COUNT(*) FROM comparison_results r WHERE r.comparison_id = c.id)
+                    AS model_count
+         FROM comparisons c ORDER BY c.created_at DESC LIMIT ? OFFSET ?",
+    )
+    .bind(limit as i64)
+    .bind(offset as i64)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to list comparisons: {}", e))
+}
+
+async fn set_winner(pool: &SqlitePool, id: &str, position: Option<u32>) -> Result<bool, String> {
+    if let Some(position) = position {
+        let exists: Option<i64> = sqlx::query_scalar(
+            "SELECT position FROM comparison_results WHERE comparison_id = ? AND position = ?",
+        )
+        .bind(id)
+        .bind(position as i64)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Failed to load comparison results: {}", e))?;
+        if exists.is_none() {
+            return Err(format!("The comparison has no answer {}", position));
+        }
+    }
+    let updated = sqlx::query("UPDATE comparisons SET winner = ? WHERE id = ?")
+        .bind(position.map(i64::from))
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to update comparison: {}", e))?;
+    Ok(updated.rows_affected() > 0)
+}
+
+async fn delete(pool: &SqlitePool, id: &str) -> Result<bool, String> {
+    let deleted = sqlx::query("DELETE FROM comparisons WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to delete comparison: {}", e))?;
+    Ok(deleted.rows_affected() > 0)
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// Send `prompt` to every model in `models` at once and keep their answers.
+/// Returns once all of them have finished, failed or been aborted.
+#[tauri::command]
+pub async fn compare_completions(
+    app: AppHandle,
+    prompt: String,
+    models: Vec<ModelConfig>,
+    system_prompt: Option<String>,
+) -> Result<Comparison, String> {
+    if prompt.trim().is_empty() {
+        return Err("Nothing to compare: the prompt is empty".to_string());
+    }
+    if models.len() < 2 || models.len() > MAX_MODELS {
+        return Err(format!("Compare between 2 and {} models", MAX_MODELS));
+    }
+    if models.iter().any(|config| config.model.trim().is_empty()) {
+        return Err("Every compared model needs a name".to_string());
+    }
+    let system_prompt = system_prompt.filter(|prompt| !prompt.trim().is_empty());
+
+    let pool = crate::db::pool(&app).await?;
+    let id = uuid::Uuid::new_v4().to_string();
+    let created_at = crate::db::now_millis();
+    insert_comparison(&pool, &id, &prompt, system_prompt.as_deref(), created_at).await?;
+
+    let requests: Vec<(String, CompletionRequest)> = models
+        .iter()
+        .enumerate()
+        .map(|(position, config)| {
+            (
+                format!("{}:{}", id, position),
+                config.request(&prompt, system_prompt.as_deref()),
+            )
+        })
+        .collect();
+    let started = ComparisonStarted {
+        comparison_id: &id,
+        channels: requests
+            .iter()
+            .enumerate()
+            .map(|(position, (request_id, request))| ComparisonChannel {
+                position,
+                request_id,
+                provider: request.provider,
+                model: &request.model,
+            })
+            .collect(),
+    };
+    if let Err(e) = app.emit("comparison-started", started) {
+        warn!("Failed to emit comparison-started: {}", e);
+    }
+
+    let runs = requests
+        .iter()
+        .enumerate()
+        .map(|(position, (request_id, request))| {
+            let (app, pool, id) = (&app, &pool, &id);
+            async move {
+                let started = Instant::now();
+                let output = super::run_completion(app, "compare", request_id, request).await;
+                let latency_ms = started.elapsed().as_millis() as i64;
+                let result =
+                    ComparisonResult::new(position, request_id, request, output, latency_ms);
+                if let Err(e) = insert_result(pool, id, &result).await {
+                    warn!("{}", e);
+                }
+                let finished = ComparisonFinished {
+                    comparison_id: id,
+                    result: &result,
+                };
+                if let Err(e) = app.emit("comparison-result", finished) {
+                    warn!("Failed to emit comparison-result: {}", e);
+                }
+                result
+            }
+        });
+    let results = futures_util::future::join_all(runs).await;
+
+    Ok(Comparison {
+        id,
+        prompt,
+        system_prompt,
+        winner: None,
+        created_at,
+        results,
+    })
+}
+
+/// Past comparisons, newest first.
+#[tauri::command]
+pub async fn list_comparisons(
+    app: AppHandle,
+    limit: Option<u32>,
+    offset: Option<u32>,
+) -> Result<Vec<ComparisonSummary>, String> {
+    let pool = crate::db::pool(&app).await?;
+    list(
+        &pool,
+        limit.unwrap_or(DEFAULT_PAGE_SIZE),
+        offset.unwrap_or_default(),
+    )
+    .await
+}
+
+#[tauri::command]
+pub async fn get_comparison(app: AppHandle, id: String) -> Result<Option<Comparison>, String> {
+    let pool = crate::db::pool(&app).await?;
+    get(&pool, &id).await
+}
+
+/// Mark the answer at `position` as the preferred one; `None` clears it.
+#[tauri::command]
+pub async fn pick_comparison_winner(
+    app: AppHandle,
+    id: String,
+    position: Option<u32>,
+) -> Result<bool, String> {
+    let pool = crate::db::pool(&app).await?;
+    set_winner(&pool, &id, position).await
+}
+
+#[tauri::command]
+pub async fn delete_comparison(app: AppHandle, id: String) -> Result<bool, String> {
+    let pool = crate::db::pool(&app).await?;
+    delete(&pool, &id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::TokenUsage;
+
+    #[tokio::test]
+    async fn comparisons_keep_every_answer() {
+        let pool = crate::db::test_pool().await;
+        let config: ModelConfig = serde_json::from_value(serde_json::json!({
+            "provider": "ollama",
+            "model": " llama3 ",
+        }))
+        .unwrap();
+        let request = config.request("Which is faster?", None);
+        assert_eq!(request.model, "llama3");
+
+        insert_comparison(&pool, "c1", "Which is faster?", None, 1)
+            .await
+            .unwrap();
+        let answered = ComparisonResult::new(
+            1,
+            "c1:1",
+            &request,
+            Ok(CompletionOutput {
+                text: "The first.".to_string(),
+                model: Some("llama3:8b".to_string()),
+                finish_reason: Some("stop".to_string()),
+                usage: Some(TokenUsage {
+                    input_tokens: 5,
+                    output_tokens: 3,
+                }),
+                cached: false,
+            }),
+            120,
+        );
+        let failed = ComparisonResult::new(0, "c1:0", &request, Err("offline".into()), 10);
+        // Saved in the order they finish
+        insert_result(&pool, "c1", &answered).await.unwrap();
+        insert_result(&pool, "c1", &failed).await.unwrap();
+
+        let comparison = get(&pool, "c1").await.unwrap().unwrap();
+        assert_eq!(comparison.results, [failed, answered]);
+        assert_eq!(comparison.results[1].model, "llama3:8b");
+        assert_eq!(comparison.results[0].error.as_deref(), Some("offline"));
+
+        assert!(set_winner(&pool, "c1", Some(2)).await.is_err());
+        assert!(set_winner(&pool, "c1", Some(1)).await.unwrap());
+        let summaries = list(&pool, 10, 0).await.unwrap();
+        assert_eq!(summaries[0].winner, Some(1));
+        assert_eq!(summaries[0].model_count, 2);
+
+        assert!(delete(&pool, "c1").await.unwrap());
+        let orphans: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM comparison_results")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(orphans, 0);
+    }
+}
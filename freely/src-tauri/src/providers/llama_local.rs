@@ -0,0 +1,425 @@
+//! Fully local completions from GGUF models through llama.cpp.
+//!
+//! Models are files in the local model store (`models/gguf`, see
+//! [`crate::models`]) and a request's `model` is the file name. The last
+//! model used stays loaded, so follow-up requests skip the load. Generation
+//! runs on a blocking thread and hands each piece of text back to the async
+//! side as it is sampled.
+//!
+//! llama.cpp is a native build, so it is only compiled in with the
+//! `local-llm` feature; `local-llm-metal`, `local-llm-cuda` and
+//! `local-llm-vulkan` add a GPU backend. Without it the provider reports
+//! itself unavailable through `get_inference_capabilities`.
+
+use super::{ChatRole, CompletionOutput, CompletionProvider, CompletionRequest, TokenUsage};
+use crate::models::{self, LocalModel, ModelKind};
+use futures_util::future::BoxFuture;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+const DEFAULT_MAX_TOKENS: u32 = 1024;
+/// Context window used when the model was trained on a longer one; larger
+/// windows cost memory for every request.
+const MAX_CONTEXT_TOKENS: u32 = 8192;
+
+/// A chat turn ready for the model's chat template.
+#[derive(Debug, Clone, PartialEq)]
+struct Turn {
+    role: &'static str,
+    content: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Options {
+    temperature: f32,
+    max_tokens: u32,
+}
+
+#[derive(Debug)]
+struct Generated {
+    prompt_tokens: u32,
+    output_tokens: u32,
+    finish_reason: &'static str,
+}
+
+pub struct LlamaLocalProvider {
+    models_dir: PathBuf,
+}
+
+impl LlamaLocalProvider {
+    pub fn new(models_dir: PathBuf) -> Self {
+        Self { models_dir }
+    }
+
+    fn model_path(&self, model: &str) -> Result<PathBuf, String> {
+        let path = self.models_dir.join(models::validate_filename(model)?);
+        if !path.is_file() {
+            return Err(format!("Local model is not downloaded: {}", model));
+        }
+        Ok(path)
+    }
+}
+
+fn turns(request: &CompletionRequest) -> Result<Vec<Turn>, String> {
+    if request.messages.iter().any(|m| !m.images.is_empty()) {
+        return Err("Local models can't read images".to_string());
+    }
+    let system = request
+        .system_prompt
+        .as_deref()
+        .filter(|prompt| !prompt.trim().is_empty())
+        .map(|prompt| Turn {
+            role: "system",
+            content: prompt.to_string(),
+        });
+    let messages = request.messages.iter().map(|message| Turn {
+        role: match message.role {
+            ChatRole::System => "system",
+            ChatRole::User => "user",
+            ChatRole::Assistant => "assistant",
+        },
+        content: message.content.clone(),
+    });
+    Ok(system.into_iter().chain(messages).collect())
+}
+
+/// ChatML, for models whose GGUF file carries no chat template.
+fn chatml(turns: &[Turn]) -> String {
+    let mut prompt = String::new();
+    for turn in turns {
+        prompt.push_str(&format!(
+            "<|im_start|>{}\n{}<|im_end|>\n",
+            turn.role, turn.content
+        ));
+    }
+    prompt.push_str("<|im_start|>assistant\n");
+    prompt
+}
+
+/// Split off the longest valid UTF-8 prefix of `pending`. Tokens can end in
+/// the middle of a multi-byte character, so the rest waits for the next one.
+fn take_utf8(pending: &mut Vec<u8>) -> String {
+    let valid = match std::str::from_utf8(pending) {
+        Ok(text) => text.len(),
+        Err(e) => e.valid_up_to(),
+    };
+    let rest = pending.split_off(valid);
+    String::from_utf8(std::mem::replace(pending, rest)).unwrap_or_default()
+}
+
+impl CompletionProvider for LlamaLocalProvider {
+    fn stream_completion<'a>(
+        &'a self,
+        request: &'a CompletionRequest,
+        on_delta: &'a (dyn Fn(&str) + Send + Sync),
+    ) -> BoxFuture<'a, Result<CompletionOutput, String>> {
+        Box::pin(async move {
+            let path = self.model_path(&request.model)?;
+            let turns = turns(request)?;
+            let options = Options {
+                temperature: request.temperature.unwrap_or(0.7),
+                max_tokens: request.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            };
+
+            let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+            let generation = tokio::task::spawn_blocking(move || {
+                engine::generate(&path, &turns, options, &mut |piece| {
+                    // The receiver only goes away if the request was dropped
+                    let _ = tx.send(piece.to_string());
+                })
+            });
+
+            let mut text = String::new();
+            while let Some(piece) = rx.recv().await {
+                on_delta(&piece);
+                text.push_str(&piece);
+            }
+            let generated = generation
+                .await
+                .map_err(|e| format!("Local inference stopped: {}", e))??;
+
+            Ok(CompletionOutput {
+                text,
+                model: Some(request.model.clone()),
+                finish_reason: Some(generated.finish_reason.to_string()),
+                usage: Some(TokenUsage {
+                    input_tokens: generated.prompt_tokens,
+                    output_tokens: generated.output_tokens,
+                }),
+                cached: false,
+            })
+        })
+    }
+}
+
+#[cfg(feature = "local-llm")]
+mod engine {
+    use super::{chatml, take_utf8, Generated, Options, Turn, MAX_CONTEXT_TOKENS};
+    use llama_cpp_2::context::params::LlamaContextParams;
+    use llama_cpp_2::llama_backend::LlamaBackend;
+    use llama_cpp_2::llama_batch::LlamaBatch;
+    use llama_cpp_2::model::params::LlamaModelParams;
+    use llama_cpp_2::model::{AddBos, LlamaChatMessage, LlamaModel, Special};
+    use llama_cpp_2::sampling::LlamaSampler;
+    use once_cell::sync::{Lazy, OnceCell};
+    use parking_lot::Mutex;
+    use std::num::NonZeroU32;
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+
+    /// Offload every layer; llama.cpp caps this at the model's layer count.
+    const ALL_LAYERS: u32 = 999;
+
+    static BACKEND: OnceCell<LlamaBackend> = OnceCell::new();
+    static LOADED: Lazy<Mutex<Option<(PathBuf, Arc<LlamaModel>)>>> = Lazy::new(|| Mutex::new(None));
+
+    fn backend() -> Result<&'static LlamaBackend, String> {
+        BACKEND.get_or_try_init(|| {
+            LlamaBackend::init().map_err(|e| format!("Failed to start llama.cpp: {}", e))
+        })
+    }
+
+    pub(super) fn supports_gpu_offload() -> bool {
+        backend().is_ok_and(|backend| backend.supports_gpu_offload())
+    }
+
+    fn load(path: &Path) -> Result<Arc<LlamaModel>, String> {
+        let mut loaded = LOADED.lock();
+        if let Some((_, model)) = loaded.as_ref().filter(|(p, _)| p == path) {
+            return Ok(model.clone());
+        }
+        // Free the previous model before loading the next one
+        *loaded = None;
+
+        let backend = backend()?;
+        let layers = if backend.supports_gpu_offload() {
+            ALL_LAYERS
+        } else {
+            0
+        };
+        let params = LlamaModelParams::default().with_n_gpu_layers(layers);
+        let model = LlamaModel::load_from_file(backend, path, &params)
+            .map_err(|e| format!("Failed to load model: {}", e))?;
+        let model = Arc::new(model);
+        *loaded = Some((path.to_path_buf(), model.clone()));
+        Ok(model)
+    }
+
+    fn prompt(model: &LlamaModel, turns: &[Turn]) -> Result<String, String> {
+        let Ok(template) = model.chat_template(None) else {
+            return Ok(chatml(turns));
+        };
+        let messages = turns
+            .iter()
+            .map(|turn| LlamaChatMessage::new(turn.role.to_string(), turn.content.clone()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Invalid message: {}", e))?;
+        model
+            .apply_chat_template(&template, &messages, true)
+            .map_err(|e| format!("Failed to apply chat template: {}", e))
+    }
+
+    pub(super) fn generate(
+        path: &Path,
+        turns: &[Turn],
+        options: Options,
+        on_piece: &mut dyn FnMut(&str),
+    ) -> Result<Generated, String> {
+        let backend = backend()?;
+        let model = load(path)?;
+        let prompt = prompt(&model, turns)?;
+        let tokens = model
+            .str_to_token(&prompt, AddBos::Always)
+            .map_err(|e| format!("Failed to tokenize prompt: {}", e))?;
+
+        let n_ctx = model.n_ctx_train().clamp(1, MAX_CONTEXT_TOKENS);
+        if tokens.len() as u32 >= n_ctx {
+            return Err(format!(
+                "Prompt is {} tokens, more than the {} the model can take",
+                tokens.len(),
+                n_ctx
+            ));
+        }
+        let max_tokens = options.max_tokens.min(n_ctx - tokens.len() as u32);
+        let params = LlamaContextParams::default().with_n_ctx(NonZeroU32::new(n_ctx));
+        let mut ctx = model
+            .new_context(backend, params)
+            .map_err(|e| format!("Failed to create inference context: {}", e))?;
+
+        let mut batch = LlamaBatch::new(n_ctx as usize, 1);
+        let last = tokens.len() as i32 - 1;
+        for (position, token) in (0i32..).zip(&tokens) {
+            batch
+                .add(*token, position, &[0], position == last)
+                .map_err(|e| format!("Failed to queue prompt: {}", e))?;
+        }
+        ctx.decode(&mut batch)
+            .map_err(|e| format!("Failed to read prompt: {}", e))?;
+
+        let mut sampler = if options.temperature <= 0.0 {
+            LlamaSampler::greedy()
+        } else {
+            LlamaSampler::chain_simple([
+                LlamaSampler::temp(options.temperature),
+                LlamaSampler::dist(rand_seed()),
+            ])
+        };
+
+        let mut position = batch.n_tokens();
+        let mut pending = Vec::new();
+        let mut output_tokens = 0;
+        let mut finish_reason = "length";
+        while output_tokens < max_tokens {
+            let token = sampler.sample(&ctx, batch.n_tokens() - 1);
+            sampler.accept(token);
+            if model.is_eog_token(token) {
+                finish_reason = "stop";
+                break;
+            }
+            output_tokens += 1;
+
+            let bytes = model
+                .token_to_bytes(token, Special::Tokenize)
+                .map_err(|e| format!("Failed to decode token: {}", e))?;
+            pending.extend_from_slice(&bytes);
+            let piece = take_utf8(&mut pending);
+            if !piece.is_empty() {
+                on_piece(&piece);
+            }
+
+            batch.clear();
+            batch
+                .add(token, position, &[0], true)
+                .map_err(|e| format!("Failed to queue token: {}", e))?;
+            position += 1;
+            ctx.decode(&mut batch)
+                .map_err(|e| format!("Inference failed: {}", e))?;
+        }
+
+        Ok(Generated {
+            prompt_tokens: tokens.len() as u32,
+            output_tokens,
+            finish_reason,
+        })
+    }
+
+    fn rand_seed() -> u32 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(not(feature = "local-llm"))]
+mod engine {
+    use super::{Generated, Options, Turn};
+    use std::path::Path;
+
+    pub(super) fn supports_gpu_offload() -> bool {
+        false
+    }
+
+    pub(super) fn generate(
+        _path: &Path,
+        _turns: &[Turn],
+        _options: Options,
+        _on_piece: &mut dyn FnMut(&str),
+    ) -> Result<Generated, String> {
+        Err("This build doesn't include local inference".to_string())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InferenceCapabilities {
+    /// Whether this build can run GGUF models at all.
+    pub available: bool,
+    /// GPU backend llama.cpp was built with: `metal`, `cuda`, `vulkan`, or
+    /// `cpu` when there is none.
+    pub backend: &'static str,
+    /// Whether layers are actually offloaded to the GPU on this machine.
+    pub gpu_offload: bool,
+    pub cpu_threads: usize,
+    /// GGUF files in the local model store, usable as the request `model`.
+    pub models: Vec<LocalModel>,
+}
+
+fn compiled_backend() -> &'static str {
+    if cfg!(feature = "local-llm-metal") {
+        "metal"
+    } else if cfg!(feature = "local-llm-cuda") {
+        "cuda"
+    } else if cfg!(feature = "local-llm-vulkan") {
+        "vulkan"
+    } else {
+        "cpu"
+    }
+}
+
+/// Directory [`LlamaLocalProvider`] reads models from.
+pub(crate) fn models_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    models::models_dir(app, ModelKind::Gguf)
+}
+
+fn is_gguf(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("gguf"))
+}
+
+#[tauri::command]
+pub fn get_inference_capabilities(app: AppHandle) -> Result<InferenceCapabilities, String> {
+    let models = models::list_local_models(app, Some(ModelKind::Gguf))?
+        .into_iter()
+        .filter(|model| is_gguf(Path::new(&model.path)))
+        .collect();
+    Ok(InferenceCapabilities {
+        available: cfg!(feature = "local-llm"),
+        backend: compiled_backend(),
+        gpu_offload: engine::supports_gpu_offload(),
+        cpu_threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+        models,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::{ChatMessage, ProviderKind};
+
+    #[test]
+    fn prompts_keep_the_system_prompt_first() {
+        let request = CompletionRequest {
+            provider: ProviderKind::LlamaLocal,
+            model: "qwen2.5-0.5b-instruct-q4_k_m.gguf".to_string(),
+            messages: vec![ChatMessage {
+                role: ChatRole::User,
+                content: "hi".to_string(),
+                images: Vec::new(),
+            }],
+            system_prompt: Some("Be brief.".to_string()),
+            temperature: None,
+            max_tokens: None,
+            base_url: None,
+            api_key_name: None,
+            retry: None,
+            profile_id: None,
+            skip_cache: false,
+        };
+        let turns = turns(&request).unwrap();
+        assert_eq!(turns[0].role, "system");
+        assert_eq!(
+            chatml(&turns),
+            "<|im_start|>system\nBe brief.<|im_end|>\n\
+             <|im_start|>user\nhi<|im_end|>\n<|im_start|>assistant\n"
+        );
+
+        // "é" is two bytes; a token ending between them waits for the next
+        let mut pending = b"caf\xc3".to_vec();
+        assert_eq!(take_utf8(&mut pending), "caf");
+        pending.push(0xa9);
+        assert_eq!(take_utf8(&mut pending), "é");
+        assert!(pending.is_empty());
+    }
+}
@@ -0,0 +1,227 @@
+//! Retry with backoff for provider requests.
+//!
+//! Only the initial request is retried: once a stream has started sending
+//! tokens, a failure is reported instead of replaying the prompt. Rate limits
+//! (429), overload (529) and transient 5xx responses are retried, honouring
+//! `Retry-After` when the server sends it.
+
+use chrono::{DateTime, Utc};
+use reqwest::header::HeaderMap;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetryPolicy {
+    /// Total attempts including the first; 1 disables retrying.
+    pub max_attempts: u32,
+    pub base_delay_ms: u64,
+    /// Longest wait between attempts. A `Retry-After` beyond this is treated
+    /// as a hard failure rather than stalling the UI.
+    pub max_delay_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay_ms: 1_000,
+            max_delay_ms: 30_000,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Exponential backoff for the wait after failed attempt `attempt` (1-based).
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u64.saturating_pow(attempt.saturating_sub(1));
+        Duration::from_millis(
+            self.base_delay_ms
+                .saturating_mul(factor)
+                .min(self.max_delay_ms),
+        )
+    }
+}
+
+/// Sent to the UI before each wait.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryNotice {
+    /// The attempt that just failed (1-based).
+    pub attempt: u32,
+    pub max_attempts: u32,
+    pub delay_ms: u64,
+    pub reason: String,
+}
+
+pub type RetryCallback = Arc<dyn Fn(&RetryNotice) + Send + Sync>;
+
+/// A policy plus an optional observer, shared by every request a provider makes.
+#[derive(Clone, Default)]
+pub struct Retry {
+    pub policy: RetryPolicy,
+    pub on_retry: Option<RetryCallback>,
+}
+
+impl Retry {
+    pub fn new(policy: RetryPolicy, on_retry: Option<RetryCallback>) -> Self {
+        Self { policy, on_retry }
+    }
+
+    /// Send `request`, retrying transient failures. The final response is
+    /// returned as-is, so non-retryable statuses still reach
+    /// `error_for_status` with the server's message intact.
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response, String> {
+        let max_attempts = self.policy.max_attempts.max(1);
+        let mut attempt = 1;
+
+        loop {
+            // Bodies built with `.json()` can always be cloned
+            let this_try = request
+                .try_clone()
+                .ok_or("Request body cannot be retried")?;
+            let (reason, delay) = match this_try.send().await {
+                Ok(response) if !is_retryable_status(response.status()) => return Ok(response),
+                Ok(response) => {
+                    let delay = match retry_after(response.headers(), Utc::now()) {
+                        Some(delay) if delay > Duration::from_millis(self.policy.max_delay_ms) => {
+                            return Ok(response);
+                        }
+                        Some(delay) => delay,
+                        None => self.policy.backoff(attempt),
+                    };
+                    if attempt >= max_attempts {
+                        return Ok(response);
+                    }
+                    (status_reason(response.status()), delay)
+                }
+                Err(e) if attempt < max_attempts && (e.is_connect() || e.is_timeout()) => (
+                    format!("Connection failed: {}", e),
+                    self.policy.backoff(attempt),
+                ),
+                Err(e) => return Err(format!("Failed to reach provider: {}", e)),
+            };
+
+            if let Some(on_retry) = &self.on_retry {
+                on_retry(&RetryNotice {
+                    attempt,
+                    max_attempts,
+                    delay_ms: delay.as_millis() as u64,
+                    reason,
+                });
+            }
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(status.as_u16(), 408 | 429 | 500 | 502 | 503 | 504 | 529)
+}
+
+fn status_reason(status: StatusCode) -> String {
+    match status.as_u16() {
+        429 => "Rate limited".to_string(),
+        529 => "Provider overloaded".to_string(),
+        _ => format!("Provider returned {}", status),
+    }
+}
+
+/// Parse the wait a server asked for. Besides the standard `Retry-After`
+/// (seconds or HTTP date), OpenAI sends a more precise `retry-after-ms`.
+pub(crate) fn retry_after(headers: &HeaderMap, now: DateTime<Utc>) -> Option<Duration> {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+    };
+
+    if let Some(ms) = header("retry-after-ms").and_then(|v| v.parse::<f64>().ok()) {
+        if ms.is_finite() && ms >= 0.0 {
+            return Some(Duration::from_millis(ms as u64));
+        }
+    }
+
+    let value = header("retry-after")?;
+    if let Ok(secs) = value.parse::<f64>() {
+        return Duration::try_from_secs_f64(secs).ok();
+    }
+    let at = DateTime::parse_from_rfc2822(value)
+        .ok()?
+        .with_timezone(&Utc);
+    Some((at - now).to_std().unwrap_or(Duration::ZERO))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for &(name, value) in pairs {
+            map.insert(name, HeaderValue::from_static(value));
+        }
+        map
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            base_delay_ms: 500,
+            max_delay_ms: 3_000,
+        };
+        let delays: Vec<u64> = (1..=5)
+            .map(|a| policy.backoff(a).as_millis() as u64)
+            .collect();
+        assert_eq!(delays, [500, 1_000, 2_000, 3_000, 3_000]);
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_millis(3_000));
+    }
+
+    #[test]
+    fn retry_after_accepts_seconds_dates_and_milliseconds() {
+        let now = DateTime::parse_from_rfc3339("2015-10-21T07:28:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert_eq!(
+            retry_after(&headers(&[("retry-after", "3")]), now),
+            Some(Duration::from_secs(3))
+        );
+        assert_eq!(
+            retry_after(
+                &headers(&[("retry-after", "Wed, 21 Oct 2015 07:28:05 GMT")]),
+                now
+            ),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(
+            retry_after(
+                &headers(&[("retry-after", "Wed, 21 Oct 2015 07:27:00 GMT")]),
+                now
+            ),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(
+            retry_after(
+                &headers(&[("retry-after", "2"), ("retry-after-ms", "1500")]),
+                now
+            ),
+            Some(Duration::from_millis(1_500))
+        );
+        assert_eq!(retry_after(&headers(&[("retry-after", "soon")]), now), None);
+        assert_eq!(retry_after(&HeaderMap::new(), now), None);
+    }
+
+    #[test]
+    fn policy_fields_default_individually() {
+        let policy: RetryPolicy = serde_json::from_str(r#"{"maxAttempts":5}"#).unwrap();
+        assert_eq!(policy.max_attempts, 5);
+        assert_eq!(policy.base_delay_ms, RetryPolicy::default().base_delay_ms);
+    }
+}
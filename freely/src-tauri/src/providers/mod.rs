@@ -0,0 +1,676 @@
+//! LLM completions made from Rust.
+//!
+//! The webview sends a [`CompletionRequest`] and receives tokens as
+//! `completion-delta` events tagged with its `requestId`. Requests go out
+//! through reqwest, so there is no CORS proxying, and API keys are read from
+//! the secrets store here and never handed to the renderer. Each completed
+//! request is recorded by [`crate::usage`].
+//!
+//! A completion can be stopped with `abort_completion`. The text received so
+//! far is kept rather than discarded: it is the output, with
+//! `finishReason: "aborted"`, and is saved as a truncated reply when the
+//! request names its conversation. Repeated prompts can be answered from
+//! [`cache`].
+
+pub mod anthropic;
+pub mod cache;
+pub mod compare;
+pub mod llama_local;
+pub mod middleware;
+pub mod ollama;
+pub mod openai;
+pub mod probe;
+pub mod profiles;
+pub(crate) mod sse;
+
+use futures_util::future::BoxFuture;
+use middleware::{Retry, RetryNotice, RetryPolicy};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::oneshot;
+use tracing::warn;
+
+/// `finishReason` of a completion stopped by `abort_completion`.
+pub(crate) const FINISH_ABORTED: &str = "aborted";
+
+/// Completions that can still be aborted, by request id.
+static IN_FLIGHT: Lazy<Mutex<HashMap<String, oneshot::Sender<()>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProviderKind {
+    #[serde(rename = "openai")]
+    OpenAi,
+    #[serde(rename = "anthropic")]
+    Anthropic,
+    /// Any server speaking OpenAI's `/chat/completions` API (Groq, OpenRouter,
+    /// LM Studio, vLLM, ...). Needs a `baseUrl`.
+    #[serde(rename = "openai-compatible")]
+    OpenAiCompatible,
+    /// A local Ollama server; no API key.
+    #[serde(rename = "ollama")]
+    Ollama,
+    /// A GGUF model from the local model store, run in-process by llama.cpp.
+    #[serde(rename = "llama-local")]
+    LlamaLocal,
+}
+
+impl ProviderKind {
+    /// Same as the serialized name.
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            ProviderKind::OpenAi => "openai",
+            ProviderKind::Anthropic => "anthropic",
+            ProviderKind::OpenAiCompatible => "openai-compatible",
+            ProviderKind::Ollama => "ollama",
+            ProviderKind::LlamaLocal => "llama-local",
+        }
+    }
+
+    /// Runs on this machine, so requests cost nothing.
+    pub(crate) fn is_local(self) -> bool {
+        matches!(self, ProviderKind::Ollama | ProviderKind::LlamaLocal)
+    }
+
+    /// Secrets entry the API key is read from when the request names none.
+    fn default_key_name(self) -> Option<&'static str> {
+        match self {
+            ProviderKind::OpenAi => Some("openai"),
+            ProviderKind::Anthropic => Some("anthropic"),
+            ProviderKind::OpenAiCompatible => Some("openai-compatible"),
+            ProviderKind::Ollama | ProviderKind::LlamaLocal => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatRole {
+    System,
+    User,
+    Assistant,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatMessage {
+    pub role: ChatRole,
+    pub content: String,
+    /// Base64 images (raw or data URLs) attached to this message.
+    #[serde(default)]
+    pub images: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletionRequest {
+    pub provider: ProviderKind,
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    /// Overrides the provider's default endpoint; required for
+    /// [`ProviderKind::OpenAiCompatible`].
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Secrets entry holding the API key, defaulting to the provider name.
+    #[serde(default)]
+    pub api_key_name: Option<String>,
+    /// Overrides the default retry policy for rate limits and outages.
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
+    /// Named endpoint from `provider_profiles`, used in place of
+    /// `baseUrl` and `apiKeyName`; `provider` should be `openai-compatible`.
+    #[serde(default)]
+    pub profile_id: Option<String>,
+    /// Ask the provider even when the response cache has an answer, e.g. to
+    /// regenerate one. The new answer replaces the cached one.
+    #[serde(default)]
+    pub skip_cache: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenUsage {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletionOutput {
+    pub text: String,
+    /// Model name reported by the server, which may differ from the alias
+    /// that was requested.
+    pub model: Option<String>,
+    pub finish_reason: Option<String>,
+    pub usage: Option<TokenUsage>,
+    /// Served from the response cache rather than the provider.
+    pub cached: bool,
+}
+
+/// A backend that can stream a chat completion.
+///
+/// `on_delta` is called with each text fragment as it arrives; the returned
+/// output holds the full text once the stream ends.
+pub trait CompletionProvider: Send + Sync {
+    fn stream_completion<'a>(
+        &'a self,
+        request: &'a CompletionRequest,
+        on_delta: &'a (dyn Fn(&str) + Send + Sync),
+    ) -> BoxFuture<'a, Result<CompletionOutput, String>>;
+}
+
+/// Build the provider for `kind`. `api_key` may be `None` for local
+/// OpenAI-compatible servers that don't check it. For
+/// [`ProviderKind::LlamaLocal`], `base_url` is the directory GGUF files are
+/// read from; [`connect`] points it at the local model store.
+pub fn build_provider(
+    kind: ProviderKind,
+    base_url: Option<&str>,
+    api_key: Option<String>,
+    retry: Retry,
+) -> Result<Box<dyn CompletionProvider>, String> {
+    let client = crate::net::client::http_client(Some(kind))?;
+    let base_url = base_url.map(|url| url.trim().trim_end_matches('/').to_string());
+
+    Ok(match kind {
+        ProviderKind::OpenAi => Box::new(openai::OpenAiProvider::new(
+            client,
+            base_url.unwrap_or_else(|| openai::DEFAULT_BASE_URL.to_string()),
+            Some(api_key.ok_or("No API key stored for OpenAI")?),
+            retry,
+        )),
+        ProviderKind::OpenAiCompatible => Box::new(openai::OpenAiProvider::new(
+            client,
+            base_url
+                .filter(|url| !url.is_empty())
+                .ok_or("An OpenAI-compatible provider needs a base URL")?,
+            api_key,
+            retry,
+        )),
+        ProviderKind::Anthropic => Box::new(anthropic::AnthropicProvider::new(
+            client,
+            base_url.unwrap_or_else(|| anthropic::DEFAULT_BASE_URL.to_string()),
+            api_key.ok_or("No API key stored for Anthropic")?,
+            retry,
+        )),
+        ProviderKind::Ollama => Box::new(ollama::OllamaProvider::new(
+            client,
+            base_url.unwrap_or_else(|| ollama::DEFAULT_BASE_URL.to_string()),
+            retry,
+        )),
+        ProviderKind::LlamaLocal => Box::new(llama_local::LlamaLocalProvider::new(
+            base_url
+                .filter(|dir| !dir.is_empty())
+                .ok_or("A local model provider needs its models directory")?
+                .into(),
+        )),
+    })
+}
+
+/// Build the provider for `kind` with its API key loaded from the secrets
+/// store. `api_key_name` overrides the provider's default entry.
+pub(crate) async fn connect(
+    app: &AppHandle,
+    kind: ProviderKind,
+    base_url: Option<&str>,
+    api_key_name: Option<&str>,
+    retry: Retry,
+) -> Result<Box<dyn CompletionProvider>, String> {
+    if kind == ProviderKind::LlamaLocal {
+        let dir = llama_local::models_dir(app)?;
+        return build_provider(kind, Some(&dir.to_string_lossy()), None, retry);
+    }
+    crate::net::connectivity::require_online_for(kind, base_url)?;
+    let api_key = match api_key_name.or(kind.default_key_name()) {
+        Some(name) => crate::secrets::load_api_key(app, name).await?,
+        None => None,
+    };
+    build_provider(kind, base_url, api_key, retry)
+}
+
+/// Split a base64 image (raw or data URL) into its media type and payload.
+/// Raw base64 is assumed to be PNG, which is what the screenshot commands
+/// produce.
+pub(crate) fn split_image(image: &str) -> (&str, &str) {
+    image
+        .strip_prefix("data:")
+        .and_then(|rest| rest.split_once(";base64,"))
+        .unwrap_or(("image/png", image))
+}
+
+/// Turn a non-2xx response into an error, keeping the server's message.
+pub(crate) async fn error_for_status(
+    response: reqwest::Response,
+) -> Result<reqwest::Response, String> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let body = response.text().await.unwrap_or_default();
+    let message = serde_json::from_str::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|json| {
+            json.pointer("/error/message")
+                .or_else(|| json.get("error"))
+                .and_then(|m| m.as_str().map(str::to_string))
+        })
+        .unwrap_or(body);
+    Err(format!("Provider returned {}: {}", status, message.trim()))
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CompletionDelta<'a> {
+    request_id: &'a str,
+    delta: &'a str,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ProviderRetry<'a> {
+    request_id: &'a str,
+    #[serde(flatten)]
+    notice: &'a RetryNotice,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CompletionDone<'a> {
+    request_id: &'a str,
+    output: &'a CompletionOutput,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CompletionAborted<'a> {
+    request_id: &'a str,
+    output: &'a CompletionOutput,
+    /// The truncated reply saved to the conversation, if one was named.
+    message_id: Option<&'a str>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CompletionError<'a> {
+    request_id: &'a str,
+    error: &'a str,
+    /// Machine-readable reason, for failures the UI handles specially.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<&'static str>,
+}
+
+/// Why a completion didn't run.
+#[derive(Debug)]
+pub(crate) enum CompletionFailure {
+    /// Refused before sending because a monthly spend limit was reached.
+    BudgetExceeded(crate::usage::BudgetExceeded),
+    Failed(String),
+}
+
+impl CompletionFailure {
+    fn code(&self) -> Option<&'static str> {
+        match self {
+            CompletionFailure::BudgetExceeded(_) => Some("budget-exceeded"),
+            CompletionFailure::Failed(_) => None,
+        }
+    }
+}
+
+impl std::fmt::Display for CompletionFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompletionFailure::BudgetExceeded(exceeded) => exceeded.fmt(f),
+            CompletionFailure::Failed(error) => f.write_str(error),
+        }
+    }
+}
+
+impl From<String> for CompletionFailure {
+    fn from(error: String) -> Self {
+        CompletionFailure::Failed(error)
+    }
+}
+
+impl From<&str> for CompletionFailure {
+    fn from(error: &str) -> Self {
+        CompletionFailure::Failed(error.to_string())
+    }
+}
+
+/// Stream a completion. Text arrives as `completion-delta` events; the run
+/// ends with `completion-done`, `completion-aborted` or `completion-error`.
+/// Waits before a retry are announced with `provider-retry`. Requests over a
+/// monthly budget are refused with `code: "budget-exceeded"`. The final
+/// output is also returned, so callers that don't need live tokens can just
+/// await it. `conversation_id` is where an aborted reply is saved.
+#[tauri::command]
+pub async fn stream_completion(
+    app: AppHandle,
+    request_id: String,
+    request: CompletionRequest,
+    conversation_id: Option<String>,
+) -> Result<CompletionOutput, String> {
+    let result = run_completion(&app, "chat", &request_id, &request).await;
+
+    if let Ok(output) = &result {
+        if output.finish_reason.as_deref() == Some(FINISH_ABORTED) {
+            let message_id = match &conversation_id {
+                Some(conversation_id) => save_truncated(&app, conversation_id, output).await,
+                None => None,
+            };
+            let payload = CompletionAborted {
+                request_id: &request_id,
+                output,
+                message_id: message_id.as_deref(),
+            };
+            if let Err(e) = app.emit("completion-aborted", payload) {
+                warn!("Failed to emit completion result: {}", e);
+            }
+            return Ok(output.clone());
+        }
+        crate::automations::fire(
+            &app,
+            crate::automations::Trigger::ConversationFinished,
+            serde_json::json!({
+                "requestId": request_id,
+                "provider": request.provider,
+                "model": output.model.as_deref().unwrap_or(&request.model),
+                "finishReason": output.finish_reason,
+                "usage": output.usage,
+                "content": output.text,
+            }),
+        );
+    }
+
+    let emitted = match &result {
+        Ok(output) => app.emit(
+            "completion-done",
+            CompletionDone {
+                request_id: &request_id,
+                output,
+            },
+        ),
+        Err(failure) => app.emit(
+            "completion-error",
+            CompletionError {
+                request_id: &request_id,
+                error: &failure.to_string(),
+                code: failure.code(),
+            },
+        ),
+    };
+    if let Err(e) = emitted {
+        warn!("Failed to emit completion result: {}", e);
+    }
+    result.map_err(|failure| failure.to_string())
+}
+
+/// Stop a completion started with `stream_completion`. It ends with the
+/// text received so far.
+#[tauri::command]
+pub fn abort_completion(request_id: String) -> Result<(), String> {
+    let abort = IN_FLIGHT
+        .lock()
+        .remove(&request_id)
+        .ok_or_else(|| format!("No completion in progress: {}", request_id))?;
+    // The completion may have finished in the meantime
+    let _ = abort.send(());
+    Ok(())
+}
+
+/// Append an aborted reply to `conversation_id`, flagged as truncated,
+/// returning its id. Nothing is saved if no text had arrived.
+async fn save_truncated(
+    app: &AppHandle,
+    conversation_id: &str,
+    output: &CompletionOutput,
+) -> Option<String> {
+    if output.text.trim().is_empty() {
+        return None;
+    }
+    let message = crate::db::chat::Message {
+        id: uuid::Uuid::new_v4().to_string(),
+        role: crate::db::chat::MessageRole::Assistant,
+        content: output.text.clone(),
+        timestamp: crate::db::now_millis(),
+        attached_files: None,
+        truncated: true,
+    };
+    let saved = async {
+        let pool = crate::db::pool(app).await?;
+        crate::db::chat::append(&pool, conversation_id, &message).await
+    }
+    .await;
+    match saved {
+        Ok(()) => Some(message.id),
+        Err(e) => {
+            warn!("Failed to save aborted reply: {}", e);
+            None
+        }
+    }
+}
+
+/// Run `provider` until it finishes or `abort` fires. An aborted run ends
+/// with the text received so far and `finishReason: "aborted"`.
+async fn stream_or_abort(
+    provider: &dyn CompletionProvider,
+    request: &CompletionRequest,
+    on_delta: &(dyn Fn(&str) + Send + Sync),
+    abort: oneshot::Receiver<()>,
+) -> Result<CompletionOutput, String> {
+    let partial = Mutex::new(String::new());
+    let collect = |delta: &str| {
+        partial.lock().push_str(delta);
+        on_delta(delta);
+    };
+    tokio::select! {
+        output = provider.stream_completion(request, &collect) => output,
+        Ok(()) = abort => Ok(CompletionOutput {
+            text: std::mem::take(&mut *partial.lock()),
+            finish_reason: Some(FINISH_ABORTED.to_string()),
+            ..Default::default()
+        }),
+    }
+}
+
+/// Run a completion that `abort_completion` can stop. Deltas are emitted
+/// for `request_id` and usage is recorded under `source`.
+pub(crate) async fn run_completion(
+    app: &AppHandle,
+    source: &str,
+    request_id: &str,
+    request: &CompletionRequest,
+) -> Result<CompletionOutput, CompletionFailure> {
+    let (abort_tx, abort_rx) = oneshot::channel();
+    IN_FLIGHT.lock().insert(request_id.to_string(), abort_tx);
+    let result = complete(app, source, request_id, request, abort_rx).await;
+    IN_FLIGHT.lock().remove(request_id);
+    result
+}
+
+async fn complete(
+    app: &AppHandle,
+    source: &str,
+    request_id: &str,
+    request: &CompletionRequest,
+    abort: oneshot::Receiver<()>,
+) -> Result<CompletionOutput, CompletionFailure> {
+    if request.model.trim().is_empty() {
+        return Err("No model selected".into());
+    }
+    if request.messages.is_empty() {
+        return Err("Completion request has no messages".into());
+    }
+    let redacted;
+    let request =
+        match crate::redaction::for_prompt(app, request.provider, request.base_url.as_deref())
+            .await?
+        {
+            Some(redactor) => {
+                redacted = redactor.request(request);
+                &redacted
+            }
+            None => request,
+        };
+    let on_delta = |delta: &str| {
+        if let Err(e) = app.emit("completion-delta", CompletionDelta { request_id, delta }) {
+            warn!("Failed to emit completion delta: {}", e);
+        }
+    };
+
+    // Hits cost nothing, so they are served even over budget
+    let cache = cache::ResponseCache::open(app).await;
+    let cache_key = cache.as_ref().map(|_| cache::cache_key(request));
+    if let (Some(cache), Some(key), false) = (&cache, &cache_key, request.skip_cache) {
+        if let Some(output) = cache.get(key).await {
+            on_delta(&output.text);
+            return Ok(output);
+        }
+    }
+    if let Some(exceeded) = crate::usage::budget_exceeded(app, request.provider).await {
+        return Err(CompletionFailure::BudgetExceeded(exceeded));
+    }
+
+    let retry_app = app.clone();
+    let retry_request_id = request_id.to_string();
+    let retry = Retry::new(
+        request.retry.unwrap_or_default(),
+        Some(Arc::new(move |notice: &RetryNotice| {
+            let payload = ProviderRetry {
+                request_id: &retry_request_id,
+                notice,
+            };
+            if let Err(e) = retry_app.emit("provider-retry", payload) {
+                warn!("Failed to emit provider retry: {}", e);
+            }
+        })),
+    );
+    let provider = match &request.profile_id {
+        Some(profile_id) => profiles::connect(app, profile_id, retry).await?,
+        None => {
+            connect(
+                app,
+                request.provider,
+                request.base_url.as_deref(),
+                request.api_key_name.as_deref(),
+                retry,
+            )
+            .await?
+        }
+    };
+
+    let started = Instant::now();
+    // Counted even when aborted; tokens generated so far are still billed
+    let output = stream_or_abort(provider.as_ref(), request, &on_delta, abort).await?;
+    crate::usage::record(
+        app,
+        source,
+        Some(request_id),
+        request,
+        &output,
+        started.elapsed(),
+    )
+    .await;
+    if let (Some(cache), Some(key)) = (&cache, &cache_key) {
+        cache.put(key, request, &output).await;
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_deserializes_from_frontend_shape() {
+        let request: CompletionRequest = serde_json::from_value(serde_json::json!({
+            "provider": "openai-compatible",
+            "model": "llama-3.1-8b",
+            "baseUrl": "http://localhost:1234/v1",
+            "messages": [{ "role": "user", "content": "hi" }]
+        }))
+        .unwrap();
+
+        assert_eq!(request.provider, ProviderKind::OpenAiCompatible);
+        assert_eq!(request.messages[0].role, ChatRole::User);
+        assert!(request.messages[0].images.is_empty());
+        assert_eq!(request.system_prompt, None);
+    }
+
+    #[test]
+    fn images_split_into_media_type_and_data() {
+        assert_eq!(
+            split_image("data:image/jpeg;base64,AAAA"),
+            ("image/jpeg", "AAAA")
+        );
+        assert_eq!(split_image("AAAA"), ("image/png", "AAAA"));
+    }
+
+    struct Stalls;
+
+    impl CompletionProvider for Stalls {
+        fn stream_completion<'a>(
+            &'a self,
+            _request: &'a CompletionRequest,
+            on_delta: &'a (dyn Fn(&str) + Send + Sync),
+        ) -> BoxFuture<'a, Result<CompletionOutput, String>> {
+            Box::pin(async move {
+                on_delta("Hello");
+                on_delta(", wor");
+                std::future::pending().await
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn aborted_completions_keep_the_partial_text() {
+        let request: CompletionRequest = serde_json::from_value(serde_json::json!({
+            "provider": "ollama",
+            "model": "llama3",
+            "messages": [{ "role": "user", "content": "hi" }]
+        }))
+        .unwrap();
+        let (abort_tx, abort_rx) = oneshot::channel();
+        let abort_tx = Mutex::new(Some(abort_tx));
+        let abort_after_second = |delta: &str| {
+            if delta == ", wor" {
+                if let Some(abort) = abort_tx.lock().take() {
+                    abort.send(()).unwrap();
+                }
+            }
+        };
+
+        let output = stream_or_abort(&Stalls, &request, &abort_after_second, abort_rx)
+            .await
+            .unwrap();
+        assert_eq!(output.text, "Hello, wor");
+        assert_eq!(output.finish_reason.as_deref(), Some(FINISH_ABORTED));
+    }
+
+    #[test]
+    fn compatible_provider_requires_base_url() {
+        assert!(
+            build_provider(ProviderKind::OpenAiCompatible, None, None, Retry::default()).is_err()
+        );
+        assert!(build_provider(
+            ProviderKind::OpenAiCompatible,
+            Some("http://localhost:1234/v1/"),
+            None,
+            Retry::default()
+        )
+        .is_ok());
+        assert!(build_provider(ProviderKind::Anthropic, None, None, Retry::default()).is_err());
+    }
+}
@@ -0,0 +1,398 @@
+//! Local models served by Ollama.
+//!
+//! Ollama streams newline-delimited JSON rather than SSE, both for chat and
+//! for model pulls. No API key is involved; the server is expected on
+//! `localhost:11434` unless the caller passes another base URL.
+
+use super::middleware::Retry;
+use super::{
+    error_for_status, split_image, ChatMessage, CompletionOutput, CompletionProvider,
+    CompletionRequest, ProviderKind, TokenUsage,
+};
+use futures_util::future::BoxFuture;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tracing::warn;
+
+pub const DEFAULT_BASE_URL: &str = "http://localhost:11434";
+/// Detection should fail fast when nothing is listening.
+const DETECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub struct OllamaProvider {
+    client: reqwest::Client,
+    base_url: String,
+    retry: Retry,
+}
+
+impl OllamaProvider {
+    pub fn new(client: reqwest::Client, base_url: String, retry: Retry) -> Self {
+        Self {
+            client,
+            base_url,
+            retry,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OllamaStatus {
+    pub running: bool,
+    pub version: Option<String>,
+    pub base_url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OllamaModel {
+    pub name: String,
+    pub size: u64,
+    #[serde(rename(deserialize = "modified_at"))]
+    pub modified_at: String,
+    #[serde(default)]
+    pub details: Option<OllamaModelDetails>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OllamaModelDetails {
+    #[serde(default, rename(deserialize = "parameter_size"))]
+    pub parameter_size: Option<String>,
+    #[serde(default, rename(deserialize = "quantization_level"))]
+    pub quantization_level: Option<String>,
+    #[serde(default)]
+    pub family: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TagsResponse {
+    #[serde(default)]
+    models: Vec<OllamaModel>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PullProgress<'a> {
+    model: &'a str,
+    status: String,
+    completed_bytes: Option<u64>,
+    total_bytes: Option<u64>,
+}
+
+fn base_url(base_url: Option<String>) -> String {
+    base_url
+        .map(|url| url.trim().trim_end_matches('/').to_string())
+        .filter(|url| !url.is_empty())
+        .unwrap_or_else(|| DEFAULT_BASE_URL.to_string())
+}
+
+/// Split a byte stream into JSON lines, calling `on_line` for each one.
+/// Returning `false` from `on_line` stops reading early.
+async fn read_json_lines(
+    response: reqwest::Response,
+    mut on_line: impl FnMut(Value) -> Result<bool, String>,
+) -> Result<(), String> {
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut body = response.bytes_stream();
+
+    while let Some(chunk) = body.next().await {
+        let chunk = chunk.map_err(|e| format!("Ollama stream interrupted: {}", e))?;
+        buffer.extend_from_slice(&chunk);
+        while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=pos).collect();
+            if let Some(value) = parse_line(&line)? {
+                if !on_line(value)? {
+                    return Ok(());
+                }
+            }
+        }
+    }
+    if let Some(value) = parse_line(&buffer)? {
+        on_line(value)?;
+    }
+    Ok(())
+}
+
+fn parse_line(line: &[u8]) -> Result<Option<Value>, String> {
+    let line = String::from_utf8_lossy(line);
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(None);
+    }
+    let value: Value =
+        serde_json::from_str(line).map_err(|e| format!("Invalid Ollama response: {}", e))?;
+    if let Some(error) = value.get("error").and_then(Value::as_str) {
+        return Err(format!("Ollama error: {}", error));
+    }
+    Ok(Some(value))
+}
+
+fn message_json(message: &ChatMessage) -> Value {
+    let mut value = json!({ "role": message.role, "content": message.content });
+    if !message.images.is_empty() {
+        let images: Vec<&str> = message.images.iter().map(|i| split_image(i).1).collect();
+        value["images"] = json!(images);
+    }
+    value
+}
+
+pub(crate) fn request_body(request: &CompletionRequest) -> Value {
+    let mut messages = Vec::with_capacity(request.messages.len() + 1);
+    if let Some(system) = request.system_prompt.as_deref().filter(|s| !s.is_empty()) {
+        messages.push(json!({ "role": "system", "content": system }));
+    }
+    messages.extend(request.messages.iter().map(message_json));
+
+    let mut options = serde_json::Map::new();
+    if let Some(temperature) = request.temperature {
+        options.insert("temperature".into(), json!(temperature));
+    }
+    if let Some(max_tokens) = request.max_tokens {
+        options.insert("num_predict".into(), json!(max_tokens));
+    }
+
+    let mut body = json!({
+        "model": request.model,
+        "messages": messages,
+        "stream": true,
+    });
+    if !options.is_empty() {
+        body["options"] = Value::Object(options);
+    }
+    body
+}
+
+/// Fold one chat line into `output`, returning the text it added.
+pub(crate) fn apply_line(output: &mut CompletionOutput, line: &Value) -> Option<String> {
+    if output.model.is_none() {
+        output.model = line["model"].as_str().map(str::to_string);
+    }
+    if line["done"].as_bool() == Some(true) {
+        output.finish_reason = line["done_reason"]
+            .as_str()
+            .map(str::to_string)
+            .or_else(|| Some("stop".to_string()));
+        output.usage = Some(TokenUsage {
+            input_tokens: line["prompt_eval_count"].as_u64().unwrap_or(0) as u32,
+            output_tokens: line["eval_count"].as_u64().unwrap_or(0) as u32,
+        });
+    }
+
+    let text = line["message"]["content"]
+        .as_str()
+        .filter(|text| !text.is_empty())?;
+    output.text.push_str(text);
+    Some(text.to_string())
+}
+
+impl CompletionProvider for OllamaProvider {
+    fn stream_completion<'a>(
+        &'a self,
+        request: &'a CompletionRequest,
+        on_delta: &'a (dyn Fn(&str) + Send + Sync),
+    ) -> BoxFuture<'a, Result<CompletionOutput, String>> {
+        Box::pin(async move {
+            let http = self
+                .client
+                .post(format!("{}/api/chat", self.base_url))
+                .json(&request_body(request));
+            let response = error_for_status(self.retry.send(http).await?).await?;
+
+            let mut output = CompletionOutput::default();
+            read_json_lines(response, |line| {
+                if let Some(delta) = apply_line(&mut output, &line) {
+                    on_delta(&delta);
+                }
+                Ok(line["done"].as_bool() != Some(true))
+            })
+            .await?;
+            Ok(output)
+        })
+    }
+}
+
+/// Check whether an Ollama server is reachable.
+#[tauri::command]
+pub async fn ollama_status(base_url: Option<String>) -> Result<OllamaStatus, String> {
+    let base_url = self::base_url(base_url);
+    let client = crate::net::client::http_client(Some(ProviderKind::Ollama))?;
+
+    let version = match client
+        .get(format!("{}/api/version", base_url))
+        .timeout(DETECT_TIMEOUT)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+    {
+        Ok(response) => response
+            .json::<Value>()
+            .await
+            .ok()
+            .and_then(|v| v["version"].as_str().map(str::to_string)),
+        Err(_) => {
+            return Ok(OllamaStatus {
+                running: false,
+                version: None,
+                base_url,
+            })
+        }
+    };
+
+    Ok(OllamaStatus {
+        running: true,
+        version,
+        base_url,
+    })
+}
+
+/// Models installed on the Ollama server.
+#[tauri::command]
+pub async fn list_ollama_models(base_url: Option<String>) -> Result<Vec<OllamaModel>, String> {
+    let base_url = self::base_url(base_url);
+    let response = crate::net::client::http_client(Some(ProviderKind::Ollama))?
+        .get(format!("{}/api/tags", base_url))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach Ollama: {}", e))?;
+    let tags: TagsResponse = error_for_status(response)
+        .await?
+        .json()
+        .await
+        .map_err(|e| format!("Invalid Ollama response: {}", e))?;
+    Ok(tags.models)
+}
+
+/// Download `model` into Ollama, emitting `ollama-pull-progress` events as
+/// layers arrive. Resolves once the pull has finished.
+#[tauri::command]
+pub async fn pull_ollama_model(
+    app: AppHandle,
+    model: String,
+    base_url: Option<String>,
+) -> Result<(), String> {
+    if model.trim().is_empty() {
+        return Err("Model name must not be empty".to_string());
+    }
+    let base_url = self::base_url(base_url);
+    let response = crate::net::client::http_client(Some(ProviderKind::Ollama))?
+        .post(format!("{}/api/pull", base_url))
+        .json(&json!({ "model": model, "stream": true }))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach Ollama: {}", e))?;
+    let response = error_for_status(response).await?;
+
+    let mut succeeded = false;
+    read_json_lines(response, |line| {
+        let status = line["status"].as_str().unwrap_or_default().to_string();
+        succeeded = status == "success";
+        let progress = PullProgress {
+            model: &model,
+            status,
+            completed_bytes: line["completed"].as_u64(),
+            total_bytes: line["total"].as_u64(),
+        };
+        if let Err(e) = app.emit("ollama-pull-progress", &progress) {
+            warn!("Failed to emit pull progress: {}", e);
+        }
+        Ok(true)
+    })
+    .await?;
+
+    if succeeded {
+        Ok(())
+    } else {
+        Err(format!("Pull of {} ended before it completed", model))
+    }
+}
+
+/// Remove an installed model from the Ollama server.
+#[tauri::command]
+pub async fn delete_ollama_model(model: String, base_url: Option<String>) -> Result<(), String> {
+    let base_url = self::base_url(base_url);
+    let response = crate::net::client::http_client(Some(ProviderKind::Ollama))?
+        .delete(format!("{}/api/delete", base_url))
+        .json(&json!({ "model": model }))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach Ollama: {}", e))?;
+    error_for_status(response).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::{ChatRole, ProviderKind};
+
+    #[test]
+    fn body_maps_options_and_strips_image_prefixes() {
+        let request = CompletionRequest {
+            provider: ProviderKind::Ollama,
+            model: "llava:7b".into(),
+            messages: vec![ChatMessage {
+                role: ChatRole::User,
+                content: "What is on screen?".into(),
+                images: vec!["data:image/png;base64,iVBOR".into()],
+            }],
+            system_prompt: None,
+            temperature: Some(0.1),
+            max_tokens: Some(128),
+            base_url: None,
+            api_key_name: None,
+            retry: None,
+            profile_id: None,
+            skip_cache: false,
+        };
+        let body = request_body(&request);
+
+        assert_eq!(body["messages"][0]["images"], json!(["iVBOR"]));
+        assert_eq!(body["options"]["num_predict"], 128);
+        assert!(body["options"]["temperature"].is_number());
+    }
+
+    #[test]
+    fn lines_accumulate_text_and_final_counts() {
+        let mut output = CompletionOutput::default();
+        let lines = [
+            json!({ "model": "llama3.2", "message": { "role": "assistant", "content": "Hi" }, "done": false }),
+            json!({ "model": "llama3.2", "message": { "role": "assistant", "content": " there" }, "done": false }),
+            json!({ "model": "llama3.2", "message": { "role": "assistant", "content": "" }, "done": true,
+                    "done_reason": "stop", "prompt_eval_count": 26, "eval_count": 2 }),
+        ];
+        let deltas: Vec<_> = lines
+            .iter()
+            .filter_map(|l| apply_line(&mut output, l))
+            .collect();
+
+        assert_eq!(deltas, ["Hi", " there"]);
+        assert_eq!(output.text, "Hi there");
+        assert_eq!(
+            output.usage,
+            Some(TokenUsage {
+                input_tokens: 26,
+                output_tokens: 2
+            })
+        );
+        assert!(parse_line(br#"{"error":"model not found"}"#).is_err());
+    }
+
+    #[test]
+    fn tags_response_parses() {
+        let tags: TagsResponse = serde_json::from_str(
+            r#"{"models":[{"name":"llama3.2:latest","modified_at":"2024-10-01T12:00:00Z","size":2019393189,
+                "digest":"a80c4f17acd5","details":{"family":"llama","parameter_size":"3.2B","quantization_level":"Q4_K_M"}}]}"#,
+        )
+        .unwrap();
+
+        let model = &tags.models[0];
+        assert_eq!(model.name, "llama3.2:latest");
+        let details = model.details.as_ref().unwrap();
+        assert_eq!(details.parameter_size.as_deref(), Some("3.2B"));
+        let json = serde_json::to_value(model).unwrap();
+        assert_eq!(json["modifiedAt"], "2024-10-01T12:00:00Z");
+    }
+}
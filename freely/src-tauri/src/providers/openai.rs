@@ -0,0 +1,253 @@
+//! OpenAI `/chat/completions`, also used for OpenAI-compatible servers.
+
+use super::middleware::Retry;
+use super::sse::read_events;
+use super::{
+    error_for_status, split_image, ChatMessage, CompletionOutput, CompletionProvider,
+    CompletionRequest, TokenUsage,
+};
+use futures_util::future::BoxFuture;
+use reqwest::header::{HeaderMap, HeaderName};
+use serde_json::{json, Value};
+
+pub const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+
+pub struct OpenAiProvider {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    retry: Retry,
+    /// Sent with every request, e.g. a gateway's routing or tenant headers.
+    headers: HeaderMap,
+    /// Header carrying the API key as-is; `None` sends it as a bearer token.
+    auth_header: Option<HeaderName>,
+}
+
+impl OpenAiProvider {
+    pub fn new(
+        client: reqwest::Client,
+        base_url: String,
+        api_key: Option<String>,
+        retry: Retry,
+    ) -> Self {
+        Self {
+            client,
+            base_url,
+            api_key,
+            retry,
+            headers: HeaderMap::new(),
+            auth_header: None,
+        }
+    }
+
+    /// Add `headers` to every request and send the API key in `auth_header`
+    /// instead of `Authorization: Bearer`.
+    pub fn with_headers(mut self, headers: HeaderMap, auth_header: Option<HeaderName>) -> Self {
+        self.headers = headers;
+        self.auth_header = auth_header;
+        self
+    }
+
+    /// Compatible servers often reject fields they don't know, so the
+    /// OpenAI-only ones are sent to api.openai.com alone.
+    fn is_official(&self) -> bool {
+        self.base_url.starts_with(DEFAULT_BASE_URL)
+    }
+}
+
+fn message_json(message: &ChatMessage) -> Value {
+    if message.images.is_empty() {
+        return json!({ "role": message.role, "content": message.content });
+    }
+
+    let mut parts = vec![json!({ "type": "text", "text": message.content })];
+    for image in &message.images {
+        let (media_type, data) = split_image(image);
+        parts.push(json!({
+            "type": "image_url",
+            "image_url": { "url": format!("data:{};base64,{}", media_type, data) },
+        }));
+    }
+    json!({ "role": message.role, "content": parts })
+}
+
+pub(crate) fn request_body(request: &CompletionRequest, official: bool) -> Value {
+    let mut messages = Vec::with_capacity(request.messages.len() + 1);
+    if let Some(system) = request.system_prompt.as_deref().filter(|s| !s.is_empty()) {
+        messages.push(json!({ "role": "system", "content": system }));
+    }
+    messages.extend(request.messages.iter().map(message_json));
+
+    let mut body = json!({
+        "model": request.model,
+        "messages": messages,
+        "stream": true,
+    });
+    if let Some(temperature) = request.temperature {
+        body["temperature"] = json!(temperature);
+    }
+    if official {
+        body["stream_options"] = json!({ "include_usage": true });
+        if let Some(max_tokens) = request.max_tokens {
+            body["max_completion_tokens"] = json!(max_tokens);
+        }
+    } else if let Some(max_tokens) = request.max_tokens {
+        body["max_tokens"] = json!(max_tokens);
+    }
+    body
+}
+
+/// Fold one stream chunk into `output`, returning the text it added.
+pub(crate) fn apply_chunk(
+    output: &mut CompletionOutput,
+    chunk: &Value,
+) -> Result<Option<String>, String> {
+    if let Some(error) = chunk.get("error") {
+        let message = error
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or("unknown error");
+        return Err(format!("Provider error: {}", message));
+    }
+
+    if output.model.is_none() {
+        output.model = chunk
+            .get("model")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+    }
+    if let Some(usage) = chunk.get("usage").filter(|u| !u.is_null()) {
+        output.usage = Some(TokenUsage {
+            input_tokens: usage["prompt_tokens"].as_u64().unwrap_or(0) as u32,
+            output_tokens: usage["completion_tokens"].as_u64().unwrap_or(0) as u32,
+        });
+    }
+
+    let Some(choice) = chunk.pointer("/choices/0") else {
+        return Ok(None);
+    };
+    if let Some(reason) = choice.get("finish_reason").and_then(Value::as_str) {
+        output.finish_reason = Some(reason.to_string());
+    }
+    let delta = choice
+        .pointer("/delta/content")
+        .and_then(Value::as_str)
+        .filter(|text| !text.is_empty());
+    if let Some(text) = delta {
+        output.text.push_str(text);
+    }
+    Ok(delta.map(str::to_string))
+}
+
+impl CompletionProvider for OpenAiProvider {
+    fn stream_completion<'a>(
+        &'a self,
+        request: &'a CompletionRequest,
+        on_delta: &'a (dyn Fn(&str) + Send + Sync),
+    ) -> BoxFuture<'a, Result<CompletionOutput, String>> {
+        Box::pin(async move {
+            let mut http = self
+                .client
+                .post(format!("{}/chat/completions", self.base_url))
+                .headers(self.headers.clone())
+                .json(&request_body(request, self.is_official()));
+            if let Some(key) = self.api_key.as_deref().filter(|k| !k.is_empty()) {
+                http = match &self.auth_header {
+                    Some(name) => http.header(name, key),
+                    None => http.bearer_auth(key),
+                };
+            }
+
+            let response = error_for_status(self.retry.send(http).await?).await?;
+
+            let mut output = CompletionOutput::default();
+            read_events(response, |event| {
+                if event.data == "[DONE]" {
+                    return Ok(false);
+                }
+                let chunk: Value = serde_json::from_str(&event.data)
+                    .map_err(|e| format!("Invalid completion chunk: {}", e))?;
+                if let Some(delta) = apply_chunk(&mut output, &chunk)? {
+                    on_delta(&delta);
+                }
+                Ok(true)
+            })
+            .await?;
+            Ok(output)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::{ChatRole, ProviderKind};
+
+    fn request() -> CompletionRequest {
+        CompletionRequest {
+            provider: ProviderKind::OpenAi,
+            model: "gpt-4o-mini".into(),
+            messages: vec![ChatMessage {
+                role: ChatRole::User,
+                content: "What is this?".into(),
+                images: vec!["iVBORw0".into()],
+            }],
+            system_prompt: Some("Be brief".into()),
+            temperature: None,
+            max_tokens: Some(256),
+            base_url: None,
+            api_key_name: None,
+            retry: None,
+            profile_id: None,
+            skip_cache: false,
+        }
+    }
+
+    #[test]
+    fn body_puts_system_prompt_first_and_inlines_images() {
+        let body = request_body(&request(), true);
+        assert_eq!(
+            body["messages"][0],
+            json!({ "role": "system", "content": "Be brief" })
+        );
+        assert_eq!(
+            body["messages"][1]["content"][1]["image_url"]["url"],
+            "data:image/png;base64,iVBORw0"
+        );
+        assert_eq!(body["max_completion_tokens"], 256);
+
+        let compatible = request_body(&request(), false);
+        assert!(compatible.get("stream_options").is_none());
+        assert_eq!(compatible["max_tokens"], 256);
+    }
+
+    #[test]
+    fn chunks_accumulate_text_and_usage() {
+        let mut output = CompletionOutput::default();
+        let chunks = [
+            json!({ "model": "gpt-4o-mini-2024", "choices": [{ "delta": { "role": "assistant", "content": "" } }] }),
+            json!({ "choices": [{ "delta": { "content": "Hel" } }] }),
+            json!({ "choices": [{ "delta": { "content": "lo" }, "finish_reason": "stop" }] }),
+            json!({ "choices": [], "usage": { "prompt_tokens": 12, "completion_tokens": 2 } }),
+        ];
+        let deltas: Vec<_> = chunks
+            .iter()
+            .filter_map(|c| apply_chunk(&mut output, c).unwrap())
+            .collect();
+
+        assert_eq!(deltas, ["Hel", "lo"]);
+        assert_eq!(output.text, "Hello");
+        assert_eq!(output.model.as_deref(), Some("gpt-4o-mini-2024"));
+        assert_eq!(output.finish_reason.as_deref(), Some("stop"));
+        assert_eq!(
+            output.usage,
+            Some(TokenUsage {
+                input_tokens: 12,
+                output_tokens: 2
+            })
+        );
+
+        let error = json!({ "error": { "message": "overloaded" } });
+        assert!(apply_chunk(&mut output, &error).is_err());
+    }
+}
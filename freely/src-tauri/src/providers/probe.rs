@@ -0,0 +1,176 @@
+//! Provider health checks for the settings screen.
+//!
+//! A probe sends the cheapest request each API offers that still checks the
+//! credentials, listing models, so it costs no tokens. It is sent once,
+//! without the retry policy, so a slow or failing provider shows up as such
+//! instead of being waited out.
+
+use super::{anthropic, ollama, openai, ProviderKind};
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderProbe {
+    pub provider: ProviderKind,
+    /// Whether the server answered at all.
+    pub reachable: bool,
+    /// Whether the API key was accepted; `None` when the provider takes no
+    /// key or the answer didn't say.
+    pub authenticated: Option<bool>,
+    /// Round trip of the probe request.
+    pub latency_ms: Option<u64>,
+    /// HTTP status of the probe response.
+    pub status: Option<u16>,
+    pub error: Option<String>,
+}
+
+impl ProviderProbe {
+    fn new(provider: ProviderKind) -> Self {
+        Self {
+            provider,
+            reachable: false,
+            authenticated: None,
+            latency_ms: None,
+            status: None,
+            error: None,
+        }
+    }
+}
+
+/// Interpret the probe's response status. `needs_key` is whether the
+/// provider checks credentials at all.
+fn classify(
+    provider: ProviderKind,
+    needs_key: bool,
+    status: u16,
+    latency: Duration,
+) -> ProviderProbe {
+    let mut probe = ProviderProbe {
+        reachable: true,
+        latency_ms: Some(latency.as_millis() as u64),
+        status: Some(status),
+        ..ProviderProbe::new(provider)
+    };
+    match status {
+        200..=299 => probe.authenticated = needs_key.then_some(true),
+        401 | 403 => {
+            probe.authenticated = Some(false);
+            probe.error = Some("The API key was rejected".to_string());
+        }
+        _ => probe.error = Some(format!("Provider returned {}", status)),
+    }
+    probe
+}
+
+fn probe_local(provider: ProviderKind, app: &AppHandle) -> Result<ProviderProbe, String> {
+    let capabilities = super::llama_local::get_inference_capabilities(app.clone())?;
+    let mut probe = ProviderProbe::new(provider);
+    if !capabilities.available {
+        probe.error = Some("This build doesn't include local inference".to_string());
+    } else if capabilities.models.is_empty() {
+        probe.error = Some("No local models downloaded".to_string());
+    } else {
+        probe.reachable = true;
+    }
+    Ok(probe)
+}
+
+/// Check that `provider` is reachable and accepts its stored API key, and
+/// how long it takes to answer. Network failures are reported in the
+/// result, not as an error.
+#[tauri::command]
+pub async fn probe_provider(
+    app: AppHandle,
+    provider: ProviderKind,
+    base_url: Option<String>,
+    api_key_name: Option<String>,
+) -> Result<ProviderProbe, String> {
+    let base_url = base_url
+        .map(|url| url.trim().trim_end_matches('/').to_string())
+        .filter(|url| !url.is_empty());
+    let api_key = match api_key_name.as_deref().or(provider.default_key_name()) {
+        Some(name) => crate::secrets::load_api_key(&app, name)
+            .await?
+            .filter(|key| !key.is_empty()),
+        None => None,
+    };
+
+    let client = crate::net::client::http_client(Some(provider))?;
+    let request = match provider {
+        ProviderKind::OpenAi | ProviderKind::OpenAiCompatible => {
+            let base_url = match (provider, base_url) {
+                (_, Some(url)) => url,
+                (ProviderKind::OpenAi, None) => openai::DEFAULT_BASE_URL.to_string(),
+                _ => return Err("An OpenAI-compatible provider needs a base URL".to_string()),
+            };
+            let request = client.get(format!("{}/models", base_url));
+            match &api_key {
+                Some(key) => request.bearer_auth(key),
+                None => request,
+            }
+        }
+        ProviderKind::Anthropic => client
+            .get(format!(
+                "{}/v1/models",
+                base_url.as_deref().unwrap_or(anthropic::DEFAULT_BASE_URL)
+            ))
+            .header("x-api-key", api_key.as_deref().unwrap_or_default())
+            .header("anthropic-version", anthropic::API_VERSION),
+        ProviderKind::Ollama => client.get(format!(
+            "{}/api/version",
+            base_url.as_deref().unwrap_or(ollama::DEFAULT_BASE_URL)
+        )),
+        ProviderKind::LlamaLocal => return probe_local(provider, &app),
+    };
+
+    // Compatible servers may not check keys at all
+    let needs_key = matches!(provider, ProviderKind::OpenAi | ProviderKind::Anthropic);
+    let started = Instant::now();
+    let mut probe = match request.timeout(PROBE_TIMEOUT).send().await {
+        Ok(response) => classify(
+            provider,
+            needs_key || api_key.is_some(),
+            response.status().as_u16(),
+            started.elapsed(),
+        ),
+        Err(e) => ProviderProbe {
+            error: Some(format!("Failed to reach provider: {}", e)),
+            ..ProviderProbe::new(provider)
+        },
+    };
+    if needs_key && api_key.is_none() && probe.authenticated != Some(true) {
+        probe.error = Some(format!("No API key stored for {}", provider.as_str()));
+    }
+    Ok(probe)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statuses_map_to_reachability_and_auth() {
+        let latency = Duration::from_millis(42);
+        let ok = classify(ProviderKind::OpenAi, true, 200, latency);
+        assert!(ok.reachable);
+        assert_eq!(ok.authenticated, Some(true));
+        assert_eq!(ok.latency_ms, Some(42));
+        assert!(ok.error.is_none());
+
+        let rejected = classify(ProviderKind::Anthropic, true, 401, latency);
+        assert!(rejected.reachable);
+        assert_eq!(rejected.authenticated, Some(false));
+
+        let keyless = classify(ProviderKind::Ollama, false, 200, latency);
+        assert_eq!(keyless.authenticated, None);
+
+        let down = classify(ProviderKind::OpenAi, true, 503, latency);
+        assert!(down.reachable);
+        assert_eq!(down.authenticated, None);
+        assert_eq!(down.error.as_deref(), Some("Provider returned 503"));
+    }
+}
@@ -0,0 +1,439 @@
+//! Named OpenAI-compatible endpoints (migration 20).
+//!
+//! A profile saves what it takes to talk to a LiteLLM proxy, a vLLM or LM
+//! Studio server, or a corporate gateway: its base URL, the models it
+//! offers, extra headers and how the API key is sent. Requests naming a
+//! `profileId` go through the generic [`OpenAiProvider`] configured from the
+//! profile. The key itself is kept in the secrets store under the profile's
+//! `apiKeyName`, which defaults to `profile-<id>`.
+
+use super::middleware::Retry;
+use super::openai::OpenAiProvider;
+use super::{CompletionProvider, ProviderKind};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::BTreeMap;
+use tauri::AppHandle;
+
+const MAX_NAME_LEN: usize = 100;
+const PROFILE_COLUMNS: &str = "id, name, base_url, models, headers, auth_scheme, auth_header,
+     api_key_name, created_at, updated_at";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthScheme {
+    /// `Authorization: Bearer <key>`, as OpenAI does.
+    #[default]
+    Bearer,
+    /// The key as-is in a named header, e.g. Azure's `api-key`.
+    Header,
+    /// No key; for servers on localhost or behind a VPN.
+    None,
+}
+
+impl AuthScheme {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Bearer => "bearer",
+            Self::Header => "header",
+            Self::None => "none",
+        }
+    }
+
+    fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "bearer" => Ok(Self::Bearer),
+            "header" => Ok(Self::Header),
+            "none" => Ok(Self::None),
+            other => Err(format!("Unknown auth scheme: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderProfile {
+    pub id: String,
+    pub name: String,
+    pub base_url: String,
+    pub models: Vec<String>,
+    pub headers: BTreeMap<String, String>,
+    pub auth_scheme: AuthScheme,
+    /// Header name for [`AuthScheme::Header`].
+    pub auth_header: Option<String>,
+    /// Secrets entry holding the API key.
+    pub api_key_name: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// The editable fields of a profile.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileInput {
+    pub name: String,
+    pub base_url: String,
+    #[serde(default)]
+    pub models: Vec<String>,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub auth_scheme: AuthScheme,
+    #[serde(default)]
+    pub auth_header: Option<String>,
+    #[serde(default)]
+    pub api_key_name: Option<String>,
+}
+
+#[derive(sqlx::FromRow)]
+struct ProfileRow {
+    id: String,
+    name: String,
+    base_url: String,
+    models: String,
+    headers: String,
+    auth_scheme: String,
+    auth_header: Option<String>,
+    api_key_name: String,
+    created_at: i64,
+    updated_at: i64,
+}
+
+impl TryFrom<ProfileRow> for ProviderProfile {
+    type Error = String;
+
+    fn try_from(row: ProfileRow) -> Result<Self, Self::Error> {
+        Ok(ProviderProfile {
+            models: serde_json::from_str(&row.models).unwrap_or_default(),
+            headers: serde_json::from_str(&row.headers).unwrap_or_default(),
+            auth_scheme: AuthScheme::parse(&row.auth_scheme)?,
+            id: row.id,
+            name: row.name,
+            base_url: row.base_url,
+            auth_header: row.auth_header,
+            api_key_name: row.api_key_name,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        })
+    }
+}
+
+/// Headers as reqwest wants them, rejecting names or values HTTP can't carry.
+fn header_map(headers: &BTreeMap<String, String>) -> Result<HeaderMap, String> {
+    let mut map = HeaderMap::with_capacity(headers.len());
+    for (name, value) in headers {
+        let name = HeaderName::try_from(name.trim())
+            .map_err(|_| format!("Invalid header name: {}", name))?;
+        let value = HeaderValue::try_from(value.trim())
+            .map_err(|_| format!("Invalid value for header {}", name))?;
+        map.insert(name, value);
+    }
+    Ok(map)
+}
+
+/// Trim and check `input`, returning it ready to store.
+fn validate(mut input: ProfileInput) -> Result<ProfileInput, String> {
+    input.name = input.name.trim().to_string();
+    if input.name.is_empty() {
+        return Err("Invalid profile: name must not be empty".to_string());
+    }
+    if input.name.chars().count() > MAX_NAME_LEN {
+        return Err(format!(
+            "Invalid profile: name exceeds {} characters",
+            MAX_NAME_LEN
+        ));
+    }
+
+    input.base_url = input.base_url.trim().trim_end_matches('/').to_string();
+    if !input.base_url.starts_with("https://") && !input.base_url.starts_with("http://") {
+        return Err(format!(
+            "Invalid profile: base URL {} is not http(s)",
+            input.base_url
+        ));
+    }
+
+    let mut models: Vec<String> = Vec::with_capacity(input.models.len());
+    for model in input
+        .models
+        .iter()
+        .map(|m| m.trim())
+        .filter(|m| !m.is_empty())
+    {
+        if !models.iter().any(|m| m == model) {
+            models.push(model.to_string());
+        }
+    }
+    input.models = models;
+
+    header_map(&input.headers)?;
+    input.auth_header = input
+        .auth_header
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty());
+    match (input.auth_scheme, &input.auth_header) {
+        (AuthScheme::Header, None) => {
+            return Err("Invalid profile: header auth needs a header name".to_string())
+        }
+        (AuthScheme::Header, Some(name)) => {
+            HeaderName::try_from(name.as_str())
+                .map_err(|_| format!("Invalid header name: {}", name))?;
+        }
+        _ => input.auth_header = None,
+    }
+
+    input.api_key_name = input
+        .api_key_name
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty());
+    if let Some(name) = &input.api_key_name {
+        crate::secrets::validate_provider(name)?;
+    }
+    Ok(input)
+}
+
+fn map_unique(e: sqlx::Error, name: &str, action: &str) -> String {
+    match &e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => {
+            format!("A profile named {} already exists", name)
+        }
+        _ => format!("Failed to {} profile: {}", action, e),
+    }
+}
+
+fn json(value: &impl Serialize) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+pub(crate) async fn get(pool: &SqlitePool, id: &str) -> Result<Option<ProviderProfile>, String> {
+    sqlx::query_as::<_, ProfileRow>(&format!(
+        "SELECT {} FROM provider_profiles WHERE id = ?",
+        PROFILE_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to load profile: {}", e))?
+    .map(ProviderProfile::try_from)
+    .transpose()
+}
+
+async fn require(pool: &SqlitePool, id: &str) -> Result<ProviderProfile, String> {
+    get(pool, id)
+        .await?
+        .ok_or_else(|| format!("Provider profile not found: {}", id))
+}
+
+pub(crate) async fn create(
+    pool: &SqlitePool,
+    input: ProfileInput,
+) -> Result<ProviderProfile, String> {
+    let input = validate(input)?;
+    let id = uuid::Uuid::new_v4().to_string();
+    let api_key_name = input
+        .api_key_name
+        .clone()
+        .unwrap_or_else(|| format!("profile-{}", id));
+    let now = crate::db::now_millis();
+
+    sqlx::query(
+        "INSERT INTO provider_profiles
+             (id, name, base_url, models, headers, auth_scheme, auth_header, api_key_name,
+              created_at, updated_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&id)
+    .bind(&input.name)
+    .bind(&input.base_url)
+    .bind(json(&input.models))
+    .bind(json(&input.headers))
+    .bind(input.auth_scheme.as_str())
+    .bind(&input.auth_header)
+    .bind(&api_key_name)
+    .bind(now)
+    .bind(now)
+    .execute(pool)
+    .await
+    .map_err(|e| map_unique(e, &input.name, "create"))?;
+    require(pool, &id).await
+}
+
+/// Replace the settings of profile `id`. Leaving out `apiKeyName` keeps the
+/// current one.
+pub(crate) async fn update(
+    pool: &SqlitePool,
+    id: &str,
+    input: ProfileInput,
+) -> Result<ProviderProfile, String> {
+    let input = validate(input)?;
+    let current = require(pool, id).await?;
+    sqlx::query(
+        "UPDATE provider_profiles
+         SET name = ?, base_url = ?, models = ?, headers = ?, auth_scheme = ?,
+             auth_header = ?, api_key_name = ?, updated_at = ?
+         WHERE id = ?",
+    )
+    .bind(&input.name)
+    .bind(&input.base_url)
+    .bind(json(&input.models))
+    .bind(json(&input.headers))
+    .bind(input.auth_scheme.as_str())
+    .bind(&input.auth_header)
+    .bind(input.api_key_name.as_ref().unwrap_or(&current.api_key_name))
+    .bind(crate::db::now_millis())
+    .bind(id)
+    .execute(pool)
+    .await
+    .map_err(|e| map_unique(e, &input.name, "update"))?;
+    require(pool, id).await
+}
+
+pub(crate) async fn delete(pool: &SqlitePool, id: &str) -> Result<bool, String> {
+    let result = sqlx::query("DELETE FROM provider_profiles WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to delete profile: {}", e))?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Every profile, sorted by name.
+pub(crate) async fn list(pool: &SqlitePool) -> Result<Vec<ProviderProfile>, String> {
+    sqlx::query_as::<_, ProfileRow>(&format!(
+        "SELECT {} FROM provider_profiles ORDER BY name COLLATE NOCASE",
+        PROFILE_COLUMNS
+    ))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to list profiles: {}", e))?
+    .into_iter()
+    .map(ProviderProfile::try_from)
+    .collect()
+}
+
+/// Build the generic provider for profile `id`, with its key loaded from
+/// the secrets store.
+pub(crate) async fn connect(
+    app: &AppHandle,
+    id: &str,
+    retry: Retry,
+) -> Result<Box<dyn CompletionProvider>, String> {
+    let pool = crate::db::pool(app).await?;
+    let profile = require(&pool, id).await?;
+    crate::net::connectivity::require_online_for(
+        ProviderKind::OpenAiCompatible,
+        Some(&profile.base_url),
+    )?;
+
+    let api_key = match profile.auth_scheme {
+        AuthScheme::None => None,
+        AuthScheme::Bearer | AuthScheme::Header => {
+            let key = crate::secrets::load_api_key(app, &profile.api_key_name).await?;
+            Some(key.ok_or_else(|| format!("No API key stored for {}", profile.name))?)
+        }
+    };
+    let auth_header = profile
+        .auth_header
+        .as_deref()
+        .map(HeaderName::try_from)
+        .transpose()
+        .map_err(|_| format!("Invalid header name in profile {}", profile.name))?;
+
+    let provider = OpenAiProvider::new(
+        crate::net::client::http_client(Some(ProviderKind::OpenAiCompatible))?,
+        profile.base_url,
+        api_key,
+        retry,
+    )
+    .with_headers(header_map(&profile.headers)?, auth_header);
+    Ok(Box::new(provider))
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+#[tauri::command]
+pub async fn list_provider_profiles(app: AppHandle) -> Result<Vec<ProviderProfile>, String> {
+    let pool = crate::db::pool(&app).await?;
+    list(&pool).await
+}
+
+/// Save a new endpoint profile. Store its key with `set_api_key` under the
+/// returned `apiKeyName`.
+#[tauri::command]
+pub async fn create_provider_profile(
+    app: AppHandle,
+    profile: ProfileInput,
+) -> Result<ProviderProfile, String> {
+    let pool = crate::db::pool(&app).await?;
+    create(&pool, profile).await
+}
+
+#[tauri::command]
+pub async fn update_provider_profile(
+    app: AppHandle,
+    id: String,
+    profile: ProfileInput,
+) -> Result<ProviderProfile, String> {
+    let pool = crate::db::pool(&app).await?;
+    update(&pool, &id, profile).await
+}
+
+/// Delete a profile. Its stored API key is left for `delete_api_key`, since
+/// other profiles may share it. Returns `false` if it did not exist.
+#[tauri::command]
+pub async fn delete_provider_profile(app: AppHandle, id: String) -> Result<bool, String> {
+    let pool = crate::db::pool(&app).await?;
+    delete(&pool, &id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(name: &str) -> ProfileInput {
+        ProfileInput {
+            name: name.to_string(),
+            base_url: "https://llm.example.com/v1/".to_string(),
+            models: vec![" gpt-4o ".to_string(), "gpt-4o".to_string(), "".to_string()],
+            headers: BTreeMap::from([("X-Tenant".to_string(), "acme".to_string())]),
+            auth_scheme: AuthScheme::Header,
+            auth_header: Some("api-key".to_string()),
+            api_key_name: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn profiles_are_validated_and_round_trip() {
+        let pool = crate::db::test_pool().await;
+        let profile = create(&pool, input("Gateway")).await.unwrap();
+        assert_eq!(profile.base_url, "https://llm.example.com/v1");
+        assert_eq!(profile.models, ["gpt-4o"]);
+        assert_eq!(profile.headers["X-Tenant"], "acme");
+        assert_eq!(profile.api_key_name, format!("profile-{}", profile.id));
+        assert!(
+            create(&pool, input("gateway")).await.is_err(),
+            "names are unique"
+        );
+
+        let mut bad = input("Bad");
+        bad.auth_header = None;
+        assert!(create(&pool, bad).await.is_err());
+        let mut bad = input("Bad");
+        bad.headers
+            .insert("Bad Header".to_string(), "x".to_string());
+        assert!(create(&pool, bad).await.is_err());
+
+        let mut local = input("LM Studio");
+        local.base_url = "http://localhost:1234/v1".to_string();
+        local.auth_scheme = AuthScheme::None;
+        let updated = update(&pool, &profile.id, local).await.unwrap();
+        assert_eq!(updated.auth_header, None);
+        assert_eq!(updated.api_key_name, profile.api_key_name);
+        assert_eq!(list(&pool).await.unwrap().len(), 1);
+
+        assert!(delete(&pool, &profile.id).await.unwrap());
+        assert!(get(&pool, &profile.id).await.unwrap().is_none());
+    }
+}
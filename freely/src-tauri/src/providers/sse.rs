@@ -0,0 +1,130 @@
+//! Minimal server-sent events decoder for streaming completion responses.
+
+use futures_util::StreamExt;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct SseEvent {
+    pub event: Option<String>,
+    pub data: String,
+}
+
+/// Incremental decoder: feed it network chunks, get back complete events.
+/// Chunks may split lines (and UTF-8 sequences) anywhere.
+#[derive(Default)]
+pub(crate) struct SseDecoder {
+    buffer: Vec<u8>,
+    event: Option<String>,
+    data: Vec<String>,
+}
+
+impl SseDecoder {
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+
+        while let Some(pos) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            if let Some(event) = self.process_line(&String::from_utf8_lossy(&line)) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    /// Flush an event left unterminated when the stream closed.
+    pub fn finish(&mut self) -> Option<SseEvent> {
+        let rest = std::mem::take(&mut self.buffer);
+        if let Some(event) = self.process_line(&String::from_utf8_lossy(&rest)) {
+            return Some(event);
+        }
+        self.dispatch()
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<SseEvent> {
+        let line = line.trim_end_matches(['\n', '\r']);
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => self.data.push(value.to_string()),
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event = self.event.take();
+        if self.data.is_empty() {
+            return None;
+        }
+        let data = std::mem::take(&mut self.data).join("\n");
+        Some(SseEvent { event, data })
+    }
+}
+
+/// Read `response` as an event stream, calling `on_event` for each event.
+/// Returning `false` from `on_event` stops reading early.
+pub(crate) async fn read_events(
+    response: reqwest::Response,
+    mut on_event: impl FnMut(SseEvent) -> Result<bool, String>,
+) -> Result<(), String> {
+    let mut decoder = SseDecoder::default();
+    let mut body = response.bytes_stream();
+
+    while let Some(chunk) = body.next().await {
+        let chunk = chunk.map_err(|e| format!("Completion stream interrupted: {}", e))?;
+        for event in decoder.push(&chunk) {
+            if !on_event(event)? {
+                return Ok(());
+            }
+        }
+    }
+    if let Some(event) = decoder.finish() {
+        on_event(event)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_survive_arbitrary_chunk_boundaries() {
+        let stream = "event: message_start\r\ndata: {\"a\":1}\r\n\r\n: keep-alive\n\ndata: h\u{e9}llo\ndata: world\n\n";
+        let mut decoder = SseDecoder::default();
+        let mut events = Vec::new();
+        for byte in stream.as_bytes().chunks(3) {
+            events.extend(decoder.push(byte));
+        }
+
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    event: Some("message_start".into()),
+                    data: "{\"a\":1}".into(),
+                },
+                SseEvent {
+                    event: None,
+                    data: "h\u{e9}llo\nworld".into(),
+                },
+            ]
+        );
+        assert_eq!(decoder.finish(), None);
+    }
+
+    #[test]
+    fn unterminated_event_is_flushed() {
+        let mut decoder = SseDecoder::default();
+        assert!(decoder.push(b"data: [DONE]").is_empty());
+        assert_eq!(decoder.finish().unwrap().data, "[DONE]");
+    }
+}
@@ -0,0 +1,206 @@
+//! Terminal sessions for the embedded terminal panel.
+//!
+//! `pty_spawn` starts the user's default shell on a pseudo-terminal in the
+//! active project (or a directory inside a registered project) and returns
+//! a session ID. Everything the shell prints is emitted as
+//! `pty:output:{id}`, and `pty:exit:{id}` follows once it exits. Keystrokes
+//! go back through `pty_write`; `pty_resize` keeps the terminal size in step
+//! with the panel.
+//!
+//! Reading and waiting block, so each session has two plain threads rather
+//! than async tasks.
+
+use crate::runner::take_utf8;
+use crate::workspace::fs as workspace_fs;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use portable_pty::{native_pty_system, ChildKiller, CommandBuilder, MasterPty, PtySize};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::warn;
+
+struct PtySession {
+    master: Mutex<Box<dyn MasterPty + Send>>,
+    writer: Mutex<Box<dyn Write + Send>>,
+    killer: Mutex<Box<dyn ChildKiller + Send + Sync>>,
+}
+
+static SESSIONS: Lazy<Mutex<HashMap<String, Arc<PtySession>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PtyOutput {
+    pub id: String,
+    pub data: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PtyExit {
+    pub id: String,
+    pub code: Option<u32>,
+}
+
+fn size(cols: u16, rows: u16) -> PtySize {
+    PtySize {
+        rows: rows.max(1),
+        cols: cols.max(1),
+        pixel_width: 0,
+        pixel_height: 0,
+    }
+}
+
+fn session(id: &str) -> Result<Arc<PtySession>, String> {
+    SESSIONS
+        .lock()
+        .get(id)
+        .cloned()
+        .ok_or_else(|| format!("Terminal session not found: {}", id))
+}
+
+/// `cwd` if it is inside a registered project, else the active project,
+/// else the home directory.
+async fn start_dir(app: &AppHandle, cwd: Option<&str>) -> Result<PathBuf, String> {
+    if let Some(cwd) = cwd {
+        let roots = workspace_fs::project_roots(app).await?;
+        return workspace_fs::resolve(&roots, cwd);
+    }
+    if let Some(dir) = crate::db::projects::active_path(app).await? {
+        return Ok(dir);
+    }
+    app.path()
+        .home_dir()
+        .map_err(|e| format!("Could not resolve home directory: {}", e))
+}
+
+fn spawn_reader(app: AppHandle, id: String, mut reader: Box<dyn Read + Send>) {
+    std::thread::spawn(move || {
+        let event_name = format!("pty:output:{}", id);
+        let mut buf = [0u8; 8192];
+        let mut pending = Vec::new();
+        // Ends with an error or EOF once the shell and its children are gone
+        while let Ok(read @ 1..) = reader.read(&mut buf) {
+            pending.extend_from_slice(&buf[..read]);
+            let data = take_utf8(&mut pending);
+            if data.is_empty() {
+                continue;
+            }
+            let output = PtyOutput {
+                id: id.clone(),
+                data,
+            };
+            if let Err(e) = app.emit(&event_name, &output) {
+                warn!("Failed to emit terminal output: {}", e);
+            }
+        }
+    });
+}
+
+/// Open a shell of `cols` × `rows` and return its session ID.
+#[tauri::command]
+pub async fn pty_spawn(
+    app: AppHandle,
+    cols: u16,
+    rows: u16,
+    cwd: Option<String>,
+) -> Result<String, String> {
+    let dir = start_dir(&app, cwd.as_deref()).await?;
+    let pair = native_pty_system()
+        .openpty(size(cols, rows))
+        .map_err(|e| format!("Failed to open terminal: {}", e))?;
+
+    let mut cmd = CommandBuilder::new_default_prog();
+    cmd.cwd(&dir);
+    cmd.env("TERM", "xterm-256color");
+    cmd.env("COLORTERM", "truecolor");
+    let mut child = pair
+        .slave
+        .spawn_command(cmd)
+        .map_err(|e| format!("Failed to start shell: {}", e))?;
+    // Only the shell should hold the terminal open
+    drop(pair.slave);
+
+    let reader = pair
+        .master
+        .try_clone_reader()
+        .map_err(|e| format!("Failed to open terminal: {}", e))?;
+    let writer = pair
+        .master
+        .take_writer()
+        .map_err(|e| format!("Failed to open terminal: {}", e))?;
+
+    let id = uuid::Uuid::new_v4().to_string();
+    SESSIONS.lock().insert(
+        id.clone(),
+        Arc::new(PtySession {
+            master: Mutex::new(pair.master),
+            writer: Mutex::new(writer),
+            killer: Mutex::new(child.clone_killer()),
+        }),
+    );
+    spawn_reader(app.clone(), id.clone(), reader);
+
+    let exit_id = id.clone();
+    std::thread::spawn(move || {
+        let code = match child.wait() {
+            Ok(status) => Some(status.exit_code()),
+            Err(e) => {
+                warn!("Failed to wait for shell: {}", e);
+                None
+            }
+        };
+        SESSIONS.lock().remove(&exit_id);
+        let exit = PtyExit {
+            id: exit_id.clone(),
+            code,
+        };
+        if let Err(e) = app.emit(&format!("pty:exit:{}", exit_id), &exit) {
+            warn!("Failed to emit terminal exit: {}", e);
+        }
+    });
+
+    Ok(id)
+}
+
+/// Send input, as typed, to terminal `id`.
+#[tauri::command]
+pub fn pty_write(id: String, data: String) -> Result<(), String> {
+    let session = session(&id)?;
+    let mut writer = session.writer.lock();
+    writer
+        .write_all(data.as_bytes())
+        .and_then(|_| writer.flush())
+        .map_err(|e| format!("Failed to write to terminal: {}", e))
+}
+
+#[tauri::command]
+pub fn pty_resize(id: String, cols: u16, rows: u16) -> Result<(), String> {
+    session(&id)?
+        .master
+        .lock()
+        .resize(size(cols, rows))
+        .map_err(|e| format!("Failed to resize terminal: {}", e))
+}
+
+/// Kill the shell of terminal `id`; `pty:exit:{id}` follows.
+#[tauri::command]
+pub fn pty_kill(id: String) -> Result<(), String> {
+    session(&id)?
+        .killer
+        .lock()
+        .kill()
+        .map_err(|e| format!("Failed to kill terminal: {}", e))
+}
+
+/// Kill every shell. Called when the app exits.
+pub fn shutdown_all() {
+    let sessions: Vec<Arc<PtySession>> = SESSIONS.lock().drain().map(|(_, s)| s).collect();
+    for session in sessions {
+        let _ = session.killer.lock().kill();
+    }
+}
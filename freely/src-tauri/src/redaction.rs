@@ -0,0 +1,438 @@
+//! PII redaction for exports and outgoing prompts.
+//!
+//! A redaction profile turns on built-in rules for email addresses, phone
+//! numbers, API keys and names, and can add its own regex rules. Names are
+//! found "NER-lite": the profile's list of known names, plus capitalised
+//! words after an introduction or a title ("my name is Dana", "Dr. Okafor").
+//! Profiles live in the `redaction` setting. Exports pick one by name, and
+//! the one named as `promptProfile` is applied to chat requests before they
+//! leave the machine; local models get the prompt as written.
+
+use crate::db::chat::Conversation;
+use crate::providers::{CompletionRequest, ProviderKind};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use tauri::AppHandle;
+
+pub(crate) const SETTINGS_KEY: &str = "redaction";
+
+static API_KEY_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"\b(?:sk-[A-Za-z0-9_-]{20,}|gsk_[A-Za-z0-9]{20,}|AIza[A-Za-z0-9_-]{35}|(?:ghp|gho|ghs|ghu)_[A-Za-z0-9]{36}|github_pat_[A-Za-z0-9_]{22,}|xox[abprs]-[A-Za-z0-9-]{10,}|AKIA[A-Z0-9]{16})",
+    )
+    .expect("valid API key pattern")
+});
+static EMAIL_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}\b")
+        .expect("valid email pattern")
+});
+static PHONE_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"(?:\+\d{1,3}[ .-]?)?(?:\(\d{1,4}\)[ .-]?)?\b\d{2,4}(?:[ .-]\d{2,4}){1,4}\b|\+\d{7,15}\b",
+    )
+    .expect("valid phone pattern")
+});
+static DATE_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^\d{1,4}[./-]\d{1,2}[./-]\d{1,4}$").expect("valid date pattern"));
+/// An introduction or title, then one or two capitalised words.
+static NAME_INTRO_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"((?i:\bmy name is|\bname's|\bcall me|\b(?:mr|mrs|ms|miss|dr|prof)\.?)\s+)([A-Z][a-z]+(?:[ -][A-Z][a-z]+)?)",
+    )
+    .expect("valid name pattern")
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RedactionKind {
+    ApiKey,
+    Email,
+    Phone,
+    Name,
+    Custom,
+}
+
+impl RedactionKind {
+    fn placeholder(self) -> &'static str {
+        match self {
+            Self::ApiKey => "[redacted API key]",
+            Self::Email => "[redacted email]",
+            Self::Phone => "[redacted phone]",
+            Self::Name => "[redacted name]",
+            Self::Custom => "[redacted]",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomRule {
+    /// Regex, in the syntax of the `regex` crate.
+    pub pattern: String,
+    /// Defaults to `[redacted]`. `$1`-style group references work.
+    #[serde(default)]
+    pub replacement: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RedactionProfile {
+    pub name: String,
+    pub emails: bool,
+    pub phone_numbers: bool,
+    pub api_keys: bool,
+    /// Names after introductions and titles.
+    pub names: bool,
+    /// Always redacted, whole words and ignoring case.
+    pub known_names: Vec<String>,
+    pub custom_rules: Vec<CustomRule>,
+}
+
+impl Default for RedactionProfile {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            emails: true,
+            phone_numbers: true,
+            api_keys: true,
+            names: false,
+            known_names: Vec::new(),
+            custom_rules: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RedactionSettings {
+    pub profiles: Vec<RedactionProfile>,
+    /// Profile applied to chat requests sent to remote providers.
+    pub prompt_profile: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RedactedText {
+    pub text: String,
+    /// How many matches each rule replaced.
+    pub counts: BTreeMap<RedactionKind, u32>,
+}
+
+enum Rule {
+    /// Replaces the whole match.
+    Whole(RedactionKind, Regex, String),
+    /// A phone-shaped match, unless it is a date.
+    Phone,
+    /// Keeps the introduction (group 1) and replaces the name (group 2).
+    NameIntro,
+}
+
+/// The compiled rules of one profile.
+pub struct Redactor {
+    rules: Vec<Rule>,
+}
+
+impl Redactor {
+    pub(crate) fn new(profile: &RedactionProfile) -> Result<Self, String> {
+        let mut rules = Vec::new();
+        // Keys first, so a token containing `@` isn't half-taken as an email
+        if profile.api_keys {
+            rules.push(Rule::Whole(
+                RedactionKind::ApiKey,
+                API_KEY_PATTERN.clone(),
+                RedactionKind::ApiKey.placeholder().to_string(),
+            ));
+        }
+        if profile.emails {
+            rules.push(Rule::Whole(
+                RedactionKind::Email,
+                EMAIL_PATTERN.clone(),
+                RedactionKind::Email.placeholder().to_string(),
+            ));
+        }
+        if profile.phone_numbers {
+            rules.push(Rule::Phone);
+        }
+
+        let mut known: Vec<&str> = profile
+            .known_names
+            .iter()
+            .map(|name| name.trim())
+            .filter(|name| !name.is_empty())
+            .collect();
+        if !known.is_empty() {
+            // Longest first, so "Ann Lee" wins over "Ann"
+            known.sort_by_key(|name| std::cmp::Reverse(name.len()));
+            let alternatives: Vec<String> = known.iter().map(|name| regex::escape(name)).collect();
+            let pattern = format!(r"(?i)\b(?:{})\b", alternatives.join("|"));
+            rules.push(Rule::Whole(
+                RedactionKind::Name,
+                Regex::new(&pattern).map_err(|e| format!("Invalid known names: {}", e))?,
+                RedactionKind::Name.placeholder().to_string(),
+            ));
+        }
+        if profile.names {
+            rules.push(Rule::NameIntro);
+        }
+
+        for rule in &profile.custom_rules {
+            let regex = Regex::new(&rule.pattern)
+                .map_err(|e| format!("Invalid redaction pattern {:?}: {}", rule.pattern, e))?;
+            let replacement = rule
+                .replacement
+                .clone()
+                .unwrap_or_else(|| RedactionKind::Custom.placeholder().to_string());
+            rules.push(Rule::Whole(RedactionKind::Custom, regex, replacement));
+        }
+        Ok(Self { rules })
+    }
+
+    /// Leaves everything as written.
+    pub(crate) fn none() -> Self {
+        Self { rules: Vec::new() }
+    }
+
+    pub(crate) fn redact(&self, text: &str) -> RedactedText {
+        let mut text = text.to_string();
+        let mut counts = BTreeMap::new();
+        for rule in &self.rules {
+            let mut count = 0;
+            let replaced = match rule {
+                Rule::Whole(_, regex, replacement) => {
+                    regex.replace_all(&text, |caps: &Captures| {
+                        count += 1;
+                        let mut out = String::new();
+                        caps.expand(replacement, &mut out);
+                        out
+                    })
+                }
+                Rule::Phone => PHONE_PATTERN.replace_all(&text, |caps: &Captures| {
+                    let found = &caps[0];
+                    let digits = found.chars().filter(char::is_ascii_digit).count();
+                    if DATE_PATTERN.is_match(found) || !(7..=15).contains(&digits) {
+                        found.to_string()
+                    } else {
+                        count += 1;
+                        RedactionKind::Phone.placeholder().to_string()
+                    }
+                }),
+                Rule::NameIntro => NAME_INTRO_PATTERN.replace_all(&text, |caps: &Captures| {
+                    count += 1;
+                    format!("{}{}", &caps[1], RedactionKind::Name.placeholder())
+                }),
+            }
+            .into_owned();
+            let kind = match rule {
+                Rule::Whole(kind, ..) => *kind,
+                Rule::Phone => RedactionKind::Phone,
+                Rule::NameIntro => RedactionKind::Name,
+            };
+            if count > 0 {
+                *counts.entry(kind).or_insert(0) += count;
+            }
+            text = replaced;
+        }
+        RedactedText { text, counts }
+    }
+
+    pub(crate) fn apply(&self, text: &str) -> String {
+        if self.rules.is_empty() {
+            return text.to_string();
+        }
+        self.redact(text).text
+    }
+
+    /// A copy of `conversation` with its title, messages and attachment
+    /// names redacted.
+    pub(crate) fn conversation(&self, conversation: &Conversation) -> Conversation {
+        let mut conversation = conversation.clone();
+        if self.rules.is_empty() {
+            return conversation;
+        }
+        conversation.title = self.apply(&conversation.title);
+        for message in &mut conversation.messages {
+            message.content = self.apply(&message.content);
+            if let Some(files) = message
+                .attached_files
+                .as_mut()
+                .and_then(|f| f.as_array_mut())
+            {
+                for file in files {
+                    if let Some(name) = file.get("name").and_then(|n| n.as_str()) {
+                        file["name"] = self.apply(name).into();
+                    }
+                }
+            }
+        }
+        conversation
+    }
+
+    /// A copy of `request` with its system prompt and messages redacted.
+    pub(crate) fn request(&self, request: &CompletionRequest) -> CompletionRequest {
+        let mut request = request.clone();
+        request.system_prompt = request.system_prompt.map(|prompt| self.apply(&prompt));
+        for message in &mut request.messages {
+            message.content = self.apply(&message.content);
+        }
+        request
+    }
+}
+
+fn validate(settings: &RedactionSettings) -> Result<(), String> {
+    let mut names = HashSet::new();
+    for profile in &settings.profiles {
+        let name = profile.name.trim();
+        if name.is_empty() {
+            return Err("Redaction profiles need a name".to_string());
+        }
+        if !names.insert(name) {
+            return Err(format!("Duplicate redaction profile: {}", name));
+        }
+        Redactor::new(profile)?;
+    }
+    match &settings.prompt_profile {
+        Some(name) if !names.contains(name.trim()) => {
+            Err(format!("Redaction profile not found: {}", name))
+        }
+        _ => Ok(()),
+    }
+}
+
+async fn load_settings(app: &AppHandle) -> Result<RedactionSettings, String> {
+    let pool = crate::db::pool(app).await?;
+    Ok(crate::db::settings::get(&pool, SETTINGS_KEY)
+        .await?
+        .unwrap_or_default())
+}
+
+/// The saved profile `name`.
+pub(crate) async fn profile(app: &AppHandle, name: &str) -> Result<RedactionProfile, String> {
+    load_settings(app)
+        .await?
+        .profiles
+        .into_iter()
+        .find(|profile| profile.name.trim() == name.trim())
+        .ok_or_else(|| format!("Redaction profile not found: {}", name))
+}
+
+/// The compiled profile `name`.
+pub(crate) async fn load(app: &AppHandle, name: &str) -> Result<Redactor, String> {
+    Redactor::new(&profile(app, name).await?)
+}
+
+/// The prompt profile, for a request going to `provider` at `base_url`.
+/// `None` when there is none or the model runs on this machine.
+pub(crate) async fn for_prompt(
+    app: &AppHandle,
+    provider: ProviderKind,
+    base_url: Option<&str>,
+) -> Result<Option<Redactor>, String> {
+    if provider.is_local() || base_url.is_some_and(crate::net::connectivity::is_loopback_url) {
+        return Ok(None);
+    }
+    match load_settings(app).await?.prompt_profile {
+        Some(name) => load(app, &name).await.map(Some),
+        None => Ok(None),
+    }
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+#[tauri::command]
+pub async fn get_redaction_settings(app: AppHandle) -> Result<RedactionSettings, String> {
+    load_settings(&app).await
+}
+
+#[tauri::command]
+pub async fn set_redaction_settings(
+    app: AppHandle,
+    settings: RedactionSettings,
+) -> Result<(), String> {
+    validate(&settings)?;
+    let pool = crate::db::pool(&app).await?;
+    crate::db::settings::set(&pool, SETTINGS_KEY, &settings).await
+}
+
+/// Redact `text` with the saved profile named `profile`.
+#[tauri::command]
+pub async fn redact_text(
+    app: AppHandle,
+    text: String,
+    profile: String,
+) -> Result<RedactedText, String> {
+    Ok(load(&app, &profile).await?.redact(&text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn built_in_and_custom_rules() {
+        let profile = RedactionProfile {
+            name: "interviews".to_string(),
+            names: true,
+            known_names: vec!["Ann".to_string(), "Ann Lee".to_string()],
+            custom_rules: vec![CustomRule {
+                pattern: r"(EMP)-\d+".to_string(),
+                replacement: Some("$1-***".to_string()),
+            }],
+            ..Default::default()
+        };
+        let redactor = Redactor::new(&profile).unwrap();
+        let redacted = redactor.redact(
+            "Hi, my name is Dana Cruz (dana@example.com, +1 415-555-0134). \
+Ann Lee and ann referred me on 2024-03-15, badge EMP-4471, key sk-abcdefghijklmnopqrstuvwx.",
+        );
+        assert_eq!(
+            redacted.text,
+            "Hi, my name is [redacted name] ([redacted email], [redacted phone]). \
+[redacted name] and [redacted name] referred me on 2024-03-15, badge EMP-***, key \
+[redacted API key]."
+        );
+        assert_eq!(redacted.counts[&RedactionKind::Name], 3);
+        assert_eq!(redacted.counts[&RedactionKind::Phone], 1);
+        assert_eq!(redacted.counts[&RedactionKind::Custom], 1);
+
+        // Version numbers and short counts aren't phone numbers
+        assert_eq!(
+            redactor.apply("v1.2.3 took 12 34 ms"),
+            "v1.2.3 took 12 34 ms"
+        );
+        assert_eq!(
+            Redactor::none().apply("dana@example.com"),
+            "dana@example.com"
+        );
+    }
+
+    #[test]
+    fn settings_are_validated() {
+        let profile = |name: &str| RedactionProfile {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        let mut settings = RedactionSettings {
+            profiles: vec![profile("share"), profile("prompts")],
+            prompt_profile: Some("prompts".to_string()),
+        };
+        assert!(validate(&settings).is_ok());
+
+        settings.prompt_profile = Some("missing".to_string());
+        assert!(validate(&settings).is_err());
+        settings.prompt_profile = None;
+
+        settings.profiles.push(profile("share"));
+        assert!(validate(&settings).is_err());
+        settings.profiles.pop();
+
+        settings.profiles[0].custom_rules.push(CustomRule {
+            pattern: "(".to_string(),
+            replacement: None,
+        });
+        assert!(validate(&settings).is_err());
+    }
+}
@@ -0,0 +1,411 @@
+//! Automatic cleanup of old data, configured by the `retention` setting.
+//!
+//! Each kind of data has its own age limit in days, off (`null`) by
+//! default: transcripts are deleted, recordings removed from disk, and
+//! unpinned conversations moved to the trash, where the `trash` setting
+//! decides when they are purged. Ages are measured from when a transcript
+//! ended, a recording started and a conversation was last updated.
+//!
+//! Nothing is deleted automatically until the current policy has been
+//! previewed with `preview_retention_cleanup`, so the user sees what the
+//! first run would remove; saving a new policy requires a new preview. After
+//! that a daily `retentionCleanup` job applies it.
+
+use crate::audio::recorder;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::path::Path;
+use std::time::Duration;
+use tauri::AppHandle;
+use tracing::warn;
+
+/// Settings key for [`RetentionConfig`].
+pub(crate) const SETTING_KEY: &str = "retention";
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+const MAX_DAYS: u32 = 10 * 365;
+const RUN_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const STARTUP_DELAY: Duration = Duration::from_secs(180);
+
+/// Age limits in days; `None` keeps that kind of data forever.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetentionPolicy {
+    pub transcript_days: Option<u32>,
+    pub recording_days: Option<u32>,
+    pub conversation_days: Option<u32>,
+}
+
+impl RetentionPolicy {
+    fn is_empty(&self) -> bool {
+        self.transcript_days.is_none()
+            && self.recording_days.is_none()
+            && self.conversation_days.is_none()
+    }
+}
+
+/// Stored under the `retention` setting.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetentionConfig {
+    #[serde(flatten)]
+    pub policy: RetentionPolicy,
+    /// When this policy was last previewed; automatic runs wait for it.
+    pub previewed_at: Option<i64>,
+    pub last_run_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranscriptCleanup {
+    pub count: i64,
+    pub minutes: f64,
+    pub oldest_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingCleanup {
+    pub id: String,
+    pub conversation_id: Option<String>,
+    pub started_at: i64,
+    pub size_bytes: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversationCleanup {
+    pub id: String,
+    pub title: String,
+    pub updated_at: i64,
+}
+
+/// What a cleanup would remove now, or did remove.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionReport {
+    pub transcripts: TranscriptCleanup,
+    pub recordings: Vec<RecordingCleanup>,
+    pub conversations: Vec<ConversationCleanup>,
+}
+
+fn validate(policy: &RetentionPolicy) -> Result<(), String> {
+    for days in [
+        policy.transcript_days,
+        policy.recording_days,
+        policy.conversation_days,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        if days == 0 || days > MAX_DAYS {
+            return Err(format!("Retention must be between 1 and {} days", MAX_DAYS));
+        }
+    }
+    Ok(())
+}
+
+fn cutoff(now: i64, days: u32) -> i64 {
+    now - i64::from(days) * DAY_MS
+}
+
+async fn load_config(pool: &SqlitePool) -> Result<RetentionConfig, String> {
+    Ok(crate::settings::get_setting(pool, SETTING_KEY)
+        .await?
+        .unwrap_or_default())
+}
+
+async fn expired_transcripts(pool: &SqlitePool, cutoff: i64) -> Result<TranscriptCleanup, String> {
+    let (count, minutes, oldest_at): (i64, f64, Option<i64>) = sqlx::query_as(
+        "SELECT COUNT(*), TOTAL(ended_at - started_at) / 60000.0, MIN(ended_at)
+         FROM transcripts WHERE ended_at < ?",
+    )
+    .bind(cutoff)
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Failed to read transcripts: {}", e))?;
+    Ok(TranscriptCleanup {
+        count,
+        minutes,
+        oldest_at,
+    })
+}
+
+async fn expired_conversations(
+    pool: &SqlitePool,
+    cutoff: i64,
+) -> Result<Vec<ConversationCleanup>, String> {
+    sqlx::query_as::<_, ConversationCleanup>(
+        "SELECT id, title, updated_at FROM conversations
+         WHERE deleted_at IS NULL AND pinned = 0 AND updated_at < ?
+         ORDER BY updated_at ASC",
+    )
+    .bind(cutoff)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to read conversations: {}", e))
+}
+
+fn expired_recordings(root: &Path, cutoff: i64) -> Result<Vec<RecordingCleanup>, String> {
+    Ok(recorder::list_in(root, None)?
+        .into_iter()
+        .filter(|recording| recording.ended_at.is_some() && recording.started_at < cutoff)
+        .map(|recording| RecordingCleanup {
+            size_bytes: recording.files.iter().map(|f| f.size_bytes).sum(),
+            id: recording.id,
+            conversation_id: recording.conversation_id,
+            started_at: recording.started_at,
+        })
+        .collect())
+}
+
+/// Everything `policy` covers as of `now`, without deleting it.
+pub(crate) async fn preview(
+    pool: &SqlitePool,
+    recordings_root: &Path,
+    policy: &RetentionPolicy,
+    now: i64,
+) -> Result<RetentionReport, String> {
+    let mut report = RetentionReport::default();
+    if let Some(days) = policy.transcript_days {
+        report.transcripts = expired_transcripts(pool, cutoff(now, days)).await?;
+    }
+    if let Some(days) = policy.recording_days {
+        report.recordings = expired_recordings(recordings_root, cutoff(now, days))?;
+    }
+    if let Some(days) = policy.conversation_days {
+        report.conversations = expired_conversations(pool, cutoff(now, days)).await?;
+    }
+    Ok(report)
+}
+
+/// Delete everything `policy` covers as of `now`, returning what went.
+pub(crate) async fn apply(
+    pool: &SqlitePool,
+    recordings_root: &Path,
+    policy: &RetentionPolicy,
+    now: i64,
+) -> Result<RetentionReport, String> {
+    let mut report = preview(pool, recordings_root, policy, now).await?;
+
+    if let Some(days) = policy.transcript_days {
+        sqlx::query("DELETE FROM transcripts WHERE ended_at < ?")
+            .bind(cutoff(now, days))
+            .execute(pool)
+            .await
+            .map_err(|e| format!("Failed to delete transcripts: {}", e))?;
+    }
+    if let Some(days) = policy.conversation_days {
+        sqlx::query(
+            "UPDATE conversations SET deleted_at = ?
+             WHERE deleted_at IS NULL AND pinned = 0 AND updated_at < ?",
+        )
+        .bind(now)
+        .bind(cutoff(now, days))
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to trash conversations: {}", e))?;
+    }
+    report.recordings.retain(|recording| {
+        match recorder::delete_in(recordings_root, &recording.id) {
+            Ok(()) => true,
+            Err(e) => {
+                warn!("Failed to delete recording {}: {}", recording.id, e);
+                false
+            }
+        }
+    });
+    Ok(report)
+}
+
+/// Apply the saved policy. Runs as the `retentionCleanup` job.
+pub(crate) async fn run_cleanup(
+    app: &AppHandle,
+    pool: &SqlitePool,
+) -> Result<RetentionReport, String> {
+    let mut config = load_config(pool).await?;
+    if config.previewed_at.is_none() {
+        return Err("Preview the retention policy before it runs".to_string());
+    }
+    let now = crate::db::now_millis();
+    let report = apply(pool, &recorder::recordings_dir(app)?, &config.policy, now).await?;
+    config.last_run_at = Some(now);
+    crate::settings::set_setting(app, SETTING_KEY, &config).await?;
+    Ok(report)
+}
+
+fn is_due(config: &RetentionConfig, now: i64) -> bool {
+    !config.policy.is_empty()
+        && config.previewed_at.is_some()
+        && config
+            .last_run_at
+            .is_none_or(|last| now - last >= RUN_INTERVAL.as_millis() as i64)
+}
+
+async fn run_scheduled(app: &AppHandle) -> Result<(), String> {
+    let pool = crate::db::pool(app).await?;
+    if is_due(&load_config(&pool).await?, crate::db::now_millis()) {
+        crate::jobs::enqueue(app, crate::jobs::JobSpec::RetentionCleanup).await?;
+    }
+    Ok(())
+}
+
+/// Queue the cleanup job once a day. Called once from `setup`.
+pub fn start_retention_scheduler(app: AppHandle) {
+    crate::jobs::run_periodically(
+        app,
+        "Scheduled retention cleanup",
+        CHECK_INTERVAL,
+        STARTUP_DELAY,
+        |app| async move { run_scheduled(&app).await },
+    );
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+#[tauri::command]
+pub async fn get_retention_policy(app: AppHandle) -> Result<RetentionConfig, String> {
+    let pool = crate::db::pool(&app).await?;
+    load_config(&pool).await
+}
+
+/// Save `policy`. It doesn't run automatically until previewed.
+#[tauri::command]
+pub async fn set_retention_policy(
+    app: AppHandle,
+    policy: RetentionPolicy,
+) -> Result<RetentionConfig, String> {
+    validate(&policy)?;
+    let pool = crate::db::pool(&app).await?;
+    let mut config = load_config(&pool).await?;
+    if config.policy != policy {
+        config.policy = policy;
+        config.previewed_at = None;
+    }
+    crate::settings::set_setting(&app, SETTING_KEY, &config).await?;
+    Ok(config)
+}
+
+/// What the saved policy would remove if it ran now. Previewing lets the
+/// daily cleanup start.
+#[tauri::command]
+pub async fn preview_retention_cleanup(app: AppHandle) -> Result<RetentionReport, String> {
+    let pool = crate::db::pool(&app).await?;
+    let mut config = load_config(&pool).await?;
+    let now = crate::db::now_millis();
+    let report = preview(&pool, &recorder::recordings_dir(&app)?, &config.policy, now).await?;
+    if config.previewed_at.is_none() && !config.policy.is_empty() {
+        config.previewed_at = Some(now);
+        crate::settings::set_setting(&app, SETTING_KEY, &config).await?;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::recorder::{RecordingFormat, RecordingInfo};
+    use tempfile::TempDir;
+
+    fn write_recording(root: &Path, id: &str, started_at: i64) {
+        let dir = root.join(id);
+        std::fs::create_dir_all(&dir).unwrap();
+        let info = RecordingInfo {
+            id: id.to_string(),
+            conversation_id: None,
+            format: RecordingFormat::Wav,
+            started_at,
+            ended_at: Some(started_at + 1000),
+            files: Vec::new(),
+            path: String::new(),
+        };
+        recorder::save_metadata(&dir, &info).unwrap();
+    }
+
+    #[tokio::test]
+    async fn old_data_is_previewed_then_removed() {
+        let pool = crate::db::test_pool().await;
+        let tmp = TempDir::new().unwrap();
+        let now = 100 * DAY_MS;
+
+        sqlx::query(
+            "INSERT INTO conversations (id, title, created_at, updated_at, pinned)
+             VALUES ('old', 'Old', 0, ?1, 0), ('pinned', 'Pinned', 0, ?1, 1),
+                    ('new', 'New', 0, ?2, 0)",
+        )
+        .bind(now - 60 * DAY_MS)
+        .bind(now - DAY_MS)
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            "INSERT INTO transcripts (id, source, text, started_at, ended_at, created_at)
+             VALUES ('t-old', 'microphone', 'a', ?1, ?1 + 60000, ?1),
+                    ('t-new', 'microphone', 'b', ?2, ?2, ?2)",
+        )
+        .bind(now - 95 * DAY_MS)
+        .bind(now - DAY_MS)
+        .execute(&pool)
+        .await
+        .unwrap();
+        write_recording(tmp.path(), "rec-old", now - 40 * DAY_MS);
+        write_recording(tmp.path(), "rec-new", now - DAY_MS);
+
+        let policy = RetentionPolicy {
+            transcript_days: Some(90),
+            recording_days: Some(30),
+            conversation_days: Some(30),
+        };
+        let report = preview(&pool, tmp.path(), &policy, now).await.unwrap();
+        assert_eq!(report.transcripts.count, 1);
+        assert_eq!(report.transcripts.minutes, 1.0);
+        assert_eq!(report.recordings.len(), 1);
+        assert_eq!(report.conversations.len(), 1);
+        assert_eq!(report.conversations[0].id, "old");
+        // Previewing changes nothing
+        assert!(tmp.path().join("rec-old").exists());
+
+        apply(&pool, tmp.path(), &policy, now).await.unwrap();
+        let transcripts: Vec<String> = sqlx::query_scalar("SELECT id FROM transcripts")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(transcripts, ["t-new"]);
+        let trashed: Vec<String> =
+            sqlx::query_scalar("SELECT id FROM conversations WHERE deleted_at IS NOT NULL")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(trashed, ["old"]);
+        assert!(!tmp.path().join("rec-old").exists());
+        assert!(tmp.path().join("rec-new").exists());
+
+        let unset = preview(&pool, tmp.path(), &RetentionPolicy::default(), now)
+            .await
+            .unwrap();
+        assert_eq!(unset.transcripts.count, 0);
+    }
+
+    #[test]
+    fn cleanup_waits_for_a_preview() {
+        let mut config = RetentionConfig {
+            policy: RetentionPolicy {
+                transcript_days: Some(90),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(!is_due(&config, DAY_MS));
+        config.previewed_at = Some(0);
+        assert!(is_due(&config, DAY_MS));
+        config.last_run_at = Some(DAY_MS);
+        assert!(!is_due(&config, DAY_MS + 1000));
+        assert!(validate(&RetentionPolicy {
+            recording_days: Some(0),
+            ..Default::default()
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,403 @@
+//! Running short code snippets the user has approved.
+//!
+//! `run_snippet` writes the snippet to a fresh temporary directory and runs
+//! it with the system's Python, Node or shell, then returns a run ID right
+//! away. Output is streamed as `runner:output:{run_id}` events and
+//! `runner:exit:{run_id}` is emitted once at the end.
+//!
+//! The child is constrained, not isolated:
+//! - it runs in the temporary directory, or in a directory inside a
+//!   registered project when one is requested
+//! - its environment is cleared except for `PATH` and locale, with `HOME`
+//!   and the temp directory pointing at the run's directory
+//! - it is killed, with everything it started, after its timeout or once
+//!   its output passes [`MAX_OUTPUT_BYTES`]
+//!
+//! It can still read the user's files and reach the network, so only
+//! snippets the user has looked at should be run.
+
+use crate::workspace::fs as workspace_fs;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::Command;
+use tokio::sync::{oneshot, Notify};
+use tracing::warn;
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_TIMEOUT: Duration = Duration::from_secs(120);
+/// Output allowed across stdout and stderr before the run is stopped.
+const MAX_OUTPUT_BYTES: usize = 256 * 1024;
+/// How long to wait for the pipes to close after the process is killed.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Variables passed through from Freely's own environment.
+const KEPT_ENV: &[&str] = &["PATH", "LANG", "LC_ALL", "SYSTEMROOT", "COMSPEC"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SnippetLanguage {
+    Python,
+    Node,
+    Shell,
+}
+
+impl SnippetLanguage {
+    fn file_name(self) -> &'static str {
+        match self {
+            Self::Python => "snippet.py",
+            Self::Node => "snippet.js",
+            Self::Shell if cfg!(windows) => "snippet.ps1",
+            Self::Shell => "snippet.sh",
+        }
+    }
+
+    /// Program and arguments that run `file`.
+    fn command(self, file: &Path) -> (&'static str, Vec<String>) {
+        let file = file.to_string_lossy().into_owned();
+        match self {
+            Self::Python if cfg!(windows) => ("python", vec![file]),
+            Self::Python => ("python3", vec![file]),
+            Self::Node => ("node", vec![file]),
+            Self::Shell if cfg!(windows) => (
+                "powershell",
+                vec![
+                    "-NoProfile".to_string(),
+                    "-NonInteractive".to_string(),
+                    "-ExecutionPolicy".to_string(),
+                    "Bypass".to_string(),
+                    "-File".to_string(),
+                    file,
+                ],
+            ),
+            Self::Shell => ("sh", vec![file]),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnippetRequest {
+    pub language: SnippetLanguage,
+    pub code: String,
+    /// Must be inside a registered project; a temporary directory when
+    /// omitted.
+    pub working_directory: Option<String>,
+    /// Capped at [`MAX_TIMEOUT`].
+    pub timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnippetOutput {
+    pub run_id: String,
+    /// "stdout" | "stderr"
+    pub stream: &'static str,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnippetExit {
+    pub run_id: String,
+    pub code: Option<i32>,
+    pub timed_out: bool,
+    /// Stopped for writing more than [`MAX_OUTPUT_BYTES`].
+    pub output_truncated: bool,
+    pub cancelled: bool,
+    pub duration_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+struct RunningSnippet {
+    pid: Option<u32>,
+    cancel: oneshot::Sender<()>,
+}
+
+static RUNNING: Lazy<Mutex<HashMap<String, RunningSnippet>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+fn timeout_for(request: &SnippetRequest) -> Duration {
+    request
+        .timeout_secs
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_TIMEOUT)
+        .min(MAX_TIMEOUT)
+}
+
+/// Take the longest valid UTF-8 prefix of `pending`, keeping an incomplete
+/// character at the end for the next read. Invalid bytes are replaced.
+pub(crate) fn take_utf8(pending: &mut Vec<u8>) -> String {
+    let valid = match std::str::from_utf8(pending) {
+        Ok(_) => pending.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        Err(_) => {
+            let text = String::from_utf8_lossy(pending).into_owned();
+            pending.clear();
+            return text;
+        }
+    };
+    let rest = pending.split_off(valid);
+    let text = String::from_utf8_lossy(pending).into_owned();
+    *pending = rest;
+    text
+}
+
+/// The run's own process group on Unix, so everything it starts can be
+/// killed together.
+#[cfg(unix)]
+fn isolate_process_group(cmd: &mut Command) {
+    cmd.process_group(0);
+}
+
+#[cfg(windows)]
+fn isolate_process_group(_cmd: &mut Command) {}
+
+#[cfg(unix)]
+fn kill_tree_blocking(pid: u32) {
+    let _ = std::process::Command::new("kill")
+        .args(["-KILL", "--", &format!("-{}", pid)])
+        .status();
+}
+
+#[cfg(windows)]
+fn kill_tree_blocking(pid: u32) {
+    let _ = std::process::Command::new("taskkill")
+        .args(["/F", "/T", "/PID", &pid.to_string()])
+        .status();
+}
+
+async fn forward_output(
+    app: AppHandle,
+    run_id: String,
+    stream: &'static str,
+    mut pipe: impl AsyncRead + Unpin,
+    written: Arc<AtomicUsize>,
+    over_limit: Arc<Notify>,
+) {
+    let event_name = format!("runner:output:{}", run_id);
+    let mut buf = [0u8; 8192];
+    let mut pending = Vec::new();
+    loop {
+        let read = match pipe.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(read) => read,
+        };
+        let total = written.fetch_add(read, Ordering::Relaxed) + read;
+        let allowed = read.saturating_sub(total.saturating_sub(MAX_OUTPUT_BYTES));
+        pending.extend_from_slice(&buf[..allowed]);
+        let text = take_utf8(&mut pending);
+        if !text.is_empty() {
+            let output = SnippetOutput {
+                run_id: run_id.clone(),
+                stream,
+                text,
+            };
+            if let Err(e) = app.emit(&event_name, &output) {
+                warn!("Failed to emit snippet output: {}", e);
+            }
+        }
+        if total > MAX_OUTPUT_BYTES {
+            over_limit.notify_one();
+            break;
+        }
+    }
+}
+
+fn prepare_dir(request: &SnippetRequest, run_id: &str) -> Result<(PathBuf, PathBuf), String> {
+    let dir = std::env::temp_dir().join(format!("freely-run-{}", run_id));
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to prepare snippet: {}", e))?;
+    let file = dir.join(request.language.file_name());
+    std::fs::write(&file, &request.code)
+        .map_err(|e| format!("Failed to prepare snippet: {}", e))?;
+    Ok((dir, file))
+}
+
+/// Run an approved snippet and return its run ID.
+#[tauri::command]
+pub async fn run_snippet(app: AppHandle, request: SnippetRequest) -> Result<String, String> {
+    if request.code.trim().is_empty() {
+        return Err("Snippet is empty".to_string());
+    }
+    let working_dir = match request.working_directory.as_deref() {
+        Some(path) => {
+            let roots = workspace_fs::project_roots(&app).await?;
+            let dir = workspace_fs::resolve(&roots, path)?;
+            if !dir.is_dir() {
+                return Err(format!("Not a directory: {}", path));
+            }
+            Some(dir)
+        }
+        None => None,
+    };
+
+    let run_id = uuid::Uuid::new_v4().to_string();
+    let (run_dir, file) = prepare_dir(&request, &run_id)?;
+    let (program, args) = request.language.command(&file);
+    let mut cmd = Command::new(program);
+    cmd.args(&args)
+        .current_dir(working_dir.as_deref().unwrap_or(&run_dir))
+        .env_clear()
+        .envs(
+            KEPT_ENV
+                .iter()
+                .filter_map(|k| Some((*k, std::env::var_os(k)?))),
+        )
+        .env("HOME", &run_dir)
+        .env("USERPROFILE", &run_dir)
+        .env("TMPDIR", &run_dir)
+        .env("TEMP", &run_dir)
+        .env("TMP", &run_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    isolate_process_group(&mut cmd);
+
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            let _ = std::fs::remove_dir_all(&run_dir);
+            return Err(format!("Failed to run {}: {}", program, e));
+        }
+    };
+    let pid = child.id();
+    let (cancel_tx, cancel_rx) = oneshot::channel();
+    RUNNING.lock().insert(
+        run_id.clone(),
+        RunningSnippet {
+            pid,
+            cancel: cancel_tx,
+        },
+    );
+
+    let written = Arc::new(AtomicUsize::new(0));
+    let over_limit = Arc::new(Notify::new());
+    let mut readers = Vec::new();
+    if let Some(stdout) = child.stdout.take() {
+        readers.push(tokio::spawn(forward_output(
+            app.clone(),
+            run_id.clone(),
+            "stdout",
+            stdout,
+            written.clone(),
+            over_limit.clone(),
+        )));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        readers.push(tokio::spawn(forward_output(
+            app.clone(),
+            run_id.clone(),
+            "stderr",
+            stderr,
+            written,
+            over_limit.clone(),
+        )));
+    }
+
+    let timeout = timeout_for(&request);
+    let id = run_id.clone();
+    tokio::spawn(async move {
+        let started = Instant::now();
+        let (mut timed_out, mut output_truncated, mut cancelled) = (false, false, false);
+        let status = tokio::select! {
+            status = child.wait() => Some(status),
+            _ = tokio::time::sleep(timeout) => { timed_out = true; None }
+            _ = over_limit.notified() => { output_truncated = true; None }
+            _ = cancel_rx => { cancelled = true; None }
+        };
+        let status = match status {
+            Some(status) => status,
+            None => {
+                if let Some(pid) = pid {
+                    let _ = tokio::task::spawn_blocking(move || kill_tree_blocking(pid)).await;
+                }
+                let _ = child.start_kill();
+                child.wait().await
+            }
+        };
+        // Processes the snippet started may still hold the pipes open
+        for reader in readers {
+            if tokio::time::timeout(DRAIN_TIMEOUT, reader).await.is_err() {
+                warn!("Snippet {} output was still open after exit", id);
+            }
+        }
+        RUNNING.lock().remove(&id);
+        if let Err(e) = std::fs::remove_dir_all(&run_dir) {
+            warn!("Failed to clean up snippet {}: {}", id, e);
+        }
+
+        let (code, error) = match status {
+            Ok(status) => (status.code(), None),
+            Err(e) => (None, Some(format!("Failed to wait for snippet: {}", e))),
+        };
+        let exit = SnippetExit {
+            run_id: id.clone(),
+            code,
+            timed_out,
+            output_truncated,
+            cancelled,
+            duration_ms: started.elapsed().as_millis() as u64,
+            error,
+        };
+        if let Err(e) = app.emit(&format!("runner:exit:{}", id), &exit) {
+            warn!("Failed to emit snippet exit: {}", e);
+        }
+    });
+
+    Ok(run_id)
+}
+
+/// Stop a running snippet. Unknown or finished runs are a no-op.
+#[tauri::command]
+pub fn cancel_snippet(run_id: String) {
+    if let Some(running) = RUNNING.lock().remove(&run_id) {
+        let _ = running.cancel.send(());
+    }
+}
+
+/// Kill every running snippet. Called when the app exits.
+pub fn shutdown_all() {
+    let running: Vec<RunningSnippet> = RUNNING.lock().drain().map(|(_, r)| r).collect();
+    for pid in running.into_iter().filter_map(|r| r.pid) {
+        kill_tree_blocking(pid);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_is_split_on_character_boundaries() {
+        let mut pending = "héllo".as_bytes().to_vec();
+        let tail = pending.split_off(2);
+        assert_eq!(take_utf8(&mut pending), "h");
+        assert_eq!(pending, [0xC3]);
+        pending.extend_from_slice(&tail);
+        assert_eq!(take_utf8(&mut pending), "éllo");
+        assert!(pending.is_empty());
+
+        let mut invalid = vec![b'a', 0xFF, b'b'];
+        assert_eq!(take_utf8(&mut invalid), "a\u{FFFD}b");
+
+        let request = |timeout_secs| SnippetRequest {
+            language: SnippetLanguage::Python,
+            code: "print(1)".to_string(),
+            working_directory: None,
+            timeout_secs,
+        };
+        assert_eq!(timeout_for(&request(None)), DEFAULT_TIMEOUT);
+        assert_eq!(timeout_for(&request(Some(5))), Duration::from_secs(5));
+        assert_eq!(timeout_for(&request(Some(3600))), MAX_TIMEOUT);
+    }
+}
@@ -0,0 +1,777 @@
+//! Scheduled prompts and reminders.
+//!
+//! Tasks live in `scheduled_tasks` (migration 23), each with a five-field
+//! cron expression in local time (`minute hour day-of-month month
+//! day-of-week`, or `@hourly`, `@daily`, `@weekly`, `@monthly`). A ticker
+//! checks for due tasks every [`CHECK_INTERVAL`] and queues a
+//! `runScheduledTask` job for each, so runs survive restarts and show up
+//! with the other background jobs. Runs missed while Freely was closed are
+//! caught up once, not once per missed slot.
+//!
+//! A reminder just shows a notification. A prompt is sent to the task's
+//! provider, optionally with today's conversations attached, and the answer
+//! is saved as a new conversation; the notification then previews it, with a
+//! button to open the conversation.
+//! Either way `scheduled-task-completed` is emitted.
+
+use crate::db::chat::{self, Conversation, Message, MessageRole};
+use crate::notify::{self, NotificationAction};
+use crate::providers::middleware::Retry;
+use crate::providers::{ChatMessage, ChatRole, CompletionRequest, ProviderKind};
+use chrono::{
+    Datelike, Duration as ChronoDuration, Local, NaiveDate, NaiveDateTime, TimeZone, Timelike,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use tracing::warn;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+const STARTUP_DELAY: Duration = Duration::from_secs(20);
+/// How far ahead to look for the next match before calling an expression
+/// unsatisfiable (e.g. February 30th).
+const SEARCH_DAYS: i64 = 5 * 366;
+/// Transcript of today's conversations attached to a prompt, in characters.
+const MAX_TRANSCRIPT_CHARS: usize = 60_000;
+const PREVIEW_CHARS: usize = 200;
+
+const TASK_COLUMNS: &str = "id, name, schedule, action, enabled, next_run_at, last_run_at,
+     last_error, created_at, updated_at";
+
+// ============================================================================
+// Cron expressions
+// ============================================================================
+
+/// A parsed cron expression. Each field is a bit set of allowed values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Cron matches either day field when both are restricted.
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+fn parse_value(value: &str, min: u32, max: u32) -> Result<u32, String> {
+    let value: u32 = value
+        .parse()
+        .map_err(|_| format!("Invalid cron value: {}", value))?;
+    if value < min || value > max {
+        return Err(format!("Cron value {} is outside {}-{}", value, min, max));
+    }
+    Ok(value)
+}
+
+/// Bit set for one field: lists of `*`, `n`, `a-b`, each with an optional
+/// `/step`.
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, parse_value(step, 1, max.max(1))?),
+            None => (part, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((a, b)) => (parse_value(a, min, max)?, parse_value(b, min, max)?),
+                // `n/step` runs from n to the end of the range
+                None if step > 1 => (parse_value(range, min, max)?, max),
+                None => {
+                    let n = parse_value(range, min, max)?;
+                    (n, n)
+                }
+            },
+        };
+        if start > end {
+            return Err(format!("Invalid cron range: {}", range));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+impl CronSchedule {
+    pub(crate) fn parse(expression: &str) -> Result<Self, String> {
+        let expanded = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(format!(
+                "A cron expression has five fields, got {}: {}",
+                fields.len(),
+                expression
+            ));
+        };
+        let mut weekday_bits = parse_field(weekdays, 0, 7)?;
+        // Both 0 and 7 are Sunday
+        if weekday_bits & (1 << 7) != 0 {
+            weekday_bits = (weekday_bits | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            minutes: parse_field(minutes, 0, 59)?,
+            hours: parse_field(hours, 0, 23)?,
+            days: parse_field(days, 1, 31)?,
+            months: parse_field(months, 1, 12)?,
+            weekdays: weekday_bits,
+            days_restricted: days != "*",
+            weekdays_restricted: weekdays != "*",
+        })
+    }
+
+    fn day_matches(&self, date: NaiveDate) -> bool {
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day || weekday,
+            (true, false) => day,
+            (false, true) => weekday,
+            (false, false) => true,
+        }
+    }
+
+    /// The first matching minute strictly after `after`.
+    pub(crate) fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut t = after.with_second(0)?.with_nanosecond(0)? + ChronoDuration::minutes(1);
+        let limit = t + ChronoDuration::days(SEARCH_DAYS);
+        while t < limit {
+            let midnight = t.date().and_hms_opt(0, 0, 0)?;
+            if self.months & (1 << t.month()) == 0 {
+                let (year, month) = match t.month() {
+                    12 => (t.year() + 1, 1),
+                    month => (t.year(), month + 1),
+                };
+                t = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+            } else if !self.day_matches(t.date()) {
+                t = midnight + ChronoDuration::days(1);
+            } else if self.hours & (1 << t.hour()) == 0 {
+                t = midnight + ChronoDuration::hours(t.hour() as i64 + 1);
+            } else if self.minutes & (1 << t.minute()) == 0 {
+                t += ChronoDuration::minutes(1);
+            } else {
+                return Some(t);
+            }
+        }
+        None
+    }
+}
+
+/// Milliseconds of the next local run of `schedule` after `after`.
+fn next_run_at(schedule: &CronSchedule, after: i64) -> Option<i64> {
+    let mut local = Local.timestamp_millis_opt(after).single()?.naive_local();
+    // A time skipped by a DST change moves on to the next match
+    for _ in 0..4 {
+        let next = schedule.next_after(local)?;
+        if let Some(at) = Local.from_local_datetime(&next).earliest() {
+            return Some(at.timestamp_millis());
+        }
+        local = next;
+    }
+    None
+}
+
+// ============================================================================
+// Tasks
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ScheduledAction {
+    /// Show `message` as a notification.
+    Reminder { message: String },
+    /// Ask a model and save the answer as a new conversation.
+    #[serde(rename_all = "camelCase")]
+    Prompt {
+        prompt: String,
+        provider: ProviderKind,
+        model: String,
+        #[serde(default)]
+        base_url: Option<String>,
+        #[serde(default)]
+        api_key_name: Option<String>,
+        #[serde(default)]
+        profile_id: Option<String>,
+        /// Attach the messages sent since midnight.
+        #[serde(default)]
+        include_todays_conversations: bool,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledTask {
+    pub id: String,
+    pub name: String,
+    pub schedule: String,
+    pub action: ScheduledAction,
+    pub enabled: bool,
+    pub next_run_at: Option<i64>,
+    pub last_run_at: Option<i64>,
+    pub last_error: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledTaskInput {
+    pub name: String,
+    pub schedule: String,
+    pub action: ScheduledAction,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(sqlx::FromRow)]
+struct TaskRow {
+    id: String,
+    name: String,
+    schedule: String,
+    action: String,
+    enabled: bool,
+    next_run_at: Option<i64>,
+    last_run_at: Option<i64>,
+    last_error: Option<String>,
+    created_at: i64,
+    updated_at: i64,
+}
+
+impl TryFrom<TaskRow> for ScheduledTask {
+    type Error = String;
+
+    fn try_from(row: TaskRow) -> Result<Self, Self::Error> {
+        Ok(ScheduledTask {
+            action: serde_json::from_str(&row.action)
+                .map_err(|e| format!("Invalid action for task {}: {}", row.id, e))?,
+            id: row.id,
+            name: row.name,
+            schedule: row.schedule,
+            enabled: row.enabled,
+            next_run_at: row.next_run_at,
+            last_run_at: row.last_run_at,
+            last_error: row.last_error,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        })
+    }
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TaskCompleted<'a> {
+    task_id: &'a str,
+    /// The conversation holding a prompt's answer.
+    conversation_id: Option<&'a str>,
+}
+
+/// Checks `input` and returns when it should first run.
+fn validate(input: &ScheduledTaskInput, now: i64) -> Result<Option<i64>, String> {
+    if input.name.trim().is_empty() {
+        return Err("Invalid task: name must not be empty".to_string());
+    }
+    match &input.action {
+        ScheduledAction::Reminder { message } if message.trim().is_empty() => {
+            return Err("Invalid task: reminder message must not be empty".to_string());
+        }
+        ScheduledAction::Prompt { prompt, model, .. }
+            if prompt.trim().is_empty() || model.trim().is_empty() =>
+        {
+            return Err("Invalid task: a prompt needs text and a model".to_string());
+        }
+        _ => {}
+    }
+    let schedule = CronSchedule::parse(&input.schedule)?;
+    let next = next_run_at(&schedule, now)
+        .ok_or_else(|| format!("Schedule never runs: {}", input.schedule))?;
+    Ok(input.enabled.then_some(next))
+}
+
+fn action_json(action: &ScheduledAction) -> Result<String, String> {
+    serde_json::to_string(action).map_err(|e| format!("Failed to save task: {}", e))
+}
+
+pub(crate) async fn get(pool: &SqlitePool, id: &str) -> Result<Option<ScheduledTask>, String> {
+    let row = sqlx::query_as::<_, TaskRow>(&format!(
+        "SELECT {} FROM scheduled_tasks WHERE id = ?",
+        TASK_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to load task: {}", e))?;
+    row.map(ScheduledTask::try_from).transpose()
+}
+
+async fn list(pool: &SqlitePool) -> Result<Vec<ScheduledTask>, String> {
+    let rows = sqlx::query_as::<_, TaskRow>(&format!(
+        "SELECT {} FROM scheduled_tasks ORDER BY created_at",
+        TASK_COLUMNS
+    ))
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to list tasks: {}", e))?;
+    rows.into_iter().map(ScheduledTask::try_from).collect()
+}
+
+async fn insert(pool: &SqlitePool, input: &ScheduledTaskInput) -> Result<ScheduledTask, String> {
+    let now = crate::db::now_millis();
+    let next_run_at = validate(input, now)?;
+    let id = uuid::Uuid::new_v4().to_string();
+    sqlx::query(
+        "INSERT INTO scheduled_tasks
+             (id, name, schedule, action, enabled, next_run_at, created_at, updated_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&id)
+    .bind(input.name.trim())
+    .bind(input.schedule.trim())
+    .bind(action_json(&input.action)?)
+    .bind(input.enabled)
+    .bind(next_run_at)
+    .bind(now)
+    .bind(now)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to create task: {}", e))?;
+    get(pool, &id)
+        .await?
+        .ok_or_else(|| format!("Task not found: {}", id))
+}
+
+async fn update(
+    pool: &SqlitePool,
+    id: &str,
+    input: &ScheduledTaskInput,
+) -> Result<ScheduledTask, String> {
+    let now = crate::db::now_millis();
+    let next_run_at = validate(input, now)?;
+    let result = sqlx::query(
+        "UPDATE scheduled_tasks
+         SET name = ?, schedule = ?, action = ?, enabled = ?, next_run_at = ?, updated_at = ?
+         WHERE id = ?",
+    )
+    .bind(input.name.trim())
+    .bind(input.schedule.trim())
+    .bind(action_json(&input.action)?)
+    .bind(input.enabled)
+    .bind(next_run_at)
+    .bind(now)
+    .bind(id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to update task: {}", e))?;
+    if result.rows_affected() == 0 {
+        return Err(format!("Task not found: {}", id));
+    }
+    get(pool, id)
+        .await?
+        .ok_or_else(|| format!("Task not found: {}", id))
+}
+
+async fn delete(pool: &SqlitePool, id: &str) -> Result<bool, String> {
+    let result = sqlx::query("DELETE FROM scheduled_tasks WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to delete task: {}", e))?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Enabled tasks whose next run is at or before `now`.
+async fn due(pool: &SqlitePool, now: i64) -> Result<Vec<ScheduledTask>, String> {
+    let rows = sqlx::query_as::<_, TaskRow>(&format!(
+        "SELECT {} FROM scheduled_tasks
+         WHERE enabled = 1 AND next_run_at IS NOT NULL AND next_run_at <= ?
+         ORDER BY next_run_at",
+        TASK_COLUMNS
+    ))
+    .bind(now)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load due tasks: {}", e))?;
+    rows.into_iter().map(ScheduledTask::try_from).collect()
+}
+
+async fn set_next_run(pool: &SqlitePool, id: &str, next_run_at: Option<i64>) -> Result<(), String> {
+    sqlx::query("UPDATE scheduled_tasks SET next_run_at = ? WHERE id = ?")
+        .bind(next_run_at)
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to schedule task: {}", e))?;
+    Ok(())
+}
+
+async fn record_run(pool: &SqlitePool, id: &str, error: Option<&str>) -> Result<(), String> {
+    sqlx::query("UPDATE scheduled_tasks SET last_run_at = ?, last_error = ? WHERE id = ?")
+        .bind(crate::db::now_millis())
+        .bind(error)
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to record task run: {}", e))?;
+    Ok(())
+}
+
+// ============================================================================
+// Running
+// ============================================================================
+
+fn preview(text: &str) -> String {
+    let text = text.trim();
+    match text.char_indices().nth(PREVIEW_CHARS) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// Messages sent since local midnight, grouped by conversation.
+async fn todays_transcript(pool: &SqlitePool) -> Result<String, String> {
+    let midnight = Local::now()
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .and_then(|t| Local.from_local_datetime(&t).earliest())
+        .map(|t| t.timestamp_millis())
+        .unwrap_or_default();
+    let rows: Vec<(String, String, String)> = sqlx::query_as(
+        "SELECT c.title, m.role, m.content FROM messages m
+         JOIN conversations c ON c.id = m.conversation_id
+         WHERE m.timestamp >= ? AND c.deleted_at IS NULL AND m.role != 'system'
+         ORDER BY c.updated_at, c.id, m.timestamp",
+    )
+    .bind(midnight)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to load today's conversations: {}", e))?;
+
+    let mut transcript = String::new();
+    let mut current_title: Option<&str> = None;
+    for (title, role, content) in &rows {
+        if current_title != Some(title.as_str()) {
+            transcript.push_str(&format!("\n## {}\n\n", title));
+            current_title = Some(title);
+        }
+        transcript.push_str(&format!("{}: {}\n\n", role, content));
+        if transcript.len() > MAX_TRANSCRIPT_CHARS {
+            transcript.push_str("[Later messages omitted]\n");
+            break;
+        }
+    }
+    Ok(transcript)
+}
+
+/// Send a prompt task to its model and save the exchange as a new
+/// conversation. Returns the conversation's ID.
+async fn run_prompt(
+    app: &AppHandle,
+    pool: &SqlitePool,
+    task: &ScheduledTask,
+) -> Result<String, String> {
+    let ScheduledAction::Prompt {
+        prompt,
+        provider,
+        model,
+        base_url,
+        api_key_name,
+        profile_id,
+        include_todays_conversations,
+    } = &task.action
+    else {
+        return Err(format!("Task {} is not a prompt", task.id));
+    };
+
+    let mut content = prompt.clone();
+    if *include_todays_conversations {
+        let transcript = todays_transcript(pool).await?;
+        if transcript.trim().is_empty() {
+            content.push_str("\n\n(There were no conversations today.)");
+        } else {
+            content.push_str(&format!("\n\nToday's conversations:\n{}", transcript));
+        }
+    }
+    let request = CompletionRequest {
+        provider: *provider,
+        model: model.clone(),
+        messages: vec![ChatMessage {
+            role: ChatRole::User,
+            content,
+            images: Vec::new(),
+        }],
+        system_prompt: None,
+        temperature: None,
+        max_tokens: None,
+        base_url: base_url.clone(),
+        api_key_name: api_key_name.clone(),
+        retry: None,
+        profile_id: profile_id.clone(),
+        skip_cache: false,
+    };
+    if let Some(exceeded) = crate::usage::budget_exceeded(app, request.provider).await {
+        return Err(exceeded.to_string());
+    }
+    let client = match profile_id {
+        Some(profile_id) => {
+            crate::providers::profiles::connect(app, profile_id, Retry::default()).await?
+        }
+        None => {
+            crate::providers::connect(
+                app,
+                request.provider,
+                request.base_url.as_deref(),
+                request.api_key_name.as_deref(),
+                Retry::default(),
+            )
+            .await?
+        }
+    };
+    let started = Instant::now();
+    let output = client.stream_completion(&request, &|_| {}).await?;
+    crate::usage::record(app, "scheduled", None, &request, &output, started.elapsed()).await;
+
+    let now = crate::db::now_millis();
+    let message = |role, content: &str, timestamp| Message {
+        id: uuid::Uuid::new_v4().to_string(),
+        role,
+        content: content.to_string(),
+        timestamp,
+        attached_files: None,
+        truncated: false,
+    };
+    let conversation = chat::create(
+        pool,
+        Conversation {
+            id: uuid::Uuid::new_v4().to_string(),
+            title: task.name.clone(),
+            created_at: now,
+            updated_at: now,
+            pinned: false,
+            archived_at: None,
+            deleted_at: None,
+            messages: vec![
+                message(MessageRole::User, prompt, now),
+                message(MessageRole::Assistant, &output.text, now + 1),
+            ],
+        },
+    )
+    .await?;
+    let open = NotificationAction::OpenConversation {
+        conversation_id: conversation.id.clone(),
+    };
+    notify::show(app, &task.name, &preview(&output.text), vec![open]);
+    Ok(conversation.id)
+}
+
+/// Run task `id` now. Runs as the `runScheduledTask` job.
+pub(crate) async fn run_task(
+    app: &AppHandle,
+    pool: &SqlitePool,
+    id: &str,
+) -> Result<Value, String> {
+    let task = get(pool, id)
+        .await?
+        .ok_or_else(|| format!("Task not found: {}", id))?;
+    let outcome = match &task.action {
+        ScheduledAction::Reminder { message } => {
+            notify::show(app, &task.name, message, Vec::new());
+            Ok(None)
+        }
+        ScheduledAction::Prompt { .. } => run_prompt(app, pool, &task).await.map(Some),
+    };
+    record_run(pool, id, outcome.as_ref().err().map(String::as_str)).await?;
+    let conversation_id = outcome?;
+
+    let event = TaskCompleted {
+        task_id: id,
+        conversation_id: conversation_id.as_deref(),
+    };
+    if let Err(e) = app.emit("scheduled-task-completed", event) {
+        warn!("Failed to emit scheduled task completion: {}", e);
+    }
+    Ok(json!({ "conversationId": conversation_id }))
+}
+
+async fn queue_due(app: &AppHandle) -> Result<(), String> {
+    let pool = crate::db::pool(app).await?;
+    let now = crate::db::now_millis();
+    for task in due(&pool, now).await? {
+        // Advance first, so a slow or failing run isn't queued again
+        let next = CronSchedule::parse(&task.schedule)
+            .ok()
+            .and_then(|schedule| next_run_at(&schedule, now));
+        set_next_run(&pool, &task.id, next).await?;
+        let spec = crate::jobs::JobSpec::RunScheduledTask {
+            task_id: task.id.clone(),
+        };
+        crate::jobs::enqueue(app, spec).await?;
+    }
+    Ok(())
+}
+
+/// Queue scheduled tasks as they come due. Called once from `setup`.
+pub fn start_scheduler(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(STARTUP_DELAY).await;
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Err(e) = queue_due(&app).await {
+                warn!("Failed to queue scheduled tasks: {}", e);
+            }
+        }
+    });
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+#[tauri::command]
+pub async fn list_scheduled_tasks(app: AppHandle) -> Result<Vec<ScheduledTask>, String> {
+    let pool = crate::db::pool(&app).await?;
+    list(&pool).await
+}
+
+#[tauri::command]
+pub async fn create_scheduled_task(
+    app: AppHandle,
+    input: ScheduledTaskInput,
+) -> Result<ScheduledTask, String> {
+    let pool = crate::db::pool(&app).await?;
+    insert(&pool, &input).await
+}
+
+#[tauri::command]
+pub async fn update_scheduled_task(
+    app: AppHandle,
+    id: String,
+    input: ScheduledTaskInput,
+) -> Result<ScheduledTask, String> {
+    let pool = crate::db::pool(&app).await?;
+    update(&pool, &id, &input).await
+}
+
+#[tauri::command]
+pub async fn delete_scheduled_task(app: AppHandle, id: String) -> Result<bool, String> {
+    let pool = crate::db::pool(&app).await?;
+    delete(&pool, &id).await
+}
+
+/// Queue task `id` right away, e.g. to try it out. Its schedule is
+/// unchanged.
+#[tauri::command]
+pub async fn run_scheduled_task_now(
+    app: AppHandle,
+    id: String,
+) -> Result<crate::jobs::store::Job, String> {
+    let pool = crate::db::pool(&app).await?;
+    if get(&pool, &id).await?.is_none() {
+        return Err(format!("Task not found: {}", id));
+    }
+    crate::jobs::enqueue(&app, crate::jobs::JobSpec::RunScheduledTask { task_id: id }).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn cron_expressions_find_the_next_run() {
+        let six_pm = CronSchedule::parse("0 18 * * *").unwrap();
+        assert_eq!(
+            six_pm.next_after(at("2024-05-01 17:59")),
+            Some(at("2024-05-01 18:00"))
+        );
+        assert_eq!(
+            six_pm.next_after(at("2024-05-01 18:00")),
+            Some(at("2024-05-02 18:00"))
+        );
+
+        // Weekdays at 9:30; 2024-05-03 is a Friday
+        let weekdays = CronSchedule::parse("30 9 * * 1-5").unwrap();
+        assert_eq!(
+            weekdays.next_after(at("2024-05-03 10:00")),
+            Some(at("2024-05-06 09:30"))
+        );
+
+        let every_15 = CronSchedule::parse("*/15 * * * *").unwrap();
+        assert_eq!(
+            every_15.next_after(at("2024-05-01 10:07")),
+            Some(at("2024-05-01 10:15"))
+        );
+
+        // Both day fields restricted: either matches
+        let first_or_sunday = CronSchedule::parse("0 0 1 * 7").unwrap();
+        assert_eq!(
+            first_or_sunday.next_after(at("2024-05-02 00:00")),
+            Some(at("2024-05-05 00:00"))
+        );
+        assert_eq!(
+            CronSchedule::parse("@monthly")
+                .unwrap()
+                .next_after(at("2024-12-15 00:00")),
+            Some(at("2025-01-01 00:00"))
+        );
+
+        assert!(CronSchedule::parse("0 0 30 2 *")
+            .unwrap()
+            .next_after(at("2024-01-01 00:00"))
+            .is_none());
+        assert!(CronSchedule::parse("60 * * * *").is_err());
+        assert!(CronSchedule::parse("0 18 * *").is_err());
+        assert!(CronSchedule::parse("5-1 * * * *").is_err());
+    }
+
+    #[tokio::test]
+    async fn due_tasks_and_validation() {
+        let pool = crate::db::test_pool().await;
+        let input = ScheduledTaskInput {
+            name: "Flashcards".to_string(),
+            schedule: "0 18 * * *".to_string(),
+            action: ScheduledAction::Reminder {
+                message: "Review flashcards".to_string(),
+            },
+            enabled: true,
+        };
+        let task = insert(&pool, &input).await.unwrap();
+        let next = task.next_run_at.unwrap();
+        assert!(next > task.created_at);
+        assert!(due(&pool, next - 1).await.unwrap().is_empty());
+        assert_eq!(due(&pool, next).await.unwrap().len(), 1);
+
+        let disabled = ScheduledTaskInput {
+            enabled: false,
+            ..input.clone()
+        };
+        let task = update(&pool, &task.id, &disabled).await.unwrap();
+        assert_eq!(task.next_run_at, None);
+        assert!(due(&pool, i64::MAX).await.unwrap().is_empty());
+
+        let invalid = ScheduledTaskInput {
+            schedule: "every evening".to_string(),
+            ..input
+        };
+        assert!(insert(&pool, &invalid).await.is_err());
+        assert!(delete(&pool, &task.id).await.unwrap());
+    }
+}
@@ -0,0 +1,329 @@
+//! Programmatic screenshots for chat attachments.
+//!
+//! Unlike the overlay flow in `capture.rs`, these commands capture without
+//! any UI: a whole monitor, a single window, or a rectangle in desktop
+//! coordinates. Results come back as base64 PNG or, for large captures the
+//! webview doesn't need to hold in memory, as a temp file path.
+
+use base64::Engine;
+use image::codecs::png::PngEncoder;
+use image::{ColorType, GenericImageView, ImageEncoder, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use xcap::{Monitor, Window};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScreenshotOutput {
+    #[default]
+    Base64,
+    File,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Screenshot {
+    pub width: u32,
+    pub height: u32,
+    /// Set for [`ScreenshotOutput::Base64`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub png_base64: Option<String>,
+    /// Set for [`ScreenshotOutput::File`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureWindowInfo {
+    pub id: u32,
+    pub title: String,
+    pub app_name: String,
+    pub is_minimized: bool,
+}
+
+/// A rectangle in desktop coordinates, as reported by xcap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    fn of_monitor(monitor: &Monitor) -> Self {
+        Rect {
+            x: monitor.x(),
+            y: monitor.y(),
+            width: monitor.width(),
+            height: monitor.height(),
+        }
+    }
+
+    fn right(&self) -> i64 {
+        self.x as i64 + self.width as i64
+    }
+
+    fn bottom(&self) -> i64 {
+        self.y as i64 + self.height as i64
+    }
+
+    pub(crate) fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let left = self.x.max(other.x);
+        let top = self.y.max(other.y);
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+
+        if right <= left as i64 || bottom <= top as i64 {
+            return None;
+        }
+        Some(Rect {
+            x: left,
+            y: top,
+            width: (right - left as i64) as u32,
+            height: (bottom - top as i64) as u32,
+        })
+    }
+
+    fn area(&self) -> u64 {
+        self.width as u64 * self.height as u64
+    }
+}
+
+/// Index of the monitor covering most of `region`.
+pub(crate) fn best_monitor(monitors: &[Rect], region: &Rect) -> Option<usize> {
+    monitors
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, m)| m.intersection(region).map(|overlap| (idx, overlap.area())))
+        .max_by_key(|&(_, area)| area)
+        .map(|(idx, _)| idx)
+}
+
+/// Crop the part of `region` visible on `monitor` out of its captured image.
+///
+/// The image may be larger than the monitor's reported size on HiDPI
+/// displays, so coordinates are scaled by the image/monitor ratio.
+pub(crate) fn crop_to_region(
+    image: &RgbaImage,
+    monitor: Rect,
+    region: Rect,
+) -> Result<RgbaImage, String> {
+    let visible = monitor
+        .intersection(&region)
+        .ok_or("Region is outside every monitor")?;
+    if image.width() == 0 || image.height() == 0 {
+        return Err("Captured image is empty".to_string());
+    }
+
+    let scale_x = image.width() as f64 / monitor.width.max(1) as f64;
+    let scale_y = image.height() as f64 / monitor.height.max(1) as f64;
+
+    let x = (((visible.x - monitor.x) as f64 * scale_x).round() as u32).min(image.width() - 1);
+    let y = (((visible.y - monitor.y) as f64 * scale_y).round() as u32).min(image.height() - 1);
+    let width = ((visible.width as f64 * scale_x).round() as u32).clamp(1, image.width() - x);
+    let height = ((visible.height as f64 * scale_y).round() as u32).clamp(1, image.height() - y);
+
+    Ok(image.view(x, y, width, height).to_image())
+}
+
+fn encode_png(image: &RgbaImage) -> Result<Vec<u8>, String> {
+    let mut png_buffer = Vec::new();
+    PngEncoder::new(&mut png_buffer)
+        .write_image(
+            image.as_raw(),
+            image.width(),
+            image.height(),
+            ColorType::Rgba8.into(),
+        )
+        .map_err(|e| format!("Failed to encode to PNG: {}", e))?;
+    Ok(png_buffer)
+}
+
+fn screenshots_dir() -> PathBuf {
+    std::env::temp_dir().join("freely").join("screenshots")
+}
+
+fn finish(image: RgbaImage, output: ScreenshotOutput) -> Result<Screenshot, String> {
+    let png = encode_png(&image)?;
+    let mut screenshot = Screenshot {
+        width: image.width(),
+        height: image.height(),
+        png_base64: None,
+        path: None,
+    };
+
+    match output {
+        ScreenshotOutput::Base64 => {
+            screenshot.png_base64 = Some(base64::engine::general_purpose::STANDARD.encode(png));
+        }
+        ScreenshotOutput::File => {
+            let dir = screenshots_dir();
+            std::fs::create_dir_all(&dir)
+                .map_err(|e| format!("Failed to create screenshot directory: {}", e))?;
+            let path = dir.join(format!(
+                "screenshot-{}-{}.png",
+                crate::db::now_millis(),
+                &uuid::Uuid::new_v4().simple().to_string()[..8]
+            ));
+            std::fs::write(&path, png).map_err(|e| format!("Failed to write screenshot: {}", e))?;
+            screenshot.path = Some(path.to_string_lossy().into_owned());
+        }
+    }
+
+    Ok(screenshot)
+}
+
+async fn run_blocking<F>(task: F) -> Result<Screenshot, String>
+where
+    F: FnOnce() -> Result<Screenshot, String> + Send + 'static,
+{
+    tauri::async_runtime::spawn_blocking(task)
+        .await
+        .map_err(|e| format!("Task panicked: {}", e))?
+}
+
+/// Capture a whole monitor; the primary one unless `monitor_id` is given.
+#[tauri::command]
+pub async fn capture_screen(
+    monitor_id: Option<u32>,
+    output: Option<ScreenshotOutput>,
+) -> Result<Screenshot, String> {
+    run_blocking(move || {
+        let monitors = Monitor::all().map_err(|e| format!("Failed to get monitors: {}", e))?;
+        let monitor = match monitor_id {
+            Some(id) => monitors.into_iter().find(|m| m.id() == id),
+            None => {
+                let primary = monitors.iter().position(|m| m.is_primary()).unwrap_or(0);
+                monitors.into_iter().nth(primary)
+            }
+        }
+        .ok_or("Monitor not found")?;
+
+        let image = monitor
+            .capture_image()
+            .map_err(|e| format!("Failed to capture image: {}", e))?;
+        finish(image, output.unwrap_or_default())
+    })
+    .await
+}
+
+/// Windows that can be passed to `capture_window`, topmost first where the
+/// platform reports stacking order. Our own windows are excluded.
+#[tauri::command]
+pub async fn list_capture_windows() -> Result<Vec<CaptureWindowInfo>, String> {
+    tauri::async_runtime::spawn_blocking(|| {
+        let own_pid = std::process::id();
+        let windows = Window::all().map_err(|e| format!("Failed to list windows: {}", e))?;
+        Ok(windows
+            .iter()
+            .filter(|w| w.process_id() != own_pid && w.width() > 0 && w.height() > 0)
+            .map(|w| CaptureWindowInfo {
+                id: w.id(),
+                title: w.title().to_string(),
+                app_name: w.app_name().to_string(),
+                is_minimized: w.is_minimized(),
+            })
+            .collect())
+    })
+    .await
+    .map_err(|e| format!("Task panicked: {}", e))?
+}
+
+/// Capture one window; without `window_id`, the topmost visible window that
+/// isn't Freely's own.
+#[tauri::command]
+pub async fn capture_window(
+    window_id: Option<u32>,
+    output: Option<ScreenshotOutput>,
+) -> Result<Screenshot, String> {
+    run_blocking(move || {
+        let own_pid = std::process::id();
+        let windows = Window::all().map_err(|e| format!("Failed to list windows: {}", e))?;
+        let window = match window_id {
+            Some(id) => windows.into_iter().find(|w| w.id() == id),
+            None => windows.into_iter().find(|w| {
+                w.process_id() != own_pid && !w.is_minimized() && w.width() > 0 && w.height() > 0
+            }),
+        }
+        .ok_or("Window not found")?;
+
+        if window.is_minimized() {
+            return Err("Cannot capture a minimized window".to_string());
+        }
+
+        let image = window
+            .capture_image()
+            .map_err(|e| format!("Failed to capture window: {}", e))?;
+        finish(image, output.unwrap_or_default())
+    })
+    .await
+}
+
+/// Capture a rectangle in desktop coordinates. A region spanning several
+/// monitors is cropped to the monitor that holds most of it.
+#[tauri::command]
+pub async fn capture_region(
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    output: Option<ScreenshotOutput>,
+) -> Result<Screenshot, String> {
+    if width == 0 || height == 0 {
+        return Err("Invalid selection dimensions".to_string());
+    }
+    let region = Rect {
+        x,
+        y,
+        width,
+        height,
+    };
+
+    run_blocking(move || {
+        let monitors = Monitor::all().map_err(|e| format!("Failed to get monitors: {}", e))?;
+        let rects: Vec<Rect> = monitors.iter().map(Rect::of_monitor).collect();
+        let idx = best_monitor(&rects, &region).ok_or("Region is outside every monitor")?;
+
+        let image = monitors[idx]
+            .capture_image()
+            .map_err(|e| format!("Failed to capture image: {}", e))?;
+        let cropped = crop_to_region(&image, rects[idx], region)?;
+        finish(cropped, output.unwrap_or_default())
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: i32, y: i32, width: u32, height: u32) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn best_monitor_picks_largest_overlap() {
+        let monitors = [rect(0, 0, 1920, 1080), rect(1920, 0, 1920, 1080)];
+        assert_eq!(best_monitor(&monitors, &rect(1800, 100, 400, 300)), Some(1));
+        assert_eq!(best_monitor(&monitors, &rect(-500, -500, 100, 100)), None);
+    }
+
+    #[test]
+    fn crop_scales_for_hidpi_and_clamps_to_monitor() {
+        // 100x50 logical monitor captured at 2x
+        let mut image = RgbaImage::new(200, 100);
+        image.put_pixel(40, 20, image::Rgba([255, 0, 0, 255]));
+
+        let monitor = rect(1000, 0, 100, 50);
+        let cropped = crop_to_region(&image, monitor, rect(1020, 10, 500, 10)).unwrap();
+        assert_eq!((cropped.width(), cropped.height()), (160, 20));
+        assert_eq!(cropped.get_pixel(0, 0), &image::Rgba([255, 0, 0, 255]));
+    }
+}
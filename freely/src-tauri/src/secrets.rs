@@ -0,0 +1,464 @@
+//! Provider API key storage.
+//!
+//! Keys are kept in the OS credential store (macOS Keychain, Windows Credential
+//! Manager, Secret Service / libsecret on Linux). When no credential store is
+//! available — e.g. a Linux session without a Secret Service daemon — keys fall
+//! back to `secrets.json` in the app's local data directory, encrypted with
+//! AES-256-GCM under a random per-install key stored next to it in
+//! `secrets.key` (owner-only permissions on Unix).
+
+use aes_gcm::aead::{Aead, KeyInit, OsRng};
+use aes_gcm::{AeadCore, Aes256Gcm, Key, Nonce};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+use tracing::warn;
+
+/// Service name under which entries are stored in the OS credential store.
+const KEYCHAIN_SERVICE: &str = "com.freely.app";
+
+const SECRETS_FILE: &str = "secrets.json";
+const SECRETS_KEY_FILE: &str = "secrets.key";
+const NONCE_LEN: usize = 12;
+
+/// Prefix of the secrets the app keeps for itself, such as the lock passcode
+/// hash and encryption keys. The webview's key commands refuse these names.
+const INTERNAL_PREFIX: &str = "internal.";
+
+/// Names become credential-store account names and JSON keys, so they are
+/// restricted to a conservative character set.
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Check a provider (API key) name, which must also stay out of the
+/// internal namespace.
+pub(crate) fn validate_provider(provider: &str) -> Result<(), String> {
+    if !is_valid_name(provider) {
+        return Err(format!("Invalid provider name: {:?}", provider));
+    }
+    if provider.starts_with(INTERNAL_PREFIX) {
+        return Err(format!("Reserved provider name: {:?}", provider));
+    }
+    Ok(())
+}
+
+// ============================================================================
+// OS credential store
+// ============================================================================
+
+fn keychain_entry(provider: &str) -> keyring::Result<keyring::Entry> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, provider)
+}
+
+fn keychain_set(provider: &str, key: &str) -> keyring::Result<()> {
+    keychain_entry(provider)?.set_password(key)
+}
+
+/// `Ok(None)` means the store works but has no entry for `provider`.
+fn keychain_get(provider: &str) -> keyring::Result<Option<String>> {
+    match keychain_entry(provider)?.get_password() {
+        Ok(key) => Ok(Some(key)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn keychain_delete(provider: &str) -> keyring::Result<()> {
+    match keychain_entry(provider)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+// ============================================================================
+// Encrypted file fallback
+// ============================================================================
+
+#[derive(Serialize, Deserialize)]
+struct EncryptedSecrets {
+    version: u32,
+    /// base64(nonce || ciphertext) of the JSON-encoded provider → key map.
+    data: String,
+}
+
+/// Encrypted provider → key map stored under a directory.
+pub(crate) struct FileStore {
+    dir: PathBuf,
+}
+
+impl FileStore {
+    pub(crate) fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn load_or_create_key(&self) -> Result<Key<Aes256Gcm>, String> {
+        let path = self.dir.join(SECRETS_KEY_FILE);
+
+        if path.exists() {
+            let bytes = std::fs::read(&path)
+                .map_err(|e| format!("Failed to read {}: {}", SECRETS_KEY_FILE, e))?;
+            if bytes.len() != 32 {
+                return Err(format!("Corrupt {}", SECRETS_KEY_FILE));
+            }
+            return Ok(*Key::<Aes256Gcm>::from_slice(&bytes));
+        }
+
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create secrets directory: {}", e))?;
+
+        let key = Aes256Gcm::generate_key(OsRng);
+        write_private(&path, key.as_slice())?;
+        Ok(key)
+    }
+
+    fn read_all(&self) -> Result<BTreeMap<String, String>, String> {
+        let path = self.dir.join(SECRETS_FILE);
+        if !path.exists() {
+            return Ok(BTreeMap::new());
+        }
+
+        let raw = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", SECRETS_FILE, e))?;
+        let file: EncryptedSecrets = serde_json::from_str(&raw)
+            .map_err(|e| format!("Failed to parse {}: {}", SECRETS_FILE, e))?;
+        let blob = BASE64
+            .decode(file.data)
+            .map_err(|e| format!("Failed to decode {}: {}", SECRETS_FILE, e))?;
+        if blob.len() < NONCE_LEN {
+            return Err(format!("Corrupt {}", SECRETS_FILE));
+        }
+
+        let (nonce, ciphertext) = blob.split_at(NONCE_LEN);
+        let cipher = Aes256Gcm::new(&self.load_or_create_key()?);
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| format!("Failed to decrypt {}", SECRETS_FILE))?;
+
+        serde_json::from_slice(&plaintext)
+            .map_err(|e| format!("Failed to parse decrypted secrets: {}", e))
+    }
+
+    fn write_all(&self, secrets: &BTreeMap<String, String>) -> Result<(), String> {
+        let path = self.dir.join(SECRETS_FILE);
+        if secrets.is_empty() {
+            if path.exists() {
+                std::fs::remove_file(&path)
+                    .map_err(|e| format!("Failed to remove {}: {}", SECRETS_FILE, e))?;
+            }
+            return Ok(());
+        }
+
+        let plaintext = serde_json::to_vec(secrets)
+            .map_err(|e| format!("Failed to serialize secrets: {}", e))?;
+        let cipher = Aes256Gcm::new(&self.load_or_create_key()?);
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext.as_slice())
+            .map_err(|_| "Failed to encrypt secrets".to_string())?;
+
+        let mut blob = nonce.to_vec();
+        blob.extend_from_slice(&ciphertext);
+        let file = EncryptedSecrets {
+            version: 1,
+            data: BASE64.encode(blob),
+        };
+        let content = serde_json::to_string(&file)
+            .map_err(|e| format!("Failed to serialize {}: {}", SECRETS_FILE, e))?;
+
+        write_private(&path, content.as_bytes())
+    }
+
+    pub(crate) fn get(&self, provider: &str) -> Result<Option<String>, String> {
+        Ok(self.read_all()?.remove(provider))
+    }
+
+    pub(crate) fn set(&self, provider: &str, key: &str) -> Result<(), String> {
+        let mut secrets = self.read_all()?;
+        secrets.insert(provider.to_string(), key.to_string());
+        self.write_all(&secrets)
+    }
+
+    pub(crate) fn delete(&self, provider: &str) -> Result<(), String> {
+        let mut secrets = self.read_all()?;
+        if secrets.remove(provider).is_some() {
+            self.write_all(&secrets)?;
+        }
+        Ok(())
+    }
+}
+
+/// Write a file readable only by the current user (on Unix).
+pub(crate) fn write_private(path: &Path, contents: &[u8]) -> Result<(), String> {
+    let tmp = path.with_extension("tmp");
+
+    #[cfg(unix)]
+    {
+        use std::io::Write;
+        use std::os::unix::fs::OpenOptionsExt;
+
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&tmp)
+            .map_err(|e| format!("Failed to open {}: {}", tmp.display(), e))?;
+        file.write_all(contents)
+            .map_err(|e| format!("Failed to write {}: {}", tmp.display(), e))?;
+    }
+
+    #[cfg(not(unix))]
+    std::fs::write(&tmp, contents)
+        .map_err(|e| format!("Failed to write {}: {}", tmp.display(), e))?;
+
+    std::fs::rename(&tmp, path).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+fn file_store(app: &AppHandle) -> Result<FileStore, String> {
+    let data_dir = app
+        .path()
+        .app_local_data_dir()
+        .map_err(|e| format!("Could not resolve app_local_data_dir: {}", e))?;
+
+    Ok(FileStore::new(data_dir))
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+// Credential store calls can block on user prompts or D-Bus round trips, so
+// every command runs them off the async executor.
+async fn blocking<T: Send + 'static>(
+    f: impl FnOnce() -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| format!("Secrets task failed: {}", e))?
+}
+
+/// Store the API key for `provider`, preferring the OS credential store.
+#[tauri::command]
+pub async fn set_api_key(app: AppHandle, provider: String, key: String) -> Result<(), String> {
+    validate_provider(&provider)?;
+    if key.trim().is_empty() {
+        return Err("API key must not be empty".to_string());
+    }
+    store(&app, provider, key).await
+}
+
+/// Look up the key for `provider` from Rust code, e.g. provider clients that
+/// make requests without the key ever reaching the webview.
+pub(crate) async fn load_api_key(
+    app: &AppHandle,
+    provider: &str,
+) -> Result<Option<String>, String> {
+    validate_provider(provider)?;
+    fetch(app, provider.to_string()).await
+}
+
+/// Return the stored API key for `provider`, or `None` if there is none.
+#[tauri::command]
+pub async fn get_api_key(app: AppHandle, provider: String) -> Result<Option<String>, String> {
+    load_api_key(&app, &provider).await
+}
+
+/// Remove the API key for `provider` from every store.
+#[tauri::command]
+pub async fn delete_api_key(app: AppHandle, provider: String) -> Result<(), String> {
+    validate_provider(&provider)?;
+    remove(&app, provider).await
+}
+
+async fn store(app: &AppHandle, name: String, key: String) -> Result<(), String> {
+    let files = file_store(app)?;
+
+    blocking(move || match keychain_set(&name, &key) {
+        // Drop any copy left in the fallback file from an earlier session
+        Ok(()) => files.delete(&name),
+        Err(e) => {
+            warn!("OS keychain unavailable, using encrypted file: {}", e);
+            files.set(&name, &key)
+        }
+    })
+    .await
+}
+
+async fn fetch(app: &AppHandle, name: String) -> Result<Option<String>, String> {
+    let files = file_store(app)?;
+
+    blocking(move || lookup(&files, &name)).await
+}
+
+fn lookup(files: &FileStore, name: &str) -> Result<Option<String>, String> {
+    match keychain_get(name) {
+        Ok(Some(key)) => Ok(Some(key)),
+        Ok(None) => files.get(name),
+        Err(e) => {
+            warn!("OS keychain unavailable, using encrypted file: {}", e);
+            files.get(name)
+        }
+    }
+}
+
+async fn remove(app: &AppHandle, name: String) -> Result<(), String> {
+    let files = file_store(app)?;
+
+    blocking(move || {
+        if let Err(e) = keychain_delete(&name) {
+            warn!("Failed to delete API key from OS keychain: {}", e);
+        }
+        files.delete(&name)
+    })
+    .await
+}
+
+// ============================================================================
+// Internal secrets
+// ============================================================================
+
+fn internal_name(name: &str) -> Result<String, String> {
+    let internal = format!("{}{}", INTERNAL_PREFIX, name);
+    if !is_valid_name(&internal) {
+        return Err(format!("Invalid secret name: {:?}", name));
+    }
+    Ok(internal)
+}
+
+/// Look up one of the app's own secrets.
+pub(crate) async fn load_secret(app: &AppHandle, name: &str) -> Result<Option<String>, String> {
+    fetch(app, internal_name(name)?).await
+}
+
+/// Blocking [`load_secret`] for callers without an [`AppHandle`], such as
+/// the CLI, with the fallback file under `data_dir`.
+pub(crate) fn load_secret_in(data_dir: &Path, name: &str) -> Result<Option<String>, String> {
+    lookup(
+        &FileStore::new(data_dir.to_path_buf()),
+        &internal_name(name)?,
+    )
+}
+
+pub(crate) async fn set_secret(app: &AppHandle, name: &str, secret: String) -> Result<(), String> {
+    if secret.is_empty() {
+        return Err("Secret must not be empty".to_string());
+    }
+    store(app, internal_name(name)?, secret).await
+}
+
+pub(crate) async fn delete_secret(app: &AppHandle, name: &str) -> Result<(), String> {
+    remove(app, internal_name(name)?).await
+}
+
+/// A random AES-256-GCM key for encrypting app data at rest, kept as the
+/// internal secret `name` and created the first time it is asked for.
+pub(crate) async fn load_or_create_data_key(
+    app: &AppHandle,
+    name: &str,
+) -> Result<Key<Aes256Gcm>, String> {
+    if let Some(encoded) = load_secret(app, name).await? {
+        return decode_data_key(name, &encoded);
+    }
+
+    let key = Aes256Gcm::generate_key(OsRng);
+    set_secret(app, name, BASE64.encode(key)).await?;
+    Ok(key)
+}
+
+/// The data key `name` if it has been created, looked up like
+/// [`load_secret_in`].
+pub(crate) fn load_data_key_in(
+    data_dir: &Path,
+    name: &str,
+) -> Result<Option<Key<Aes256Gcm>>, String> {
+    load_secret_in(data_dir, name)?
+        .map(|encoded| decode_data_key(name, &encoded))
+        .transpose()
+}
+
+fn decode_data_key(name: &str, encoded: &str) -> Result<Key<Aes256Gcm>, String> {
+    let bytes = BASE64
+        .decode(encoded)
+        .map_err(|e| format!("Failed to decode data key {}: {}", name, e))?;
+    if bytes.len() != 32 {
+        return Err(format!("Corrupt data key {}", name));
+    }
+    Ok(*Key::<Aes256Gcm>::from_slice(&bytes))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn provider_names_are_restricted() {
+        assert!(validate_provider("openai").is_ok());
+        assert!(validate_provider("my-provider_2.local").is_ok());
+        assert!(validate_provider("").is_err());
+        assert!(validate_provider("../etc").is_err());
+        assert!(validate_provider("a b").is_err());
+        assert!(validate_provider("internal.app-lock-passcode").is_err());
+    }
+
+    #[test]
+    fn file_store_round_trip() {
+        let tmp = TempDir::new().unwrap();
+        let store = FileStore::new(tmp.path().to_path_buf());
+
+        assert_eq!(store.get("openai").unwrap(), None);
+        store.set("openai", "sk-test").unwrap();
+        store.set("anthropic", "sk-ant").unwrap();
+        assert_eq!(store.get("openai").unwrap().as_deref(), Some("sk-test"));
+
+        // Key material never appears in plaintext on disk
+        let raw = std::fs::read_to_string(tmp.path().join(SECRETS_FILE)).unwrap();
+        assert!(!raw.contains("sk-test"));
+
+        store.delete("openai").unwrap();
+        assert_eq!(store.get("openai").unwrap(), None);
+        assert_eq!(store.get("anthropic").unwrap().as_deref(), Some("sk-ant"));
+
+        store.delete("anthropic").unwrap();
+        assert!(!tmp.path().join(SECRETS_FILE).exists());
+    }
+
+    #[test]
+    fn file_store_rejects_wrong_key() {
+        let tmp = TempDir::new().unwrap();
+        let store = FileStore::new(tmp.path().to_path_buf());
+        store.set("openai", "sk-test").unwrap();
+
+        std::fs::write(tmp.path().join(SECRETS_KEY_FILE), [7u8; 32]).unwrap();
+        assert!(store.get("openai").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn file_store_files_are_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp = TempDir::new().unwrap();
+        let store = FileStore::new(tmp.path().to_path_buf());
+        store.set("openai", "sk-test").unwrap();
+
+        for name in [SECRETS_FILE, SECRETS_KEY_FILE] {
+            let mode = std::fs::metadata(tmp.path().join(name))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600, "{} should be 0600", name);
+        }
+    }
+}
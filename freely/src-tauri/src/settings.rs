@@ -0,0 +1,264 @@
+//! App configuration stored in the `settings` table (migration 5).
+//!
+//! Preferences the frontend used to keep in localStorage live here under the
+//! same key names, so they survive a cleared webview and can be read from
+//! Rust. Reads fall back to the built-in default for each known key, and
+//! every write emits `setting-changed` so all windows stay in sync.
+//!
+//! Keys owned by a Rust module (shortcuts, compaction, embeddings, the
+//! active project) are validated by that module's own commands and can't be
+//! written through the generic ones.
+
+use once_cell::sync::Lazy;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Map, Value};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use tauri::{AppHandle, Emitter};
+
+const MAX_KEY_LEN: usize = 64;
+
+/// Written only through their owning module's commands.
+const RESERVED_KEYS: &[&str] = &[
+    crate::shortcuts::SHORTCUTS_SETTING_KEY,
+    crate::context::compactor::CONFIG_SETTING_KEY,
+    crate::embeddings::CONFIG_SETTING_KEY,
+    crate::db::projects::ACTIVE_PROJECT_KEY,
+    crate::logging::LOG_LEVEL_SETTING_KEY,
+    crate::window_state::STATE_SETTING_KEY,
+    crate::window_modes::CONTENT_PROTECTION_SETTING_KEY,
+    crate::audio::denoise::CONFIG_SETTING_KEY,
+    crate::net::client::PROXY_SETTING_KEY,
+    crate::net::client::TLS_SETTING_KEY,
+    crate::redaction::SETTINGS_KEY,
+    crate::tts::CONFIG_SETTING_KEY,
+    crate::stt::translate::CONFIG_SETTING_KEY,
+    crate::db::cipher::SETTING_KEY,
+    crate::lock::CONFIG_SETTING_KEY,
+    crate::retention::SETTING_KEY,
+    crate::sync::SETTING_KEY,
+    crate::plugins::SETTING_KEY,
+    crate::automations::SETTING_KEY,
+    crate::api_server::SETTING_KEY,
+    crate::integrations::obsidian::SETTING_KEY,
+    crate::integrations::calendar::SETTING_KEY,
+    crate::integrations::issues::SETTING_KEY,
+    crate::providers::cache::SETTING_KEY,
+];
+
+static DEFAULTS: Lazy<HashMap<&'static str, Value>> = Lazy::new(|| {
+    HashMap::from([
+        ("theme", json!("system")),
+        ("transparency", json!(10)),
+        (
+            "system_prompt",
+            json!("You are a helpful AI assistant. Be concise, accurate, and friendly in your responses"),
+        ),
+        (
+            "screenshot_config",
+            json!({
+                "mode": "manual",
+                "autoPrompt": "Analyze this screenshot and provide insights",
+                "enabled": false,
+            }),
+        ),
+        (
+            "response_settings",
+            json!({
+                "responseLength": "auto",
+                "language": "english",
+                "autoScroll": true,
+            }),
+        ),
+        ("supports_images", json!(true)),
+        ("backups", json!({ "enabled": true, "keep": 7 })),
+        ("push_to_talk", json!({ "minDurationMs": 300 })),
+        (
+            crate::clipboard::CONFIG_SETTING_KEY,
+            json!({ "enabled": false, "maxEntries": 50, "skipSecrets": true }),
+        ),
+        (crate::updater::AUTO_CHECK_SETTING_KEY, json!(true)),
+        ("trash", json!({ "retentionDays": 30 })),
+        (
+            crate::usage::BUDGET_SETTING_KEY,
+            json!({ "monthlyLimitUsd": null, "providers": {} }),
+        ),
+    ])
+});
+
+#[derive(Clone, Serialize)]
+struct SettingChanged<'a> {
+    key: &'a str,
+    /// The default (or null) after a reset.
+    value: Option<Value>,
+}
+
+fn validate_key(key: &str) -> Result<(), String> {
+    if key.is_empty()
+        || key.len() > MAX_KEY_LEN
+        || !key
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    {
+        return Err(format!(
+            "Invalid setting key {:?}: use lowercase letters, digits and '_'",
+            key
+        ));
+    }
+    Ok(())
+}
+
+pub(crate) fn check_writable(key: &str) -> Result<(), String> {
+    validate_key(key)?;
+    if is_reserved(key) {
+        return Err(format!("Setting {} has its own command", key));
+    }
+    Ok(())
+}
+
+/// Owned by a module and not written, exported or synced as a plain setting.
+pub(crate) fn is_reserved(key: &str) -> bool {
+    RESERVED_KEYS.contains(&key)
+}
+
+pub(crate) fn default_for(key: &str) -> Option<Value> {
+    DEFAULTS.get(key).cloned()
+}
+
+/// The stored value of `key`, or its default if it has never been written.
+pub(crate) async fn get_setting<T: DeserializeOwned>(
+    pool: &SqlitePool,
+    key: &str,
+) -> Result<Option<T>, String> {
+    if let Some(value) = crate::db::settings::get(pool, key).await? {
+        return Ok(Some(value));
+    }
+    default_for(key)
+        .map(|value| {
+            serde_json::from_value(value).map_err(|e| format!("Invalid setting {}: {}", key, e))
+        })
+        .transpose()
+}
+
+/// Store `value` under `key` and notify every window.
+pub(crate) async fn set_setting<T: Serialize + ?Sized>(
+    app: &AppHandle,
+    key: &str,
+    value: &T,
+) -> Result<(), String> {
+    let value = serde_json::to_value(value)
+        .map_err(|e| format!("Failed to serialize setting {}: {}", key, e))?;
+    let pool = crate::db::pool(app).await?;
+    crate::db::settings::set(&pool, key, &value).await?;
+    emit_changed(app, key, Some(value));
+    Ok(())
+}
+
+pub(crate) fn emit_changed(app: &AppHandle, key: &str, value: Option<Value>) {
+    if let Err(e) = app.emit("setting-changed", SettingChanged { key, value }) {
+        tracing::warn!("Failed to emit setting change: {}", e);
+    }
+}
+
+/// Defaults overlaid with everything stored, minus the reserved keys.
+async fn all_settings(pool: &SqlitePool) -> Result<Map<String, Value>, String> {
+    let mut settings: Map<String, Value> = DEFAULTS
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.clone()))
+        .collect();
+    for (key, json) in crate::db::settings::all(pool).await? {
+        if RESERVED_KEYS.contains(&key.as_str()) {
+            continue;
+        }
+        match serde_json::from_str(&json) {
+            Ok(value) => {
+                settings.insert(key, value);
+            }
+            Err(e) => tracing::warn!("Skipping invalid setting {}: {}", key, e),
+        }
+    }
+    Ok(settings)
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+#[tauri::command]
+pub async fn get_app_setting(app: AppHandle, key: String) -> Result<Option<Value>, String> {
+    validate_key(&key)?;
+    let pool = crate::db::pool(&app).await?;
+    get_setting(&pool, &key).await
+}
+
+/// Every app setting keyed by name, with defaults filled in.
+#[tauri::command]
+pub async fn get_app_settings(app: AppHandle) -> Result<Map<String, Value>, String> {
+    let pool = crate::db::pool(&app).await?;
+    all_settings(&pool).await
+}
+
+#[tauri::command]
+pub async fn set_app_setting(app: AppHandle, key: String, value: Value) -> Result<(), String> {
+    check_writable(&key)?;
+    set_setting(&app, &key, &value).await
+}
+
+/// Forget the stored value of `key` and return the default it reverts to.
+#[tauri::command]
+pub async fn reset_app_setting(app: AppHandle, key: String) -> Result<Option<Value>, String> {
+    check_writable(&key)?;
+    let pool = crate::db::pool(&app).await?;
+    crate::db::settings::delete(&pool, &key).await?;
+    let value = default_for(&key);
+    emit_changed(&app, &key, value.clone());
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn stored_values_override_defaults() {
+        let pool = crate::db::test_pool().await;
+        assert_eq!(
+            get_setting::<u32>(&pool, "transparency").await.unwrap(),
+            Some(10)
+        );
+        assert_eq!(get_setting::<String>(&pool, "unknown").await.unwrap(), None);
+
+        crate::db::settings::set(&pool, "transparency", &35)
+            .await
+            .unwrap();
+        crate::db::settings::set(&pool, "embeddings", &json!({}))
+            .await
+            .unwrap();
+        assert_eq!(
+            get_setting::<u32>(&pool, "transparency").await.unwrap(),
+            Some(35)
+        );
+
+        let all = all_settings(&pool).await.unwrap();
+        assert_eq!(all["transparency"], 35);
+        assert_eq!(all["theme"], "system");
+        assert!(!all.contains_key("embeddings"));
+
+        assert!(crate::db::settings::delete(&pool, "transparency")
+            .await
+            .unwrap());
+        assert_eq!(
+            get_setting::<u32>(&pool, "transparency").await.unwrap(),
+            Some(10)
+        );
+    }
+
+    #[test]
+    fn reserved_and_malformed_keys_are_rejected() {
+        assert!(check_writable("theme").is_ok());
+        assert!(check_writable("active_project_id").is_err());
+        assert!(check_writable("shortcuts").is_err());
+        assert!(check_writable("Theme").is_err());
+        assert!(check_writable("").is_err());
+    }
+}
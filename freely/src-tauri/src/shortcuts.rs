@@ -56,6 +56,41 @@ pub struct ShortcutsConfig {
     pub bindings: HashMap<String, ShortcutBinding>,
 }
 
+/// `settings` row holding the persisted [`ShortcutsConfig`].
+pub(crate) const SHORTCUTS_SETTING_KEY: &str = "shortcuts";
+
+/// Built-in actions and their default keys, mirroring `src/config/shortcuts.ts`.
+const DEFAULT_BINDINGS: &[(&str, &str)] = &[
+    ("toggle_dashboard", "CommandOrControl+Shift+D"),
+    ("toggle_window", "CommandOrControl+Backslash"),
+    ("focus_input", "CommandOrControl+Shift+I"),
+    ("move_window", "CommandOrControl"),
+    ("system_audio", "CommandOrControl+Shift+M"),
+    ("audio_recording", "CommandOrControl+Shift+A"),
+    ("screenshot", "CommandOrControl+Shift+S"),
+    ("push_to_talk", "CommandOrControl+Shift+Space"),
+    ("toggle_click_through", "CommandOrControl+Shift+X"),
+];
+
+impl Default for ShortcutsConfig {
+    fn default() -> Self {
+        let bindings = DEFAULT_BINDINGS
+            .iter()
+            .map(|(action, key)| {
+                (
+                    action.to_string(),
+                    ShortcutBinding {
+                        action: action.to_string(),
+                        key: key.to_string(),
+                        enabled: true,
+                    },
+                )
+            })
+            .collect();
+        ShortcutsConfig { bindings }
+    }
+}
+
 /// Initialize global shortcuts for the application
 pub fn setup_global_shortcuts<R: Runtime>(
     app: &AppHandle<R>,
@@ -65,11 +100,11 @@ pub fn setup_global_shortcuts<R: Runtime>(
     let _registered = match state.shortcuts.lock() {
         Ok(guard) => guard,
         Err(poisoned) => {
-            eprintln!("Mutex poisoned in setup, recovering...");
+            tracing::warn!("Mutex poisoned in setup, recovering...");
             poisoned.into_inner()
         }
     };
-    eprintln!("Global shortcuts state initialized, waiting for frontend config");
+    tracing::debug!("Global shortcuts state initialized, waiting for frontend config");
 
     Ok(())
 }
@@ -87,6 +122,8 @@ pub fn handle_shortcut_action<R: Runtime>(app: &AppHandle<R>, action_id: &str) {
         "audio_recording" => handle_audio_shortcut(app),
         "screenshot" => handle_screenshot_shortcut(app),
         "system_audio" => handle_system_audio_shortcut(app),
+        "push_to_talk" => handle_push_to_talk(app, true),
+        "toggle_click_through" => crate::window_modes::toggle_click_through(app),
         custom_action => {
             // Emit custom action event for frontend to handle
             if let Some(window) = app.get_webview_window("main") {
@@ -94,13 +131,20 @@ pub fn handle_shortcut_action<R: Runtime>(app: &AppHandle<R>, action_id: &str) {
                     "custom-shortcut-triggered",
                     json!({ "action": custom_action }),
                 ) {
-                    eprintln!("Failed to emit custom shortcut event: {}", e);
+                    tracing::warn!("Failed to emit custom shortcut event: {}", e);
                 }
             }
         }
     }
 }
 
+/// Handle key release for actions that care about it
+pub fn handle_shortcut_release<R: Runtime>(app: &AppHandle<R>, action_id: &str) {
+    if action_id == "push_to_talk" {
+        handle_push_to_talk(app, false);
+    }
+}
+
 pub fn start_move_window<R: Runtime>(app: &AppHandle<R>, direction: &str) {
     let state = app.state::<MoveWindowState>();
     let mut tasks = match state.tasks.lock() {
@@ -166,18 +210,18 @@ fn handle_toggle_window<R: Runtime>(app: &AppHandle<R>) {
         *is_hidden = !*is_hidden;
 
         if let Err(e) = window.emit("toggle-window-visibility", *is_hidden) {
-            eprintln!("Failed to emit toggle-window-visibility event: {}", e);
+            tracing::warn!("Failed to emit toggle-window-visibility event: {}", e);
         }
 
         if !*is_hidden {
             if let Err(e) = window.show() {
-                eprintln!("Failed to show window: {}", e);
+                tracing::warn!("Failed to show window: {}", e);
             }
             if let Err(e) = window.set_focus() {
-                eprintln!("Failed to focus window: {}", e);
+                tracing::warn!("Failed to focus window: {}", e);
             }
             if let Err(e) = window.emit("focus-text-input", json!({})) {
-                eprintln!("Failed to emit focus-text-input event: {}", e);
+                tracing::warn!("Failed to emit focus-text-input event: {}", e);
             }
         }
         return;
@@ -193,17 +237,17 @@ fn handle_toggle_window<R: Runtime>(app: &AppHandle<R>) {
             }
             // Window is visible, hide it and handle app icon based on user settings
             if let Err(e) = window.hide() {
-                eprintln!("Failed to hide window: {}", e);
+                tracing::warn!("Failed to hide window: {}", e);
             }
         }
         Ok(false) => {
             // Window is hidden, show it and handle app icon based on user settings
             if let Err(e) = window.show() {
-                eprintln!("Failed to show window: {}", e);
+                tracing::warn!("Failed to show window: {}", e);
             }
 
             if let Err(e) = window.set_focus() {
-                eprintln!("Failed to focus window: {}", e);
+                tracing::warn!("Failed to focus window: {}", e);
             }
 
             #[cfg(target_os = "macos")]
@@ -215,7 +259,7 @@ fn handle_toggle_window<R: Runtime>(app: &AppHandle<R>) {
             window.emit("focus-text-input", json!({})).unwrap();
         }
         Err(e) => {
-            eprintln!("Failed to check window visibility: {}", e);
+            tracing::warn!("Failed to check window visibility: {}", e);
         }
     }
 }
@@ -229,13 +273,13 @@ fn handle_audio_shortcut<R: Runtime>(app: &AppHandle<R>) {
                 return;
             }
             if let Err(e) = window.set_focus() {
-                eprintln!("Failed to focus window: {}", e);
+                tracing::warn!("Failed to focus window: {}", e);
             }
         }
 
         // Emit event to start audio recording
         if let Err(e) = window.emit("start-audio-recording", json!({})) {
-            eprintln!("Failed to emit audio recording event: {}", e);
+            tracing::warn!("Failed to emit audio recording event: {}", e);
         }
     }
 }
@@ -245,7 +289,7 @@ fn handle_screenshot_shortcut<R: Runtime>(app: &AppHandle<R>) {
     if let Some(window) = app.get_webview_window("main") {
         // Emit event to trigger screenshot - frontend will determine auto/manual mode
         if let Err(e) = window.emit("trigger-screenshot", json!({})) {
-            eprintln!("Failed to emit screenshot event: {}", e);
+            tracing::warn!("Failed to emit screenshot event: {}", e);
         }
     }
 }
@@ -256,17 +300,31 @@ fn handle_system_audio_shortcut<R: Runtime>(app: &AppHandle<R>) {
         // Ensure window is visible
         if let Ok(false) = window.is_visible() {
             if let Err(e) = window.show() {
-                eprintln!("Failed to show window: {}", e);
+                tracing::warn!("Failed to show window: {}", e);
                 return;
             }
             if let Err(e) = window.set_focus() {
-                eprintln!("Failed to focus window: {}", e);
+                tracing::warn!("Failed to focus window: {}", e);
             }
         }
 
         // Emit event to toggle system audio capture - frontend will determine current state
         if let Err(e) = window.emit("toggle-system-audio", json!({})) {
-            eprintln!("Failed to emit system audio event: {}", e);
+            tracing::warn!("Failed to emit system audio event: {}", e);
+        }
+    }
+}
+
+/// Handle push-to-talk: recording lasts for as long as the key is held
+fn handle_push_to_talk<R: Runtime>(app: &AppHandle<R>, pressed: bool) {
+    if let Some(window) = app.get_webview_window("main") {
+        let event = if pressed {
+            "push-to-talk-pressed"
+        } else {
+            "push-to-talk-released"
+        };
+        if let Err(e) = window.emit(event, json!({})) {
+            tracing::warn!("Failed to emit {} event: {}", event, e);
         }
     }
 }
@@ -280,7 +338,7 @@ pub fn get_registered_shortcuts<R: Runtime>(
     let registered = match state.shortcuts.lock() {
         Ok(guard) => guard,
         Err(poisoned) => {
-            eprintln!("Mutex poisoned in get_registered_shortcuts, recovering...");
+            tracing::warn!("Mutex poisoned in get_registered_shortcuts, recovering...");
             poisoned.into_inner()
         }
     };
@@ -293,62 +351,92 @@ pub fn update_shortcuts<R: Runtime>(
     app: AppHandle<R>,
     config: ShortcutsConfig,
 ) -> Result<(), String> {
-    eprintln!("Updating shortcuts with {} bindings", config.bindings.len());
+    tracing::debug!("Updating shortcuts with {} bindings", config.bindings.len());
+    apply_shortcuts(&app, &config)
+}
 
-    let mut shortcuts_to_register = Vec::new();
+/// Parse a binding into the concrete shortcuts it registers. `move_window`
+/// holds only modifiers and expands to one shortcut per arrow key.
+fn expand_binding(
+    action_id: &str,
+    binding: &ShortcutBinding,
+) -> Result<Vec<(String, String, Shortcut)>, String> {
+    if !binding.enabled || binding.key.trim().is_empty() {
+        return Ok(Vec::new());
+    }
 
-    for (action_id, binding) in &config.bindings {
-        if binding.enabled && !binding.key.is_empty() {
-            if action_id == "move_window" {
-                let modifiers = binding.key.trim();
-                if modifiers.is_empty() {
-                    continue;
-                }
+    if action_id == "move_window" {
+        let modifiers = binding.key.trim();
+        return ["up", "down", "left", "right"]
+            .iter()
+            .map(|arrow| {
+                let full_key = format!("{}+{}", modifiers, arrow);
+                full_key
+                    .parse::<Shortcut>()
+                    .map(|shortcut| (format!("move_window_{}", arrow), full_key.clone(), shortcut))
+                    .map_err(|e| format!("Invalid shortcut '{}' for move_window: {}", full_key, e))
+            })
+            .collect();
+    }
 
-                let arrow_keys = vec!["up", "down", "left", "right"];
-                for arrow in arrow_keys {
-                    let full_key = format!("{}+{}", modifiers, arrow);
-                    match full_key.parse::<Shortcut>() {
-                        Ok(shortcut) => {
-                            let direction_action_id = format!("move_window_{}", arrow);
-                            shortcuts_to_register.push((direction_action_id, full_key, shortcut));
-                        }
-                        Err(e) => {
-                            eprintln!("Invalid shortcut '{}' for move_window: {}", full_key, e);
-                            return Err(format!(
-                                "Invalid shortcut '{}' for move_window: {}",
-                                full_key, e
-                            ));
-                        }
-                    }
-                }
+    let shortcut = binding.key.parse::<Shortcut>().map_err(|e| {
+        format!(
+            "Invalid shortcut '{}' for action '{}': {}",
+            binding.key, action_id, e
+        )
+    })?;
+    Ok(vec![(action_id.to_string(), binding.key.clone(), shortcut)])
+}
 
-                continue;
-            }
+/// The action that already uses any shortcut `binding` would register.
+pub(crate) fn find_conflict(
+    bindings: &HashMap<String, ShortcutBinding>,
+    action_id: &str,
+    binding: &ShortcutBinding,
+) -> Result<Option<String>, String> {
+    let wanted = expand_binding(action_id, binding)?;
+    if wanted.is_empty() {
+        return Ok(None);
+    }
 
-            match binding.key.parse::<Shortcut>() {
-                Ok(shortcut) => {
-                    shortcuts_to_register.push((action_id.clone(), binding.key.clone(), shortcut));
-                }
-                Err(e) => {
-                    eprintln!(
-                        "Invalid shortcut '{}' for action '{}': {}",
-                        binding.key, action_id, e
-                    );
-                    return Err(format!(
-                        "Invalid shortcut '{}' for action '{}': {}",
-                        binding.key, action_id, e
-                    ));
-                }
+    for (other_id, other) in bindings {
+        if other_id == action_id {
+            continue;
+        }
+        // Stored bindings that no longer parse can't collide with anything
+        let Ok(taken) = expand_binding(other_id, other) else {
+            continue;
+        };
+        if taken
+            .iter()
+            .any(|(_, _, t)| wanted.iter().any(|(_, _, w)| w == t))
+        {
+            return Ok(Some(other_id.clone()));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Replace every registered global shortcut with those in `config`.
+fn apply_shortcuts<R: Runtime>(app: &AppHandle<R>, config: &ShortcutsConfig) -> Result<(), String> {
+    let mut shortcuts_to_register = Vec::new();
+
+    for (action_id, binding) in &config.bindings {
+        match expand_binding(action_id, binding) {
+            Ok(expanded) => shortcuts_to_register.extend(expanded),
+            Err(e) => {
+                tracing::warn!("{}", e);
+                return Err(e);
             }
         }
     }
 
     // First, stop any ongoing window movement
-    stop_all_move_windows(&app);
+    stop_all_move_windows(app);
 
     // Then, unregister all existing shortcuts
-    unregister_all_shortcuts(&app)?;
+    unregister_all_shortcuts(app)?;
 
     // Now register all new shortcuts
     let mut successfully_registered = HashMap::new();
@@ -358,11 +446,11 @@ pub fn update_shortcuts<R: Runtime>(
     for (action_id, shortcut_str, shortcut) in shortcuts_to_register {
         match app.global_shortcut().register(shortcut) {
             Ok(_) => {
-                eprintln!("Registered shortcut: {} -> {}", action_id, shortcut_str);
+                tracing::debug!("Registered shortcut: {} -> {}", action_id, shortcut_str);
                 successfully_registered.insert(action_id, shortcut_str);
             }
             Err(e) => {
-                eprintln!("Failed to register {} shortcut: {}", action_id, e);
+                tracing::warn!("Failed to register {} shortcut: {}", action_id, e);
                 registration_failures.push((action_id, shortcut_str, e.to_string()));
             }
         }
@@ -374,7 +462,7 @@ pub fn update_shortcuts<R: Runtime>(
         let mut registered = match state.shortcuts.lock() {
             Ok(guard) => guard,
             Err(poisoned) => {
-                eprintln!("Mutex poisoned in update_shortcuts, recovering...");
+                tracing::warn!("Mutex poisoned in update_shortcuts, recovering...");
                 poisoned.into_inner()
             }
         };
@@ -386,7 +474,7 @@ pub fn update_shortcuts<R: Runtime>(
     if !registration_failures.is_empty() {
         if let Some(window) = app.get_webview_window("main") {
             if let Err(e) = window.emit("shortcut-registration-error", &registration_failures) {
-                eprintln!("Failed to emit shortcut registration error event: {}", e);
+                tracing::warn!("Failed to emit shortcut registration error event: {}", e);
             }
         }
 
@@ -404,13 +492,110 @@ pub fn update_shortcuts<R: Runtime>(
     Ok(())
 }
 
+/// Defaults overlaid with whatever the user has saved.
+fn merge_with_defaults(stored: Option<ShortcutsConfig>) -> ShortcutsConfig {
+    let mut config = ShortcutsConfig::default();
+    if let Some(stored) = stored {
+        config.bindings.extend(stored.bindings);
+    }
+    config
+}
+
+async fn load_shortcuts(pool: &sqlx::SqlitePool) -> Result<ShortcutsConfig, String> {
+    let stored = crate::db::settings::get(pool, SHORTCUTS_SETTING_KEY).await?;
+    Ok(merge_with_defaults(stored))
+}
+
+/// Register the bindings saved by `set_shortcut`, if any. The SQL plugin may
+/// still be opening the database when `setup` runs, so this retries briefly.
+pub fn restore_persisted_shortcuts(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut pool = None;
+        for _ in 0..20 {
+            match crate::db::pool(&app).await {
+                Ok(p) => {
+                    pool = Some(p);
+                    break;
+                }
+                Err(_) => sleep(Duration::from_millis(250)).await,
+            }
+        }
+        let Some(pool) = pool else {
+            tracing::warn!("Database not available, skipping persisted shortcuts");
+            return;
+        };
+
+        match crate::db::settings::get::<ShortcutsConfig>(&pool, SHORTCUTS_SETTING_KEY).await {
+            Ok(Some(stored)) => {
+                if let Err(e) = apply_shortcuts(&app, &merge_with_defaults(Some(stored))) {
+                    tracing::warn!("Failed to restore shortcuts: {}", e);
+                }
+            }
+            // Nothing saved yet; the frontend registers its own config
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Failed to load shortcuts: {}", e),
+        }
+    });
+}
+
+/// Tauri command to get the persisted shortcut bindings, including defaults
+#[tauri::command]
+pub async fn get_shortcuts(app: AppHandle) -> Result<ShortcutsConfig, String> {
+    let pool = crate::db::pool(&app).await?;
+    load_shortcuts(&pool).await
+}
+
+/// Tauri command to change one binding. Rejects keys already used by another
+/// action, re-registers every shortcut and persists the result.
+#[tauri::command]
+pub async fn set_shortcut(
+    app: AppHandle,
+    action: String,
+    key: String,
+    enabled: Option<bool>,
+) -> Result<ShortcutsConfig, String> {
+    let action = action.trim().to_string();
+    if action.is_empty() {
+        return Err("Shortcut action must not be empty".to_string());
+    }
+
+    let pool = crate::db::pool(&app).await?;
+    let mut config = load_shortcuts(&pool).await?;
+
+    let binding = ShortcutBinding {
+        action: action.clone(),
+        key: key.trim().to_string(),
+        enabled: enabled.unwrap_or(true),
+    };
+    if let Some(other) = find_conflict(&config.bindings, &action, &binding)? {
+        return Err(format!(
+            "Shortcut '{}' is already used by '{}'",
+            binding.key, other
+        ));
+    }
+
+    let previous = config.clone();
+    config.bindings.insert(action, binding);
+
+    if let Err(e) = apply_shortcuts(&app, &config) {
+        // Don't leave the app without shortcuts because one key was refused
+        if let Err(restore_err) = apply_shortcuts(&app, &previous) {
+            tracing::warn!("Failed to restore previous shortcuts: {}", restore_err);
+        }
+        return Err(e);
+    }
+
+    crate::db::settings::set(&pool, SHORTCUTS_SETTING_KEY, &config).await?;
+    Ok(config)
+}
+
 /// Unregister all currently registered shortcuts
 fn unregister_all_shortcuts<R: Runtime>(app: &AppHandle<R>) -> Result<(), String> {
     let state = app.state::<RegisteredShortcuts>();
     let registered = match state.shortcuts.lock() {
         Ok(guard) => guard,
         Err(poisoned) => {
-            eprintln!("Mutex poisoned in unregister_all_shortcuts, recovering...");
+            tracing::warn!("Mutex poisoned in unregister_all_shortcuts, recovering...");
             poisoned.into_inner()
         }
     };
@@ -419,10 +604,10 @@ fn unregister_all_shortcuts<R: Runtime>(app: &AppHandle<R>) -> Result<(), String
         if let Ok(shortcut) = shortcut_str.parse::<Shortcut>() {
             match app.global_shortcut().unregister(shortcut) {
                 Ok(_) => {
-                    eprintln!("Unregistered shortcut: {} -> {}", action_id, shortcut_str);
+                    tracing::debug!("Unregistered shortcut: {} -> {}", action_id, shortcut_str);
                 }
                 Err(e) => {
-                    eprintln!("Failed to unregister shortcut {}: {}", shortcut_str, e);
+                    tracing::warn!("Failed to unregister shortcut {}: {}", shortcut_str, e);
                 }
             }
         }
@@ -438,7 +623,7 @@ pub fn check_shortcuts_registered<R: Runtime>(app: AppHandle<R>) -> Result<bool,
     let registered = match state.shortcuts.lock() {
         Ok(guard) => guard,
         Err(poisoned) => {
-            eprintln!("Mutex poisoned in check_shortcuts_registered, recovering...");
+            tracing::warn!("Mutex poisoned in check_shortcuts_registered, recovering...");
             poisoned.into_inner()
         }
     };
@@ -451,7 +636,7 @@ pub fn validate_shortcut_key(key: String) -> Result<bool, String> {
     match key.parse::<Shortcut>() {
         Ok(_) => Ok(true),
         Err(e) => {
-            eprintln!("Invalid shortcut '{}': {}", key, e);
+            tracing::warn!("Invalid shortcut '{}': {}", key, e);
             Ok(false)
         }
     }
@@ -470,7 +655,7 @@ pub fn set_app_icon_visibility<R: Runtime>(app: AppHandle<R>, visible: bool) ->
         };
 
         app.set_activation_policy(policy).map_err(|e| {
-            eprintln!("Failed to set activation policy: {}", e);
+            tracing::warn!("Failed to set activation policy: {}", e);
             format!("Failed to set activation policy: {}", e)
         })?;
     }
@@ -483,7 +668,7 @@ pub fn set_app_icon_visibility<R: Runtime>(app: AppHandle<R>, visible: bool) ->
                 .set_skip_taskbar(!visible)
                 .map_err(|e| format!("Failed to set taskbar visibility: {}", e))?;
         } else {
-            eprintln!("Main window not found on Windows");
+            tracing::warn!("Main window not found on Windows");
         }
     }
 
@@ -495,7 +680,7 @@ pub fn set_app_icon_visibility<R: Runtime>(app: AppHandle<R>, visible: bool) ->
                 .set_skip_taskbar(!visible)
                 .map_err(|e| format!("Failed to set panel visibility: {}", e))?;
         } else {
-            eprintln!("Main window not found on Linux");
+            tracing::warn!("Main window not found on Linux");
         }
     }
 
@@ -523,27 +708,27 @@ fn handle_toggle_dashboard<R: Runtime>(app: &AppHandle<R>) {
             Ok(true) => {
                 // Window is visible, hide it
                 if let Err(e) = dashboard_window.hide() {
-                    eprintln!("Failed to hide dashboard window: {}", e);
+                    tracing::warn!("Failed to hide dashboard window: {}", e);
                 }
             }
             Ok(false) => {
                 // Window is hidden, show and focus it
                 if let Err(e) = dashboard_window.show() {
-                    eprintln!("Failed to show dashboard window: {}", e);
+                    tracing::warn!("Failed to show dashboard window: {}", e);
                 }
                 if let Err(e) = dashboard_window.set_focus() {
-                    eprintln!("Failed to focus dashboard window: {}", e);
+                    tracing::warn!("Failed to focus dashboard window: {}", e);
                 }
             }
             Err(e) => {
-                eprintln!("Failed to check dashboard visibility: {}", e);
+                tracing::warn!("Failed to check dashboard visibility: {}", e);
             }
         }
     } else {
         // Window doesn't exist, create and show it
         match show_dashboard_window(app) {
-            Ok(_) => eprintln!("Dashboard window created and shown successfully"),
-            Err(e) => eprintln!("Failed to create/show dashboard window: {}", e),
+            Ok(_) => tracing::debug!("Dashboard window created and shown successfully"),
+            Err(e) => tracing::warn!("Failed to create/show dashboard window: {}", e),
         }
     }
 }
@@ -572,7 +757,7 @@ fn handle_move_window<R: Runtime>(app: &AppHandle<R>, direction: &str) {
                     "left" => (current_pos.x - step, current_pos.y),
                     "right" => (current_pos.x + step, current_pos.y),
                     _ => {
-                        eprintln!("Invalid direction: {}", direction);
+                        tracing::warn!("Invalid direction: {}", direction);
                         return;
                     }
                 };
@@ -583,15 +768,15 @@ fn handle_move_window<R: Runtime>(app: &AppHandle<R>, direction: &str) {
                         y: new_y,
                     }))
                 {
-                    eprintln!("Failed to set window position: {}", e);
+                    tracing::warn!("Failed to set window position: {}", e);
                 }
             }
             Err(e) => {
-                eprintln!("Failed to get window position: {}", e);
+                tracing::warn!("Failed to get window position: {}", e);
             }
         }
     } else {
-        eprintln!("Main window not found");
+        tracing::warn!("Main window not found");
     }
 }
 
@@ -600,3 +785,75 @@ fn handle_move_window<R: Runtime>(app: &AppHandle<R>, direction: &str) {
 pub fn exit_app(app_handle: tauri::AppHandle) {
     app_handle.exit(0);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binding(action: &str, key: &str, enabled: bool) -> ShortcutBinding {
+        ShortcutBinding {
+            action: action.to_string(),
+            key: key.to_string(),
+            enabled,
+        }
+    }
+
+    #[test]
+    fn conflict_reports_the_action_already_using_the_key() {
+        let config = ShortcutsConfig::default();
+
+        let taken = binding("custom", "CommandOrControl+Shift+S", true);
+        assert_eq!(
+            find_conflict(&config.bindings, "custom", &taken).unwrap(),
+            Some("screenshot".to_string())
+        );
+
+        // Rebinding an action to its own key is not a conflict
+        let same = binding("screenshot", "CommandOrControl+Shift+S", true);
+        assert_eq!(
+            find_conflict(&config.bindings, "screenshot", &same).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn conflict_checks_move_window_arrows_and_skips_disabled() {
+        let mut config = ShortcutsConfig::default();
+
+        let arrow = binding("custom", "CommandOrControl+Up", true);
+        assert_eq!(
+            find_conflict(&config.bindings, "custom", &arrow).unwrap(),
+            Some("move_window".to_string())
+        );
+
+        config.bindings.insert(
+            "move_window".into(),
+            binding("move_window", "CommandOrControl", false),
+        );
+        assert_eq!(
+            find_conflict(&config.bindings, "custom", &arrow).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn conflict_rejects_unparseable_keys() {
+        let config = ShortcutsConfig::default();
+        let bad = binding("custom", "Shift+NotAKey+", true);
+        assert!(find_conflict(&config.bindings, "custom", &bad).is_err());
+    }
+
+    #[test]
+    fn stored_bindings_override_defaults() {
+        let mut stored = ShortcutsConfig {
+            bindings: HashMap::new(),
+        };
+        stored
+            .bindings
+            .insert("screenshot".into(), binding("screenshot", "Alt+P", false));
+
+        let merged = merge_with_defaults(Some(stored));
+        assert!(!merged.bindings["screenshot"].enabled);
+        assert!(merged.bindings.contains_key("push_to_talk"));
+    }
+}
@@ -256,18 +256,18 @@ impl SpeakerInput {
                 source_name.as_deref(),
                 init_tx,
             ) {
-                eprintln!("Audio capture loop failed: {}", e);
+                tracing::warn!("Audio capture loop failed: {}", e);
             }
         }));
 
         let (sample_rate, init_success) = match init_rx.recv() {
             Ok(Ok(sr)) => (sr, true),
             Ok(Err(e)) => {
-                eprintln!("Audio initialization failed: {}", e);
+                tracing::warn!("Audio initialization failed: {}", e);
                 (DEFAULT_SAMPLE_RATE, false)
             }
             Err(e) => {
-                eprintln!("Failed to receive audio init signal: {}", e);
+                tracing::warn!("Failed to receive audio init signal: {}", e);
                 (DEFAULT_SAMPLE_RATE, false)
             }
         };
@@ -2,7 +2,7 @@ use std::path::PathBuf;
 use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
 use serde::{Serialize, Deserialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum WhisperModel {
     TinyEn,
     BaseEn,
@@ -10,6 +10,8 @@ pub enum WhisperModel {
 }
 
 impl WhisperModel {
+    pub const ALL: [WhisperModel; 3] = [Self::TinyEn, Self::BaseEn, Self::SmallEn];
+
     pub fn filename(&self) -> &str {
         match self {
             Self::TinyEn => "ggml-tiny.en.bin",
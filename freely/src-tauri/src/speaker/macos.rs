@@ -322,11 +322,11 @@ fn process_audio_data(ctx: &mut Ctx, data: &[f32]) {
 
         // Only terminate after many consecutive drops (prevents temporary spikes from killing stream)
         if consecutive == 25 {
-            eprintln!("Warning: Audio buffer experiencing drops - system may be overloaded");
+            tracing::warn!("Audio buffer experiencing drops - system may be overloaded");
         }
 
         if consecutive > 50 {
-            eprintln!("Critical: Audio buffer overflow - capture stopping");
+            tracing::error!("Audio buffer overflow - capture stopping");
             ctx.should_terminate.store(true, Ordering::Release);
             return;
         }
@@ -0,0 +1,376 @@
+//! Speaker diarization for system-audio transcripts.
+//!
+//! While running, the last few minutes of system audio are kept at 16 kHz.
+//! When a system-audio transcript is saved, the audio for its time range is
+//! reduced to a voice fingerprint: the average shape and spread of the
+//! log-energy across a bank of band-pass filters, with loudness factored
+//! out. Fingerprints are clustered per conversation; one close enough to an
+//! existing speaker joins it, anything else becomes a new speaker, up to
+//! `maxSpeakers`. The result is stored as `speaker_label` ("Speaker 1", ...),
+//! which the user can rename with `rename_transcript_speaker`.
+//!
+//! This is deliberately lightweight: it separates clearly different voices
+//! well, and anything it can't fingerprint (too short, too quiet, already
+//! out of the buffer) is saved without a label.
+
+use super::local::{resample_linear, WHISPER_SAMPLE_RATE};
+use crate::audio::{AudioSource, PcmBlock};
+use crate::db::transcripts::Transcript;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Deserialize;
+use std::collections::{HashMap, VecDeque};
+use std::f32::consts::PI;
+use tauri::{AppHandle, Manager};
+use tokio::sync::{broadcast, oneshot};
+use tracing::warn;
+
+const SAMPLE_RATE: u32 = WHISPER_SAMPLE_RATE;
+/// How much audio is kept for transcripts that arrive late.
+const HISTORY_MS: i64 = 3 * 60 * 1000;
+/// 20ms analysis frames.
+const FRAME_LEN: usize = SAMPLE_RATE as usize / 50;
+/// Band centres, roughly log-spaced across the speech range.
+const BAND_HZ: [f32; 12] = [
+    150.0, 220.0, 320.0, 460.0, 660.0, 950.0, 1350.0, 1900.0, 2700.0, 3800.0, 5200.0, 7000.0,
+];
+const BAND_Q: f32 = 2.0;
+/// Frames quieter than this are skipped as silence.
+const MIN_FRAME_RMS: f32 = 0.005;
+/// Half a second of speech is needed for a usable fingerprint.
+const MIN_VOICED_FRAMES: usize = 25;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DiarizationOptions {
+    /// Once reached, new voices join the closest existing speaker.
+    pub max_speakers: usize,
+    /// Largest fingerprint distance still treated as the same speaker.
+    pub threshold: f32,
+}
+
+impl Default for DiarizationOptions {
+    fn default() -> Self {
+        Self {
+            max_speakers: 6,
+            threshold: 1.2,
+        }
+    }
+}
+
+/// RBJ band-pass biquad (0 dB peak gain).
+struct BandPass {
+    b0: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    x1: f32,
+    x2: f32,
+    y1: f32,
+    y2: f32,
+}
+
+impl BandPass {
+    fn new(center_hz: f32, sample_rate: u32) -> Self {
+        let w0 = 2.0 * PI * center_hz / sample_rate as f32;
+        let alpha = w0.sin() / (2.0 * BAND_Q);
+        let a0 = 1.0 + alpha;
+        Self {
+            b0: alpha / a0,
+            b2: -alpha / a0,
+            a1: -2.0 * w0.cos() / a0,
+            a2: (1.0 - alpha) / a0,
+            x1: 0.0,
+            x2: 0.0,
+            y1: 0.0,
+            y2: 0.0,
+        }
+    }
+
+    fn process(&mut self, x: f32) -> f32 {
+        let y = self.b0 * x + self.b2 * self.x2 - self.a1 * self.y1 - self.a2 * self.y2;
+        self.x2 = self.x1;
+        self.x1 = x;
+        self.y2 = self.y1;
+        self.y1 = y;
+        y
+    }
+}
+
+/// Mean and standard deviation of each band's loudness-normalized log
+/// energy over the voiced frames of `samples` (mono, 16 kHz). `None` when
+/// there isn't enough speech.
+fn voice_fingerprint(samples: &[f32]) -> Option<Vec<f32>> {
+    let mut filters: Vec<BandPass> = BAND_HZ
+        .iter()
+        .map(|&hz| BandPass::new(hz, SAMPLE_RATE))
+        .collect();
+    let bands = BAND_HZ.len();
+    let mut sum = vec![0.0f32; bands];
+    let mut sum_sq = vec![0.0f32; bands];
+    let mut voiced = 0usize;
+    let mut energies = vec![0.0f32; bands];
+
+    for frame in samples.chunks_exact(FRAME_LEN) {
+        energies.iter_mut().for_each(|e| *e = 0.0);
+        for &x in frame {
+            for (filter, energy) in filters.iter_mut().zip(energies.iter_mut()) {
+                let y = filter.process(x);
+                *energy += y * y;
+            }
+        }
+        let rms = (frame.iter().map(|x| x * x).sum::<f32>() / FRAME_LEN as f32).sqrt();
+        if rms < MIN_FRAME_RMS {
+            continue;
+        }
+
+        let logs: Vec<f32> = energies.iter().map(|e| (e + 1e-9).ln()).collect();
+        // Subtracting the frame mean leaves the spectral shape, not the volume
+        let mean = logs.iter().sum::<f32>() / bands as f32;
+        for (band, log) in logs.iter().enumerate() {
+            let shaped = log - mean;
+            sum[band] += shaped;
+            sum_sq[band] += shaped * shaped;
+        }
+        voiced += 1;
+    }
+
+    if voiced < MIN_VOICED_FRAMES {
+        return None;
+    }
+    let n = voiced as f32;
+    let means = sum.iter().map(|s| s / n);
+    let stds = sum
+        .iter()
+        .zip(&sum_sq)
+        .map(|(s, sq)| (sq / n - (s / n).powi(2)).max(0.0).sqrt());
+    Some(means.chain(stds).collect())
+}
+
+/// Root-mean-square difference between two fingerprints.
+fn distance(a: &[f32], b: &[f32]) -> f32 {
+    let sum: f32 = a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum();
+    (sum / a.len().max(1) as f32).sqrt()
+}
+
+/// Online clustering of fingerprints into speakers.
+#[derive(Default)]
+struct Speakers {
+    /// Running mean fingerprint and utterance count per speaker.
+    centroids: Vec<(Vec<f32>, usize)>,
+}
+
+impl Speakers {
+    /// Index of the speaker `fingerprint` belongs to, adding one if needed.
+    fn assign(&mut self, fingerprint: Vec<f32>, options: &DiarizationOptions) -> usize {
+        let nearest = self
+            .centroids
+            .iter()
+            .enumerate()
+            .map(|(i, (centroid, _))| (i, distance(centroid, &fingerprint)))
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+
+        match nearest {
+            Some((i, d))
+                if d <= options.threshold
+                    || self.centroids.len() >= options.max_speakers.max(1) =>
+            {
+                let (centroid, count) = &mut self.centroids[i];
+                *count += 1;
+                let weight = 1.0 / *count as f32;
+                for (c, f) in centroid.iter_mut().zip(&fingerprint) {
+                    *c += (f - *c) * weight;
+                }
+                i
+            }
+            _ => {
+                self.centroids.push((fingerprint, 1));
+                self.centroids.len() - 1
+            }
+        }
+    }
+}
+
+struct Session {
+    cancel: oneshot::Sender<()>,
+    options: DiarizationOptions,
+    /// Recent system audio at [`SAMPLE_RATE`], oldest first.
+    history: VecDeque<PcmBlock>,
+    /// Keyed by conversation id ("" for none).
+    speakers: HashMap<String, Speakers>,
+}
+
+static SESSION: Lazy<Mutex<Option<Session>>> = Lazy::new(|| Mutex::new(None));
+
+/// Samples of `history` between `start_ms` and `end_ms`.
+fn extract(history: &VecDeque<PcmBlock>, start_ms: i64, end_ms: i64) -> Vec<f32> {
+    let mut samples = Vec::new();
+    for block in history {
+        let block_end =
+            block.timestamp_ms + block.samples.len() as i64 * 1000 / block.sample_rate as i64;
+        if block_end <= start_ms || block.timestamp_ms >= end_ms {
+            continue;
+        }
+        let offset = |ms: i64| {
+            (((ms - block.timestamp_ms).max(0) * block.sample_rate as i64 / 1000) as usize)
+                .min(block.samples.len())
+        };
+        samples.extend_from_slice(&block.samples[offset(start_ms)..offset(end_ms)]);
+    }
+    samples
+}
+
+fn push_block(block: PcmBlock) {
+    let block = PcmBlock {
+        samples: resample_linear(&block.samples, block.sample_rate, SAMPLE_RATE).into(),
+        sample_rate: SAMPLE_RATE,
+        ..block
+    };
+    let mut session = SESSION.lock();
+    let Some(session) = session.as_mut() else {
+        return;
+    };
+    let cutoff = block.timestamp_ms - HISTORY_MS;
+    session.history.push_back(block);
+    while session
+        .history
+        .front()
+        .is_some_and(|oldest| oldest.timestamp_ms < cutoff)
+    {
+        session.history.pop_front();
+    }
+}
+
+/// Fill in `speaker_label` for an unlabelled system-audio transcript when
+/// diarization is running and the audio is still buffered.
+pub(crate) fn label(mut transcript: Transcript) -> Transcript {
+    if transcript.source != AudioSource::SystemAudio || transcript.speaker_label.is_some() {
+        return transcript;
+    }
+    let mut session = SESSION.lock();
+    let Some(session) = session.as_mut() else {
+        return transcript;
+    };
+
+    let samples = extract(&session.history, transcript.started_at, transcript.ended_at);
+    let Some(fingerprint) = voice_fingerprint(&samples) else {
+        return transcript;
+    };
+    let speaker = session
+        .speakers
+        .entry(transcript.conversation_id.clone().unwrap_or_default())
+        .or_default()
+        .assign(fingerprint, &session.options);
+    transcript.speaker_label = Some(format!("Speaker {}", speaker + 1));
+    transcript
+}
+
+pub(crate) fn is_running() -> bool {
+    SESSION.lock().is_some()
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// Start labelling system-audio transcripts by speaker. Needs the system
+/// audio stream (`start_system_audio_stream`), which is started separately.
+#[tauri::command]
+pub fn start_diarization(
+    app: AppHandle,
+    options: Option<DiarizationOptions>,
+) -> Result<(), String> {
+    let mut session = SESSION.lock();
+    if session.is_some() {
+        return Err("Diarization already running".to_string());
+    }
+
+    let mut rx = app
+        .state::<crate::audio::loopback::LoopbackState>()
+        .subscribe();
+    let (cancel, mut cancel_rx) = oneshot::channel();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = &mut cancel_rx => break,
+                block = rx.recv() => match block {
+                    Ok(block) => push_block(block),
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Diarization fell behind, skipped {} audio blocks", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+            }
+        }
+    });
+
+    *session = Some(Session {
+        cancel,
+        options: options.unwrap_or_default(),
+        history: VecDeque::new(),
+        speakers: HashMap::new(),
+    });
+    Ok(())
+}
+
+/// Stop diarization and forget the buffered audio and known speakers.
+#[tauri::command]
+pub fn stop_diarization() -> Result<(), String> {
+    if let Some(session) = SESSION.lock().take() {
+        let _ = session.cancel.send(());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A harmonic "voice" with its energy around `fundamental`'s overtones.
+    fn voice(fundamental: f32, secs: f32) -> Vec<f32> {
+        let len = (SAMPLE_RATE as f32 * secs) as usize;
+        (0..len)
+            .map(|i| {
+                let t = i as f32 / SAMPLE_RATE as f32;
+                (1..=4)
+                    .map(|h| (2.0 * PI * fundamental * h as f32 * t).sin() * 0.1 / h as f32)
+                    .sum()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn different_voices_become_different_speakers() {
+        let options = DiarizationOptions::default();
+        let mut speakers = Speakers::default();
+        let low = voice(120.0, 1.0);
+        let high = voice(900.0, 1.0);
+
+        let a = speakers.assign(voice_fingerprint(&low).unwrap(), &options);
+        let b = speakers.assign(voice_fingerprint(&high).unwrap(), &options);
+        // Same voice, quieter
+        let quiet: Vec<f32> = low.iter().map(|x| x * 0.5).collect();
+        let a_again = speakers.assign(voice_fingerprint(&quiet).unwrap(), &options);
+
+        assert_ne!(a, b);
+        assert_eq!(a, a_again);
+        assert!(voice_fingerprint(&voice(120.0, 0.2)).is_none());
+        assert!(voice_fingerprint(&vec![0.0; SAMPLE_RATE as usize]).is_none());
+    }
+
+    #[test]
+    fn extract_cuts_across_blocks() {
+        let block = |timestamp_ms: i64, value: f32| PcmBlock {
+            source: AudioSource::SystemAudio,
+            sample_rate: 1000,
+            timestamp_ms,
+            samples: vec![value; 100].into(),
+        };
+        let history = VecDeque::from([block(0, 1.0), block(100, 2.0)]);
+
+        let samples = extract(&history, 50, 150);
+        assert_eq!(samples.len(), 100);
+        assert_eq!((samples[0], samples[99]), (1.0, 2.0));
+        assert!(extract(&history, 500, 600).is_empty());
+    }
+}
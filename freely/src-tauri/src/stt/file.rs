@@ -0,0 +1,486 @@
+//! Transcription of existing audio and video files.
+//!
+//! The file's first audio track is decoded with Symphonia (WAV, FLAC, MP3,
+//! AAC/M4A, Ogg Vorbis, and the audio of MP4/MKV videos), downmixed and
+//! resampled to 16 kHz, then transcribed by a local Whisper model or sent to
+//! Deepgram's prerecorded API with the key stored for `deepgram`. The
+//! result is saved as a new conversation: one message holding the
+//! timestamped transcript, plus a `transcripts` row per segment. Segment
+//! times are offsets from the conversation's `createdAt`.
+//!
+//! `transcribe_file` runs one file directly; `transcribe_files` queues a
+//! `transcribeFile` job per file, which reports progress and can be
+//! cancelled. Local transcription loads its own copy of the model, so it
+//! never holds up live transcription.
+
+use crate::audio::AudioSource;
+use crate::db::chat::{self, Conversation, Message, MessageRole};
+use crate::db::transcripts::{self, Transcript};
+use crate::jobs::{self, JobSpec};
+use crate::models::ModelKind;
+use crate::speaker::local_whisper::{WhisperEngine, WhisperModel};
+use crate::stt::local::{self as whisper, WHISPER_SAMPLE_RATE};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use tauri::AppHandle;
+
+const DEEPGRAM_URL: &str =
+    "https://api.deepgram.com/v1/listen?punctuate=true&smart_format=true&utterances=true";
+const CANCELLED: &str = "Transcription cancelled";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileTranscriptionEngine {
+    #[default]
+    Local,
+    Deepgram,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TranscribeFileOptions {
+    pub engine: FileTranscriptionEngine,
+    /// Local only; the largest downloaded model when omitted.
+    pub model: Option<WhisperModel>,
+    /// Deepgram only, e.g. "en" or "de"; Deepgram's default when omitted.
+    pub language: Option<String>,
+    /// Deepgram only: label segments by speaker.
+    pub diarize: bool,
+    /// Conversation title; the file name when omitted.
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileTranscription {
+    pub conversation_id: String,
+    pub segments: usize,
+    pub duration_ms: u64,
+}
+
+/// A stretch of transcribed speech, timed from the start of the file.
+#[derive(Debug, Clone, PartialEq)]
+struct Segment {
+    start_ms: u64,
+    end_ms: u64,
+    text: String,
+    confidence: Option<f64>,
+    speaker: Option<String>,
+}
+
+/// `hh:mm:ss` for an offset into a recording.
+pub(crate) fn format_offset(millis: u64) -> String {
+    let secs = millis / 1000;
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// The first audio track of `path` as 16 kHz mono samples.
+fn decode_file(path: &Path) -> Result<Vec<f32>, String> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(extension);
+    }
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|e| format!("Unsupported media file {}: {}", path.display(), e))?;
+    let mut format = probed.format;
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL && t.codec_params.sample_rate.is_some())
+        .ok_or_else(|| format!("{} has no audio track", path.display()))?;
+    let track_id = track.id;
+    let sample_rate = track
+        .codec_params
+        .sample_rate
+        .unwrap_or(WHISPER_SAMPLE_RATE);
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(|e| format!("Unsupported audio codec: {}", e))?;
+
+    let mut samples = Vec::new();
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                break
+            }
+            Err(SymphoniaError::ResetRequired) => break,
+            Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // A damaged frame costs a few milliseconds, not the file
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(e) => return Err(format!("Failed to decode {}: {}", path.display(), e)),
+        };
+        let spec = *decoded.spec();
+        let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        buffer.copy_interleaved_ref(decoded);
+        samples.extend(crate::audio::dsp::downmix_to_mono(
+            buffer.samples(),
+            spec.channels.count(),
+        ));
+    }
+    if samples.is_empty() {
+        return Err(format!("{} contains no audio", path.display()));
+    }
+    Ok(whisper::resample_linear(
+        &samples,
+        sample_rate,
+        WHISPER_SAMPLE_RATE,
+    ))
+}
+
+fn duration_ms(samples: &[f32]) -> u64 {
+    samples.len() as u64 * 1000 / WHISPER_SAMPLE_RATE as u64
+}
+
+async fn transcribe_local(
+    app: &AppHandle,
+    samples: Vec<f32>,
+    model: Option<WhisperModel>,
+    progress: impl Fn(f64) -> Result<(), String>,
+) -> Result<Vec<Segment>, String> {
+    let dir = crate::models::models_dir(app, ModelKind::Whisper)?;
+    let model = match model {
+        Some(model) => model,
+        None => whisper::largest_downloaded(&dir).ok_or("No Whisper model downloaded")?,
+    };
+    let model_path = dir.join(model.filename());
+    if !model_path.is_file() {
+        return Err(format!("Model {} is not downloaded", model.filename()));
+    }
+
+    let total_ms = duration_ms(&samples).max(1);
+    let stop = Arc::new(AtomicBool::new(false));
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let task = tokio::task::spawn_blocking({
+        let stop = stop.clone();
+        move || {
+            let mut engine = WhisperEngine::new();
+            engine.init(model_path)?;
+            whisper::transcribe_windows(&engine, &samples, |window| {
+                if stop.load(Ordering::Relaxed) {
+                    return Err(CANCELLED.to_string());
+                }
+                let _ = tx.send(Segment {
+                    start_ms: window.start_ms,
+                    end_ms: window.end_ms,
+                    text: window.text.clone(),
+                    confidence: None,
+                    speaker: None,
+                });
+                Ok(())
+            })
+        }
+    });
+
+    let mut segments = Vec::new();
+    let mut stopped = None;
+    // Ends once the blocking task drops its sender
+    while let Some(segment) = rx.recv().await {
+        if let Err(e) = progress(segment.end_ms as f64 / total_ms as f64) {
+            stop.store(true, Ordering::Relaxed);
+            stopped.get_or_insert(e);
+        }
+        if !segment.text.is_empty() {
+            segments.push(segment);
+        }
+    }
+    task.await
+        .map_err(|e| format!("Transcription task failed: {}", e))??;
+    match stopped {
+        Some(e) => Err(e),
+        None => Ok(segments),
+    }
+}
+
+fn encode_wav(samples: &[f32]) -> Result<Vec<u8>, String> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: WHISPER_SAMPLE_RATE,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    let mut writer = hound::WavWriter::new(&mut bytes, spec)
+        .map_err(|e| format!("Failed to encode audio: {}", e))?;
+    for sample in samples {
+        writer
+            .write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)
+            .map_err(|e| format!("Failed to encode audio: {}", e))?;
+    }
+    writer
+        .finalize()
+        .map_err(|e| format!("Failed to encode audio: {}", e))?;
+    Ok(bytes.into_inner())
+}
+
+/// `results.utterances` of a Deepgram prerecorded response.
+fn parse_deepgram(response: &Value) -> Vec<Segment> {
+    let millis = |utterance: &Value, key: &str| {
+        (utterance.get(key).and_then(Value::as_f64).unwrap_or(0.0) * 1000.0) as u64
+    };
+    response
+        .pointer("/results/utterances")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|utterance| {
+            let text = utterance.get("transcript")?.as_str()?.trim();
+            if text.is_empty() {
+                return None;
+            }
+            Some(Segment {
+                start_ms: millis(utterance, "start"),
+                end_ms: millis(utterance, "end"),
+                text: text.to_string(),
+                confidence: utterance
+                    .get("confidence")
+                    .and_then(Value::as_f64)
+                    .map(|c| c.clamp(0.0, 1.0)),
+                speaker: utterance
+                    .get("speaker")
+                    .and_then(Value::as_u64)
+                    .map(|speaker| format!("Speaker {}", speaker + 1)),
+            })
+        })
+        .collect()
+}
+
+async fn transcribe_deepgram(
+    app: &AppHandle,
+    samples: &[f32],
+    options: &TranscribeFileOptions,
+) -> Result<Vec<Segment>, String> {
+    crate::net::connectivity::require_online()?;
+    let api_key = crate::secrets::load_api_key(app, "deepgram")
+        .await?
+        .ok_or("No API key stored for deepgram")?;
+    let mut url = DEEPGRAM_URL.to_string();
+    if options.diarize {
+        url.push_str("&diarize=true");
+    }
+    let mut query = Vec::new();
+    if let Some(language) = &options.language {
+        query.push(("language", language.as_str()));
+    }
+
+    let response = crate::net::client::http_client(None)?
+        .post(&url)
+        .query(&query)
+        .header("Authorization", format!("Token {}", api_key))
+        .header("Content-Type", "audio/wav")
+        .body(encode_wav(samples)?)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach Deepgram: {}", e))?;
+    let status = response.status();
+    let body: Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Invalid Deepgram response: {}", e))?;
+    if !status.is_success() {
+        let message = body
+            .get("err_msg")
+            .and_then(Value::as_str)
+            .unwrap_or("request failed");
+        return Err(format!("Deepgram error ({}): {}", status, message));
+    }
+    Ok(parse_deepgram(&body))
+}
+
+/// The conversation message: one `[hh:mm:ss]` line per segment.
+fn render_transcript(file_name: &str, segments: &[Segment]) -> String {
+    let mut text = format!("Transcript of {}\n", file_name);
+    for segment in segments {
+        text.push_str(&format!("\n[{}] ", format_offset(segment.start_ms)));
+        if let Some(speaker) = &segment.speaker {
+            text.push_str(&format!("{}: ", speaker));
+        }
+        text.push_str(&segment.text);
+    }
+    text
+}
+
+async fn save(
+    pool: &SqlitePool,
+    conversation_id: &str,
+    path: &Path,
+    options: &TranscribeFileOptions,
+    segments: &[Segment],
+) -> Result<String, String> {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string());
+    let title = options
+        .title
+        .clone()
+        .filter(|title| !title.trim().is_empty())
+        .unwrap_or_else(|| file_name.clone());
+    let now = crate::db::now_millis();
+    let conversation = chat::create(
+        pool,
+        Conversation {
+            id: conversation_id.to_string(),
+            title,
+            created_at: now,
+            updated_at: now,
+            pinned: false,
+            archived_at: None,
+            deleted_at: None,
+            messages: vec![Message {
+                id: uuid::Uuid::new_v4().to_string(),
+                role: MessageRole::User,
+                content: render_transcript(&file_name, segments),
+                timestamp: now,
+                attached_files: None,
+                truncated: false,
+            }],
+        },
+    )
+    .await?;
+
+    for segment in segments {
+        transcripts::save(
+            pool,
+            Transcript {
+                id: String::new(),
+                conversation_id: Some(conversation.id.clone()),
+                source: AudioSource::Mixed,
+                text: segment.text.clone(),
+                started_at: now + segment.start_ms as i64,
+                ended_at: now + segment.end_ms.max(segment.start_ms) as i64,
+                confidence: segment.confidence,
+                speaker_label: segment.speaker.clone(),
+                translated_text: None,
+                translation_language: None,
+                created_at: 0,
+            },
+        )
+        .await?;
+    }
+    Ok(conversation.id)
+}
+
+/// Transcribe `path` and save it as conversation `conversation_id`.
+/// `progress` gets the fraction of the file done, where the engine reports
+/// it, and stops the transcription by returning `Err`. The `transcribeFile`
+/// job passes its own id, so a job that runs again finds its conversation.
+pub(crate) async fn transcribe(
+    app: &AppHandle,
+    pool: &SqlitePool,
+    conversation_id: &str,
+    path: &Path,
+    options: &TranscribeFileOptions,
+    progress: impl Fn(f64) -> Result<(), String>,
+) -> Result<FileTranscription, String> {
+    let decode_path = path.to_path_buf();
+    let samples = tokio::task::spawn_blocking(move || decode_file(&decode_path))
+        .await
+        .map_err(|e| format!("Decoding task failed: {}", e))??;
+    let duration_ms = duration_ms(&samples);
+
+    let segments = match options.engine {
+        FileTranscriptionEngine::Local => {
+            transcribe_local(app, samples, options.model, progress).await?
+        }
+        FileTranscriptionEngine::Deepgram => transcribe_deepgram(app, &samples, options).await?,
+    };
+    if segments.is_empty() {
+        return Err(format!("No speech found in {}", path.display()));
+    }
+    let conversation_id = save(pool, conversation_id, path, options, &segments).await?;
+    Ok(FileTranscription {
+        conversation_id,
+        segments: segments.len(),
+        duration_ms,
+    })
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// Transcribe one audio or video file into a new conversation.
+#[tauri::command]
+pub async fn transcribe_file(
+    app: AppHandle,
+    path: String,
+    options: Option<TranscribeFileOptions>,
+) -> Result<FileTranscription, String> {
+    let pool = crate::db::pool(&app).await?;
+    let options = options.unwrap_or_default();
+    let conversation_id = uuid::Uuid::new_v4().to_string();
+    let path = PathBuf::from(path);
+    transcribe(&app, &pool, &conversation_id, &path, &options, |_| Ok(())).await
+}
+
+/// Queue a `transcribeFile` job for each of `paths`.
+#[tauri::command]
+pub async fn transcribe_files(
+    app: AppHandle,
+    paths: Vec<String>,
+    options: Option<TranscribeFileOptions>,
+) -> Result<Vec<jobs::store::Job>, String> {
+    let options = options.unwrap_or_default();
+    let mut queued = Vec::with_capacity(paths.len());
+    for path in paths {
+        let spec = JobSpec::TranscribeFile {
+            path,
+            options: options.clone(),
+        };
+        queued.push(jobs::enqueue(&app, spec).await?);
+    }
+    Ok(queued)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn deepgram_utterances_become_segments() {
+        let response = json!({
+            "results": { "utterances": [
+                { "start": 0.5, "end": 2.25, "transcript": "Hello there.", "confidence": 0.93, "speaker": 0 },
+                { "start": 2.5, "end": 3.0, "transcript": " ", "speaker": 1 },
+                { "start": 61.0, "end": 63.5, "transcript": "Hi!", "speaker": 1 }
+            ]}
+        });
+        let segments = parse_deepgram(&response);
+        assert_eq!(segments.len(), 2);
+        assert_eq!((segments[0].start_ms, segments[0].end_ms), (500, 2250));
+        assert_eq!(segments[1].speaker.as_deref(), Some("Speaker 2"));
+
+        assert_eq!(
+            render_transcript("call.m4a", &segments),
+            "Transcript of call.m4a\n\n[00:00:00] Speaker 1: Hello there.\n[00:01:01] Speaker 2: Hi!"
+        );
+    }
+}
@@ -0,0 +1,343 @@
+//! Offline Whisper transcription.
+//!
+//! Manages ggml model files under `<app_local_data_dir>/models/whisper` and
+//! drives the `WhisperEngine` from `speaker::local_whisper`. Long recordings
+//! are transcribed in fixed windows so the frontend receives
+//! `stt-local-partial` events as each window finishes instead of waiting for
+//! the whole buffer.
+
+use crate::models::ModelKind;
+use crate::speaker::local_whisper::{WhisperEngine, WhisperModel};
+use base64::{engine::general_purpose::STANDARD as B64, Engine as _};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::warn;
+
+/// Sample rate Whisper models expect.
+pub const WHISPER_SAMPLE_RATE: u32 = 16_000;
+
+/// Window length for partial results; Whisper's native context is 30s.
+pub(crate) const WINDOW_SECS: usize = 30;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WhisperModelInfo {
+    pub model: WhisperModel,
+    pub filename: String,
+    pub downloaded: bool,
+    pub size_bytes: Option<u64>,
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadProgress {
+    pub model: WhisperModel,
+    pub downloaded_bytes: u64,
+    pub total_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PartialTranscript {
+    pub request_id: String,
+    pub index: usize,
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub text: String,
+}
+
+fn models_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    crate::models::models_dir(app, ModelKind::Whisper)
+}
+
+fn model_info(dir: &std::path::Path, model: WhisperModel) -> WhisperModelInfo {
+    let path = dir.join(model.filename());
+    let size_bytes = std::fs::metadata(&path).ok().map(|m| m.len());
+
+    WhisperModelInfo {
+        model,
+        filename: model.filename().to_string(),
+        downloaded: size_bytes.is_some(),
+        size_bytes,
+        path: path.to_string_lossy().into_owned(),
+    }
+}
+
+#[tauri::command]
+pub fn list_whisper_models(app: AppHandle) -> Result<Vec<WhisperModelInfo>, String> {
+    let dir = models_dir(&app)?;
+    Ok(WhisperModel::ALL
+        .iter()
+        .map(|&model| model_info(&dir, model))
+        .collect())
+}
+
+/// Download `model` from Hugging Face, emitting `whisper-model-download-progress`.
+/// The file is written to a `.part` path and only renamed once complete, so an
+/// interrupted download never looks like a usable model and picks up where it
+/// stopped next time.
+#[tauri::command]
+pub async fn download_whisper_model(
+    app: AppHandle,
+    model: WhisperModel,
+) -> Result<WhisperModelInfo, String> {
+    download_model(&app, model, |progress| {
+        if let Err(e) = app.emit("whisper-model-download-progress", progress) {
+            warn!("Failed to emit download progress: {}", e);
+        }
+        Ok(())
+    })
+    .await
+}
+
+/// Download `model` through the shared model downloader, so an interrupted
+/// download resumes. An error from `on_progress` stops the download.
+pub(crate) async fn download_model(
+    app: &AppHandle,
+    model: WhisperModel,
+    mut on_progress: impl FnMut(&DownloadProgress) -> Result<(), String>,
+) -> Result<WhisperModelInfo, String> {
+    crate::models::download(
+        app,
+        ModelKind::Whisper,
+        &model.download_url(),
+        model.filename(),
+        None,
+        |progress| {
+            on_progress(&DownloadProgress {
+                model,
+                downloaded_bytes: progress.downloaded_bytes,
+                total_bytes: progress.total_bytes,
+            })
+        },
+    )
+    .await?;
+    Ok(model_info(&models_dir(app)?, model))
+}
+
+/// Drop the shared engine if it has `path` loaded, so it doesn't keep using
+/// a deleted file.
+pub(crate) fn unload_if_loaded(app: &AppHandle, path: &Path) {
+    let state = app.state::<crate::WhisperState>();
+    let mut slot = state.engine.lock();
+    if slot
+        .as_ref()
+        .and_then(|engine| engine.status().model_path)
+        .is_some_and(|loaded| Path::new(&loaded) == path)
+    {
+        *slot = None;
+    }
+}
+
+#[tauri::command]
+pub fn delete_whisper_model(app: AppHandle, model: WhisperModel) -> Result<(), String> {
+    let path = models_dir(&app)?.join(model.filename());
+    unload_if_loaded(&app, &path);
+
+    match std::fs::remove_file(&path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("Failed to delete model: {}", e)),
+    }
+}
+
+/// Load a downloaded model into the shared engine.
+#[tauri::command]
+pub async fn load_whisper_model(app: AppHandle, model: WhisperModel) -> Result<(), String> {
+    let path = models_dir(&app)?.join(model.filename());
+    if !path.is_file() {
+        return Err(format!("Model {} is not downloaded", model.filename()));
+    }
+
+    // Loading a model reads hundreds of MB; keep it off the async executor
+    tokio::task::spawn_blocking(move || {
+        let mut engine = WhisperEngine::new();
+        engine.init(path)?;
+        *app.state::<crate::WhisperState>().engine.lock() = Some(engine);
+        Ok(())
+    })
+    .await
+    .map_err(|e| format!("Model loading task failed: {}", e))?
+}
+
+/// One [`WINDOW_SECS`] window of a longer transcription.
+pub(crate) struct TranscriptWindow {
+    pub index: usize,
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub text: String,
+}
+
+/// Decode a base64 WAV file into mono f32 samples at its native rate.
+pub(crate) fn decode_wav_b64(audio_b64: &str) -> Result<(Vec<f32>, u32), String> {
+    let wav_bytes = B64
+        .decode(audio_b64)
+        .map_err(|e| format!("Base64 decode error: {}", e))?;
+    decode_wav(&wav_bytes)
+}
+
+/// Decode WAV bytes into mono f32 samples at their native rate.
+pub(crate) fn decode_wav(wav_bytes: &[u8]) -> Result<(Vec<f32>, u32), String> {
+    let reader = hound::WavReader::new(std::io::Cursor::new(wav_bytes))
+        .map_err(|e| format!("WAV decode error: {}", e))?;
+    let spec = reader.spec();
+
+    let interleaved: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader
+            .into_samples::<f32>()
+            .filter_map(|s| s.ok())
+            .collect(),
+        hound::SampleFormat::Int => {
+            let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .into_samples::<i32>()
+                .filter_map(|s| s.ok())
+                .map(|s| s as f32 / scale)
+                .collect()
+        }
+    };
+
+    Ok((
+        crate::audio::downmix_to_mono(&interleaved, spec.channels as usize),
+        spec.sample_rate,
+    ))
+}
+
+/// Linear-interpolation resampler; adequate for speech going into Whisper.
+pub(crate) fn resample_linear(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    if from_rate == to_rate || samples.is_empty() {
+        return samples.to_vec();
+    }
+
+    let ratio = from_rate as f64 / to_rate as f64;
+    let out_len = (samples.len() as f64 / ratio).floor() as usize;
+
+    (0..out_len)
+        .map(|i| {
+            let pos = i as f64 * ratio;
+            let idx = pos as usize;
+            let frac = (pos - idx as f64) as f32;
+            let a = samples[idx];
+            let b = samples.get(idx + 1).copied().unwrap_or(a);
+            a + (b - a) * frac
+        })
+        .collect()
+}
+
+/// The largest model downloaded to `dir`, for when none is named.
+pub(crate) fn largest_downloaded(dir: &Path) -> Option<WhisperModel> {
+    WhisperModel::ALL
+        .into_iter()
+        .rev()
+        .find(|model| dir.join(model.filename()).is_file())
+}
+
+/// Transcribe 16 kHz `samples` window by window, handing each window to
+/// `on_window` as it finishes; an `Err` from it stops the transcription.
+/// Returns the whole transcript.
+pub(crate) fn transcribe_windows(
+    engine: &WhisperEngine,
+    samples: &[f32],
+    mut on_window: impl FnMut(&TranscriptWindow) -> Result<(), String>,
+) -> Result<String, String> {
+    let window = WHISPER_SAMPLE_RATE as usize * WINDOW_SECS;
+    let mut texts = Vec::new();
+
+    for (index, chunk) in samples.chunks(window).enumerate() {
+        let text = engine.transcribe(chunk, WHISPER_SAMPLE_RATE)?;
+        let start_ms = (index * window) as u64 * 1000 / WHISPER_SAMPLE_RATE as u64;
+        let transcript = TranscriptWindow {
+            index,
+            start_ms,
+            end_ms: start_ms + chunk.len() as u64 * 1000 / WHISPER_SAMPLE_RATE as u64,
+            text,
+        };
+        on_window(&transcript)?;
+        if !transcript.text.is_empty() {
+            texts.push(transcript.text);
+        }
+    }
+
+    Ok(texts.join(" "))
+}
+
+/// Transcribe a base64 WAV recording of any length with the loaded model.
+///
+/// Each 30s window emits a `stt-local-partial` event as soon as it is done;
+/// the full transcript is returned at the end.
+#[tauri::command]
+pub async fn transcribe_local_buffered(
+    app: AppHandle,
+    request_id: String,
+    audio_b64: String,
+) -> Result<String, String> {
+    let (samples, sample_rate) = decode_wav_b64(&audio_b64)?;
+    let samples = resample_linear(&samples, sample_rate, WHISPER_SAMPLE_RATE);
+
+    tokio::task::spawn_blocking(move || {
+        let state = app.state::<crate::WhisperState>();
+        let slot = state.engine.lock();
+        let engine = slot
+            .as_ref()
+            .ok_or("Whisper engine not initialized; load a model first")?;
+
+        transcribe_windows(engine, &samples, |window| {
+            let partial = PartialTranscript {
+                request_id: request_id.clone(),
+                index: window.index,
+                start_ms: window.start_ms,
+                end_ms: window.end_ms,
+                text: window.text.clone(),
+            };
+            if let Err(e) = app.emit("stt-local-partial", &partial) {
+                warn!("Failed to emit stt-local-partial: {}", e);
+            }
+            Ok(())
+        })
+    })
+    .await
+    .map_err(|e| format!("Transcription task failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resample_halves_length_for_double_rate() {
+        let samples: Vec<f32> = (0..32_000).map(|i| (i % 100) as f32 / 100.0).collect();
+        let out = resample_linear(&samples, 32_000, 16_000);
+        assert_eq!(out.len(), 16_000);
+        assert_eq!(out[1], samples[2]);
+    }
+
+    #[test]
+    fn resample_same_rate_is_identity() {
+        let samples = vec![0.1, 0.2, 0.3];
+        assert_eq!(resample_linear(&samples, 16_000, 16_000), samples);
+    }
+
+    #[test]
+    fn decode_wav_downmixes_stereo() {
+        let mut cursor = std::io::Cursor::new(Vec::new());
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 16_000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::new(&mut cursor, spec).unwrap();
+        for _ in 0..10 {
+            writer.write_sample(i16::MAX).unwrap();
+            writer.write_sample(0i16).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let (samples, rate) = decode_wav_b64(&B64.encode(cursor.into_inner())).unwrap();
+        assert_eq!(rate, 16_000);
+        assert_eq!(samples.len(), 10);
+        assert!((samples[0] - 0.5).abs() < 0.001);
+    }
+}
@@ -0,0 +1,9 @@
+//! Speech-to-text engines that run inside the app.
+
+pub mod diarization;
+pub mod file;
+pub mod local;
+pub mod push_to_talk;
+pub mod streaming;
+pub mod translate;
+pub mod vad;
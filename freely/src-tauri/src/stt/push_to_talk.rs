@@ -0,0 +1,254 @@
+//! Push-to-talk: hold the `push_to_talk` shortcut to record, release to
+//! transcribe.
+//!
+//! The shortcut handler emits `push-to-talk-pressed` and
+//! `push-to-talk-released`; this module listens for both. Pressing starts a
+//! microphone session (or taps the one already running, which is then left
+//! alone on release). Releasing stops it and transcribes what was captured
+//! with the loaded Whisper model, emitting `push-to-talk-transcript`. Holds
+//! shorter than `minDurationMs` in the `push_to_talk` setting are treated as
+//! accidental and only emit `push-to-talk-cancelled`.
+
+use super::local::{WHISPER_SAMPLE_RATE, WINDOW_SECS};
+use crate::audio::capture::{self, MicCaptureInfo, MicCaptureState};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Listener, Manager};
+use tokio::sync::{broadcast, oneshot};
+use tracing::warn;
+
+const CONFIG_SETTING_KEY: &str = "push_to_talk";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct PushToTalkConfig {
+    /// Holds shorter than this are discarded.
+    min_duration_ms: u64,
+    /// Microphone to record from; the default input when unset.
+    device_id: Option<String>,
+}
+
+impl Default for PushToTalkConfig {
+    fn default() -> Self {
+        Self {
+            min_duration_ms: 300,
+            device_id: None,
+        }
+    }
+}
+
+struct Recording {
+    started_at: i64,
+    /// Set when this recording started the microphone session itself.
+    start: Option<JoinHandle<Result<MicCaptureInfo, String>>>,
+    stop: oneshot::Sender<()>,
+    /// Resolves to the captured mono samples and their sample rate.
+    collected: JoinHandle<(Vec<f32>, u32)>,
+}
+
+static RECORDING: Lazy<Mutex<Option<Recording>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PushToTalkTranscript {
+    text: String,
+    duration_ms: i64,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PushToTalkCancelled {
+    duration_ms: i64,
+    min_duration_ms: u64,
+}
+
+async fn load_config(app: &AppHandle) -> PushToTalkConfig {
+    let pool = match crate::db::pool(app).await {
+        Ok(pool) => pool,
+        Err(_) => return PushToTalkConfig::default(),
+    };
+    match crate::settings::get_setting(&pool, CONFIG_SETTING_KEY).await {
+        Ok(config) => config.unwrap_or_default(),
+        Err(e) => {
+            warn!("{}", e);
+            PushToTalkConfig::default()
+        }
+    }
+}
+
+/// Collect microphone blocks until `stop` fires.
+async fn collect(
+    mut rx: broadcast::Receiver<crate::audio::PcmBlock>,
+    mut stop: oneshot::Receiver<()>,
+) -> (Vec<f32>, u32) {
+    let mut samples = Vec::new();
+    let mut sample_rate = WHISPER_SAMPLE_RATE;
+    loop {
+        tokio::select! {
+            _ = &mut stop => break,
+            block = rx.recv() => match block {
+                Ok(block) => {
+                    sample_rate = block.sample_rate;
+                    samples.extend_from_slice(&block.samples);
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!("Push-to-talk fell behind, skipped {} audio blocks", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+        }
+    }
+    (samples, sample_rate)
+}
+
+fn press(app: &AppHandle) {
+    let mut recording = RECORDING.lock();
+    // Held keys repeat on some platforms
+    if recording.is_some() {
+        return;
+    }
+
+    // Subscribe before starting so the first block isn't missed
+    let rx = app.state::<MicCaptureState>().subscribe();
+    let (stop, stop_rx) = oneshot::channel();
+    let collected = tauri::async_runtime::spawn(collect(rx, stop_rx));
+
+    let already_running = matches!(
+        capture::get_microphone_capture_status(app.clone()),
+        Ok(Some(_))
+    );
+    let start = (!already_running).then(|| {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let config = load_config(&app).await;
+            capture::start_microphone_capture(app, config.device_id, None).await
+        })
+    });
+
+    *recording = Some(Recording {
+        started_at: crate::db::now_millis(),
+        start,
+        stop,
+        collected,
+    });
+}
+
+async fn release(app: AppHandle) {
+    let Some(recording) = RECORDING.lock().take() else {
+        return;
+    };
+    let duration_ms = crate::db::now_millis() - recording.started_at;
+
+    if let Some(start) = recording.start {
+        match start.await {
+            Ok(Ok(_)) => {
+                if let Err(e) = capture::stop_microphone_capture(app.clone()).await {
+                    warn!("Failed to stop push-to-talk capture: {}", e);
+                }
+            }
+            Ok(Err(e)) => emit_error(&app, e),
+            Err(e) => emit_error(&app, format!("Failed to start capture: {}", e)),
+        }
+    }
+    let _ = recording.stop.send(());
+    let (samples, sample_rate) = recording.collected.await.unwrap_or_default();
+
+    let config = load_config(&app).await;
+    if duration_ms < config.min_duration_ms as i64 || samples.is_empty() {
+        let cancelled = PushToTalkCancelled {
+            duration_ms,
+            min_duration_ms: config.min_duration_ms,
+        };
+        if let Err(e) = app.emit("push-to-talk-cancelled", cancelled) {
+            warn!("Failed to emit push-to-talk-cancelled: {}", e);
+        }
+        return;
+    }
+
+    let samples = super::local::resample_linear(&samples, sample_rate, WHISPER_SAMPLE_RATE);
+    let transcribe_app = app.clone();
+    let text = tauri::async_runtime::spawn_blocking(move || {
+        let state = transcribe_app.state::<crate::WhisperState>();
+        let slot = state.engine.lock();
+        let engine = slot
+            .as_ref()
+            .ok_or("Whisper engine not initialized; load a model first")?;
+        let window = WHISPER_SAMPLE_RATE as usize * WINDOW_SECS;
+        let mut texts = Vec::new();
+        for chunk in samples.chunks(window) {
+            let text = engine.transcribe(chunk, WHISPER_SAMPLE_RATE)?;
+            if !text.is_empty() {
+                texts.push(text);
+            }
+        }
+        Ok::<_, String>(texts.join(" "))
+    })
+    .await
+    .map_err(|e| format!("Transcription task failed: {}", e))
+    .and_then(|result| result);
+
+    match text {
+        Ok(text) => {
+            let transcript = PushToTalkTranscript { text, duration_ms };
+            if let Err(e) = app.emit("push-to-talk-transcript", transcript) {
+                warn!("Failed to emit push-to-talk-transcript: {}", e);
+            }
+        }
+        Err(e) => emit_error(&app, e),
+    }
+}
+
+fn emit_error(app: &AppHandle, message: String) {
+    warn!("Push-to-talk failed: {}", message);
+    if let Err(e) = app.emit("push-to-talk-error", message) {
+        warn!("Failed to emit push-to-talk-error: {}", e);
+    }
+}
+
+/// Listen for the push-to-talk shortcut events. Called once from `setup`.
+pub fn start_push_to_talk(app: &AppHandle) {
+    let handle = app.clone();
+    app.listen_any("push-to-talk-pressed", move |_| press(&handle));
+    let handle = app.clone();
+    app.listen_any("push-to-talk-released", move |_| {
+        tauri::async_runtime::spawn(release(handle.clone()));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::{AudioSource, PcmBlock};
+
+    #[tokio::test]
+    async fn collect_keeps_blocks_until_stopped() {
+        let (tx, rx) = broadcast::channel(8);
+        let (stop, stop_rx) = oneshot::channel();
+        let collected = tokio::spawn(collect(rx, stop_rx));
+
+        for n in 0..3 {
+            let block = PcmBlock {
+                source: AudioSource::Microphone,
+                sample_rate: 48_000,
+                timestamp_ms: n,
+                samples: vec![n as f32; 2].into(),
+            };
+            tx.send(block).unwrap();
+        }
+        tokio::task::yield_now().await;
+        stop.send(()).unwrap();
+
+        let (samples, sample_rate) = collected.await.unwrap();
+        assert_eq!(samples, [0.0, 0.0, 1.0, 1.0, 2.0, 2.0]);
+        assert_eq!(sample_rate, 48_000);
+    }
+
+    #[test]
+    fn config_fills_in_missing_fields() {
+        let config: PushToTalkConfig = serde_json::from_str(r#"{"deviceId":"USB"}"#).unwrap();
+        assert_eq!(config.min_duration_ms, 300);
+        assert_eq!(config.device_id.as_deref(), Some("USB"));
+    }
+}
@@ -0,0 +1,436 @@
+//! Realtime speech-to-text over a provider websocket (Deepgram or
+//! AssemblyAI).
+//!
+//! A session subscribes to the microphone or system audio [`PcmBlock`]
+//! broadcast, resamples to 16 kHz and sends 16-bit PCM frames as they are
+//! captured, so text arrives while someone is still talking rather than
+//! after each chunk is uploaded. Provider messages become
+//! `stt-streaming-transcript` events; interim results have `isFinal: false`
+//! and are superseded by the next result for the same audio. The API key is
+//! read from the secrets store under the provider's name (`deepgram` or
+//! `assemblyai`) and never reaches the webview. Final results are also
+//! broadcast in-process for [`crate::meeting`].
+//!
+//! Stopping asks the provider to flush what it has, waits briefly for the
+//! last final results and then closes the socket.
+
+use crate::audio::{pcm_s16le, AudioSource, PcmBlock};
+use crate::stt::local::resample_linear;
+use futures_util::{SinkExt, StreamExt};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::{broadcast, oneshot};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::Message;
+use tracing::warn;
+
+const SAMPLE_RATE: u32 = 16_000;
+/// How long to wait for final results after asking the provider to flush.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamingProvider {
+    Deepgram,
+    AssemblyAi,
+}
+
+impl StreamingProvider {
+    /// Secrets store entry holding the API key.
+    fn key_name(self) -> &'static str {
+        match self {
+            Self::Deepgram => "deepgram",
+            Self::AssemblyAi => "assemblyai",
+        }
+    }
+
+    fn url(self, options: &StreamingOptions) -> String {
+        match self {
+            Self::Deepgram => {
+                let mut url = format!(
+                    "wss://api.deepgram.com/v1/listen?encoding=linear16&sample_rate={}&channels=1&interim_results=true&punctuate=true",
+                    SAMPLE_RATE
+                );
+                if let Some(language) = &options.language {
+                    url.push_str(&format!("&language={}", language));
+                }
+                if let Some(model) = &options.model {
+                    url.push_str(&format!("&model={}", model));
+                }
+                url
+            }
+            Self::AssemblyAi => {
+                let mut url = format!(
+                    "wss://streaming.assemblyai.com/v3/ws?sample_rate={}&encoding=pcm_s16le&format_turns=true",
+                    SAMPLE_RATE
+                );
+                if let Some(model) = &options.model {
+                    url.push_str(&format!("&speech_model={}", model));
+                }
+                url
+            }
+        }
+    }
+
+    fn authorization(self, api_key: &str) -> String {
+        match self {
+            Self::Deepgram => format!("Token {}", api_key),
+            Self::AssemblyAi => api_key.to_string(),
+        }
+    }
+
+    /// Asks the provider to finish the stream and send its last results.
+    fn close_message(self) -> &'static str {
+        match self {
+            Self::Deepgram => r#"{"type":"CloseStream"}"#,
+            Self::AssemblyAi => r#"{"type":"Terminate"}"#,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamingOptions {
+    pub provider: StreamingProvider,
+    #[serde(default = "default_source")]
+    pub source: AudioSource,
+    /// BCP-47 language code, where the provider supports choosing one.
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+fn default_source() -> AudioSource {
+    AudioSource::Microphone
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamingTranscript {
+    pub source: AudioSource,
+    pub provider: StreamingProvider,
+    pub text: String,
+    /// Interim results are replaced by later ones; final ones are not.
+    pub is_final: bool,
+    pub confidence: Option<f64>,
+    /// Capture time of the audio the text covers, in milliseconds, when the
+    /// provider reports word timing.
+    pub started_at: Option<i64>,
+    pub ended_at: Option<i64>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StreamingError {
+    source: AudioSource,
+    message: String,
+}
+
+struct Session {
+    id: u64,
+    stop: oneshot::Sender<()>,
+}
+
+static SESSIONS: Lazy<Mutex<HashMap<AudioSource, Session>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+/// Final results of every session.
+static FINALS: Lazy<broadcast::Sender<StreamingTranscript>> =
+    Lazy::new(|| broadcast::channel(256).0);
+
+/// What a provider message means for the transcript.
+#[derive(Debug, PartialEq)]
+enum ProviderEvent {
+    Transcript {
+        text: String,
+        is_final: bool,
+        confidence: Option<f64>,
+        /// Start and end of the audio, in milliseconds into the stream.
+        span_ms: Option<(i64, i64)>,
+    },
+    Error(String),
+    Ignored,
+}
+
+fn parse_message(provider: StreamingProvider, raw: &str) -> ProviderEvent {
+    let Ok(message) = serde_json::from_str::<Value>(raw) else {
+        return ProviderEvent::Ignored;
+    };
+    let kind = message["type"].as_str().unwrap_or_default();
+    match (provider, kind) {
+        (StreamingProvider::Deepgram, "Results") => {
+            let alternative = &message["channel"]["alternatives"][0];
+            ProviderEvent::Transcript {
+                text: alternative["transcript"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                is_final: message["is_final"].as_bool().unwrap_or(false),
+                confidence: alternative["confidence"].as_f64(),
+                span_ms: message["start"].as_f64().map(|start| {
+                    let end = start + message["duration"].as_f64().unwrap_or(0.0);
+                    ((start * 1000.0) as i64, (end * 1000.0) as i64)
+                }),
+            }
+        }
+        (StreamingProvider::AssemblyAi, "Turn") => {
+            let end_of_turn = message["end_of_turn"].as_bool().unwrap_or(false);
+            // With format_turns, each finished turn is sent again formatted;
+            // only that copy is final
+            let formatted = message["turn_is_formatted"].as_bool().unwrap_or(false);
+            ProviderEvent::Transcript {
+                text: message["transcript"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                is_final: end_of_turn && formatted,
+                confidence: message["end_of_turn_confidence"].as_f64(),
+                span_ms: message["words"].as_array().and_then(|words| {
+                    let start = words.first()?["start"].as_i64()?;
+                    let end = words.last()?["end"].as_i64()?;
+                    Some((start, end))
+                }),
+            }
+        }
+        (_, "Error") => ProviderEvent::Error(
+            message["description"]
+                .as_str()
+                .or(message["error"].as_str())
+                .unwrap_or("Unknown provider error")
+                .to_string(),
+        ),
+        _ => match message["error"].as_str() {
+            Some(error) => ProviderEvent::Error(error.to_string()),
+            None => ProviderEvent::Ignored,
+        },
+    }
+}
+
+/// `stream_start` is the capture time of the first block sent.
+fn handle_message(
+    app: &AppHandle,
+    options: &StreamingOptions,
+    stream_start: Option<i64>,
+    raw: &str,
+) {
+    match parse_message(options.provider, raw) {
+        ProviderEvent::Transcript {
+            text,
+            is_final,
+            confidence,
+            span_ms,
+        } => {
+            if text.trim().is_empty() {
+                return;
+            }
+            let span = stream_start.zip(span_ms);
+            let transcript = StreamingTranscript {
+                source: options.source,
+                provider: options.provider,
+                text,
+                is_final,
+                confidence,
+                started_at: span.map(|(base, (start, _))| base + start),
+                ended_at: span.map(|(base, (_, end))| base + end),
+            };
+            if let Err(e) = app.emit("stt-streaming-transcript", &transcript) {
+                warn!("Failed to emit stt-streaming-transcript: {}", e);
+            }
+            if is_final {
+                // No receivers is fine: only meetings listen
+                let _ = FINALS.send(transcript);
+            }
+        }
+        ProviderEvent::Error(message) => emit_error(app, options.source, message),
+        ProviderEvent::Ignored => {}
+    }
+}
+
+fn emit_error(app: &AppHandle, source: AudioSource, message: String) {
+    warn!("Streaming STT error: {}", message);
+    if let Err(e) = app.emit("stt-streaming-error", StreamingError { source, message }) {
+        warn!("Failed to emit stt-streaming-error: {}", e);
+    }
+}
+
+/// Final results of all sessions, as they arrive.
+pub(crate) fn subscribe_finals() -> broadcast::Receiver<StreamingTranscript> {
+    FINALS.subscribe()
+}
+
+fn to_frame(block: &PcmBlock) -> Message {
+    let samples = resample_linear(&block.samples, block.sample_rate, SAMPLE_RATE);
+    Message::Binary(pcm_s16le(&samples))
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// Connect to the provider and stream `source` to it until stopped. The
+/// capture itself is started separately.
+#[tauri::command]
+pub async fn start_streaming_stt(app: AppHandle, options: StreamingOptions) -> Result<(), String> {
+    if SESSIONS.lock().contains_key(&options.source) {
+        return Err("Streaming STT already running for this source".to_string());
+    }
+
+    let provider = options.provider;
+    let api_key = crate::secrets::load_api_key(&app, provider.key_name())
+        .await?
+        .ok_or_else(|| format!("No API key stored for {}", provider.key_name()))?;
+    let mut request = provider
+        .url(&options)
+        .into_client_request()
+        .map_err(|e| format!("Invalid streaming URL: {}", e))?;
+    let authorization = HeaderValue::from_str(&provider.authorization(&api_key))
+        .map_err(|_| "API key contains invalid characters".to_string())?;
+    request.headers_mut().insert("Authorization", authorization);
+
+    let (socket, _) = tokio_tungstenite::connect_async(request)
+        .await
+        .map_err(|e| format!("Failed to connect to {}: {}", provider.key_name(), e))?;
+    let (mut sink, mut stream) = socket.split();
+
+    let mut rx = crate::stt::vad::subscribe(&app, options.source);
+    let (stop, mut stop_rx) = oneshot::channel();
+    let id = NEXT_ID.fetch_add(1, Ordering::SeqCst);
+    {
+        let mut sessions = SESSIONS.lock();
+        if sessions.contains_key(&options.source) {
+            return Err("Streaming STT already running for this source".to_string());
+        }
+        sessions.insert(options.source, Session { id, stop });
+    }
+
+    tauri::async_runtime::spawn(async move {
+        let source = options.source;
+        let mut closed = false;
+        let mut stream_start = None;
+        loop {
+            tokio::select! {
+                _ = &mut stop_rx => break,
+                block = rx.recv() => match block {
+                    Ok(block) => {
+                        stream_start.get_or_insert(block.timestamp_ms);
+                        if let Err(e) = sink.send(to_frame(&block)).await {
+                            emit_error(&app, source, format!("Failed to send audio: {}", e));
+                            closed = true;
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Streaming STT fell behind, skipped {} audio blocks", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                message = stream.next() => match message {
+                    Some(Ok(Message::Text(raw))) => {
+                        handle_message(&app, &options, stream_start, &raw)
+                    }
+                    Some(Ok(Message::Close(_))) | None => {
+                        closed = true;
+                        break;
+                    }
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => {
+                        emit_error(&app, source, format!("Connection lost: {}", e));
+                        closed = true;
+                        break;
+                    }
+                },
+            }
+        }
+
+        if !closed {
+            let close = Message::Text(provider.close_message().to_string());
+            if sink.send(close).await.is_ok() {
+                // Final results for the audio already sent arrive before the close
+                let drain = async {
+                    while let Some(Ok(message)) = stream.next().await {
+                        match message {
+                            Message::Text(raw) => {
+                                handle_message(&app, &options, stream_start, &raw)
+                            }
+                            Message::Close(_) => break,
+                            _ => {}
+                        }
+                    }
+                };
+                let _ = tokio::time::timeout(DRAIN_TIMEOUT, drain).await;
+            }
+            let _ = sink.close().await;
+        }
+
+        {
+            let mut sessions = SESSIONS.lock();
+            if sessions.get(&source).is_some_and(|s| s.id == id) {
+                sessions.remove(&source);
+            }
+        }
+        if let Err(e) = app.emit("stt-streaming-closed", source) {
+            warn!("Failed to emit stt-streaming-closed: {}", e);
+        }
+    });
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn stop_streaming_stt(source: AudioSource) -> Result<(), String> {
+    if let Some(session) = SESSIONS.lock().remove(&source) {
+        let _ = session.stop.send(());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deepgram_results_are_parsed() {
+        let raw = r#"{"type":"Results","is_final":true,"start":1.5,"duration":2.25,"channel":{"alternatives":[{"transcript":"hello there","confidence":0.98}]}}"#;
+        assert_eq!(
+            parse_message(StreamingProvider::Deepgram, raw),
+            ProviderEvent::Transcript {
+                text: "hello there".into(),
+                is_final: true,
+                confidence: Some(0.98),
+                span_ms: Some((1500, 3750)),
+            }
+        );
+        assert_eq!(
+            parse_message(StreamingProvider::Deepgram, r#"{"type":"Metadata"}"#),
+            ProviderEvent::Ignored
+        );
+    }
+
+    #[test]
+    fn assemblyai_turns_are_final_once_formatted() {
+        let interim =
+            r#"{"type":"Turn","transcript":"hello","end_of_turn":false,"turn_is_formatted":false}"#;
+        let unformatted = r#"{"type":"Turn","transcript":"hello there","end_of_turn":true,"turn_is_formatted":false}"#;
+        let formatted = r#"{"type":"Turn","transcript":"Hello there.","end_of_turn":true,"turn_is_formatted":true,"end_of_turn_confidence":0.9}"#;
+
+        let is_final = |raw| match parse_message(StreamingProvider::AssemblyAi, raw) {
+            ProviderEvent::Transcript { is_final, .. } => is_final,
+            other => panic!("unexpected {:?}", other),
+        };
+        assert!(!is_final(interim));
+        assert!(!is_final(unformatted));
+        assert!(is_final(formatted));
+        assert_eq!(
+            parse_message(StreamingProvider::AssemblyAi, r#"{"error":"Unauthorized"}"#),
+            ProviderEvent::Error("Unauthorized".into())
+        );
+    }
+}
@@ -0,0 +1,302 @@
+//! Translation of final transcripts.
+//!
+//! When the `transcript_translation` setting is enabled, every transcript
+//! saved from a live source (`save_transcript` and meetings) is translated
+//! into `targetLanguage` in the background, by a configured chat model or
+//! by DeepL with the key stored as `deepl`. The translation is stored next
+//! to the original text and emitted as `transcript-translated`. Saving
+//! never waits for it, and a failed translation leaves the original alone.
+
+use crate::db::transcripts::{self, Transcript};
+use crate::providers::middleware::Retry;
+use crate::providers::{ChatMessage, ChatRole, CompletionRequest, ProviderKind};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Instant;
+use tauri::{AppHandle, Emitter};
+use tracing::warn;
+
+pub(crate) const CONFIG_SETTING_KEY: &str = "transcript_translation";
+const DEEPL_KEY_NAME: &str = "deepl";
+const TRANSLATION_MAX_TOKENS: u32 = 1024;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum TranslationEngine {
+    /// One of the chat providers.
+    #[serde(rename_all = "camelCase")]
+    Llm {
+        provider: ProviderKind,
+        model: String,
+        #[serde(default)]
+        base_url: Option<String>,
+        #[serde(default)]
+        api_key_name: Option<String>,
+        #[serde(default)]
+        profile_id: Option<String>,
+    },
+    #[serde(rename = "deepl")]
+    DeepL,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TranslationConfig {
+    pub enabled: bool,
+    /// Language to translate into: a name or code for a model ("German",
+    /// "pt-BR"), a DeepL code for DeepL ("DE", "EN-GB").
+    pub target_language: String,
+    pub engine: TranslationEngine,
+}
+
+fn translation_prompt(target_language: &str) -> String {
+    format!(
+        "Translate the user's message, a segment of a speech transcript, into {}. Reply with \
+the translation only, without quotes or notes. Keep names, numbers and technical terms. If \
+it is already in {}, repeat it unchanged.",
+        target_language, target_language
+    )
+}
+
+async fn translate_with_model(
+    app: &AppHandle,
+    engine: &TranslationEngine,
+    target_language: &str,
+    text: &str,
+) -> Result<String, String> {
+    let TranslationEngine::Llm {
+        provider,
+        model,
+        base_url,
+        api_key_name,
+        profile_id,
+    } = engine
+    else {
+        return Err("Not a model translation engine".to_string());
+    };
+    let request = CompletionRequest {
+        provider: *provider,
+        model: model.clone(),
+        messages: vec![ChatMessage {
+            role: ChatRole::User,
+            content: text.to_string(),
+            images: Vec::new(),
+        }],
+        system_prompt: Some(translation_prompt(target_language)),
+        temperature: Some(0.0),
+        max_tokens: Some(TRANSLATION_MAX_TOKENS),
+        base_url: base_url.clone(),
+        api_key_name: api_key_name.clone(),
+        retry: None,
+        profile_id: profile_id.clone(),
+        skip_cache: false,
+    };
+    if let Some(exceeded) = crate::usage::budget_exceeded(app, request.provider).await {
+        return Err(exceeded.to_string());
+    }
+    let client = match profile_id {
+        Some(profile_id) => {
+            crate::providers::profiles::connect(app, profile_id, Retry::default()).await?
+        }
+        None => {
+            crate::providers::connect(
+                app,
+                request.provider,
+                request.base_url.as_deref(),
+                request.api_key_name.as_deref(),
+                Retry::default(),
+            )
+            .await?
+        }
+    };
+    let started = Instant::now();
+    let output = client.stream_completion(&request, &|_| {}).await?;
+    crate::usage::record(
+        app,
+        "translation",
+        None,
+        &request,
+        &output,
+        started.elapsed(),
+    )
+    .await;
+    Ok(output.text.trim().to_string())
+}
+
+/// Free-plan keys end in `:fx` and have their own host.
+fn deepl_url(api_key: &str) -> &'static str {
+    if api_key.ends_with(":fx") {
+        "https://api-free.deepl.com/v2/translate"
+    } else {
+        "https://api.deepl.com/v2/translate"
+    }
+}
+
+fn parse_deepl(response: &Value) -> Option<String> {
+    response
+        .pointer("/translations/0/text")
+        .and_then(Value::as_str)
+        .map(|text| text.trim().to_string())
+}
+
+async fn translate_with_deepl(
+    app: &AppHandle,
+    target_language: &str,
+    text: &str,
+) -> Result<String, String> {
+    let api_key = crate::secrets::load_api_key(app, DEEPL_KEY_NAME)
+        .await?
+        .ok_or("No API key stored for deepl")?;
+    let body = serde_json::json!({
+        "text": [text],
+        "target_lang": target_language.to_uppercase(),
+    });
+    let response = crate::net::client::http_client(None)?
+        .post(deepl_url(&api_key))
+        .header("Authorization", format!("DeepL-Auth-Key {}", api_key))
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to reach DeepL: {}", e))?;
+    let status = response.status();
+    let body: Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Invalid DeepL response: {}", e))?;
+    if !status.is_success() {
+        let message = body
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or("request failed");
+        return Err(format!("DeepL error ({}): {}", status, message));
+    }
+    parse_deepl(&body).ok_or_else(|| "DeepL returned no translation".to_string())
+}
+
+async fn translate_text(
+    app: &AppHandle,
+    config: &TranslationConfig,
+    text: &str,
+) -> Result<String, String> {
+    crate::net::connectivity::require_online()?;
+    let translated = match &config.engine {
+        TranslationEngine::Llm { .. } => {
+            translate_with_model(app, &config.engine, &config.target_language, text).await?
+        }
+        TranslationEngine::DeepL => {
+            translate_with_deepl(app, &config.target_language, text).await?
+        }
+    };
+    if translated.is_empty() {
+        return Err("The translation came back empty".to_string());
+    }
+    Ok(translated)
+}
+
+async fn load_config(app: &AppHandle) -> Result<Option<TranslationConfig>, String> {
+    let pool = crate::db::pool(app).await?;
+    crate::db::settings::get(&pool, CONFIG_SETTING_KEY).await
+}
+
+async fn translate_saved(
+    app: &AppHandle,
+    config: &TranslationConfig,
+    mut transcript: Transcript,
+) -> Result<Transcript, String> {
+    let translated = translate_text(app, config, &transcript.text).await?;
+    let pool = crate::db::pool(app).await?;
+    transcripts::set_translation(&pool, &transcript.id, &translated, &config.target_language)
+        .await?;
+    transcript.translated_text = Some(translated);
+    transcript.translation_language = Some(config.target_language.clone());
+    if let Err(e) = app.emit("transcript-translated", &transcript) {
+        warn!("Failed to emit transcript-translated: {}", e);
+    }
+    Ok(transcript)
+}
+
+/// Translate a just-saved transcript if translation is on.
+pub(crate) fn translate_in_background(app: &AppHandle, transcript: &Transcript) {
+    let (app, transcript) = (app.clone(), transcript.clone());
+    tauri::async_runtime::spawn(async move {
+        let config = match load_config(&app).await {
+            Ok(Some(config)) if config.enabled => config,
+            Ok(_) => return,
+            Err(e) => {
+                warn!("{}", e);
+                return;
+            }
+        };
+        if let Err(e) = translate_saved(&app, &config, transcript).await {
+            warn!("Failed to translate transcript: {}", e);
+        }
+    });
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+#[tauri::command]
+pub async fn get_translation_config(app: AppHandle) -> Result<Option<TranslationConfig>, String> {
+    load_config(&app).await
+}
+
+#[tauri::command]
+pub async fn set_translation_config(
+    app: AppHandle,
+    config: TranslationConfig,
+) -> Result<(), String> {
+    if config.target_language.trim().is_empty() {
+        return Err("A target language is required".to_string());
+    }
+    if let TranslationEngine::Llm { model, .. } = &config.engine {
+        if model.trim().is_empty() {
+            return Err("A model is required for model translation".to_string());
+        }
+    }
+    let pool = crate::db::pool(&app).await?;
+    crate::db::settings::set(&pool, CONFIG_SETTING_KEY, &config).await
+}
+
+/// Translate saved transcript `id` now, with the configured engine and
+/// target even while automatic translation is off.
+#[tauri::command]
+pub async fn translate_transcript(app: AppHandle, id: String) -> Result<Transcript, String> {
+    let config = load_config(&app)
+        .await?
+        .ok_or("Transcript translation is not configured")?;
+    let pool = crate::db::pool(&app).await?;
+    let transcript = transcripts::get(&pool, &id)
+        .await?
+        .ok_or_else(|| format!("Transcript not found: {}", id))?;
+    translate_saved(&app, &config, transcript).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn deepl_keys_and_responses() {
+        assert_eq!(
+            deepl_url("abc:fx"),
+            "https://api-free.deepl.com/v2/translate"
+        );
+        assert_eq!(deepl_url("abc"), "https://api.deepl.com/v2/translate");
+        let response = json!({
+            "translations": [{ "detected_source_language": "EN", "text": " Guten Morgen. " }]
+        });
+        assert_eq!(parse_deepl(&response).as_deref(), Some("Guten Morgen."));
+        assert_eq!(parse_deepl(&json!({ "translations": [] })), None);
+
+        let config: TranslationConfig = serde_json::from_value(json!({
+            "enabled": true,
+            "targetLanguage": "DE",
+            "engine": { "type": "deepl" }
+        }))
+        .unwrap();
+        assert_eq!(config.engine, TranslationEngine::DeepL);
+    }
+}
@@ -0,0 +1,355 @@
+//! Voice activity detection on native capture streams.
+//!
+//! Subscribes to the microphone or system audio [`PcmBlock`] broadcast,
+//! resamples to 16kHz, classifies 30ms frames with WebRTC VAD and smooths the
+//! result with a small hysteresis gate. Transitions are emitted as
+//! `vad-speech-start` / `vad-speech-end` events carrying capture timestamps.
+//!
+//! `webrtc_vad::Vad` is not `Send`, so each session runs the detector on its
+//! own thread, fed by a tokio task that forwards blocks from the broadcast.
+
+use crate::audio::{AudioSource, PcmBlock};
+use crate::stt::local::resample_linear;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{mpsc, Mutex};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::{broadcast, oneshot};
+use tracing::warn;
+use webrtc_vad::{SampleRate, Vad, VadMode};
+
+const VAD_SAMPLE_RATE: u32 = 16_000;
+const FRAME_MS: u32 = 30;
+const FRAME_LEN: usize = (VAD_SAMPLE_RATE * FRAME_MS / 1000) as usize;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct VadOptions {
+    /// WebRTC aggressiveness, 0 (least) to 3 (most aggressive).
+    pub aggressiveness: u8,
+    /// Voiced audio required before `vad-speech-start` fires.
+    pub start_ms: u32,
+    /// Trailing silence required before `vad-speech-end` fires.
+    pub hangover_ms: u32,
+}
+
+impl Default for VadOptions {
+    fn default() -> Self {
+        Self {
+            aggressiveness: 2,
+            start_ms: 90,
+            hangover_ms: 600,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpeechEvent {
+    pub source: AudioSource,
+    /// Milliseconds since the Unix epoch, from the capture clock.
+    pub timestamp_ms: i64,
+    /// Length of the utterance; only set on `vad-speech-end`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Transition {
+    SpeechStart { at_ms: i64 },
+    SpeechEnd { at_ms: i64, started_ms: i64 },
+}
+
+/// Hysteresis over per-frame voice decisions.
+pub(crate) struct SpeechGate {
+    start_frames: u32,
+    hangover_frames: u32,
+    voiced_run: u32,
+    silent_run: u32,
+    /// Start of the current voiced run, used as the speech start timestamp.
+    run_start_ms: i64,
+    /// Start of the utterance while in speech.
+    speech_start_ms: Option<i64>,
+    /// End of the last voiced frame while in speech.
+    last_voiced_end_ms: i64,
+}
+
+impl SpeechGate {
+    pub(crate) fn new(options: &VadOptions) -> Self {
+        Self {
+            start_frames: options.start_ms.div_ceil(FRAME_MS).max(1),
+            hangover_frames: options.hangover_ms.div_ceil(FRAME_MS).max(1),
+            voiced_run: 0,
+            silent_run: 0,
+            run_start_ms: 0,
+            speech_start_ms: None,
+            last_voiced_end_ms: 0,
+        }
+    }
+
+    pub(crate) fn push(&mut self, voiced: bool, frame_start_ms: i64) -> Option<Transition> {
+        let frame_end_ms = frame_start_ms + FRAME_MS as i64;
+
+        if voiced {
+            if self.voiced_run == 0 {
+                self.run_start_ms = frame_start_ms;
+            }
+            self.voiced_run += 1;
+            self.silent_run = 0;
+            self.last_voiced_end_ms = frame_end_ms;
+
+            if self.speech_start_ms.is_none() && self.voiced_run >= self.start_frames {
+                self.speech_start_ms = Some(self.run_start_ms);
+                return Some(Transition::SpeechStart {
+                    at_ms: self.run_start_ms,
+                });
+            }
+        } else {
+            self.voiced_run = 0;
+            self.silent_run += 1;
+
+            if self.silent_run >= self.hangover_frames {
+                return self.finish();
+            }
+        }
+        None
+    }
+
+    /// End an in-progress utterance, e.g. when the stream stops.
+    pub(crate) fn finish(&mut self) -> Option<Transition> {
+        let started_ms = self.speech_start_ms.take()?;
+        Some(Transition::SpeechEnd {
+            at_ms: self.last_voiced_end_ms,
+            started_ms,
+        })
+    }
+}
+
+struct VadSession {
+    cancel: oneshot::Sender<()>,
+}
+
+/// Active VAD sessions, at most one per audio source.
+#[derive(Default)]
+pub struct VadState {
+    sessions: Mutex<HashMap<AudioSource, VadSession>>,
+}
+
+fn vad_mode(aggressiveness: u8) -> VadMode {
+    match aggressiveness {
+        0 => VadMode::Quality,
+        1 => VadMode::LowBitrate,
+        2 => VadMode::Aggressive,
+        _ => VadMode::VeryAggressive,
+    }
+}
+
+fn emit_transition(app: &AppHandle, source: AudioSource, transition: Transition) {
+    let (name, event) = match transition {
+        Transition::SpeechStart { at_ms } => (
+            "vad-speech-start",
+            SpeechEvent {
+                source,
+                timestamp_ms: at_ms,
+                duration_ms: None,
+            },
+        ),
+        Transition::SpeechEnd { at_ms, started_ms } => (
+            "vad-speech-end",
+            SpeechEvent {
+                source,
+                timestamp_ms: at_ms,
+                duration_ms: Some(at_ms - started_ms),
+            },
+        ),
+    };
+    if let Err(e) = app.emit(name, &event) {
+        warn!("Failed to emit {}: {}", name, e);
+    }
+}
+
+/// Detector loop; returns once the forwarding task drops its sender.
+fn run_detector(
+    app: AppHandle,
+    source: AudioSource,
+    options: VadOptions,
+    blocks: mpsc::Receiver<PcmBlock>,
+) {
+    let mut vad =
+        Vad::new_with_rate_and_mode(SampleRate::Rate16kHz, vad_mode(options.aggressiveness));
+    let mut gate = SpeechGate::new(&options);
+    let mut pending: Vec<i16> = Vec::with_capacity(FRAME_LEN * 2);
+    // Capture time of pending[0]
+    let mut pending_start_ms: Option<i64> = None;
+
+    while let Ok(block) = blocks.recv() {
+        let resampled = resample_linear(&block.samples, block.sample_rate, VAD_SAMPLE_RATE);
+        if pending.is_empty() {
+            pending_start_ms = Some(block.timestamp_ms);
+        }
+        pending.extend(
+            resampled
+                .iter()
+                .map(|&s| (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16),
+        );
+
+        let mut offset = 0;
+        while pending.len() - offset >= FRAME_LEN {
+            let frame = &pending[offset..offset + FRAME_LEN];
+            let frame_start_ms = pending_start_ms.unwrap_or(block.timestamp_ms)
+                + (offset as i64 * 1000 / VAD_SAMPLE_RATE as i64);
+            let voiced = vad.is_voice_segment(frame).unwrap_or(false);
+            if let Some(transition) = gate.push(voiced, frame_start_ms) {
+                emit_transition(&app, source, transition);
+            }
+            offset += FRAME_LEN;
+        }
+
+        if offset > 0 {
+            let consumed_ms = offset as i64 * 1000 / VAD_SAMPLE_RATE as i64;
+            pending.drain(..offset);
+            pending_start_ms = pending_start_ms.map(|start| start + consumed_ms);
+        }
+    }
+
+    if let Some(transition) = gate.finish() {
+        emit_transition(&app, source, transition);
+    }
+}
+
+pub(crate) fn subscribe(app: &AppHandle, source: AudioSource) -> broadcast::Receiver<PcmBlock> {
+    match source {
+        AudioSource::Microphone => app
+            .state::<crate::audio::capture::MicCaptureState>()
+            .subscribe(),
+        AudioSource::SystemAudio => app
+            .state::<crate::audio::loopback::LoopbackState>()
+            .subscribe(),
+        AudioSource::Mixed => app.state::<crate::audio::dsp::MixState>().subscribe(),
+    }
+}
+
+/// Start VAD on a native capture source. The capture itself is started
+/// separately; VAD keeps listening across capture restarts until stopped.
+#[tauri::command]
+pub fn start_vad(
+    app: AppHandle,
+    source: AudioSource,
+    options: Option<VadOptions>,
+) -> Result<(), String> {
+    let state = app.state::<VadState>();
+    let mut sessions = state
+        .sessions
+        .lock()
+        .map_err(|e| format!("Failed to acquire lock: {}", e))?;
+    if sessions.contains_key(&source) {
+        return Err("VAD already running for this source".to_string());
+    }
+
+    let options = options.unwrap_or_default();
+    let mut rx = subscribe(&app, source);
+    let (block_tx, block_rx) = mpsc::channel::<PcmBlock>();
+    let (cancel_tx, mut cancel_rx) = oneshot::channel();
+
+    let detector_app = app.clone();
+    std::thread::Builder::new()
+        .name("vad".to_string())
+        .spawn(move || run_detector(detector_app, source, options, block_rx))
+        .map_err(|e| format!("Failed to spawn VAD thread: {}", e))?;
+
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = &mut cancel_rx => break,
+                block = rx.recv() => match block {
+                    Ok(block) => {
+                        if block_tx.send(block).is_err() {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("VAD fell behind, skipped {} audio blocks", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+            }
+        }
+        // Dropping block_tx stops the detector thread
+    });
+
+    sessions.insert(source, VadSession { cancel: cancel_tx });
+    Ok(())
+}
+
+#[tauri::command]
+pub fn stop_vad(app: AppHandle, source: AudioSource) -> Result<(), String> {
+    let session = app
+        .state::<VadState>()
+        .sessions
+        .lock()
+        .map_err(|e| format!("Failed to acquire lock: {}", e))?
+        .remove(&source);
+
+    if let Some(session) = session {
+        let _ = session.cancel.send(());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gate() -> SpeechGate {
+        SpeechGate::new(&VadOptions {
+            aggressiveness: 2,
+            start_ms: 90,
+            hangover_ms: 300,
+        })
+    }
+
+    fn feed(gate: &mut SpeechGate, pattern: &str) -> Vec<Transition> {
+        pattern
+            .chars()
+            .enumerate()
+            .filter_map(|(i, c)| gate.push(c == '#', i as i64 * FRAME_MS as i64))
+            .collect()
+    }
+
+    #[test]
+    fn short_blips_do_not_start_speech() {
+        let mut gate = gate();
+        assert!(feed(&mut gate, "#.#..##......").is_empty());
+    }
+
+    #[test]
+    fn speech_start_uses_first_voiced_frame_and_end_waits_for_hangover() {
+        let mut gate = gate();
+        let transitions = feed(&mut gate, "..####.##...........");
+        assert_eq!(
+            transitions,
+            vec![
+                Transition::SpeechStart { at_ms: 60 },
+                // Last voiced frame is index 8 → ends at 270ms
+                Transition::SpeechEnd {
+                    at_ms: 270,
+                    started_ms: 60
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn finish_closes_open_utterance() {
+        let mut gate = gate();
+        feed(&mut gate, "#####");
+        assert_eq!(
+            gate.finish(),
+            Some(Transition::SpeechEnd {
+                at_ms: 150,
+                started_ms: 0
+            })
+        );
+        assert_eq!(gate.finish(), None);
+    }
+}
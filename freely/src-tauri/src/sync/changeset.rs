@@ -0,0 +1,247 @@
+//! Change set files in the sync folder.
+//!
+//! Each device writes only to its own directory,
+//! `<folder>/freely-sync/<device id>/`, one numbered JSON file per sync, so
+//! a file syncing service never has two devices writing the same file.
+//! Files are written under a temporary name and renamed into place.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Change sets with a newer format are left for a newer Freely to read.
+pub(crate) const FORMAT_VERSION: u32 = 1;
+/// Directory the change sets live under, in the folder or on a remote.
+pub(crate) const ROOT_DIR: &str = "freely-sync";
+const EXTENSION: &str = "json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordKind {
+    Conversation,
+    Prompt,
+    Setting,
+}
+
+impl RecordKind {
+    pub(crate) const ALL: [RecordKind; 3] = [Self::Conversation, Self::Prompt, Self::Setting];
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Conversation => "conversation",
+            Self::Prompt => "prompt",
+            Self::Setting => "setting",
+        }
+    }
+}
+
+/// One version of a conversation, prompt or setting, or its deletion.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Record {
+    pub kind: RecordKind,
+    /// Conversation id, prompt `sync_id` or setting key.
+    pub id: String,
+    pub updated_at: i64,
+    #[serde(default)]
+    pub deleted: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeSet {
+    pub version: u32,
+    pub device_id: String,
+    pub device_name: String,
+    pub seq: i64,
+    pub created_at: i64,
+    pub records: Vec<Record>,
+}
+
+fn root_dir(folder: &Path) -> PathBuf {
+    folder.join(ROOT_DIR)
+}
+
+/// Device ids are generated UUIDs; anything else in the folder is ignored.
+fn is_device_id(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+fn parse_seq(path: &Path) -> Option<i64> {
+    if path.extension()? != EXTENSION {
+        return None;
+    }
+    path.file_stem()?.to_str()?.parse().ok()
+}
+
+/// Where change set `seq` of `device_id` lives, relative to the folder.
+pub(crate) fn object_path(device_id: &str, seq: i64) -> String {
+    format!("{}/{}/{:010}.{}", ROOT_DIR, device_id, seq, EXTENSION)
+}
+
+/// The device and sequence number of a path from [`object_path`].
+pub(crate) fn parse_object_path(path: &str) -> Option<(&str, i64)> {
+    let rest = path.strip_prefix(ROOT_DIR)?.strip_prefix('/')?;
+    let (device_id, file) = rest.split_once('/')?;
+    let seq = file.strip_suffix(EXTENSION)?.strip_suffix('.')?;
+    if !is_device_id(device_id) || seq.len() != 10 {
+        return None;
+    }
+    Some((device_id, seq.parse().ok()?))
+}
+
+/// Sequence numbers of the change sets in `device_id`'s directory, ascending.
+pub(crate) fn list_seqs(folder: &Path, device_id: &str) -> Result<Vec<(i64, PathBuf)>, String> {
+    let dir = root_dir(folder).join(device_id);
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read {}: {}", dir.display(), e)),
+    };
+    let mut seqs: Vec<(i64, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            parse_seq(&path).map(|seq| (seq, path))
+        })
+        .collect();
+    seqs.sort_by_key(|(seq, _)| *seq);
+    Ok(seqs)
+}
+
+/// Every device that has written to the sync folder.
+pub(crate) fn devices(folder: &Path) -> Result<Vec<String>, String> {
+    let dir = root_dir(folder);
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read {}: {}", dir.display(), e)),
+    };
+    let mut devices: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| is_device_id(name))
+        .collect();
+    devices.sort();
+    Ok(devices)
+}
+
+/// The highest sequence number `device_id` has written, or 0.
+pub(crate) fn last_seq(folder: &Path, device_id: &str) -> Result<i64, String> {
+    Ok(list_seqs(folder, device_id)?
+        .last()
+        .map(|(seq, _)| *seq)
+        .unwrap_or(0))
+}
+
+/// Change sets from `device_id` numbered after `after`, in order. Stops at
+/// the first file that can't be read yet, which is usually one the syncing
+/// service hasn't finished copying; the next sync picks up from there.
+pub(crate) fn read_since(
+    folder: &Path,
+    device_id: &str,
+    after: i64,
+) -> Result<Vec<ChangeSet>, String> {
+    let mut changes = Vec::new();
+    for (seq, path) in list_seqs(folder, device_id)? {
+        if seq <= after {
+            continue;
+        }
+        let parsed = std::fs::read(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|raw| serde_json::from_slice::<ChangeSet>(&raw).map_err(|e| e.to_string()));
+        let change_set = match parsed {
+            Ok(change_set) => change_set,
+            Err(e) => {
+                warn!("Skipping sync change set {} for now: {}", path.display(), e);
+                break;
+            }
+        };
+        if change_set.version > FORMAT_VERSION {
+            warn!(
+                "Change set {} needs a newer version of Freely",
+                path.display()
+            );
+            break;
+        }
+        changes.push(change_set);
+    }
+    Ok(changes)
+}
+
+pub(crate) fn write(folder: &Path, change_set: &ChangeSet) -> Result<PathBuf, String> {
+    let json = serde_json::to_vec(change_set)
+        .map_err(|e| format!("Failed to serialize change set: {}", e))?;
+    let path = folder.join(object_path(&change_set.device_id, change_set.seq));
+    write_atomic(&path, &json)?;
+    Ok(path)
+}
+
+/// Write `bytes` to `path` under a temporary name first, so other readers
+/// never see half a file.
+pub(crate) fn write_atomic(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, bytes).map_err(|e| format!("Failed to write {}: {}", tmp.display(), e))?;
+    std::fs::rename(&tmp, path).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn change_set(seq: i64) -> ChangeSet {
+        ChangeSet {
+            version: FORMAT_VERSION,
+            device_id: "device-a".to_string(),
+            device_name: "Desktop".to_string(),
+            seq,
+            created_at: seq,
+            records: vec![Record {
+                kind: RecordKind::Setting,
+                id: "theme".to_string(),
+                updated_at: seq,
+                deleted: false,
+                data: Some(Value::from("dark")),
+            }],
+        }
+    }
+
+    #[test]
+    fn reads_change_sets_in_order_up_to_an_unreadable_one() {
+        let tmp = TempDir::new().unwrap();
+        for seq in [2, 1, 4] {
+            write(tmp.path(), &change_set(seq)).unwrap();
+        }
+        // Still being copied by the syncing service
+        std::fs::write(
+            tmp.path().join("freely-sync/device-a/0000000003.json"),
+            b"{\"vers",
+        )
+        .unwrap();
+
+        assert_eq!(devices(tmp.path()).unwrap(), ["device-a"]);
+        assert_eq!(last_seq(tmp.path(), "device-a").unwrap(), 4);
+        let seqs: Vec<i64> = read_since(tmp.path(), "device-a", 0)
+            .unwrap()
+            .iter()
+            .map(|c| c.seq)
+            .collect();
+        assert_eq!(seqs, [1, 2]);
+        assert!(read_since(tmp.path(), "device-b", 0).unwrap().is_empty());
+
+        let path = object_path("device-a", 12);
+        assert_eq!(path, "freely-sync/device-a/0000000012.json");
+        assert_eq!(parse_object_path(&path), Some(("device-a", 12)));
+        assert_eq!(parse_object_path("freely-sync/key.json"), None);
+        assert_eq!(parse_object_path("freely-sync/../0000000001.json"), None);
+    }
+}
@@ -0,0 +1,161 @@
+//! Client-side encryption of change sets sent to a sync remote.
+//!
+//! Every device derives the same XChaCha20-Poly1305 key from the sync
+//! passphrase with Argon2id, salted with the random salt in the remote's
+//! `freely-sync/key.json`. That file also holds a known value encrypted
+//! under the key, so a wrong passphrase is caught when the remote is
+//! configured rather than on the first download. Objects are bound to their
+//! path as associated data, so the remote can't swap one for another.
+
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, KeyInit, OsRng, Payload};
+use chacha20poly1305::{AeadCore, Key, XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+
+/// Path of the key file on the remote.
+pub(crate) const KEY_FILE: &str = "freely-sync/key.json";
+const MAGIC: &[u8] = b"FREELYSYNC1";
+const NONCE_LEN: usize = 24;
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+const CHECK_PLAINTEXT: &[u8] = b"freely-sync";
+const MIN_PASSPHRASE_LEN: usize = 8;
+
+#[derive(Clone)]
+pub(crate) struct SyncKey(Key);
+
+impl SyncKey {
+    pub(crate) fn encode(&self) -> String {
+        BASE64.encode(self.0)
+    }
+
+    pub(crate) fn decode(encoded: &str) -> Result<Self, String> {
+        let bytes = BASE64
+            .decode(encoded)
+            .map_err(|e| format!("Failed to decode sync key: {}", e))?;
+        if bytes.len() != KEY_LEN {
+            return Err("Corrupt sync key".to_string());
+        }
+        Ok(Self(*Key::from_slice(&bytes)))
+    }
+}
+
+/// `freely-sync/key.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct KeyFile {
+    version: u32,
+    salt: String,
+    check: String,
+}
+
+fn derive(passphrase: &str, salt: &[u8]) -> Result<SyncKey, String> {
+    let mut key = [0u8; KEY_LEN];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Failed to derive sync key: {}", e))?;
+    Ok(SyncKey(*Key::from_slice(&key)))
+}
+
+/// A new key file for a remote that has none yet, with the key it holds.
+pub(crate) fn create_key_file(passphrase: &str) -> Result<(KeyFile, SyncKey), String> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err(format!(
+            "Sync passphrase must be at least {} characters",
+            MIN_PASSPHRASE_LEN
+        ));
+    }
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let key = derive(passphrase, &salt)?;
+    let check = encrypt(&key, KEY_FILE, CHECK_PLAINTEXT)?;
+    let file = KeyFile {
+        version: 1,
+        salt: BASE64.encode(salt),
+        check: BASE64.encode(check),
+    };
+    Ok((file, key))
+}
+
+/// The key `passphrase` gives for `file`, if it is the right passphrase.
+pub(crate) fn unlock_key_file(file: &KeyFile, passphrase: &str) -> Result<SyncKey, String> {
+    let decode = |value: &str| {
+        BASE64
+            .decode(value)
+            .map_err(|e| format!("Corrupt sync key file: {}", e))
+    };
+    let key = derive(passphrase, &decode(&file.salt)?)?;
+    match decrypt(&key, KEY_FILE, &decode(&file.check)?) {
+        Ok(check) if check == CHECK_PLAINTEXT => Ok(key),
+        _ => Err("Wrong sync passphrase".to_string()),
+    }
+}
+
+/// `MAGIC || nonce || ciphertext` of `plaintext`, bound to `path`.
+pub(crate) fn encrypt(key: &SyncKey, path: &str, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    let cipher = XChaCha20Poly1305::new(&key.0);
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad: path.as_bytes(),
+            },
+        )
+        .map_err(|_| "Failed to encrypt change set".to_string())?;
+
+    let mut out = Vec::with_capacity(MAGIC.len() + NONCE_LEN + ciphertext.len());
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&ciphertext);
+    Ok(out)
+}
+
+pub(crate) fn decrypt(key: &SyncKey, path: &str, data: &[u8]) -> Result<Vec<u8>, String> {
+    let rest = data
+        .strip_prefix(MAGIC)
+        .filter(|rest| rest.len() > NONCE_LEN)
+        .ok_or_else(|| format!("{} is not an encrypted change set", path))?;
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    XChaCha20Poly1305::new(&key.0)
+        .decrypt(
+            XNonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: path.as_bytes(),
+            },
+        )
+        .map_err(|_| format!("Failed to decrypt {}: wrong key or damaged file", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn change_sets_round_trip_under_the_passphrase_key() {
+        let (file, key) = create_key_file("correct horse").unwrap();
+        let json = serde_json::to_string(&file).unwrap();
+        let file: KeyFile = serde_json::from_str(&json).unwrap();
+
+        let other_device = unlock_key_file(&file, "correct horse").unwrap();
+        assert_eq!(other_device.encode(), key.encode());
+        assert!(unlock_key_file(&file, "wrong horse").is_err());
+        assert!(create_key_file("short").is_err());
+
+        let path = "freely-sync/a/0000000001.json";
+        let sealed = encrypt(&key, path, b"{\"records\":[]}").unwrap();
+        assert!(!sealed.windows(7).any(|w| w == b"records"));
+        assert_eq!(
+            decrypt(&other_device, path, &sealed).unwrap(),
+            b"{\"records\":[]}"
+        );
+        // Moved to another path, or tampered with
+        assert!(decrypt(&key, "freely-sync/a/0000000002.json", &sealed).is_err());
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(decrypt(&key, path, &tampered).is_err());
+    }
+}
@@ -0,0 +1,1042 @@
+//! Keeping conversations, system prompts and settings aligned between
+//! devices through a folder the user already syncs (Syncthing, Dropbox,
+//! iCloud Drive and the like).
+//!
+//! Each sync first reads the change sets other devices wrote since the last
+//! one and merges them record by record, last write wins: the version with
+//! the later `updatedAt` is kept, ties going to the higher device id. When a
+//! record changed here as well since it was last synced, the losing version
+//! is logged in `sync_conflicts` so nothing is lost silently. Then every
+//! record that changed here (or was deleted) since it was last exchanged is
+//! written to a new change set of this device's own. Which versions were
+//! exchanged is kept in `sync_records` (migration 26).
+//!
+//! System prompts are merged by content instead: the same prompt written on
+//! two devices becomes one, and when a prompt was edited differently on two
+//! devices the older version is kept as a "conflicted copy" next to it.
+//!
+//! Instead of a folder, an S3 bucket or WebDAV server can be configured
+//! with `configure_sync_remote`; see [`remote`]. Change sets are encrypted
+//! before they are uploaded, with a key derived from a passphrase only the
+//! user's devices know.
+//!
+//! Settings owned by a Rust module (shortcuts, window state, the app lock
+//! and so on) describe one machine and are not synced. Syncing runs every
+//! few minutes while enabled and on `sync_now`. Each step is emitted as
+//! `sync-status` and the result as `sync-completed`.
+
+pub(crate) mod changeset;
+pub(crate) mod crypto;
+pub(crate) mod remote;
+pub(crate) mod store;
+
+pub use remote::RemoteConfig;
+pub use store::SyncConflict;
+
+use changeset::{ChangeSet, Record, RecordKind};
+use crypto::SyncKey;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use remote::Remote;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use store::{Local, NewConflict, Synced};
+use tauri::{AppHandle, Emitter, Manager};
+use tracing::{info, warn};
+
+/// Settings key for [`SyncConfig`].
+pub(crate) const SETTING_KEY: &str = "sync";
+const SYNC_INTERVAL: Duration = Duration::from_secs(5 * 60);
+const STARTUP_DELAY: Duration = Duration::from_secs(60);
+const MAX_DEVICE_NAME_LEN: usize = 64;
+/// Holds decrypted change sets during a remote sync.
+const STAGING_DIR: &str = "sync-staging";
+
+static SYNCING: AtomicBool = AtomicBool::new(false);
+static STATUS: Lazy<Mutex<SyncStatus>> = Lazy::new(|| {
+    Mutex::new(SyncStatus {
+        phase: SyncPhase::Idle,
+        error: None,
+        updated_at: 0,
+    })
+});
+
+/// Stored under the `sync` setting.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SyncConfig {
+    pub enabled: bool,
+    pub folder: Option<String>,
+    /// Used instead of `folder` when set.
+    pub remote: Option<RemoteConfig>,
+    /// Generated the first time sync is configured.
+    pub device_id: String,
+    /// Shown on other devices, for example in conflicts.
+    pub device_name: String,
+    pub last_sync_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncReport {
+    /// Records written to this device's new change set.
+    pub exported: usize,
+    /// Records taken from other devices.
+    pub imported: usize,
+    pub conflicts: usize,
+    /// Settings changed by the import, for `setting-changed`.
+    pub changed_settings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SyncPhase {
+    Idle,
+    /// Fetching other devices' change sets from the remote.
+    Downloading,
+    Merging,
+    Uploading,
+    /// The last sync failed; `error` says why.
+    Failed,
+}
+
+/// Emitted as `sync-status` whenever the phase changes.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStatus {
+    pub phase: SyncPhase,
+    pub error: Option<String>,
+    pub updated_at: i64,
+}
+
+fn report_status(app: &AppHandle, phase: SyncPhase, error: Option<String>) {
+    let status = SyncStatus {
+        phase,
+        error,
+        updated_at: crate::db::now_millis(),
+    };
+    *STATUS.lock() = status.clone();
+    if let Err(e) = app.emit("sync-status", status) {
+        warn!("Failed to emit sync status: {}", e);
+    }
+}
+
+fn default_device_name() -> String {
+    ["COMPUTERNAME", "HOSTNAME"]
+        .iter()
+        .find_map(|var| {
+            std::env::var(var)
+                .ok()
+                .filter(|name| !name.trim().is_empty())
+        })
+        .unwrap_or_else(|| "This device".to_string())
+}
+
+async fn load_config(pool: &SqlitePool) -> Result<SyncConfig, String> {
+    Ok(crate::settings::get_setting(pool, SETTING_KEY)
+        .await?
+        .unwrap_or_default())
+}
+
+/// Whether `(a_at, a_device)` is newer than `(b_at, b_device)`.
+fn is_newer(a_at: i64, a_device: &str, b_at: i64, b_device: &str) -> bool {
+    (a_at, a_device) > (b_at, b_device)
+}
+
+/// Whether a record changed here since it was last exchanged.
+fn changed_locally(local: Option<&Local>, synced: Option<&Synced>) -> bool {
+    match (local, synced) {
+        (Some(local), Some(synced)) => synced.deleted || local.fingerprint != synced.fingerprint,
+        (Some(_), None) => true,
+        (None, Some(synced)) => !synced.deleted,
+        (None, None) => false,
+    }
+}
+
+/// When a local change happened. Edits that keep `updated_at` (and
+/// deletions) count as happening now.
+fn change_time(local: Option<&Local>, synced: Option<&Synced>, now: i64) -> i64 {
+    match (local, synced) {
+        (Some(local), Some(synced)) if local.updated_at <= synced.updated_at => {
+            now.max(synced.updated_at + 1)
+        }
+        (Some(local), _) => local.updated_at,
+        (None, synced) => now.max(synced.map_or(0, |s| s.updated_at + 1)),
+    }
+}
+
+struct Device<'a> {
+    id: &'a str,
+    name: &'a str,
+}
+
+/// Merge one remote record. Returns whether it was applied and whether a
+/// conflict was logged.
+async fn import_record(
+    pool: &SqlitePool,
+    device: &Device<'_>,
+    from: &ChangeSet,
+    record: &Record,
+    now: i64,
+) -> Result<(bool, bool), String> {
+    let local = store::local_state_of(pool, record.kind, &record.id).await?;
+    let synced = store::synced_state_of(pool, record.kind, &record.id).await?;
+    let remote_fingerprint = record
+        .data
+        .as_ref()
+        .map(|data| store::fingerprint(record.kind, data));
+
+    if record.kind == RecordKind::Prompt && local.is_none() && synced.is_none() {
+        if let Some(fingerprint) = &remote_fingerprint {
+            if let Some(twin) = store::find_prompt(pool, fingerprint).await? {
+                merge_twin_prompts(pool, record, &twin, fingerprint).await?;
+                return Ok((false, false));
+            }
+        }
+    }
+
+    let local_changed = changed_locally(local.as_ref(), synced.as_ref());
+    // The version this device holds now and when it was written
+    let current_at = if local_changed {
+        change_time(local.as_ref(), synced.as_ref(), now)
+    } else {
+        synced
+            .as_ref()
+            .map(|s| s.updated_at)
+            .or(local.as_ref().map(|l| l.updated_at))
+            .unwrap_or(0)
+    };
+    let same_content = match (&local, &remote_fingerprint) {
+        (Some(local), Some(remote)) => !record.deleted && local.fingerprint == *remote,
+        (None, _) => record.deleted,
+        (Some(_), None) => false,
+    };
+
+    if same_content {
+        let fingerprint = local.as_ref().map_or("", |l| l.fingerprint.as_str());
+        store::mark_synced(
+            pool,
+            record.kind,
+            &record.id,
+            record.updated_at,
+            fingerprint,
+            local.is_none(),
+        )
+        .await?;
+        return Ok((false, false));
+    }
+
+    let remote_wins = is_newer(record.updated_at, &from.device_id, current_at, device.id);
+    if record.kind == RecordKind::Prompt && local_changed {
+        return resolve_prompt(pool, device, from, record, remote_wins).await;
+    }
+    if !remote_wins && !local_changed {
+        // An older version this device has already moved past
+        return Ok((false, false));
+    }
+
+    let mut conflict = false;
+    if local_changed {
+        let discarded = if remote_wins {
+            store::load(pool, record.kind, &record.id)
+                .await?
+                .map(|(_, data)| data)
+        } else {
+            record.data.clone()
+        };
+        store::log_conflict(
+            pool,
+            NewConflict {
+                kind: record.kind,
+                record_id: &record.id,
+                device_name: &from.device_name,
+                kept_remote: remote_wins,
+                local_updated_at: Some(current_at),
+                remote_updated_at: record.updated_at,
+                discarded: discarded.as_ref(),
+            },
+            now,
+        )
+        .await?;
+        conflict = true;
+    }
+    if !remote_wins {
+        // The local version goes out with the next change set
+        return Ok((false, conflict));
+    }
+
+    apply_remote(pool, record).await?;
+    Ok((true, conflict))
+}
+
+/// Store `record` and remember it as exchanged.
+async fn apply_remote(pool: &SqlitePool, record: &Record) -> Result<(), String> {
+    store::apply(pool, record).await?;
+    let fingerprint = store::local_state_of(pool, record.kind, &record.id)
+        .await?
+        .map(|local| local.fingerprint)
+        .unwrap_or_default();
+    store::mark_synced(
+        pool,
+        record.kind,
+        &record.id,
+        record.updated_at,
+        &fingerprint,
+        record.deleted,
+    )
+    .await
+}
+
+/// The same prompt written separately on two devices. Both keep the lower
+/// of the two sync ids, so they converge on one prompt.
+async fn merge_twin_prompts(
+    pool: &SqlitePool,
+    record: &Record,
+    local_id: &str,
+    fingerprint: &str,
+) -> Result<(), String> {
+    if record.id.as_str() < local_id {
+        // The old id goes out as a deletion with the next change set
+        store::set_prompt_sync_id(pool, local_id, &record.id).await?;
+        store::mark_synced(
+            pool,
+            RecordKind::Prompt,
+            &record.id,
+            record.updated_at,
+            fingerprint,
+            false,
+        )
+        .await
+    } else {
+        // The other device adopts ours when it reads it
+        store::mark_synced(
+            pool,
+            RecordKind::Prompt,
+            &record.id,
+            record.updated_at,
+            "",
+            true,
+        )
+        .await
+    }
+}
+
+/// A prompt that changed both here and on `from`. An edit beats a
+/// deletion, and when both were edited the newer version keeps the prompt
+/// while the older one is saved next to it as a renamed copy. Nothing is
+/// lost, so nothing is logged as a conflict.
+async fn resolve_prompt(
+    pool: &SqlitePool,
+    device: &Device<'_>,
+    from: &ChangeSet,
+    record: &Record,
+    remote_wins: bool,
+) -> Result<(bool, bool), String> {
+    if record.deleted {
+        // The local version goes out again with the next change set
+        return Ok((false, false));
+    }
+    let Some((_, local_data)) = store::load(pool, RecordKind::Prompt, &record.id).await? else {
+        // Deleted here, edited there
+        apply_remote(pool, record).await?;
+        return Ok((true, false));
+    };
+
+    if remote_wins {
+        store::insert_prompt_copy(pool, &local_data, device.name).await?;
+        apply_remote(pool, record).await?;
+    } else if let Some(remote_data) = &record.data {
+        store::insert_prompt_copy(pool, remote_data, &from.device_name).await?;
+    }
+    Ok((remote_wins, true))
+}
+
+async fn import(
+    pool: &SqlitePool,
+    folder: &Path,
+    device: &Device<'_>,
+    report: &mut SyncReport,
+    now: i64,
+) -> Result<(), String> {
+    for device_id in changeset::devices(folder)? {
+        if device_id == device.id {
+            continue;
+        }
+        let last_seq = store::device_seq(pool, &device_id).await?;
+        for change_set in changeset::read_since(folder, &device_id, last_seq)? {
+            if change_set.device_id != device_id {
+                warn!(
+                    "Skipping change set {} in the folder of device {}",
+                    change_set.seq, device_id
+                );
+                continue;
+            }
+            for record in &change_set.records {
+                let (applied, conflict) =
+                    match import_record(pool, device, &change_set, record, now).await {
+                        Ok(result) => result,
+                        Err(e) => {
+                            warn!(
+                                "Skipping synced {} {}: {}",
+                                record.kind.as_str(),
+                                record.id,
+                                e
+                            );
+                            continue;
+                        }
+                    };
+                if applied {
+                    report.imported += 1;
+                    if record.kind == RecordKind::Setting {
+                        report.changed_settings.push(record.id.clone());
+                    }
+                }
+                if conflict {
+                    report.conflicts += 1;
+                }
+            }
+            store::set_device_seq(
+                pool,
+                &device_id,
+                &change_set.device_name,
+                change_set.seq,
+                now,
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Records changed here since they were last exchanged, with the sync
+/// state to save once they are written.
+async fn collect_changes(pool: &SqlitePool, now: i64) -> Result<Vec<(Record, String)>, String> {
+    let mut changes = Vec::new();
+    for kind in RecordKind::ALL {
+        let local = store::local_state(pool, kind).await?;
+        let mut synced = store::synced_state(pool, kind).await?;
+
+        let mut ids: Vec<&String> = local.keys().collect();
+        ids.sort();
+        for id in ids {
+            let entry = &local[id];
+            let previous = synced.remove(id);
+            if !changed_locally(Some(entry), previous.as_ref()) {
+                continue;
+            }
+            let Some((_, data)) = store::load(pool, kind, id).await? else {
+                continue;
+            };
+            changes.push((
+                Record {
+                    kind,
+                    id: id.clone(),
+                    updated_at: change_time(Some(entry), previous.as_ref(), now),
+                    deleted: false,
+                    data: Some(data),
+                },
+                entry.fingerprint.clone(),
+            ));
+        }
+
+        // Whatever is left was exchanged before and is gone here
+        let mut gone: Vec<Synced> = synced.into_values().filter(|s| !s.deleted).collect();
+        gone.sort_by(|a, b| a.record_id.cmp(&b.record_id));
+        for previous in gone {
+            changes.push((
+                Record {
+                    kind,
+                    updated_at: change_time(None, Some(&previous), now),
+                    id: previous.record_id,
+                    deleted: true,
+                    data: None,
+                },
+                String::new(),
+            ));
+        }
+    }
+    Ok(changes)
+}
+
+async fn export(
+    pool: &SqlitePool,
+    folder: &Path,
+    device: &Device<'_>,
+    report: &mut SyncReport,
+    now: i64,
+) -> Result<(), String> {
+    let changes = collect_changes(pool, now).await?;
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    // Files already in the folder win over the table, in case the database
+    // was restored from a backup
+    let seq = store::device_seq(pool, device.id)
+        .await?
+        .max(changeset::last_seq(folder, device.id)?)
+        + 1;
+    let (records, fingerprints): (Vec<Record>, Vec<String>) = changes.into_iter().unzip();
+    let change_set = ChangeSet {
+        version: changeset::FORMAT_VERSION,
+        device_id: device.id.to_string(),
+        device_name: device.name.to_string(),
+        seq,
+        created_at: now,
+        records,
+    };
+    changeset::write(folder, &change_set)?;
+
+    for (record, fingerprint) in change_set.records.iter().zip(&fingerprints) {
+        store::mark_synced(
+            pool,
+            record.kind,
+            &record.id,
+            record.updated_at,
+            fingerprint,
+            record.deleted,
+        )
+        .await?;
+    }
+    store::set_device_seq(pool, device.id, device.name, seq, now).await?;
+    report.exported = change_set.records.len();
+    Ok(())
+}
+
+/// Import other devices' changes from `folder`, then export this one's.
+pub(crate) async fn sync_folder(
+    pool: &SqlitePool,
+    folder: &Path,
+    device_id: &str,
+    device_name: &str,
+    now: i64,
+) -> Result<SyncReport, String> {
+    if !folder.is_dir() {
+        return Err(format!("Sync folder not found: {}", folder.display()));
+    }
+    let device = Device {
+        id: device_id,
+        name: device_name,
+    };
+    let mut report = SyncReport::default();
+    import(pool, folder, &device, &mut report, now).await?;
+    export(pool, folder, &device, &mut report, now).await?;
+    Ok(report)
+}
+
+fn staging_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app
+        .path()
+        .app_local_data_dir()
+        .map_err(|e| format!("Could not resolve app_local_data_dir: {}", e))?
+        .join(STAGING_DIR))
+}
+
+async fn sync_remote(
+    app: &AppHandle,
+    pool: &SqlitePool,
+    remote: RemoteConfig,
+    config: &SyncConfig,
+    now: i64,
+) -> Result<SyncReport, String> {
+    crate::net::connectivity::require_online()?;
+    let secret = crate::secrets::load_secret(app, remote::SECRET_NAME)
+        .await?
+        .ok_or_else(|| "Enter the sync remote's credentials again".to_string())?;
+    let key = crate::secrets::load_secret(app, remote::KEY_NAME)
+        .await?
+        .ok_or_else(|| "Enter the sync passphrase again".to_string())?;
+    let key = SyncKey::decode(&key)?;
+    let remote = Remote::new(remote, secret)?;
+    let staging = staging_dir(app)?;
+    std::fs::create_dir_all(&staging)
+        .map_err(|e| format!("Failed to create {}: {}", staging.display(), e))?;
+
+    report_status(app, SyncPhase::Downloading, None);
+    let uploaded = remote::pull(
+        &remote,
+        &key,
+        pool,
+        &staging,
+        &config.device_id,
+        &config.device_name,
+        now,
+    )
+    .await?;
+
+    report_status(app, SyncPhase::Merging, None);
+    let report = sync_folder(pool, &staging, &config.device_id, &config.device_name, now).await?;
+
+    report_status(app, SyncPhase::Uploading, None);
+    remote::push(&remote, &key, &staging, &config.device_id, &uploaded).await?;
+    if let Err(e) = std::fs::remove_dir_all(&staging) {
+        warn!("Failed to empty {}: {}", staging.display(), e);
+    }
+    Ok(report)
+}
+
+async fn sync_once(app: &AppHandle) -> Result<SyncReport, String> {
+    let pool = crate::db::pool(app).await?;
+    let mut config = load_config(&pool).await?;
+    if config.device_id.is_empty() {
+        return Err("Set up sync first".to_string());
+    }
+    let now = crate::db::now_millis();
+    let report = match config.remote.clone() {
+        Some(remote) => sync_remote(app, &pool, remote, &config, now).await?,
+        None => {
+            let folder = config
+                .folder
+                .clone()
+                .ok_or_else(|| "Choose a sync folder first".to_string())?;
+            report_status(app, SyncPhase::Merging, None);
+            sync_folder(
+                &pool,
+                &PathBuf::from(folder),
+                &config.device_id,
+                &config.device_name,
+                now,
+            )
+            .await?
+        }
+    };
+
+    config.last_sync_at = Some(now);
+    crate::settings::set_setting(app, SETTING_KEY, &config).await?;
+    for key in &report.changed_settings {
+        let value = crate::db::settings::get(&pool, key).await.unwrap_or(None);
+        let value = value.or_else(|| crate::settings::default_for(key));
+        crate::settings::emit_changed(app, key, value);
+    }
+    if let Err(e) = app.emit("sync-completed", &report) {
+        warn!("Failed to emit sync completion: {}", e);
+    }
+    Ok(report)
+}
+
+async fn run_sync(app: &AppHandle) -> Result<SyncReport, String> {
+    if SYNCING.swap(true, Ordering::SeqCst) {
+        return Err("A sync is already running".to_string());
+    }
+    let result = sync_once(app).await;
+    SYNCING.store(false, Ordering::SeqCst);
+    match &result {
+        Ok(_) => report_status(app, SyncPhase::Idle, None),
+        Err(e) => report_status(app, SyncPhase::Failed, Some(e.clone())),
+    }
+    result
+}
+
+async fn run_scheduled(app: &AppHandle) -> Result<(), String> {
+    let pool = crate::db::pool(app).await?;
+    let config = load_config(&pool).await?;
+    let has_target = config.folder.is_some() || config.remote.is_some();
+    if !config.enabled || !has_target || SYNCING.load(Ordering::SeqCst) {
+        return Ok(());
+    }
+    // Remote syncs wait for the network instead of failing every few minutes
+    if config.remote.is_some() && !crate::net::connectivity::is_online() {
+        return Ok(());
+    }
+    let report = run_sync(app).await?;
+    if report.imported > 0 || report.exported > 0 {
+        info!(
+            "Synced: {} imported, {} exported, {} conflicts",
+            report.imported, report.exported, report.conflicts
+        );
+    }
+    Ok(())
+}
+
+/// Sync every few minutes while enabled. Called once from `setup`.
+pub fn start_sync_scheduler(app: AppHandle) {
+    crate::jobs::run_periodically(
+        app,
+        "Scheduled sync",
+        SYNC_INTERVAL,
+        STARTUP_DELAY,
+        |app| async move { run_scheduled(&app).await },
+    );
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+#[tauri::command]
+pub async fn get_sync_config(app: AppHandle) -> Result<SyncConfig, String> {
+    let pool = crate::db::pool(&app).await?;
+    load_config(&pool).await
+}
+
+fn set_device(config: &mut SyncConfig, device_name: Option<String>) -> Result<(), String> {
+    if config.device_id.is_empty() {
+        config.device_id = uuid::Uuid::new_v4().to_string();
+    }
+    if let Some(name) = device_name.map(|n| n.trim().to_string()) {
+        if name.is_empty() || name.chars().count() > MAX_DEVICE_NAME_LEN {
+            return Err(format!(
+                "Device name must be 1 to {} characters",
+                MAX_DEVICE_NAME_LEN
+            ));
+        }
+        config.device_name = name;
+    }
+    if config.device_name.is_empty() {
+        config.device_name = default_device_name();
+    }
+    Ok(())
+}
+
+/// Choose the sync folder and turn syncing on or off. A device name is
+/// picked from the machine's when none is given. A configured remote takes
+/// the folder's place.
+#[tauri::command]
+pub async fn configure_sync(
+    app: AppHandle,
+    enabled: bool,
+    folder: Option<String>,
+    device_name: Option<String>,
+) -> Result<SyncConfig, String> {
+    let folder = folder
+        .map(|f| f.trim().to_string())
+        .filter(|f| !f.is_empty());
+    if let Some(folder) = &folder {
+        if !Path::new(folder).is_dir() {
+            return Err(format!("Sync folder not found: {}", folder));
+        }
+    }
+
+    let pool = crate::db::pool(&app).await?;
+    let mut config = load_config(&pool).await?;
+    if enabled && folder.is_none() && config.remote.is_none() {
+        return Err("Choose a sync folder first".to_string());
+    }
+    set_device(&mut config, device_name)?;
+    if config.remote.is_none() && config.folder != folder {
+        // Everything goes out again to the new folder
+        store::reset(&pool).await?;
+    }
+    config.enabled = enabled;
+    config.folder = folder;
+    crate::settings::set_setting(&app, SETTING_KEY, &config).await?;
+    Ok(config)
+}
+
+/// Sync through an S3-compatible bucket or a WebDAV server instead of the
+/// folder, encrypting change sets under `passphrase`, which every device
+/// must be given. `secret` is the S3 secret key or WebDAV password and can
+/// be left out to keep the stored one. `None` goes back to the folder.
+#[tauri::command]
+pub async fn configure_sync_remote(
+    app: AppHandle,
+    remote: Option<RemoteConfig>,
+    secret: Option<String>,
+    passphrase: Option<String>,
+) -> Result<SyncConfig, String> {
+    let pool = crate::db::pool(&app).await?;
+    let mut config = load_config(&pool).await?;
+
+    let Some(remote) = remote else {
+        if config.remote.take().is_some() {
+            store::reset(&pool).await?;
+        }
+        config.enabled &= config.folder.is_some();
+        for name in [remote::SECRET_NAME, remote::KEY_NAME] {
+            crate::secrets::delete_secret(&app, name).await?;
+        }
+        crate::settings::set_setting(&app, SETTING_KEY, &config).await?;
+        return Ok(config);
+    };
+
+    remote.validate()?;
+    let secret = match secret.filter(|s| !s.trim().is_empty()) {
+        Some(secret) => secret,
+        None => crate::secrets::load_secret(&app, remote::SECRET_NAME)
+            .await?
+            .ok_or_else(|| "Enter the remote's secret key or password".to_string())?,
+    };
+    let passphrase = passphrase
+        .filter(|p| !p.is_empty())
+        .ok_or_else(|| "Enter the sync passphrase".to_string())?;
+    crate::net::connectivity::require_online()?;
+
+    // Checks the credentials and the passphrase before anything is saved
+    let key = Remote::new(remote.clone(), secret.clone())?
+        .unlock(&passphrase)
+        .await?;
+    crate::secrets::set_secret(&app, remote::SECRET_NAME, secret).await?;
+    crate::secrets::set_secret(&app, remote::KEY_NAME, key.encode()).await?;
+
+    set_device(&mut config, None)?;
+    if config.remote.as_ref() != Some(&remote) {
+        store::reset(&pool).await?;
+    }
+    config.remote = Some(remote);
+    config.enabled = true;
+    crate::settings::set_setting(&app, SETTING_KEY, &config).await?;
+    Ok(config)
+}
+
+/// Where syncing is now; changes are emitted as `sync-status`.
+#[tauri::command]
+pub fn get_sync_status() -> SyncStatus {
+    STATUS.lock().clone()
+}
+
+#[tauri::command]
+pub async fn sync_now(app: AppHandle) -> Result<SyncReport, String> {
+    run_sync(&app).await
+}
+
+/// Conflicts logged by past syncs, newest first.
+#[tauri::command]
+pub async fn list_sync_conflicts(
+    app: AppHandle,
+    limit: Option<u32>,
+) -> Result<Vec<SyncConflict>, String> {
+    let pool = crate::db::pool(&app).await?;
+    store::list_conflicts(&pool, limit).await
+}
+
+#[tauri::command]
+pub async fn clear_sync_conflicts(app: AppHandle) -> Result<u64, String> {
+    let pool = crate::db::pool(&app).await?;
+    store::clear_conflicts(&pool).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    async fn conversation(pool: &SqlitePool, id: &str, title: &str, updated_at: i64) {
+        sqlx::query(
+            "INSERT INTO conversations (id, title, created_at, updated_at) VALUES (?1, ?2, 1, ?3)
+             ON CONFLICT(id) DO UPDATE SET title = ?2, updated_at = ?3",
+        )
+        .bind(id)
+        .bind(title)
+        .bind(updated_at)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    async fn title(pool: &SqlitePool, id: &str) -> Option<String> {
+        sqlx::query_scalar("SELECT title FROM conversations WHERE id = ?")
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn records_travel_between_devices() {
+        let tmp = TempDir::new().unwrap();
+        let desktop = crate::db::test_pool().await;
+        let laptop = crate::db::test_pool().await;
+
+        conversation(&desktop, "c1", "Trip plans", 100).await;
+        sqlx::query(
+            "INSERT INTO messages (id, conversation_id, role, content, timestamp)
+             VALUES ('m1', 'c1', 'user', 'Where to?', 100)",
+        )
+        .execute(&desktop)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO system_prompts (name, prompt) VALUES ('Tutor', 'Explain simply')")
+            .execute(&desktop)
+            .await
+            .unwrap();
+        crate::db::settings::set(&desktop, "theme", &"dark")
+            .await
+            .unwrap();
+        crate::db::settings::set(&desktop, "shortcuts", &json!({}))
+            .await
+            .unwrap();
+
+        let report = sync_folder(&desktop, tmp.path(), "a", "Desktop", 1000)
+            .await
+            .unwrap();
+        assert_eq!(report.exported, 3);
+        // Nothing changed since
+        let report = sync_folder(&desktop, tmp.path(), "a", "Desktop", 2000)
+            .await
+            .unwrap();
+        assert_eq!(report.exported, 0);
+
+        let report = sync_folder(&laptop, tmp.path(), "b", "Laptop", 3000)
+            .await
+            .unwrap();
+        assert_eq!(report.imported, 3);
+        assert_eq!(report.exported, 0);
+        assert_eq!(report.changed_settings, ["theme"]);
+        assert_eq!(title(&laptop, "c1").await.as_deref(), Some("Trip plans"));
+        let content: String = sqlx::query_scalar("SELECT content FROM messages WHERE id = 'm1'")
+            .fetch_one(&laptop)
+            .await
+            .unwrap();
+        assert_eq!(content, "Where to?");
+        let updated_at: i64 = sqlx::query_scalar("SELECT updated_at FROM conversations")
+            .fetch_one(&laptop)
+            .await
+            .unwrap();
+        assert_eq!(updated_at, 100);
+        let prompt: String = sqlx::query_scalar("SELECT prompt FROM system_prompts")
+            .fetch_one(&laptop)
+            .await
+            .unwrap();
+        assert_eq!(prompt, "Explain simply");
+        assert_eq!(
+            crate::db::settings::get::<Value>(&laptop, "shortcuts")
+                .await
+                .unwrap(),
+            None
+        );
+
+        // Deleting on the laptop removes it on the desktop
+        crate::db::chat::purge(&laptop, "c1").await.unwrap();
+        let report = sync_folder(&laptop, tmp.path(), "b", "Laptop", 4000)
+            .await
+            .unwrap();
+        assert_eq!(report.exported, 1);
+        sync_folder(&desktop, tmp.path(), "a", "Desktop", 5000)
+            .await
+            .unwrap();
+        assert_eq!(title(&desktop, "c1").await, None);
+    }
+
+    #[tokio::test]
+    async fn last_write_wins_and_the_loser_is_logged() {
+        let tmp = TempDir::new().unwrap();
+        let desktop = crate::db::test_pool().await;
+        let laptop = crate::db::test_pool().await;
+
+        conversation(&desktop, "c1", "Draft", 100).await;
+        sync_folder(&desktop, tmp.path(), "a", "Desktop", 1000)
+            .await
+            .unwrap();
+        sync_folder(&laptop, tmp.path(), "b", "Laptop", 1000)
+            .await
+            .unwrap();
+
+        // Both rename it before syncing again; the laptop's is later
+        conversation(&desktop, "c1", "Desktop title", 200).await;
+        conversation(&laptop, "c1", "Laptop title", 300).await;
+        sync_folder(&desktop, tmp.path(), "a", "Desktop", 2000)
+            .await
+            .unwrap();
+        let report = sync_folder(&laptop, tmp.path(), "b", "Laptop", 2000)
+            .await
+            .unwrap();
+        assert_eq!(report.imported, 0);
+        assert_eq!(report.conflicts, 1);
+        assert_eq!(report.exported, 1);
+        assert_eq!(title(&laptop, "c1").await.as_deref(), Some("Laptop title"));
+
+        let conflicts = store::list_conflicts(&laptop, None).await.unwrap();
+        assert_eq!(conflicts[0].kept, "local");
+        assert_eq!(conflicts[0].device_name, "Desktop");
+        assert!(conflicts[0]
+            .discarded
+            .as_deref()
+            .unwrap()
+            .contains("Desktop title"));
+
+        let report = sync_folder(&desktop, tmp.path(), "a", "Desktop", 3000)
+            .await
+            .unwrap();
+        assert_eq!(report.imported, 1);
+        assert_eq!(report.conflicts, 0);
+        assert_eq!(title(&desktop, "c1").await.as_deref(), Some("Laptop title"));
+    }
+
+    async fn prompt_names(pool: &SqlitePool) -> Vec<String> {
+        sqlx::query_scalar("SELECT name FROM system_prompts ORDER BY name")
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    async fn edit_prompt(pool: &SqlitePool, prompt: &str, updated_at: &str) {
+        sqlx::query("UPDATE system_prompts SET prompt = ?, updated_at = ?")
+            .bind(prompt)
+            .bind(updated_at)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn prompts_dedupe_and_diverging_edits_keep_a_copy() {
+        let tmp = TempDir::new().unwrap();
+        let desktop = crate::db::test_pool().await;
+        let laptop = crate::db::test_pool().await;
+
+        // Written separately on both devices
+        for pool in [&desktop, &laptop] {
+            sqlx::query(
+                "INSERT INTO system_prompts (name, prompt) VALUES ('Tutor', 'Explain simply')",
+            )
+            .execute(pool)
+            .await
+            .unwrap();
+        }
+        for _ in 0..2 {
+            sync_folder(&desktop, tmp.path(), "a", "Desktop", 1000)
+                .await
+                .unwrap();
+            sync_folder(&laptop, tmp.path(), "b", "MacBook", 1000)
+                .await
+                .unwrap();
+        }
+        assert_eq!(prompt_names(&desktop).await, ["Tutor"]);
+        assert_eq!(prompt_names(&laptop).await, ["Tutor"]);
+        let sync_id = "SELECT sync_id FROM system_prompts";
+        let desktop_id: String = sqlx::query_scalar(sync_id)
+            .fetch_one(&desktop)
+            .await
+            .unwrap();
+        let laptop_id: String = sqlx::query_scalar(sync_id)
+            .fetch_one(&laptop)
+            .await
+            .unwrap();
+        assert_eq!(desktop_id, laptop_id);
+
+        // Edited differently on both; the desktop's edit is newer
+        edit_prompt(&desktop, "Explain like I'm five", "2030-01-02 00:00:00").await;
+        edit_prompt(&laptop, "Explain with examples", "2030-01-01 00:00:00").await;
+        sync_folder(&laptop, tmp.path(), "b", "MacBook", 2000)
+            .await
+            .unwrap();
+        let report = sync_folder(&desktop, tmp.path(), "a", "Desktop", 2000)
+            .await
+            .unwrap();
+        assert_eq!(report.conflicts, 1);
+        sync_folder(&laptop, tmp.path(), "b", "MacBook", 3000)
+            .await
+            .unwrap();
+
+        for pool in [&desktop, &laptop] {
+            assert_eq!(
+                prompt_names(pool).await,
+                ["Tutor", "Tutor (conflicted copy from MacBook)"]
+            );
+            let prompts: Vec<String> =
+                sqlx::query_scalar("SELECT prompt FROM system_prompts ORDER BY name")
+                    .fetch_all(pool)
+                    .await
+                    .unwrap();
+            assert_eq!(prompts, ["Explain like I'm five", "Explain with examples"]);
+        }
+        assert!(store::list_conflicts(&desktop, None)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
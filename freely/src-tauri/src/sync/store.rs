@@ -0,0 +1,621 @@
+//! Reading and writing synced records, and the sync tables (migration 26).
+//!
+//! Every record is compared by a fingerprint of its content, so a change
+//! is noticed even when it doesn't move `updated_at` (editing a message
+//! keeps the conversation's timestamp).
+
+use super::changeset::{Record, RecordKind};
+use crate::db::chat::{self, Conversation};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+const DEFAULT_CONFLICT_LIMIT: u32 = 100;
+
+/// A record as it is in this database now.
+#[derive(Debug, Clone)]
+pub(crate) struct Local {
+    pub updated_at: i64,
+    pub fingerprint: String,
+}
+
+/// The last version of a record exchanged with the sync folder.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub(crate) struct Synced {
+    pub record_id: String,
+    pub updated_at: i64,
+    pub fingerprint: String,
+    pub deleted: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncConflict {
+    pub id: i64,
+    pub kind: String,
+    pub record_id: String,
+    /// The device the remote version came from.
+    pub device_name: String,
+    /// `local` or `remote`: whichever version was newer.
+    pub kept: String,
+    pub local_updated_at: Option<i64>,
+    pub remote_updated_at: i64,
+    /// The version that lost, as JSON; `None` when it was a deletion.
+    pub discarded: Option<String>,
+    pub created_at: i64,
+}
+
+/// A system prompt as synced; `sync_id` is the record id.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PromptData {
+    name: String,
+    prompt: String,
+    created_at: String,
+}
+
+/// Objects rebuilt with sorted keys, so equal values serialize the same.
+fn canonical(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(String, Value)> = map.into_iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, canonical(value)))
+                    .collect::<Map<String, Value>>(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.into_iter().map(canonical).collect()),
+        other => other,
+    }
+}
+
+/// Hash of a record's content. Prompts are addressed by name and text
+/// alone, so the same prompt written on two devices hashes the same.
+pub(crate) fn fingerprint(kind: RecordKind, data: &Value) -> String {
+    let mut data = canonical(data.clone());
+    if kind == RecordKind::Prompt {
+        if let Some(map) = data.as_object_mut() {
+            map.remove("createdAt");
+        }
+    }
+    let json = serde_json::to_vec(&data).unwrap_or_default();
+    format!("{:x}", Sha256::digest(json))
+}
+
+/// A conversation's synced content: everything but `updatedAt`, which the
+/// record carries, with messages in a stable order.
+fn conversation_data(mut conversation: Conversation) -> Result<Value, String> {
+    conversation
+        .messages
+        .sort_by(|a, b| (a.timestamp, &a.id).cmp(&(b.timestamp, &b.id)));
+    let mut data = serde_json::to_value(&conversation)
+        .map_err(|e| format!("Failed to serialize conversation: {}", e))?;
+    if let Some(map) = data.as_object_mut() {
+        map.remove("id");
+        map.remove("updatedAt");
+    }
+    Ok(canonical(data))
+}
+
+async fn conversation_ids(pool: &SqlitePool) -> Result<Vec<String>, String> {
+    sqlx::query_scalar("SELECT id FROM conversations ORDER BY id")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to list conversations: {}", e))
+}
+
+/// The current version of one record with its content, or `None` if it
+/// doesn't exist here.
+pub(crate) async fn load(
+    pool: &SqlitePool,
+    kind: RecordKind,
+    id: &str,
+) -> Result<Option<(i64, Value)>, String> {
+    match kind {
+        RecordKind::Conversation => match chat::get(pool, id).await? {
+            Some(conversation) => {
+                let updated_at = conversation.updated_at;
+                Ok(Some((updated_at, conversation_data(conversation)?)))
+            }
+            None => Ok(None),
+        },
+        RecordKind::Prompt => {
+            let row: Option<(String, String, String, i64)> = sqlx::query_as(
+                "SELECT name, prompt, created_at,
+                        CAST(strftime('%s', updated_at) AS INTEGER) * 1000
+                 FROM system_prompts WHERE sync_id = ?",
+            )
+            .bind(id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| format!("Failed to load system prompt: {}", e))?;
+            row.map(|(name, prompt, created_at, updated_at)| {
+                let data = serde_json::to_value(PromptData {
+                    name,
+                    prompt,
+                    created_at,
+                })
+                .map_err(|e| format!("Failed to serialize system prompt: {}", e))?;
+                Ok((updated_at, canonical(data)))
+            })
+            .transpose()
+        }
+        RecordKind::Setting => {
+            if crate::settings::is_reserved(id) {
+                return Ok(None);
+            }
+            let row: Option<(String, i64)> =
+                sqlx::query_as("SELECT value, updated_at FROM settings WHERE key = ?")
+                    .bind(id)
+                    .fetch_optional(pool)
+                    .await
+                    .map_err(|e| format!("Failed to read setting {}: {}", id, e))?;
+            row.map(|(json, updated_at)| {
+                let value: Value = serde_json::from_str(&json)
+                    .map_err(|e| format!("Invalid setting {}: {}", id, e))?;
+                Ok((updated_at, canonical(value)))
+            })
+            .transpose()
+        }
+    }
+}
+
+/// Every record of `kind` in this database, by id.
+pub(crate) async fn local_state(
+    pool: &SqlitePool,
+    kind: RecordKind,
+) -> Result<HashMap<String, Local>, String> {
+    let ids: Vec<String> = match kind {
+        RecordKind::Conversation => conversation_ids(pool).await?,
+        RecordKind::Prompt => sqlx::query_scalar(
+            "SELECT sync_id FROM system_prompts WHERE sync_id IS NOT NULL ORDER BY sync_id",
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to list system prompts: {}", e))?,
+        RecordKind::Setting => crate::db::settings::all(pool)
+            .await?
+            .into_iter()
+            .map(|(key, _)| key)
+            .filter(|key| !crate::settings::is_reserved(key))
+            .collect(),
+    };
+
+    let mut state = HashMap::with_capacity(ids.len());
+    for id in ids {
+        if let Some(local) = local_state_of(pool, kind, &id).await? {
+            state.insert(id, local);
+        }
+    }
+    Ok(state)
+}
+
+/// One record's current state, or `None` if it doesn't exist here.
+pub(crate) async fn local_state_of(
+    pool: &SqlitePool,
+    kind: RecordKind,
+    id: &str,
+) -> Result<Option<Local>, String> {
+    Ok(load(pool, kind, id).await?.map(|(updated_at, data)| Local {
+        updated_at,
+        fingerprint: fingerprint(kind, &data),
+    }))
+}
+
+async fn apply_conversation(
+    pool: &SqlitePool,
+    id: &str,
+    updated_at: i64,
+    data: &Value,
+) -> Result<(), String> {
+    // `id` is the record's, not part of the data
+    let mut data = data.clone();
+    if let Some(map) = data.as_object_mut() {
+        map.insert("id".to_string(), Value::from(id));
+    }
+    let conversation: Conversation = serde_json::from_value(data)
+        .map_err(|e| format!("Invalid synced conversation {}: {}", id, e))?;
+    chat::validate_conversation(&conversation)?;
+
+    let message_ids: Vec<&str> = conversation
+        .messages
+        .iter()
+        .map(|m| m.id.as_str())
+        .collect();
+    let message_ids = serde_json::to_string(&message_ids)
+        .map_err(|e| format!("Failed to serialize message ids: {}", e))?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    sqlx::query(
+        "INSERT INTO conversations (id, title, created_at, updated_at, pinned, archived_at, deleted_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         ON CONFLICT(id) DO UPDATE SET title = ?2, created_at = ?3, pinned = ?5,
+             archived_at = ?6, deleted_at = ?7",
+    )
+    .bind(id)
+    .bind(&conversation.title)
+    .bind(conversation.created_at)
+    .bind(updated_at)
+    .bind(conversation.pinned)
+    .bind(conversation.archived_at)
+    .bind(conversation.deleted_at)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to save conversation: {}", e))?;
+
+    sqlx::query(
+        "DELETE FROM messages WHERE conversation_id = ?
+         AND id NOT IN (SELECT value FROM json_each(?))",
+    )
+    .bind(id)
+    .bind(&message_ids)
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to remove messages: {}", e))?;
+
+    for message in &conversation.messages {
+        sqlx::query(
+            "INSERT INTO messages
+                 (id, conversation_id, role, content, timestamp, attached_files, truncated)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT(id) DO UPDATE SET conversation_id = ?2, role = ?3, content = ?4,
+                 timestamp = ?5, attached_files = ?6, truncated = ?7
+             WHERE messages.content IS NOT ?4 OR messages.timestamp IS NOT ?5
+                OR messages.role IS NOT ?3 OR messages.attached_files IS NOT ?6
+                OR messages.conversation_id IS NOT ?2 OR messages.truncated IS NOT ?7",
+        )
+        .bind(&message.id)
+        .bind(id)
+        .bind(message.role.as_str())
+        .bind(&message.content)
+        .bind(message.timestamp)
+        .bind(
+            message
+                .attached_files
+                .as_ref()
+                .map(|files| files.to_string()),
+        )
+        .bind(message.truncated)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to save message {}: {}", message.id, e))?;
+    }
+
+    // The message triggers moved `updated_at`; put back the synced one
+    sqlx::query("UPDATE conversations SET updated_at = ? WHERE id = ?")
+        .bind(updated_at)
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to save conversation: {}", e))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit conversation: {}", e))
+}
+
+async fn apply_prompt(
+    pool: &SqlitePool,
+    id: &str,
+    updated_at: i64,
+    data: &Value,
+) -> Result<(), String> {
+    let prompt: PromptData = serde_json::from_value(data.clone())
+        .map_err(|e| format!("Invalid synced prompt {}: {}", id, e))?;
+    // Setting `updated_at` explicitly keeps the timestamp trigger out of it
+    let updated = sqlx::query(
+        "UPDATE system_prompts
+         SET name = ?, prompt = ?, created_at = ?, updated_at = datetime(? / 1000, 'unixepoch')
+         WHERE sync_id = ?",
+    )
+    .bind(&prompt.name)
+    .bind(&prompt.prompt)
+    .bind(&prompt.created_at)
+    .bind(updated_at)
+    .bind(id)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to save system prompt: {}", e))?;
+    if updated.rows_affected() == 0 {
+        sqlx::query(
+            "INSERT INTO system_prompts (name, prompt, created_at, updated_at, sync_id)
+             VALUES (?, ?, ?, datetime(? / 1000, 'unixepoch'), ?)",
+        )
+        .bind(&prompt.name)
+        .bind(&prompt.prompt)
+        .bind(&prompt.created_at)
+        .bind(updated_at)
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to save system prompt: {}", e))?;
+    }
+    Ok(())
+}
+
+/// The sync id of a local prompt with `fingerprint`, if there is one.
+pub(crate) async fn find_prompt(
+    pool: &SqlitePool,
+    fingerprint: &str,
+) -> Result<Option<String>, String> {
+    Ok(local_state(pool, RecordKind::Prompt)
+        .await?
+        .into_iter()
+        .filter(|(_, local)| local.fingerprint == fingerprint)
+        .map(|(id, _)| id)
+        .min())
+}
+
+pub(crate) async fn set_prompt_sync_id(
+    pool: &SqlitePool,
+    from: &str,
+    to: &str,
+) -> Result<(), String> {
+    sqlx::query("UPDATE system_prompts SET sync_id = ? WHERE sync_id = ?")
+        .bind(to)
+        .bind(from)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to update system prompt: {}", e))?;
+    Ok(())
+}
+
+/// Save `data` as a new prompt named "<name> (conflicted copy from
+/// <device>)", with a sync id of its own.
+pub(crate) async fn insert_prompt_copy(
+    pool: &SqlitePool,
+    data: &Value,
+    device_name: &str,
+) -> Result<(), String> {
+    let prompt: PromptData = serde_json::from_value(data.clone())
+        .map_err(|e| format!("Invalid synced prompt: {}", e))?;
+    sqlx::query("INSERT INTO system_prompts (name, prompt) VALUES (?, ?)")
+        .bind(format!(
+            "{} (conflicted copy from {})",
+            prompt.name, device_name
+        ))
+        .bind(&prompt.prompt)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to save system prompt: {}", e))?;
+    Ok(())
+}
+
+async fn apply_setting(
+    pool: &SqlitePool,
+    key: &str,
+    updated_at: i64,
+    value: &Value,
+) -> Result<(), String> {
+    sqlx::query(
+        "INSERT INTO settings (key, value, updated_at) VALUES (?, ?, ?)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+    )
+    .bind(key)
+    .bind(value.to_string())
+    .bind(updated_at)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to save setting {}: {}", key, e))?;
+    Ok(())
+}
+
+async fn delete(pool: &SqlitePool, kind: RecordKind, id: &str) -> Result<(), String> {
+    let sql = match kind {
+        RecordKind::Conversation => {
+            chat::purge(pool, id).await?;
+            return Ok(());
+        }
+        RecordKind::Prompt => "DELETE FROM system_prompts WHERE sync_id = ?",
+        RecordKind::Setting => "DELETE FROM settings WHERE key = ?",
+    };
+    sqlx::query(sql)
+        .bind(id)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to delete synced {}: {}", kind.as_str(), e))?;
+    Ok(())
+}
+
+/// Make this database hold `record`'s version.
+pub(crate) async fn apply(pool: &SqlitePool, record: &Record) -> Result<(), String> {
+    if record.kind == RecordKind::Setting {
+        crate::settings::check_writable(&record.id)?;
+    }
+    let data = match (&record.data, record.deleted) {
+        (_, true) => return delete(pool, record.kind, &record.id).await,
+        (Some(data), false) => data,
+        (None, false) => {
+            return Err(format!(
+                "Synced {} {} has no data",
+                record.kind.as_str(),
+                record.id
+            ))
+        }
+    };
+    match record.kind {
+        RecordKind::Conversation => {
+            apply_conversation(pool, &record.id, record.updated_at, data).await
+        }
+        RecordKind::Prompt => apply_prompt(pool, &record.id, record.updated_at, data).await,
+        RecordKind::Setting => apply_setting(pool, &record.id, record.updated_at, data).await,
+    }
+}
+
+// ============================================================================
+// Sync state
+// ============================================================================
+
+pub(crate) async fn synced_state(
+    pool: &SqlitePool,
+    kind: RecordKind,
+) -> Result<HashMap<String, Synced>, String> {
+    let rows = sqlx::query_as::<_, Synced>(
+        "SELECT record_id, updated_at, fingerprint, deleted FROM sync_records WHERE kind = ?",
+    )
+    .bind(kind.as_str())
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to read sync state: {}", e))?;
+    Ok(rows
+        .into_iter()
+        .map(|row| (row.record_id.clone(), row))
+        .collect())
+}
+
+/// Forget what was exchanged, for a new folder or remote: every record is
+/// exported again and every change set read again.
+pub(crate) async fn reset(pool: &SqlitePool) -> Result<(), String> {
+    sqlx::raw_sql("DELETE FROM sync_records; DELETE FROM sync_devices;")
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to reset sync state: {}", e))?;
+    Ok(())
+}
+
+pub(crate) async fn synced_state_of(
+    pool: &SqlitePool,
+    kind: RecordKind,
+    id: &str,
+) -> Result<Option<Synced>, String> {
+    sqlx::query_as::<_, Synced>(
+        "SELECT record_id, updated_at, fingerprint, deleted FROM sync_records
+         WHERE kind = ? AND record_id = ?",
+    )
+    .bind(kind.as_str())
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to read sync state: {}", e))
+}
+
+pub(crate) async fn mark_synced(
+    pool: &SqlitePool,
+    kind: RecordKind,
+    id: &str,
+    updated_at: i64,
+    fingerprint: &str,
+    deleted: bool,
+) -> Result<(), String> {
+    sqlx::query(
+        "INSERT INTO sync_records (kind, record_id, updated_at, fingerprint, deleted)
+         VALUES (?, ?, ?, ?, ?)
+         ON CONFLICT(kind, record_id) DO UPDATE SET updated_at = excluded.updated_at,
+             fingerprint = excluded.fingerprint, deleted = excluded.deleted",
+    )
+    .bind(kind.as_str())
+    .bind(id)
+    .bind(updated_at)
+    .bind(fingerprint)
+    .bind(deleted)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to save sync state: {}", e))?;
+    Ok(())
+}
+
+/// The last change set read from `device_id`, or written by it for this
+/// device.
+pub(crate) async fn device_seq(pool: &SqlitePool, device_id: &str) -> Result<i64, String> {
+    let seq: Option<i64> =
+        sqlx::query_scalar("SELECT last_seq FROM sync_devices WHERE device_id = ?")
+            .bind(device_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(|e| format!("Failed to read sync devices: {}", e))?;
+    Ok(seq.unwrap_or(0))
+}
+
+pub(crate) async fn set_device_seq(
+    pool: &SqlitePool,
+    device_id: &str,
+    device_name: &str,
+    seq: i64,
+    now: i64,
+) -> Result<(), String> {
+    sqlx::query(
+        "INSERT INTO sync_devices (device_id, device_name, last_seq, synced_at)
+         VALUES (?, ?, ?, ?)
+         ON CONFLICT(device_id) DO UPDATE SET device_name = excluded.device_name,
+             last_seq = excluded.last_seq, synced_at = excluded.synced_at",
+    )
+    .bind(device_id)
+    .bind(device_name)
+    .bind(seq)
+    .bind(now)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to save sync device: {}", e))?;
+    Ok(())
+}
+
+pub(crate) struct NewConflict<'a> {
+    pub kind: RecordKind,
+    pub record_id: &'a str,
+    pub device_name: &'a str,
+    pub kept_remote: bool,
+    pub local_updated_at: Option<i64>,
+    pub remote_updated_at: i64,
+    pub discarded: Option<&'a Value>,
+}
+
+pub(crate) async fn log_conflict(
+    pool: &SqlitePool,
+    conflict: NewConflict<'_>,
+    now: i64,
+) -> Result<(), String> {
+    sqlx::query(
+        "INSERT INTO sync_conflicts (kind, record_id, device_name, kept, local_updated_at,
+             remote_updated_at, discarded, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(conflict.kind.as_str())
+    .bind(conflict.record_id)
+    .bind(conflict.device_name)
+    .bind(if conflict.kept_remote {
+        "remote"
+    } else {
+        "local"
+    })
+    .bind(conflict.local_updated_at)
+    .bind(conflict.remote_updated_at)
+    .bind(conflict.discarded.map(|data| data.to_string()))
+    .bind(now)
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to log sync conflict: {}", e))?;
+    Ok(())
+}
+
+/// Logged conflicts, newest first.
+pub(crate) async fn list_conflicts(
+    pool: &SqlitePool,
+    limit: Option<u32>,
+) -> Result<Vec<SyncConflict>, String> {
+    sqlx::query_as::<_, SyncConflict>(
+        "SELECT id, kind, record_id, device_name, kept, local_updated_at, remote_updated_at,
+                discarded, created_at
+         FROM sync_conflicts ORDER BY created_at DESC, id DESC LIMIT ?",
+    )
+    .bind(limit.unwrap_or(DEFAULT_CONFLICT_LIMIT) as i64)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to list sync conflicts: {}", e))
+}
+
+pub(crate) async fn clear_conflicts(pool: &SqlitePool) -> Result<u64, String> {
+    let result = sqlx::query("DELETE FROM sync_conflicts")
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to clear sync conflicts: {}", e))?;
+    Ok(result.rows_affected())
+}
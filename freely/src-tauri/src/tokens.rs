@@ -0,0 +1,281 @@
+//! Token counting and context-window budgeting.
+//!
+//! OpenAI models are counted with their real tiktoken encodings. Other
+//! providers don't publish tokenizers, so their counts use `cl100k_base` and
+//! are flagged as estimates; that is close enough to keep prompts inside a
+//! budget with some headroom. Images are not counted.
+
+use crate::providers::{ChatMessage, ChatRole};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use tiktoken_rs::tokenizer::{get_tokenizer, Tokenizer};
+use tiktoken_rs::CoreBPE;
+
+static CL100K: Lazy<CoreBPE> =
+    Lazy::new(|| tiktoken_rs::cl100k_base().expect("embedded cl100k_base is valid"));
+static O200K: Lazy<CoreBPE> =
+    Lazy::new(|| tiktoken_rs::o200k_base().expect("embedded o200k_base is valid"));
+
+/// Chat framing per message (`<|start|>{role}\n{content}<|end|>\n`).
+const TOKENS_PER_MESSAGE: usize = 3;
+/// Every reply is primed with `<|start|>assistant<|message|>`.
+const REPLY_PRIMING_TOKENS: usize = 3;
+
+/// Prefixes of OpenAI models newer than the tiktoken-rs model table, all of
+/// which use `o200k_base`.
+const O200K_PREFIXES: &[&str] = &["gpt-4.1", "gpt-4.5", "gpt-5", "o1", "o3", "o4"];
+
+#[derive(Clone, Copy)]
+pub(crate) struct Encoding {
+    bpe: &'static Lazy<CoreBPE>,
+    name: &'static str,
+    exact: bool,
+}
+
+impl Encoding {
+    pub(crate) fn count(&self, text: &str) -> usize {
+        self.bpe.encode_ordinary(text).len()
+    }
+
+    /// Cut `text` to at most `max_tokens` tokens.
+    pub(crate) fn truncate(&self, text: &str, max_tokens: usize) -> String {
+        let mut tokens = self.bpe.encode_ordinary(text);
+        if tokens.len() <= max_tokens {
+            return text.to_string();
+        }
+        tokens.truncate(max_tokens);
+        // The cut may land inside a multi-byte character
+        while !tokens.is_empty() {
+            if let Ok(text) = self.bpe.decode(tokens.clone()) {
+                return text;
+            }
+            tokens.pop();
+        }
+        String::new()
+    }
+}
+
+/// Pick the encoding for `model`, which may carry a provider prefix such as
+/// `openai/gpt-4o` (OpenRouter style).
+pub(crate) fn encoding_for(model: &str) -> Encoding {
+    let name = model.rsplit('/').next().unwrap_or(model).trim();
+    let tokenizer = get_tokenizer(name).or_else(|| {
+        O200K_PREFIXES
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+            .then_some(Tokenizer::O200kBase)
+    });
+
+    match tokenizer {
+        Some(Tokenizer::O200kBase) => Encoding {
+            bpe: &O200K,
+            name: "o200k_base",
+            exact: true,
+        },
+        Some(Tokenizer::Cl100kBase) => Encoding {
+            bpe: &CL100K,
+            name: "cl100k_base",
+            exact: true,
+        },
+        // Legacy completion models and non-OpenAI models
+        _ => Encoding {
+            bpe: &CL100K,
+            name: "cl100k_base",
+            exact: false,
+        },
+    }
+}
+
+fn role_name(role: ChatRole) -> &'static str {
+    match role {
+        ChatRole::System => "system",
+        ChatRole::User => "user",
+        ChatRole::Assistant => "assistant",
+    }
+}
+
+/// Tokens `message` takes up in a chat prompt, framing included.
+pub(crate) fn message_tokens(encoding: &Encoding, message: &ChatMessage) -> usize {
+    TOKENS_PER_MESSAGE + encoding.count(role_name(message.role)) + encoding.count(&message.content)
+}
+
+/// Tokens for a whole chat prompt, including the reply priming.
+pub(crate) fn prompt_tokens(encoding: &Encoding, messages: &[ChatMessage]) -> usize {
+    REPLY_PRIMING_TOKENS
+        + messages
+            .iter()
+            .map(|m| message_tokens(encoding, m))
+            .sum::<usize>()
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenCount {
+    pub tokens: usize,
+    pub encoding: String,
+    /// False when the model's own tokenizer isn't available and the count
+    /// is an estimate.
+    pub exact: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrimmedMessages {
+    pub messages: Vec<ChatMessage>,
+    /// Prompt size of `messages`, framing included.
+    pub tokens: usize,
+    /// How many of the oldest messages were dropped.
+    pub dropped: usize,
+    pub exact: bool,
+}
+
+/// Drop the oldest non-system messages until the prompt fits `budget`.
+///
+/// System messages and the final message are always kept; if those alone
+/// exceed the budget it is an error rather than a silently broken prompt.
+pub(crate) fn trim_to_budget(
+    encoding: &Encoding,
+    messages: Vec<ChatMessage>,
+    budget: usize,
+) -> Result<TrimmedMessages, String> {
+    let costs: Vec<usize> = messages
+        .iter()
+        .map(|m| message_tokens(encoding, m))
+        .collect();
+    let last = messages.len().saturating_sub(1);
+
+    let required: usize = REPLY_PRIMING_TOKENS
+        + messages
+            .iter()
+            .zip(&costs)
+            .enumerate()
+            .filter(|(idx, (m, _))| m.role == ChatRole::System || *idx == last)
+            .map(|(_, (_, cost))| cost)
+            .sum::<usize>();
+    if !messages.is_empty() && required > budget {
+        return Err(format!(
+            "System prompt and latest message need {} tokens, over the budget of {}",
+            required, budget
+        ));
+    }
+
+    // Walk back from the newest message, keeping whatever still fits
+    let mut keep = vec![false; messages.len()];
+    let mut used = required;
+    for idx in (0..messages.len()).rev() {
+        if messages[idx].role == ChatRole::System || idx == last {
+            keep[idx] = true;
+        } else if used + costs[idx] <= budget {
+            keep[idx] = true;
+            used += costs[idx];
+        } else {
+            // Stop at the first gap so the kept history stays contiguous
+            break;
+        }
+    }
+    // Older system messages past the gap are still kept
+    for (idx, message) in messages.iter().enumerate() {
+        if message.role == ChatRole::System {
+            keep[idx] = true;
+        }
+    }
+
+    let total = messages.len();
+    let kept: Vec<ChatMessage> = messages
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(m, keep)| keep.then_some(m))
+        .collect();
+    Ok(TrimmedMessages {
+        tokens: prompt_tokens(encoding, &kept),
+        dropped: total - kept.len(),
+        messages: kept,
+        exact: encoding.exact,
+    })
+}
+
+/// Count the tokens in `text` for `model`.
+#[tauri::command]
+pub async fn count_tokens(model: String, text: String) -> Result<TokenCount, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let encoding = encoding_for(&model);
+        TokenCount {
+            tokens: encoding.count(&text),
+            encoding: encoding.name.to_string(),
+            exact: encoding.exact,
+        }
+    })
+    .await
+    .map_err(|e| format!("Token counting failed: {}", e))
+}
+
+/// Trim a conversation so the prompt fits in `max_tokens`, dropping the
+/// oldest turns first.
+#[tauri::command]
+pub async fn trim_messages_to_budget(
+    model: String,
+    messages: Vec<ChatMessage>,
+    max_tokens: usize,
+) -> Result<TrimmedMessages, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        trim_to_budget(&encoding_for(&model), messages, max_tokens)
+    })
+    .await
+    .map_err(|e| format!("Token counting failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(role: ChatRole, content: &str) -> ChatMessage {
+        ChatMessage {
+            role,
+            content: content.to_string(),
+            images: vec![],
+        }
+    }
+
+    #[test]
+    fn encodings_follow_the_model() {
+        assert_eq!(encoding_for("gpt-4o-mini").name, "o200k_base");
+        assert_eq!(encoding_for("openai/gpt-4.1").name, "o200k_base");
+        assert!(encoding_for("gpt-4-turbo-preview").exact);
+
+        let claude = encoding_for("claude-3-5-sonnet-latest");
+        assert_eq!(claude.name, "cl100k_base");
+        assert!(!claude.exact);
+    }
+
+    #[test]
+    fn counts_match_tiktoken() {
+        let encoding = encoding_for("gpt-4");
+        assert_eq!(encoding.count("hello world"), 2);
+        // Matches the OpenAI cookbook's per-message framing
+        let messages = [message(ChatRole::User, "hello world")];
+        assert_eq!(prompt_tokens(&encoding, &messages), 3 + 1 + 2 + 3);
+    }
+
+    #[test]
+    fn trimming_drops_oldest_turns_but_keeps_system_and_latest() {
+        let encoding = encoding_for("gpt-4o");
+        let messages = vec![
+            message(ChatRole::System, "You are helpful."),
+            message(ChatRole::User, &"old question ".repeat(50)),
+            message(ChatRole::Assistant, "old answer"),
+            message(ChatRole::User, "new question"),
+        ];
+        let full = prompt_tokens(&encoding, &messages);
+
+        let untouched = trim_to_budget(&encoding, messages.clone(), full).unwrap();
+        assert_eq!((untouched.dropped, untouched.tokens), (0, full));
+
+        let trimmed = trim_to_budget(&encoding, messages.clone(), full - 1).unwrap();
+        assert_eq!(trimmed.dropped, 1);
+        assert_eq!(trimmed.messages[0].role, ChatRole::System);
+        assert_eq!(trimmed.messages[1].content, "old answer");
+        assert!(trimmed.tokens < full);
+
+        assert!(trim_to_budget(&encoding, messages, 5).is_err());
+    }
+}
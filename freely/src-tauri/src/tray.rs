@@ -0,0 +1,217 @@
+//! System tray icon with quick actions.
+//!
+//! The icon gains a red dot while any audio capture is running so users can
+//! tell at a glance when the microphone is hot. Native capture is tracked by
+//! listening to its start/stop events; recording driven by the webview
+//! reports itself through `set_recording_indicator`.
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+use tauri::image::Image;
+use tauri::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+use tauri::{AppHandle, Emitter, Listener, Manager};
+use tracing::warn;
+
+const TRAY_ID: &str = "main";
+const TOOLTIP: &str = "Freely";
+const TOOLTIP_RECORDING: &str = "Freely — recording";
+
+/// Capture events that switch the indicator, with the source they belong to.
+const CAPTURE_EVENTS: &[(&str, &str, bool)] = &[
+    ("microphone-capture-started", "microphone", true),
+    ("microphone-capture-stopped", "microphone", false),
+    ("continuous-recording-start", "system_audio", true),
+    ("continuous-recording-stopped", "system_audio", false),
+];
+
+/// Source name used for recording reported by the frontend.
+const FRONTEND_SOURCE: &str = "frontend";
+
+#[derive(Default)]
+pub struct TrayState {
+    active_sources: Mutex<HashSet<String>>,
+    recording_item: Mutex<Option<MenuItem<tauri::Wry>>>,
+    idle_icon: Mutex<Option<Image<'static>>>,
+    recording_icon: Mutex<Option<Image<'static>>>,
+}
+
+/// Paint a red dot into the bottom-right corner of an RGBA icon.
+pub(crate) fn with_recording_dot(rgba: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let mut out = rgba.to_vec();
+    let radius = (width.min(height) as f32 * 0.22).max(1.0);
+    let cx = width as f32 - radius - 1.0;
+    let cy = height as f32 - radius - 1.0;
+
+    for y in 0..height {
+        for x in 0..width {
+            let dx = x as f32 + 0.5 - cx;
+            let dy = y as f32 + 0.5 - cy;
+            if dx * dx + dy * dy <= radius * radius {
+                let i = ((y * width + x) * 4) as usize;
+                if let Some(px) = out.get_mut(i..i + 4) {
+                    px.copy_from_slice(&[0xE5, 0x39, 0x35, 0xFF]);
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Build the tray icon. Called once from `setup`.
+pub fn create_tray(app: &AppHandle) -> tauri::Result<()> {
+    let show_hide = MenuItem::with_id(app, "show_hide", "Show/Hide Freely", true, None::<&str>)?;
+    let new_chat = MenuItem::with_id(app, "new_chat", "New Chat", true, None::<&str>)?;
+    let recording = MenuItem::with_id(
+        app,
+        "toggle_recording",
+        "Start Recording",
+        true,
+        None::<&str>,
+    )?;
+    let quit = MenuItem::with_id(app, "quit", "Quit Freely", true, None::<&str>)?;
+    let menu = Menu::with_items(
+        app,
+        &[
+            &show_hide,
+            &new_chat,
+            &recording,
+            &PredefinedMenuItem::separator(app)?,
+            &quit,
+        ],
+    )?;
+
+    let state = app.state::<TrayState>();
+    if let Some(icon) = app.default_window_icon() {
+        let recording_rgba = with_recording_dot(icon.rgba(), icon.width(), icon.height());
+        *lock(&state.recording_icon) = Some(Image::new_owned(
+            recording_rgba,
+            icon.width(),
+            icon.height(),
+        ));
+        *lock(&state.idle_icon) = Some(icon.clone().to_owned());
+    }
+    *lock(&state.recording_item) = Some(recording);
+
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip(TOOLTIP)
+        .menu(&menu)
+        .show_menu_on_left_click(false)
+        .on_menu_event(handle_menu_event)
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                crate::shortcuts::handle_shortcut_action(tray.app_handle(), "toggle_window");
+            }
+        });
+    if let Some(icon) = lock(&state.idle_icon).clone() {
+        builder = builder.icon(icon);
+    }
+    builder.build(app)?;
+
+    for &(event, source, active) in CAPTURE_EVENTS {
+        let handle = app.clone();
+        app.listen_any(event, move |_| set_source_active(&handle, source, active));
+    }
+
+    Ok(())
+}
+
+fn handle_menu_event(app: &AppHandle, event: MenuEvent) {
+    match event.id().as_ref() {
+        "show_hide" => crate::shortcuts::handle_shortcut_action(app, "toggle_window"),
+        "new_chat" => {
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.show();
+                let _ = window.set_focus();
+                if let Err(e) = window.emit("new-chat", ()) {
+                    warn!("Failed to emit new-chat: {}", e);
+                }
+            }
+        }
+        // Same path as the voice input shortcut; the frontend toggles
+        "toggle_recording" => crate::shortcuts::handle_shortcut_action(app, "audio_recording"),
+        "quit" => app.exit(0),
+        _ => {}
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn set_source_active(app: &AppHandle, source: &str, active: bool) {
+    let state = app.state::<TrayState>();
+    let recording = {
+        let mut sources = lock(&state.active_sources);
+        if active {
+            sources.insert(source.to_string());
+        } else {
+            sources.remove(source);
+        }
+        !sources.is_empty()
+    };
+
+    let icon = if recording {
+        lock(&state.recording_icon).clone()
+    } else {
+        lock(&state.idle_icon).clone()
+    };
+    let label = if recording {
+        "Stop Recording"
+    } else {
+        "Start Recording"
+    };
+    if let Some(item) = lock(&state.recording_item).as_ref() {
+        if let Err(e) = item.set_text(label) {
+            warn!("Failed to update tray menu: {}", e);
+        }
+    }
+
+    if let Some(tray) = app.tray_by_id(TRAY_ID) {
+        if let Err(e) = tray.set_icon(icon) {
+            warn!("Failed to update tray icon: {}", e);
+        }
+        let tooltip = if recording {
+            TOOLTIP_RECORDING
+        } else {
+            TOOLTIP
+        };
+        if let Err(e) = tray.set_tooltip(Some(tooltip)) {
+            warn!("Failed to update tray tooltip: {}", e);
+        }
+    }
+}
+
+/// Report webview-driven recording so the tray indicator reflects it.
+#[tauri::command]
+pub fn set_recording_indicator(app: AppHandle, active: bool) -> Result<(), String> {
+    set_source_active(&app, FRONTEND_SOURCE, active);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recording_dot_paints_bottom_right_only() {
+        let (w, h) = (32u32, 32u32);
+        let rgba = vec![0u8; (w * h * 4) as usize];
+        let out = with_recording_dot(&rgba, w, h);
+
+        let px = |x: u32, y: u32| {
+            let i = ((y * w + x) * 4) as usize;
+            out[i..i + 4].to_vec()
+        };
+        assert_eq!(px(0, 0), vec![0, 0, 0, 0]);
+        assert_eq!(px(w - 6, h - 6), vec![0xE5, 0x39, 0x35, 0xFF]);
+        assert_eq!(out.len(), rgba.len());
+    }
+}
@@ -0,0 +1,622 @@
+//! Reading assistant responses aloud.
+//!
+//! The system backend uses the platform voices through the `tts` crate:
+//! AVSpeechSynthesizer on macOS, Windows.Media.SpeechSynthesis on Windows
+//! and speech-dispatcher on Linux. The provider backend sends the text to
+//! OpenAI's `/audio/speech` (or a compatible server) in chunks and plays
+//! the returned PCM on the default output device, so a long answer starts
+//! playing after the first chunk. Which one is used, and the default voice,
+//! is the `tts` setting.
+//!
+//! Markdown is reduced to plain sentences first and code blocks are
+//! skipped. Starting a new utterance stops the current one. Playback is
+//! reported as `tts-playback` events: `started`, `progress` (provider
+//! backend only), then `finished` or `stopped`.
+
+use crate::providers::ProviderKind;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SizedSample};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tracing::{error, warn};
+
+pub(crate) const CONFIG_SETTING_KEY: &str = "tts";
+/// `/audio/speech` takes at most 4096 characters per request.
+const MAX_CHUNK_CHARS: usize = 4_000;
+/// Raw PCM from `/audio/speech` is 24 kHz, 16-bit, mono.
+const PROVIDER_SAMPLE_RATE: u32 = 24_000;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+const MIN_RATE: f32 = 0.5;
+const MAX_RATE: f32 = 2.0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TtsBackend {
+    #[default]
+    System,
+    /// OpenAI's speech endpoint, or a server with the same API.
+    Provider,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TtsConfig {
+    pub backend: TtsBackend,
+    /// Voice used when `speak` isn't given one: a system voice ID, or a
+    /// provider voice such as "alloy".
+    pub voice: Option<String>,
+    /// Provider backend only.
+    pub model: String,
+    pub base_url: Option<String>,
+    pub api_key_name: Option<String>,
+}
+
+impl Default for TtsConfig {
+    fn default() -> Self {
+        Self {
+            backend: TtsBackend::System,
+            voice: None,
+            model: "gpt-4o-mini-tts".to_string(),
+            base_url: None,
+            api_key_name: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TtsVoice {
+    pub id: String,
+    pub name: String,
+    /// BCP-47 tag, e.g. "en-US".
+    pub language: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum PlaybackState {
+    Started,
+    Progress,
+    Finished,
+    Stopped,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PlaybackEvent<'a> {
+    id: &'a str,
+    state: PlaybackState,
+    /// Fraction played in [0, 1], when known.
+    progress: Option<f64>,
+}
+
+fn emit_playback(app: &AppHandle, id: &str, state: PlaybackState, progress: Option<f64>) {
+    let event = PlaybackEvent {
+        id,
+        state,
+        progress,
+    };
+    if let Err(e) = app.emit("tts-playback", event) {
+        warn!("Failed to emit tts-playback: {}", e);
+    }
+}
+
+struct Playback {
+    id: String,
+    backend: TtsBackend,
+    stop: Arc<AtomicBool>,
+}
+
+static PLAYBACK: Lazy<Mutex<Option<Playback>>> = Lazy::new(|| Mutex::new(None));
+/// Created on first use; the platform engine is slow to start.
+static SYSTEM: Lazy<Mutex<Option<tts::Tts>>> = Lazy::new(|| Mutex::new(None));
+/// Playback IDs of the utterances handed to the system engine, oldest
+/// first. Each utterance ends or is stopped exactly once, in order.
+static SYSTEM_QUEUE: Lazy<Mutex<VecDeque<String>>> = Lazy::new(|| Mutex::new(VecDeque::new()));
+
+/// Markdown reduced to what is worth saying: code blocks, markup and link
+/// targets are dropped.
+fn speakable(markdown: &str) -> String {
+    let mut lines = Vec::new();
+    let mut in_code = false;
+    for line in markdown.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code || trimmed.is_empty() {
+            continue;
+        }
+        let trimmed = trimmed
+            .trim_start_matches(['#', '>'])
+            .trim_start_matches(['-', '*', '+'])
+            .trim();
+        let mut text = String::with_capacity(trimmed.len());
+        let mut rest = trimmed;
+        // [label](target) -> label
+        while let Some(open) = rest.find('[') {
+            let Some(close) = rest[open..].find("](").map(|i| open + i) else {
+                break;
+            };
+            let Some(end) = rest[close..].find(')').map(|i| close + i) else {
+                break;
+            };
+            text.push_str(&rest[..open]);
+            text.push_str(&rest[open + 1..close]);
+            rest = &rest[end + 1..];
+        }
+        text.push_str(rest);
+        let text: String = text
+            .chars()
+            .filter(|c| !matches!(c, '*' | '_' | '`' | '~'))
+            .collect();
+        if !text.trim().is_empty() {
+            lines.push(text.trim().to_string());
+        }
+    }
+    lines.join("\n")
+}
+
+/// Split `text` into requests of at most `max_chars`, preferring sentence
+/// and line ends.
+fn chunks(text: &str, max_chars: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    for sentence in text.split_inclusive(['.', '!', '?', '\n']) {
+        if !current.is_empty() && current.chars().count() + sentence.chars().count() > max_chars {
+            chunks.push(std::mem::take(&mut current));
+        }
+        current.push_str(sentence);
+        // A single sentence longer than a request is cut where it must be
+        while current.chars().count() > max_chars {
+            let split = current
+                .char_indices()
+                .nth(max_chars)
+                .map(|(i, _)| i)
+                .unwrap_or(current.len());
+            let rest = current.split_off(split);
+            chunks.push(std::mem::replace(&mut current, rest));
+        }
+    }
+    if !current.trim().is_empty() {
+        chunks.push(current);
+    }
+    chunks
+        .into_iter()
+        .map(|chunk| chunk.trim().to_string())
+        .filter(|chunk| !chunk.is_empty())
+        .collect()
+}
+
+async fn load_config(app: &AppHandle) -> Result<TtsConfig, String> {
+    let pool = crate::db::pool(app).await?;
+    Ok(crate::db::settings::get(&pool, CONFIG_SETTING_KEY)
+        .await?
+        .unwrap_or_default())
+}
+
+/// Stop whatever is playing.
+fn stop_current() {
+    let Some(playback) = PLAYBACK.lock().take() else {
+        return;
+    };
+    playback.stop.store(true, Ordering::Relaxed);
+    if playback.backend == TtsBackend::System {
+        if let Some(engine) = SYSTEM.lock().as_mut() {
+            if let Err(e) = engine.stop() {
+                warn!("Failed to stop speech: {}", e);
+            }
+        }
+    }
+}
+
+/// Playback `id` is over: forget it if it is still the current one, and
+/// emit `state`.
+fn finish(app: &AppHandle, id: &str, state: PlaybackState) {
+    {
+        let mut playback = PLAYBACK.lock();
+        if playback.as_ref().is_some_and(|p| p.id == id) {
+            *playback = None;
+        }
+    }
+    emit_playback(app, id, state, None);
+}
+
+// ============================================================================
+// System voices
+// ============================================================================
+
+fn system_callback(app: &AppHandle, state: PlaybackState) -> Box<dyn FnMut(tts::UtteranceId)> {
+    let app = app.clone();
+    Box::new(move |_| {
+        let id = SYSTEM_QUEUE.lock().pop_front();
+        if let Some(id) = id {
+            finish(&app, &id, state);
+        }
+    })
+}
+
+fn with_system<T>(
+    app: &AppHandle,
+    f: impl FnOnce(&mut tts::Tts) -> Result<T, String>,
+) -> Result<T, String> {
+    let mut system = SYSTEM.lock();
+    let engine = match system.as_mut() {
+        Some(engine) => engine,
+        None => {
+            let engine =
+                tts::Tts::default().map_err(|e| format!("Speech is unavailable: {}", e))?;
+            // Engines without callbacks still speak; they just never
+            // report finishing
+            if engine.supported_features().utterance_callbacks {
+                let ended = system_callback(app, PlaybackState::Finished);
+                let stopped = system_callback(app, PlaybackState::Stopped);
+                if let Err(e) = engine
+                    .on_utterance_end(Some(ended))
+                    .and_then(|_| engine.on_utterance_stop(Some(stopped)))
+                {
+                    warn!("Failed to watch speech playback: {}", e);
+                }
+            }
+            system.insert(engine)
+        }
+    };
+    f(engine)
+}
+
+fn speak_system(
+    app: &AppHandle,
+    id: &str,
+    text: &str,
+    voice: Option<&str>,
+    rate: f32,
+) -> Result<(), String> {
+    with_system(app, |engine| {
+        if let Some(voice) = voice {
+            let voices = engine
+                .voices()
+                .map_err(|e| format!("Failed to list voices: {}", e))?;
+            let voice = voices
+                .iter()
+                .find(|v| v.id() == voice || v.name() == voice)
+                .ok_or_else(|| format!("Voice not found: {}", voice))?;
+            engine
+                .set_voice(voice)
+                .map_err(|e| format!("Failed to set voice: {}", e))?;
+        }
+        // `rate` is relative to normal speed; engines use their own scales
+        let scaled = if rate >= 1.0 {
+            engine.normal_rate()
+                + (engine.max_rate() - engine.normal_rate()) * (rate - 1.0) / (MAX_RATE - 1.0)
+        } else {
+            engine.normal_rate()
+                - (engine.normal_rate() - engine.min_rate()) * (1.0 - rate) / (1.0 - MIN_RATE)
+        };
+        engine
+            .set_rate(scaled)
+            .map_err(|e| format!("Failed to set speech rate: {}", e))?;
+
+        SYSTEM_QUEUE.lock().push_back(id.to_string());
+        if let Err(e) = engine.speak(text, true) {
+            SYSTEM_QUEUE.lock().retain(|queued| queued != id);
+            return Err(format!("Failed to speak: {}", e));
+        }
+        Ok(())
+    })
+}
+
+// ============================================================================
+// Provider voices
+// ============================================================================
+
+async fn synthesize(
+    app: &AppHandle,
+    config: &TtsConfig,
+    text: &str,
+    voice: &str,
+    rate: f32,
+) -> Result<Vec<f32>, String> {
+    let key_name = config.api_key_name.as_deref().unwrap_or("openai");
+    let api_key = crate::secrets::load_api_key(app, key_name)
+        .await?
+        .ok_or_else(|| format!("No API key stored for {}", key_name))?;
+    let base_url = config
+        .base_url
+        .as_deref()
+        .unwrap_or(crate::providers::openai::DEFAULT_BASE_URL)
+        .trim_end_matches('/');
+    let body = serde_json::json!({
+        "model": config.model,
+        "input": text,
+        "voice": voice,
+        "response_format": "pcm",
+        "speed": rate,
+    });
+    let response = crate::net::client::http_client(Some(ProviderKind::OpenAi))?
+        .post(format!("{}/audio/speech", base_url))
+        .bearer_auth(api_key)
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to request speech: {}", e))?;
+    let status = response.status();
+    if !status.is_success() {
+        let detail = response.text().await.unwrap_or_default();
+        return Err(format!("Speech request failed ({}): {}", status, detail));
+    }
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to read speech audio: {}", e))?;
+    Ok(bytes
+        .chunks_exact(2)
+        .map(|pair| i16::from_le_bytes([pair[0], pair[1]]) as f32 / 32768.0)
+        .collect())
+}
+
+fn build_output<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    queue: Arc<Mutex<VecDeque<f32>>>,
+) -> Result<cpal::Stream, cpal::BuildStreamError>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    device.build_output_stream(
+        config,
+        move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+            let mut queue = queue.lock();
+            for sample in data.iter_mut() {
+                *sample = T::from_sample(queue.pop_front().unwrap_or(0.0));
+            }
+        },
+        |e| error!("Speech output error: {}", e),
+        None,
+    )
+}
+
+/// Play the chunks arriving on `rx` (mono, [`PROVIDER_SAMPLE_RATE`]) until
+/// the sender is dropped and everything is played, or `stop` is set.
+/// Returns whether playback ran to the end.
+fn play_chunks(
+    app: &AppHandle,
+    id: &str,
+    chunk_count: usize,
+    rx: mpsc::Receiver<Vec<f32>>,
+    stop: &AtomicBool,
+) -> Result<bool, String> {
+    let device = cpal::default_host()
+        .default_output_device()
+        .ok_or("No audio output device")?;
+    let supported = device
+        .default_output_config()
+        .map_err(|e| format!("Failed to get output config: {}", e))?;
+    let sample_format = supported.sample_format();
+    let config: cpal::StreamConfig = supported.into();
+    let channels = config.channels as usize;
+    let queue = Arc::new(Mutex::new(VecDeque::new()));
+    let stream = match sample_format {
+        cpal::SampleFormat::F32 => build_output::<f32>(&device, &config, queue.clone()),
+        cpal::SampleFormat::I16 => build_output::<i16>(&device, &config, queue.clone()),
+        cpal::SampleFormat::U16 => build_output::<u16>(&device, &config, queue.clone()),
+        cpal::SampleFormat::I32 => build_output::<i32>(&device, &config, queue.clone()),
+        other => return Err(format!("Unsupported sample format: {:?}", other)),
+    }
+    .map_err(|e| format!("Failed to open audio output: {}", e))?;
+    stream
+        .play()
+        .map_err(|e| format!("Failed to start audio output: {}", e))?;
+
+    for (index, chunk) in rx.iter().enumerate() {
+        let samples =
+            crate::stt::local::resample_linear(&chunk, PROVIDER_SAMPLE_RATE, config.sample_rate.0);
+        let total = samples.len().max(1) * channels;
+        queue.lock().extend(
+            samples
+                .iter()
+                .flat_map(|&s| std::iter::repeat_n(s, channels)),
+        );
+        loop {
+            if stop.load(Ordering::Relaxed) {
+                return Ok(false);
+            }
+            let left = queue.lock().len();
+            let played = (total - left.min(total)) as f64 / total as f64;
+            emit_playback(
+                app,
+                id,
+                PlaybackState::Progress,
+                Some((index as f64 + played) / chunk_count as f64),
+            );
+            if left == 0 {
+                break;
+            }
+            std::thread::sleep(PROGRESS_INTERVAL.min(Duration::from_millis(
+                (left / channels) as u64 * 1000 / config.sample_rate.0 as u64 + 10,
+            )));
+        }
+    }
+    Ok(!stop.load(Ordering::Relaxed))
+}
+
+fn speak_provider(
+    app: &AppHandle,
+    id: &str,
+    config: TtsConfig,
+    text: String,
+    voice: String,
+    rate: f32,
+    stop: Arc<AtomicBool>,
+) -> Result<(), String> {
+    let parts = chunks(&text, MAX_CHUNK_CHARS);
+    let (tx, rx) = mpsc::channel();
+
+    let (fetch_app, fetch_stop) = (app.clone(), stop.clone());
+    let chunk_count = parts.len().max(1);
+    tauri::async_runtime::spawn(async move {
+        for part in parts {
+            if fetch_stop.load(Ordering::Relaxed) {
+                return;
+            }
+            match synthesize(&fetch_app, &config, &part, &voice, rate).await {
+                Ok(samples) => {
+                    if tx.send(samples).is_err() {
+                        return;
+                    }
+                }
+                Err(e) => {
+                    warn!("{}", e);
+                    if let Err(e) = fetch_app.emit("tts-error", &e) {
+                        warn!("Failed to emit tts-error: {}", e);
+                    }
+                    // Stops the player once the fetched audio has played
+                    return;
+                }
+            }
+        }
+    });
+
+    let (play_app, play_id) = (app.clone(), id.to_string());
+    std::thread::Builder::new()
+        .name("tts-playback".to_string())
+        .spawn(move || {
+            let state = match play_chunks(&play_app, &play_id, chunk_count, rx, &stop) {
+                Ok(true) => PlaybackState::Finished,
+                Ok(false) => PlaybackState::Stopped,
+                Err(e) => {
+                    warn!("{}", e);
+                    if let Err(e) = play_app.emit("tts-error", &e) {
+                        warn!("Failed to emit tts-error: {}", e);
+                    }
+                    PlaybackState::Stopped
+                }
+            };
+            finish(&play_app, &play_id, state);
+        })
+        .map_err(|e| format!("Failed to spawn playback thread: {}", e))?;
+    Ok(())
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// Read `text` (Markdown) aloud, replacing anything already playing.
+/// `voice` falls back to the configured one; `rate` is relative to normal
+/// speed, from 0.5 to 2. Returns the playback ID used in `tts-playback`.
+#[tauri::command]
+pub async fn speak(
+    app: AppHandle,
+    text: String,
+    voice: Option<String>,
+    rate: Option<f32>,
+) -> Result<String, String> {
+    let text = speakable(&text);
+    if text.is_empty() {
+        return Err("Nothing to read aloud".to_string());
+    }
+    let config = load_config(&app).await?;
+    let voice = voice.or_else(|| config.voice.clone());
+    let rate = rate.unwrap_or(1.0).clamp(MIN_RATE, MAX_RATE);
+
+    // The previous playback reports `stopped` itself
+    stop_current();
+    let id = uuid::Uuid::new_v4().to_string();
+    let stop = Arc::new(AtomicBool::new(false));
+    *PLAYBACK.lock() = Some(Playback {
+        id: id.clone(),
+        backend: config.backend,
+        stop: stop.clone(),
+    });
+    emit_playback(&app, &id, PlaybackState::Started, Some(0.0));
+
+    let started = match config.backend {
+        TtsBackend::System => {
+            let (app, id) = (app.clone(), id.clone());
+            tokio::task::spawn_blocking(move || {
+                speak_system(&app, &id, &text, voice.as_deref(), rate)
+            })
+            .await
+            .map_err(|e| format!("Speech task failed: {}", e))?
+        }
+        TtsBackend::Provider => {
+            let voice = voice.unwrap_or_else(|| "alloy".to_string());
+            speak_provider(&app, &id, config, text, voice, rate, stop)
+        }
+    };
+    if let Err(e) = started {
+        finish(&app, &id, PlaybackState::Stopped);
+        return Err(e);
+    }
+    Ok(id)
+}
+
+/// Stop reading aloud. Does nothing when nothing is playing.
+#[tauri::command]
+pub fn stop_speaking() -> Result<(), String> {
+    stop_current();
+    Ok(())
+}
+
+/// Voices of the platform engine.
+#[tauri::command]
+pub async fn list_system_voices(app: AppHandle) -> Result<Vec<TtsVoice>, String> {
+    tokio::task::spawn_blocking(move || {
+        with_system(&app, |engine| {
+            let voices = engine
+                .voices()
+                .map_err(|e| format!("Failed to list voices: {}", e))?;
+            Ok(voices
+                .into_iter()
+                .map(|voice| TtsVoice {
+                    id: voice.id(),
+                    name: voice.name(),
+                    language: voice.language().to_string(),
+                })
+                .collect())
+        })
+    })
+    .await
+    .map_err(|e| format!("Speech task failed: {}", e))?
+}
+
+#[tauri::command]
+pub async fn get_tts_config(app: AppHandle) -> Result<TtsConfig, String> {
+    load_config(&app).await
+}
+
+#[tauri::command]
+pub async fn set_tts_config(app: AppHandle, config: TtsConfig) -> Result<(), String> {
+    if config.backend == TtsBackend::Provider && config.model.trim().is_empty() {
+        return Err("A speech model is required for the provider backend".to_string());
+    }
+    let pool = crate::db::pool(&app).await?;
+    crate::db::settings::set(&pool, CONFIG_SETTING_KEY, &config).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markdown_is_reduced_to_speech() {
+        let markdown = "## Fix\n\nUse **`cargo fmt`** first, see [the guide](https://x.y/z).\n\n```sh\ncargo fmt\n```\n- Then commit.";
+        assert_eq!(
+            speakable(markdown),
+            "Fix\nUse cargo fmt first, see the guide.\nThen commit."
+        );
+
+        let long = "One two. ".repeat(10);
+        let parts = chunks(&long, 20);
+        assert!(parts.iter().all(|part| part.chars().count() <= 20));
+        assert_eq!(parts.join(" "), long.trim());
+        assert_eq!(chunks(&"x".repeat(45), 20).len(), 3);
+    }
+}
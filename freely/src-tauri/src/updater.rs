@@ -0,0 +1,213 @@
+//! Update checks and deferred installs through `tauri-plugin-updater`.
+//!
+//! `check_for_update` remembers the update it found so `download_update` can
+//! fetch it while the user keeps working. The downloaded bundle is held in
+//! memory until `install_on_quit` schedules it, and then installed when the
+//! app exits instead of interrupting a session. When the `auto_update_check`
+//! setting is on, a background task checks periodically and emits
+//! `update-available`, with a notification the first time it sees each
+//! version.
+
+use crate::notify::{self, NotificationAction};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_updater::{Update, UpdaterExt};
+use tracing::{info, warn};
+
+/// Settings key for the automatic check toggle.
+pub(crate) const AUTO_CHECK_SETTING_KEY: &str = "auto_update_check";
+const CHECK_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+const STARTUP_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Default)]
+struct UpdateState {
+    /// Latest update found by a check.
+    available: Option<Update>,
+    /// Bundle for `available`, once downloaded.
+    bytes: Option<Vec<u8>>,
+    install_on_quit: bool,
+}
+
+static STATE: Lazy<Mutex<UpdateState>> = Lazy::new(|| Mutex::new(UpdateState::default()));
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateInfo {
+    pub version: String,
+    pub current_version: String,
+    /// Release date in milliseconds, when the manifest has one.
+    pub date: Option<i64>,
+    /// Release notes.
+    pub body: Option<String>,
+    pub downloaded: bool,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DownloadProgress {
+    downloaded: u64,
+    content_length: Option<u64>,
+}
+
+fn info_for(update: &Update, downloaded: bool) -> UpdateInfo {
+    UpdateInfo {
+        version: update.version.clone(),
+        current_version: update.current_version.clone(),
+        date: update.date.map(|d| d.unix_timestamp() * 1000),
+        body: update.body.clone(),
+        downloaded,
+    }
+}
+
+async fn check(app: &AppHandle) -> Result<Option<UpdateInfo>, String> {
+    let update = app
+        .updater()
+        .map_err(|e| format!("Updater unavailable: {}", e))?
+        .check()
+        .await
+        .map_err(|e| format!("Failed to check for updates: {}", e))?;
+
+    let mut state = STATE.lock();
+    let Some(update) = update else {
+        *state = UpdateState::default();
+        return Ok(None);
+    };
+    // Keep a download that's still for the same version
+    let same = state
+        .available
+        .as_ref()
+        .is_some_and(|current| current.version == update.version);
+    if !same {
+        *state = UpdateState::default();
+    }
+    let info = info_for(&update, same && state.bytes.is_some());
+    state.available = Some(update);
+    Ok(Some(info))
+}
+
+/// Install the scheduled update, if any. Called on `RunEvent::Exit`.
+pub fn install_pending() {
+    let mut state = STATE.lock();
+    if !state.install_on_quit {
+        return;
+    }
+    let (Some(update), Some(bytes)) = (state.available.take(), state.bytes.take()) else {
+        return;
+    };
+    info!("Installing update {} on quit", update.version);
+    if let Err(e) = update.install(bytes) {
+        warn!("Failed to install update {}: {}", update.version, e);
+    }
+}
+
+/// Check for updates every few hours while `auto_update_check` is on.
+/// Called once from `setup`.
+pub fn start_update_checker(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(STARTUP_DELAY).await;
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        let mut notified: Option<String> = None;
+        loop {
+            interval.tick().await;
+            let enabled = match crate::db::pool(&app).await {
+                Ok(pool) => crate::settings::get_setting(&pool, AUTO_CHECK_SETTING_KEY)
+                    .await
+                    .ok()
+                    .flatten()
+                    .unwrap_or(true),
+                Err(_) => continue,
+            };
+            if !enabled {
+                continue;
+            }
+            match check(&app).await {
+                Ok(Some(info)) => {
+                    if let Err(e) = app.emit("update-available", &info) {
+                        warn!("Failed to emit update-available: {}", e);
+                    }
+                    if notified.as_ref() != Some(&info.version) {
+                        notify::show(
+                            &app,
+                            &format!("Freely {} is available", info.version),
+                            &format!("You're on version {}.", info.current_version),
+                            vec![NotificationAction::InstallUpdate],
+                        );
+                        notified = Some(info.version);
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => warn!("{}", e),
+            }
+        }
+    });
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// The newer release, or `None` when this build is current.
+#[tauri::command]
+pub async fn check_for_update(app: AppHandle) -> Result<Option<UpdateInfo>, String> {
+    check(&app).await
+}
+
+/// Download the update found by the last check, emitting
+/// `update-download-progress` as bytes arrive. Nothing is installed yet.
+#[tauri::command]
+pub async fn download_update(app: AppHandle) -> Result<UpdateInfo, String> {
+    let update = {
+        let state = STATE.lock();
+        if let (Some(update), Some(_)) = (&state.available, &state.bytes) {
+            return Ok(info_for(update, true));
+        }
+        state.available.clone()
+    }
+    .ok_or("No update available; check for updates first")?;
+
+    let mut downloaded = 0u64;
+    let bytes = update
+        .download(
+            |chunk, content_length| {
+                downloaded += chunk as u64;
+                let progress = DownloadProgress {
+                    downloaded,
+                    content_length,
+                };
+                if let Err(e) = app.emit("update-download-progress", progress) {
+                    warn!("Failed to emit update progress: {}", e);
+                }
+            },
+            || {},
+        )
+        .await
+        .map_err(|e| format!("Failed to download update: {}", e))?;
+
+    let mut state = STATE.lock();
+    // A newer check may have replaced the update while this one downloaded
+    if state
+        .available
+        .as_ref()
+        .is_none_or(|current| current.version != update.version)
+    {
+        return Err("Update changed during download; try again".to_string());
+    }
+    state.bytes = Some(bytes);
+    Ok(info_for(&update, true))
+}
+
+/// Install the downloaded update when the app quits (or cancel that with
+/// `enabled: false`).
+#[tauri::command]
+pub async fn install_on_quit(enabled: Option<bool>) -> Result<(), String> {
+    let enabled = enabled.unwrap_or(true);
+    let mut state = STATE.lock();
+    if enabled && state.bytes.is_none() {
+        return Err("Download the update before scheduling it".to_string());
+    }
+    state.install_on_quit = enabled;
+    Ok(())
+}
@@ -0,0 +1,666 @@
+//! Usage and cost tracking for provider requests.
+//!
+//! Every completion made through [`crate::providers`] is recorded in the
+//! `usage_events` table (migration 15) with its token counts, latency and an
+//! estimated cost. Tokens come from the provider's own usage report; when a
+//! server doesn't send one they are counted locally and flagged as estimates.
+//!
+//! Prices are USD per million tokens. The built-in table covers the common
+//! OpenAI and Anthropic models; the `model_pricing` setting, keyed by model
+//! name, overrides it and prices anything else (OpenRouter, self-hosted,
+//! ...). Ollama runs locally and is always free. Costs are fixed when a
+//! request is recorded, so later price changes don't rewrite history.
+//!
+//! The `usage_budget` setting caps monthly spend, overall and per provider.
+//! Once a cap is reached the provider layer refuses new paid requests with
+//! [`BudgetExceeded`] and emits `budget-exceeded`, until the limit is raised
+//! or the calendar month ends. The first refusal for each limit also fires
+//! the `budget-exceeded` automation trigger.
+
+use crate::db::usage::{DailyUsage, UsageEvent};
+use crate::providers::{CompletionOutput, CompletionRequest, ProviderKind};
+use chrono::{DateTime, Datelike, Local, Months, NaiveDate, NaiveTime};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tracing::warn;
+
+pub(crate) const PRICING_SETTING_KEY: &str = "model_pricing";
+pub(crate) const BUDGET_SETTING_KEY: &str = "usage_budget";
+
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+const DEFAULT_RANGE_DAYS: i64 = 30;
+
+/// Limits already passed to automations, by provider (or `None` for the
+/// overall limit) and reset time, so webhooks hear about each one once
+/// rather than for every refused request.
+static ANNOUNCED: Lazy<Mutex<HashSet<(Option<String>, i64)>>> =
+    Lazy::new(|| Mutex::new(HashSet::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelPrice {
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+}
+
+impl ModelPrice {
+    const fn new(input_per_million: f64, output_per_million: f64) -> Self {
+        Self {
+            input_per_million,
+            output_per_million,
+        }
+    }
+
+    fn cost(&self, input_tokens: u32, output_tokens: u32) -> f64 {
+        (input_tokens as f64 * self.input_per_million
+            + output_tokens as f64 * self.output_per_million)
+            / 1_000_000.0
+    }
+}
+
+/// Matched by prefix, so dated snapshots (`claude-sonnet-4-20250514`) share
+/// their family's price. More specific prefixes come first.
+const BUILTIN_PRICES: &[(&str, ModelPrice)] = &[
+    ("gpt-4o-mini", ModelPrice::new(0.15, 0.60)),
+    ("gpt-4o", ModelPrice::new(2.50, 10.00)),
+    ("gpt-4.1-nano", ModelPrice::new(0.10, 0.40)),
+    ("gpt-4.1-mini", ModelPrice::new(0.40, 1.60)),
+    ("gpt-4.1", ModelPrice::new(2.00, 8.00)),
+    ("gpt-5-nano", ModelPrice::new(0.05, 0.40)),
+    ("gpt-5-mini", ModelPrice::new(0.25, 2.00)),
+    ("gpt-5", ModelPrice::new(1.25, 10.00)),
+    ("o3-mini", ModelPrice::new(1.10, 4.40)),
+    ("o4-mini", ModelPrice::new(1.10, 4.40)),
+    ("o3", ModelPrice::new(2.00, 8.00)),
+    ("claude-3-haiku", ModelPrice::new(0.25, 1.25)),
+    ("claude-3-5-haiku", ModelPrice::new(0.80, 4.00)),
+    ("claude-haiku-4", ModelPrice::new(1.00, 5.00)),
+    ("claude-3-5-sonnet", ModelPrice::new(3.00, 15.00)),
+    ("claude-3-7-sonnet", ModelPrice::new(3.00, 15.00)),
+    ("claude-sonnet-4", ModelPrice::new(3.00, 15.00)),
+    ("claude-opus-4-5", ModelPrice::new(5.00, 25.00)),
+    ("claude-opus-4", ModelPrice::new(15.00, 75.00)),
+    ("claude-3-opus", ModelPrice::new(15.00, 75.00)),
+];
+
+fn price_for(
+    provider: ProviderKind,
+    model: &str,
+    overrides: &HashMap<String, ModelPrice>,
+) -> Option<ModelPrice> {
+    if let Some(price) = overrides.get(model) {
+        return Some(*price);
+    }
+    match provider {
+        ProviderKind::Ollama | ProviderKind::LlamaLocal => Some(ModelPrice::new(0.0, 0.0)),
+        // Compatible servers host the same model names at their own prices
+        ProviderKind::OpenAiCompatible => None,
+        ProviderKind::OpenAi | ProviderKind::Anthropic => BUILTIN_PRICES
+            .iter()
+            .find(|(prefix, _)| model.starts_with(prefix))
+            .map(|(_, price)| *price),
+    }
+}
+
+/// Token counts for `output`, counting locally when the provider didn't
+/// report them. The `bool` is whether they are estimates.
+fn token_counts(request: &CompletionRequest, output: &CompletionOutput) -> (u32, u32, bool) {
+    if let Some(usage) = &output.usage {
+        return (usage.input_tokens, usage.output_tokens, false);
+    }
+
+    let encoding = crate::tokens::encoding_for(&request.model);
+    let system = request
+        .system_prompt
+        .as_deref()
+        .map(|prompt| encoding.count(prompt))
+        .unwrap_or(0);
+    let input = crate::tokens::prompt_tokens(&encoding, &request.messages) + system;
+    let output = encoding.count(&output.text);
+    (input as u32, output as u32, true)
+}
+
+fn usage_event(
+    source: &str,
+    request_id: Option<&str>,
+    request: &CompletionRequest,
+    output: &CompletionOutput,
+    latency: Duration,
+    overrides: &HashMap<String, ModelPrice>,
+) -> UsageEvent {
+    let (input_tokens, output_tokens, estimated) = token_counts(request, output);
+    // Price what actually answered, which may differ from a requested alias
+    let model = output
+        .model
+        .as_deref()
+        .filter(|model| !model.is_empty())
+        .unwrap_or(&request.model);
+    let cost_usd = price_for(request.provider, model, overrides)
+        .or_else(|| price_for(request.provider, &request.model, overrides))
+        .map(|price| price.cost(input_tokens, output_tokens));
+
+    UsageEvent {
+        id: uuid::Uuid::new_v4().to_string(),
+        request_id: request_id.map(str::to_string),
+        provider: request.provider.as_str().to_string(),
+        model: model.to_string(),
+        source: source.to_string(),
+        input_tokens: input_tokens as i64,
+        output_tokens: output_tokens as i64,
+        estimated,
+        latency_ms: latency.as_millis() as i64,
+        cost_usd,
+        created_at: crate::db::now_millis(),
+    }
+}
+
+/// Record a completed request. Failures are logged rather than returned, so
+/// a usage problem never fails the completion itself.
+pub(crate) async fn record(
+    app: &AppHandle,
+    source: &str,
+    request_id: Option<&str>,
+    request: &CompletionRequest,
+    output: &CompletionOutput,
+    latency: Duration,
+) {
+    let result = async {
+        let pool = crate::db::pool(app).await?;
+        let overrides: HashMap<String, ModelPrice> =
+            crate::settings::get_setting(&pool, PRICING_SETTING_KEY)
+                .await?
+                .unwrap_or_default();
+        let event = usage_event(source, request_id, request, output, latency, &overrides);
+        crate::db::usage::insert(&pool, &event).await
+    }
+    .await;
+
+    if let Err(e) = result {
+        warn!("{}", e);
+    }
+}
+
+/// Millisecond timestamps; `to` defaults to now and `from` to 30 days
+/// before `to`.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageRange {
+    #[serde(default)]
+    pub from: Option<i64>,
+    #[serde(default)]
+    pub to: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageTotals {
+    pub requests: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub cost_usd: f64,
+    pub unpriced_requests: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageSummary {
+    pub from: i64,
+    pub to: i64,
+    pub days: Vec<DailyUsage>,
+    pub totals: UsageTotals,
+}
+
+fn totals(days: &[DailyUsage]) -> UsageTotals {
+    days.iter().fold(UsageTotals::default(), |mut totals, day| {
+        totals.requests += day.requests;
+        totals.input_tokens += day.input_tokens;
+        totals.output_tokens += day.output_tokens;
+        totals.cost_usd += day.cost_usd;
+        totals.unpriced_requests += day.unpriced_requests;
+        totals
+    })
+}
+
+/// Daily usage per provider and model over `range`, with overall totals.
+#[tauri::command]
+pub async fn get_usage_summary(
+    app: AppHandle,
+    range: Option<UsageRange>,
+) -> Result<UsageSummary, String> {
+    let range = range.unwrap_or_default();
+    let to = range.to.unwrap_or_else(crate::db::now_millis);
+    let from = range.from.unwrap_or(to - DEFAULT_RANGE_DAYS * DAY_MS);
+    if from >= to {
+        return Err("Usage range must end after it starts".to_string());
+    }
+
+    let pool = crate::db::pool(&app).await?;
+    let days = crate::db::usage::daily(&pool, from, to).await?;
+    Ok(UsageSummary {
+        from,
+        to,
+        totals: totals(&days),
+        days,
+    })
+}
+
+// ============================================================================
+// Budgets
+// ============================================================================
+
+/// Monthly spend limits in USD. `providers` limits one provider, keyed by
+/// its serialized name (`openai`, `anthropic`, ...), on top of the overall
+/// `monthlyLimitUsd`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BudgetConfig {
+    pub monthly_limit_usd: Option<f64>,
+    pub providers: BTreeMap<String, f64>,
+}
+
+/// Payload of `budget-exceeded`, and the reason a request was refused.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetExceeded {
+    /// Set when a provider's own limit was hit rather than the overall one.
+    pub provider: Option<String>,
+    pub limit_usd: f64,
+    pub spent_usd: f64,
+    /// Start of next month, when the limit resets.
+    pub resets_at: i64,
+}
+
+impl std::fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.provider {
+            Some(provider) => write!(f, "Monthly {} budget", provider)?,
+            None => write!(f, "Monthly budget")?,
+        }
+        write!(
+            f,
+            " of ${:.2} reached (${:.2} spent). Raise the limit or wait until next month.",
+            self.limit_usd, self.spent_usd
+        )
+    }
+}
+
+/// The current calendar month in local time, in milliseconds.
+fn month_bounds(now: DateTime<Local>) -> (i64, i64) {
+    let start = now.date_naive().with_day(1).unwrap_or(now.date_naive());
+    let next = start
+        .checked_add_months(Months::new(1))
+        .unwrap_or(NaiveDate::MAX);
+    let millis = |date: NaiveDate| {
+        let midnight = date.and_time(NaiveTime::MIN);
+        midnight
+            .and_local_timezone(Local)
+            .earliest()
+            // Midnight can fall in a DST gap
+            .map(|time| time.timestamp_millis())
+            .unwrap_or_else(|| midnight.and_utc().timestamp_millis())
+    };
+    (millis(start), millis(next))
+}
+
+async fn check_budget(
+    pool: &SqlitePool,
+    config: &BudgetConfig,
+    provider: ProviderKind,
+    now: DateTime<Local>,
+) -> Result<Option<BudgetExceeded>, String> {
+    let (month_start, resets_at) = month_bounds(now);
+
+    if let Some(&limit_usd) = config.providers.get(provider.as_str()) {
+        let spent_usd =
+            crate::db::usage::spend_since(pool, month_start, Some(provider.as_str())).await?;
+        if spent_usd >= limit_usd {
+            return Ok(Some(BudgetExceeded {
+                provider: Some(provider.as_str().to_string()),
+                limit_usd,
+                spent_usd,
+                resets_at,
+            }));
+        }
+    }
+    if let Some(limit_usd) = config.monthly_limit_usd {
+        let spent_usd = crate::db::usage::spend_since(pool, month_start, None).await?;
+        if spent_usd >= limit_usd {
+            return Ok(Some(BudgetExceeded {
+                provider: None,
+                limit_usd,
+                spent_usd,
+                resets_at,
+            }));
+        }
+    }
+    Ok(None)
+}
+
+/// Whether a new request to `provider` would go over a monthly limit, and by
+/// how much. Emits `budget-exceeded` when it would. Local models cost
+/// nothing and are never refused.
+///
+/// Spend is only known once a request finishes, so requests already in
+/// flight when the limit is reached can still take it slightly over.
+pub(crate) async fn budget_exceeded(
+    app: &AppHandle,
+    provider: ProviderKind,
+) -> Option<BudgetExceeded> {
+    if provider.is_local() {
+        return None;
+    }
+
+    let result = async {
+        let pool = crate::db::pool(app).await?;
+        let config: BudgetConfig = crate::settings::get_setting(&pool, BUDGET_SETTING_KEY)
+            .await?
+            .unwrap_or_default();
+        check_budget(&pool, &config, provider, Local::now()).await
+    }
+    .await;
+
+    match result {
+        Ok(Some(exceeded)) => {
+            if let Err(e) = app.emit("budget-exceeded", &exceeded) {
+                warn!("Failed to emit budget exceeded: {}", e);
+            }
+            let limit = (exceeded.provider.clone(), exceeded.resets_at);
+            if ANNOUNCED.lock().insert(limit) {
+                if let Ok(data) = serde_json::to_value(&exceeded) {
+                    crate::automations::fire(
+                        app,
+                        crate::automations::Trigger::BudgetExceeded,
+                        data,
+                    );
+                }
+            }
+            Some(exceeded)
+        }
+        Ok(None) => None,
+        // Don't block requests because the usage table can't be read
+        Err(e) => {
+            warn!("Failed to check budget: {}", e);
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderSpend {
+    pub provider: String,
+    pub limit_usd: f64,
+    pub spent_usd: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetStatus {
+    pub monthly_limit_usd: Option<f64>,
+    pub spent_usd: f64,
+    pub providers: Vec<ProviderSpend>,
+    pub month_start: i64,
+    pub resets_at: i64,
+}
+
+/// This month's spend against the limits in the `usage_budget` setting.
+#[tauri::command]
+pub async fn get_budget_status(app: AppHandle) -> Result<BudgetStatus, String> {
+    let pool = crate::db::pool(&app).await?;
+    let config: BudgetConfig = crate::settings::get_setting(&pool, BUDGET_SETTING_KEY)
+        .await?
+        .unwrap_or_default();
+    let (month_start, resets_at) = month_bounds(Local::now());
+
+    let mut providers = Vec::with_capacity(config.providers.len());
+    for (provider, &limit_usd) in &config.providers {
+        providers.push(ProviderSpend {
+            provider: provider.clone(),
+            limit_usd,
+            spent_usd: crate::db::usage::spend_since(&pool, month_start, Some(provider)).await?,
+        });
+    }
+
+    Ok(BudgetStatus {
+        monthly_limit_usd: config.monthly_limit_usd,
+        spent_usd: crate::db::usage::spend_since(&pool, month_start, None).await?,
+        providers,
+        month_start,
+        resets_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::{ChatMessage, ChatRole, TokenUsage};
+
+    fn request(provider: ProviderKind, model: &str) -> CompletionRequest {
+        CompletionRequest {
+            provider,
+            model: model.to_string(),
+            messages: vec![ChatMessage {
+                role: ChatRole::User,
+                content: "What is the capital of France?".to_string(),
+                images: Vec::new(),
+            }],
+            system_prompt: None,
+            temperature: None,
+            max_tokens: None,
+            base_url: None,
+            api_key_name: None,
+            retry: None,
+            profile_id: None,
+            skip_cache: false,
+        }
+    }
+
+    #[test]
+    fn costs_use_overrides_then_builtin_prices() {
+        let none = HashMap::new();
+        let price = price_for(ProviderKind::Anthropic, "claude-sonnet-4-20250514", &none).unwrap();
+        assert_eq!(price.cost(1_000_000, 100_000), 4.5);
+        assert_eq!(
+            price_for(ProviderKind::OpenAi, "gpt-4o-mini-2024-07-18", &none),
+            Some(ModelPrice::new(0.15, 0.60))
+        );
+        assert_eq!(
+            price_for(ProviderKind::OpenAiCompatible, "gpt-4o", &none),
+            None
+        );
+        assert_eq!(
+            price_for(ProviderKind::OpenAi, "unknown-model", &none),
+            None
+        );
+        assert_eq!(
+            price_for(ProviderKind::Ollama, "llama3.1", &none),
+            Some(ModelPrice::new(0.0, 0.0))
+        );
+
+        let overrides = HashMap::from([("gpt-4o".to_string(), ModelPrice::new(1.0, 2.0))]);
+        assert_eq!(
+            price_for(ProviderKind::OpenAiCompatible, "gpt-4o", &overrides),
+            Some(ModelPrice::new(1.0, 2.0))
+        );
+    }
+
+    #[test]
+    fn missing_usage_is_estimated() {
+        let request = request(ProviderKind::OpenAi, "gpt-4o");
+        let reported = CompletionOutput {
+            text: "Paris.".to_string(),
+            model: Some("gpt-4o-2024-08-06".to_string()),
+            usage: Some(TokenUsage {
+                input_tokens: 14,
+                output_tokens: 2,
+            }),
+            ..Default::default()
+        };
+        let event = usage_event(
+            "chat",
+            Some("req-1"),
+            &request,
+            &reported,
+            Duration::from_millis(850),
+            &HashMap::new(),
+        );
+        assert_eq!((event.input_tokens, event.output_tokens), (14, 2));
+        assert!(!event.estimated);
+        assert_eq!(event.model, "gpt-4o-2024-08-06");
+        assert_eq!(event.latency_ms, 850);
+        assert!(event.cost_usd.unwrap() > 0.0);
+
+        let unreported = CompletionOutput {
+            usage: None,
+            ..reported
+        };
+        let event = usage_event(
+            "chat",
+            None,
+            &request,
+            &unreported,
+            Duration::ZERO,
+            &HashMap::new(),
+        );
+        assert!(event.estimated);
+        assert!(event.input_tokens > 7);
+        assert_eq!(event.output_tokens, 2);
+    }
+
+    #[tokio::test]
+    async fn events_aggregate_by_day_and_model() {
+        let pool = crate::db::test_pool().await;
+        let request = request(ProviderKind::Anthropic, "claude-3-5-haiku-latest");
+        let noon = chrono::NaiveDate::from_ymd_opt(2024, 5, 1)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+            .and_local_timezone(chrono::Local)
+            .unwrap()
+            .timestamp_millis();
+
+        for (offset, model, cost) in [
+            (0, "claude-3-5-haiku-latest", Some(0.5)),
+            (60_000, "claude-3-5-haiku-latest", Some(0.25)),
+            (120_000, "custom", None),
+            (DAY_MS, "claude-3-5-haiku-latest", Some(1.0)),
+        ] {
+            let output = CompletionOutput {
+                model: Some(model.to_string()),
+                usage: Some(TokenUsage {
+                    input_tokens: 100,
+                    output_tokens: 10,
+                }),
+                ..Default::default()
+            };
+            let mut event = usage_event(
+                "chat",
+                None,
+                &request,
+                &output,
+                Duration::from_millis(200),
+                &HashMap::new(),
+            );
+            event.cost_usd = cost;
+            event.created_at = noon + offset;
+            crate::db::usage::insert(&pool, &event).await.unwrap();
+        }
+
+        let days = crate::db::usage::daily(&pool, noon, noon + 2 * DAY_MS)
+            .await
+            .unwrap();
+        let rows: Vec<_> = days
+            .iter()
+            .map(|d| (d.date.as_str(), d.model.as_str(), d.requests))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ("2024-05-01", "claude-3-5-haiku-latest", 2),
+                ("2024-05-01", "custom", 1),
+                ("2024-05-02", "claude-3-5-haiku-latest", 1),
+            ]
+        );
+        assert_eq!(days[0].cost_usd, 0.75);
+        assert_eq!(days[1].unpriced_requests, 1);
+
+        let totals = totals(&days);
+        assert_eq!(totals.requests, 4);
+        assert_eq!(totals.input_tokens, 400);
+        assert_eq!(totals.cost_usd, 1.75);
+        assert_eq!(totals.unpriced_requests, 1);
+    }
+
+    #[tokio::test]
+    async fn budgets_stop_at_the_monthly_limit() {
+        let pool = crate::db::test_pool().await;
+        let now = chrono::NaiveDate::from_ymd_opt(2024, 5, 20)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+            .and_local_timezone(Local)
+            .unwrap();
+        let (month_start, resets_at) = month_bounds(now);
+        assert_eq!(
+            DateTime::from_timestamp_millis(resets_at)
+                .unwrap()
+                .with_timezone(&Local)
+                .date_naive(),
+            NaiveDate::from_ymd_opt(2024, 6, 1).unwrap()
+        );
+
+        let request = request(ProviderKind::OpenAi, "gpt-4o");
+        // Last month's spend doesn't count
+        for (created_at, cost) in [(month_start - 1, 100.0), (month_start, 4.0)] {
+            let mut event = usage_event(
+                "chat",
+                None,
+                &request,
+                &CompletionOutput::default(),
+                Duration::ZERO,
+                &HashMap::new(),
+            );
+            event.cost_usd = Some(cost);
+            event.created_at = created_at;
+            crate::db::usage::insert(&pool, &event).await.unwrap();
+        }
+
+        let mut config = BudgetConfig {
+            monthly_limit_usd: Some(5.0),
+            ..Default::default()
+        };
+        assert_eq!(
+            check_budget(&pool, &config, ProviderKind::OpenAi, now)
+                .await
+                .unwrap(),
+            None
+        );
+
+        config.providers.insert("openai".to_string(), 3.0);
+        let exceeded = check_budget(&pool, &config, ProviderKind::OpenAi, now)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(exceeded.provider.as_deref(), Some("openai"));
+        assert_eq!(exceeded.spent_usd, 4.0);
+        assert_eq!(exceeded.resets_at, resets_at);
+        assert_eq!(
+            check_budget(&pool, &config, ProviderKind::Anthropic, now)
+                .await
+                .unwrap(),
+            None
+        );
+
+        config.monthly_limit_usd = Some(4.0);
+        let exceeded = check_budget(&pool, &config, ProviderKind::Anthropic, now)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(exceeded.provider, None);
+    }
+}
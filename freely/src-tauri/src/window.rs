@@ -1,6 +1,6 @@
 #[cfg(target_os = "macos")]
 use tauri::LogicalPosition;
-use tauri::{App, AppHandle, Manager, Runtime, WebviewWindow, WebviewWindowBuilder};
+use tauri::{App, AppHandle, Emitter, Manager, Runtime, WebviewWindow, WebviewWindowBuilder};
 
 // The offset from the top of the screen to the window
 const TOP_OFFSET: i32 = 54;
@@ -16,7 +16,9 @@ pub fn setup_main_window(app: &mut App) -> Result<(), Box<dyn std::error::Error>
         })
         .ok_or("No window found")?;
 
+    // Top center until the saved position (if any) is restored
     position_window_top_center(&window, TOP_OFFSET)?;
+    crate::window_state::manage(app.handle(), &window, false);
 
     // Set window as non-focusable on Windows
     // #[cfg(target_os = "windows")]
@@ -161,7 +163,7 @@ pub fn create_dashboard_window<R: Runtime>(
         .min_inner_size(800.0, 600.0)
         .hidden_title(true)
         .title_bar_style(tauri::TitleBarStyle::Overlay)
-        .content_protected(true)
+        .content_protected(crate::window_modes::content_protected())
         .visible(true)
         .traffic_light_position(LogicalPosition::new(14.0, 18.0));
 
@@ -172,7 +174,7 @@ pub fn create_dashboard_window<R: Runtime>(
         .decorations(true)
         .inner_size(800.0, 600.0)
         .min_inner_size(800.0, 600.0)
-        .content_protected(true)
+        .content_protected(crate::window_modes::content_protected())
         .visible(false);
 
     let window = base_builder.build()?;
@@ -192,7 +194,7 @@ fn setup_dashboard_close_handler<R: Runtime>(window: &WebviewWindow<R>) {
             api.prevent_close();
             // Hide the window instead
             if let Err(e) = window_clone.hide() {
-                eprintln!("Failed to hide dashboard window on close: {}", e);
+                tracing::warn!("Failed to hide dashboard window on close: {}", e);
             }
         }
     });
@@ -221,3 +223,33 @@ pub fn show_dashboard_window<R: Runtime>(app: &AppHandle<R>) -> Result<(), Strin
     }
     Ok(())
 }
+
+/// Shows the main window, if hidden, and focuses its input
+pub fn show_main_window<R: Runtime>(app: &AppHandle<R>) -> Result<(), String> {
+    let window = app
+        .get_webview_window("main")
+        .ok_or("Main window not found")?;
+
+    // Windows hides the main window through the frontend
+    #[cfg(target_os = "windows")]
+    {
+        let state = app.state::<crate::shortcuts::WindowVisibility>();
+        let mut is_hidden = state.is_hidden.lock().unwrap_or_else(|e| e.into_inner());
+        if *is_hidden {
+            *is_hidden = false;
+            window
+                .emit("toggle-window-visibility", false)
+                .map_err(|e| format!("Failed to emit toggle-window-visibility: {}", e))?;
+        }
+    }
+
+    window
+        .show()
+        .map_err(|e| format!("Failed to show main window: {}", e))?;
+    window
+        .set_focus()
+        .map_err(|e| format!("Failed to focus main window: {}", e))?;
+    window
+        .emit("focus-text-input", serde_json::json!({}))
+        .map_err(|e| format!("Failed to emit focus-text-input: {}", e))
+}
@@ -0,0 +1,206 @@
+//! Overlay and click-through modes for the main window.
+//!
+//! Overlay mode keeps the window above other apps with a fully transparent
+//! background, so it can float over an IDE or a call. Click-through passes
+//! mouse input to whatever is underneath; since the window can't be clicked
+//! then, the `toggle_click_through` shortcut is the way back. Both modes last
+//! until the app quits, and every change is broadcast as
+//! `window-mode-changed` so the frontend can drop its own background and show
+//! an indicator.
+//!
+//! Content protection (on by default) excludes every Freely window from
+//! screenshots, recordings and screen sharing where the OS allows it. Unlike
+//! the other modes it's stored, under the `content_protection` setting.
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::window::Color;
+use tauri::{AppHandle, Emitter, Manager, Runtime, WebviewWindow};
+
+pub(crate) const CONTENT_PROTECTION_SETTING_KEY: &str = "content_protection";
+
+static OVERLAY: AtomicBool = AtomicBool::new(false);
+static CLICK_THROUGH: AtomicBool = AtomicBool::new(false);
+static CONTENT_PROTECTED: AtomicBool = AtomicBool::new(true);
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowModes {
+    pub overlay: bool,
+    pub click_through: bool,
+}
+
+fn current() -> WindowModes {
+    WindowModes {
+        overlay: OVERLAY.load(Ordering::SeqCst),
+        click_through: CLICK_THROUGH.load(Ordering::SeqCst),
+    }
+}
+
+fn main_window<R: Runtime>(app: &AppHandle<R>) -> Result<WebviewWindow<R>, String> {
+    app.get_webview_window("main")
+        .ok_or_else(|| "Main window not found".to_string())
+}
+
+fn broadcast<R: Runtime>(app: &AppHandle<R>) -> WindowModes {
+    let modes = current();
+    if let Err(e) = app.emit("window-mode-changed", modes) {
+        tracing::warn!("Failed to emit window-mode-changed: {}", e);
+    }
+    modes
+}
+
+fn apply_overlay<R: Runtime>(window: &WebviewWindow<R>, enabled: bool) -> Result<(), String> {
+    window
+        .set_always_on_top(enabled)
+        .map_err(|e| format!("Failed to set always on top: {}", e))?;
+    // Cosmetic, and not supported everywhere
+    let background = enabled.then_some(Color(0, 0, 0, 0));
+    if let Err(e) = window.set_background_color(background) {
+        tracing::debug!("Failed to set window background: {}", e);
+    }
+    Ok(())
+}
+
+pub(crate) fn set_click_through_for<R: Runtime>(
+    app: &AppHandle<R>,
+    enabled: bool,
+) -> Result<WindowModes, String> {
+    main_window(app)?
+        .set_ignore_cursor_events(enabled)
+        .map_err(|e| format!("Failed to set click-through: {}", e))?;
+    CLICK_THROUGH.store(enabled, Ordering::SeqCst);
+    Ok(broadcast(app))
+}
+
+/// Flip click-through; bound to the `toggle_click_through` shortcut.
+pub(crate) fn toggle_click_through<R: Runtime>(app: &AppHandle<R>) {
+    let enabled = !CLICK_THROUGH.load(Ordering::SeqCst);
+    if let Err(e) = set_click_through_for(app, enabled) {
+        tracing::warn!("{}", e);
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentProtectionSupport {
+    pub supported: bool,
+    pub enabled: bool,
+    /// Caveats worth showing next to the toggle.
+    pub note: Option<String>,
+}
+
+fn content_protection_support() -> (bool, Option<&'static str>) {
+    if cfg!(target_os = "macos") {
+        (
+            true,
+            Some("Some recorders built on ScreenCaptureKit can still capture the window"),
+        )
+    } else if cfg!(target_os = "windows") {
+        (
+            true,
+            Some("Requires Windows 10 version 2004 or later; older versions show a black box instead"),
+        )
+    } else {
+        (
+            false,
+            Some("This platform has no way to exclude a window from capture"),
+        )
+    }
+}
+
+/// Whether new windows should be created with content protection.
+pub(crate) fn content_protected() -> bool {
+    CONTENT_PROTECTED.load(Ordering::SeqCst)
+}
+
+fn apply_content_protection<R: Runtime>(app: &AppHandle<R>, enabled: bool) -> Result<(), String> {
+    for (label, window) in app.webview_windows() {
+        window
+            .set_content_protected(enabled)
+            .map_err(|e| format!("Failed to set content protection on {}: {}", label, e))?;
+    }
+    CONTENT_PROTECTED.store(enabled, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Apply the stored content protection setting once the database is
+/// available. Windows start protected, so this only matters when it's off.
+pub fn restore_content_protection(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut pool = None;
+        for _ in 0..20 {
+            match crate::db::pool(&app).await {
+                Ok(p) => {
+                    pool = Some(p);
+                    break;
+                }
+                Err(_) => tokio::time::sleep(Duration::from_millis(250)).await,
+            }
+        }
+        let Some(pool) = pool else {
+            return;
+        };
+
+        match crate::db::settings::get::<bool>(&pool, CONTENT_PROTECTION_SETTING_KEY).await {
+            Ok(Some(false)) => {
+                if let Err(e) = apply_content_protection(&app, false) {
+                    tracing::warn!("{}", e);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Failed to load content protection setting: {}", e),
+        }
+    });
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+#[tauri::command]
+pub fn get_window_modes() -> WindowModes {
+    current()
+}
+
+/// Float the main window above other apps with a transparent background.
+/// Turning overlay mode off also turns off click-through.
+#[tauri::command]
+pub fn set_overlay_mode(app: AppHandle, enabled: bool) -> Result<WindowModes, String> {
+    let window = main_window(&app)?;
+    apply_overlay(&window, enabled)?;
+    OVERLAY.store(enabled, Ordering::SeqCst);
+    if !enabled && CLICK_THROUGH.load(Ordering::SeqCst) {
+        return set_click_through_for(&app, false);
+    }
+    Ok(broadcast(&app))
+}
+
+/// Let mouse input pass through the main window to the app underneath.
+#[tauri::command]
+pub fn set_click_through(app: AppHandle, enabled: bool) -> Result<WindowModes, String> {
+    set_click_through_for(&app, enabled)
+}
+
+#[tauri::command]
+pub fn get_content_protection_support() -> ContentProtectionSupport {
+    let (supported, note) = content_protection_support();
+    ContentProtectionSupport {
+        supported,
+        enabled: content_protected(),
+        note: note.map(str::to_string),
+    }
+}
+
+/// Hide every Freely window from screen captures and video calls (or show
+/// them again), and remember the choice.
+#[tauri::command]
+pub async fn set_content_protected(app: AppHandle, enabled: bool) -> Result<(), String> {
+    if enabled && !content_protection_support().0 {
+        return Err("Content protection isn't supported on this platform".to_string());
+    }
+    apply_content_protection(&app, enabled)?;
+    let pool = crate::db::pool(&app).await?;
+    crate::db::settings::set(&pool, CONTENT_PROTECTION_SETTING_KEY, &enabled).await
+}
@@ -0,0 +1,328 @@
+//! Remembers where windows were placed, per monitor layout.
+//!
+//! Positions are saved in physical pixels under the `window_state` setting,
+//! keyed by a fingerprint of the connected monitors, so docking and
+//! undocking a laptop each bring windows back to where they were in that
+//! setup. With an unknown layout the most recent position is used instead,
+//! clamped so the window stays on a connected monitor: losing a screen
+//! never leaves a window off-screen.
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+use tauri::{AppHandle, Monitor, PhysicalPosition, PhysicalSize, WebviewWindow, WindowEvent};
+use tracing::warn;
+
+pub(crate) const STATE_SETTING_KEY: &str = "window_state";
+/// Writes wait for the window to stop moving for this long.
+const SAVE_DELAY: Duration = Duration::from_millis(500);
+/// How much of a window must overlap a monitor to count as visible.
+const MIN_VISIBLE: i64 = 80;
+
+/// Bumped on every move or resize so only the last pending save writes.
+static GENERATION: Lazy<Mutex<HashMap<String, u64>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    fn from_monitor(monitor: &Monitor) -> Self {
+        let (position, size) = (monitor.position(), monitor.size());
+        Self {
+            x: position.x,
+            y: position.y,
+            width: size.width,
+            height: size.height,
+        }
+    }
+
+    /// Area shared with `other`, in square pixels.
+    fn overlap(&self, other: &Rect) -> i64 {
+        let left = self.x.max(other.x) as i64;
+        let top = self.y.max(other.y) as i64;
+        let right = (self.x as i64 + self.width as i64).min(other.x as i64 + other.width as i64);
+        let bottom = (self.y as i64 + self.height as i64).min(other.y as i64 + other.height as i64);
+        (right - left).max(0) * (bottom - top).max(0)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WindowStates {
+    /// Layout fingerprint -> window label -> placement.
+    #[serde(default)]
+    layouts: BTreeMap<String, BTreeMap<String, Rect>>,
+    /// Window label -> most recent placement in any layout.
+    #[serde(default)]
+    last: BTreeMap<String, Rect>,
+}
+
+fn layout_key(monitors: &[Rect]) -> String {
+    let mut monitors = monitors.to_vec();
+    monitors.sort_by_key(|m| (m.x, m.y));
+    monitors
+        .iter()
+        .map(|m| format!("{},{},{}x{}", m.x, m.y, m.width, m.height))
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// Move `rect` onto the monitor it overlaps most (or the first one if it
+/// overlaps none well enough), shrinking it if it's larger than that monitor.
+fn clamp_to_monitors(rect: Rect, monitors: &[Rect]) -> Rect {
+    let Some(best) = monitors.iter().max_by_key(|m| m.overlap(&rect)) else {
+        return rect;
+    };
+    let visible = best.overlap(&rect);
+    let needed = MIN_VISIBLE * MIN_VISIBLE.min(rect.height as i64).max(1);
+    let target = if visible >= needed {
+        best
+    } else {
+        &monitors[0]
+    };
+    if visible >= needed && target.overlap(&rect) == rect.overlap(&rect) {
+        return rect;
+    }
+
+    let width = rect.width.min(target.width);
+    let height = rect.height.min(target.height);
+    let max_x = target.x + (target.width - width) as i32;
+    let max_y = target.y + (target.height - height) as i32;
+    Rect {
+        x: rect.x.clamp(target.x, max_x),
+        y: rect.y.clamp(target.y, max_y),
+        width,
+        height,
+    }
+}
+
+/// Where `label` should go on `monitors`, if it was ever saved.
+fn placement_for(states: &WindowStates, label: &str, monitors: &[Rect]) -> Option<Rect> {
+    if let Some(rect) = states
+        .layouts
+        .get(&layout_key(monitors))
+        .and_then(|windows| windows.get(label))
+    {
+        return Some(clamp_to_monitors(*rect, monitors));
+    }
+    states
+        .last
+        .get(label)
+        .map(|rect| clamp_to_monitors(*rect, monitors))
+}
+
+fn monitors_of(window: &WebviewWindow) -> Vec<Rect> {
+    match window.available_monitors() {
+        Ok(monitors) => monitors.iter().map(Rect::from_monitor).collect(),
+        Err(e) => {
+            warn!("Failed to list monitors: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+async fn load(app: &AppHandle) -> Result<WindowStates, String> {
+    let pool = crate::db::pool(app).await?;
+    Ok(crate::db::settings::get(&pool, STATE_SETTING_KEY)
+        .await?
+        .unwrap_or_default())
+}
+
+async fn save(app: &AppHandle, window: &WebviewWindow) -> Result<(), String> {
+    let monitors = monitors_of(window);
+    if monitors.is_empty() {
+        return Ok(());
+    }
+    let position = window
+        .outer_position()
+        .map_err(|e| format!("Failed to get window position: {}", e))?;
+    let size = window
+        .outer_size()
+        .map_err(|e| format!("Failed to get window size: {}", e))?;
+    let rect = Rect {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+    };
+
+    let pool = crate::db::pool(app).await?;
+    let mut states: WindowStates = crate::db::settings::get(&pool, STATE_SETTING_KEY)
+        .await?
+        .unwrap_or_default();
+    let label = window.label().to_string();
+    states
+        .layouts
+        .entry(layout_key(&monitors))
+        .or_default()
+        .insert(label.clone(), rect);
+    states.last.insert(label, rect);
+    crate::db::settings::set(&pool, STATE_SETTING_KEY, &states).await
+}
+
+fn schedule_save(app: &AppHandle, window: &WebviewWindow) {
+    let label = window.label().to_string();
+    let generation = {
+        let mut generations = GENERATION.lock();
+        let generation = generations.entry(label.clone()).or_default();
+        *generation += 1;
+        *generation
+    };
+
+    let (app, window) = (app.clone(), window.clone());
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(SAVE_DELAY).await;
+        if GENERATION.lock().get(&label) != Some(&generation) {
+            return;
+        }
+        if let Err(e) = save(&app, &window).await {
+            warn!("Failed to save {} window position: {}", label, e);
+        }
+    });
+}
+
+/// Move `window` to its saved placement for the current monitors. The size
+/// is only restored when `with_size` is set; the main window sizes itself.
+fn restore(window: &WebviewWindow, states: &WindowStates, with_size: bool) {
+    let mut monitors = monitors_of(window);
+    // The primary monitor first, as the fallback for clamping
+    if let Ok(Some(primary)) = window.primary_monitor() {
+        let primary = Rect::from_monitor(&primary);
+        monitors.sort_by_key(|m| *m != primary);
+    }
+    let Some(mut rect) = placement_for(states, window.label(), &monitors) else {
+        return;
+    };
+
+    if with_size {
+        if let Err(e) = window.set_size(PhysicalSize::new(rect.width, rect.height)) {
+            warn!("Failed to restore window size: {}", e);
+        }
+    } else if let Ok(size) = window.outer_size() {
+        // Clamp against the size the window actually has
+        rect = clamp_to_monitors(
+            Rect {
+                width: size.width,
+                height: size.height,
+                ..rect
+            },
+            &monitors,
+        );
+    }
+    if let Err(e) = window.set_position(PhysicalPosition::new(rect.x, rect.y)) {
+        warn!("Failed to restore window position: {}", e);
+    }
+}
+
+/// Restore `window`'s saved placement once the database is available, then
+/// save it whenever it's moved or resized. Until then the window keeps the
+/// position it was created with.
+pub fn manage(app: &AppHandle, window: &WebviewWindow, with_size: bool) {
+    let (app, window) = (app.clone(), window.clone());
+    tauri::async_runtime::spawn(async move {
+        let mut states = None;
+        for _ in 0..20 {
+            match load(&app).await {
+                Ok(loaded) => {
+                    states = Some(loaded);
+                    break;
+                }
+                Err(_) => tokio::time::sleep(Duration::from_millis(250)).await,
+            }
+        }
+        match states {
+            Some(states) => restore(&window, &states, with_size),
+            None => warn!("Failed to load window state for {}", window.label()),
+        }
+
+        let tracked = window.clone();
+        window.on_window_event(move |event| {
+            if matches!(event, WindowEvent::Moved(_) | WindowEvent::Resized(_)) {
+                schedule_save(&app, &tracked);
+            }
+        });
+    });
+}
+
+/// Forget every saved window placement.
+#[tauri::command]
+pub async fn reset_window_state(app: AppHandle) -> Result<(), String> {
+    let pool = crate::db::pool(&app).await?;
+    crate::db::settings::delete(&pool, STATE_SETTING_KEY).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(x: i32, y: i32, width: u32, height: u32) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn windows_on_a_missing_monitor_are_pulled_back() {
+        let laptop = rect(0, 0, 1440, 900);
+        let external = rect(1440, 0, 2560, 1440);
+
+        // Fully visible placements are kept as they are
+        let on_external = rect(2000, 100, 800, 600);
+        assert_eq!(
+            clamp_to_monitors(on_external, &[laptop, external]),
+            on_external
+        );
+
+        // With the external monitor gone, the window lands on the laptop
+        let clamped = clamp_to_monitors(on_external, &[laptop]);
+        assert_eq!(clamped, rect(640, 100, 800, 600));
+
+        // Hanging off an edge is pulled fully onto the screen
+        assert_eq!(
+            clamp_to_monitors(rect(-300, 850, 600, 200), &[laptop]),
+            rect(0, 700, 600, 200)
+        );
+        // Too large for the monitor: shrunk to fit
+        assert_eq!(
+            clamp_to_monitors(rect(100, 100, 2000, 1200), &[laptop]),
+            laptop
+        );
+    }
+
+    #[test]
+    fn placements_are_per_layout_with_last_as_fallback() {
+        let docked = [rect(0, 0, 1440, 900), rect(1440, 0, 2560, 1440)];
+        let undocked = [rect(0, 0, 1440, 900)];
+        assert_eq!(layout_key(&docked), layout_key(&[docked[1], docked[0]]));
+
+        let mut states = WindowStates::default();
+        states.layouts.insert(
+            layout_key(&undocked),
+            BTreeMap::from([("main".to_string(), rect(100, 54, 600, 54))]),
+        );
+        states
+            .last
+            .insert("main".to_string(), rect(2000, 54, 600, 54));
+
+        assert_eq!(
+            placement_for(&states, "main", &undocked),
+            Some(rect(100, 54, 600, 54))
+        );
+        assert_eq!(
+            placement_for(&states, "main", &docked),
+            Some(rect(2000, 54, 600, 54))
+        );
+        assert_eq!(placement_for(&states, "dashboard", &docked), None);
+    }
+}
@@ -0,0 +1,226 @@
+//! Review and undo of agent file edits.
+//!
+//! When [`crate::agent_audit`] sees a Write, Edit, MultiEdit or NotebookEdit
+//! call, the file is snapshotted here before the CLI gets to run the tool,
+//! once per session and path. `get_pending_diffs` compares the snapshots
+//! with the files on disk as unified diffs, and `revert_file` puts a file
+//! back the way it was, deleting it if the agent created it.
+//!
+//! Snapshots are kept in memory until the file is reverted or Freely quits.
+//! Files over [`MAX_SNAPSHOT_BYTES`] and files that aren't UTF-8 text are
+//! not snapshotted, so their edits can't be undone here.
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use serde::Serialize;
+use similar::TextDiff;
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+const EDIT_TOOLS: &[&str] = &["Write", "Edit", "MultiEdit", "NotebookEdit"];
+const MAX_SNAPSHOT_BYTES: u64 = 2 * 1024 * 1024;
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Clone)]
+struct Snapshot {
+    path: PathBuf,
+    /// `None` when the file didn't exist yet.
+    original: Option<String>,
+    taken_at: i64,
+}
+
+/// Snapshots by agent session ID.
+static SNAPSHOTS: Lazy<Mutex<HashMap<String, Vec<Snapshot>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileDiff {
+    pub path: String,
+    /// Unified diff from the snapshot to the file on disk.
+    pub diff: String,
+    /// The agent created the file.
+    pub created: bool,
+    /// The file is gone from disk.
+    pub deleted: bool,
+}
+
+pub(crate) fn is_edit_tool(tool_name: &str) -> bool {
+    EDIT_TOOLS.contains(&tool_name)
+}
+
+fn has_snapshot(session_id: &str, path: &Path) -> bool {
+    SNAPSHOTS
+        .lock()
+        .get(session_id)
+        .is_some_and(|snapshots| snapshots.iter().any(|s| s.path == path))
+}
+
+/// `Ok(None)` when the file doesn't exist.
+fn read_text(path: &Path) -> Result<Option<String>, String> {
+    let metadata = match std::fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+    };
+    if metadata.len() > MAX_SNAPSHOT_BYTES {
+        return Err(format!(
+            "{} is too large to snapshot ({} bytes)",
+            path.display(),
+            metadata.len()
+        ));
+    }
+    std::fs::read_to_string(path)
+        .map(Some)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+}
+
+/// Remember `path` as it is now, unless `session_id` already has it.
+pub(crate) fn snapshot(session_id: &str, path: &Path) {
+    if has_snapshot(session_id, path) {
+        return;
+    }
+    let original = match read_text(path) {
+        Ok(original) => original,
+        Err(e) => {
+            warn!("Agent edit can't be undone: {}", e);
+            return;
+        }
+    };
+    let mut snapshots = SNAPSHOTS.lock();
+    let session = snapshots.entry(session_id.to_string()).or_default();
+    if !session.iter().any(|s| s.path == path) {
+        session.push(Snapshot {
+            path: path.to_path_buf(),
+            original,
+            taken_at: crate::db::now_millis(),
+        });
+    }
+}
+
+fn unified_diff(path: &str, original: Option<&str>, current: Option<&str>) -> String {
+    let old_header = match original {
+        Some(_) => format!("a/{}", path.trim_start_matches('/')),
+        None => "/dev/null".to_string(),
+    };
+    let new_header = match current {
+        Some(_) => format!("b/{}", path.trim_start_matches('/')),
+        None => "/dev/null".to_string(),
+    };
+    TextDiff::from_lines(original.unwrap_or_default(), current.unwrap_or_default())
+        .unified_diff()
+        .context_radius(CONTEXT_LINES)
+        .header(&old_header, &new_header)
+        .to_string()
+}
+
+fn diff_snapshot(snapshot: &Snapshot) -> Option<FileDiff> {
+    let current = match read_text(&snapshot.path) {
+        Ok(current) => current,
+        Err(e) => {
+            warn!("Failed to diff agent edit: {}", e);
+            return None;
+        }
+    };
+    if current == snapshot.original {
+        return None;
+    }
+    let path = snapshot.path.to_string_lossy().into_owned();
+    Some(FileDiff {
+        diff: unified_diff(&path, snapshot.original.as_deref(), current.as_deref()),
+        created: snapshot.original.is_none(),
+        deleted: current.is_none(),
+        path,
+    })
+}
+
+/// The files the agent changed in `session_id`, in the order it first
+/// touched them. Files changed back to their original content are left out.
+#[tauri::command]
+pub async fn get_pending_diffs(session_id: String) -> Vec<FileDiff> {
+    let snapshots = SNAPSHOTS
+        .lock()
+        .get(&session_id)
+        .cloned()
+        .unwrap_or_default();
+    snapshots.iter().filter_map(diff_snapshot).collect()
+}
+
+/// Undo the agent's changes to `path`. When several sessions edited it,
+/// the file goes back to before the first of them.
+#[tauri::command]
+pub fn revert_file(path: String) -> Result<(), String> {
+    let target = PathBuf::from(&path);
+    let original = {
+        let snapshots = SNAPSHOTS.lock();
+        snapshots
+            .values()
+            .flatten()
+            .filter(|s| s.path == target)
+            .min_by_key(|s| s.taken_at)
+            .cloned()
+            .ok_or_else(|| format!("No agent changes recorded for {}", path))?
+            .original
+    };
+
+    match original {
+        Some(text) => {
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to revert {}: {}", path, e))?;
+            }
+            std::fs::write(&target, text)
+                .map_err(|e| format!("Failed to revert {}: {}", path, e))?;
+        }
+        None => match std::fs::remove_file(&target) {
+            Err(e) if e.kind() != ErrorKind::NotFound => {
+                return Err(format!("Failed to revert {}: {}", path, e));
+            }
+            _ => {}
+        },
+    }
+
+    let mut snapshots = SNAPSHOTS.lock();
+    for session in snapshots.values_mut() {
+        session.retain(|s| s.path != target);
+    }
+    snapshots.retain(|_, session| !session.is_empty());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn agent_edits_are_diffed_and_reverted() {
+        let dir = tempfile::tempdir().unwrap();
+        let edited = dir.path().join("main.rs");
+        let created = dir.path().join("new.rs");
+        std::fs::write(&edited, "fn main() {}\n").unwrap();
+
+        snapshot("diff-test", &edited);
+        snapshot("diff-test", &created);
+        std::fs::write(&edited, "fn main() {\n    run();\n}\n").unwrap();
+        // A later edit in the same session keeps the first snapshot
+        snapshot("diff-test", &edited);
+        std::fs::write(&created, "pub fn run() {}\n").unwrap();
+
+        let diffs = get_pending_diffs("diff-test".to_string()).await;
+        assert_eq!(diffs.len(), 2);
+        assert!(diffs[0].diff.contains("-fn main() {}"));
+        assert!(diffs[0].diff.contains("+    run();"));
+        assert!(!diffs[0].created);
+        assert!(diffs[1].created);
+        assert!(diffs[1].diff.starts_with("--- /dev/null"));
+
+        revert_file(edited.to_string_lossy().into_owned()).unwrap();
+        revert_file(created.to_string_lossy().into_owned()).unwrap();
+        assert_eq!(std::fs::read_to_string(&edited).unwrap(), "fn main() {}\n");
+        assert!(!created.exists());
+        assert!(get_pending_diffs("diff-test".to_string()).await.is_empty());
+        assert!(revert_file(edited.to_string_lossy().into_owned()).is_err());
+    }
+}
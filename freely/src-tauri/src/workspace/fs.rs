@@ -0,0 +1,235 @@
+//! Read-only access to project files for the frontend.
+//!
+//! Every path is canonicalized, resolving `..` and symlinks, and must then
+//! lie inside one of the registered project directories. A symlink inside a
+//! project that points elsewhere is listed but can't be read. `.git`
+//! directories are left out of listings.
+
+use serde::{Deserialize, Serialize};
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tauri::AppHandle;
+
+const MAX_READ_BYTES: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileStat {
+    pub path: String,
+    pub is_dir: bool,
+    pub size: u64,
+    pub modified_at: Option<i64>,
+    pub readonly: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DirEntry {
+    pub name: String,
+    pub path: String,
+    pub is_dir: bool,
+    pub is_symlink: bool,
+    pub size: u64,
+}
+
+/// 1-based, inclusive lines.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LineRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileContent {
+    pub path: String,
+    pub content: String,
+    /// Lines returned, 1-based and inclusive; both 0 for an empty range.
+    pub start_line: usize,
+    pub end_line: usize,
+    pub total_lines: usize,
+}
+
+pub(crate) async fn project_roots(app: &AppHandle) -> Result<Vec<PathBuf>, String> {
+    let pool = crate::db::pool(app).await?;
+    let projects = crate::db::projects::list(&pool).await?;
+    // A project whose directory is gone just stops matching
+    Ok(projects
+        .iter()
+        .filter_map(|project| Path::new(&project.path).canonicalize().ok())
+        .collect())
+}
+
+/// `path` canonicalized, if it is inside one of `roots`.
+pub(crate) fn resolve(roots: &[PathBuf], path: &str) -> Result<PathBuf, String> {
+    let resolved = Path::new(path.trim())
+        .canonicalize()
+        .map_err(|e| format!("Failed to open {}: {}", path, e))?;
+    if roots.iter().any(|root| resolved.starts_with(root)) {
+        Ok(resolved)
+    } else {
+        Err(format!("{} is outside the registered projects", path))
+    }
+}
+
+fn modified_at(metadata: &Metadata) -> Option<i64> {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as i64)
+}
+
+fn list_in(roots: &[PathBuf], path: &str) -> Result<Vec<DirEntry>, String> {
+    let dir = resolve(roots, path)?;
+    let read_dir =
+        std::fs::read_dir(&dir).map_err(|e| format!("Failed to list {}: {}", path, e))?;
+    let mut entries: Vec<DirEntry> = read_dir
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name == ".git" {
+                return None;
+            }
+            let is_symlink = entry.file_type().ok()?.is_symlink();
+            // Follows symlinks, so a link to a directory lists as one
+            let metadata = std::fs::metadata(entry.path()).ok();
+            Some(DirEntry {
+                path: entry.path().to_string_lossy().into_owned(),
+                is_dir: metadata.as_ref().is_some_and(Metadata::is_dir),
+                size: metadata.as_ref().map_or(0, Metadata::len),
+                is_symlink,
+                name,
+            })
+        })
+        .collect();
+    entries.sort_by(|a, b| {
+        b.is_dir
+            .cmp(&a.is_dir)
+            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+    });
+    Ok(entries)
+}
+
+fn read_in(roots: &[PathBuf], path: &str, range: Option<LineRange>) -> Result<FileContent, String> {
+    let file = resolve(roots, path)?;
+    let metadata =
+        std::fs::metadata(&file).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    if metadata.is_dir() {
+        return Err(format!("{} is a directory", path));
+    }
+    if metadata.len() > MAX_READ_BYTES {
+        return Err(format!(
+            "{} is too large to read ({} bytes)",
+            path,
+            metadata.len()
+        ));
+    }
+    let bytes = std::fs::read(&file).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let text = String::from_utf8(bytes)
+        .ok()
+        .filter(|text| !text.contains('\0'))
+        .ok_or_else(|| format!("{} is not a text file", path))?;
+
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    let total_lines = lines.len();
+    let (start, end) = match range {
+        Some(range) => (range.start.max(1), range.end.min(total_lines)),
+        None => (1, total_lines),
+    };
+    let (content, start_line, end_line) = if start > end {
+        (String::new(), 0, 0)
+    } else {
+        (lines[start - 1..end].concat(), start, end)
+    };
+    Ok(FileContent {
+        path: file.to_string_lossy().into_owned(),
+        content,
+        start_line,
+        end_line,
+        total_lines,
+    })
+}
+
+fn stat_in(roots: &[PathBuf], path: &str) -> Result<FileStat, String> {
+    let resolved = resolve(roots, path)?;
+    let metadata =
+        std::fs::metadata(&resolved).map_err(|e| format!("Failed to stat {}: {}", path, e))?;
+    Ok(FileStat {
+        path: resolved.to_string_lossy().into_owned(),
+        is_dir: metadata.is_dir(),
+        size: metadata.len(),
+        modified_at: modified_at(&metadata),
+        readonly: metadata.permissions().readonly(),
+    })
+}
+
+/// The entries of directory `path`, directories first.
+#[tauri::command]
+pub async fn list_dir(app: AppHandle, path: String) -> Result<Vec<DirEntry>, String> {
+    let roots = project_roots(&app).await?;
+    list_in(&roots, &path)
+}
+
+/// A UTF-8 text file, or lines `range` of it.
+#[tauri::command]
+pub async fn read_file(
+    app: AppHandle,
+    path: String,
+    range: Option<LineRange>,
+) -> Result<FileContent, String> {
+    let roots = project_roots(&app).await?;
+    read_in(&roots, &path, range)
+}
+
+#[tauri::command]
+pub async fn stat(app: AppHandle, path: String) -> Result<FileStat, String> {
+    let roots = project_roots(&app).await?;
+    stat_in(&roots, &path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn access_is_limited_to_project_roots() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("project");
+        std::fs::create_dir_all(project.join("src")).unwrap();
+        std::fs::create_dir_all(project.join(".git")).unwrap();
+        std::fs::write(project.join("src/main.rs"), "a\nb\nc\nd\n").unwrap();
+        std::fs::write(project.join("README.md"), "hi").unwrap();
+        std::fs::write(dir.path().join("secret.txt"), "no").unwrap();
+        let roots = vec![project.canonicalize().unwrap()];
+        let inside = |p: &str| project.join(p).to_string_lossy().into_owned();
+
+        let entries = list_in(&roots, &inside("")).unwrap();
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["src", "README.md"]);
+
+        let snippet = read_in(
+            &roots,
+            &inside("src/main.rs"),
+            Some(LineRange { start: 2, end: 3 }),
+        )
+        .unwrap();
+        assert_eq!(snippet.content, "b\nc\n");
+        assert_eq!(
+            (snippet.start_line, snippet.end_line, snippet.total_lines),
+            (2, 3, 4)
+        );
+
+        assert!(stat_in(&roots, &inside("src")).unwrap().is_dir);
+        assert!(read_in(&roots, &inside("../secret.txt"), None).is_err());
+        assert!(list_in(&roots, &dir.path().to_string_lossy()).is_err());
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(dir.path().join("secret.txt"), project.join("link"))
+                .unwrap();
+            assert!(read_in(&roots, &inside("link"), None).is_err());
+        }
+    }
+}
@@ -0,0 +1,5 @@
+//! The files an agent works on, in its working directory or a registered
+//! project.
+
+pub mod diff;
+pub mod fs;
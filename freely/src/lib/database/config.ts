@@ -13,7 +13,8 @@ let dbInstance: Database | null = null;
 export async function getDatabase(): Promise<Database> {
   if (!dbInstance) {
     try {
-      dbInstance = await Database.load(DB_NAME);
+      // Opened (and decrypted, if encrypted) by the Rust side at startup
+      dbInstance = Database.get(DB_NAME);
     } catch (error) {
       throw new Error(
         `Failed to initialize database: ${